@@ -0,0 +1,3 @@
+ALTER TABLE sessions DROP COLUMN client_id;
+
+DROP TABLE oauth_clients;
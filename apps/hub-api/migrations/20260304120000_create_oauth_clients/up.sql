@@ -0,0 +1,18 @@
+CREATE TABLE oauth_clients (
+    id                  TEXT PRIMARY KEY,
+    -- NULL for first-party clients managed by the Hub itself.
+    owner_id            TEXT REFERENCES users(id) ON DELETE CASCADE,
+    name                TEXT NOT NULL,
+    client_type         TEXT NOT NULL CHECK (client_type IN ('public', 'confidential')),
+    client_secret_hash  TEXT,
+    redirect_uris       TEXT[] NOT NULL DEFAULT '{}',
+    allowed_scopes      TEXT[] NOT NULL DEFAULT '{openid}',
+    grant_types         TEXT[] NOT NULL DEFAULT '{authorization_code,refresh_token}',
+    created_at          TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at          TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_oauth_clients_owner ON oauth_clients(owner_id);
+
+ALTER TABLE sessions
+    ADD COLUMN client_id TEXT;
@@ -0,0 +1,4 @@
+ALTER TABLE oauth_clients DROP COLUMN bot_user_id;
+
+DROP INDEX idx_users_bot_owner;
+ALTER TABLE users DROP COLUMN bot_owner_id;
@@ -0,0 +1,9 @@
+-- Bots are users with the BOT flag, owned by a human user.
+ALTER TABLE users
+    ADD COLUMN bot_owner_id TEXT REFERENCES users(id) ON DELETE CASCADE;
+
+CREATE INDEX idx_users_bot_owner ON users(bot_owner_id) WHERE bot_owner_id IS NOT NULL;
+
+-- The client a bot authenticates with (client_credentials grant).
+ALTER TABLE oauth_clients
+    ADD COLUMN bot_user_id TEXT UNIQUE REFERENCES users(id) ON DELETE CASCADE;
@@ -0,0 +1,2 @@
+DROP INDEX idx_sessions_family;
+ALTER TABLE sessions DROP COLUMN scopes, DROP COLUMN family_id;
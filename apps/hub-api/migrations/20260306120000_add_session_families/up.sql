@@ -0,0 +1,16 @@
+-- Refresh token rotation creates a new session row each time. Rows from the
+-- same sign-in share a family, so replaying a rotated token can revoke them
+-- all. Scopes are carried along so refreshes keep the original grant.
+ALTER TABLE sessions
+    ADD COLUMN family_id TEXT,
+    ADD COLUMN scopes TEXT[];
+
+-- Existing sessions start their own family and keep the scopes refreshes
+-- used to hand out.
+UPDATE sessions SET family_id = id, scopes = '{openid,profile,email,pods}';
+
+ALTER TABLE sessions
+    ALTER COLUMN family_id SET NOT NULL,
+    ALTER COLUMN scopes SET NOT NULL;
+
+CREATE INDEX idx_sessions_family ON sessions(family_id);
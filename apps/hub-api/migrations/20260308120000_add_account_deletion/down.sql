@@ -0,0 +1,8 @@
+DROP TABLE IF EXISTS pod_notifications;
+
+ALTER TABLE sessions DROP CONSTRAINT sessions_user_id_fkey;
+ALTER TABLE sessions ADD CONSTRAINT sessions_user_id_fkey
+    FOREIGN KEY (user_id) REFERENCES users(id);
+
+DROP INDEX IF EXISTS idx_users_deletion;
+ALTER TABLE users DROP COLUMN IF EXISTS deletion_scheduled_at;
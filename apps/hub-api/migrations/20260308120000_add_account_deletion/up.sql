@@ -0,0 +1,27 @@
+-- Set when the user asks to delete their account; purged once it passes.
+ALTER TABLE users ADD COLUMN deletion_scheduled_at TIMESTAMPTZ;
+
+CREATE INDEX idx_users_deletion ON users(deletion_scheduled_at)
+    WHERE deletion_scheduled_at IS NOT NULL;
+
+-- Purging a user takes their sessions with them.
+ALTER TABLE sessions DROP CONSTRAINT sessions_user_id_fkey;
+ALTER TABLE sessions ADD CONSTRAINT sessions_user_id_fkey
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE;
+
+-- Outbox of signed events for Pods. `user_id` has no foreign key: the
+-- events outlive the user they are about.
+CREATE TABLE pod_notifications (
+    id              TEXT PRIMARY KEY,
+    pod_id          TEXT NOT NULL REFERENCES pods(id) ON DELETE CASCADE,
+    event           TEXT NOT NULL,
+    user_id         TEXT NOT NULL,
+    attempts        INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_error      TEXT,
+    delivered_at    TIMESTAMPTZ,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_pod_notifications_pending ON pod_notifications(next_attempt_at)
+    WHERE delivered_at IS NULL;
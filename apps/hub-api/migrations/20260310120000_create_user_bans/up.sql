@@ -0,0 +1,10 @@
+-- Hub-level bans and suspensions (RFC §14.4). `users.status` mirrors the
+-- row's status while it is in force.
+CREATE TABLE user_bans (
+    user_id         TEXT PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    status          TEXT NOT NULL,
+    reason          TEXT NOT NULL,
+    banned_by       TEXT REFERENCES users(id) ON DELETE SET NULL,
+    expires_at      TIMESTAMPTZ,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
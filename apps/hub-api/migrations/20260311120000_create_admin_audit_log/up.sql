@@ -0,0 +1,12 @@
+-- Every action taken through the staff-only admin API.
+CREATE TABLE admin_audit_log (
+    id              TEXT PRIMARY KEY,
+    actor_id        TEXT REFERENCES users(id) ON DELETE SET NULL,
+    action          TEXT NOT NULL,
+    target_type     TEXT,
+    target_id       TEXT,
+    changes         JSONB,
+    reason          TEXT,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+CREATE INDEX idx_admin_audit_target ON admin_audit_log(target_id, created_at DESC);
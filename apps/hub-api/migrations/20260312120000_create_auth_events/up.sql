@@ -0,0 +1,15 @@
+-- Authentication events (RFC §18.4). `user_id` is null for failed sign-ins
+-- to accounts that don't exist.
+CREATE TABLE auth_events (
+    id              TEXT PRIMARY KEY,
+    user_id         TEXT REFERENCES users(id) ON DELETE CASCADE,
+    event           TEXT NOT NULL,
+    ip_address      INET,
+    user_agent      TEXT,
+    client_id       TEXT,
+    pod_id          TEXT,
+    details         JSONB,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+CREATE INDEX idx_auth_events_user ON auth_events(user_id, created_at DESC);
+CREATE INDEX idx_auth_events_created ON auth_events(created_at);
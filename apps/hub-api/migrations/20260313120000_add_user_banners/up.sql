@@ -0,0 +1,2 @@
+-- Profile banner, served from the Hub media route like `avatar_url`.
+ALTER TABLE users ADD COLUMN banner_url TEXT;
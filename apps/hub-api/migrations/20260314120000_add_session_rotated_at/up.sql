@@ -0,0 +1,13 @@
+-- A session is revoked both when its refresh token is rotated and when it's
+-- signed out. Only presenting a rotated token again means the chain leaked,
+-- so rotation is recorded separately.
+ALTER TABLE sessions ADD COLUMN rotated_at TIMESTAMPTZ;
+
+-- Revoked sessions with a later session in their family were rotated.
+UPDATE sessions s
+SET rotated_at = s.last_active_at
+WHERE s.revoked
+  AND EXISTS (
+      SELECT 1 FROM sessions n
+      WHERE n.family_id = s.family_id AND n.created_at > s.created_at
+  );
@@ -0,0 +1,91 @@
+//! Account deletion (RFC §18.1): accounts are purged once their grace period
+//! runs out, and every Pod the user signed in to is told to anonymize them.
+
+use std::time::Duration;
+
+use chrono::Utc;
+use diesel::dsl::{exists, not};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+
+use crate::auth::events;
+use crate::db::schema::{pods, users};
+use crate::error::ApiError;
+use crate::media::{self, MediaKind};
+use crate::{exports, pod_events, AppState};
+
+/// How often the background jobs run.
+const JOB_INTERVAL_SECS: u64 = 60;
+
+/// Purge every account whose deletion is due, along with its bots. Sessions,
+/// bookmarks, preferences, uploaded media and everything else keyed to the
+/// user go with it.
+/// Returns how many accounts were purged.
+pub async fn purge_due_accounts(state: &AppState) -> Result<usize, ApiError> {
+    let mut conn = state.db.get().await?;
+    // Pod owners can't schedule deletion, but may have registered a Pod
+    // since. Their purge waits until the Pod is handed over.
+    let due: Vec<String> = users::table
+        .filter(users::deletion_scheduled_at.le(Utc::now()))
+        .filter(not(exists(
+            pods::table.filter(pods::owner_id.eq(users::id)),
+        )))
+        .select(users::id)
+        .load(&mut conn)
+        .await
+        .map_err(ApiError::from)?;
+
+    for user_id in &due {
+        // Bots are deleted with their owner, so Pods hear about them too.
+        let bots: Vec<String> = users::table
+            .filter(users::bot_owner_id.eq(user_id))
+            .select(users::id)
+            .load(&mut conn)
+            .await
+            .map_err(ApiError::from)?;
+
+        for id in bots.iter().chain(std::iter::once(user_id)) {
+            crate::routes::sessions::revoke_sessions(state, id, None).await?;
+            pod_events::enqueue_user_event(&mut conn, id, pod_events::EVENT_USER_DELETED).await?;
+            for kind in [MediaKind::Avatar, MediaKind::Banner] {
+                let prefix = media::owner_prefix(kind, id);
+                if let Err(e) = state.media.delete_prefix(&prefix).await {
+                    tracing::warn!(user_id = %id, %prefix, error = %e.message, "failed to delete media");
+                }
+            }
+        }
+
+        diesel::delete(users::table.find(user_id))
+            .execute(&mut conn)
+            .await
+            .map_err(ApiError::from)?;
+        tracing::info!(%user_id, bots = bots.len(), "account purged");
+    }
+    Ok(due.len())
+}
+
+/// Run account purges, Pod event delivery, data exports and auth event
+/// retention until the process exits.
+pub async fn run_background_jobs(state: AppState) {
+    let mut tick = tokio::time::interval(Duration::from_secs(JOB_INTERVAL_SECS));
+    loop {
+        tick.tick().await;
+        if let Err(e) = purge_due_accounts(&state).await {
+            tracing::warn!(error = %e.message, "account purge failed");
+        }
+        if let Err(e) = pod_events::deliver_pending(&state).await {
+            tracing::warn!(error = %e.message, "pod event delivery failed");
+        }
+        if let Err(e) = exports::process_pending(&state).await {
+            tracing::warn!(error = %e.message, "data export processing failed");
+        }
+        if let Err(e) = exports::purge_expired(&state.db).await {
+            tracing::warn!(error = %e.message, "data export cleanup failed");
+        }
+        if let Err(e) =
+            events::purge_expired(&state.db, state.config.auth_event_retention_days).await
+        {
+            tracing::warn!(error = %e.message, "auth event cleanup failed");
+        }
+    }
+}
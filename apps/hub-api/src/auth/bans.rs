@@ -0,0 +1,93 @@
+//! Hub-level bans and suspensions (RFC §14.4). A banned account can't get
+//! new SIAs or tokens; bots are held to their owner's standing too.
+
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+
+use crate::db::pool::DbPool;
+use crate::db::schema::{user_bans, users};
+use crate::error::ApiError;
+use crate::models::user::USER_STATUS_ACTIVE;
+use crate::models::user_ban::{UserBan, BAN_STATUS_SUSPENDED};
+
+/// Fail with 403 if the user, or the owner of a bot, is banned. Bans past
+/// their expiry are lifted on the way. Unknown users pass; callers load the
+/// user themselves.
+pub async fn check_standing(db: &DbPool, user_id: &str) -> Result<(), ApiError> {
+    let mut conn = db.get().await?;
+    let Some((status, owner_id)) = users::table
+        .find(user_id)
+        .select((users::status, users::bot_owner_id))
+        .first::<(String, Option<String>)>(&mut conn)
+        .await
+        .optional()
+        .map_err(ApiError::from)?
+    else {
+        return Ok(());
+    };
+
+    if status != USER_STATUS_ACTIVE {
+        if let Some(message) = ban_message(db, user_id).await? {
+            return Err(ApiError::forbidden(message));
+        }
+    }
+
+    if let Some(owner_id) = owner_id {
+        let owner_status: String = users::table
+            .find(&owner_id)
+            .select(users::status)
+            .first(&mut conn)
+            .await
+            .map_err(ApiError::from)?;
+        if owner_status != USER_STATUS_ACTIVE && ban_message(db, &owner_id).await?.is_some() {
+            return Err(ApiError::forbidden("This bot's owner is suspended"));
+        }
+    }
+    Ok(())
+}
+
+/// Why a user whose status isn't active may not sign in, or `None` once
+/// their ban has run out.
+async fn ban_message(db: &DbPool, user_id: &str) -> Result<Option<String>, ApiError> {
+    let mut conn = db.get().await?;
+    let ban: Option<UserBan> = user_bans::table
+        .find(user_id)
+        .select(UserBan::as_select())
+        .first(&mut conn)
+        .await
+        .optional()
+        .map_err(ApiError::from)?;
+
+    let Some(ban) = ban else {
+        return Ok(Some("This account is disabled".to_string()));
+    };
+    if ban.is_expired() {
+        lift_ban(&mut conn, user_id).await?;
+        tracing::info!(%user_id, "expired hub ban lifted");
+        return Ok(None);
+    }
+
+    Ok(Some(match (ban.status.as_str(), ban.expires_at) {
+        (BAN_STATUS_SUSPENDED, Some(until)) => format!(
+            "This account is suspended until {}: {}",
+            until.format("%Y-%m-%d %H:%M UTC"),
+            ban.reason
+        ),
+        _ => format!("This account has been banned: {}", ban.reason),
+    }))
+}
+
+/// Remove the user's ban and mark them active again. Returns whether they
+/// were banned.
+pub async fn lift_ban(conn: &mut AsyncPgConnection, user_id: &str) -> Result<bool, ApiError> {
+    let removed = diesel::delete(user_bans::table.find(user_id))
+        .execute(conn)
+        .await
+        .map_err(ApiError::from)?;
+    diesel::update(users::table.find(user_id))
+        .set(users::status.eq(USER_STATUS_ACTIVE))
+        .execute(conn)
+        .await
+        .map_err(ApiError::from)?;
+    Ok(removed > 0)
+}
@@ -0,0 +1,131 @@
+use axum::http::header::AUTHORIZATION;
+use axum::http::HeaderMap;
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use sha2::{Digest, Sha256};
+
+use crate::auth::tokens::generate_opaque_token;
+use crate::config::Config;
+use crate::db::pool::DbPool;
+use crate::db::schema::oauth_clients;
+use crate::error::ApiError;
+use crate::models::oauth_client::{
+    NewOAuthClient, OAuthClient, CLIENT_TYPE_PUBLIC, GRANT_AUTHORIZATION_CODE, GRANT_DEVICE_CODE,
+    GRANT_REFRESH_TOKEN, SUPPORTED_SCOPES,
+};
+
+/// Client ID of the Voxora web app. Its registration is kept in sync with
+/// the Hub's config by [`ensure_first_party_client`].
+pub const FIRST_PARTY_CLIENT_ID: &str = "voxora-web";
+
+/// Generate a new client secret (opaque, `hcs_` prefix).
+pub fn generate_client_secret() -> String {
+    generate_opaque_token("hcs", 32)
+}
+
+/// Hash a client secret for storage. Secrets are random, so a fast hash is
+/// enough.
+pub fn hash_client_secret(secret: &str) -> String {
+    let hash = Sha256::digest(secret.as_bytes());
+    hash.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Look up a registered client by ID.
+pub async fn load_client(db: &DbPool, client_id: &str) -> Result<Option<OAuthClient>, ApiError> {
+    let mut conn = db.get().await?;
+    oauth_clients::table
+        .find(client_id)
+        .select(OAuthClient::as_select())
+        .first(&mut conn)
+        .await
+        .optional()
+        .map_err(ApiError::from)
+}
+
+/// Register the Voxora apps as a public client, or update its redirect URIs
+/// to match `WEB_REDIRECT_URIS`. The terminal client and kiosks share it
+/// through the device grant. Run at startup.
+pub async fn ensure_first_party_client(db: &DbPool, config: &Config) -> Result<(), ApiError> {
+    let client = NewOAuthClient {
+        id: FIRST_PARTY_CLIENT_ID.to_string(),
+        owner_id: None,
+        name: "Voxora".to_string(),
+        client_type: CLIENT_TYPE_PUBLIC.to_string(),
+        client_secret_hash: None,
+        redirect_uris: config.web_redirect_uris.clone(),
+        allowed_scopes: SUPPORTED_SCOPES.iter().map(|s| s.to_string()).collect(),
+        grant_types: vec![
+            GRANT_AUTHORIZATION_CODE.to_string(),
+            GRANT_REFRESH_TOKEN.to_string(),
+            GRANT_DEVICE_CODE.to_string(),
+        ],
+        bot_user_id: None,
+    };
+
+    let mut conn = db.get().await?;
+    diesel::insert_into(oauth_clients::table)
+        .values(&client)
+        .on_conflict(oauth_clients::id)
+        .do_update()
+        .set((
+            oauth_clients::redirect_uris.eq(&client.redirect_uris),
+            oauth_clients::allowed_scopes.eq(&client.allowed_scopes),
+            oauth_clients::grant_types.eq(&client.grant_types),
+            oauth_clients::updated_at.eq(Utc::now()),
+        ))
+        .execute(&mut conn)
+        .await
+        .map_err(ApiError::from)?;
+    Ok(())
+}
+
+/// Identify and authenticate the client calling the token endpoint.
+///
+/// Confidential clients send their secret either in an HTTP Basic
+/// `Authorization` header (`client_secret_basic`) or as the `client_secret`
+/// form field (`client_secret_post`). Public clients only send `client_id`.
+pub async fn authenticate_client(
+    db: &DbPool,
+    headers: &HeaderMap,
+    form_client_id: Option<&str>,
+    form_client_secret: Option<&str>,
+) -> Result<OAuthClient, ApiError> {
+    let (client_id, secret) = match basic_credentials(headers) {
+        Some((id, secret)) => (id, Some(secret)),
+        None => (
+            form_client_id
+                .ok_or_else(|| ApiError::unauthorized("client_id is required"))?
+                .to_string(),
+            form_client_secret.map(|s| s.to_string()),
+        ),
+    };
+
+    let client = load_client(db, &client_id)
+        .await?
+        .ok_or_else(|| ApiError::unauthorized("invalid client credentials"))?;
+
+    if client.is_confidential() {
+        let expected = client.client_secret_hash.as_deref();
+        let given = secret.as_deref().map(hash_client_secret);
+        if expected.is_none() || given.as_deref() != expected {
+            return Err(ApiError::unauthorized("invalid client credentials"));
+        }
+    }
+
+    Ok(client)
+}
+
+/// Parse `Authorization: Basic base64(client_id:client_secret)`.
+fn basic_credentials(headers: &HeaderMap) -> Option<(String, String)> {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+
+    let encoded = headers
+        .get(AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Basic ")?;
+    let decoded = String::from_utf8(STANDARD.decode(encoded.trim()).ok()?).ok()?;
+    let (id, secret) = decoded.split_once(':')?;
+    Some((id.to_string(), secret.to_string()))
+}
@@ -0,0 +1,236 @@
+//! Device authorization grant (RFC 8628): device and user codes for signing
+//! in clients that have no browser, such as the terminal client or kiosks.
+
+use chrono::Utc;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::auth::tokens::generate_opaque_token;
+use crate::db::kv::KeyValueStore;
+use crate::error::ApiError;
+
+/// How long the user has to enter the code and approve the device.
+pub const DEVICE_CODE_TTL_SECS: i64 = 600;
+
+/// Minimum seconds between token polls. Raised by 5 on every `slow_down`.
+pub const DEVICE_POLL_INTERVAL_SECS: i64 = 5;
+
+/// User codes use consonants only, so they can't spell words and are easy to
+/// read aloud (RFC 8628 §6.1).
+const USER_CODE_ALPHABET: &[u8] = b"BCDFGHJKLMNPQRSTVWXZ";
+
+/// Characters in a user code, shown as two groups of four.
+const USER_CODE_LEN: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceStatus {
+    Pending,
+    Approved,
+    Denied,
+}
+
+/// A device waiting for its user to approve it.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeviceCodeData {
+    pub client_id: String,
+    pub scopes: Vec<String>,
+    /// Normalized user code (no separator).
+    pub user_code: String,
+    pub status: DeviceStatus,
+    /// Set once approved.
+    pub user_id: Option<String>,
+    /// Authentication methods the approving user completed.
+    #[serde(default)]
+    pub amr: Vec<String>,
+    /// Unix time the codes expire.
+    pub expires_at: i64,
+}
+
+/// How fast a pending device is polling. Kept under its own key so recording
+/// a poll never writes over the user's decision.
+#[derive(Debug, Serialize, Deserialize)]
+struct DevicePollState {
+    /// Current minimum poll interval in seconds.
+    interval: i64,
+    /// Unix time of the last token poll.
+    last_polled_at: i64,
+}
+
+/// Generate a device code (opaque, `hdc_` prefix). Only the device sees it.
+pub fn generate_device_code() -> String {
+    generate_opaque_token("hdc", 32)
+}
+
+/// Generate a normalized user code.
+pub fn generate_user_code() -> String {
+    let mut rng = rand::thread_rng();
+    (0..USER_CODE_LEN)
+        .map(|_| USER_CODE_ALPHABET[rng.gen_range(0..USER_CODE_ALPHABET.len())] as char)
+        .collect()
+}
+
+/// Format a normalized user code for display, e.g. `BDFG-HJKL`.
+pub fn format_user_code(user_code: &str) -> String {
+    let (a, b) = user_code.split_at(user_code.len().min(USER_CODE_LEN / 2));
+    format!("{a}-{b}")
+}
+
+/// Normalize what the user typed: case and separators don't matter.
+pub fn normalize_user_code(input: &str) -> String {
+    input
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+/// Seconds until `data` expires, at least 1.
+fn remaining_ttl(data: &DeviceCodeData) -> u64 {
+    (data.expires_at - Utc::now().timestamp()).max(1) as u64
+}
+
+/// Store a device code, keeping its original expiry.
+pub async fn store_device_code(
+    kv: &dyn KeyValueStore,
+    device_code: &str,
+    data: &DeviceCodeData,
+) -> Result<(), ApiError> {
+    let value = serde_json::to_string(data).map_err(|_| ApiError::internal("serialization"))?;
+    kv.set_ex(
+        &format!("hub:device:{device_code}"),
+        &value,
+        remaining_ttl(data),
+    )
+    .await
+}
+
+/// Index a device code by its user code, for the verification page.
+pub async fn store_user_code(
+    kv: &dyn KeyValueStore,
+    device_code: &str,
+    data: &DeviceCodeData,
+) -> Result<(), ApiError> {
+    kv.set_ex(
+        &format!("hub:device_user:{}", data.user_code),
+        device_code,
+        remaining_ttl(data),
+    )
+    .await
+}
+
+/// Whether a user code is already in use.
+pub async fn user_code_taken(kv: &dyn KeyValueStore, user_code: &str) -> Result<bool, ApiError> {
+    Ok(kv
+        .get(&format!("hub:device_user:{user_code}"))
+        .await?
+        .is_some())
+}
+
+/// Look up a device code.
+pub async fn load_device_code(
+    kv: &dyn KeyValueStore,
+    device_code: &str,
+) -> Result<Option<DeviceCodeData>, ApiError> {
+    match kv.get(&format!("hub:device:{device_code}")).await? {
+        Some(v) => {
+            let data: DeviceCodeData = serde_json::from_str(&v)
+                .map_err(|_| ApiError::internal("corrupt device code data"))?;
+            Ok(Some(data))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Look up a pending device by the code the user typed. Returns the device
+/// code with its data.
+pub async fn load_by_user_code(
+    kv: &dyn KeyValueStore,
+    input: &str,
+) -> Result<Option<(String, DeviceCodeData)>, ApiError> {
+    let user_code = normalize_user_code(input);
+    if user_code.len() != USER_CODE_LEN {
+        return Ok(None);
+    }
+    let Some(device_code) = kv.get(&format!("hub:device_user:{user_code}")).await? else {
+        return Ok(None);
+    };
+    Ok(load_device_code(kv, &device_code)
+        .await?
+        .filter(|d| d.status == DeviceStatus::Pending)
+        .map(|d| (device_code, d)))
+}
+
+/// Record the user's decision. Only the first decision counts: returns
+/// `false`, changing nothing, if the device was already approved or denied.
+/// The user code stops working either way.
+pub async fn decide(
+    kv: &dyn KeyValueStore,
+    device_code: &str,
+    mut data: DeviceCodeData,
+    approved_by: Option<(String, Vec<String>)>,
+) -> Result<bool, ApiError> {
+    let claim = format!("hub:device_decided:{device_code}");
+    if !kv.set_nx_ex(&claim, "1", remaining_ttl(&data)).await? {
+        return Ok(false);
+    }
+    match approved_by {
+        Some((user_id, amr)) => {
+            data.status = DeviceStatus::Approved;
+            data.user_id = Some(user_id);
+            data.amr = amr;
+        }
+        None => data.status = DeviceStatus::Denied,
+    }
+    store_device_code(kv, device_code, &data).await?;
+    kv.del(&format!("hub:device_user:{}", data.user_code))
+        .await?;
+    Ok(true)
+}
+
+/// Record a token poll of a pending device. Returns whether it came sooner
+/// than the current interval, in which case the interval goes up by
+/// [`DEVICE_POLL_INTERVAL_SECS`] (RFC 8628 §3.5).
+pub async fn record_poll(
+    kv: &dyn KeyValueStore,
+    device_code: &str,
+    data: &DeviceCodeData,
+) -> Result<bool, ApiError> {
+    let key = format!("hub:device_poll:{device_code}");
+    let now = Utc::now().timestamp();
+    let previous: Option<DevicePollState> = kv
+        .get(&key)
+        .await?
+        .and_then(|v| serde_json::from_str(&v).ok());
+
+    let mut interval = previous
+        .as_ref()
+        .map_or(DEVICE_POLL_INTERVAL_SECS, |p| p.interval);
+    let too_soon = previous.is_some_and(|p| now - p.last_polled_at < p.interval);
+    if too_soon {
+        interval += DEVICE_POLL_INTERVAL_SECS;
+    }
+
+    let state = DevicePollState {
+        interval,
+        last_polled_at: now,
+    };
+    let value = serde_json::to_string(&state).map_err(|_| ApiError::internal("serialization"))?;
+    kv.set_ex(&key, &value, remaining_ttl(data)).await?;
+    Ok(too_soon)
+}
+
+/// Take an approved or denied device code, deleting it so it's acted on at
+/// most once. Returns `None` if another poll took it first.
+pub async fn take_device_code(
+    kv: &dyn KeyValueStore,
+    device_code: &str,
+) -> Result<Option<DeviceCodeData>, ApiError> {
+    let Some(value) = kv.get_del(&format!("hub:device:{device_code}")).await? else {
+        return Ok(None);
+    };
+    kv.del(&format!("hub:device_poll:{device_code}")).await?;
+    let data: DeviceCodeData =
+        serde_json::from_str(&value).map_err(|_| ApiError::internal("corrupt device code data"))?;
+    Ok(Some(data))
+}
@@ -0,0 +1,132 @@
+//! Authentication event log (RFC §18.4). Sign-ins, second factors, token
+//! grants, SIAs and password changes are recorded with the address and
+//! `User-Agent` they came from, so users can review activity on their
+//! account. Entries are deleted after `AUTH_EVENT_RETENTION_DAYS`.
+
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use ipnet::IpNet;
+
+use crate::auth::middleware::ClientInfo;
+use crate::db::pool::DbPool;
+use crate::db::schema::auth_events;
+use crate::error::ApiError;
+use crate::models::auth_event::NewAuthEvent;
+
+/// A sign-in completed, with every factor the account requires.
+pub const LOGIN_SUCCESS: &str = "login.success";
+/// A sign-in was refused; `details.reason` says why.
+pub const LOGIN_FAILURE: &str = "login.failure";
+/// A TOTP or recovery code was accepted.
+pub const MFA_SUCCESS: &str = "mfa.success";
+/// A TOTP or recovery code was rejected.
+pub const MFA_FAILURE: &str = "mfa.failure";
+/// TOTP was turned on for the account.
+pub const MFA_ENABLED: &str = "mfa.enabled";
+/// TOTP was turned off for the account.
+pub const MFA_DISABLED: &str = "mfa.disabled";
+/// Tokens were issued at the token endpoint.
+pub const TOKEN_ISSUED: &str = "token.issued";
+/// A refresh token was rotated.
+pub const TOKEN_REFRESHED: &str = "token.refreshed";
+/// A token was revoked through the revocation endpoint.
+pub const TOKEN_REVOKED: &str = "token.revoked";
+/// A rotated refresh token was presented again and its family revoked.
+pub const TOKEN_REUSE_DETECTED: &str = "token.reuse_detected";
+/// A SIA was issued for a pod.
+pub const SIA_ISSUED: &str = "sia.issued";
+/// The account's password was changed.
+pub const PASSWORD_CHANGED: &str = "password.changed";
+/// Password sign-in was locked after repeated failures.
+pub const ACCOUNT_LOCKED: &str = "account.locked";
+/// A lock was lifted before it ran out.
+pub const ACCOUNT_UNLOCKED: &str = "account.unlocked";
+
+/// An event to record. Built with [`AuthEvent::new`] and the setters for
+/// whatever context applies.
+#[derive(Debug)]
+pub struct AuthEvent<'a> {
+    event: &'a str,
+    user_id: Option<&'a str>,
+    client_id: Option<&'a str>,
+    pod_id: Option<&'a str>,
+    details: Option<serde_json::Value>,
+}
+
+impl<'a> AuthEvent<'a> {
+    pub fn new(event: &'a str) -> Self {
+        Self {
+            event,
+            user_id: None,
+            client_id: None,
+            pod_id: None,
+            details: None,
+        }
+    }
+
+    pub fn user(mut self, user_id: &'a str) -> Self {
+        self.user_id = Some(user_id);
+        self
+    }
+
+    pub fn client(mut self, client_id: &'a str) -> Self {
+        self.client_id = Some(client_id);
+        self
+    }
+
+    pub fn pod(mut self, pod_id: &'a str) -> Self {
+        self.pod_id = Some(pod_id);
+        self
+    }
+
+    pub fn details(mut self, details: serde_json::Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+}
+
+/// Record an event for a request from `client`.
+///
+/// The log must never stand in the way of signing in, so failures are only
+/// traced.
+pub async fn record(db: &DbPool, client: &ClientInfo, event: AuthEvent<'_>) {
+    if let Err(e) = insert(db, client, &event).await {
+        tracing::warn!(event = %event.event, error = %e.message, "failed to record auth event");
+    }
+}
+
+async fn insert(db: &DbPool, client: &ClientInfo, event: &AuthEvent<'_>) -> Result<(), ApiError> {
+    let mut conn = db.get().await?;
+    let event_id = voxora_common::id::prefixed_ulid(voxora_common::id::prefix::AUTH_EVENT);
+
+    diesel::insert_into(auth_events::table)
+        .values(NewAuthEvent {
+            id: &event_id,
+            user_id: event.user_id,
+            event: event.event,
+            ip_address: client.ip_address.map(IpNet::from),
+            user_agent: client.user_agent.as_deref(),
+            client_id: event.client_id,
+            pod_id: event.pod_id,
+            details: event.details.clone(),
+            created_at: Utc::now(),
+        })
+        .execute(&mut conn)
+        .await
+        .map_err(ApiError::from)?;
+
+    Ok(())
+}
+
+/// Delete events older than `retention_days`. Returns how many were deleted.
+pub async fn purge_expired(db: &DbPool, retention_days: i64) -> Result<usize, ApiError> {
+    let mut conn = db.get().await?;
+    diesel::delete(
+        auth_events::table
+            .filter(auth_events::created_at.lt(Utc::now() - Duration::days(retention_days))),
+    )
+    .execute(&mut conn)
+    .await
+    .map_err(ApiError::from)
+}
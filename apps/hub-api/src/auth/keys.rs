@@ -1,12 +1,24 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
 use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Utc;
 use ed25519_dalek::{SigningKey, VerifyingKey};
 use jsonwebtoken::{DecodingKey, EncodingKey};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use utoipa::ToSchema;
+
+use crate::config::Config;
+use crate::error::ApiError;
 
 /// Holds the Ed25519 keypair and derived `jsonwebtoken` encoding/decoding keys.
 ///
-/// In production, keys would come from a KMS. For Phase 1, we derive deterministically
-/// from `SIGNING_KEY_SEED` so that restarts produce the same key (stable JWKS).
+/// One key of a [`Keyring`]. In development the only key is derived
+/// deterministically from `SIGNING_KEY_SEED` so that restarts produce the
+/// same key (stable JWKS).
 #[derive(Clone)]
 pub struct SigningKeys {
     /// Key ID for the JWKS entry.
@@ -24,11 +36,12 @@ impl SigningKeys {
     ///
     /// The seed is hashed via SHA-256 to produce exactly 32 bytes.
     pub fn from_seed(seed: &str) -> Self {
-        let hash = Sha256::digest(seed.as_bytes());
-        let mut secret_bytes = [0u8; 32];
-        secret_bytes.copy_from_slice(&hash);
+        Self::from_secret(&seed_secret(seed))
+    }
 
-        let signing_key = SigningKey::from_bytes(&secret_bytes);
+    /// Build a keypair from a raw 32-byte Ed25519 secret key.
+    pub fn from_secret(secret_bytes: &[u8; 32]) -> Self {
+        let signing_key = SigningKey::from_bytes(secret_bytes);
         let verifying_key: VerifyingKey = (&signing_key).into();
 
         let secret = signing_key.to_bytes();
@@ -56,6 +69,13 @@ impl SigningKeys {
     }
 }
 
+fn seed_secret(seed: &str) -> [u8; 32] {
+    let hash = Sha256::digest(seed.as_bytes());
+    let mut secret_bytes = [0u8; 32];
+    secret_bytes.copy_from_slice(&hash);
+    secret_bytes
+}
+
 fn hex_prefix(bytes: &[u8], chars: usize) -> String {
     bytes
         .iter()
@@ -81,8 +101,6 @@ fn wrap_ed25519_private_pkcs8(secret: &[u8; 32]) -> Vec<u8> {
     der
 }
 
-
-
 impl std::fmt::Debug for SigningKeys {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("SigningKeys")
@@ -91,3 +109,320 @@ impl std::fmt::Debug for SigningKeys {
             .finish_non_exhaustive()
     }
 }
+
+// ===========================================================================
+// Keyring
+// ===========================================================================
+
+/// How long a retired key stays in JWKS. Comfortably longer than any JWT the
+/// Hub signs (ID tokens live 15 minutes, SIAs 5).
+pub const RETIRED_KEY_TTL_SECS: i64 = 3600;
+
+/// Where a key is in its lifecycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyStatus {
+    /// Signs new tokens.
+    Active,
+    /// Published so verifiers can cache it; becomes active on the next rotation.
+    Next,
+    /// No longer signs, but still verifies tokens issued before the rotation.
+    Retired,
+}
+
+/// A key as published in JWKS.
+#[derive(Debug, Clone)]
+pub struct PublishedKey {
+    pub keys: Arc<SigningKeys>,
+    pub status: KeyStatus,
+    /// Unix time the key was retired.
+    pub retired_at: Option<i64>,
+}
+
+/// On-disk manifest (`keyring.json`) naming the key in each slot. Each key's
+/// secret lives next to it in `<kid>.key`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    active: String,
+    next: Option<String>,
+    #[serde(default)]
+    retired: Vec<RetiredEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RetiredEntry {
+    kid: String,
+    retired_at: i64,
+}
+
+struct KeyringState {
+    active: Arc<SigningKeys>,
+    next: Option<Arc<SigningKeys>>,
+    retired: Vec<(Arc<SigningKeys>, i64)>,
+}
+
+/// The Hub's signing keys: one active key that signs, the next key waiting
+/// to take over, and recently retired keys that still verify.
+///
+/// With `SIGNING_KEY_DIR` set the keyring is stored in that directory and
+/// can be rotated. Otherwise a single key is derived from `SIGNING_KEY_SEED`
+/// and rotation is unavailable.
+pub struct Keyring {
+    dir: Option<PathBuf>,
+    state: RwLock<KeyringState>,
+    /// Held for a whole rotation, so rotations run one at a time while the
+    /// state lock is only taken to swap in the result.
+    rotating: tokio::sync::Mutex<()>,
+}
+
+impl Keyring {
+    /// Load the keyring described by the config.
+    pub fn from_config(config: &Config) -> io::Result<Self> {
+        match (&config.signing_key_dir, &config.signing_key_seed) {
+            (Some(dir), seed) => Self::load_dir(Path::new(dir), seed.as_deref()),
+            (None, Some(seed)) => Ok(Self::from_seed(seed)),
+            (None, None) => Err(io::Error::other(
+                "either SIGNING_KEY_DIR or SIGNING_KEY_SEED is required",
+            )),
+        }
+    }
+
+    /// A fixed keyring with a single key derived from `seed`.
+    pub fn from_seed(seed: &str) -> Self {
+        Self {
+            dir: None,
+            state: RwLock::new(KeyringState {
+                active: Arc::new(SigningKeys::from_seed(seed)),
+                next: None,
+                retired: Vec::new(),
+            }),
+            rotating: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Load a keyring from `dir`, creating it if the directory is empty.
+    ///
+    /// A new keyring's active key is derived from `initial_seed` when given,
+    /// so tokens signed before switching to a key directory stay valid.
+    pub fn load_dir(dir: &Path, initial_seed: Option<&str>) -> io::Result<Self> {
+        let manifest_path = dir.join(MANIFEST_FILE);
+        if !manifest_path.exists() {
+            std::fs::create_dir_all(dir)?;
+            let secret = initial_seed.map_or_else(random_secret, seed_secret);
+            let active = SigningKeys::from_secret(&secret);
+            write_secret(dir, &active.kid, &secret)?;
+            let next = generate_key(dir)?;
+            write_manifest(
+                dir,
+                &Manifest {
+                    active: active.kid.clone(),
+                    next: Some(next.kid.clone()),
+                    retired: Vec::new(),
+                },
+            )?;
+        }
+
+        let manifest: Manifest = serde_json::from_str(&std::fs::read_to_string(&manifest_path)?)
+            .map_err(|e| io::Error::other(format!("invalid {MANIFEST_FILE}: {e}")))?;
+        let now = Utc::now().timestamp();
+
+        let state = KeyringState {
+            active: Arc::new(read_key(dir, &manifest.active)?),
+            next: match &manifest.next {
+                Some(kid) => Some(Arc::new(read_key(dir, kid)?)),
+                None => None,
+            },
+            retired: manifest
+                .retired
+                .iter()
+                .filter(|r| r.retired_at + RETIRED_KEY_TTL_SECS > now)
+                .map(|r| Ok((Arc::new(read_key(dir, &r.kid)?), r.retired_at)))
+                .collect::<io::Result<_>>()?,
+        };
+
+        Ok(Self {
+            dir: Some(dir.to_path_buf()),
+            state: RwLock::new(state),
+            rotating: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    /// The key new tokens are signed with.
+    pub fn active(&self) -> Arc<SigningKeys> {
+        self.state.read().expect("keyring lock").active.clone()
+    }
+
+    /// Every key verifiers should accept: active, next, and retired keys
+    /// whose tokens may not have expired yet.
+    pub fn published(&self) -> Vec<PublishedKey> {
+        let state = self.state.read().expect("keyring lock");
+        let now = Utc::now().timestamp();
+
+        let mut keys = vec![PublishedKey {
+            keys: state.active.clone(),
+            status: KeyStatus::Active,
+            retired_at: None,
+        }];
+        keys.extend(state.next.iter().map(|k| PublishedKey {
+            keys: k.clone(),
+            status: KeyStatus::Next,
+            retired_at: None,
+        }));
+        keys.extend(
+            state
+                .retired
+                .iter()
+                .filter(|(_, at)| at + RETIRED_KEY_TTL_SECS > now)
+                .map(|(k, at)| PublishedKey {
+                    keys: k.clone(),
+                    status: KeyStatus::Retired,
+                    retired_at: Some(*at),
+                }),
+        );
+        keys
+    }
+
+    /// Promote the next key to active, retire the active key, and publish a
+    /// fresh next key. Retired keys past their TTL are deleted.
+    pub async fn rotate(&self) -> Result<(), ApiError> {
+        let Some(dir) = self.dir.clone() else {
+            return Err(ApiError::conflict(
+                "Signing keys are derived from SIGNING_KEY_SEED; set SIGNING_KEY_DIR to rotate them",
+            ));
+        };
+        let io_err = |e: io::Error| {
+            tracing::error!(?e, "signing key rotation failed");
+            ApiError::internal("Signing key rotation failed")
+        };
+
+        let _rotating = self.rotating.lock().await;
+        let (old_active, next, retired) = {
+            let state = self.state.read().expect("keyring lock");
+            (
+                state.active.clone(),
+                state.next.clone(),
+                state.retired.clone(),
+            )
+        };
+        let now = Utc::now().timestamp();
+
+        let (live, expired): (Vec<_>, Vec<_>) = retired
+            .into_iter()
+            .partition(|(_, at)| at + RETIRED_KEY_TTL_SECS > now);
+        let mut retired = live;
+        retired.push((old_active.clone(), now));
+        let retired_entries: Vec<_> = retired
+            .iter()
+            .map(|(k, at)| RetiredEntry {
+                kid: k.kid.clone(),
+                retired_at: *at,
+            })
+            .collect();
+
+        // Key files and the manifest are written off the async runtime, and
+        // before the new keys are swapped in.
+        let (new_active, new_next) = tokio::task::spawn_blocking(move || {
+            let new_next = Arc::new(generate_key(&dir)?);
+            // A keyring without a next key (e.g. edited by hand) activates a
+            // fresh one immediately.
+            let new_active = match next {
+                Some(next) => next,
+                None => Arc::new(generate_key(&dir)?),
+            };
+            write_manifest(
+                &dir,
+                &Manifest {
+                    active: new_active.kid.clone(),
+                    next: Some(new_next.kid.clone()),
+                    retired: retired_entries,
+                },
+            )?;
+            for (keys, _) in &expired {
+                let _ = std::fs::remove_file(dir.join(format!("{}.key", keys.kid)));
+            }
+            Ok((new_active, new_next))
+        })
+        .await
+        .map_err(io::Error::other)
+        .and_then(|r| r)
+        .map_err(io_err)?;
+
+        tracing::info!(
+            active = %new_active.kid,
+            next = %new_next.kid,
+            retired = %old_active.kid,
+            "signing keys rotated"
+        );
+        *self.state.write().expect("keyring lock") = KeyringState {
+            active: new_active,
+            next: Some(new_next),
+            retired,
+        };
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for Keyring {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Keyring")
+            .field("dir", &self.dir)
+            .field("active", &self.active().kid)
+            .finish_non_exhaustive()
+    }
+}
+
+const MANIFEST_FILE: &str = "keyring.json";
+
+fn random_secret() -> [u8; 32] {
+    let mut secret = [0u8; 32];
+    rand::rngs::OsRng.fill_bytes(&mut secret);
+    secret
+}
+
+/// Generate a random key and store its secret in `dir`.
+fn generate_key(dir: &Path) -> io::Result<SigningKeys> {
+    let secret = random_secret();
+    let keys = SigningKeys::from_secret(&secret);
+    write_secret(dir, &keys.kid, &secret)?;
+    Ok(keys)
+}
+
+fn write_secret(dir: &Path, kid: &str, secret: &[u8; 32]) -> io::Result<()> {
+    use std::io::Write;
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(dir.join(format!("{kid}.key")))?;
+    file.write_all(URL_SAFE_NO_PAD.encode(secret).as_bytes())
+}
+
+fn read_key(dir: &Path, kid: &str) -> io::Result<SigningKeys> {
+    let encoded = std::fs::read_to_string(dir.join(format!("{kid}.key")))?;
+    let secret: [u8; 32] = URL_SAFE_NO_PAD
+        .decode(encoded.trim())
+        .ok()
+        .and_then(|b| b.try_into().ok())
+        .ok_or_else(|| io::Error::other(format!("{kid}.key is not a 32-byte base64url key")))?;
+    let keys = SigningKeys::from_secret(&secret);
+    if keys.kid != kid {
+        return Err(io::Error::other(format!(
+            "{kid}.key holds the key for {}",
+            keys.kid
+        )));
+    }
+    Ok(keys)
+}
+
+/// Replace the manifest atomically, so a crash mid-rotation leaves the old
+/// keyring in place.
+fn write_manifest(dir: &Path, manifest: &Manifest) -> io::Result<()> {
+    let tmp = dir.join(format!("{MANIFEST_FILE}.tmp"));
+    let json = serde_json::to_string_pretty(manifest).map_err(io::Error::other)?;
+    std::fs::write(&tmp, json)?;
+    std::fs::rename(tmp, dir.join(MANIFEST_FILE))
+}
@@ -0,0 +1,297 @@
+//! Multi-factor authentication: TOTP (RFC 6238) and single-use recovery codes.
+
+use chrono::Utc;
+use data_encoding::BASE32_NOPAD;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+use crate::db::kv::KeyValueStore;
+use crate::db::pool::DbPool;
+use crate::db::schema::mfa_recovery_codes;
+use crate::error::ApiError;
+use crate::models::recovery_code::NewRecoveryCode;
+use crate::models::user::User;
+
+/// Issuer label shown in authenticator apps.
+pub const TOTP_ISSUER: &str = "Voxora";
+
+/// Number of digits in a TOTP code.
+pub const TOTP_DIGITS: u32 = 6;
+
+/// TOTP time step in seconds.
+pub const TOTP_PERIOD_SECS: u64 = 30;
+
+/// Steps accepted either side of the current one, to tolerate clock drift.
+const TOTP_SKEW_STEPS: u64 = 1;
+
+/// Secret length in bytes (160 bits, as recommended by RFC 4226).
+const TOTP_SECRET_BYTES: usize = 20;
+
+/// Number of recovery codes issued at enrollment (RFC §5.3).
+pub const RECOVERY_CODE_COUNT: usize = 8;
+
+/// Authentication Method Reference values (RFC 8176) for the ID token `amr` claim.
+pub mod amr {
+    pub const PASSWORD: &str = "pwd";
+    pub const OTP: &str = "otp";
+    pub const MFA: &str = "mfa";
+    pub const HARDWARE_KEY: &str = "hwk";
+    /// Signed in through an upstream identity provider. Not in RFC 8176.
+    pub const FEDERATED: &str = "fed";
+}
+
+// ---------------------------------------------------------------------------
+// TOTP
+// ---------------------------------------------------------------------------
+
+/// Generate a random base32-encoded TOTP secret.
+pub fn generate_totp_secret() -> String {
+    let mut buf = [0u8; TOTP_SECRET_BYTES];
+    rand::thread_rng().fill(&mut buf[..]);
+    BASE32_NOPAD.encode(&buf)
+}
+
+/// Build the `otpauth://` URI that authenticator apps scan as a QR code.
+pub fn provisioning_uri(secret: &str, account: &str) -> String {
+    format!(
+        "otpauth://totp/{issuer}:{account}?secret={secret}&issuer={issuer}&algorithm=SHA1&digits={TOTP_DIGITS}&period={TOTP_PERIOD_SECS}",
+        issuer = uri_encode(TOTP_ISSUER),
+        account = uri_encode(account),
+    )
+}
+
+/// Compute the TOTP code for a time step. Returns `None` if the secret isn't
+/// valid base32.
+pub fn totp_code(secret: &str, step: u64) -> Option<String> {
+    let key = BASE32_NOPAD.decode(secret.as_bytes()).ok()?;
+    let mut mac = Hmac::<Sha1>::new_from_slice(&key).expect("HMAC accepts any key length");
+    mac.update(&step.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    // Dynamic truncation (RFC 4226 §5.3).
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        digest[offset] & 0x7f,
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]);
+    let code = binary % 10u32.pow(TOTP_DIGITS);
+    Some(format!("{code:0width$}", width = TOTP_DIGITS as usize))
+}
+
+/// The TOTP time step containing `unix_time`.
+pub fn totp_step(unix_time: u64) -> u64 {
+    unix_time / TOTP_PERIOD_SECS
+}
+
+/// Check a TOTP code against the steps around `unix_time`. Returns the
+/// matching step so callers can reject replays.
+pub fn verify_totp(secret: &str, code: &str, unix_time: u64) -> Option<u64> {
+    let code = code.trim();
+    if code.len() != TOTP_DIGITS as usize || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let now = totp_step(unix_time);
+    (now.saturating_sub(TOTP_SKEW_STEPS)..=now + TOTP_SKEW_STEPS)
+        .find(|&step| totp_code(secret, step).is_some_and(|c| c == code))
+}
+
+// ---------------------------------------------------------------------------
+// Recovery codes
+// ---------------------------------------------------------------------------
+
+/// Generate a fresh set of recovery codes, formatted `xxxxx-xxxxx`.
+pub fn generate_recovery_codes() -> Vec<String> {
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let mut buf = [0u8; 10];
+            rand::thread_rng().fill(&mut buf[..]);
+            let encoded = BASE32_NOPAD.encode(&buf).to_lowercase();
+            format!("{}-{}", &encoded[..5], &encoded[5..10])
+        })
+        .collect()
+}
+
+/// Hash a recovery code for storage. Case, spaces and dashes are ignored.
+pub fn hash_recovery_code(code: &str) -> String {
+    let normalized: String = code
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .flat_map(char::to_lowercase)
+        .collect();
+    let hash = Sha256::digest(normalized.as_bytes());
+    hash.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Replace all of a user's recovery codes with a new set. Returns the
+/// plaintext codes, which are shown to the user exactly once.
+pub async fn replace_recovery_codes(db: &DbPool, user_id: &str) -> Result<Vec<String>, ApiError> {
+    let codes = generate_recovery_codes();
+    let rows: Vec<NewRecoveryCode> = codes
+        .iter()
+        .map(|code| NewRecoveryCode {
+            id: voxora_common::id::prefixed_ulid(voxora_common::id::prefix::RECOVERY_CODE),
+            user_id: user_id.to_string(),
+            code_hash: hash_recovery_code(code),
+        })
+        .collect();
+
+    let mut conn = db.get().await?;
+    diesel::delete(mfa_recovery_codes::table.filter(mfa_recovery_codes::user_id.eq(user_id)))
+        .execute(&mut conn)
+        .await?;
+    diesel::insert_into(mfa_recovery_codes::table)
+        .values(&rows)
+        .execute(&mut conn)
+        .await?;
+
+    Ok(codes)
+}
+
+// ---------------------------------------------------------------------------
+// Second-factor verification
+// ---------------------------------------------------------------------------
+
+/// Which second factor a user presented.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecondFactor {
+    Totp,
+    RecoveryCode,
+}
+
+impl SecondFactor {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SecondFactor::Totp => "totp",
+            SecondFactor::RecoveryCode => "recovery_code",
+        }
+    }
+}
+
+/// Verify `code` as a TOTP code for `user`, or failing that as one of their
+/// unused recovery codes (which is consumed). Returns `None` if neither
+/// matches or the user has no TOTP secret.
+pub async fn verify_second_factor(
+    db: &DbPool,
+    kv: &dyn KeyValueStore,
+    user: &User,
+    code: &str,
+) -> Result<Option<SecondFactor>, ApiError> {
+    let Some(secret) = user.mfa_secret.as_deref() else {
+        return Ok(None);
+    };
+
+    let now = Utc::now().timestamp().max(0) as u64;
+    if let Some(step) = verify_totp(secret, code, now) {
+        // Each code is only good once, even within its window.
+        let key = format!("hub:totp_used:{}:{}", user.id, step);
+        let ttl = TOTP_PERIOD_SECS * (2 * TOTP_SKEW_STEPS + 1);
+        if !kv.set_nx_ex(&key, "1", ttl).await? {
+            return Ok(None);
+        }
+        return Ok(Some(SecondFactor::Totp));
+    }
+
+    let mut conn = db.get().await?;
+    let consumed = diesel::update(
+        mfa_recovery_codes::table
+            .filter(mfa_recovery_codes::user_id.eq(&user.id))
+            .filter(mfa_recovery_codes::code_hash.eq(hash_recovery_code(code)))
+            .filter(mfa_recovery_codes::used_at.is_null()),
+    )
+    .set(mfa_recovery_codes::used_at.eq(Utc::now()))
+    .execute(&mut conn)
+    .await?;
+
+    Ok((consumed > 0).then_some(SecondFactor::RecoveryCode))
+}
+
+/// Percent-encode everything except RFC 3986 unreserved characters.
+fn uri_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+// ---------------------------------------------------------------------------
+// Sign-in challenges
+// ---------------------------------------------------------------------------
+
+/// How long a user has to enter their second factor after their password.
+pub const MFA_CHALLENGE_TTL_SECS: u64 = 300;
+
+/// Wrong codes allowed per challenge before the sign-in must start over.
+pub const MAX_MFA_ATTEMPTS: u32 = 5;
+
+/// A first-factor-verified sign-in waiting for its second factor. Carries the
+/// authorization request so the code can be issued once MFA succeeds.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MfaChallengeData {
+    pub user_id: String,
+    pub client_id: String,
+    pub redirect_uri: String,
+    pub code_challenge: String,
+    pub scopes: Vec<String>,
+    pub nonce: Option<String>,
+    pub state: Option<String>,
+    /// Set when the sign-in approves a device (RFC 8628) instead of
+    /// returning to a client's redirect URI.
+    #[serde(default)]
+    pub device_code: Option<String>,
+    /// Methods the first step used; a password when empty.
+    #[serde(default)]
+    pub amr: Vec<String>,
+}
+
+/// Store a new MFA challenge.
+pub async fn store_mfa_challenge(
+    kv: &dyn KeyValueStore,
+    token: &str,
+    data: &MfaChallengeData,
+) -> Result<(), ApiError> {
+    let key = format!("hub:mfa:{}", token);
+    let value = serde_json::to_string(data).map_err(|_| ApiError::internal("serialization"))?;
+    kv.set_ex(&key, &value, MFA_CHALLENGE_TTL_SECS).await
+}
+
+/// Look up an MFA challenge without consuming it.
+pub async fn load_mfa_challenge(
+    kv: &dyn KeyValueStore,
+    token: &str,
+) -> Result<Option<MfaChallengeData>, ApiError> {
+    let key = format!("hub:mfa:{}", token);
+    match kv.get(&key).await? {
+        Some(v) => {
+            let data: MfaChallengeData = serde_json::from_str(&v)
+                .map_err(|_| ApiError::internal("corrupt challenge data"))?;
+            Ok(Some(data))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Count a wrong code against an MFA challenge. Returns the wrong codes
+/// entered so far, including this one. The count is kept beside the challenge
+/// so recording it never extends the challenge's expiry.
+pub async fn record_mfa_failure(kv: &dyn KeyValueStore, token: &str) -> Result<u32, ApiError> {
+    let key = format!("hub:mfa_attempts:{}", token);
+    let attempts = kv.incr_ex(&key, MFA_CHALLENGE_TTL_SECS).await?;
+    Ok(attempts.try_into().unwrap_or(u32::MAX))
+}
+
+/// Delete an MFA challenge once it has succeeded or run out of attempts.
+pub async fn delete_mfa_challenge(kv: &dyn KeyValueStore, token: &str) -> Result<(), ApiError> {
+    kv.del(&format!("hub:mfa:{}", token)).await?;
+    kv.del(&format!("hub:mfa_attempts:{}", token)).await
+}
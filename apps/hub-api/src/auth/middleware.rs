@@ -1,5 +1,8 @@
-use axum::extract::FromRequestParts;
-use axum::http::header::AUTHORIZATION;
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+
+use axum::extract::{ConnectInfo, FromRequestParts};
+use axum::http::header::{AUTHORIZATION, USER_AGENT};
 use axum::http::request::Parts;
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
@@ -19,6 +22,8 @@ use crate::AppState;
 pub struct AuthUser {
     pub user_id: String,
     pub scopes: Vec<String>,
+    /// Session the access token belongs to, if it came from a token grant.
+    pub session_id: Option<String>,
 }
 
 /// Rejection returned when the bearer token is missing or invalid.
@@ -71,6 +76,70 @@ impl FromRequestParts<AppState> for AuthUser {
         Ok(AuthUser {
             user_id: data.user_id,
             scopes: data.scopes,
+            session_id: data.session_id,
+        })
+    }
+}
+
+/// Where a request came from: the client address and `User-Agent`, recorded
+/// on sessions so users can recognise their devices.
+///
+/// The address is the peer address of the connection. When the peer is one of
+/// the configured trusted proxies, `X-Forwarded-For` is walked from the right
+/// and the first hop that isn't a trusted proxy is the client; anything further
+/// left was supplied by the client and can't be believed.
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfo {
+    pub ip_address: Option<IpAddr>,
+    pub user_agent: Option<String>,
+}
+
+impl FromRequestParts<AppState> for ClientInfo {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+        let user_agent = parts
+            .headers
+            .get(USER_AGENT)
+            .and_then(|v| v.to_str().ok())
+            .map(|ua| ua.chars().take(512).collect());
+
+        Ok(ClientInfo {
+            ip_address: peer.map(|peer| client_ip(peer, parts, &state.config.trusted_proxies)),
+            user_agent,
         })
     }
 }
+
+/// The client address for a request from `peer`, following `X-Forwarded-For`
+/// back through trusted proxies only.
+fn client_ip(peer: IpAddr, parts: &Parts, trusted: &[IpAddr]) -> IpAddr {
+    let mut client = peer;
+    if !trusted.contains(&client) {
+        return client;
+    }
+    let hops = parts
+        .headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .collect::<Vec<_>>();
+    for hop in hops.into_iter().rev() {
+        let Ok(ip) = hop.trim().parse::<IpAddr>() else {
+            break;
+        };
+        client = ip;
+        if !trusted.contains(&client) {
+            break;
+        }
+    }
+    client
+}
@@ -1,5 +1,13 @@
+pub mod bans;
+pub mod clients;
+pub mod device;
+pub mod events;
 pub mod keys;
+pub mod mfa;
 pub mod middleware;
 pub mod pod;
 pub mod sia;
+pub mod throttle;
 pub mod tokens;
+pub mod upstream;
+pub mod webauthn;
@@ -4,6 +4,7 @@ use axum::http::request::Parts;
 use diesel::prelude::*;
 use diesel_async::RunQueryDsl;
 
+use crate::db::pool::DbPool;
 use crate::db::schema::pods;
 use crate::error::ApiError;
 use crate::models::pod::Pod;
@@ -38,7 +39,14 @@ impl FromRequestParts<AppState> for PodClient {
             .strip_prefix("Bearer ")
             .ok_or_else(|| ApiError::unauthorized("Invalid Authorization header format"))?;
 
-        let mut conn = state.db.get().await?;
+        PodClient::authenticate(&state.db, secret).await
+    }
+}
+
+impl PodClient {
+    /// Look up the pod a client secret belongs to.
+    pub async fn authenticate(db: &DbPool, secret: &str) -> Result<Self, ApiError> {
+        let mut conn = db.get().await?;
 
         let pod: Pod = pods::table
             .filter(pods::client_secret.eq(secret))
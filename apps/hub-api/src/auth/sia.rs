@@ -4,6 +4,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::auth::keys::SigningKeys;
 use crate::error::ApiError;
+use crate::models::user::{USER_FLAG_BOT, USER_FLAG_STAFF, USER_FLAG_VERIFIED};
 
 /// SIA (Signed Identity Assertion) lifetime in seconds (5 minutes).
 pub const SIA_TTL_SECS: i64 = 300;
@@ -37,6 +38,7 @@ pub struct SiaClaims {
 }
 
 /// Mint a signed SIA JWT for a user targeting a specific Pod.
+#[allow(clippy::too_many_arguments)]
 pub fn mint_sia(
     keys: &SigningKeys,
     issuer: &str,
@@ -54,8 +56,7 @@ pub fn mint_sia(
 
     let jti = voxora_common::id::prefixed_ulid(voxora_common::id::prefix::SIA);
 
-    // Convert integer flags to string list. For Phase 1, flags are unused
-    // so this will be empty, but the structure supports future flag names.
+    // Pods receive flags by name so the bit layout stays a Hub detail.
     let flag_names = flags_to_names(flags);
 
     let claims = SiaClaims {
@@ -87,14 +88,16 @@ pub fn mint_sia(
 }
 
 /// Convert the integer flags bitfield to a list of flag names.
-/// For Phase 1 no flags are defined; this returns an empty vec for flags == 0.
 fn flags_to_names(flags: i64) -> Vec<String> {
     let mut names = Vec::new();
-    if flags & 1 != 0 {
+    if flags & USER_FLAG_STAFF != 0 {
         names.push("staff".to_string());
     }
-    if flags & 2 != 0 {
+    if flags & USER_FLAG_VERIFIED != 0 {
         names.push("verified".to_string());
     }
+    if flags & USER_FLAG_BOT != 0 {
+        names.push("bot".to_string());
+    }
     names
 }
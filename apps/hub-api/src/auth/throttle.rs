@@ -0,0 +1,329 @@
+//! Brute-force protection for password sign-in and registration.
+//!
+//! Failed sign-ins are counted in sliding windows kept in the KV store, keyed
+//! by the client address, the account and both together. Repeated failures
+//! from one address slow it down progressively; too many against one account
+//! lock its password sign-in for a while and email the owner a link to
+//! unlock it. Passkeys and upstream providers still work while locked, unless
+//! the account needs a second factor.
+//!
+//! Wrong second-factor codes count against the account the same way, and
+//! failures are only forgotten once every factor has passed, so a known
+//! password doesn't buy unlimited guesses at the second factor.
+//!
+//! Logins that don't match an account are counted under the login itself, so
+//! they're refused exactly like real ones and the answer never reveals
+//! whether an account exists.
+
+use std::net::IpAddr;
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::auth::events::{self, AuthEvent};
+use crate::auth::middleware::ClientInfo;
+use crate::auth::tokens::generate_opaque_token;
+use crate::db::kv::KeyValueStore;
+use crate::error::ApiError;
+use crate::mail::EmailMessage;
+use crate::models::user::User;
+use crate::AppState;
+
+/// How far back failed sign-ins are counted.
+pub const LOGIN_WINDOW_SECS: i64 = 15 * 60;
+
+/// Failed sign-ins from one address, across all accounts, before it's
+/// refused for the rest of the window.
+pub const MAX_FAILURES_PER_IP: usize = 50;
+
+/// Failed sign-ins to one account, from anywhere, before it's locked.
+pub const MAX_FAILURES_PER_ACCOUNT: usize = 10;
+
+/// Failed sign-ins to one account from one address before each further
+/// attempt has to wait.
+pub const FREE_FAILURES_PER_ACCOUNT_IP: usize = 3;
+
+/// Longest wait between attempts from one address.
+pub const MAX_LOGIN_DELAY_SECS: i64 = 5 * 60;
+
+/// How long an account stays locked.
+pub const LOCKOUT_SECS: i64 = 15 * 60;
+
+/// How long the unlock link in the lockout email works.
+pub const UNLOCK_TOKEN_TTL_SECS: u64 = 60 * 60;
+
+/// How far back registrations are counted.
+pub const REGISTRATION_WINDOW_SECS: i64 = 60 * 60;
+
+/// Registration attempts from one address per window.
+pub const MAX_REGISTRATIONS_PER_IP: usize = 5;
+
+/// Attempts to register one username per window.
+pub const MAX_REGISTRATIONS_PER_USERNAME: usize = 5;
+
+/// Shown for every refused sign-in, whatever the reason.
+pub const LOGIN_THROTTLED: &str =
+    "Too many sign-in attempts. Please wait a few minutes and try again.";
+
+/// What the failure counters are keyed by: the account if the login matched
+/// one, otherwise the login itself.
+pub fn subject(user: Option<&User>, login: &str) -> String {
+    match user {
+        Some(user) => user.id.clone(),
+        None => format!("login:{}", login.trim().to_lowercase()),
+    }
+}
+
+fn ip_key(ip: Option<IpAddr>) -> String {
+    ip.map(|ip| ip.to_string())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+fn lock_key(subject: &str) -> String {
+    format!("hub:login_lock:{subject}")
+}
+
+fn unlock_key(token: &str) -> String {
+    format!("hub:login_unlock:{token}")
+}
+
+/// What an unlock token in the KV store points at.
+#[derive(Debug, Serialize, Deserialize)]
+struct UnlockData {
+    user_id: String,
+}
+
+// ---------------------------------------------------------------------------
+// Sliding windows
+// ---------------------------------------------------------------------------
+
+/// Timestamps of the attempts under `key` within the last `window_secs`,
+/// oldest first.
+async fn window(kv: &dyn KeyValueStore, key: &str, window_secs: i64) -> Result<Vec<i64>, ApiError> {
+    kv.recent_hits(key, Utc::now().timestamp() - window_secs)
+        .await
+}
+
+/// Add an attempt now under `key`. Returns the attempts in the window,
+/// including this one. Concurrent attempts are all counted.
+async fn hit(kv: &dyn KeyValueStore, key: &str, window_secs: i64) -> Result<Vec<i64>, ApiError> {
+    kv.record_hit(key, Utc::now().timestamp(), window_secs)
+        .await
+}
+
+/// Seconds the next attempt has to wait after `failures`, oldest first. The
+/// wait doubles with every failure past the free ones.
+fn delay_remaining(failures: &[i64], now: i64) -> Option<i64> {
+    let extra = failures.len().checked_sub(FREE_FAILURES_PER_ACCOUNT_IP)?;
+    let last = *failures.last()?;
+    let delay = 1i64
+        .checked_shl(extra as u32)
+        .unwrap_or(MAX_LOGIN_DELAY_SECS)
+        .min(MAX_LOGIN_DELAY_SECS);
+    let remaining = last + delay - now;
+    (remaining > 0).then_some(remaining)
+}
+
+// ---------------------------------------------------------------------------
+// Sign-in
+// ---------------------------------------------------------------------------
+
+/// Whether a password sign-in to `subject` from `ip` may go ahead. Returns
+/// how many seconds to wait if not.
+pub async fn check_login(
+    kv: &dyn KeyValueStore,
+    subject: &str,
+    ip: Option<IpAddr>,
+) -> Result<Option<i64>, ApiError> {
+    let now = Utc::now().timestamp();
+    let ip = ip_key(ip);
+
+    let from_ip = window(kv, &format!("hub:login_fail:ip:{ip}"), LOGIN_WINDOW_SECS).await?;
+    if from_ip.len() >= MAX_FAILURES_PER_IP {
+        return Ok(Some(from_ip[0] + LOGIN_WINDOW_SECS - now));
+    }
+
+    if let Some(until) = kv.get(&lock_key(subject)).await? {
+        let until: i64 = until.parse().unwrap_or(0);
+        if until > now {
+            return Ok(Some(until - now));
+        }
+    }
+
+    let failures = window(
+        kv,
+        &format!("hub:login_fail:acct_ip:{subject}:{ip}"),
+        LOGIN_WINDOW_SECS,
+    )
+    .await?;
+    Ok(delay_remaining(&failures, now))
+}
+
+/// Count a failed password sign-in to `subject`, which is `user` if the login
+/// matched an account. Locks the account once it has had too many, and
+/// emails its owner an unlock link.
+pub async fn login_failed(
+    state: &AppState,
+    client: &ClientInfo,
+    subject: &str,
+    user: Option<&User>,
+) -> Result<(), ApiError> {
+    let kv = state.kv.as_ref();
+    let ip = ip_key(client.ip_address);
+
+    hit(kv, &format!("hub:login_fail:ip:{ip}"), LOGIN_WINDOW_SECS).await?;
+    hit(
+        kv,
+        &format!("hub:login_fail:acct_ip:{subject}:{ip}"),
+        LOGIN_WINDOW_SECS,
+    )
+    .await?;
+    let failures = hit(
+        kv,
+        &format!("hub:login_fail:acct:{subject}"),
+        LOGIN_WINDOW_SECS,
+    )
+    .await?;
+    if failures.len() < MAX_FAILURES_PER_ACCOUNT {
+        return Ok(());
+    }
+
+    // Start over once the lock is up.
+    let until = Utc::now().timestamp() + LOCKOUT_SECS;
+    kv.set_ex(&lock_key(subject), &until.to_string(), LOCKOUT_SECS as u64)
+        .await?;
+    kv.del(&format!("hub:login_fail:acct:{subject}")).await?;
+
+    let Some(user) = user else {
+        return Ok(());
+    };
+    tracing::warn!(user_id = %user.id, "account locked after repeated failed sign-ins");
+    events::record(
+        &state.db,
+        client,
+        AuthEvent::new(events::ACCOUNT_LOCKED)
+            .user(&user.id)
+            .details(serde_json::json!({ "failures": failures.len() })),
+    )
+    .await;
+
+    // The lock holds whether or not the owner hears about it.
+    if let Err(e) = email_unlock_link(state, user).await {
+        tracing::warn!(user_id = %user.id, error = %e.message, "failed to send lockout email");
+    }
+    Ok(())
+}
+
+/// Forget failures against `subject` from `ip` after a successful sign-in.
+pub async fn login_succeeded(
+    kv: &dyn KeyValueStore,
+    subject: &str,
+    ip: Option<IpAddr>,
+) -> Result<(), ApiError> {
+    let ip = ip_key(ip);
+    kv.del(&format!("hub:login_fail:acct_ip:{subject}:{ip}"))
+        .await?;
+    kv.del(&format!("hub:login_fail:acct:{subject}")).await
+}
+
+/// Whether the user's password sign-in is locked.
+pub async fn is_locked(kv: &dyn KeyValueStore, user_id: &str) -> Result<bool, ApiError> {
+    let now = Utc::now().timestamp();
+    Ok(kv
+        .get(&lock_key(user_id))
+        .await?
+        .and_then(|until| until.parse::<i64>().ok())
+        .is_some_and(|until| until > now))
+}
+
+/// Lift a lock and forget the account's recent failures. Returns whether it
+/// was locked.
+pub async fn unlock(kv: &dyn KeyValueStore, user_id: &str) -> Result<bool, ApiError> {
+    let locked = is_locked(kv, user_id).await?;
+    kv.del(&lock_key(user_id)).await?;
+    kv.del(&format!("hub:login_fail:acct:{user_id}")).await?;
+    Ok(locked)
+}
+
+async fn email_unlock_link(state: &AppState, user: &User) -> Result<(), ApiError> {
+    let Some(ref email) = user.email else {
+        return Ok(());
+    };
+
+    let token = generate_opaque_token("hul", 32);
+    let data = UnlockData {
+        user_id: user.id.clone(),
+    };
+    let value = serde_json::to_string(&data).map_err(|_| ApiError::internal("serialization"))?;
+    state
+        .kv
+        .set_ex(&unlock_key(&token), &value, UNLOCK_TOKEN_TTL_SECS)
+        .await?;
+
+    let link = format!(
+        "{}/unlock-account?token={token}",
+        state.config.web_url.trim_end_matches('/')
+    );
+    let message = EmailMessage {
+        to: email.clone(),
+        subject: "Your Voxora account was locked".to_string(),
+        text: format!(
+            "Hi {},\n\n\
+             There were too many failed attempts to sign in to your Voxora \
+             account, so signing in with a password is paused for {} minutes. \
+             If this was you, you can unlock it now:\n\n\
+             {link}\n\n\
+             If it wasn't you, someone may be guessing your password. Consider \
+             resetting it from the sign-in page.\n",
+            user.display_name,
+            LOCKOUT_SECS / 60
+        ),
+    };
+    state.mailer.send(&message).await?;
+
+    tracing::info!(user_id = %user.id, "lockout email sent");
+    Ok(())
+}
+
+/// Redeem an unlock token from a lockout email. Returns the user it
+/// unlocked, or `None` if the token is unknown or expired.
+pub async fn consume_unlock_token(
+    kv: &dyn KeyValueStore,
+    token: &str,
+) -> Result<Option<String>, ApiError> {
+    let Some(value) = kv.get_del(&unlock_key(token)).await? else {
+        return Ok(None);
+    };
+    let data: UnlockData =
+        serde_json::from_str(&value).map_err(|_| ApiError::internal("corrupt unlock data"))?;
+    unlock(kv, &data.user_id).await?;
+    Ok(Some(data.user_id))
+}
+
+// ---------------------------------------------------------------------------
+// Registration
+// ---------------------------------------------------------------------------
+
+/// Count a registration attempt for `username` from `ip`, failing with 429
+/// if either has made too many recently.
+pub async fn check_registration(
+    kv: &dyn KeyValueStore,
+    username: &str,
+    ip: Option<IpAddr>,
+) -> Result<(), ApiError> {
+    let ip_key = format!("hub:register:ip:{}", ip_key(ip));
+    let name_key = format!("hub:register:name:{}", username.trim().to_lowercase());
+
+    let from_ip = window(kv, &ip_key, REGISTRATION_WINDOW_SECS).await?;
+    let for_name = window(kv, &name_key, REGISTRATION_WINDOW_SECS).await?;
+    if from_ip.len() >= MAX_REGISTRATIONS_PER_IP || for_name.len() >= MAX_REGISTRATIONS_PER_USERNAME
+    {
+        return Err(ApiError::too_many_requests(
+            "Too many sign-up attempts. Please try again later.",
+        ));
+    }
+
+    hit(kv, &ip_key, REGISTRATION_WINDOW_SECS).await?;
+    hit(kv, &name_key, REGISTRATION_WINDOW_SECS).await?;
+    Ok(())
+}
@@ -55,6 +55,9 @@ pub struct IdTokenClaims {
     /// Nonce echoed back from the authorization request.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub nonce: Option<String>,
+    /// Authentication methods used at sign-in (RFC 8176), e.g. `["pwd", "otp", "mfa"]`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub amr: Vec<String>,
 
     // Profile claims (included when `profile` scope is requested).
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -72,6 +75,7 @@ pub struct IdTokenClaims {
 }
 
 /// Mint a signed ID token JWT.
+#[allow(clippy::too_many_arguments)]
 pub fn mint_id_token(
     keys: &SigningKeys,
     issuer: &str,
@@ -79,6 +83,7 @@ pub fn mint_id_token(
     user_id: &str,
     nonce: Option<&str>,
     scopes: &[String],
+    amr: &[String],
     // User profile fields.
     username: &str,
     display_name: &str,
@@ -95,6 +100,7 @@ pub fn mint_id_token(
         exp: (now + Duration::seconds(ACCESS_TOKEN_TTL_SECS)).timestamp(),
         iat: now.timestamp(),
         nonce: nonce.map(|n| n.to_string()),
+        amr: amr.to_vec(),
         preferred_username: None,
         name: None,
         picture: None,
@@ -131,6 +137,33 @@ pub fn mint_id_token(
 pub struct AccessTokenData {
     pub user_id: String,
     pub scopes: Vec<String>,
+    /// Session (refresh token row) the token was issued with, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    /// OAuth client the token was issued to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    /// Unix time the token expires.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<i64>,
+}
+
+impl AccessTokenData {
+    /// Data for a token issued now, expiring after [`ACCESS_TOKEN_TTL_SECS`].
+    pub fn new(
+        user_id: String,
+        scopes: Vec<String>,
+        session_id: Option<String>,
+        client_id: Option<String>,
+    ) -> Self {
+        Self {
+            user_id,
+            scopes,
+            session_id,
+            client_id,
+            expires_at: Some((Utc::now() + Duration::seconds(ACCESS_TOKEN_TTL_SECS)).timestamp()),
+        }
+    }
 }
 
 /// Stored alongside an authorization code.
@@ -142,12 +175,18 @@ pub struct AuthCodeData {
     pub code_challenge: String,
     pub scopes: Vec<String>,
     pub nonce: Option<String>,
+    /// Authentication methods the user completed, for the ID token `amr` claim.
+    #[serde(default)]
+    pub amr: Vec<String>,
 }
 
 /// Authorization code TTL in seconds.
 pub const AUTH_CODE_TTL_SECS: u64 = 60;
 
 /// Store an access token.
+///
+/// Tokens tied to a session are also indexed by session ID so revoking the
+/// session can delete them.
 pub async fn store_access_token(
     kv: &dyn KeyValueStore,
     token: &str,
@@ -156,7 +195,16 @@ pub async fn store_access_token(
     let key = format!("hub:at:{}", token);
     let value = serde_json::to_string(data).map_err(|_| ApiError::internal("serialization"))?;
     kv.set_ex(&key, &value, ACCESS_TOKEN_TTL_SECS as u64)
-        .await
+        .await?;
+    if let Some(ref session_id) = data.session_id {
+        kv.set_ex(
+            &format!("hub:session_at:{session_id}"),
+            token,
+            ACCESS_TOKEN_TTL_SECS as u64,
+        )
+        .await?;
+    }
+    Ok(())
 }
 
 /// Look up an access token.
@@ -184,6 +232,19 @@ pub async fn delete_access_token(
     kv.del(&key).await
 }
 
+/// Delete the access token issued with a session, if it hasn't expired yet.
+pub async fn delete_session_access_token(
+    kv: &dyn KeyValueStore,
+    session_id: &str,
+) -> Result<(), ApiError> {
+    let key = format!("hub:session_at:{session_id}");
+    if let Some(token) = kv.get(&key).await? {
+        delete_access_token(kv, &token).await?;
+        kv.del(&key).await?;
+    }
+    Ok(())
+}
+
 /// Store an authorization code with 60s TTL.
 pub async fn store_auth_code(
     kv: &dyn KeyValueStore,
@@ -0,0 +1,212 @@
+//! Sign-in through upstream OIDC/OAuth2 identity providers ("OAuth
+//! connections", RFC §10.1.4): the Hub acts as a client of the provider,
+//! exchanges the code with PKCE, and reads the account from userinfo.
+
+use std::time::Duration;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::auth::tokens::generate_opaque_token;
+use crate::config::{Config, UpstreamIdp};
+use crate::db::kv::KeyValueStore;
+use crate::error::ApiError;
+
+/// How long the user has to finish signing in at the provider.
+pub const UPSTREAM_STATE_TTL_SECS: u64 = 600;
+
+/// Timeout for each request to the provider.
+const UPSTREAM_HTTP_TIMEOUT_SECS: u64 = 10;
+
+/// An authorization request to resume once the provider sends the user back.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoginRequest {
+    pub client_id: String,
+    pub redirect_uri: String,
+    pub scope: String,
+    pub state: Option<String>,
+    pub code_challenge: String,
+    pub nonce: Option<String>,
+}
+
+/// What the round trip to the provider is for.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "purpose", rename_all = "snake_case")]
+pub enum UpstreamPurpose {
+    /// Sign in (or register) and continue an authorization request.
+    Login(LoginRequest),
+    /// Link the provider account to a signed-in user.
+    Link { user_id: String },
+}
+
+/// A round trip to the provider in progress, keyed by its `state` parameter.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpstreamStateData {
+    pub provider: String,
+    /// PKCE verifier for the provider's token endpoint.
+    pub code_verifier: String,
+    #[serde(flatten)]
+    pub purpose: UpstreamPurpose,
+}
+
+/// The account the provider vouched for, mapped through the configured claims.
+#[derive(Debug)]
+pub struct UpstreamProfile {
+    pub subject: String,
+    pub username: Option<String>,
+    pub email: Option<String>,
+    pub email_verified: bool,
+    pub name: Option<String>,
+}
+
+impl UpstreamProfile {
+    /// Read a profile out of a userinfo response. Returns `None` without a
+    /// subject. Numeric subjects (e.g. GitHub's `id`) are stringified.
+    pub fn from_claims(idp: &UpstreamIdp, claims: &serde_json::Value) -> Option<Self> {
+        let text = |name: &str| match &claims[name] {
+            serde_json::Value::String(s) if !s.trim().is_empty() => Some(s.trim().to_string()),
+            serde_json::Value::Number(n) => Some(n.to_string()),
+            _ => None,
+        };
+        Some(Self {
+            subject: text(&idp.subject_claim)?,
+            username: text(&idp.username_claim),
+            email: text(&idp.email_claim).map(|e| e.to_lowercase()),
+            email_verified: claims["email_verified"].as_bool().unwrap_or(false),
+            name: text(&idp.name_claim),
+        })
+    }
+}
+
+/// The redirect URI registered with the provider.
+pub fn callback_uri(config: &Config, idp: &UpstreamIdp) -> String {
+    format!(
+        "{}/oidc/upstream/{}/callback",
+        config.hub_domain.trim_end_matches('/'),
+        idp.id
+    )
+}
+
+/// Generate a PKCE code verifier (43 URL-safe characters).
+pub fn generate_code_verifier() -> String {
+    let mut buf = [0u8; 32];
+    rand::thread_rng().fill(&mut buf[..]);
+    URL_SAFE_NO_PAD.encode(buf)
+}
+
+/// Store a new round trip and return the provider URL to send the user to.
+pub async fn begin(
+    config: &Config,
+    kv: &dyn KeyValueStore,
+    idp: &UpstreamIdp,
+    purpose: UpstreamPurpose,
+) -> Result<String, ApiError> {
+    let state = generate_opaque_token("hus", 24);
+    let data = UpstreamStateData {
+        provider: idp.id.clone(),
+        code_verifier: generate_code_verifier(),
+        purpose,
+    };
+    let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(data.code_verifier.as_bytes()));
+
+    let value = serde_json::to_string(&data).map_err(|_| ApiError::internal("serialization"))?;
+    kv.set_ex(
+        &format!("hub:upstream:{}", state),
+        &value,
+        UPSTREAM_STATE_TTL_SECS,
+    )
+    .await?;
+
+    let url = reqwest::Url::parse_with_params(
+        &idp.authorization_endpoint,
+        &[
+            ("response_type", "code"),
+            ("client_id", idp.client_id.as_str()),
+            ("redirect_uri", callback_uri(config, idp).as_str()),
+            ("scope", idp.scopes.as_str()),
+            ("state", state.as_str()),
+            ("code_challenge", challenge.as_str()),
+            ("code_challenge_method", "S256"),
+        ],
+    )
+    .map_err(|_| ApiError::internal("invalid upstream authorization endpoint"))?;
+    Ok(url.into())
+}
+
+/// Look up and delete a round trip (single-use).
+pub async fn consume_state(
+    kv: &dyn KeyValueStore,
+    state: &str,
+) -> Result<Option<UpstreamStateData>, ApiError> {
+    let key = format!("hub:upstream:{}", state);
+    match kv.get(&key).await? {
+        Some(v) => {
+            kv.del(&key).await?;
+            let data: UpstreamStateData = serde_json::from_str(&v)
+                .map_err(|_| ApiError::internal("corrupt upstream state"))?;
+            Ok(Some(data))
+        }
+        None => Ok(None),
+    }
+}
+
+#[derive(Deserialize)]
+struct UpstreamTokenResponse {
+    access_token: String,
+}
+
+/// Redeem the provider's code and fetch the account it belongs to.
+pub async fn fetch_profile(
+    config: &Config,
+    idp: &UpstreamIdp,
+    code: &str,
+    code_verifier: &str,
+) -> Result<UpstreamProfile, ApiError> {
+    let failed = |stage: &str, e: &dyn std::fmt::Display| {
+        tracing::warn!(provider = %idp.id, error = %e, "upstream {stage} failed");
+        ApiError::unauthorized("upstream sign-in failed")
+    };
+
+    let http = reqwest::Client::builder()
+        .timeout(Duration::from_secs(UPSTREAM_HTTP_TIMEOUT_SECS))
+        .user_agent("Voxora-Hub")
+        .build()
+        .map_err(|e| failed("client setup", &e))?;
+
+    let redirect_uri = callback_uri(config, idp);
+    let token: UpstreamTokenResponse = http
+        .post(&idp.token_endpoint)
+        .header(reqwest::header::ACCEPT, "application/json")
+        .form(&[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", redirect_uri.as_str()),
+            ("client_id", idp.client_id.as_str()),
+            ("client_secret", idp.client_secret.as_str()),
+            ("code_verifier", code_verifier),
+        ])
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| failed("token exchange", &e))?
+        .json()
+        .await
+        .map_err(|e| failed("token exchange", &e))?;
+
+    let claims: serde_json::Value = http
+        .get(&idp.userinfo_endpoint)
+        .header(reqwest::header::ACCEPT, "application/json")
+        .bearer_auth(&token.access_token)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| failed("userinfo", &e))?
+        .json()
+        .await
+        .map_err(|e| failed("userinfo", &e))?;
+
+    UpstreamProfile::from_claims(idp, &claims)
+        .ok_or_else(|| failed("userinfo", &"no subject claim"))
+}
@@ -0,0 +1,667 @@
+//! WebAuthn (passkey) relying-party ceremonies.
+//!
+//! Covers registration and authentication (WebAuthn Level 2 §7) for the
+//! credential types passkeys use in practice: ES256 (P-256) and EdDSA
+//! (Ed25519). Options ask for `attestation: "none"` and attestation statements
+//! are not checked — a passkey is trusted because a signed-in user registered
+//! it, not because of who built the authenticator.
+//!
+//! Every ceremony requires a discoverable credential and user verification,
+//! so a passkey can stand in for both the password and the second factor.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Utc;
+use ciborium::Value;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use utoipa::ToSchema;
+
+use crate::config::Config;
+use crate::db::kv::KeyValueStore;
+use crate::db::pool::DbPool;
+use crate::db::schema::passkeys;
+use crate::error::ApiError;
+use crate::models::passkey::Passkey;
+use crate::models::user::User;
+
+/// How long the client has to finish a ceremony.
+pub const CEREMONY_TTL_SECS: u64 = 300;
+
+/// Relying party name shown by the browser.
+const RP_NAME: &str = "Voxora";
+
+/// Challenge length in bytes (WebAuthn §13.4.3 asks for at least 16).
+const CHALLENGE_BYTES: usize = 32;
+
+/// Longest credential ID accepted (WebAuthn §5.8.3).
+const MAX_CREDENTIAL_ID_LEN: usize = 1023;
+
+/// COSE algorithm identifiers (RFC 9053).
+pub const COSE_ALG_ES256: i64 = -7;
+pub const COSE_ALG_EDDSA: i64 = -8;
+
+// Authenticator data flags (WebAuthn §6.1).
+const FLAG_USER_PRESENT: u8 = 0x01;
+const FLAG_USER_VERIFIED: u8 = 0x04;
+const FLAG_ATTESTED_CREDENTIAL: u8 = 0x40;
+
+// ---------------------------------------------------------------------------
+// Options sent to the browser
+// ---------------------------------------------------------------------------
+
+/// `PublicKeyCredentialCreationOptions`, JSON-encoded (binary fields are
+/// base64url).
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreationOptions {
+    pub challenge: String,
+    pub rp: RelyingPartyEntity,
+    pub user: UserEntity,
+    pub pub_key_cred_params: Vec<CredentialParameters>,
+    /// Milliseconds.
+    pub timeout: u64,
+    /// The user's existing passkeys, so an authenticator isn't registered twice.
+    pub exclude_credentials: Vec<CredentialDescriptor>,
+    pub authenticator_selection: AuthenticatorSelection,
+    pub attestation: String,
+}
+
+/// `PublicKeyCredentialRequestOptions`, JSON-encoded.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestOptions {
+    pub challenge: String,
+    /// Milliseconds.
+    pub timeout: u64,
+    pub rp_id: String,
+    /// Empty for passwordless sign-in: the authenticator offers whichever
+    /// discoverable credentials it holds for this relying party.
+    pub allow_credentials: Vec<CredentialDescriptor>,
+    pub user_verification: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RelyingPartyEntity {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UserEntity {
+    /// Base64url user handle (the Voxora user ID).
+    pub id: String,
+    pub name: String,
+    pub display_name: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CredentialParameters {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub alg: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CredentialDescriptor {
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// Base64url credential ID.
+    pub id: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub transports: Vec<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthenticatorSelection {
+    pub resident_key: String,
+    pub require_resident_key: bool,
+    pub user_verification: String,
+}
+
+impl From<&Passkey> for CredentialDescriptor {
+    fn from(p: &Passkey) -> Self {
+        Self {
+            kind: "public-key".to_string(),
+            id: URL_SAFE_NO_PAD.encode(&p.credential_id),
+            transports: p.transports.clone().unwrap_or_default(),
+        }
+    }
+}
+
+/// Build registration options for `user`, excluding their `existing` passkeys.
+pub fn creation_options(
+    config: &Config,
+    challenge: String,
+    user: &User,
+    existing: &[Passkey],
+) -> CreationOptions {
+    CreationOptions {
+        challenge,
+        rp: RelyingPartyEntity {
+            id: config.webauthn_rp_id.clone(),
+            name: RP_NAME.to_string(),
+        },
+        user: UserEntity {
+            id: URL_SAFE_NO_PAD.encode(user.id.as_bytes()),
+            name: user.username.clone(),
+            display_name: user.display_name.clone(),
+        },
+        pub_key_cred_params: [COSE_ALG_ES256, COSE_ALG_EDDSA]
+            .into_iter()
+            .map(|alg| CredentialParameters {
+                kind: "public-key".to_string(),
+                alg,
+            })
+            .collect(),
+        timeout: CEREMONY_TTL_SECS * 1000,
+        exclude_credentials: existing.iter().map(CredentialDescriptor::from).collect(),
+        authenticator_selection: AuthenticatorSelection {
+            resident_key: "required".to_string(),
+            require_resident_key: true,
+            user_verification: "required".to_string(),
+        },
+        attestation: "none".to_string(),
+    }
+}
+
+/// Build options for a passwordless sign-in.
+pub fn request_options(config: &Config, challenge: String) -> RequestOptions {
+    RequestOptions {
+        challenge,
+        timeout: CEREMONY_TTL_SECS * 1000,
+        rp_id: config.webauthn_rp_id.clone(),
+        allow_credentials: Vec::new(),
+        user_verification: "required".to_string(),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Responses from the browser
+// ---------------------------------------------------------------------------
+
+/// A `PublicKeyCredential` from `navigator.credentials.create()`, with binary
+/// fields base64url-encoded (the shape of `PublicKeyCredential.toJSON()`).
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistrationCredential {
+    pub id: String,
+    pub raw_id: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub response: AttestationResponse,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AttestationResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    pub attestation_object: String,
+    #[serde(default)]
+    pub transports: Vec<String>,
+}
+
+/// A `PublicKeyCredential` from `navigator.credentials.get()`.
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthenticationCredential {
+    pub id: String,
+    pub raw_id: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub response: AssertionResponse,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AssertionResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    pub authenticator_data: String,
+    pub signature: String,
+    #[serde(default)]
+    pub user_handle: Option<String>,
+}
+
+/// `CollectedClientData` (WebAuthn §5.8.1).
+#[derive(Debug, Deserialize)]
+struct ClientData {
+    #[serde(rename = "type")]
+    kind: String,
+    challenge: String,
+    origin: String,
+    #[serde(default, rename = "crossOrigin")]
+    cross_origin: bool,
+}
+
+// ---------------------------------------------------------------------------
+// Challenges
+// ---------------------------------------------------------------------------
+
+/// Which ceremony a challenge was issued for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Ceremony {
+    Registration,
+    Authentication,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PendingCeremony {
+    ceremony: Ceremony,
+    /// The signed-in user, for registration. Passwordless sign-in doesn't
+    /// know who the user is until the assertion arrives.
+    user_id: Option<String>,
+}
+
+/// Issue and store a fresh challenge. Challenges are single-use and keyed by
+/// their own value, which the browser echoes back in the client data.
+pub async fn begin_ceremony(
+    kv: &dyn KeyValueStore,
+    ceremony: Ceremony,
+    user_id: Option<&str>,
+) -> Result<String, ApiError> {
+    let mut buf = [0u8; CHALLENGE_BYTES];
+    rand::thread_rng().fill(&mut buf[..]);
+    let challenge = URL_SAFE_NO_PAD.encode(buf);
+
+    let pending = PendingCeremony {
+        ceremony,
+        user_id: user_id.map(str::to_string),
+    };
+    let value = serde_json::to_string(&pending).map_err(|_| ApiError::internal("serialization"))?;
+    kv.set_ex(
+        &format!("hub:webauthn:{challenge}"),
+        &value,
+        CEREMONY_TTL_SECS,
+    )
+    .await?;
+    Ok(challenge)
+}
+
+/// Consume a challenge. Returns `None` if it's unknown, expired, or was
+/// issued for a different ceremony.
+async fn take_ceremony(
+    kv: &dyn KeyValueStore,
+    challenge: &str,
+    ceremony: Ceremony,
+) -> Result<Option<PendingCeremony>, ApiError> {
+    let key = format!("hub:webauthn:{challenge}");
+    let Some(value) = kv.get_del(&key).await? else {
+        return Ok(None);
+    };
+    let pending: PendingCeremony =
+        serde_json::from_str(&value).map_err(|_| ApiError::internal("corrupt challenge data"))?;
+    Ok((pending.ceremony == ceremony).then_some(pending))
+}
+
+// ---------------------------------------------------------------------------
+// Registration
+// ---------------------------------------------------------------------------
+
+/// A credential that passed registration checks, ready to be stored.
+#[derive(Debug)]
+pub struct VerifiedRegistration {
+    pub credential_id: Vec<u8>,
+    /// COSE-encoded public key.
+    pub public_key: Vec<u8>,
+    pub sign_count: u32,
+    pub transports: Vec<String>,
+}
+
+/// Verify a registration response for `user_id` (WebAuthn §7.1). Failures are
+/// `400 Bad Request` with the reason.
+pub async fn finish_registration(
+    config: &Config,
+    kv: &dyn KeyValueStore,
+    user_id: &str,
+    credential: &RegistrationCredential,
+) -> Result<VerifiedRegistration, ApiError> {
+    let client_data_raw =
+        decode_b64(&credential.response.client_data_json).map_err(ApiError::bad_request)?;
+    let client_data = check_client_data(config, &client_data_raw, "webauthn.create")
+        .map_err(ApiError::bad_request)?;
+
+    let pending = take_ceremony(kv, &client_data.challenge, Ceremony::Registration).await?;
+    if pending.and_then(|p| p.user_id).as_deref() != Some(user_id) {
+        return Err(ApiError::bad_request("Unknown or expired challenge"));
+    }
+
+    let verified = verify_attestation(config, credential).map_err(ApiError::bad_request)?;
+    Ok(verified)
+}
+
+fn verify_attestation(
+    config: &Config,
+    credential: &RegistrationCredential,
+) -> Result<VerifiedRegistration, &'static str> {
+    if credential.kind != "public-key" {
+        return Err("Unsupported credential type");
+    }
+    let attestation_object = decode_b64(&credential.response.attestation_object)?;
+    let auth_data_raw = parse_attestation_object(&attestation_object)?;
+    let auth_data = parse_authenticator_data(&auth_data_raw)?;
+    check_authenticator_data(config, &auth_data)?;
+
+    let attested = auth_data
+        .attested
+        .ok_or("Attestation carries no credential")?;
+    if decode_b64(&credential.raw_id)? != attested.credential_id {
+        return Err("Credential ID mismatch");
+    }
+    // Reject keys we couldn't verify a signature with later.
+    CredentialKey::from_cose(&attested.public_key)?;
+
+    Ok(VerifiedRegistration {
+        credential_id: attested.credential_id,
+        public_key: attested.public_key,
+        sign_count: auth_data.sign_count,
+        transports: credential.response.transports.clone(),
+    })
+}
+
+/// Pull `authData` out of an attestation object. Only the `none` format is
+/// requested; other formats are accepted but their statements ignored.
+fn parse_attestation_object(data: &[u8]) -> Result<Vec<u8>, &'static str> {
+    let value: Value = ciborium::from_reader(data).map_err(|_| "Malformed attestation object")?;
+    let map = value.as_map().ok_or("Malformed attestation object")?;
+    let field = |name: &str| {
+        map.iter()
+            .find(|(k, _)| k.as_text() == Some(name))
+            .map(|(_, v)| v)
+    };
+
+    let fmt = field("fmt")
+        .and_then(Value::as_text)
+        .ok_or("Malformed attestation object")?;
+    if fmt == "none" {
+        let statement = field("attStmt").and_then(Value::as_map);
+        if statement.is_none_or(|s| !s.is_empty()) {
+            return Err("Malformed attestation statement");
+        }
+    }
+
+    field("authData")
+        .and_then(Value::as_bytes)
+        .cloned()
+        .ok_or("Malformed attestation object")
+}
+
+// ---------------------------------------------------------------------------
+// Authentication
+// ---------------------------------------------------------------------------
+
+/// Verify a sign-in assertion (WebAuthn §7.2) and bump the passkey's
+/// signature counter. Returns the updated passkey; its `user_id` is the user
+/// who signed in. Failures are `401 Unauthorized`.
+pub async fn finish_authentication(
+    config: &Config,
+    db: &DbPool,
+    kv: &dyn KeyValueStore,
+    credential: &AuthenticationCredential,
+) -> Result<Passkey, ApiError> {
+    let reject = |reason: &'static str| {
+        tracing::debug!(reason, "passkey assertion rejected");
+        ApiError::unauthorized("Passkey verification failed")
+    };
+
+    let client_data_raw = decode_b64(&credential.response.client_data_json).map_err(reject)?;
+    let client_data =
+        check_client_data(config, &client_data_raw, "webauthn.get").map_err(reject)?;
+    if take_ceremony(kv, &client_data.challenge, Ceremony::Authentication)
+        .await?
+        .is_none()
+    {
+        return Err(reject("unknown or expired challenge"));
+    }
+
+    let credential_id = decode_b64(&credential.raw_id).map_err(reject)?;
+    let mut conn = db.get().await?;
+    let passkey: Passkey = passkeys::table
+        .filter(passkeys::credential_id.eq(&credential_id))
+        .select(Passkey::as_select())
+        .first(&mut conn)
+        .await
+        .optional()?
+        .ok_or_else(|| reject("unknown credential"))?;
+
+    if let Some(handle) = &credential.response.user_handle {
+        if decode_b64(handle).map_err(reject)? != passkey.user_id.as_bytes() {
+            return Err(reject("user handle mismatch"));
+        }
+    }
+
+    let auth_data_raw = decode_b64(&credential.response.authenticator_data).map_err(reject)?;
+    let signature = decode_b64(&credential.response.signature).map_err(reject)?;
+    let sign_count = verify_assertion(
+        config,
+        &passkey,
+        &client_data_raw,
+        &auth_data_raw,
+        &signature,
+    )
+    .map_err(reject)?;
+
+    // Compare-and-set on the old counter so two concurrent uses of the same
+    // assertion can't both succeed.
+    let updated: Option<Passkey> = diesel::update(
+        passkeys::table
+            .find(&passkey.id)
+            .filter(passkeys::sign_count.eq(passkey.sign_count)),
+    )
+    .set((
+        passkeys::sign_count.eq(i64::from(sign_count)),
+        passkeys::last_used_at.eq(Utc::now()),
+    ))
+    .returning(Passkey::as_returning())
+    .get_result(&mut conn)
+    .await
+    .optional()?;
+
+    updated.ok_or_else(|| reject("concurrent use of passkey"))
+}
+
+/// Check an assertion signature against a stored passkey and return the new
+/// signature counter.
+fn verify_assertion(
+    config: &Config,
+    passkey: &Passkey,
+    client_data_raw: &[u8],
+    auth_data_raw: &[u8],
+    signature: &[u8],
+) -> Result<u32, &'static str> {
+    let auth_data = parse_authenticator_data(auth_data_raw)?;
+    check_authenticator_data(config, &auth_data)?;
+
+    let mut signed = auth_data_raw.to_vec();
+    signed.extend_from_slice(&Sha256::digest(client_data_raw));
+    let key = CredentialKey::from_cose(&passkey.public_key)?;
+    if !key.verify(&signed, signature) {
+        return Err("bad signature");
+    }
+
+    // A counter that doesn't move forward means the credential may have been
+    // cloned (§6.1.1). Authenticators that don't count always send zero.
+    let stored = passkey.sign_count;
+    if (auth_data.sign_count != 0 || stored != 0) && i64::from(auth_data.sign_count) <= stored {
+        tracing::warn!(
+            passkey_id = %passkey.id,
+            stored,
+            received = auth_data.sign_count,
+            "passkey signature counter went backwards"
+        );
+        return Err("signature counter did not increase");
+    }
+
+    Ok(auth_data.sign_count)
+}
+
+// ---------------------------------------------------------------------------
+// Shared parsing
+// ---------------------------------------------------------------------------
+
+fn decode_b64(s: &str) -> Result<Vec<u8>, &'static str> {
+    URL_SAFE_NO_PAD
+        .decode(s.trim_end_matches('='))
+        .map_err(|_| "Invalid base64url encoding")
+}
+
+/// Check the client data type and origin. The challenge is checked by the
+/// caller, since it's also the key the ceremony was stored under.
+fn check_client_data(
+    config: &Config,
+    raw: &[u8],
+    expected_type: &str,
+) -> Result<ClientData, &'static str> {
+    let client_data: ClientData =
+        serde_json::from_slice(raw).map_err(|_| "Malformed client data")?;
+    if client_data.kind != expected_type {
+        return Err("Wrong client data type");
+    }
+    if client_data.cross_origin || !config.webauthn_origins.contains(&client_data.origin) {
+        return Err("Origin not allowed");
+    }
+    Ok(client_data)
+}
+
+/// Parsed authenticator data (WebAuthn §6.1).
+struct AuthenticatorData {
+    rp_id_hash: [u8; 32],
+    flags: u8,
+    sign_count: u32,
+    attested: Option<AttestedCredential>,
+}
+
+struct AttestedCredential {
+    credential_id: Vec<u8>,
+    public_key: Vec<u8>,
+}
+
+fn parse_authenticator_data(data: &[u8]) -> Result<AuthenticatorData, &'static str> {
+    const MALFORMED: &str = "Malformed authenticator data";
+    if data.len() < 37 {
+        return Err(MALFORMED);
+    }
+    let rp_id_hash: [u8; 32] = data[..32].try_into().map_err(|_| MALFORMED)?;
+    let flags = data[32];
+    let sign_count = u32::from_be_bytes(data[33..37].try_into().map_err(|_| MALFORMED)?);
+
+    let attested = if flags & FLAG_ATTESTED_CREDENTIAL != 0 {
+        // aaguid (16) || credentialIdLength (2) || credentialId || COSE key
+        let rest = data.get(37..).filter(|r| r.len() >= 18).ok_or(MALFORMED)?;
+        let id_len = u16::from_be_bytes([rest[16], rest[17]]) as usize;
+        if id_len > MAX_CREDENTIAL_ID_LEN {
+            return Err(MALFORMED);
+        }
+        let rest = &rest[18..];
+        let credential_id = rest.get(..id_len).ok_or(MALFORMED)?.to_vec();
+
+        // The key is a single CBOR item; decode it to find where it ends
+        // (extensions may follow).
+        let key_start = &rest[id_len..];
+        let mut reader = key_start;
+        let _: Value = ciborium::from_reader(&mut reader).map_err(|_| MALFORMED)?;
+        let key_len = key_start.len() - reader.len();
+
+        Some(AttestedCredential {
+            credential_id,
+            public_key: key_start[..key_len].to_vec(),
+        })
+    } else {
+        None
+    };
+
+    Ok(AuthenticatorData {
+        rp_id_hash,
+        flags,
+        sign_count,
+        attested,
+    })
+}
+
+/// Check the RP ID hash and that the user was present and verified.
+fn check_authenticator_data(
+    config: &Config,
+    auth_data: &AuthenticatorData,
+) -> Result<(), &'static str> {
+    if auth_data.rp_id_hash[..] != Sha256::digest(config.webauthn_rp_id.as_bytes())[..] {
+        return Err("Relying party ID mismatch");
+    }
+    if auth_data.flags & FLAG_USER_PRESENT == 0 {
+        return Err("User presence required");
+    }
+    if auth_data.flags & FLAG_USER_VERIFIED == 0 {
+        return Err("User verification required");
+    }
+    Ok(())
+}
+
+/// A credential public key decoded from its COSE form (RFC 9052 §7).
+enum CredentialKey {
+    Es256(p256::ecdsa::VerifyingKey),
+    EdDsa(ed25519_dalek::VerifyingKey),
+}
+
+impl CredentialKey {
+    fn from_cose(bytes: &[u8]) -> Result<Self, &'static str> {
+        const MALFORMED: &str = "Malformed credential public key";
+        let value: Value = ciborium::from_reader(bytes).map_err(|_| MALFORMED)?;
+        let map = value.as_map().ok_or(MALFORMED)?;
+        let label = |label: i64| {
+            map.iter()
+                .find(|(k, _)| k.as_integer().map(i128::from) == Some(i128::from(label)))
+                .map(|(_, v)| v)
+        };
+        let int = |l: i64| {
+            label(l)
+                .and_then(Value::as_integer)
+                .and_then(|i| i64::try_from(i).ok())
+        };
+        let bytes = |l: i64| label(l).and_then(Value::as_bytes);
+
+        // kty: 2 = EC2, 1 = OKP. crv: 1 = P-256, 6 = Ed25519.
+        match (int(1), int(3), int(-1)) {
+            (Some(2), Some(COSE_ALG_ES256), Some(1)) => {
+                let (x, y) = bytes(-2).zip(bytes(-3)).ok_or(MALFORMED)?;
+                if x.len() != 32 || y.len() != 32 {
+                    return Err(MALFORMED);
+                }
+                let point = p256::EncodedPoint::from_affine_coordinates(
+                    p256::FieldBytes::from_slice(x),
+                    p256::FieldBytes::from_slice(y),
+                    false,
+                );
+                p256::ecdsa::VerifyingKey::from_encoded_point(&point)
+                    .map(Self::Es256)
+                    .map_err(|_| MALFORMED)
+            }
+            (Some(1), Some(COSE_ALG_EDDSA), Some(6)) => {
+                let x: [u8; 32] = bytes(-2)
+                    .and_then(|x| x.as_slice().try_into().ok())
+                    .ok_or(MALFORMED)?;
+                ed25519_dalek::VerifyingKey::from_bytes(&x)
+                    .map(Self::EdDsa)
+                    .map_err(|_| MALFORMED)
+            }
+            _ => Err("Unsupported credential algorithm"),
+        }
+    }
+
+    fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+        match self {
+            // WebAuthn ES256 signatures are ASN.1 DER.
+            Self::Es256(key) => {
+                use p256::ecdsa::signature::Verifier;
+                p256::ecdsa::Signature::from_der(signature)
+                    .is_ok_and(|sig| key.verify(message, &sig).is_ok())
+            }
+            Self::EdDsa(key) => ed25519_dalek::Signature::from_slice(signature)
+                .is_ok_and(|sig| key.verify_strict(message, &sig).is_ok()),
+        }
+    }
+}
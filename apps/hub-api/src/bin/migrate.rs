@@ -57,7 +57,7 @@ fn with_test_db_suffix(database_url: &str) -> String {
         return database_url.to_string();
     }
 
-    let mut updated = format!("{}/{}", prefix, format!("{db_name}_test"));
+    let mut updated = format!("{prefix}/{db_name}_test");
     if let Some(query) = query {
         updated.push('?');
         updated.push_str(query);
@@ -1,3 +1,7 @@
+use std::net::IpAddr;
+
+use serde::Deserialize;
+
 /// Hub API configuration, loaded from environment variables.
 #[derive(Debug, Clone)]
 pub struct Config {
@@ -7,16 +11,97 @@ pub struct Config {
     pub redis_url: String,
     /// The public-facing origin of the Hub (e.g. `http://localhost:4001`).
     pub hub_domain: String,
-    /// Seed used to derive the Ed25519 signing key (dev only — use KMS in prod).
-    pub signing_key_seed: String,
+    /// Seed used to derive the Ed25519 signing key (dev only). With
+    /// `signing_key_dir` set, it only seeds a new keyring's first key.
+    pub signing_key_seed: Option<String>,
+    /// Directory holding the rotatable signing keyring.
+    pub signing_key_dir: Option<String>,
     /// Port the HTTP server binds to.
     pub port: u16,
+    /// Reverse proxies whose `X-Forwarded-For` is believed. Requests from any
+    /// other peer are attributed to the peer address.
+    pub trusted_proxies: Vec<IpAddr>,
+    /// How long shutdown waits for in-flight requests and connections to drain.
+    pub shutdown_timeout_secs: u64,
     /// Shared secret for coturn REST API credential generation.
     pub turn_shared_secret: String,
     /// STUN server URLs.
     pub stun_urls: Vec<String>,
     /// TURN server URLs.
     pub turn_urls: Vec<String>,
+    /// WebAuthn relying party ID — the domain passkeys are bound to.
+    pub webauthn_rp_id: String,
+    /// Origins allowed to run WebAuthn ceremonies (the Hub and the web client).
+    pub webauthn_origins: Vec<String>,
+    /// Public origin of the web client, used for links in emails.
+    pub web_url: String,
+    /// Redirect URIs registered for the first-party web client.
+    pub web_redirect_uris: Vec<String>,
+    /// How email is delivered: `smtp`, `file`, or `log`.
+    pub mail_transport: String,
+    /// SMTP connection URL (required when `mail_transport` is `smtp`).
+    pub smtp_url: Option<String>,
+    /// `From` address for outgoing email.
+    pub mail_from: String,
+    /// Directory the `file` transport writes messages to.
+    pub mail_outbox_dir: String,
+    /// Days a deleted account can still be recovered before it's purged.
+    pub account_deletion_grace_days: i64,
+    /// Days authentication events are kept before they're deleted.
+    pub auth_event_retention_days: i64,
+    /// Directory uploaded avatars and banners are stored in.
+    pub media_dir: String,
+    /// Upstream OIDC/OAuth2 providers users can sign in with.
+    pub upstream_idps: Vec<UpstreamIdp>,
+}
+
+/// An upstream OIDC or OAuth2 identity provider, configured in the
+/// `UPSTREAM_IDPS` JSON array.
+///
+/// Accounts are looked up through the userinfo endpoint, so plain OAuth2
+/// providers work as long as they expose one. Claim names can be remapped for
+/// providers that don't use the OIDC ones.
+#[derive(Debug, Clone, Deserialize)]
+pub struct UpstreamIdp {
+    /// Short identifier used in URLs and stored on linked identities.
+    pub id: String,
+    /// Name shown on the login page.
+    pub name: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub userinfo_endpoint: String,
+    pub client_id: String,
+    pub client_secret: String,
+    #[serde(default = "default_idp_scopes")]
+    pub scopes: String,
+    #[serde(default = "default_subject_claim")]
+    pub subject_claim: String,
+    #[serde(default = "default_username_claim")]
+    pub username_claim: String,
+    #[serde(default = "default_email_claim")]
+    pub email_claim: String,
+    #[serde(default = "default_name_claim")]
+    pub name_claim: String,
+}
+
+fn default_idp_scopes() -> String {
+    "openid profile email".to_string()
+}
+
+fn default_subject_claim() -> String {
+    "sub".to_string()
+}
+
+fn default_username_claim() -> String {
+    "preferred_username".to_string()
+}
+
+fn default_email_claim() -> String {
+    "email".to_string()
+}
+
+fn default_name_claim() -> String {
+    "name".to_string()
 }
 
 impl Config {
@@ -24,16 +109,36 @@ impl Config {
     ///
     /// Panics with a descriptive message if a required variable is missing.
     pub fn from_env() -> Self {
+        let hub_domain = required_var("HUB_DOMAIN");
+        let web_url =
+            std::env::var("WEB_URL").unwrap_or_else(|_| "http://localhost:5173".to_string());
         Self {
             database_url: required_var("DATABASE_URL"),
             redis_url: std::env::var("REDIS_URL")
                 .unwrap_or_else(|_| "redis://localhost:6379/0".to_string()),
-            hub_domain: required_var("HUB_DOMAIN"),
-            signing_key_seed: required_var("SIGNING_KEY_SEED"),
+            hub_domain: hub_domain.clone(),
+            signing_key_seed: std::env::var("SIGNING_KEY_SEED")
+                .ok()
+                .filter(|v| !v.is_empty()),
+            signing_key_dir: std::env::var("SIGNING_KEY_DIR")
+                .ok()
+                .filter(|v| !v.is_empty()),
             port: std::env::var("PORT")
                 .ok()
                 .and_then(|v| v.parse().ok())
                 .unwrap_or(4001),
+            trusted_proxies: csv_var("TRUSTED_PROXIES", Vec::new())
+                .iter()
+                .map(|ip| {
+                    ip.parse().unwrap_or_else(|e| {
+                        panic!("TRUSTED_PROXIES has invalid address {ip:?}: {e}")
+                    })
+                })
+                .collect(),
+            shutdown_timeout_secs: std::env::var("SHUTDOWN_TIMEOUT_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(30),
             turn_shared_secret: required_var("TURN_SHARED_SECRET"),
             stun_urls: csv_var("STUN_URLS", vec!["stun:localhost:3478".to_string()]),
             turn_urls: csv_var(
@@ -43,14 +148,54 @@ impl Config {
                     "turn:localhost:3478?transport=tcp".to_string(),
                 ],
             ),
+            webauthn_rp_id: std::env::var("WEBAUTHN_RP_ID")
+                .unwrap_or_else(|_| host_of(&hub_domain).to_string()),
+            webauthn_origins: csv_var("WEBAUTHN_ORIGINS", vec![hub_domain]),
+            web_redirect_uris: csv_var(
+                "WEB_REDIRECT_URIS",
+                vec![format!("{}/callback", web_url.trim_end_matches('/'))],
+            ),
+            web_url,
+            mail_transport: std::env::var("MAIL_TRANSPORT").unwrap_or_else(|_| "log".to_string()),
+            smtp_url: std::env::var("SMTP_URL").ok(),
+            mail_from: std::env::var("MAIL_FROM")
+                .unwrap_or_else(|_| "Voxora <no-reply@localhost>".to_string()),
+            mail_outbox_dir: std::env::var("MAIL_OUTBOX_DIR")
+                .unwrap_or_else(|_| "outbox".to_string()),
+            account_deletion_grace_days: std::env::var("ACCOUNT_DELETION_GRACE_DAYS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(14),
+            auth_event_retention_days: std::env::var("AUTH_EVENT_RETENTION_DAYS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(90),
+            media_dir: std::env::var("MEDIA_DIR").unwrap_or_else(|_| "media".to_string()),
+            upstream_idps: match std::env::var("UPSTREAM_IDPS") {
+                Ok(val) if !val.trim().is_empty() => serde_json::from_str(&val)
+                    .unwrap_or_else(|e| panic!("UPSTREAM_IDPS is not valid JSON: {e}")),
+                _ => Vec::new(),
+            },
         }
     }
+
+    /// Look up a configured upstream provider by ID.
+    pub fn upstream_idp(&self, id: &str) -> Option<&UpstreamIdp> {
+        self.upstream_idps.iter().find(|p| p.id == id)
+    }
 }
 
 fn required_var(name: &str) -> String {
     std::env::var(name).unwrap_or_else(|_| panic!("{name} env var is required"))
 }
 
+/// Strip the scheme, port and path from an origin, leaving the host.
+fn host_of(origin: &str) -> &str {
+    let rest = origin.split_once("://").map_or(origin, |(_, rest)| rest);
+    let authority = rest.split('/').next().unwrap_or(rest);
+    authority.split(':').next().unwrap_or(authority)
+}
+
 fn csv_var(name: &str, default: Vec<String>) -> Vec<String> {
     match std::env::var(name) {
         Ok(val) if !val.is_empty() => val.split(',').map(|s| s.trim().to_string()).collect(),
@@ -13,6 +13,20 @@ pub trait KeyValueStore: Send + Sync {
     async fn set_ex(&self, key: &str, value: &str, ttl_secs: u64) -> Result<(), ApiError>;
     async fn get(&self, key: &str) -> Result<Option<String>, ApiError>;
     async fn del(&self, key: &str) -> Result<(), ApiError>;
+    /// Delete `key` and return the value it held, in one step, so only one
+    /// caller can consume it.
+    async fn get_del(&self, key: &str) -> Result<Option<String>, ApiError>;
+    /// Store `value` only if `key` doesn't exist yet. Returns whether it was
+    /// stored.
+    async fn set_nx_ex(&self, key: &str, value: &str, ttl_secs: u64) -> Result<bool, ApiError>;
+    /// Increment the counter at `key` and return its new value. A new counter
+    /// expires after `ttl_secs`; an existing one keeps its expiry.
+    async fn incr_ex(&self, key: &str, ttl_secs: u64) -> Result<i64, ApiError>;
+    /// Add a hit at unix time `at` to the sliding window under `key`, drop
+    /// hits older than `window_secs`, and return the hits left, oldest first.
+    async fn record_hit(&self, key: &str, at: i64, window_secs: i64) -> Result<Vec<i64>, ApiError>;
+    /// Hits under `key` after unix time `since`, oldest first.
+    async fn recent_hits(&self, key: &str, since: i64) -> Result<Vec<i64>, ApiError>;
 }
 
 // ---------------------------------------------------------------------------
@@ -59,6 +73,98 @@ impl KeyValueStore for RedisStore {
             ApiError::internal("KV store delete failed")
         })
     }
+
+    async fn get_del(&self, key: &str) -> Result<Option<String>, ApiError> {
+        use redis::AsyncCommands;
+        let mut conn = self.conn.clone();
+        conn.get_del(key).await.map_err(|e| {
+            tracing::error!(?e, "redis getdel failed");
+            ApiError::internal("KV store delete failed")
+        })
+    }
+
+    async fn set_nx_ex(&self, key: &str, value: &str, ttl_secs: u64) -> Result<bool, ApiError> {
+        let mut conn = self.conn.clone();
+        let reply: Option<String> = redis::cmd("SET")
+            .arg(key)
+            .arg(value)
+            .arg("NX")
+            .arg("EX")
+            .arg(ttl_secs)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| {
+                tracing::error!(?e, "redis set nx failed");
+                ApiError::internal("KV store write failed")
+            })?;
+        Ok(reply.is_some())
+    }
+
+    async fn incr_ex(&self, key: &str, ttl_secs: u64) -> Result<i64, ApiError> {
+        // One script so a crash between INCR and EXPIRE can't leave a
+        // counter that never expires.
+        let script = redis::Script::new(
+            r"
+            local n = redis.call('INCR', KEYS[1])
+            if n == 1 then
+                redis.call('EXPIRE', KEYS[1], ARGV[1])
+            end
+            return n
+            ",
+        );
+        let mut conn = self.conn.clone();
+        script
+            .key(key)
+            .arg(ttl_secs)
+            .invoke_async(&mut conn)
+            .await
+            .map_err(|e| {
+                tracing::error!(?e, "redis incr failed");
+                ApiError::internal("KV store write failed")
+            })
+    }
+
+    async fn record_hit(&self, key: &str, at: i64, window_secs: i64) -> Result<Vec<i64>, ApiError> {
+        // A sorted set scored by time. Members only need to be unique.
+        let script = redis::Script::new(
+            r"
+            redis.call('ZREMRANGEBYSCORE', KEYS[1], '-inf', ARGV[1] - ARGV[3])
+            redis.call('ZADD', KEYS[1], ARGV[1], ARGV[2])
+            redis.call('EXPIRE', KEYS[1], ARGV[3])
+            return redis.call('ZRANGE', KEYS[1], 0, -1, 'WITHSCORES')
+            ",
+        );
+        let member = format!("{at}:{:016x}", rand::random::<u64>());
+        let mut conn = self.conn.clone();
+        let hits: Vec<(String, i64)> = script
+            .key(key)
+            .arg(at)
+            .arg(member)
+            .arg(window_secs)
+            .invoke_async(&mut conn)
+            .await
+            .map_err(|e| {
+                tracing::error!(?e, "redis record_hit failed");
+                ApiError::internal("KV store write failed")
+            })?;
+        Ok(hits.into_iter().map(|(_, at)| at).collect())
+    }
+
+    async fn recent_hits(&self, key: &str, since: i64) -> Result<Vec<i64>, ApiError> {
+        let mut conn = self.conn.clone();
+        let hits: Vec<(String, i64)> = redis::cmd("ZRANGEBYSCORE")
+            .arg(key)
+            .arg(format!("({since}"))
+            .arg("+inf")
+            .arg("WITHSCORES")
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| {
+                tracing::error!(?e, "redis recent_hits failed");
+                ApiError::internal("KV store read failed")
+            })?;
+        Ok(hits.into_iter().map(|(_, at)| at).collect())
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -67,12 +173,20 @@ impl KeyValueStore for RedisStore {
 
 pub struct MemoryStore {
     data: Mutex<HashMap<String, String>>,
+    hits: Mutex<HashMap<String, Vec<i64>>>,
+}
+
+impl Default for MemoryStore {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl MemoryStore {
     pub fn new() -> Self {
         Self {
             data: Mutex::new(HashMap::new()),
+            hits: Mutex::new(HashMap::new()),
         }
     }
 }
@@ -93,6 +207,49 @@ impl KeyValueStore for MemoryStore {
 
     async fn del(&self, key: &str) -> Result<(), ApiError> {
         self.data.lock().unwrap().remove(key);
+        self.hits.lock().unwrap().remove(key);
         Ok(())
     }
+
+    async fn get_del(&self, key: &str) -> Result<Option<String>, ApiError> {
+        Ok(self.data.lock().unwrap().remove(key))
+    }
+
+    async fn set_nx_ex(&self, key: &str, value: &str, _ttl_secs: u64) -> Result<bool, ApiError> {
+        let mut data = self.data.lock().unwrap();
+        if data.contains_key(key) {
+            return Ok(false);
+        }
+        data.insert(key.to_string(), value.to_string());
+        Ok(true)
+    }
+
+    async fn incr_ex(&self, key: &str, _ttl_secs: u64) -> Result<i64, ApiError> {
+        let mut data = self.data.lock().unwrap();
+        let entry = data
+            .entry(key.to_string())
+            .or_insert_with(|| "0".to_string());
+        let n = entry
+            .parse::<i64>()
+            .map_err(|_| ApiError::internal("KV value is not a counter"))?
+            + 1;
+        *entry = n.to_string();
+        Ok(n)
+    }
+    async fn record_hit(&self, key: &str, at: i64, window_secs: i64) -> Result<Vec<i64>, ApiError> {
+        let mut hits = self.hits.lock().unwrap();
+        let window = hits.entry(key.to_string()).or_default();
+        window.retain(|&t| t > at - window_secs);
+        window.push(at);
+        window.sort_unstable();
+        Ok(window.clone())
+    }
+
+    async fn recent_hits(&self, key: &str, since: i64) -> Result<Vec<i64>, ApiError> {
+        let hits = self.hits.lock().unwrap();
+        Ok(hits
+            .get(key)
+            .map(|window| window.iter().copied().filter(|&t| t > since).collect())
+            .unwrap_or_default())
+    }
 }
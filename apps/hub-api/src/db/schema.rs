@@ -14,6 +14,47 @@ diesel::table! {
         status -> Text,
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
+        mfa_enabled -> Bool,
+        mfa_secret -> Nullable<Text>,
+        bot_owner_id -> Nullable<Text>,
+        deletion_scheduled_at -> Nullable<Timestamptz>,
+        banner_url -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    mfa_recovery_codes (id) {
+        id -> Text,
+        user_id -> Text,
+        code_hash -> Text,
+        used_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    passkeys (id) {
+        id -> Text,
+        user_id -> Text,
+        credential_id -> Bytea,
+        public_key -> Bytea,
+        sign_count -> Int8,
+        name -> Text,
+        transports -> Nullable<Array<Text>>,
+        created_at -> Timestamptz,
+        last_used_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    user_identities (id) {
+        id -> Text,
+        user_id -> Text,
+        provider -> Text,
+        subject -> Text,
+        email -> Nullable<Text>,
+        created_at -> Timestamptz,
+        last_used_at -> Nullable<Timestamptz>,
     }
 }
 
@@ -28,6 +69,26 @@ diesel::table! {
         expires_at -> Timestamptz,
         revoked -> Bool,
         created_at -> Timestamptz,
+        client_id -> Nullable<Text>,
+        family_id -> Text,
+        scopes -> Array<Text>,
+        rotated_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    oauth_clients (id) {
+        id -> Text,
+        owner_id -> Nullable<Text>,
+        name -> Text,
+        client_type -> Text,
+        client_secret_hash -> Nullable<Text>,
+        redirect_uris -> Array<Text>,
+        allowed_scopes -> Array<Text>,
+        grant_types -> Array<Text>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        bot_user_id -> Nullable<Text>,
     }
 }
 
@@ -56,6 +117,71 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    pod_notifications (id) {
+        id -> Text,
+        pod_id -> Text,
+        event -> Text,
+        user_id -> Text,
+        attempts -> Int4,
+        next_attempt_at -> Timestamptz,
+        last_error -> Nullable<Text>,
+        delivered_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    data_exports (id) {
+        id -> Text,
+        user_id -> Text,
+        status -> Text,
+        archive -> Nullable<Bytea>,
+        error -> Nullable<Text>,
+        created_at -> Timestamptz,
+        completed_at -> Nullable<Timestamptz>,
+        expires_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    admin_audit_log (id) {
+        id -> Text,
+        actor_id -> Nullable<Text>,
+        action -> Text,
+        target_type -> Nullable<Text>,
+        target_id -> Nullable<Text>,
+        changes -> Nullable<Jsonb>,
+        reason -> Nullable<Text>,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    auth_events (id) {
+        id -> Text,
+        user_id -> Nullable<Text>,
+        event -> Text,
+        ip_address -> Nullable<Inet>,
+        user_agent -> Nullable<Text>,
+        client_id -> Nullable<Text>,
+        pod_id -> Nullable<Text>,
+        details -> Nullable<Jsonb>,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    user_bans (user_id) {
+        user_id -> Text,
+        status -> Text,
+        reason -> Text,
+        banned_by -> Nullable<Text>,
+        expires_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     user_pod_bookmarks (user_id, pod_id) {
         user_id -> Text,
@@ -72,10 +198,18 @@ diesel::table! {
     }
 }
 
+diesel::joinable!(mfa_recovery_codes -> users (user_id));
+diesel::joinable!(passkeys -> users (user_id));
+diesel::joinable!(user_identities -> users (user_id));
 diesel::joinable!(sessions -> users (user_id));
+diesel::joinable!(oauth_clients -> users (owner_id));
 diesel::joinable!(pods -> users (owner_id));
+diesel::joinable!(pod_notifications -> pods (pod_id));
+diesel::joinable!(data_exports -> users (user_id));
+diesel::joinable!(admin_audit_log -> users (actor_id));
+diesel::joinable!(auth_events -> users (user_id));
 diesel::joinable!(user_pod_bookmarks -> users (user_id));
 diesel::joinable!(user_pod_bookmarks -> pods (pod_id));
 diesel::joinable!(user_preferences -> users (user_id));
 
-diesel::allow_tables_to_appear_in_same_query!(users, sessions, pods, user_pod_bookmarks, user_preferences,);
+diesel::allow_tables_to_appear_in_same_query!(users, mfa_recovery_codes, passkeys, user_identities, sessions, oauth_clients, pods, pod_notifications, data_exports, admin_audit_log, auth_events, user_bans, user_pod_bookmarks, user_preferences,);
@@ -79,6 +79,24 @@ impl ApiError {
         }
     }
 
+    pub fn payload_too_large(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::PAYLOAD_TOO_LARGE,
+            code: "PAYLOAD_TOO_LARGE".to_string(),
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn too_many_requests(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::TOO_MANY_REQUESTS,
+            code: "RATE_LIMITED".to_string(),
+            message: message.into(),
+            details: None,
+        }
+    }
+
     pub fn internal(message: impl Into<String>) -> Self {
         Self {
             status: StatusCode::INTERNAL_SERVER_ERROR,
@@ -124,3 +142,86 @@ impl From<diesel_async::pooled_connection::deadpool::PoolError> for ApiError {
         Self::internal("An internal error occurred")
     }
 }
+
+/// Error body of the OAuth token endpoint (RFC 6749 §5.2).
+#[derive(Debug, Serialize, ToSchema)]
+pub struct OAuthErrorBody {
+    /// Error code, e.g. `invalid_grant` or `authorization_pending`.
+    pub error: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_description: Option<String>,
+}
+
+/// Error returned by the OAuth token endpoint. Unlike [`ApiError`], the code
+/// is a top-level string so standard OAuth clients can read it.
+#[derive(Debug)]
+pub struct OAuthError {
+    pub status: StatusCode,
+    pub error: &'static str,
+    pub description: Option<String>,
+}
+
+impl OAuthError {
+    /// A `400 Bad Request` with `error` and no description.
+    pub fn new(error: &'static str) -> Self {
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            error,
+            description: None,
+        }
+    }
+
+    pub fn invalid_request(description: impl Into<String>) -> Self {
+        Self::new("invalid_request").describe(description)
+    }
+
+    pub fn invalid_grant(description: impl Into<String>) -> Self {
+        Self::new("invalid_grant").describe(description)
+    }
+
+    pub fn describe(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+}
+
+/// Failures from shared helpers: bad client credentials are
+/// `invalid_client`, a suspended account can't use the grant, and anything
+/// else the request got wrong is `invalid_request`.
+impl From<ApiError> for OAuthError {
+    fn from(err: ApiError) -> Self {
+        let (status, error) = match err.status {
+            StatusCode::UNAUTHORIZED => (StatusCode::UNAUTHORIZED, "invalid_client"),
+            StatusCode::FORBIDDEN => (StatusCode::BAD_REQUEST, "invalid_grant"),
+            s if s.is_server_error() => (s, "server_error"),
+            _ => (StatusCode::BAD_REQUEST, "invalid_request"),
+        };
+        Self {
+            status,
+            error,
+            description: Some(err.message),
+        }
+    }
+}
+
+impl From<diesel::result::Error> for OAuthError {
+    fn from(err: diesel::result::Error) -> Self {
+        ApiError::from(err).into()
+    }
+}
+
+impl From<diesel_async::pooled_connection::deadpool::PoolError> for OAuthError {
+    fn from(err: diesel_async::pooled_connection::deadpool::PoolError) -> Self {
+        ApiError::from(err).into()
+    }
+}
+
+impl IntoResponse for OAuthError {
+    fn into_response(self) -> Response {
+        let body = OAuthErrorBody {
+            error: self.error.to_string(),
+            error_description: self.description,
+        };
+        (self.status, Json(body)).into_response()
+    }
+}
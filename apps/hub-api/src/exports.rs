@@ -0,0 +1,398 @@
+//! Account data exports (RFC §18.1).
+//!
+//! Users request an export, a background job collects everything the Hub
+//! holds about them into a zip of JSON and CSV files, and the archive can be
+//! downloaded through short-lived links until it expires.
+
+use std::io::{Cursor, Write};
+
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use serde::Serialize;
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+use crate::db::pool::DbPool;
+use crate::db::schema::{
+    data_exports, oauth_clients, pods, sessions, user_identities, user_pod_bookmarks,
+    user_preferences, users,
+};
+use crate::error::ApiError;
+use crate::mail::EmailMessage;
+use crate::models::data_export::{
+    EXPORT_STATUS_EXPIRED, EXPORT_STATUS_FAILED, EXPORT_STATUS_PENDING, EXPORT_STATUS_READY,
+};
+use crate::models::oauth_client::{OAuthClient, OAuthClientResponse};
+use crate::models::pod::{Pod, PodResponse};
+use crate::models::preferences::UserPreferences;
+use crate::models::session::Session;
+use crate::models::user::{User, UserResponse};
+use crate::models::user_identity::{UserIdentity, UserIdentityResponse};
+use crate::AppState;
+
+/// How long a finished archive is kept.
+pub const EXPORT_RETENTION_DAYS: i64 = 7;
+
+/// Minimum time between two exports by the same user.
+pub const EXPORT_INTERVAL_HOURS: i64 = 24;
+
+/// Exports built per run of [`process_pending`].
+const EXPORT_BATCH: i64 = 10;
+
+/// KV key a user claims for [`EXPORT_INTERVAL_HOURS`] when they request an
+/// export. Released again if the export fails, so they can retry.
+pub fn request_key(user_id: &str) -> String {
+    format!("hub:export_requested:{user_id}")
+}
+
+#[derive(Serialize)]
+struct ExportSession {
+    id: String,
+    ip_address: Option<String>,
+    user_agent: Option<String>,
+    client_id: Option<String>,
+    scopes: Vec<String>,
+    revoked: bool,
+    created_at: DateTime<Utc>,
+    last_active_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+}
+
+impl From<Session> for ExportSession {
+    fn from(s: Session) -> Self {
+        Self {
+            id: s.id,
+            ip_address: s.ip_address.map(|ip| ip.addr().to_string()),
+            user_agent: s.user_agent,
+            client_id: s.client_id,
+            scopes: s.scopes,
+            revoked: s.revoked,
+            created_at: s.created_at,
+            last_active_at: s.last_active_at,
+            expires_at: s.expires_at,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ExportBookmark {
+    pod_id: String,
+    pod_name: String,
+    pod_url: String,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+struct ExportAuthorizedClient {
+    client_id: String,
+    name: String,
+}
+
+#[derive(Serialize)]
+struct ExportConnections {
+    /// Accounts at upstream identity providers used to sign in.
+    identities: Vec<UserIdentityResponse>,
+    /// OAuth clients the user has signed in to.
+    authorized_clients: Vec<ExportAuthorizedClient>,
+    /// OAuth clients the user registered.
+    registered_clients: Vec<OAuthClientResponse>,
+}
+
+/// Quote a CSV field if it needs it (RFC 4180).
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<Vec<u8>, ApiError> {
+    serde_json::to_vec_pretty(value).map_err(|e| {
+        tracing::error!(?e, "failed to serialize export data");
+        ApiError::internal("Export data could not be serialized")
+    })
+}
+
+fn csv(header: &[&str], rows: impl IntoIterator<Item = Vec<String>>) -> String {
+    let mut out = header.join(",");
+    out.push_str("\r\n");
+    for row in rows {
+        let fields: Vec<String> = row.iter().map(|f| csv_field(f)).collect();
+        out.push_str(&fields.join(","));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+/// Collect everything the Hub holds about a user into a zip archive.
+pub async fn build_archive(db: &DbPool, user_id: &str) -> Result<Vec<u8>, ApiError> {
+    let mut conn = db.get().await?;
+
+    let user: User = users::table
+        .find(user_id)
+        .select(User::as_select())
+        .first(&mut conn)
+        .await
+        .map_err(ApiError::from)?;
+    let sessions: Vec<ExportSession> = sessions::table
+        .filter(sessions::user_id.eq(user_id))
+        .order(sessions::created_at.asc())
+        .select(Session::as_select())
+        .load::<Session>(&mut conn)
+        .await
+        .map_err(ApiError::from)?
+        .into_iter()
+        .map(ExportSession::from)
+        .collect();
+    let bookmarks: Vec<ExportBookmark> = user_pod_bookmarks::table
+        .inner_join(pods::table)
+        .filter(user_pod_bookmarks::user_id.eq(user_id))
+        .order(user_pod_bookmarks::created_at.asc())
+        .select((
+            pods::id,
+            pods::name,
+            pods::url,
+            user_pod_bookmarks::created_at,
+        ))
+        .load::<(String, String, String, DateTime<Utc>)>(&mut conn)
+        .await
+        .map_err(ApiError::from)?
+        .into_iter()
+        .map(|(pod_id, pod_name, pod_url, created_at)| ExportBookmark {
+            pod_id,
+            pod_name,
+            pod_url,
+            created_at,
+        })
+        .collect();
+    let preferences: Option<UserPreferences> = user_preferences::table
+        .find(user_id)
+        .select(UserPreferences::as_select())
+        .first(&mut conn)
+        .await
+        .optional()
+        .map_err(ApiError::from)?;
+    let owned_pods: Vec<PodResponse> = pods::table
+        .filter(pods::owner_id.eq(user_id))
+        .order(pods::created_at.asc())
+        .select(Pod::as_select())
+        .load::<Pod>(&mut conn)
+        .await
+        .map_err(ApiError::from)?
+        .into_iter()
+        .map(PodResponse::from)
+        .collect();
+    let identities: Vec<UserIdentityResponse> = user_identities::table
+        .filter(user_identities::user_id.eq(user_id))
+        .order(user_identities::created_at.asc())
+        .select(UserIdentity::as_select())
+        .load::<UserIdentity>(&mut conn)
+        .await
+        .map_err(ApiError::from)?
+        .into_iter()
+        .map(UserIdentityResponse::from)
+        .collect();
+    let authorized_ids: Vec<&str> = sessions
+        .iter()
+        .filter_map(|s| s.client_id.as_deref())
+        .collect();
+    let authorized_clients: Vec<ExportAuthorizedClient> = oauth_clients::table
+        .filter(oauth_clients::id.eq_any(&authorized_ids))
+        .order(oauth_clients::name.asc())
+        .select((oauth_clients::id, oauth_clients::name))
+        .load::<(String, String)>(&mut conn)
+        .await
+        .map_err(ApiError::from)?
+        .into_iter()
+        .map(|(client_id, name)| ExportAuthorizedClient { client_id, name })
+        .collect();
+    let registered_clients: Vec<OAuthClientResponse> = oauth_clients::table
+        .filter(oauth_clients::owner_id.eq(user_id))
+        .order(oauth_clients::created_at.asc())
+        .select(OAuthClient::as_select())
+        .load::<OAuthClient>(&mut conn)
+        .await
+        .map_err(ApiError::from)?
+        .into_iter()
+        .map(OAuthClientResponse::from)
+        .collect();
+
+    let sessions_csv = csv(
+        &[
+            "id",
+            "ip_address",
+            "user_agent",
+            "client_id",
+            "revoked",
+            "created_at",
+            "last_active_at",
+            "expires_at",
+        ],
+        sessions.iter().map(|s| {
+            vec![
+                s.id.clone(),
+                s.ip_address.clone().unwrap_or_default(),
+                s.user_agent.clone().unwrap_or_default(),
+                s.client_id.clone().unwrap_or_default(),
+                s.revoked.to_string(),
+                s.created_at.to_rfc3339(),
+                s.last_active_at.to_rfc3339(),
+                s.expires_at.to_rfc3339(),
+            ]
+        }),
+    );
+    let bookmarks_csv = csv(
+        &["pod_id", "pod_name", "pod_url", "created_at"],
+        bookmarks.iter().map(|b| {
+            vec![
+                b.pod_id.clone(),
+                b.pod_name.clone(),
+                b.pod_url.clone(),
+                b.created_at.to_rfc3339(),
+            ]
+        }),
+    );
+    let connections = ExportConnections {
+        identities,
+        authorized_clients,
+        registered_clients,
+    };
+
+    let files: Vec<(&str, Vec<u8>)> = vec![
+        ("profile.json", to_json(&UserResponse::from(user))?),
+        ("sessions.json", to_json(&sessions)?),
+        ("sessions.csv", sessions_csv.into_bytes()),
+        ("pod_bookmarks.json", to_json(&bookmarks)?),
+        ("pod_bookmarks.csv", bookmarks_csv.into_bytes()),
+        ("preferences.json", to_json(&preferences)?),
+        ("pods.json", to_json(&owned_pods)?),
+        ("connections.json", to_json(&connections)?),
+    ];
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default();
+    for (name, contents) in files {
+        zip.start_file(name, options)
+            .and_then(|_| zip.write_all(&contents).map_err(Into::into))
+            .map_err(|e| {
+                tracing::error!(?e, "failed to write export archive");
+                ApiError::internal("Export archive could not be written")
+            })?;
+    }
+    let archive = zip.finish().map_err(|e| {
+        tracing::error!(?e, "failed to finish export archive");
+        ApiError::internal("Export archive could not be written")
+    })?;
+    Ok(archive.into_inner())
+}
+
+/// Build the archives of pending exports and email their owners. Returns how
+/// many were built.
+pub async fn process_pending(state: &AppState) -> Result<usize, ApiError> {
+    let mut conn = state.db.get().await?;
+    let pending: Vec<(String, String)> = data_exports::table
+        .filter(data_exports::status.eq(EXPORT_STATUS_PENDING))
+        .order(data_exports::created_at.asc())
+        .limit(EXPORT_BATCH)
+        .select((data_exports::id, data_exports::user_id))
+        .load(&mut conn)
+        .await
+        .map_err(ApiError::from)?;
+
+    let mut built = 0;
+    for (export_id, user_id) in pending {
+        let now = Utc::now();
+        match build_archive(&state.db, &user_id).await {
+            Ok(archive) => {
+                diesel::update(data_exports::table.find(&export_id))
+                    .set((
+                        data_exports::status.eq(EXPORT_STATUS_READY),
+                        data_exports::archive.eq(archive),
+                        data_exports::completed_at.eq(now),
+                        data_exports::expires_at
+                            .eq(now + chrono::Duration::days(EXPORT_RETENTION_DAYS)),
+                    ))
+                    .execute(&mut conn)
+                    .await
+                    .map_err(ApiError::from)?;
+                built += 1;
+                tracing::info!(%user_id, %export_id, "data export ready");
+                notify_ready(state, &user_id).await;
+            }
+            Err(e) => {
+                tracing::warn!(%user_id, %export_id, error = %e.message, "data export failed");
+                diesel::update(data_exports::table.find(&export_id))
+                    .set((
+                        data_exports::status.eq(EXPORT_STATUS_FAILED),
+                        data_exports::error.eq(e.message),
+                        data_exports::completed_at.eq(now),
+                    ))
+                    .execute(&mut conn)
+                    .await
+                    .map_err(ApiError::from)?;
+                state.kv.del(&request_key(&user_id)).await?;
+            }
+        }
+    }
+    Ok(built)
+}
+
+/// Email the user that their export can be downloaded. Failures are only
+/// logged; the export is listed in their settings either way.
+async fn notify_ready(state: &AppState, user_id: &str) {
+    let Ok(mut conn) = state.db.get().await else {
+        return;
+    };
+    let user: Option<User> = users::table
+        .find(user_id)
+        .select(User::as_select())
+        .first(&mut conn)
+        .await
+        .optional()
+        .unwrap_or(None);
+    let Some(user) = user else {
+        return;
+    };
+    let Some(email) = user.email.filter(|_| user.email_verified) else {
+        return;
+    };
+
+    let link = format!(
+        "{}/settings/privacy",
+        state.config.web_url.trim_end_matches('/')
+    );
+    let message = EmailMessage {
+        to: email,
+        subject: "Your Voxora data export is ready".to_string(),
+        text: format!(
+            "Hi {},\n\n\
+             The copy of your Voxora data you asked for is ready. Download it \
+             from your privacy settings:\n\n\
+             {link}\n\n\
+             The archive is deleted after {EXPORT_RETENTION_DAYS} days.\n",
+            user.display_name
+        ),
+    };
+    if let Err(e) = state.mailer.send(&message).await {
+        tracing::warn!(%user_id, error = %e.message, "failed to send export email");
+    }
+}
+
+/// Delete archives past their expiry. Returns how many were deleted.
+pub async fn purge_expired(db: &DbPool) -> Result<usize, ApiError> {
+    let mut conn = db.get().await?;
+    diesel::update(
+        data_exports::table
+            .filter(data_exports::status.eq(EXPORT_STATUS_READY))
+            .filter(data_exports::expires_at.le(Utc::now())),
+    )
+    .set((
+        data_exports::status.eq(EXPORT_STATUS_EXPIRED),
+        data_exports::archive.eq(None::<Vec<u8>>),
+    ))
+    .execute(&mut conn)
+    .await
+    .map_err(ApiError::from)
+}
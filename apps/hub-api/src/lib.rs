@@ -1,22 +1,34 @@
+pub mod accounts;
 pub mod auth;
 pub mod config;
 pub mod db;
 pub mod error;
+pub mod exports;
+pub mod mail;
+pub mod media;
 pub mod models;
+pub mod pod_events;
+pub mod relay;
 pub mod routes;
 
 use std::sync::Arc;
 
-use auth::keys::SigningKeys;
+use auth::keys::Keyring;
 use config::Config;
 use db::kv::KeyValueStore;
 use db::pool::DbPool;
+use mail::Mailer;
+use media::MediaStore;
+use relay::NotificationRelay;
 
 /// Shared application state available to all route handlers.
 #[derive(Clone)]
 pub struct AppState {
     pub db: DbPool,
     pub kv: Arc<dyn KeyValueStore>,
-    pub keys: Arc<SigningKeys>,
+    pub keys: Arc<Keyring>,
     pub config: Arc<Config>,
+    pub mailer: Arc<dyn Mailer>,
+    pub media: Arc<dyn MediaStore>,
+    pub relay: NotificationRelay,
 }
@@ -1,5 +1,6 @@
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 
 use axum::Router;
 use tower_http::cors::{Any, CorsLayer};
@@ -9,7 +10,7 @@ use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
-use hub_api::auth::keys::SigningKeys;
+use hub_api::auth::keys::Keyring;
 use hub_api::config::Config;
 use hub_api::routes::ApiDoc;
 use hub_api::AppState;
@@ -30,10 +31,16 @@ async fn main() {
 
     let config = Config::from_env();
     let port = config.port;
+    let shutdown_timeout = Duration::from_secs(config.shutdown_timeout_secs);
 
     // Connect to PostgreSQL
     let db = hub_api::db::pool::connect(&config.database_url).await;
 
+    // Keep the web client's OAuth registration in sync with config
+    hub_api::auth::clients::ensure_first_party_client(&db, &config)
+        .await
+        .expect("failed to register first-party OAuth client");
+
     // Connect to Redis
     let redis_client = redis::Client::open(config.redis_url.as_str()).expect("invalid REDIS_URL");
     let redis_conn = redis::aio::ConnectionManager::new(redis_client)
@@ -44,17 +51,28 @@ async fn main() {
     let kv: Arc<dyn hub_api::db::kv::KeyValueStore> =
         Arc::new(hub_api::db::kv::RedisStore::new(redis_conn));
 
-    // Derive Ed25519 signing keys from seed
-    let keys = Arc::new(SigningKeys::from_seed(&config.signing_key_seed));
-    tracing::info!(kid = %keys.kid, "signing keys loaded");
+    // Load the Ed25519 signing keyring (or derive a single key from the seed)
+    let keys = Arc::new(Keyring::from_config(&config).expect("failed to load signing keys"));
+    tracing::info!(kid = %keys.active().kid, "signing keys loaded");
+
+    let mailer = hub_api::mail::from_config(&config);
+    tracing::info!(transport = %config.mail_transport, "mailer configured");
+
+    let media = hub_api::media::from_config(&config);
 
     let state = AppState {
         db,
         kv,
         keys,
         config: Arc::new(config),
+        mailer,
+        media,
+        relay: hub_api::relay::NotificationRelay::new(),
     };
 
+    // Purge deleted accounts and deliver events to Pods in the background.
+    tokio::spawn(hub_api::accounts::run_background_jobs(state.clone()));
+
     let cors = CorsLayer::new()
         .allow_origin(Any)
         .allow_methods(Any)
@@ -73,5 +91,31 @@ async fn main() {
     let listener = tokio::net::TcpListener::bind(addr)
         .await
         .expect("failed to bind");
-    axum::serve(listener, app).await.expect("server error");
+
+    let (stop_tx, stop_rx) = tokio::sync::oneshot::channel::<()>();
+    let mut server = tokio::spawn(async move {
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown(async {
+            let _ = stop_rx.await;
+        })
+        .await
+    });
+
+    tokio::select! {
+        _ = voxora_common::shutdown::signal() => {}
+        result = &mut server => voxora_common::shutdown::server_stopped(result),
+    }
+    tracing::info!("shutdown signal received, draining");
+
+    // Stop accepting connections and let in-flight requests finish.
+    let _ = stop_tx.send(());
+    if tokio::time::timeout(shutdown_timeout, server)
+        .await
+        .is_err()
+    {
+        tracing::warn!("shutdown deadline reached before in-flight requests drained");
+    }
 }
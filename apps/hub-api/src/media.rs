@@ -0,0 +1,283 @@
+//! Uploaded profile media: avatars and banners.
+//!
+//! Uploads are decoded, cropped to shape and re-encoded as PNG at a fixed
+//! set of sizes, so nothing the user sent is ever served back as-is. The
+//! renditions live in a [`MediaStore`] and are served by the Hub's `/media`
+//! route, which `avatar_url` and `banner_url` point at. Pods get those URLs
+//! in the SIA instead of arbitrary third-party ones.
+
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat, ImageReader, Limits};
+
+use crate::config::Config;
+use crate::error::ApiError;
+
+/// Largest upload accepted, in bytes.
+pub const MAX_UPLOAD_BYTES: usize = 8 * 1024 * 1024;
+
+/// Largest width or height of an uploaded image, in pixels.
+pub const MAX_SOURCE_DIMENSION: u32 = 8192;
+
+/// Formats uploads may be in. Animated GIFs keep their first frame.
+const ACCEPTED_FORMATS: &[ImageFormat] = &[
+    ImageFormat::Png,
+    ImageFormat::Jpeg,
+    ImageFormat::Gif,
+    ImageFormat::WebP,
+];
+
+/// What an image is for. Decides its shape, sizes and storage prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaKind {
+    /// Square, rendered at every size in [`MediaKind::sizes`].
+    Avatar,
+    /// 3:1, rendered at every width in [`MediaKind::sizes`].
+    Banner,
+}
+
+impl MediaKind {
+    /// Path segment the kind is stored and served under.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Avatar => "avatars",
+            Self::Banner => "banners",
+        }
+    }
+
+    pub fn from_path(segment: &str) -> Option<Self> {
+        match segment {
+            "avatars" => Some(Self::Avatar),
+            "banners" => Some(Self::Banner),
+            _ => None,
+        }
+    }
+
+    /// Widths rendered for each upload, largest first.
+    pub fn sizes(self) -> &'static [u32] {
+        match self {
+            Self::Avatar => &[512, 256, 128, 64],
+            Self::Banner => &[1500, 600],
+        }
+    }
+
+    /// Width served when the URL doesn't ask for one.
+    pub fn default_size(self) -> u32 {
+        match self {
+            Self::Avatar => 256,
+            Self::Banner => 1500,
+        }
+    }
+
+    /// Width to height.
+    fn aspect(self) -> (u32, u32) {
+        match self {
+            Self::Avatar => (1, 1),
+            Self::Banner => (3, 1),
+        }
+    }
+}
+
+/// Storage key of one rendition of an upload.
+pub fn key(kind: MediaKind, owner_id: &str, media_id: &str, size: u32) -> String {
+    format!("{}/{owner_id}/{media_id}/{size}.png", kind.as_str())
+}
+
+/// Prefix holding every rendition of an upload.
+pub fn upload_prefix(kind: MediaKind, owner_id: &str, media_id: &str) -> String {
+    format!("{}/{owner_id}/{media_id}", kind.as_str())
+}
+
+/// Prefix holding every upload of one kind by `owner_id`.
+pub fn owner_prefix(kind: MediaKind, owner_id: &str) -> String {
+    format!("{}/{owner_id}", kind.as_str())
+}
+
+/// Public URL of an upload, as stored in `avatar_url` or `banner_url`.
+pub fn public_url(hub_domain: &str, kind: MediaKind, owner_id: &str, media_id: &str) -> String {
+    format!(
+        "{}/media/{}/{owner_id}/{media_id}",
+        hub_domain.trim_end_matches('/'),
+        kind.as_str()
+    )
+}
+
+/// The media ID in `url` if it's one of `owner_id`'s uploads on this Hub.
+pub fn media_id_of<'a>(
+    hub_domain: &str,
+    kind: MediaKind,
+    owner_id: &str,
+    url: &'a str,
+) -> Option<&'a str> {
+    let prefix = format!(
+        "{}/media/{}/{owner_id}/",
+        hub_domain.trim_end_matches('/'),
+        kind.as_str()
+    );
+    url.strip_prefix(&prefix).filter(|id| is_valid_segment(id))
+}
+
+/// Whether `segment` can appear in a storage key. Keeps keys inside the
+/// store whatever the request path held.
+pub fn is_valid_segment(segment: &str) -> bool {
+    !segment.is_empty()
+        && segment.len() <= 64
+        && segment
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-')
+}
+
+// ---------------------------------------------------------------------------
+// Rendering
+// ---------------------------------------------------------------------------
+
+/// Decode an upload and render it at every size for `kind`, as
+/// `(width, png)` pairs.
+///
+/// CPU-bound: call it from a blocking task.
+pub fn render(kind: MediaKind, data: &[u8]) -> Result<Vec<(u32, Vec<u8>)>, ApiError> {
+    let mut reader = ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .map_err(|_| ApiError::bad_request("Could not read image"))?;
+    if !reader
+        .format()
+        .is_some_and(|f| ACCEPTED_FORMATS.contains(&f))
+    {
+        return Err(ApiError::bad_request(
+            "Image must be a PNG, JPEG, GIF or WebP",
+        ));
+    }
+
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_SOURCE_DIMENSION);
+    limits.max_image_height = Some(MAX_SOURCE_DIMENSION);
+    reader.limits(limits);
+    let image = reader
+        .decode()
+        .map_err(|_| ApiError::bad_request("Image is corrupt or too large"))?;
+
+    let cropped = crop_to_aspect(&image, kind.aspect());
+    let (aw, ah) = kind.aspect();
+    kind.sizes()
+        .iter()
+        .map(|&width| {
+            let height = width * ah / aw;
+            let resized = cropped.resize_exact(width, height, FilterType::Lanczos3);
+            let mut png = Vec::new();
+            DynamicImage::ImageRgba8(resized.to_rgba8())
+                .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+                .map_err(|e| {
+                    tracing::error!(?e, "failed to encode image");
+                    ApiError::internal("Failed to process image")
+                })?;
+            Ok((width, png))
+        })
+        .collect()
+}
+
+/// The largest centred region of `image` with the given aspect ratio.
+fn crop_to_aspect(image: &DynamicImage, (aw, ah): (u32, u32)) -> DynamicImage {
+    let (w, h) = (image.width(), image.height());
+    let (cw, ch) = if u64::from(w) * u64::from(ah) > u64::from(h) * u64::from(aw) {
+        ((h * aw / ah).max(1), h)
+    } else {
+        (w, (w * ah / aw).max(1))
+    };
+    image.crop_imm((w - cw) / 2, (h - ch) / 2, cw, ch)
+}
+
+// ---------------------------------------------------------------------------
+// Storage
+// ---------------------------------------------------------------------------
+
+/// Abstraction over where media is kept. Keys are `/`-separated paths built
+/// by [`key`] and friends.
+#[async_trait]
+pub trait MediaStore: Send + Sync {
+    async fn put(&self, key: &str, data: Vec<u8>) -> Result<(), ApiError>;
+
+    /// `None` if nothing is stored under `key`.
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, ApiError>;
+
+    /// Delete everything under `prefix`. Deleting nothing isn't an error.
+    async fn delete_prefix(&self, prefix: &str) -> Result<(), ApiError>;
+}
+
+/// Build the media store for `MEDIA_DIR`.
+pub fn from_config(config: &Config) -> Arc<dyn MediaStore> {
+    Arc::new(LocalMediaStore::new(&config.media_dir))
+}
+
+// ---------------------------------------------------------------------------
+// Local disk implementation
+// ---------------------------------------------------------------------------
+
+/// Stores media as files under a directory, one per key.
+pub struct LocalMediaStore {
+    dir: PathBuf,
+}
+
+impl LocalMediaStore {
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf, ApiError> {
+        let mut path = self.dir.clone();
+        for segment in key.split('/') {
+            let stem = segment.strip_suffix(".png").unwrap_or(segment);
+            if !is_valid_segment(stem) {
+                return Err(ApiError::internal("invalid media key"));
+            }
+            path.push(segment);
+        }
+        Ok(path)
+    }
+}
+
+#[async_trait]
+impl MediaStore for LocalMediaStore {
+    async fn put(&self, key: &str, data: Vec<u8>) -> Result<(), ApiError> {
+        let path = self.path(key)?;
+        let write = async {
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            tokio::fs::write(&path, data).await
+        };
+        write.await.map_err(|e| {
+            tracing::error!(?e, path = %path.display(), "failed to write media");
+            ApiError::internal("Failed to store image")
+        })
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, ApiError> {
+        let path = self.path(key)?;
+        match tokio::fs::read(&path).await {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => {
+                tracing::error!(?e, path = %path.display(), "failed to read media");
+                Err(ApiError::internal("Failed to read image"))
+            }
+        }
+    }
+
+    async fn delete_prefix(&self, prefix: &str) -> Result<(), ApiError> {
+        let path = self.path(prefix)?;
+        match tokio::fs::remove_dir_all(&path).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => {
+                tracing::error!(?e, path = %path.display(), "failed to delete media");
+                Err(ApiError::internal("Failed to delete image"))
+            }
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::db::schema::admin_audit_log;
+use crate::error::ApiError;
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = admin_audit_log)]
+pub struct NewAdminAuditLog<'a> {
+    pub id: &'a str,
+    pub actor_id: &'a str,
+    pub action: &'a str,
+    pub target_type: Option<&'a str>,
+    pub target_id: Option<&'a str>,
+    pub changes: Option<serde_json::Value>,
+    pub reason: Option<&'a str>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A staff action taken through the admin API.
+#[derive(Debug, Queryable, Selectable, Serialize, ToSchema)]
+#[diesel(table_name = admin_audit_log)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct AdminAuditLogEntry {
+    pub id: String,
+    /// The staff member, or null once their account is gone.
+    pub actor_id: Option<String>,
+    /// What was done, e.g. `user.ban` or `pod.suspend`.
+    pub action: String,
+    /// `user`, `pod` or `signing_keys`.
+    pub target_type: Option<String>,
+    pub target_id: Option<String>,
+    pub changes: Option<serde_json::Value>,
+    pub reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Record a staff action in the admin audit log. Takes a connection so the
+/// entry can be written in the same transaction as the action.
+pub async fn log(
+    conn: &mut AsyncPgConnection,
+    actor_id: &str,
+    action: &str,
+    target_type: Option<&str>,
+    target_id: Option<&str>,
+    changes: Option<serde_json::Value>,
+    reason: Option<&str>,
+) -> Result<(), ApiError> {
+    let log_id = voxora_common::id::prefixed_ulid(voxora_common::id::prefix::AUDIT);
+
+    diesel::insert_into(admin_audit_log::table)
+        .values(NewAdminAuditLog {
+            id: &log_id,
+            actor_id,
+            action,
+            target_type,
+            target_id,
+            changes,
+            reason,
+            created_at: Utc::now(),
+        })
+        .execute(conn)
+        .await
+        .map_err(ApiError::from)?;
+
+    Ok(())
+}
@@ -0,0 +1,70 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use ipnet::IpNet;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::db::schema::auth_events;
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = auth_events)]
+pub struct NewAuthEvent<'a> {
+    pub id: &'a str,
+    pub user_id: Option<&'a str>,
+    pub event: &'a str,
+    pub ip_address: Option<IpNet>,
+    pub user_agent: Option<&'a str>,
+    pub client_id: Option<&'a str>,
+    pub pod_id: Option<&'a str>,
+    pub details: Option<serde_json::Value>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Full auth event row from the database.
+#[derive(Debug, Queryable, Selectable)]
+#[diesel(table_name = auth_events)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct AuthEvent {
+    pub id: String,
+    pub user_id: Option<String>,
+    pub event: String,
+    pub ip_address: Option<IpNet>,
+    pub user_agent: Option<String>,
+    pub client_id: Option<String>,
+    pub pod_id: Option<String>,
+    pub details: Option<serde_json::Value>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An authentication event as shown to the account owner.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AuthEventResponse {
+    pub id: String,
+    /// What happened, e.g. `login.success` or `sia.issued`.
+    pub event: String,
+    /// Address the request came from.
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    /// OAuth client involved, if any.
+    pub client_id: Option<String>,
+    /// Pod a SIA was issued for.
+    pub pod_id: Option<String>,
+    /// Event-specific context, e.g. the failure reason or sign-in methods.
+    pub details: Option<serde_json::Value>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<AuthEvent> for AuthEventResponse {
+    fn from(e: AuthEvent) -> Self {
+        Self {
+            id: e.id,
+            event: e.event,
+            ip_address: e.ip_address.map(|ip| ip.addr().to_string()),
+            user_agent: e.user_agent,
+            client_id: e.client_id,
+            pod_id: e.pod_id,
+            details: e.details,
+            created_at: e.created_at,
+        }
+    }
+}
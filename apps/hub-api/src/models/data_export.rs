@@ -0,0 +1,65 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::db::schema::data_exports;
+
+/// Waiting for the background job to build the archive.
+pub const EXPORT_STATUS_PENDING: &str = "pending";
+/// The archive is built and can be downloaded until `expires_at`.
+pub const EXPORT_STATUS_READY: &str = "ready";
+/// Building the archive failed; see `error`.
+pub const EXPORT_STATUS_FAILED: &str = "failed";
+/// The archive passed `expires_at` and was deleted.
+pub const EXPORT_STATUS_EXPIRED: &str = "expired";
+
+/// A data export request. The archive bytes are left out so listing
+/// exports doesn't load them.
+#[derive(Debug, Queryable, Selectable)]
+#[diesel(table_name = data_exports)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct DataExport {
+    pub id: String,
+    pub user_id: String,
+    pub status: String,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Insertable struct for requesting a new export.
+#[derive(Debug, Insertable)]
+#[diesel(table_name = data_exports)]
+pub struct NewDataExport {
+    pub id: String,
+    pub user_id: String,
+}
+
+/// An export as shown to its owner.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DataExportResponse {
+    pub id: String,
+    /// `pending`, `ready`, `failed` or `expired`.
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    /// When the archive will be deleted.
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Short-lived link to the archive, present while it is `ready`.
+    pub download_url: Option<String>,
+}
+
+impl DataExportResponse {
+    pub fn from_export(export: DataExport, download_url: Option<String>) -> Self {
+        Self {
+            id: export.id,
+            status: export.status,
+            created_at: export.created_at,
+            completed_at: export.completed_at,
+            expires_at: export.expires_at,
+            download_url,
+        }
+    }
+}
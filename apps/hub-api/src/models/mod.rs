@@ -1,5 +1,14 @@
+pub mod admin_audit_log;
+pub mod auth_event;
 pub mod bookmark;
+pub mod data_export;
+pub mod oauth_client;
+pub mod passkey;
 pub mod pod;
+pub mod pod_notification;
 pub mod preferences;
+pub mod recovery_code;
 pub mod session;
 pub mod user;
+pub mod user_ban;
+pub mod user_identity;
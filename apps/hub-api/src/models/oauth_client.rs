@@ -0,0 +1,119 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::db::schema::oauth_clients;
+
+/// Client that can't keep a secret (browser and native apps). Must use PKCE.
+pub const CLIENT_TYPE_PUBLIC: &str = "public";
+/// Client that authenticates to the token endpoint with a secret.
+pub const CLIENT_TYPE_CONFIDENTIAL: &str = "confidential";
+
+pub const GRANT_AUTHORIZATION_CODE: &str = "authorization_code";
+pub const GRANT_REFRESH_TOKEN: &str = "refresh_token";
+/// Device authorization grant (RFC 8628) for clients without a browser.
+pub const GRANT_DEVICE_CODE: &str = "urn:ietf:params:oauth:grant-type:device_code";
+/// Only available to bot clients, which are created with their bot.
+pub const GRANT_CLIENT_CREDENTIALS: &str = "client_credentials";
+
+/// Grant types a client may be registered for.
+pub const SUPPORTED_GRANT_TYPES: &[&str] = &[
+    GRANT_AUTHORIZATION_CODE,
+    GRANT_REFRESH_TOKEN,
+    GRANT_DEVICE_CODE,
+];
+
+/// Scopes the Hub understands. Clients may be limited to a subset.
+pub const SUPPORTED_SCOPES: &[&str] = &["openid", "profile", "email", "pods", "offline_access"];
+
+/// A registered OAuth/OIDC client.
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = oauth_clients)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct OAuthClient {
+    pub id: String,
+    pub owner_id: Option<String>,
+    pub name: String,
+    pub client_type: String,
+    /// SHA-256 of the secret, hex-encoded. `None` for public clients.
+    pub client_secret_hash: Option<String>,
+    pub redirect_uris: Vec<String>,
+    pub allowed_scopes: Vec<String>,
+    pub grant_types: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Bot user this client authenticates as (`client_credentials`).
+    pub bot_user_id: Option<String>,
+}
+
+impl OAuthClient {
+    pub fn is_confidential(&self) -> bool {
+        self.client_type == CLIENT_TYPE_CONFIDENTIAL
+    }
+
+    pub fn is_bot(&self) -> bool {
+        self.bot_user_id.is_some()
+    }
+
+    /// Redirect URIs must match a registered one exactly.
+    pub fn allows_redirect_uri(&self, redirect_uri: &str) -> bool {
+        self.redirect_uris.iter().any(|u| u == redirect_uri)
+    }
+
+    pub fn allows_grant(&self, grant_type: &str) -> bool {
+        self.grant_types.iter().any(|g| g == grant_type)
+    }
+
+    pub fn allows_scopes(&self, scopes: &[String]) -> bool {
+        scopes.iter().all(|s| self.allowed_scopes.contains(s))
+    }
+}
+
+/// Insertable struct for registering a new client.
+#[derive(Debug, Insertable)]
+#[diesel(table_name = oauth_clients)]
+pub struct NewOAuthClient {
+    pub id: String,
+    pub owner_id: Option<String>,
+    pub name: String,
+    pub client_type: String,
+    pub client_secret_hash: Option<String>,
+    pub redirect_uris: Vec<String>,
+    pub allowed_scopes: Vec<String>,
+    pub grant_types: Vec<String>,
+    pub bot_user_id: Option<String>,
+}
+
+/// A client as shown to its owner. The secret is never returned after
+/// creation or rotation.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct OAuthClientResponse {
+    pub client_id: String,
+    pub name: String,
+    /// `public` or `confidential`.
+    pub client_type: String,
+    pub redirect_uris: Vec<String>,
+    pub allowed_scopes: Vec<String>,
+    pub grant_types: Vec<String>,
+    /// The bot this client belongs to, if any.
+    pub bot_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<OAuthClient> for OAuthClientResponse {
+    fn from(c: OAuthClient) -> Self {
+        Self {
+            client_id: c.id,
+            name: c.name,
+            client_type: c.client_type,
+            redirect_uris: c.redirect_uris,
+            allowed_scopes: c.allowed_scopes,
+            grant_types: c.grant_types,
+            bot_id: c.bot_user_id,
+            created_at: c.created_at,
+            updated_at: c.updated_at,
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::db::schema::passkeys;
+
+/// A registered WebAuthn credential.
+#[derive(Debug, Queryable, Selectable)]
+#[diesel(table_name = passkeys)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Passkey {
+    pub id: String,
+    pub user_id: String,
+    pub credential_id: Vec<u8>,
+    /// COSE-encoded public key, as returned by the authenticator.
+    pub public_key: Vec<u8>,
+    pub sign_count: i64,
+    pub name: String,
+    pub transports: Option<Vec<String>>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+/// Insertable struct for storing a newly registered passkey.
+#[derive(Debug, Insertable)]
+#[diesel(table_name = passkeys)]
+pub struct NewPasskey {
+    pub id: String,
+    pub user_id: String,
+    pub credential_id: Vec<u8>,
+    pub public_key: Vec<u8>,
+    pub sign_count: i64,
+    pub name: String,
+    pub transports: Option<Vec<String>>,
+}
+
+/// A passkey as shown to its owner. Key material is never returned.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PasskeyResponse {
+    pub id: String,
+    pub name: String,
+    pub transports: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+impl From<Passkey> for PasskeyResponse {
+    fn from(p: Passkey) -> Self {
+        Self {
+            id: p.id,
+            name: p.name,
+            transports: p.transports.unwrap_or_default(),
+            created_at: p.created_at,
+            last_used_at: p.last_used_at,
+        }
+    }
+}
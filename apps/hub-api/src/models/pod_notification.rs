@@ -0,0 +1,33 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+
+use crate::db::schema::pod_notifications;
+
+/// An event queued for delivery to a Pod.
+#[derive(Debug, Queryable, Selectable)]
+#[diesel(table_name = pod_notifications)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct PodNotification {
+    pub id: String,
+    pub pod_id: String,
+    /// Event name, e.g. `user.deleted`.
+    pub event: String,
+    /// The user the event is about.
+    pub user_id: String,
+    /// Failed deliveries so far.
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_error: Option<String>,
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Insertable struct for queueing an event.
+#[derive(Debug, Insertable)]
+#[diesel(table_name = pod_notifications)]
+pub struct NewPodNotification {
+    pub id: String,
+    pub pod_id: String,
+    pub event: String,
+    pub user_id: String,
+}
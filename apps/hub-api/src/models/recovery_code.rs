@@ -0,0 +1,25 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+
+use crate::db::schema::mfa_recovery_codes;
+
+/// A single-use MFA recovery code. Only the SHA-256 hash is stored.
+#[derive(Debug, Queryable, Selectable)]
+#[diesel(table_name = mfa_recovery_codes)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct RecoveryCode {
+    pub id: String,
+    pub user_id: String,
+    pub code_hash: String,
+    pub used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Insertable struct for storing a new recovery code.
+#[derive(Debug, Insertable)]
+#[diesel(table_name = mfa_recovery_codes)]
+pub struct NewRecoveryCode {
+    pub id: String,
+    pub user_id: String,
+    pub code_hash: String,
+}
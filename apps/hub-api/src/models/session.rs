@@ -1,11 +1,13 @@
 use chrono::{DateTime, Utc};
 use diesel::prelude::*;
+use ipnet::IpNet;
+use serde::Serialize;
+use utoipa::ToSchema;
 
 use crate::db::schema::sessions;
 
 /// Full session row from the database.
 ///
-/// Note: `ip_address` (Inet) is omitted and handled separately if needed.
 /// Using `Selectable` so `.select(Session::as_select())` only queries listed columns.
 #[derive(Debug, Queryable, Selectable)]
 #[diesel(table_name = sessions)]
@@ -14,22 +16,64 @@ pub struct Session {
     pub id: String,
     pub user_id: String,
     pub refresh_token: String,
+    pub ip_address: Option<IpNet>,
     pub user_agent: Option<String>,
     pub last_active_at: DateTime<Utc>,
     pub expires_at: DateTime<Utc>,
     pub revoked: bool,
     pub created_at: DateTime<Utc>,
+    /// OAuth client the session was issued to.
+    pub client_id: Option<String>,
+    /// ID of the first session in this session's rotation chain.
+    pub family_id: String,
+    /// Scopes granted at sign-in, kept across refreshes.
+    pub scopes: Vec<String>,
+    /// When the refresh token was exchanged for the next one in the family.
+    pub rotated_at: Option<DateTime<Utc>>,
 }
 
 /// Insertable struct for creating a new session.
-///
-/// `ip_address` omitted — defaults to NULL.
 #[derive(Debug, Insertable)]
 #[diesel(table_name = sessions)]
 pub struct NewSession {
     pub id: String,
     pub user_id: String,
     pub refresh_token: String,
+    pub ip_address: Option<IpNet>,
+    pub user_agent: Option<String>,
+    pub client_id: Option<String>,
+    pub expires_at: DateTime<Utc>,
+    pub family_id: String,
+    pub scopes: Vec<String>,
+}
+
+/// A signed-in device as shown to its owner (no refresh token).
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SessionResponse {
+    pub id: String,
+    /// Address the session was last refreshed from.
+    pub ip_address: Option<String>,
     pub user_agent: Option<String>,
+    /// OAuth client the session was issued to.
+    pub client_id: Option<String>,
+    /// Whether this is the session the request was made with.
+    pub current: bool,
+    pub last_active_at: DateTime<Utc>,
     pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl SessionResponse {
+    pub fn from_session(session: Session, current_session_id: Option<&str>) -> Self {
+        Self {
+            current: current_session_id == Some(session.id.as_str()),
+            ip_address: session.ip_address.map(|ip| ip.addr().to_string()),
+            id: session.id,
+            user_agent: session.user_agent,
+            client_id: session.client_id,
+            last_active_at: session.last_active_at,
+            expires_at: session.expires_at,
+            created_at: session.created_at,
+        }
+    }
 }
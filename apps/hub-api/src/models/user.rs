@@ -5,6 +5,16 @@ use utoipa::ToSchema;
 
 use crate::db::schema::users;
 
+// `users.flags` bits. Sent to Pods by name in the SIA `flags` claim.
+pub const USER_FLAG_STAFF: i64 = 1 << 0;
+pub const USER_FLAG_VERIFIED: i64 = 1 << 1;
+/// Automated account owned by a human user (RFC §17.6).
+pub const USER_FLAG_BOT: i64 = 1 << 2;
+
+/// `users.status` of an account in good standing. Otherwise it holds the
+/// status of the account's `user_bans` row.
+pub const USER_STATUS_ACTIVE: &str = "active";
+
 /// Full user row from the database.
 #[derive(Debug, Queryable, Selectable, Serialize)]
 #[diesel(table_name = users)]
@@ -23,6 +33,25 @@ pub struct User {
     pub status: String,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub mfa_enabled: bool,
+    /// Base32 TOTP secret. Set during enrollment, before `mfa_enabled`.
+    #[serde(skip)]
+    pub mfa_secret: Option<String>,
+    /// Owning user, for bots.
+    pub bot_owner_id: Option<String>,
+    /// When the account will be purged, if the user asked to delete it.
+    pub deletion_scheduled_at: Option<DateTime<Utc>>,
+    pub banner_url: Option<String>,
+}
+
+impl User {
+    pub fn is_staff(&self) -> bool {
+        self.flags & USER_FLAG_STAFF != 0
+    }
+
+    pub fn is_bot(&self) -> bool {
+        self.flags & USER_FLAG_BOT != 0
+    }
 }
 
 /// Insertable struct for creating a new user.
@@ -34,7 +63,10 @@ pub struct NewUser {
     pub username_lower: String,
     pub display_name: String,
     pub email: Option<String>,
-    pub password_hash: String,
+    /// `None` for accounts that can't sign in with a password (bots).
+    pub password_hash: Option<String>,
+    pub flags: i64,
+    pub bot_owner_id: Option<String>,
 }
 
 /// Public-facing user response (no sensitive fields).
@@ -46,8 +78,12 @@ pub struct UserResponse {
     pub email: Option<String>,
     pub email_verified: bool,
     pub avatar_url: Option<String>,
+    pub banner_url: Option<String>,
     pub flags: i64,
     pub status: String,
+    pub mfa_enabled: bool,
+    /// Set while the account is waiting out its deletion grace period.
+    pub deletion_scheduled_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -61,8 +97,11 @@ impl From<User> for UserResponse {
             email: u.email,
             email_verified: u.email_verified,
             avatar_url: u.avatar_url,
+            banner_url: u.banner_url,
             flags: u.flags,
             status: u.status,
+            mfa_enabled: u.mfa_enabled,
+            deletion_scheduled_at: u.deletion_scheduled_at,
             created_at: u.created_at,
             updated_at: u.updated_at,
         }
@@ -76,6 +115,7 @@ pub struct PublicUserResponse {
     pub username: String,
     pub display_name: String,
     pub avatar_url: Option<String>,
+    pub banner_url: Option<String>,
     pub flags: i64,
     pub created_at: DateTime<Utc>,
 }
@@ -87,6 +127,7 @@ impl From<User> for PublicUserResponse {
             username: u.username,
             display_name: u.display_name,
             avatar_url: u.avatar_url,
+            banner_url: u.banner_url,
             flags: u.flags,
             created_at: u.created_at,
         }
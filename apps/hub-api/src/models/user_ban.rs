@@ -0,0 +1,47 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::db::schema::user_bans;
+
+/// Banned until lifted by staff, or until `expires_at` if set.
+pub const BAN_STATUS_BANNED: &str = "banned";
+/// Banned until `expires_at`, which is required.
+pub const BAN_STATUS_SUSPENDED: &str = "suspended";
+
+/// A Hub-level ban or suspension (RFC §14.4).
+#[derive(Debug, Queryable, Selectable, Serialize, ToSchema)]
+#[diesel(table_name = user_bans)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct UserBan {
+    pub user_id: String,
+    /// `banned` or `suspended`.
+    pub status: String,
+    pub reason: String,
+    /// The staff member who issued it.
+    pub banned_by: Option<String>,
+    /// When the ban lifts on its own, if ever.
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl UserBan {
+    pub fn is_expired(&self) -> bool {
+        self.expires_at.is_some_and(|at| at <= Utc::now())
+    }
+}
+
+/// Insertable struct for issuing a ban. Re-banning a user replaces their
+/// current ban.
+#[derive(Debug, Insertable, AsChangeset)]
+#[diesel(table_name = user_bans)]
+#[diesel(treat_none_as_null = true)]
+pub struct NewUserBan {
+    pub user_id: String,
+    pub status: String,
+    pub reason: String,
+    pub banned_by: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
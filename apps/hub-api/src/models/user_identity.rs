@@ -0,0 +1,54 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::db::schema::user_identities;
+
+/// An account at an upstream identity provider, linked to a Hub user.
+#[derive(Debug, Queryable, Selectable)]
+#[diesel(table_name = user_identities)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct UserIdentity {
+    pub id: String,
+    pub user_id: String,
+    /// ID of the provider in `UPSTREAM_IDPS`.
+    pub provider: String,
+    /// The provider's stable identifier for the account.
+    pub subject: String,
+    /// Email the provider reported when the identity was linked.
+    pub email: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+/// Insertable struct for linking a new identity.
+#[derive(Debug, Insertable)]
+#[diesel(table_name = user_identities)]
+pub struct NewUserIdentity {
+    pub id: String,
+    pub user_id: String,
+    pub provider: String,
+    pub subject: String,
+    pub email: Option<String>,
+}
+
+/// A linked identity as shown to its owner.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UserIdentityResponse {
+    pub provider: String,
+    pub email: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+impl From<UserIdentity> for UserIdentityResponse {
+    fn from(i: UserIdentity) -> Self {
+        Self {
+            provider: i.provider,
+            email: i.email,
+            created_at: i.created_at,
+            last_used_at: i.last_used_at,
+        }
+    }
+}
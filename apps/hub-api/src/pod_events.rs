@@ -0,0 +1,188 @@
+//! Signed events the Hub pushes to Pods (RFC §18.1), such as `user.deleted`
+//! and `user.banned`.
+//!
+//! Events are written to the `pod_notifications` outbox first and delivered
+//! by a background job, so a Pod that is down gets them once it's back. Each
+//! delivery is a JWT signed with the Hub's active key, which Pods verify
+//! against the same JWKS they use for SIAs.
+
+use std::time::Duration;
+
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use jsonwebtoken::{Algorithm, Header};
+use serde::{Deserialize, Serialize};
+
+use crate::auth::keys::SigningKeys;
+use crate::db::schema::{pod_notifications, pods, user_pod_bookmarks};
+use crate::error::ApiError;
+use crate::models::pod_notification::{NewPodNotification, PodNotification};
+use crate::AppState;
+
+/// The user deleted their Hub account; the Pod must anonymize them.
+pub const EVENT_USER_DELETED: &str = "user.deleted";
+
+/// Staff banned or suspended the user; the Pod must end their sessions.
+pub const EVENT_USER_BANNED: &str = "user.banned";
+
+/// Lifetime of a signed event. Each retry signs a fresh one.
+pub const POD_EVENT_TTL_SECS: i64 = 300;
+
+/// Deliveries are abandoned after this many failures (about two days of
+/// backoff).
+pub const MAX_DELIVERY_ATTEMPTS: i32 = 12;
+
+/// Events delivered per run of [`deliver_pending`].
+const DELIVERY_BATCH: i64 = 100;
+
+/// Timeout for each delivery request.
+const DELIVERY_TIMEOUT_SECS: u64 = 10;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PodEventClaims {
+    pub iss: String,
+    /// The user the event is about.
+    pub sub: String,
+    /// The receiving Pod's ID.
+    pub aud: String,
+    pub iat: i64,
+    pub exp: i64,
+    /// The notification ID. Stable across retries, so Pods can skip repeats.
+    pub jti: String,
+    pub event: String,
+}
+
+/// Sign a queued notification for delivery.
+pub fn mint_pod_event(
+    keys: &SigningKeys,
+    issuer: &str,
+    notification: &PodNotification,
+) -> Result<String, ApiError> {
+    let now = Utc::now();
+    let claims = PodEventClaims {
+        iss: issuer.to_string(),
+        sub: notification.user_id.clone(),
+        aud: notification.pod_id.clone(),
+        iat: now.timestamp(),
+        exp: (now + chrono::Duration::seconds(POD_EVENT_TTL_SECS)).timestamp(),
+        jti: notification.id.clone(),
+        event: notification.event.clone(),
+    };
+
+    let mut header = Header::new(Algorithm::EdDSA);
+    header.kid = Some(keys.kid.clone());
+    header.typ = Some("voxora-event+jwt".to_string());
+
+    jsonwebtoken::encode(&header, &claims, &keys.encoding).map_err(|e| {
+        tracing::error!(?e, "failed to sign pod event");
+        ApiError::internal("Pod event signing failed")
+    })
+}
+
+/// Queue `event` about a user for every Pod they have signed in to. Returns
+/// how many events were queued.
+pub async fn enqueue_user_event(
+    conn: &mut AsyncPgConnection,
+    user_id: &str,
+    event: &str,
+) -> Result<usize, ApiError> {
+    let pod_ids: Vec<String> = user_pod_bookmarks::table
+        .filter(user_pod_bookmarks::user_id.eq(user_id))
+        .select(user_pod_bookmarks::pod_id)
+        .load(conn)
+        .await
+        .map_err(ApiError::from)?;
+
+    let rows: Vec<NewPodNotification> = pod_ids
+        .into_iter()
+        .map(|pod_id| NewPodNotification {
+            id: voxora_common::id::prefixed_ulid(voxora_common::id::prefix::POD_NOTIFICATION),
+            pod_id,
+            event: event.to_string(),
+            user_id: user_id.to_string(),
+        })
+        .collect();
+    diesel::insert_into(pod_notifications::table)
+        .values(&rows)
+        .execute(conn)
+        .await
+        .map_err(ApiError::from)
+}
+
+/// Delay before retrying after `attempts` failures: one minute, doubling, at
+/// most a day.
+fn retry_delay(attempts: i32) -> chrono::Duration {
+    chrono::Duration::minutes(1i64 << attempts.clamp(0, 10)).min(chrono::Duration::days(1))
+}
+
+/// Deliver queued events that are due. Failures are rescheduled with
+/// backoff. Returns how many were delivered.
+pub async fn deliver_pending(state: &AppState) -> Result<usize, ApiError> {
+    let mut conn = state.db.get().await?;
+    let due: Vec<(PodNotification, String)> = pod_notifications::table
+        .inner_join(pods::table)
+        .filter(pod_notifications::delivered_at.is_null())
+        .filter(pod_notifications::next_attempt_at.le(Utc::now()))
+        .filter(pod_notifications::attempts.lt(MAX_DELIVERY_ATTEMPTS))
+        .order(pod_notifications::next_attempt_at.asc())
+        .limit(DELIVERY_BATCH)
+        .select((PodNotification::as_select(), pods::url))
+        .load(&mut conn)
+        .await
+        .map_err(ApiError::from)?;
+    if due.is_empty() {
+        return Ok(0);
+    }
+
+    let http = reqwest::Client::builder()
+        .timeout(Duration::from_secs(DELIVERY_TIMEOUT_SECS))
+        .build()
+        .map_err(|_| ApiError::internal("HTTP client setup failed"))?;
+    let keys = state.keys.active();
+
+    let mut delivered = 0;
+    for (notification, pod_url) in due {
+        let token = mint_pod_event(&keys, &state.config.hub_domain, &notification)?;
+        let result = http
+            .post(format!(
+                "{}/api/v1/hub/events",
+                pod_url.trim_end_matches('/')
+            ))
+            .json(&serde_json::json!({ "token": token }))
+            .send()
+            .await
+            .and_then(|r| r.error_for_status());
+
+        match result {
+            Ok(_) => {
+                diesel::update(pod_notifications::table.find(&notification.id))
+                    .set(pod_notifications::delivered_at.eq(Utc::now()))
+                    .execute(&mut conn)
+                    .await
+                    .map_err(ApiError::from)?;
+                delivered += 1;
+            }
+            Err(e) => {
+                let attempts = notification.attempts + 1;
+                tracing::warn!(
+                    notification_id = %notification.id,
+                    pod_id = %notification.pod_id,
+                    attempts,
+                    error = %e,
+                    "pod event delivery failed"
+                );
+                diesel::update(pod_notifications::table.find(&notification.id))
+                    .set((
+                        pod_notifications::attempts.eq(attempts),
+                        pod_notifications::next_attempt_at.eq(Utc::now() + retry_delay(attempts)),
+                        pod_notifications::last_error.eq(e.to_string()),
+                    ))
+                    .execute(&mut conn)
+                    .await
+                    .map_err(ApiError::from)?;
+            }
+        }
+    }
+    Ok(delivered)
+}
@@ -0,0 +1,72 @@
+//! Hub notification relay (RFC §16.5.2).
+//!
+//! Pods push envelopes saying a user has unread activity in one of their
+//! channels, and the Hub forwards them to that user's Hub WebSocket
+//! connections so clients can badge Pods they aren't connected to. Envelopes
+//! carry only where the activity is and how much of it there is: never
+//! message content, authors or timestamps.
+//!
+//! Delivery is in-process: every connection subscribes to one broadcast
+//! channel and keeps the envelopes addressed to its user. Nothing is stored,
+//! so envelopes for users who aren't connected are dropped.
+
+use std::sync::Arc;
+
+use serde::Serialize;
+use tokio::sync::broadcast;
+use utoipa::ToSchema;
+
+/// Capacity of the broadcast channel. Connections that fall behind skip
+/// envelopes; the next one for the same channel carries the current count.
+const RELAY_CAPACITY: usize = 4096;
+
+/// What a client receives: unread activity in a Pod channel.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct NotificationEnvelope {
+    pub pod_id: String,
+    pub community_id: String,
+    pub channel_id: String,
+    /// Unread messages in the channel for this user.
+    pub unread_count: i64,
+    /// Whether any of them mention the user.
+    pub has_mention: bool,
+}
+
+/// An envelope and the user it's for.
+#[derive(Debug, Clone)]
+pub struct RelayedNotification {
+    pub user_id: String,
+    pub envelope: NotificationEnvelope,
+}
+
+/// Routes envelopes from Pods to connected users. Cloneable — store in
+/// AppState.
+#[derive(Clone)]
+pub struct NotificationRelay {
+    sender: broadcast::Sender<Arc<RelayedNotification>>,
+}
+
+impl Default for NotificationRelay {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NotificationRelay {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(RELAY_CAPACITY);
+        Self { sender }
+    }
+
+    /// Subscribe to every relayed envelope. Each connection filters by its
+    /// own user.
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<RelayedNotification>> {
+        self.sender.subscribe()
+    }
+
+    /// Forward an envelope to `user_id`'s connections, if any.
+    pub fn publish(&self, notification: RelayedNotification) {
+        // send() returns Err if nobody is connected — that's fine.
+        let _ = self.sender.send(Arc::new(notification));
+    }
+}
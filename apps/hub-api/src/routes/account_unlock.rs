@@ -0,0 +1,62 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::routing::post;
+use axum::{Json, Router};
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+use crate::auth::events::{self, AuthEvent};
+use crate::auth::middleware::ClientInfo;
+use crate::auth::throttle;
+use crate::error::{ApiError, ApiErrorBody};
+use crate::AppState;
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/account-unlock", post(unlock_account))
+}
+
+// =========================================================================
+// POST /api/v1/account-unlock — Lift a sign-in lockout
+// =========================================================================
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UnlockAccountRequest {
+    /// Token from the lockout email.
+    pub token: String,
+}
+
+/// `POST /api/v1/account-unlock` — Lift the lock placed on an account's
+/// password sign-in after repeated failures, using the link emailed to its
+/// owner.
+#[utoipa::path(
+    post,
+    path = "/api/v1/account-unlock",
+    tag = "Users",
+    request_body = UnlockAccountRequest,
+    responses(
+        (status = 204, description = "Account unlocked"),
+        (status = 400, description = "Invalid or expired token", body = ApiErrorBody),
+    ),
+)]
+pub async fn unlock_account(
+    State(state): State<AppState>,
+    client: ClientInfo,
+    Json(body): Json<UnlockAccountRequest>,
+) -> Result<StatusCode, ApiError> {
+    let user_id = throttle::consume_unlock_token(state.kv.as_ref(), &body.token)
+        .await?
+        .ok_or_else(|| ApiError::bad_request("Invalid or expired unlock link"))?;
+
+    events::record(
+        &state.db,
+        &client,
+        AuthEvent::new(events::ACCOUNT_UNLOCKED)
+            .user(&user_id)
+            .details(serde_json::json!({ "method": "email" })),
+    )
+    .await;
+
+    tracing::info!(%user_id, "account unlocked from lockout email");
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,1163 @@
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::routing::{get, patch, post};
+use axum::{Json, Router};
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use scoped_futures::ScopedFutureExt;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::auth::bans;
+use crate::auth::events::{self, AuthEvent};
+use crate::auth::keys::{KeyStatus, PublishedKey, RETIRED_KEY_TTL_SECS};
+use crate::auth::middleware::{AuthUser, ClientInfo};
+use crate::auth::throttle;
+use crate::db::schema::{admin_audit_log, pods, sessions, user_bans, users};
+use crate::error::{ApiError, ApiErrorBody, FieldError};
+use crate::models::admin_audit_log::{self as audit, AdminAuditLogEntry};
+use crate::models::pod::{Pod, PodResponse};
+use crate::models::session::{Session, SessionResponse};
+use crate::models::user::{User, UserResponse, USER_FLAG_STAFF, USER_FLAG_VERIFIED};
+use crate::models::user_ban::{NewUserBan, UserBan, BAN_STATUS_BANNED, BAN_STATUS_SUSPENDED};
+use crate::pod_events::{self, EVENT_USER_BANNED};
+use crate::AppState;
+
+use super::pods::ListPodsResponse;
+use super::sessions::SessionListResponse;
+
+/// Longest ban reason accepted.
+const MAX_BAN_REASON_LEN: usize = 1000;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/admin/signing-keys", get(list_signing_keys))
+        .route("/admin/signing-keys/rotate", post(rotate_signing_keys))
+        .route("/admin/users", get(search_users))
+        .route("/admin/users/{user_id}", get(get_user))
+        .route("/admin/users/{user_id}/sessions", get(list_user_sessions))
+        .route("/admin/users/{user_id}/logout", post(logout_user))
+        .route("/admin/users/{user_id}/unlock", post(unlock_user))
+        .route(
+            "/admin/users/{user_id}/password-reset",
+            post(force_password_reset),
+        )
+        .route("/admin/users/{user_id}/flags", patch(update_user_flags))
+        .route(
+            "/admin/users/{user_id}/ban",
+            get(get_user_ban).put(ban_user).delete(unban_user),
+        )
+        .route("/admin/pods", get(list_all_pods))
+        .route("/admin/pods/{pod_id}", patch(update_pod))
+        .route("/admin/audit-log", get(list_admin_audit_log))
+}
+
+/// Load the caller and make sure they are Voxora staff.
+pub(crate) async fn require_staff(state: &AppState, auth: &AuthUser) -> Result<User, ApiError> {
+    let mut conn = state.db.get().await?;
+    let user: User = users::table
+        .find(&auth.user_id)
+        .select(User::as_select())
+        .first(&mut conn)
+        .await
+        .map_err(ApiError::from)?;
+    if !user.is_staff() {
+        return Err(ApiError::forbidden("Staff only"));
+    }
+    Ok(user)
+}
+
+/// A signing key in the Hub's keyring.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SigningKeyResponse {
+    pub kid: String,
+    pub status: KeyStatus,
+    pub retired_at: Option<DateTime<Utc>>,
+    /// When a retired key drops out of JWKS.
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl From<&PublishedKey> for SigningKeyResponse {
+    fn from(key: &PublishedKey) -> Self {
+        let at = |secs: i64| DateTime::from_timestamp(secs, 0);
+        Self {
+            kid: key.keys.kid.clone(),
+            status: key.status,
+            retired_at: key.retired_at.and_then(at),
+            expires_at: key.retired_at.and_then(|r| at(r + RETIRED_KEY_TTL_SECS)),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SigningKeyListResponse {
+    pub data: Vec<SigningKeyResponse>,
+}
+
+fn key_list(state: &AppState) -> SigningKeyListResponse {
+    SigningKeyListResponse {
+        data: state
+            .keys
+            .published()
+            .iter()
+            .map(SigningKeyResponse::from)
+            .collect(),
+    }
+}
+
+// =========================================================================
+// GET /api/v1/admin/signing-keys — List signing keys
+// =========================================================================
+
+/// `GET /api/v1/admin/signing-keys` — List the keys published in JWKS.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/signing-keys",
+    tag = "Admin",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Signing keys", body = SigningKeyListResponse),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 403, description = "Not staff", body = ApiErrorBody),
+    ),
+)]
+pub async fn list_signing_keys(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Json<SigningKeyListResponse>, ApiError> {
+    require_staff(&state, &auth).await?;
+    Ok(Json(key_list(&state)))
+}
+
+// =========================================================================
+// POST /api/v1/admin/signing-keys/rotate — Rotate signing keys
+// =========================================================================
+
+/// `POST /api/v1/admin/signing-keys/rotate` — Activate the next key (already
+/// in JWKS), retire the active one, and publish a new next key.
+///
+/// Retired keys stay in JWKS until every token they signed has expired, so
+/// outstanding SIAs and ID tokens keep verifying.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/signing-keys/rotate",
+    tag = "Admin",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Keys rotated", body = SigningKeyListResponse),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 403, description = "Not staff", body = ApiErrorBody),
+        (status = 409, description = "Keys are derived from a seed and can't rotate", body = ApiErrorBody),
+    ),
+)]
+pub async fn rotate_signing_keys(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Json<SigningKeyListResponse>, ApiError> {
+    let admin = require_staff(&state, &auth).await?;
+    state.keys.rotate().await?;
+    let active_kid = state.keys.active().kid.clone();
+    let mut conn = state.db.get().await?;
+    audit::log(
+        &mut conn,
+        &admin.id,
+        "signing_keys.rotate",
+        Some("signing_keys"),
+        Some(&active_kid),
+        None,
+        None,
+    )
+    .await?;
+    tracing::info!(user_id = %admin.id, "signing keys rotated by staff");
+    Ok(Json(key_list(&state)))
+}
+
+// =========================================================================
+// PUT /api/v1/admin/users/{user_id}/ban — Ban or suspend a user
+// =========================================================================
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BanUserRequest {
+    /// `banned` or `suspended`.
+    pub status: String,
+    /// Shown to the user when they try to sign in.
+    pub reason: String,
+    /// When the ban lifts. Required for suspensions; bans without one last
+    /// until lifted by staff.
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// `PUT /api/v1/admin/users/{user_id}/ban` — Ban or suspend a user across
+/// the whole network (RFC §14.4), replacing any current ban.
+///
+/// The user and their bots are signed out of the Hub, can't get SIAs or
+/// tokens while the ban lasts, and every Pod they use is told to end their
+/// sessions.
+#[utoipa::path(
+    put,
+    path = "/api/v1/admin/users/{user_id}/ban",
+    tag = "Admin",
+    security(("bearer" = [])),
+    params(("user_id" = String, Path, description = "User ID")),
+    request_body = BanUserRequest,
+    responses(
+        (status = 200, description = "User banned", body = UserBan),
+        (status = 400, description = "Validation error", body = ApiErrorBody),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 403, description = "Not staff, or the target is staff", body = ApiErrorBody),
+        (status = 404, description = "User not found", body = ApiErrorBody),
+    ),
+)]
+pub async fn ban_user(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(user_id): Path<String>,
+    Json(body): Json<BanUserRequest>,
+) -> Result<Json<UserBan>, ApiError> {
+    let admin = require_staff(&state, &auth).await?;
+
+    let mut errors = Vec::new();
+    if body.status != BAN_STATUS_BANNED && body.status != BAN_STATUS_SUSPENDED {
+        errors.push(FieldError {
+            field: "status".to_string(),
+            message: "Must be banned or suspended".to_string(),
+        });
+    }
+    let reason = body.reason.trim();
+    if reason.is_empty() || reason.chars().count() > MAX_BAN_REASON_LEN {
+        errors.push(FieldError {
+            field: "reason".to_string(),
+            message: format!("Must be 1-{MAX_BAN_REASON_LEN} characters"),
+        });
+    }
+    match body.expires_at {
+        Some(at) if at <= Utc::now() => errors.push(FieldError {
+            field: "expires_at".to_string(),
+            message: "Must be in the future".to_string(),
+        }),
+        None if body.status == BAN_STATUS_SUSPENDED => errors.push(FieldError {
+            field: "expires_at".to_string(),
+            message: "Suspensions need an end date".to_string(),
+        }),
+        _ => {}
+    }
+    if !errors.is_empty() {
+        return Err(ApiError::validation(errors));
+    }
+
+    let mut conn = state.db.get().await?;
+    let target: User = users::table
+        .find(&user_id)
+        .select(User::as_select())
+        .first(&mut conn)
+        .await
+        .optional()
+        .map_err(ApiError::from)?
+        .ok_or_else(|| ApiError::not_found("User not found"))?;
+    if target.is_staff() {
+        return Err(ApiError::forbidden("Staff accounts can't be banned"));
+    }
+
+    let new_ban = NewUserBan {
+        user_id: target.id.clone(),
+        status: body.status.clone(),
+        reason: reason.to_string(),
+        banned_by: Some(admin.id.clone()),
+        expires_at: body.expires_at,
+        created_at: Utc::now(),
+    };
+    let (admin, target) = (&admin, &target);
+    let (ban, revoked) = conn
+        .transaction::<_, ApiError, _>(|conn| {
+            async move {
+                let ban: UserBan = diesel::insert_into(user_bans::table)
+                    .values(&new_ban)
+                    .on_conflict(user_bans::user_id)
+                    .do_update()
+                    .set(&new_ban)
+                    .returning(UserBan::as_returning())
+                    .get_result(conn)
+                    .await?;
+                diesel::update(users::table.find(&target.id))
+                    .set(users::status.eq(&ban.status))
+                    .execute(conn)
+                    .await?;
+
+                // Bots act for their owner, so they go dark with them.
+                let bots: Vec<String> = users::table
+                    .filter(users::bot_owner_id.eq(&target.id))
+                    .select(users::id)
+                    .load(conn)
+                    .await?;
+                let mut revoked = Vec::new();
+                for id in bots.iter().chain(std::iter::once(&target.id)) {
+                    revoked.extend(super::sessions::revoke_session_rows(conn, id, None).await?);
+                    pod_events::enqueue_user_event(conn, id, EVENT_USER_BANNED).await?;
+                }
+
+                audit::log(
+                    conn,
+                    &admin.id,
+                    if ban.status == BAN_STATUS_SUSPENDED {
+                        "user.suspend"
+                    } else {
+                        "user.ban"
+                    },
+                    Some("user"),
+                    Some(&target.id),
+                    Some(serde_json::json!({ "expires_at": ban.expires_at })),
+                    Some(&ban.reason),
+                )
+                .await?;
+                Ok((ban, revoked))
+            }
+            .scope_boxed()
+        })
+        .await?;
+    super::sessions::delete_access_tokens(state.kv.as_ref(), &revoked).await?;
+    tracing::info!(
+        user_id = %target.id,
+        staff_id = %admin.id,
+        status = %ban.status,
+        expires_at = ?ban.expires_at,
+        "user banned by staff"
+    );
+    Ok(Json(ban))
+}
+
+// =========================================================================
+// GET /api/v1/admin/users/{user_id}/ban — Get a user's ban
+// =========================================================================
+
+/// `GET /api/v1/admin/users/{user_id}/ban` — Get a user's current ban.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/users/{user_id}/ban",
+    tag = "Admin",
+    security(("bearer" = [])),
+    params(("user_id" = String, Path, description = "User ID")),
+    responses(
+        (status = 200, description = "Current ban", body = UserBan),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 403, description = "Not staff", body = ApiErrorBody),
+        (status = 404, description = "User is not banned", body = ApiErrorBody),
+    ),
+)]
+pub async fn get_user_ban(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(user_id): Path<String>,
+) -> Result<Json<UserBan>, ApiError> {
+    require_staff(&state, &auth).await?;
+
+    let mut conn = state.db.get().await?;
+    let ban: UserBan = user_bans::table
+        .find(&user_id)
+        .select(UserBan::as_select())
+        .first(&mut conn)
+        .await
+        .optional()
+        .map_err(ApiError::from)?
+        .filter(|ban| !ban.is_expired())
+        .ok_or_else(|| ApiError::not_found("User is not banned"))?;
+    Ok(Json(ban))
+}
+
+// =========================================================================
+// DELETE /api/v1/admin/users/{user_id}/ban — Lift a ban
+// =========================================================================
+
+/// `DELETE /api/v1/admin/users/{user_id}/ban` — Lift a user's ban. They can
+/// sign in again straight away; Pods let them back in with a fresh SIA.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/users/{user_id}/ban",
+    tag = "Admin",
+    security(("bearer" = [])),
+    params(("user_id" = String, Path, description = "User ID")),
+    responses(
+        (status = 204, description = "Ban lifted"),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 403, description = "Not staff", body = ApiErrorBody),
+        (status = 404, description = "User is not banned", body = ApiErrorBody),
+    ),
+)]
+pub async fn unban_user(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(user_id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    let admin = require_staff(&state, &auth).await?;
+    let (admin, user_id) = (&admin, &user_id);
+    let mut conn = state.db.get().await?;
+    conn.transaction::<_, ApiError, _>(|conn| {
+        async move {
+            if !bans::lift_ban(conn, user_id).await? {
+                return Err(ApiError::not_found("User is not banned"));
+            }
+            audit::log(
+                conn,
+                &admin.id,
+                "user.unban",
+                Some("user"),
+                Some(user_id),
+                None,
+                None,
+            )
+            .await
+        }
+        .scope_boxed()
+    })
+    .await?;
+    tracing::info!(%user_id, staff_id = %admin.id, "user ban lifted by staff");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Load any user by ID, for staff.
+async fn load_user(state: &AppState, user_id: &str) -> Result<User, ApiError> {
+    let mut conn = state.db.get().await?;
+    users::table
+        .find(user_id)
+        .select(User::as_select())
+        .first(&mut conn)
+        .await
+        .optional()
+        .map_err(ApiError::from)?
+        .ok_or_else(|| ApiError::not_found("User not found"))
+}
+
+/// Escape `%`, `_` and `\` so user input matches literally in `LIKE`.
+fn like_pattern(q: &str) -> String {
+    let escaped = q
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{escaped}%")
+}
+
+// =========================================================================
+// GET /api/v1/admin/users — Search users
+// =========================================================================
+
+#[derive(Debug, Deserialize)]
+pub struct SearchUsersParams {
+    pub q: Option<String>,
+    pub before: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminUserListResponse {
+    pub data: Vec<UserResponse>,
+    pub has_more: bool,
+}
+
+/// `GET /api/v1/admin/users` — Search users by ID, username or email,
+/// newest first.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/users",
+    tag = "Admin",
+    security(("bearer" = [])),
+    params(
+        ("q" = Option<String>, Query, description = "Exact user ID, or part of a username or email"),
+        ("before" = Option<String>, Query, description = "Cursor: user ID"),
+        ("limit" = Option<i64>, Query, description = "Number of users (1-100, default 50)"),
+    ),
+    responses(
+        (status = 200, description = "Matching users", body = AdminUserListResponse),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 403, description = "Not staff", body = ApiErrorBody),
+    ),
+)]
+pub async fn search_users(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Query(params): Query<SearchUsersParams>,
+) -> Result<Json<AdminUserListResponse>, ApiError> {
+    require_staff(&state, &auth).await?;
+    let limit = params.limit.unwrap_or(50).clamp(1, 100);
+
+    let mut query = users::table
+        .order(users::id.desc())
+        .limit(limit + 1)
+        .select(User::as_select())
+        .into_boxed();
+    if let Some(q) = params.q.as_deref().map(str::trim).filter(|q| !q.is_empty()) {
+        let pattern = like_pattern(&q.to_lowercase());
+        query = query.filter(
+            users::id
+                .eq(q.to_string())
+                .or(users::username_lower.like(pattern.clone()))
+                .or(users::email.like(pattern)),
+        );
+    }
+    if let Some(ref before) = params.before {
+        query = query.filter(users::id.lt(before.clone()));
+    }
+
+    let mut conn = state.db.get().await?;
+    let rows: Vec<User> = query.load(&mut conn).await.map_err(ApiError::from)?;
+
+    let has_more = rows.len() as i64 > limit;
+    let data = rows
+        .into_iter()
+        .take(limit as usize)
+        .map(UserResponse::from)
+        .collect();
+    Ok(Json(AdminUserListResponse { data, has_more }))
+}
+
+// =========================================================================
+// GET /api/v1/admin/users/{user_id} — Get a user
+// =========================================================================
+
+/// `GET /api/v1/admin/users/{user_id}` — Get any user's account.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/users/{user_id}",
+    tag = "Admin",
+    security(("bearer" = [])),
+    params(("user_id" = String, Path, description = "User ID")),
+    responses(
+        (status = 200, description = "User", body = UserResponse),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 403, description = "Not staff", body = ApiErrorBody),
+        (status = 404, description = "User not found", body = ApiErrorBody),
+    ),
+)]
+pub async fn get_user(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(user_id): Path<String>,
+) -> Result<Json<UserResponse>, ApiError> {
+    require_staff(&state, &auth).await?;
+    Ok(Json(UserResponse::from(load_user(&state, &user_id).await?)))
+}
+
+// =========================================================================
+// GET /api/v1/admin/users/{user_id}/sessions — List a user's sessions
+// =========================================================================
+
+/// `GET /api/v1/admin/users/{user_id}/sessions` — List a user's active
+/// sessions, most recently used first.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/users/{user_id}/sessions",
+    tag = "Admin",
+    security(("bearer" = [])),
+    params(("user_id" = String, Path, description = "User ID")),
+    responses(
+        (status = 200, description = "Active sessions", body = SessionListResponse),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 403, description = "Not staff", body = ApiErrorBody),
+        (status = 404, description = "User not found", body = ApiErrorBody),
+    ),
+)]
+pub async fn list_user_sessions(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(user_id): Path<String>,
+) -> Result<Json<SessionListResponse>, ApiError> {
+    require_staff(&state, &auth).await?;
+    let user = load_user(&state, &user_id).await?;
+
+    let mut conn = state.db.get().await?;
+    let rows: Vec<Session> = sessions::table
+        .filter(sessions::user_id.eq(&user.id))
+        .filter(sessions::revoked.eq(false))
+        .filter(sessions::expires_at.gt(Utc::now()))
+        .order(sessions::last_active_at.desc())
+        .select(Session::as_select())
+        .load(&mut conn)
+        .await
+        .map_err(ApiError::from)?;
+
+    let data = rows
+        .into_iter()
+        .map(|s| SessionResponse::from_session(s, None))
+        .collect();
+    Ok(Json(SessionListResponse { data }))
+}
+
+// =========================================================================
+// POST /api/v1/admin/users/{user_id}/logout — Sign a user out everywhere
+// =========================================================================
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminLogoutResponse {
+    /// How many active sessions were revoked.
+    pub revoked_sessions: usize,
+}
+
+/// `POST /api/v1/admin/users/{user_id}/logout` — Revoke all of a user's
+/// sessions. Their refresh and access tokens stop working immediately.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/users/{user_id}/logout",
+    tag = "Admin",
+    security(("bearer" = [])),
+    params(("user_id" = String, Path, description = "User ID")),
+    responses(
+        (status = 200, description = "User signed out", body = AdminLogoutResponse),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 403, description = "Not staff", body = ApiErrorBody),
+        (status = 404, description = "User not found", body = ApiErrorBody),
+    ),
+)]
+pub async fn logout_user(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(user_id): Path<String>,
+) -> Result<Json<AdminLogoutResponse>, ApiError> {
+    let admin = require_staff(&state, &auth).await?;
+    let user = load_user(&state, &user_id).await?;
+
+    let (admin, user) = (&admin, &user);
+    let mut conn = state.db.get().await?;
+    let revoked = conn
+        .transaction::<_, ApiError, _>(|conn| {
+            async move {
+                let revoked = super::sessions::revoke_session_rows(conn, &user.id, None).await?;
+                audit::log(
+                    conn,
+                    &admin.id,
+                    "user.logout",
+                    Some("user"),
+                    Some(&user.id),
+                    Some(serde_json::json!({ "revoked_sessions": revoked.len() })),
+                    None,
+                )
+                .await?;
+                Ok(revoked)
+            }
+            .scope_boxed()
+        })
+        .await?;
+    super::sessions::delete_access_tokens(state.kv.as_ref(), &revoked).await?;
+    let revoked_sessions = revoked.len();
+
+    tracing::info!(user_id = %user.id, staff_id = %admin.id, revoked_sessions, "user signed out by staff");
+    Ok(Json(AdminLogoutResponse { revoked_sessions }))
+}
+
+// =========================================================================
+// POST /api/v1/admin/users/{user_id}/unlock — Lift a sign-in lockout
+// =========================================================================
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminUnlockResponse {
+    /// Whether the account was locked.
+    pub was_locked: bool,
+}
+
+/// `POST /api/v1/admin/users/{user_id}/unlock` — Lift the lock placed on a
+/// user's password sign-in after repeated failures, and forget the failures.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/users/{user_id}/unlock",
+    tag = "Admin",
+    security(("bearer" = [])),
+    params(("user_id" = String, Path, description = "User ID")),
+    responses(
+        (status = 200, description = "Lockout lifted", body = AdminUnlockResponse),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 403, description = "Not staff", body = ApiErrorBody),
+        (status = 404, description = "User not found", body = ApiErrorBody),
+    ),
+)]
+pub async fn unlock_user(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(user_id): Path<String>,
+) -> Result<Json<AdminUnlockResponse>, ApiError> {
+    let admin = require_staff(&state, &auth).await?;
+    let user = load_user(&state, &user_id).await?;
+
+    let was_locked = throttle::unlock(state.kv.as_ref(), &user.id).await?;
+    let mut conn = state.db.get().await?;
+    audit::log(
+        &mut conn,
+        &admin.id,
+        "user.unlock",
+        Some("user"),
+        Some(&user.id),
+        Some(serde_json::json!({ "was_locked": was_locked })),
+        None,
+    )
+    .await?;
+    if was_locked {
+        // The user sees this in their security events; the staff member's
+        // address isn't theirs to see.
+        events::record(
+            &state.db,
+            &ClientInfo::default(),
+            AuthEvent::new(events::ACCOUNT_UNLOCKED)
+                .user(&user.id)
+                .details(serde_json::json!({ "method": "staff" })),
+        )
+        .await;
+    }
+
+    tracing::info!(user_id = %user.id, staff_id = %admin.id, was_locked, "user unlocked by staff");
+    Ok(Json(AdminUnlockResponse { was_locked }))
+}
+
+// =========================================================================
+// POST /api/v1/admin/users/{user_id}/password-reset — Force a password reset
+// =========================================================================
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminPasswordResetResponse {
+    /// How many active sessions were revoked.
+    pub revoked_sessions: usize,
+    /// Whether a reset link was emailed. Accounts without an email address
+    /// have to recover through a linked identity provider.
+    pub email_sent: bool,
+}
+
+/// `POST /api/v1/admin/users/{user_id}/password-reset` — Clear a user's
+/// password, sign them out everywhere, and email them a reset link.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/users/{user_id}/password-reset",
+    tag = "Admin",
+    security(("bearer" = [])),
+    params(("user_id" = String, Path, description = "User ID")),
+    responses(
+        (status = 200, description = "Password cleared", body = AdminPasswordResetResponse),
+        (status = 400, description = "Bots don't have passwords", body = ApiErrorBody),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 403, description = "Not staff", body = ApiErrorBody),
+        (status = 404, description = "User not found", body = ApiErrorBody),
+    ),
+)]
+pub async fn force_password_reset(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(user_id): Path<String>,
+) -> Result<Json<AdminPasswordResetResponse>, ApiError> {
+    let admin = require_staff(&state, &auth).await?;
+    let user = load_user(&state, &user_id).await?;
+    if user.is_bot() {
+        return Err(ApiError::bad_request("Bots don't have passwords"));
+    }
+
+    // The link goes out once the reset is committed, to whatever address
+    // the account has.
+    let email_sent = user.email.is_some();
+    let (admin, user) = (&admin, &user);
+    let mut conn = state.db.get().await?;
+    let revoked = conn
+        .transaction::<_, ApiError, _>(|conn| {
+            async move {
+                diesel::update(users::table.find(&user.id))
+                    .set((
+                        users::password_hash.eq(None::<String>),
+                        users::updated_at.eq(Utc::now()),
+                    ))
+                    .execute(conn)
+                    .await?;
+                let revoked = super::sessions::revoke_session_rows(conn, &user.id, None).await?;
+                audit::log(
+                    conn,
+                    &admin.id,
+                    "user.password_reset",
+                    Some("user"),
+                    Some(&user.id),
+                    Some(serde_json::json!({
+                        "revoked_sessions": revoked.len(),
+                        "email_sent": email_sent,
+                    })),
+                    None,
+                )
+                .await?;
+                Ok(revoked)
+            }
+            .scope_boxed()
+        })
+        .await?;
+    super::sessions::delete_access_tokens(state.kv.as_ref(), &revoked).await?;
+    let revoked_sessions = revoked.len();
+
+    if let Some(ref email) = user.email {
+        super::password_reset::email_reset_link(&state, user, email, true).await?;
+    }
+
+    tracing::info!(user_id = %user.id, staff_id = %admin.id, email_sent, "password reset forced by staff");
+    Ok(Json(AdminPasswordResetResponse {
+        revoked_sessions,
+        email_sent,
+    }))
+}
+
+// =========================================================================
+// PATCH /api/v1/admin/users/{user_id}/flags — Edit a user's flags
+// =========================================================================
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateUserFlagsRequest {
+    /// Grant or remove Voxora staff access.
+    pub staff: Option<bool>,
+    /// Grant or remove the verified badge.
+    pub verified: Option<bool>,
+    /// Why the flags changed, for the audit log.
+    pub reason: Option<String>,
+}
+
+/// `PATCH /api/v1/admin/users/{user_id}/flags` — Set or clear a user's
+/// `staff` and `verified` flags. Omitted flags are left alone. Pods see the
+/// change the next time the user signs in to them.
+#[utoipa::path(
+    patch,
+    path = "/api/v1/admin/users/{user_id}/flags",
+    tag = "Admin",
+    security(("bearer" = [])),
+    params(("user_id" = String, Path, description = "User ID")),
+    request_body = UpdateUserFlagsRequest,
+    responses(
+        (status = 200, description = "Flags updated", body = UserResponse),
+        (status = 400, description = "Bots can't be staff", body = ApiErrorBody),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 403, description = "Not staff, or editing your own flags", body = ApiErrorBody),
+        (status = 404, description = "User not found", body = ApiErrorBody),
+    ),
+)]
+pub async fn update_user_flags(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(user_id): Path<String>,
+    Json(body): Json<UpdateUserFlagsRequest>,
+) -> Result<Json<UserResponse>, ApiError> {
+    let admin = require_staff(&state, &auth).await?;
+    // Another staff member has to confirm a change to your own access.
+    if admin.id == user_id {
+        return Err(ApiError::forbidden("You can't edit your own flags"));
+    }
+    let user = load_user(&state, &user_id).await?;
+    if body.staff == Some(true) && user.is_bot() {
+        return Err(ApiError::bad_request("Bots can't be staff"));
+    }
+
+    let mut flags = user.flags;
+    for (wanted, bit) in [
+        (body.staff, USER_FLAG_STAFF),
+        (body.verified, USER_FLAG_VERIFIED),
+    ] {
+        match wanted {
+            Some(true) => flags |= bit,
+            Some(false) => flags &= !bit,
+            None => {}
+        }
+    }
+    if flags == user.flags {
+        return Ok(Json(UserResponse::from(user)));
+    }
+
+    let (admin, user, body) = (&admin, &user, &body);
+    let mut conn = state.db.get().await?;
+    let updated: User = conn
+        .transaction::<_, ApiError, _>(|conn| {
+            async move {
+                let updated: User = diesel::update(users::table.find(&user.id))
+                    .set((users::flags.eq(flags), users::updated_at.eq(Utc::now())))
+                    .returning(User::as_returning())
+                    .get_result(conn)
+                    .await?;
+                audit::log(
+                    conn,
+                    &admin.id,
+                    "user.flags_update",
+                    Some("user"),
+                    Some(&user.id),
+                    Some(serde_json::json!({ "flags": { "old": user.flags, "new": flags } })),
+                    body.reason.as_deref(),
+                )
+                .await?;
+                Ok(updated)
+            }
+            .scope_boxed()
+        })
+        .await?;
+
+    tracing::info!(user_id = %user.id, staff_id = %admin.id, old = user.flags, new = flags, "user flags updated by staff");
+    Ok(Json(UserResponse::from(updated)))
+}
+
+// =========================================================================
+// GET /api/v1/admin/pods — List every pod
+// =========================================================================
+
+#[derive(Debug, Deserialize)]
+pub struct ListAllPodsParams {
+    /// `active` or `suspended`.
+    pub status: Option<String>,
+    /// `true` for pods that have stopped sending heartbeats, `false` for
+    /// the rest.
+    pub offline: Option<bool>,
+    pub q: Option<String>,
+    pub before: Option<String>,
+    pub limit: Option<i64>,
+}
+
+/// A Pod is offline once it has missed heartbeats for this long.
+const POD_OFFLINE_AFTER_MINS: i64 = 5;
+
+/// `GET /api/v1/admin/pods` — List every registered pod, including private,
+/// suspended and offline ones, newest first.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/pods",
+    tag = "Admin",
+    security(("bearer" = [])),
+    params(
+        ("status" = Option<String>, Query, description = "Filter by status: active or suspended"),
+        ("offline" = Option<bool>, Query, description = "Only pods with no heartbeat in the last 5 minutes (true) or only those with one (false)"),
+        ("q" = Option<String>, Query, description = "Exact pod ID, or part of a name or URL"),
+        ("before" = Option<String>, Query, description = "Cursor: pod ID"),
+        ("limit" = Option<i64>, Query, description = "Number of pods (1-100, default 50)"),
+    ),
+    responses(
+        (status = 200, description = "Pods", body = ListPodsResponse),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 403, description = "Not staff", body = ApiErrorBody),
+    ),
+)]
+pub async fn list_all_pods(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Query(params): Query<ListAllPodsParams>,
+) -> Result<Json<ListPodsResponse>, ApiError> {
+    require_staff(&state, &auth).await?;
+    let limit = params.limit.unwrap_or(50).clamp(1, 100);
+
+    let mut query = pods::table
+        .order(pods::id.desc())
+        .limit(limit + 1)
+        .select(Pod::as_select())
+        .into_boxed();
+    if let Some(ref status) = params.status {
+        query = query.filter(pods::status.eq(status.clone()));
+    }
+    if let Some(offline) = params.offline {
+        let cutoff = Utc::now() - chrono::Duration::minutes(POD_OFFLINE_AFTER_MINS);
+        query = if offline {
+            query.filter(
+                pods::last_heartbeat
+                    .is_null()
+                    .or(pods::last_heartbeat.lt(cutoff)),
+            )
+        } else {
+            query.filter(pods::last_heartbeat.ge(cutoff))
+        };
+    }
+    if let Some(q) = params.q.as_deref().map(str::trim).filter(|q| !q.is_empty()) {
+        let pattern = like_pattern(q);
+        query = query.filter(
+            pods::id
+                .eq(q.to_string())
+                .or(pods::name.ilike(pattern.clone()))
+                .or(pods::url.ilike(pattern)),
+        );
+    }
+    if let Some(ref before) = params.before {
+        query = query.filter(pods::id.lt(before.clone()));
+    }
+
+    let mut conn = state.db.get().await?;
+    let rows: Vec<Pod> = query.load(&mut conn).await.map_err(ApiError::from)?;
+
+    let has_more = rows.len() as i64 > limit;
+    let data = rows
+        .into_iter()
+        .take(limit as usize)
+        .map(PodResponse::from)
+        .collect();
+    Ok(Json(ListPodsResponse { data, has_more }))
+}
+
+// =========================================================================
+// PATCH /api/v1/admin/pods/{pod_id} — Suspend or delist a pod
+// =========================================================================
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdatePodRequest {
+    /// `suspended` stops the Hub issuing SIAs for the pod; `active`
+    /// restores it.
+    pub status: Option<String>,
+    /// `false` delists the pod from the public directory.
+    pub public: Option<bool>,
+    /// Why the pod changed, for the audit log.
+    pub reason: Option<String>,
+}
+
+/// `PATCH /api/v1/admin/pods/{pod_id}` — Suspend, reinstate, delist or
+/// relist a pod. Omitted fields are left alone.
+#[utoipa::path(
+    patch,
+    path = "/api/v1/admin/pods/{pod_id}",
+    tag = "Admin",
+    security(("bearer" = [])),
+    params(("pod_id" = String, Path, description = "Pod ID")),
+    request_body = UpdatePodRequest,
+    responses(
+        (status = 200, description = "Pod updated", body = PodResponse),
+        (status = 400, description = "Validation error", body = ApiErrorBody),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 403, description = "Not staff", body = ApiErrorBody),
+        (status = 404, description = "Pod not found", body = ApiErrorBody),
+    ),
+)]
+pub async fn update_pod(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(pod_id): Path<String>,
+    Json(body): Json<UpdatePodRequest>,
+) -> Result<Json<PodResponse>, ApiError> {
+    let admin = require_staff(&state, &auth).await?;
+    if let Some(ref status) = body.status {
+        if status != "active" && status != "suspended" {
+            return Err(ApiError::validation(vec![FieldError {
+                field: "status".to_string(),
+                message: "Must be active or suspended".to_string(),
+            }]));
+        }
+    }
+
+    let mut conn = state.db.get().await?;
+    let pod: Pod = pods::table
+        .find(&pod_id)
+        .select(Pod::as_select())
+        .first(&mut conn)
+        .await
+        .optional()
+        .map_err(ApiError::from)?
+        .ok_or_else(|| ApiError::not_found("Pod not found"))?;
+
+    let status = body.status.clone().unwrap_or_else(|| pod.status.clone());
+    let public = body.public.unwrap_or(pod.public);
+    let mut actions = Vec::new();
+    if status != pod.status {
+        actions.push(if status == "suspended" {
+            "pod.suspend"
+        } else {
+            "pod.unsuspend"
+        });
+    }
+    if public != pod.public {
+        actions.push(if public { "pod.relist" } else { "pod.delist" });
+    }
+    if actions.is_empty() {
+        return Ok(Json(PodResponse::from(pod)));
+    }
+
+    let (admin, pod, body, actions) = (&admin, &pod, &body, &actions);
+    let updated: Pod = conn
+        .transaction::<_, ApiError, _>(|conn| {
+            async move {
+                let updated: Pod = diesel::update(pods::table.find(&pod.id))
+                    .set((
+                        pods::status.eq(&status),
+                        pods::public.eq(public),
+                        pods::updated_at.eq(Utc::now()),
+                    ))
+                    .returning(Pod::as_returning())
+                    .get_result(conn)
+                    .await?;
+                for action in actions {
+                    audit::log(
+                        conn,
+                        &admin.id,
+                        action,
+                        Some("pod"),
+                        Some(&pod.id),
+                        None,
+                        body.reason.as_deref(),
+                    )
+                    .await?;
+                }
+                Ok(updated)
+            }
+            .scope_boxed()
+        })
+        .await?;
+
+    for action in actions {
+        tracing::info!(pod_id = %pod.id, staff_id = %admin.id, %action, "pod updated by staff");
+    }
+    Ok(Json(PodResponse::from(updated)))
+}
+
+// =========================================================================
+// GET /api/v1/admin/audit-log — List admin actions
+// =========================================================================
+
+#[derive(Debug, Deserialize)]
+pub struct AdminAuditLogParams {
+    pub actor_id: Option<String>,
+    pub action: Option<String>,
+    pub target_id: Option<String>,
+    pub before: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminAuditLogResponse {
+    pub data: Vec<AdminAuditLogEntry>,
+    pub has_more: bool,
+}
+
+/// `GET /api/v1/admin/audit-log` — List actions taken through the admin
+/// API, newest first.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/audit-log",
+    tag = "Admin",
+    security(("bearer" = [])),
+    params(
+        ("actor_id" = Option<String>, Query, description = "Filter by staff member"),
+        ("action" = Option<String>, Query, description = "Filter by action, e.g. user.ban"),
+        ("target_id" = Option<String>, Query, description = "Filter by the user or pod acted on"),
+        ("before" = Option<String>, Query, description = "Cursor: audit log entry ID"),
+        ("limit" = Option<i64>, Query, description = "Number of entries (1-100, default 50)"),
+    ),
+    responses(
+        (status = 200, description = "Audit log entries", body = AdminAuditLogResponse),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 403, description = "Not staff", body = ApiErrorBody),
+    ),
+)]
+pub async fn list_admin_audit_log(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Query(params): Query<AdminAuditLogParams>,
+) -> Result<Json<AdminAuditLogResponse>, ApiError> {
+    require_staff(&state, &auth).await?;
+    let limit = params.limit.unwrap_or(50).clamp(1, 100);
+
+    let mut query = admin_audit_log::table
+        .order(admin_audit_log::id.desc())
+        .limit(limit + 1)
+        .select(AdminAuditLogEntry::as_select())
+        .into_boxed();
+    if let Some(ref actor_id) = params.actor_id {
+        query = query.filter(admin_audit_log::actor_id.eq(actor_id.clone()));
+    }
+    if let Some(ref action) = params.action {
+        query = query.filter(admin_audit_log::action.eq(action.clone()));
+    }
+    if let Some(ref target_id) = params.target_id {
+        query = query.filter(admin_audit_log::target_id.eq(target_id.clone()));
+    }
+    if let Some(ref before) = params.before {
+        query = query.filter(admin_audit_log::id.lt(before.clone()));
+    }
+
+    let mut conn = state.db.get().await?;
+    let rows: Vec<AdminAuditLogEntry> = query.load(&mut conn).await.map_err(ApiError::from)?;
+
+    let has_more = rows.len() as i64 > limit;
+    let data = rows.into_iter().take(limit as usize).collect();
+    Ok(Json(AdminAuditLogResponse { data, has_more }))
+}
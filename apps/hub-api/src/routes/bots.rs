@@ -0,0 +1,298 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::routing::{delete, get};
+use axum::{Json, Router};
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::auth::clients::{generate_client_secret, hash_client_secret};
+use crate::auth::middleware::AuthUser;
+use crate::db::schema::{oauth_clients, sessions, users};
+use crate::error::{ApiError, ApiErrorBody};
+use crate::models::oauth_client::{
+    NewOAuthClient, OAuthClient, CLIENT_TYPE_CONFIDENTIAL, GRANT_CLIENT_CREDENTIALS,
+};
+use crate::models::user::{NewUser, User, USER_FLAG_BOT};
+use crate::AppState;
+
+/// Maximum bots per owner.
+const MAX_BOTS_PER_USER: i64 = 20;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/bots", get(list_bots).post(create_bot))
+        .route("/bots/{bot_id}", delete(delete_bot))
+}
+
+/// A bot as shown to its owner.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BotResponse {
+    pub id: String,
+    pub username: String,
+    pub display_name: String,
+    pub avatar_url: Option<String>,
+    pub flags: i64,
+    /// Client ID the bot authenticates with.
+    pub client_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl BotResponse {
+    fn new(bot: User, client_id: Option<String>) -> Self {
+        Self {
+            id: bot.id,
+            username: bot.username,
+            display_name: bot.display_name,
+            avatar_url: bot.avatar_url,
+            flags: bot.flags,
+            client_id,
+            created_at: bot.created_at,
+        }
+    }
+}
+
+// =========================================================================
+// POST /api/v1/bots — Create a bot
+// =========================================================================
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateBotRequest {
+    pub username: String,
+    pub display_name: String,
+}
+
+/// A new bot with the credentials it signs in with.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BotCredentialsResponse {
+    pub bot: BotResponse,
+    pub client_id: String,
+    /// Store this now; it can't be retrieved again. Rotate it through
+    /// `POST /api/v1/oauth-clients/{client_id}/secret`.
+    pub client_secret: String,
+}
+
+/// `POST /api/v1/bots` — Create a bot account owned by the current user,
+/// along with a confidential client for the `client_credentials` grant.
+#[utoipa::path(
+    post,
+    path = "/api/v1/bots",
+    tag = "Bots",
+    security(("bearer" = [])),
+    request_body = CreateBotRequest,
+    responses(
+        (status = 201, description = "Bot created", body = BotCredentialsResponse),
+        (status = 400, description = "Validation error or bot limit reached", body = ApiErrorBody),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 403, description = "Bots can't create bots", body = ApiErrorBody),
+        (status = 409, description = "Username conflict", body = ApiErrorBody),
+    ),
+)]
+pub async fn create_bot(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(body): Json<CreateBotRequest>,
+) -> Result<(StatusCode, Json<BotCredentialsResponse>), ApiError> {
+    let mut errors = Vec::new();
+    let username = super::users::validate_username(&body.username, &mut errors);
+    let display_name = super::users::validate_display_name(&body.display_name, &mut errors);
+    if !errors.is_empty() {
+        return Err(ApiError::validation(errors));
+    }
+
+    let mut conn = state.db.get().await?;
+
+    let owner: User = users::table
+        .find(&auth.user_id)
+        .select(User::as_select())
+        .first(&mut conn)
+        .await
+        .map_err(ApiError::from)?;
+    if owner.is_bot() {
+        return Err(ApiError::forbidden("Bots can't create bots"));
+    }
+
+    let owned: i64 = users::table
+        .filter(users::bot_owner_id.eq(&owner.id))
+        .count()
+        .get_result(&mut conn)
+        .await
+        .map_err(ApiError::from)?;
+    if owned >= MAX_BOTS_PER_USER {
+        return Err(ApiError::bad_request(format!(
+            "You can own at most {MAX_BOTS_PER_USER} bots"
+        )));
+    }
+
+    let new_bot = NewUser {
+        id: voxora_common::id::prefixed_ulid(voxora_common::id::prefix::USER),
+        username_lower: username.to_lowercase(),
+        username,
+        display_name,
+        email: None,
+        password_hash: None,
+        flags: USER_FLAG_BOT,
+        bot_owner_id: Some(owner.id.clone()),
+    };
+    let bot: User = diesel::insert_into(users::table)
+        .values(&new_bot)
+        .returning(User::as_returning())
+        .get_result(&mut conn)
+        .await
+        .map_err(|e| match e {
+            diesel::result::Error::DatabaseError(
+                diesel::result::DatabaseErrorKind::UniqueViolation,
+                _,
+            ) => ApiError::conflict("Username is already taken"),
+            other => ApiError::from(other),
+        })?;
+
+    let client_secret = generate_client_secret();
+    let new_client = NewOAuthClient {
+        id: voxora_common::id::prefixed_ulid(voxora_common::id::prefix::OAUTH_CLIENT),
+        owner_id: Some(owner.id.clone()),
+        name: bot.display_name.clone(),
+        client_type: CLIENT_TYPE_CONFIDENTIAL.to_string(),
+        client_secret_hash: Some(hash_client_secret(&client_secret)),
+        redirect_uris: Vec::new(),
+        allowed_scopes: vec!["pods".to_string()],
+        grant_types: vec![GRANT_CLIENT_CREDENTIALS.to_string()],
+        bot_user_id: Some(bot.id.clone()),
+    };
+    let client: OAuthClient = match diesel::insert_into(oauth_clients::table)
+        .values(&new_client)
+        .returning(OAuthClient::as_returning())
+        .get_result(&mut conn)
+        .await
+    {
+        Ok(client) => client,
+        Err(e) => {
+            // Don't leave a bot behind that can't sign in.
+            diesel::delete(users::table.find(&bot.id))
+                .execute(&mut conn)
+                .await
+                .ok();
+            return Err(ApiError::from(e));
+        }
+    };
+
+    tracing::info!(bot_id = %bot.id, owner_id = %owner.id, client_id = %client.id, "bot created");
+
+    Ok((
+        StatusCode::CREATED,
+        Json(BotCredentialsResponse {
+            bot: BotResponse::new(bot, Some(client.id.clone())),
+            client_id: client.id,
+            client_secret,
+        }),
+    ))
+}
+
+// =========================================================================
+// GET /api/v1/bots — List own bots
+// =========================================================================
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BotListResponse {
+    pub data: Vec<BotResponse>,
+}
+
+/// `GET /api/v1/bots` — List the bots the current user owns.
+#[utoipa::path(
+    get,
+    path = "/api/v1/bots",
+    tag = "Bots",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Owned bots", body = BotListResponse),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+    ),
+)]
+pub async fn list_bots(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Json<BotListResponse>, ApiError> {
+    let mut conn = state.db.get().await?;
+
+    let bots: Vec<User> = users::table
+        .filter(users::bot_owner_id.eq(&auth.user_id))
+        .order(users::created_at.asc())
+        .select(User::as_select())
+        .load(&mut conn)
+        .await
+        .map_err(ApiError::from)?;
+
+    let bot_ids: Vec<&str> = bots.iter().map(|b| b.id.as_str()).collect();
+    let clients: Vec<(Option<String>, String)> = oauth_clients::table
+        .filter(oauth_clients::bot_user_id.eq_any(&bot_ids))
+        .select((oauth_clients::bot_user_id, oauth_clients::id))
+        .load(&mut conn)
+        .await
+        .map_err(ApiError::from)?;
+
+    let data = bots
+        .into_iter()
+        .map(|bot| {
+            let client_id = clients
+                .iter()
+                .find(|(bot_id, _)| bot_id.as_deref() == Some(bot.id.as_str()))
+                .map(|(_, id)| id.clone());
+            BotResponse::new(bot, client_id)
+        })
+        .collect();
+
+    Ok(Json(BotListResponse { data }))
+}
+
+// =========================================================================
+// DELETE /api/v1/bots/{bot_id}
+// =========================================================================
+
+/// `DELETE /api/v1/bots/{bot_id}` — Delete a bot and its client. Access
+/// tokens already issued expire on their own within 15 minutes, but SIAs
+/// can no longer be minted for the bot.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/bots/{bot_id}",
+    tag = "Bots",
+    security(("bearer" = [])),
+    params(("bot_id" = String, Path, description = "Bot user ID")),
+    responses(
+        (status = 204, description = "Bot deleted"),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 404, description = "Bot not found", body = ApiErrorBody),
+    ),
+)]
+pub async fn delete_bot(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(bot_id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    let mut conn = state.db.get().await?;
+
+    let bot_id: String = users::table
+        .find(&bot_id)
+        .filter(users::bot_owner_id.eq(&auth.user_id))
+        .select(users::id)
+        .first(&mut conn)
+        .await
+        .optional()
+        .map_err(ApiError::from)?
+        .ok_or_else(|| ApiError::not_found("Bot not found"))?;
+
+    diesel::delete(sessions::table.filter(sessions::user_id.eq(&bot_id)))
+        .execute(&mut conn)
+        .await
+        .map_err(ApiError::from)?;
+    // The client, bookmarks and preferences go with the user.
+    diesel::delete(users::table.find(&bot_id))
+        .execute(&mut conn)
+        .await
+        .map_err(ApiError::from)?;
+
+    tracing::info!(bot_id = %bot_id, owner_id = %auth.user_id, "bot deleted");
+
+    Ok(StatusCode::NO_CONTENT)
+}
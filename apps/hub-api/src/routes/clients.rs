@@ -0,0 +1,590 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::auth::clients::{generate_client_secret, hash_client_secret};
+use crate::auth::middleware::AuthUser;
+use crate::auth::tokens;
+use crate::db::schema::{oauth_clients, sessions};
+use crate::error::{ApiError, ApiErrorBody, FieldError};
+use crate::models::oauth_client::{
+    NewOAuthClient, OAuthClient, OAuthClientResponse, CLIENT_TYPE_CONFIDENTIAL, CLIENT_TYPE_PUBLIC,
+    GRANT_AUTHORIZATION_CODE, GRANT_REFRESH_TOKEN, SUPPORTED_GRANT_TYPES, SUPPORTED_SCOPES,
+};
+use crate::AppState;
+
+/// Maximum redirect URIs per client.
+const MAX_REDIRECT_URIS: usize = 10;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/oauth-clients", get(list_clients).post(create_client))
+        .route(
+            "/oauth-clients/{client_id}",
+            get(get_client).patch(update_client).delete(delete_client),
+        )
+        .route("/oauth-clients/{client_id}/secret", post(rotate_secret))
+}
+
+// =========================================================================
+// Validation
+// =========================================================================
+
+fn validate_name(name: &str, errors: &mut Vec<FieldError>) -> String {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > 64 {
+        errors.push(FieldError {
+            field: "name".into(),
+            message: "Name must be 1–64 characters".into(),
+        });
+    }
+    name.to_string()
+}
+
+/// A redirect URI must be absolute, have no fragment, and use `https` —
+/// except `http` on loopback for development and custom schemes for native
+/// apps.
+fn is_valid_redirect_uri(uri: &str) -> bool {
+    let Some((scheme, rest)) = uri.split_once(':') else {
+        return false;
+    };
+    if scheme.is_empty()
+        || !scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '.' || c == '-')
+        || rest.is_empty()
+        || uri.contains('#')
+        || uri.chars().any(char::is_whitespace)
+    {
+        return false;
+    }
+    match scheme.to_ascii_lowercase().as_str() {
+        "https" => rest.starts_with("//") && rest.len() > 2,
+        "http" => {
+            let authority = rest.strip_prefix("//").unwrap_or("");
+            let authority = authority.split(['/', '?']).next().unwrap_or("");
+            let host = match authority.rsplit_once(':') {
+                Some((host, _port)) if !authority.ends_with(']') => host,
+                _ => authority,
+            };
+            matches!(host, "localhost" | "127.0.0.1" | "[::1]")
+        }
+        _ => true,
+    }
+}
+
+fn validate_redirect_uris(uris: &[String], errors: &mut Vec<FieldError>) {
+    if uris.len() > MAX_REDIRECT_URIS {
+        errors.push(FieldError {
+            field: "redirect_uris".into(),
+            message: format!("At most {MAX_REDIRECT_URIS} redirect URIs are allowed"),
+        });
+    }
+    if let Some(bad) = uris.iter().find(|u| !is_valid_redirect_uri(u)) {
+        errors.push(FieldError {
+            field: "redirect_uris".into(),
+            message: format!(
+                "Invalid redirect URI {bad:?}: must be https, http on localhost, or a custom scheme, without a fragment"
+            ),
+        });
+    }
+}
+
+fn validate_subset(
+    field: &str,
+    values: &[String],
+    supported: &[&str],
+    errors: &mut Vec<FieldError>,
+) {
+    if let Some(bad) = values.iter().find(|v| !supported.contains(&v.as_str())) {
+        errors.push(FieldError {
+            field: field.into(),
+            message: format!(
+                "Unsupported value {bad:?} (expected one of: {})",
+                supported.join(", ")
+            ),
+        });
+    }
+}
+
+/// Checks that apply to the client as a whole, after any update.
+fn validate_consistency(
+    redirect_uris: &[String],
+    grant_types: &[String],
+    errors: &mut Vec<FieldError>,
+) {
+    if grant_types.iter().any(|g| g == GRANT_AUTHORIZATION_CODE) && redirect_uris.is_empty() {
+        errors.push(FieldError {
+            field: "redirect_uris".into(),
+            message: "At least one redirect URI is required for the authorization_code grant"
+                .into(),
+        });
+    }
+    if grant_types.is_empty() {
+        errors.push(FieldError {
+            field: "grant_types".into(),
+            message: "At least one grant type is required".into(),
+        });
+    }
+}
+
+fn dedup(mut values: Vec<String>) -> Vec<String> {
+    let mut seen = std::collections::HashSet::new();
+    values.retain(|v| seen.insert(v.clone()));
+    values
+}
+
+/// Load a client owned by `owner_id`. Clients owned by someone else look the
+/// same as missing ones.
+async fn load_owned_client(
+    state: &AppState,
+    owner_id: &str,
+    client_id: &str,
+) -> Result<OAuthClient, ApiError> {
+    let mut conn = state.db.get().await?;
+    oauth_clients::table
+        .find(client_id)
+        .filter(oauth_clients::owner_id.eq(owner_id))
+        .select(OAuthClient::as_select())
+        .first(&mut conn)
+        .await
+        .optional()
+        .map_err(ApiError::from)?
+        .ok_or_else(|| ApiError::not_found("OAuth client not found"))
+}
+
+// =========================================================================
+// POST /api/v1/oauth-clients — Register a client
+// =========================================================================
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateOAuthClientRequest {
+    pub name: String,
+    /// `public` (default) or `confidential`.
+    #[serde(default)]
+    pub client_type: Option<String>,
+    #[serde(default)]
+    pub redirect_uris: Vec<String>,
+    /// Defaults to `["openid", "profile"]`.
+    #[serde(default)]
+    pub allowed_scopes: Option<Vec<String>>,
+    /// Defaults to `["authorization_code", "refresh_token"]`.
+    #[serde(default)]
+    pub grant_types: Option<Vec<String>>,
+}
+
+/// A client together with its secret. Only returned when the secret is
+/// created or rotated.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct OAuthClientSecretResponse {
+    pub client_id: String,
+    pub name: String,
+    pub client_type: String,
+    /// Store this now; it can't be retrieved again. `null` for public clients.
+    pub client_secret: Option<String>,
+    pub redirect_uris: Vec<String>,
+    pub allowed_scopes: Vec<String>,
+    pub grant_types: Vec<String>,
+    pub bot_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl OAuthClientSecretResponse {
+    pub(crate) fn new(client: OAuthClient, client_secret: Option<String>) -> Self {
+        Self {
+            client_id: client.id,
+            name: client.name,
+            client_type: client.client_type,
+            client_secret,
+            redirect_uris: client.redirect_uris,
+            allowed_scopes: client.allowed_scopes,
+            grant_types: client.grant_types,
+            bot_id: client.bot_user_id,
+            created_at: client.created_at,
+            updated_at: client.updated_at,
+        }
+    }
+}
+
+/// `POST /api/v1/oauth-clients` — Register an OAuth client owned by the
+/// current user.
+#[utoipa::path(
+    post,
+    path = "/api/v1/oauth-clients",
+    tag = "OAuth Clients",
+    security(("bearer" = [])),
+    request_body = CreateOAuthClientRequest,
+    responses(
+        (status = 201, description = "Client registered", body = OAuthClientSecretResponse),
+        (status = 400, description = "Validation error", body = ApiErrorBody),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+    ),
+)]
+pub async fn create_client(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(body): Json<CreateOAuthClientRequest>,
+) -> Result<(StatusCode, Json<OAuthClientSecretResponse>), ApiError> {
+    let mut errors = Vec::new();
+
+    let name = validate_name(&body.name, &mut errors);
+
+    let client_type = body
+        .client_type
+        .unwrap_or_else(|| CLIENT_TYPE_PUBLIC.to_string());
+    if client_type != CLIENT_TYPE_PUBLIC && client_type != CLIENT_TYPE_CONFIDENTIAL {
+        errors.push(FieldError {
+            field: "client_type".into(),
+            message: "Client type must be public or confidential".into(),
+        });
+    }
+
+    let redirect_uris = dedup(body.redirect_uris);
+    validate_redirect_uris(&redirect_uris, &mut errors);
+
+    let allowed_scopes = dedup(
+        body.allowed_scopes
+            .unwrap_or_else(|| vec!["openid".to_string(), "profile".to_string()]),
+    );
+    validate_subset(
+        "allowed_scopes",
+        &allowed_scopes,
+        SUPPORTED_SCOPES,
+        &mut errors,
+    );
+
+    let grant_types = dedup(body.grant_types.unwrap_or_else(|| {
+        vec![
+            GRANT_AUTHORIZATION_CODE.to_string(),
+            GRANT_REFRESH_TOKEN.to_string(),
+        ]
+    }));
+    validate_subset(
+        "grant_types",
+        &grant_types,
+        SUPPORTED_GRANT_TYPES,
+        &mut errors,
+    );
+    validate_consistency(&redirect_uris, &grant_types, &mut errors);
+
+    if !errors.is_empty() {
+        return Err(ApiError::validation(errors));
+    }
+
+    let client_secret = (client_type == CLIENT_TYPE_CONFIDENTIAL).then(generate_client_secret);
+
+    let new_client = NewOAuthClient {
+        id: voxora_common::id::prefixed_ulid(voxora_common::id::prefix::OAUTH_CLIENT),
+        owner_id: Some(auth.user_id.clone()),
+        name,
+        client_type,
+        client_secret_hash: client_secret.as_deref().map(hash_client_secret),
+        redirect_uris,
+        allowed_scopes,
+        grant_types,
+        bot_user_id: None,
+    };
+
+    let mut conn = state.db.get().await?;
+    let client: OAuthClient = diesel::insert_into(oauth_clients::table)
+        .values(&new_client)
+        .returning(OAuthClient::as_returning())
+        .get_result(&mut conn)
+        .await
+        .map_err(ApiError::from)?;
+
+    tracing::info!(client_id = %client.id, owner_id = %auth.user_id, "oauth client registered");
+
+    Ok((
+        StatusCode::CREATED,
+        Json(OAuthClientSecretResponse::new(client, client_secret)),
+    ))
+}
+
+// =========================================================================
+// GET /api/v1/oauth-clients — List own clients
+// =========================================================================
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct OAuthClientListResponse {
+    pub data: Vec<OAuthClientResponse>,
+}
+
+/// `GET /api/v1/oauth-clients` — List the OAuth clients the current user
+/// owns.
+#[utoipa::path(
+    get,
+    path = "/api/v1/oauth-clients",
+    tag = "OAuth Clients",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Owned clients", body = OAuthClientListResponse),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+    ),
+)]
+pub async fn list_clients(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Json<OAuthClientListResponse>, ApiError> {
+    let mut conn = state.db.get().await?;
+    let rows: Vec<OAuthClient> = oauth_clients::table
+        .filter(oauth_clients::owner_id.eq(&auth.user_id))
+        .order(oauth_clients::created_at.asc())
+        .select(OAuthClient::as_select())
+        .load(&mut conn)
+        .await
+        .map_err(ApiError::from)?;
+
+    Ok(Json(OAuthClientListResponse {
+        data: rows.into_iter().map(OAuthClientResponse::from).collect(),
+    }))
+}
+
+// =========================================================================
+// GET /api/v1/oauth-clients/{client_id}
+// =========================================================================
+
+/// `GET /api/v1/oauth-clients/{client_id}` — Get one of the current user's
+/// clients.
+#[utoipa::path(
+    get,
+    path = "/api/v1/oauth-clients/{client_id}",
+    tag = "OAuth Clients",
+    security(("bearer" = [])),
+    params(("client_id" = String, Path, description = "Client ID")),
+    responses(
+        (status = 200, description = "Client", body = OAuthClientResponse),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 404, description = "Client not found", body = ApiErrorBody),
+    ),
+)]
+pub async fn get_client(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(client_id): Path<String>,
+) -> Result<Json<OAuthClientResponse>, ApiError> {
+    let client = load_owned_client(&state, &auth.user_id, &client_id).await?;
+    Ok(Json(client.into()))
+}
+
+// =========================================================================
+// PATCH /api/v1/oauth-clients/{client_id}
+// =========================================================================
+
+/// Only provided fields are changed. The client type can't be changed.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateOAuthClientRequest {
+    pub name: Option<String>,
+    pub redirect_uris: Option<Vec<String>>,
+    pub allowed_scopes: Option<Vec<String>>,
+    pub grant_types: Option<Vec<String>>,
+}
+
+/// `PATCH /api/v1/oauth-clients/{client_id}` — Update a client's name,
+/// redirect URIs, scopes or grant types.
+#[utoipa::path(
+    patch,
+    path = "/api/v1/oauth-clients/{client_id}",
+    tag = "OAuth Clients",
+    security(("bearer" = [])),
+    params(("client_id" = String, Path, description = "Client ID")),
+    request_body = UpdateOAuthClientRequest,
+    responses(
+        (status = 200, description = "Updated client", body = OAuthClientResponse),
+        (status = 400, description = "Validation error", body = ApiErrorBody),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 404, description = "Client not found", body = ApiErrorBody),
+    ),
+)]
+pub async fn update_client(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(client_id): Path<String>,
+    Json(body): Json<UpdateOAuthClientRequest>,
+) -> Result<Json<OAuthClientResponse>, ApiError> {
+    let client = load_owned_client(&state, &auth.user_id, &client_id).await?;
+    let mut errors = Vec::new();
+
+    // A bot's client only ever uses client_credentials.
+    let is_bot = client.is_bot();
+    if is_bot {
+        for (field, provided) in [
+            ("redirect_uris", body.redirect_uris.is_some()),
+            ("grant_types", body.grant_types.is_some()),
+        ] {
+            if provided {
+                errors.push(FieldError {
+                    field: field.into(),
+                    message: "Bot clients can't be changed to sign in users".into(),
+                });
+            }
+        }
+        if !errors.is_empty() {
+            return Err(ApiError::validation(errors));
+        }
+    }
+
+    let name = match body.name {
+        Some(ref n) => validate_name(n, &mut errors),
+        None => client.name,
+    };
+    let redirect_uris = match body.redirect_uris {
+        Some(uris) => {
+            let uris = dedup(uris);
+            validate_redirect_uris(&uris, &mut errors);
+            uris
+        }
+        None => client.redirect_uris,
+    };
+    let allowed_scopes = match body.allowed_scopes {
+        Some(scopes) => {
+            let scopes = dedup(scopes);
+            validate_subset("allowed_scopes", &scopes, SUPPORTED_SCOPES, &mut errors);
+            scopes
+        }
+        None => client.allowed_scopes,
+    };
+    let grant_types = match body.grant_types {
+        Some(grants) => {
+            let grants = dedup(grants);
+            validate_subset("grant_types", &grants, SUPPORTED_GRANT_TYPES, &mut errors);
+            grants
+        }
+        None => client.grant_types,
+    };
+    if !is_bot {
+        validate_consistency(&redirect_uris, &grant_types, &mut errors);
+    }
+
+    if !errors.is_empty() {
+        return Err(ApiError::validation(errors));
+    }
+
+    let mut conn = state.db.get().await?;
+    let updated: OAuthClient = diesel::update(oauth_clients::table.find(&client.id))
+        .set((
+            oauth_clients::name.eq(name),
+            oauth_clients::redirect_uris.eq(redirect_uris),
+            oauth_clients::allowed_scopes.eq(allowed_scopes),
+            oauth_clients::grant_types.eq(grant_types),
+            oauth_clients::updated_at.eq(Utc::now()),
+        ))
+        .returning(OAuthClient::as_returning())
+        .get_result(&mut conn)
+        .await
+        .map_err(ApiError::from)?;
+
+    Ok(Json(updated.into()))
+}
+
+// =========================================================================
+// DELETE /api/v1/oauth-clients/{client_id}
+// =========================================================================
+
+/// `DELETE /api/v1/oauth-clients/{client_id}` — Delete a client and revoke
+/// every session issued to it.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/oauth-clients/{client_id}",
+    tag = "OAuth Clients",
+    security(("bearer" = [])),
+    params(("client_id" = String, Path, description = "Client ID")),
+    responses(
+        (status = 204, description = "Client deleted"),
+        (status = 400, description = "Bot clients are deleted with their bot", body = ApiErrorBody),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 404, description = "Client not found", body = ApiErrorBody),
+    ),
+)]
+pub async fn delete_client(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(client_id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    let client = load_owned_client(&state, &auth.user_id, &client_id).await?;
+    if client.is_bot() {
+        return Err(ApiError::bad_request(
+            "Bot clients are deleted with their bot",
+        ));
+    }
+
+    let mut conn = state.db.get().await?;
+    let revoked: Vec<String> = diesel::update(
+        sessions::table
+            .filter(sessions::client_id.eq(&client.id))
+            .filter(sessions::revoked.eq(false)),
+    )
+    .set(sessions::revoked.eq(true))
+    .returning(sessions::id)
+    .get_results(&mut conn)
+    .await
+    .map_err(ApiError::from)?;
+    for session_id in &revoked {
+        tokens::delete_session_access_token(state.kv.as_ref(), session_id).await?;
+    }
+
+    diesel::delete(oauth_clients::table.find(&client.id))
+        .execute(&mut conn)
+        .await
+        .map_err(ApiError::from)?;
+
+    tracing::info!(client_id = %client.id, revoked = revoked.len(), "oauth client deleted");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// =========================================================================
+// POST /api/v1/oauth-clients/{client_id}/secret — Rotate the secret
+// =========================================================================
+
+/// `POST /api/v1/oauth-clients/{client_id}/secret` — Replace a confidential
+/// client's secret. The old secret stops working immediately.
+#[utoipa::path(
+    post,
+    path = "/api/v1/oauth-clients/{client_id}/secret",
+    tag = "OAuth Clients",
+    security(("bearer" = [])),
+    params(("client_id" = String, Path, description = "Client ID")),
+    responses(
+        (status = 200, description = "New secret", body = OAuthClientSecretResponse),
+        (status = 400, description = "Public clients have no secret", body = ApiErrorBody),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 404, description = "Client not found", body = ApiErrorBody),
+    ),
+)]
+pub async fn rotate_secret(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(client_id): Path<String>,
+) -> Result<Json<OAuthClientSecretResponse>, ApiError> {
+    let client = load_owned_client(&state, &auth.user_id, &client_id).await?;
+    if !client.is_confidential() {
+        return Err(ApiError::bad_request("Public clients have no secret"));
+    }
+
+    let client_secret = generate_client_secret();
+    let mut conn = state.db.get().await?;
+    let updated: OAuthClient = diesel::update(oauth_clients::table.find(&client.id))
+        .set((
+            oauth_clients::client_secret_hash.eq(hash_client_secret(&client_secret)),
+            oauth_clients::updated_at.eq(Utc::now()),
+        ))
+        .returning(OAuthClient::as_returning())
+        .get_result(&mut conn)
+        .await
+        .map_err(ApiError::from)?;
+
+    tracing::info!(client_id = %client.id, "oauth client secret rotated");
+
+    Ok(Json(OAuthClientSecretResponse::new(
+        updated,
+        Some(client_secret),
+    )))
+}
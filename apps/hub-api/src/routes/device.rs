@@ -0,0 +1,461 @@
+use axum::extract::{Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{Html, IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Form, Json, Router};
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::oidc::{error_banner, html_escape, password_matches, render_mfa};
+use crate::auth::clients;
+use crate::auth::device::{
+    self, DeviceCodeData, DeviceStatus, DEVICE_CODE_TTL_SECS, DEVICE_POLL_INTERVAL_SECS,
+};
+use crate::auth::events::{self, AuthEvent};
+use crate::auth::mfa::{self, amr, MfaChallengeData};
+use crate::auth::middleware::ClientInfo;
+use crate::auth::throttle;
+use crate::auth::tokens::generate_opaque_token;
+use crate::db::schema::users;
+use crate::error::{OAuthError, OAuthErrorBody};
+use crate::models::oauth_client::GRANT_DEVICE_CODE;
+use crate::models::user::User;
+use crate::AppState;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/oidc/device_authorization", post(device_authorization))
+        .route(
+            "/oidc/device",
+            get(verify_device).post(verify_device_submit),
+        )
+}
+
+// ===========================================================================
+// POST /oidc/device_authorization
+// ===========================================================================
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct DeviceAuthorizationRequest {
+    #[serde(default)]
+    pub client_id: Option<String>,
+    /// For confidential clients using `client_secret_post`.
+    #[serde(default)]
+    pub client_secret: Option<String>,
+    /// Space-separated scopes. Defaults to `openid`.
+    #[serde(default)]
+    pub scope: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DeviceAuthorizationResponse {
+    /// Poll `/oidc/token` with this. Keep it on the device.
+    pub device_code: String,
+    /// Show this to the user, e.g. `BDFG-HJKL`.
+    pub user_code: String,
+    pub verification_uri: String,
+    /// `verification_uri` with the user code filled in, e.g. for a QR code.
+    pub verification_uri_complete: String,
+    pub expires_in: i64,
+    /// Minimum seconds between polls.
+    pub interval: i64,
+}
+
+/// Start a device sign-in (RFC 8628 §3.1). The device shows the user code
+/// and polls the token endpoint while the user approves it elsewhere.
+#[utoipa::path(
+    post,
+    path = "/oidc/device_authorization",
+    tag = "OIDC",
+    request_body(content = DeviceAuthorizationRequest, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "Device and user codes", body = DeviceAuthorizationResponse),
+        (status = 400, description = "`unauthorized_client` or `invalid_scope`", body = OAuthErrorBody),
+        (status = 401, description = "`invalid_client`", body = OAuthErrorBody),
+    ),
+)]
+pub async fn device_authorization(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Form(form): Form<DeviceAuthorizationRequest>,
+) -> Result<Json<DeviceAuthorizationResponse>, OAuthError> {
+    let oauth_client = clients::authenticate_client(
+        &state.db,
+        &headers,
+        form.client_id.as_deref(),
+        form.client_secret.as_deref(),
+    )
+    .await?;
+    if !oauth_client.allows_grant(GRANT_DEVICE_CODE) {
+        return Err(OAuthError::new("unauthorized_client"));
+    }
+
+    let scopes: Vec<String> = form
+        .scope
+        .as_deref()
+        .unwrap_or("openid")
+        .split_whitespace()
+        .map(|s| s.to_string())
+        .collect();
+    if !oauth_client.allows_scopes(&scopes) {
+        return Err(OAuthError::new("invalid_scope"));
+    }
+
+    let kv = state.kv.as_ref();
+
+    // Collisions are unlikely (20^8 codes) but would hand one user's device
+    // to another.
+    let mut user_code = device::generate_user_code();
+    for _ in 0..5 {
+        if !device::user_code_taken(kv, &user_code).await? {
+            break;
+        }
+        user_code = device::generate_user_code();
+    }
+
+    let device_code = device::generate_device_code();
+    let data = DeviceCodeData {
+        client_id: oauth_client.id,
+        scopes,
+        user_code: user_code.clone(),
+        status: DeviceStatus::Pending,
+        user_id: None,
+        amr: Vec::new(),
+        expires_at: Utc::now().timestamp() + DEVICE_CODE_TTL_SECS,
+    };
+    device::store_device_code(kv, &device_code, &data).await?;
+    device::store_user_code(kv, &device_code, &data).await?;
+
+    let verification_uri = format!("{}/oidc/device", state.config.hub_domain);
+    let display_code = device::format_user_code(&user_code);
+
+    Ok(Json(DeviceAuthorizationResponse {
+        device_code,
+        verification_uri_complete: format!("{verification_uri}?user_code={display_code}"),
+        verification_uri,
+        user_code: display_code,
+        expires_in: DEVICE_CODE_TTL_SECS,
+        interval: DEVICE_POLL_INTERVAL_SECS,
+    }))
+}
+
+// ===========================================================================
+// GET /oidc/device  (user-code entry page)
+// POST /oidc/device (approves or denies the device)
+// ===========================================================================
+
+const INTERNAL: &str = "Something went wrong. Please try again.";
+const INVALID_CODE: &str = "That code is invalid or has expired. Check your device and try again.";
+
+fn render_device(
+    theme_class: &str,
+    user_code: &str,
+    client_name: Option<&str>,
+    error_message: &str,
+    login_value: &str,
+) -> String {
+    let client_html = match client_name {
+        Some(name) => format!(
+            r#"<p class="text-center text-sm">Signing in to <strong>{}</strong></p>"#,
+            html_escape(name),
+        ),
+        None => String::new(),
+    };
+    include_str!("../templates/device.html")
+        .replace("{{theme_class}}", theme_class)
+        .replace("{{user_code}}", &html_escape(user_code))
+        .replace("{{client_html}}", &client_html)
+        .replace("{{error_html}}", &error_banner(error_message))
+        .replace("{{login_value}}", &html_escape(login_value))
+}
+
+fn render_device_done(title: &str, message: &str) -> String {
+    include_str!("../templates/device_done.html")
+        .replace("{{theme_class}}", "")
+        .replace("{{title}}", title)
+        .replace("{{message}}", message)
+}
+
+/// Name of the client a pending device belongs to, for the entry page.
+async fn client_name(state: &AppState, data: &DeviceCodeData) -> Option<String> {
+    clients::load_client(&state.db, &data.client_id)
+        .await
+        .ok()
+        .flatten()
+        .map(|c| c.name)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeviceParams {
+    pub user_code: Option<String>,
+    /// Theme hint ("dark", "light", or empty for system).
+    pub theme: Option<String>,
+}
+
+/// Render the page where users enter the code shown on their device.
+#[utoipa::path(
+    get,
+    path = "/oidc/device",
+    tag = "OIDC",
+    responses(
+        (status = 200, description = "Code entry form", content_type = "text/html"),
+    ),
+)]
+pub async fn verify_device(
+    State(state): State<AppState>,
+    Query(params): Query<DeviceParams>,
+) -> Response {
+    let theme_class = match params.theme.as_deref() {
+        Some("dark") => "dark",
+        Some("light") => "light",
+        _ => "",
+    };
+    let user_code = params.user_code.unwrap_or_default();
+
+    // Prefilled from `verification_uri_complete`: say which app is asking.
+    let (name, error) = if user_code.is_empty() {
+        (None, "")
+    } else {
+        match device::load_by_user_code(state.kv.as_ref(), &user_code).await {
+            Ok(Some((_, data))) => (client_name(&state, &data).await, ""),
+            Ok(None) => (None, INVALID_CODE),
+            Err(_) => (None, INTERNAL),
+        }
+    };
+
+    Html(render_device(
+        theme_class,
+        &user_code,
+        name.as_deref(),
+        error,
+        "",
+    ))
+    .into_response()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeviceSubmit {
+    pub user_code: String,
+    #[serde(default)]
+    pub login: String,
+    #[serde(default)]
+    pub password: String,
+    /// `approve` (default) or `deny`.
+    #[serde(default)]
+    pub action: Option<String>,
+}
+
+/// Process the code entry form. Approving requires signing in (and MFA, for
+/// accounts that have it); denying doesn't.
+#[utoipa::path(
+    post,
+    path = "/oidc/device",
+    tag = "OIDC",
+    responses(
+        (status = 200, description = "Device approved or denied, or MFA challenge page", content_type = "text/html"),
+        (status = 400, description = "Form with an invalid code error", content_type = "text/html"),
+        (status = 401, description = "Form with a sign-in error", content_type = "text/html"),
+        (status = 429, description = "Form asking to wait after too many failures", content_type = "text/html"),
+    ),
+)]
+pub async fn verify_device_submit(
+    State(state): State<AppState>,
+    client: ClientInfo,
+    Form(form): Form<DeviceSubmit>,
+) -> Response {
+    macro_rules! device_err {
+        ($status:expr, $name:expr, $msg:expr) => {
+            return (
+                $status,
+                Html(render_device("", &form.user_code, $name, $msg, &form.login)),
+            )
+                .into_response()
+        };
+    }
+
+    let kv = state.kv.as_ref();
+    let (device_code, data) = match device::load_by_user_code(kv, &form.user_code).await {
+        Ok(Some(found)) => found,
+        Ok(None) => device_err!(StatusCode::BAD_REQUEST, None, INVALID_CODE),
+        Err(_) => device_err!(StatusCode::INTERNAL_SERVER_ERROR, None, INTERNAL),
+    };
+
+    if form.action.as_deref() == Some("deny") {
+        match device::decide(kv, &device_code, data, None).await {
+            Ok(true) => {}
+            Ok(false) => device_err!(StatusCode::BAD_REQUEST, None, INVALID_CODE),
+            Err(_) => device_err!(StatusCode::INTERNAL_SERVER_ERROR, None, INTERNAL),
+        }
+        return Html(render_device_done(
+            "Device denied",
+            "The device was not signed in. You can close this page.",
+        ))
+        .into_response();
+    }
+
+    let name = client_name(&state, &data).await;
+
+    // Look up user by username (case-insensitive) or email.
+    let login_lower = form.login.trim().to_lowercase();
+    let user: Option<User> = match state.db.get().await {
+        Ok(mut conn) => match users::table
+            .filter(
+                users::username_lower
+                    .eq(&login_lower)
+                    .or(users::email.eq(&login_lower)),
+            )
+            .select(User::as_select())
+            .first(&mut conn)
+            .await
+            .optional()
+        {
+            Ok(u) => u,
+            Err(_) => device_err!(StatusCode::INTERNAL_SERVER_ERROR, name.as_deref(), INTERNAL),
+        },
+        Err(_) => device_err!(StatusCode::INTERNAL_SERVER_ERROR, name.as_deref(), INTERNAL),
+    };
+    let subject = throttle::subject(user.as_ref(), &login_lower);
+    match throttle::check_login(kv, &subject, client.ip_address).await {
+        Ok(None) => {}
+        Ok(Some(_)) => device_err!(
+            StatusCode::TOO_MANY_REQUESTS,
+            name.as_deref(),
+            throttle::LOGIN_THROTTLED
+        ),
+        Err(_) => device_err!(StatusCode::INTERNAL_SERVER_ERROR, name.as_deref(), INTERNAL),
+    }
+
+    let password_ok = password_matches(
+        &form.password,
+        user.as_ref().and_then(|u| u.password_hash.as_deref()),
+    );
+    let user = match user {
+        Some(u) if password_ok => u,
+        user => {
+            let reason = if user.is_some() {
+                "invalid_password"
+            } else {
+                "unknown_user"
+            };
+            let mut event = AuthEvent::new(events::LOGIN_FAILURE)
+                .client(&data.client_id)
+                .details(serde_json::json!({ "reason": reason }));
+            if let Some(ref u) = user {
+                event = event.user(&u.id);
+            }
+            events::record(&state.db, &client, event).await;
+            if throttle::login_failed(&state, &client, &subject, user.as_ref())
+                .await
+                .is_err()
+            {
+                device_err!(StatusCode::INTERNAL_SERVER_ERROR, name.as_deref(), INTERNAL);
+            }
+            device_err!(
+                StatusCode::UNAUTHORIZED,
+                name.as_deref(),
+                "Invalid username or password"
+            );
+        }
+    };
+
+    // Second step, as on the login page. The MFA form approves the device.
+    if user.mfa_enabled {
+        let mfa_token = generate_opaque_token("hmc", 32);
+        let challenge = MfaChallengeData {
+            user_id: user.id.clone(),
+            client_id: data.client_id.clone(),
+            redirect_uri: String::new(),
+            code_challenge: String::new(),
+            scopes: data.scopes.clone(),
+            nonce: None,
+            state: None,
+            device_code: Some(device_code),
+            amr: vec![amr::PASSWORD.to_string()],
+        };
+        if mfa::store_mfa_challenge(kv, &mfa_token, &challenge)
+            .await
+            .is_err()
+        {
+            device_err!(StatusCode::INTERNAL_SERVER_ERROR, name.as_deref(), INTERNAL);
+        }
+        return Html(render_mfa(&mfa_token, "")).into_response();
+    }
+    let _ = throttle::login_succeeded(kv, &subject, client.ip_address).await;
+
+    approve_device(
+        &state,
+        &client,
+        &device_code,
+        user.id,
+        vec![amr::PASSWORD.to_string()],
+    )
+    .await
+}
+
+/// Approve a pending device for `user_id`, who just signed in with `amr`.
+pub(super) async fn approve_device(
+    state: &AppState,
+    client: &ClientInfo,
+    device_code: &str,
+    user_id: String,
+    amr: Vec<String>,
+) -> Response {
+    let kv = state.kv.as_ref();
+    let data = match device::load_device_code(kv, device_code).await {
+        Ok(Some(d)) if d.status == DeviceStatus::Pending => d,
+        Ok(_) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Html(render_device_done("Code expired", INVALID_CODE)),
+            )
+                .into_response()
+        }
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Html(render_device_done("Something went wrong", INTERNAL)),
+            )
+                .into_response()
+        }
+    };
+
+    let client_id = data.client_id.clone();
+    let details = serde_json::json!({ "amr": amr, "device": true });
+    match device::decide(kv, device_code, data, Some((user_id.clone(), amr))).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Html(render_device_done("Code expired", INVALID_CODE)),
+            )
+                .into_response()
+        }
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Html(render_device_done("Something went wrong", INTERNAL)),
+            )
+                .into_response();
+        }
+    }
+
+    tracing::info!(user_id = %user_id, client_id = %client_id, "device approved");
+    events::record(
+        &state.db,
+        client,
+        AuthEvent::new(events::LOGIN_SUCCESS)
+            .user(&user_id)
+            .client(&client_id)
+            .details(details),
+    )
+    .await;
+
+    Html(render_device_done(
+        "Device connected",
+        "You're signed in on your device. You can close this page.",
+    ))
+    .into_response()
+}
@@ -0,0 +1,203 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::routing::post;
+use axum::{Json, Router};
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::auth::middleware::AuthUser;
+use crate::auth::tokens::generate_opaque_token;
+use crate::db::schema::users;
+use crate::error::{ApiError, ApiErrorBody};
+use crate::mail::EmailMessage;
+use crate::models::user::User;
+use crate::AppState;
+
+/// How long a verification link stays valid.
+pub const EMAIL_VERIFICATION_TTL_SECS: u64 = 24 * 60 * 60;
+
+/// Minimum gap between verification emails to the same user.
+pub const EMAIL_VERIFICATION_RESEND_SECS: u64 = 60;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/users/@me/email/verification",
+            post(send_email_verification),
+        )
+        .route("/email/verify", post(verify_email))
+}
+
+/// What a verification token in the KV store points at. The address is kept
+/// so a link for an old address can't verify a new one.
+#[derive(Debug, Serialize, Deserialize)]
+struct EmailVerificationData {
+    user_id: String,
+    email: String,
+}
+
+/// Email `user` a link to verify their current address.
+pub(crate) async fn send_verification_email(state: &AppState, user: &User) -> Result<(), ApiError> {
+    let Some(email) = user.email.as_deref() else {
+        return Err(ApiError::bad_request("No email address on this account"));
+    };
+
+    let token = generate_opaque_token("hev", 32);
+    let data = EmailVerificationData {
+        user_id: user.id.clone(),
+        email: email.to_string(),
+    };
+    let value = serde_json::to_string(&data).map_err(|_| ApiError::internal("serialization"))?;
+    state
+        .kv
+        .set_ex(
+            &format!("hub:email_verify:{token}"),
+            &value,
+            EMAIL_VERIFICATION_TTL_SECS,
+        )
+        .await?;
+
+    let link = format!(
+        "{}/verify-email?token={token}",
+        state.config.web_url.trim_end_matches('/')
+    );
+    let message = EmailMessage {
+        to: email.to_string(),
+        subject: "Verify your Voxora email address".to_string(),
+        text: format!(
+            "Hi {},\n\n\
+             Confirm this is your email address by opening the link below:\n\n\
+             {link}\n\n\
+             The link expires in 24 hours. If you didn't sign up for Voxora, \
+             you can ignore this email.\n",
+            user.display_name
+        ),
+    };
+    state.mailer.send(&message).await?;
+
+    tracing::info!(user_id = %user.id, "verification email sent");
+    Ok(())
+}
+
+// =========================================================================
+// POST /api/v1/users/@me/email/verification — Send a verification email
+// =========================================================================
+
+/// `POST /api/v1/users/@me/email/verification` — (Re)send the verification
+/// email for the current user's address.
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/@me/email/verification",
+    tag = "Users",
+    security(("bearer" = [])),
+    responses(
+        (status = 202, description = "Verification email sent"),
+        (status = 400, description = "No email address on the account", body = ApiErrorBody),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 409, description = "Email already verified", body = ApiErrorBody),
+        (status = 429, description = "Sent too recently", body = ApiErrorBody),
+    ),
+)]
+pub async fn send_email_verification(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<StatusCode, ApiError> {
+    let user: User = {
+        let mut conn = state.db.get().await?;
+        users::table
+            .find(&auth.user_id)
+            .select(User::as_select())
+            .first(&mut conn)
+            .await
+            .map_err(ApiError::from)?
+    };
+    if user.email.is_none() {
+        return Err(ApiError::bad_request("No email address on this account"));
+    }
+    if user.email_verified {
+        return Err(ApiError::conflict("Email is already verified"));
+    }
+
+    let throttle_key = format!("hub:email_verify_sent:{}", user.id);
+    if !state
+        .kv
+        .set_nx_ex(&throttle_key, "1", EMAIL_VERIFICATION_RESEND_SECS)
+        .await?
+    {
+        return Err(ApiError::too_many_requests(
+            "A verification email was sent recently. Please wait before requesting another.",
+        ));
+    }
+
+    send_verification_email(&state, &user).await?;
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+// =========================================================================
+// POST /api/v1/email/verify — Confirm an address
+// =========================================================================
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct VerifyEmailRequest {
+    /// Token from the verification link.
+    pub token: String,
+}
+
+/// `POST /api/v1/email/verify` — Mark an address as verified using the token
+/// from a verification email. Doesn't require a session, since the link may
+/// be opened on another device.
+#[utoipa::path(
+    post,
+    path = "/api/v1/email/verify",
+    tag = "Users",
+    request_body = VerifyEmailRequest,
+    responses(
+        (status = 204, description = "Email verified"),
+        (status = 400, description = "Invalid or expired token", body = ApiErrorBody),
+    ),
+)]
+pub async fn verify_email(
+    State(state): State<AppState>,
+    Json(body): Json<VerifyEmailRequest>,
+) -> Result<StatusCode, ApiError> {
+    let key = format!("hub:email_verify:{}", body.token);
+    let data: EmailVerificationData = match state.kv.get_del(&key).await? {
+        Some(v) => {
+            serde_json::from_str(&v).map_err(|_| ApiError::internal("corrupt verification data"))?
+        }
+        None => {
+            return Err(ApiError::bad_request(
+                "Invalid or expired verification link",
+            ))
+        }
+    };
+
+    let mut conn = state.db.get().await?;
+    let updated = diesel::update(
+        users::table
+            .find(&data.user_id)
+            .filter(users::email.eq(&data.email)),
+    )
+    .set((
+        users::email_verified.eq(true),
+        users::updated_at.eq(Utc::now()),
+    ))
+    .execute(&mut conn)
+    .await
+    .map_err(ApiError::from)?;
+
+    if updated == 0 {
+        // The account's address changed since the link was sent.
+        return Err(ApiError::bad_request(
+            "Invalid or expired verification link",
+        ));
+    }
+
+    tracing::info!(user_id = %data.user_id, "email verified");
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,274 @@
+use axum::extract::{Path, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::auth::middleware::AuthUser;
+use crate::auth::tokens::generate_opaque_token;
+use crate::db::schema::data_exports;
+use crate::error::{ApiError, ApiErrorBody};
+use crate::exports::{self, EXPORT_INTERVAL_HOURS};
+use crate::models::data_export::{
+    DataExport, DataExportResponse, NewDataExport, EXPORT_STATUS_FAILED, EXPORT_STATUS_READY,
+};
+use crate::AppState;
+
+/// How long a download link works.
+pub const EXPORT_DOWNLOAD_TTL_SECS: u64 = 15 * 60;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/users/@me/exports", get(list_exports).post(request_export))
+        .route("/users/@me/exports/{export_id}", get(get_export))
+        .route("/exports/download/{token}", get(download_export))
+}
+
+fn download_key(token: &str) -> String {
+    format!("hub:export_download:{token}")
+}
+
+/// Build the response for an export, minting a fresh download link if its
+/// archive is ready.
+async fn export_response(
+    state: &AppState,
+    export: DataExport,
+) -> Result<DataExportResponse, ApiError> {
+    let live =
+        export.status == EXPORT_STATUS_READY && export.expires_at.is_some_and(|at| at > Utc::now());
+    if !live {
+        return Ok(DataExportResponse::from_export(export, None));
+    }
+
+    let token = generate_opaque_token("dxl", 32);
+    state
+        .kv
+        .set_ex(&download_key(&token), &export.id, EXPORT_DOWNLOAD_TTL_SECS)
+        .await?;
+    let url = format!(
+        "{}/api/v1/exports/download/{token}",
+        state.config.hub_domain.trim_end_matches('/')
+    );
+    Ok(DataExportResponse::from_export(export, Some(url)))
+}
+
+// =========================================================================
+// POST /api/v1/users/@me/exports — Request a data export
+// =========================================================================
+
+/// `POST /api/v1/users/@me/exports` — Ask for a copy of everything the Hub
+/// holds about the current user. The archive is built in the background;
+/// poll the export until it is `ready`. One export per day.
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/@me/exports",
+    tag = "Data Exports",
+    security(("bearer" = [])),
+    responses(
+        (status = 202, description = "Export queued", body = DataExportResponse),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 429, description = "An export was already requested today", body = ApiErrorBody),
+    ),
+)]
+pub async fn request_export(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<(StatusCode, Json<DataExportResponse>), ApiError> {
+    // Claimed atomically so concurrent requests can't both get through. The
+    // export job releases the claim if the export fails, so the user can try
+    // again straight away.
+    let key = exports::request_key(&auth.user_id);
+    let ttl = (EXPORT_INTERVAL_HOURS * 3600) as u64;
+    if !state.kv.set_nx_ex(&key, &auth.user_id, ttl).await? {
+        return Err(export_too_soon());
+    }
+
+    let export = match insert_export(&state, &auth.user_id).await {
+        Ok(export) => export,
+        Err(e) => {
+            let _ = state.kv.del(&key).await;
+            return Err(e);
+        }
+    };
+
+    tracing::info!(user_id = %auth.user_id, export_id = %export.id, "data export requested");
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(DataExportResponse::from_export(export, None)),
+    ))
+}
+
+fn export_too_soon() -> ApiError {
+    ApiError::too_many_requests(
+        "A data export was requested in the last 24 hours. Please wait before requesting another.",
+    )
+}
+
+/// Queue an export unless one was requested recently. The key-value claim is
+/// the real limit; this covers a store that has been reset since.
+async fn insert_export(state: &AppState, user_id: &str) -> Result<DataExport, ApiError> {
+    let mut conn = state.db.get().await?;
+
+    // Failed exports don't count, so the user can try again straight away.
+    let since = Utc::now() - chrono::Duration::hours(EXPORT_INTERVAL_HOURS);
+    let recent: i64 = data_exports::table
+        .filter(data_exports::user_id.eq(user_id))
+        .filter(data_exports::created_at.gt(since))
+        .filter(data_exports::status.ne(EXPORT_STATUS_FAILED))
+        .count()
+        .get_result(&mut conn)
+        .await
+        .map_err(ApiError::from)?;
+    if recent > 0 {
+        return Err(export_too_soon());
+    }
+
+    diesel::insert_into(data_exports::table)
+        .values(&NewDataExport {
+            id: voxora_common::id::prefixed_ulid(voxora_common::id::prefix::DATA_EXPORT),
+            user_id: user_id.to_string(),
+        })
+        .returning(DataExport::as_returning())
+        .get_result(&mut conn)
+        .await
+        .map_err(ApiError::from)
+}
+
+// =========================================================================
+// GET /api/v1/users/@me/exports — List data exports
+// =========================================================================
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DataExportListResponse {
+    pub data: Vec<DataExportResponse>,
+}
+
+/// `GET /api/v1/users/@me/exports` — List the current user's exports,
+/// newest first.
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/@me/exports",
+    tag = "Data Exports",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Data exports", body = DataExportListResponse),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+    ),
+)]
+pub async fn list_exports(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Json<DataExportListResponse>, ApiError> {
+    let mut conn = state.db.get().await?;
+    let rows: Vec<DataExport> = data_exports::table
+        .filter(data_exports::user_id.eq(&auth.user_id))
+        .order(data_exports::created_at.desc())
+        .select(DataExport::as_select())
+        .load(&mut conn)
+        .await
+        .map_err(ApiError::from)?;
+
+    let mut data = Vec::with_capacity(rows.len());
+    for export in rows {
+        data.push(export_response(&state, export).await?);
+    }
+    Ok(Json(DataExportListResponse { data }))
+}
+
+// =========================================================================
+// GET /api/v1/users/@me/exports/{export_id} — Get one data export
+// =========================================================================
+
+/// `GET /api/v1/users/@me/exports/{export_id}` — Get an export's status.
+/// Once it is `ready`, the response carries a download link that works for
+/// 15 minutes.
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/@me/exports/{export_id}",
+    tag = "Data Exports",
+    security(("bearer" = [])),
+    params(("export_id" = String, Path, description = "Export ID")),
+    responses(
+        (status = 200, description = "Data export", body = DataExportResponse),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 404, description = "Export not found", body = ApiErrorBody),
+    ),
+)]
+pub async fn get_export(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(export_id): Path<String>,
+) -> Result<Json<DataExportResponse>, ApiError> {
+    let mut conn = state.db.get().await?;
+    let export: DataExport = data_exports::table
+        .find(&export_id)
+        .filter(data_exports::user_id.eq(&auth.user_id))
+        .select(DataExport::as_select())
+        .first(&mut conn)
+        .await
+        .optional()
+        .map_err(ApiError::from)?
+        .ok_or_else(|| ApiError::not_found("Export not found"))?;
+
+    Ok(Json(export_response(&state, export).await?))
+}
+
+// =========================================================================
+// GET /api/v1/exports/download/{token} — Download an export archive
+// =========================================================================
+
+/// `GET /api/v1/exports/download/{token}` — Download an export archive
+/// through a link from [`get_export`]. The link is the credential, so it
+/// can be opened directly in a browser.
+#[utoipa::path(
+    get,
+    path = "/api/v1/exports/download/{token}",
+    tag = "Data Exports",
+    params(("token" = String, Path, description = "Download token")),
+    responses(
+        (status = 200, description = "Zip archive", content_type = "application/zip"),
+        (status = 404, description = "Link expired or export no longer available", body = ApiErrorBody),
+    ),
+)]
+pub async fn download_export(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+) -> Result<Response, ApiError> {
+    let export_id = state
+        .kv
+        .get(&download_key(&token))
+        .await?
+        .ok_or_else(|| ApiError::not_found("Download link expired"))?;
+
+    let mut conn = state.db.get().await?;
+    let archive: Option<Vec<u8>> = data_exports::table
+        .find(&export_id)
+        .filter(data_exports::status.eq(EXPORT_STATUS_READY))
+        .filter(data_exports::expires_at.gt(Utc::now()))
+        .select(data_exports::archive)
+        .first(&mut conn)
+        .await
+        .optional()
+        .map_err(ApiError::from)?
+        .flatten();
+    let archive = archive.ok_or_else(|| ApiError::not_found("Export no longer available"))?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/zip".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"voxora-export-{export_id}.zip\""),
+            ),
+            (header::CACHE_CONTROL, "no-store".to_string()),
+        ],
+        archive,
+    )
+        .into_response())
+}
@@ -0,0 +1,249 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::auth::middleware::AuthUser;
+use crate::auth::upstream::{self, UpstreamPurpose};
+use crate::config::UpstreamIdp;
+use crate::db::schema::{passkeys, user_identities, users};
+use crate::error::{ApiError, ApiErrorBody};
+use crate::models::user_identity::{UserIdentity, UserIdentityResponse};
+use crate::AppState;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/identity-providers", get(list_identity_providers))
+        .route("/users/@me/identities", get(list_identities))
+        .route(
+            "/users/@me/identities/{provider}",
+            post(link_identity).delete(unlink_identity),
+        )
+}
+
+fn find_provider<'a>(state: &'a AppState, provider: &str) -> Result<&'a UpstreamIdp, ApiError> {
+    state
+        .config
+        .upstream_idp(provider)
+        .ok_or_else(|| ApiError::not_found("Identity provider not found"))
+}
+
+// =========================================================================
+// GET /api/v1/identity-providers — List upstream providers
+// =========================================================================
+
+/// An upstream provider users can sign in with.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct IdentityProviderResponse {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct IdentityProviderListResponse {
+    pub data: Vec<IdentityProviderResponse>,
+}
+
+/// `GET /api/v1/identity-providers` — List the configured upstream providers.
+#[utoipa::path(
+    get,
+    path = "/api/v1/identity-providers",
+    tag = "Identities",
+    responses(
+        (status = 200, description = "Configured providers", body = IdentityProviderListResponse),
+    ),
+)]
+pub async fn list_identity_providers(
+    State(state): State<AppState>,
+) -> Json<IdentityProviderListResponse> {
+    Json(IdentityProviderListResponse {
+        data: state
+            .config
+            .upstream_idps
+            .iter()
+            .map(|p| IdentityProviderResponse {
+                id: p.id.clone(),
+                name: p.name.clone(),
+            })
+            .collect(),
+    })
+}
+
+// =========================================================================
+// GET /api/v1/users/@me/identities — List linked identities
+// =========================================================================
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UserIdentityListResponse {
+    pub data: Vec<UserIdentityResponse>,
+}
+
+/// `GET /api/v1/users/@me/identities` — List the current user's linked
+/// provider accounts.
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/@me/identities",
+    tag = "Identities",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Linked identities", body = UserIdentityListResponse),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+    ),
+)]
+pub async fn list_identities(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Json<UserIdentityListResponse>, ApiError> {
+    let mut conn = state.db.get().await?;
+    let identities: Vec<UserIdentity> = user_identities::table
+        .filter(user_identities::user_id.eq(&auth.user_id))
+        .order(user_identities::created_at.asc())
+        .select(UserIdentity::as_select())
+        .load(&mut conn)
+        .await
+        .map_err(ApiError::from)?;
+
+    Ok(Json(UserIdentityListResponse {
+        data: identities
+            .into_iter()
+            .map(UserIdentityResponse::from)
+            .collect(),
+    }))
+}
+
+// =========================================================================
+// POST /api/v1/users/@me/identities/{provider} — Start linking
+// =========================================================================
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LinkIdentityResponse {
+    /// Send the user here. The provider returns them to the web client's
+    /// `/settings/connections` with `linked` or `error` in the query.
+    pub authorization_url: String,
+}
+
+/// `POST /api/v1/users/@me/identities/{provider}` — Start linking a provider
+/// account to the current user.
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/@me/identities/{provider}",
+    tag = "Identities",
+    security(("bearer" = [])),
+    params(
+        ("provider" = String, Path, description = "Upstream provider ID"),
+    ),
+    responses(
+        (status = 200, description = "Provider URL to continue at", body = LinkIdentityResponse),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 404, description = "Unknown provider", body = ApiErrorBody),
+        (status = 409, description = "Provider already linked", body = ApiErrorBody),
+    ),
+)]
+pub async fn link_identity(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(provider): Path<String>,
+) -> Result<Json<LinkIdentityResponse>, ApiError> {
+    let idp = find_provider(&state, &provider)?;
+
+    let mut conn = state.db.get().await?;
+    let linked: i64 = user_identities::table
+        .filter(user_identities::user_id.eq(&auth.user_id))
+        .filter(user_identities::provider.eq(&idp.id))
+        .count()
+        .get_result(&mut conn)
+        .await
+        .map_err(ApiError::from)?;
+    if linked > 0 {
+        return Err(ApiError::conflict(format!(
+            "{} is already connected",
+            idp.name
+        )));
+    }
+
+    let authorization_url = upstream::begin(
+        &state.config,
+        state.kv.as_ref(),
+        idp,
+        UpstreamPurpose::Link {
+            user_id: auth.user_id,
+        },
+    )
+    .await?;
+
+    Ok(Json(LinkIdentityResponse { authorization_url }))
+}
+
+// =========================================================================
+// DELETE /api/v1/users/@me/identities/{provider} — Unlink
+// =========================================================================
+
+/// `DELETE /api/v1/users/@me/identities/{provider}` — Disconnect a provider
+/// account. Refused when it's the only way left to sign in.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/users/@me/identities/{provider}",
+    tag = "Identities",
+    security(("bearer" = [])),
+    params(
+        ("provider" = String, Path, description = "Upstream provider ID"),
+    ),
+    responses(
+        (status = 204, description = "Identity unlinked"),
+        (status = 400, description = "Last sign-in method", body = ApiErrorBody),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 404, description = "Identity not linked", body = ApiErrorBody),
+    ),
+)]
+pub async fn unlink_identity(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(provider): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    let mut conn = state.db.get().await?;
+
+    let providers: Vec<String> = user_identities::table
+        .filter(user_identities::user_id.eq(&auth.user_id))
+        .select(user_identities::provider)
+        .load(&mut conn)
+        .await
+        .map_err(ApiError::from)?;
+    if !providers.contains(&provider) {
+        return Err(ApiError::not_found("Identity not linked"));
+    }
+
+    let password_hash: Option<String> = users::table
+        .find(&auth.user_id)
+        .select(users::password_hash)
+        .first(&mut conn)
+        .await
+        .map_err(ApiError::from)?;
+    let passkey_count: i64 = passkeys::table
+        .filter(passkeys::user_id.eq(&auth.user_id))
+        .count()
+        .get_result(&mut conn)
+        .await
+        .map_err(ApiError::from)?;
+    if providers.len() == 1 && password_hash.is_none() && passkey_count == 0 {
+        return Err(ApiError::bad_request(
+            "Set a password or add a passkey before disconnecting your last sign-in method",
+        ));
+    }
+
+    diesel::delete(
+        user_identities::table
+            .filter(user_identities::user_id.eq(&auth.user_id))
+            .filter(user_identities::provider.eq(&provider)),
+    )
+    .execute(&mut conn)
+    .await
+    .map_err(ApiError::from)?;
+
+    tracing::info!(user_id = %auth.user_id, %provider, "upstream identity unlinked");
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,302 @@
+use axum::extract::multipart::MultipartError;
+use axum::extract::{DefaultBodyLimit, Multipart, Path, Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, put};
+use axum::{Json, Router};
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+use crate::auth::middleware::AuthUser;
+use crate::db::schema::users;
+use crate::error::{ApiError, ApiErrorBody};
+use crate::media::{self, MediaKind, MAX_UPLOAD_BYTES};
+use crate::models::user::{User, UserResponse};
+use crate::AppState;
+
+/// Room for the multipart framing around the file itself.
+const MULTIPART_OVERHEAD_BYTES: usize = 64 * 1024;
+
+/// Upload routes, nested under `/api/v1`.
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/users/@me/avatar",
+            put(upload_avatar).delete(delete_avatar),
+        )
+        .route(
+            "/users/@me/banner",
+            put(upload_banner).delete(delete_banner),
+        )
+        .layer(DefaultBodyLimit::max(
+            MAX_UPLOAD_BYTES + MULTIPART_OVERHEAD_BYTES,
+        ))
+}
+
+/// Public media, served outside `/api/v1` so the URLs stay short and stable.
+pub fn files_router() -> Router<AppState> {
+    Router::new().route("/media/{kind}/{owner_id}/{media_id}", get(get_media))
+}
+
+/// Multipart form for avatar and banner uploads.
+#[derive(ToSchema)]
+#[allow(dead_code)]
+pub struct MediaUploadForm {
+    /// PNG, JPEG, GIF or WebP, up to 8 MiB and 8192 pixels on a side.
+    #[schema(value_type = String, format = Binary)]
+    file: Vec<u8>,
+}
+
+// =========================================================================
+// PUT/DELETE /api/v1/users/@me/avatar — Profile picture
+// =========================================================================
+
+/// `PUT /api/v1/users/@me/avatar` — Upload a new avatar. It's cropped to a
+/// square, re-encoded at several sizes and `avatar_url` is pointed at it.
+#[utoipa::path(
+    put,
+    path = "/api/v1/users/@me/avatar",
+    tag = "Users",
+    security(("bearer" = [])),
+    request_body(content = MediaUploadForm, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, description = "Updated user profile", body = UserResponse),
+        (status = 400, description = "Missing, unsupported or corrupt image", body = ApiErrorBody),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 413, description = "Image too large", body = ApiErrorBody),
+    ),
+)]
+pub async fn upload_avatar(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    multipart: Multipart,
+) -> Result<Json<UserResponse>, ApiError> {
+    upload(&state, &auth.user_id, MediaKind::Avatar, multipart).await
+}
+
+/// `DELETE /api/v1/users/@me/avatar` — Remove the current avatar.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/users/@me/avatar",
+    tag = "Users",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Updated user profile", body = UserResponse),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+    ),
+)]
+pub async fn delete_avatar(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Json<UserResponse>, ApiError> {
+    replace_url(&state, &auth.user_id, MediaKind::Avatar, None).await
+}
+
+// =========================================================================
+// PUT/DELETE /api/v1/users/@me/banner — Profile banner
+// =========================================================================
+
+/// `PUT /api/v1/users/@me/banner` — Upload a new banner. It's cropped to
+/// 3:1, re-encoded at several widths and `banner_url` is pointed at it.
+#[utoipa::path(
+    put,
+    path = "/api/v1/users/@me/banner",
+    tag = "Users",
+    security(("bearer" = [])),
+    request_body(content = MediaUploadForm, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, description = "Updated user profile", body = UserResponse),
+        (status = 400, description = "Missing, unsupported or corrupt image", body = ApiErrorBody),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 413, description = "Image too large", body = ApiErrorBody),
+    ),
+)]
+pub async fn upload_banner(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    multipart: Multipart,
+) -> Result<Json<UserResponse>, ApiError> {
+    upload(&state, &auth.user_id, MediaKind::Banner, multipart).await
+}
+
+/// `DELETE /api/v1/users/@me/banner` — Remove the current banner.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/users/@me/banner",
+    tag = "Users",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Updated user profile", body = UserResponse),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+    ),
+)]
+pub async fn delete_banner(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Json<UserResponse>, ApiError> {
+    replace_url(&state, &auth.user_id, MediaKind::Banner, None).await
+}
+
+fn multipart_error(e: MultipartError) -> ApiError {
+    if e.status() == StatusCode::PAYLOAD_TOO_LARGE {
+        ApiError::payload_too_large("Image must be at most 8 MiB")
+    } else {
+        ApiError::bad_request("Invalid multipart body")
+    }
+}
+
+/// The contents of the `file` field.
+async fn read_file(mut multipart: Multipart) -> Result<Vec<u8>, ApiError> {
+    while let Some(field) = multipart.next_field().await.map_err(multipart_error)? {
+        if field.name() == Some("file") {
+            let data = field.bytes().await.map_err(multipart_error)?;
+            if data.len() > MAX_UPLOAD_BYTES {
+                return Err(ApiError::payload_too_large("Image must be at most 8 MiB"));
+            }
+            return Ok(data.to_vec());
+        }
+    }
+    Err(ApiError::bad_request("Missing file field"))
+}
+
+async fn upload(
+    state: &AppState,
+    user_id: &str,
+    kind: MediaKind,
+    multipart: Multipart,
+) -> Result<Json<UserResponse>, ApiError> {
+    let data = read_file(multipart).await?;
+    let renditions = tokio::task::spawn_blocking(move || media::render(kind, &data))
+        .await
+        .map_err(|e| {
+            tracing::error!(?e, "image rendering task failed");
+            ApiError::internal("Failed to process image")
+        })??;
+
+    let media_id = voxora_common::id::prefixed_ulid(voxora_common::id::prefix::MEDIA);
+    for (size, png) in renditions {
+        state
+            .media
+            .put(&media::key(kind, user_id, &media_id, size), png)
+            .await?;
+    }
+
+    let url = media::public_url(&state.config.hub_domain, kind, user_id, &media_id);
+    let response = replace_url(state, user_id, kind, Some(url)).await?;
+
+    tracing::info!(%user_id, %media_id, kind = kind.as_str(), "profile media uploaded");
+    Ok(response)
+}
+
+/// Point the user's avatar or banner at `url` and delete the upload it
+/// pointed at before, if it was one.
+async fn replace_url(
+    state: &AppState,
+    user_id: &str,
+    kind: MediaKind,
+    url: Option<String>,
+) -> Result<Json<UserResponse>, ApiError> {
+    let mut conn = state.db.get().await?;
+
+    let current = users::table.find(user_id);
+    let previous: Option<String> = match kind {
+        MediaKind::Avatar => current.select(users::avatar_url).first(&mut conn).await,
+        MediaKind::Banner => current.select(users::banner_url).first(&mut conn).await,
+    }
+    .map_err(ApiError::from)?;
+
+    let now = Utc::now();
+    let target = diesel::update(users::table.find(user_id));
+    let user: User = match kind {
+        MediaKind::Avatar => {
+            target
+                .set((users::avatar_url.eq(&url), users::updated_at.eq(now)))
+                .returning(User::as_returning())
+                .get_result(&mut conn)
+                .await
+        }
+        MediaKind::Banner => {
+            target
+                .set((users::banner_url.eq(&url), users::updated_at.eq(now)))
+                .returning(User::as_returning())
+                .get_result(&mut conn)
+                .await
+        }
+    }
+    .map_err(ApiError::from)?;
+
+    // The profile already points elsewhere, so a leftover file is harmless.
+    let old_media_id = previous
+        .as_deref()
+        .and_then(|u| media::media_id_of(&state.config.hub_domain, kind, user_id, u));
+    if let Some(old_media_id) = old_media_id {
+        let prefix = media::upload_prefix(kind, user_id, old_media_id);
+        if let Err(e) = state.media.delete_prefix(&prefix).await {
+            tracing::warn!(%user_id, %prefix, error = %e.message, "failed to delete old media");
+        }
+    }
+
+    Ok(Json(UserResponse::from(user)))
+}
+
+// =========================================================================
+// GET /media/{kind}/{owner_id}/{media_id} — Serve an upload
+// =========================================================================
+
+#[derive(Debug, Deserialize)]
+pub struct MediaParams {
+    pub size: Option<u32>,
+}
+
+/// `GET /media/{kind}/{owner_id}/{media_id}` — Serve an uploaded avatar or
+/// banner as PNG. Uploads never change once stored, so responses can be
+/// cached forever.
+#[utoipa::path(
+    get,
+    path = "/media/{kind}/{owner_id}/{media_id}",
+    tag = "Users",
+    params(
+        ("kind" = String, Path, description = "avatars or banners"),
+        ("owner_id" = String, Path, description = "User ID"),
+        ("media_id" = String, Path, description = "Upload ID"),
+        ("size" = Option<u32>, Query, description = "Width: 64, 128, 256 or 512 for avatars (default 256); 600 or 1500 for banners (default 1500)"),
+    ),
+    responses(
+        (status = 200, description = "PNG image", content_type = "image/png"),
+        (status = 400, description = "Unsupported size", body = ApiErrorBody),
+        (status = 404, description = "No such upload", body = ApiErrorBody),
+    ),
+)]
+pub async fn get_media(
+    State(state): State<AppState>,
+    Path((kind, owner_id, media_id)): Path<(String, String, String)>,
+    Query(params): Query<MediaParams>,
+) -> Result<Response, ApiError> {
+    let kind = MediaKind::from_path(&kind)
+        .filter(|_| media::is_valid_segment(&owner_id) && media::is_valid_segment(&media_id))
+        .ok_or_else(|| ApiError::not_found("Media not found"))?;
+
+    let size = params.size.unwrap_or(kind.default_size());
+    if !kind.sizes().contains(&size) {
+        return Err(ApiError::bad_request("Unsupported size"));
+    }
+
+    let data = state
+        .media
+        .get(&media::key(kind, &owner_id, &media_id, size))
+        .await?
+        .ok_or_else(|| ApiError::not_found("Media not found"))?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "image/png"),
+            (header::CACHE_CONTROL, "public, max-age=31536000, immutable"),
+            (header::X_CONTENT_TYPE_OPTIONS, "nosniff"),
+        ],
+        data,
+    )
+        .into_response())
+}
@@ -0,0 +1,362 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::auth::events::{self, AuthEvent};
+use crate::auth::middleware::{AuthUser, ClientInfo};
+use crate::auth::{mfa, throttle};
+use crate::db::schema::{mfa_recovery_codes, users};
+use crate::error::{ApiError, ApiErrorBody};
+use crate::models::user::User;
+use crate::AppState;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/users/@me/mfa", get(get_mfa).delete(disable_mfa))
+        .route("/users/@me/mfa/totp", post(enroll_totp))
+        .route("/users/@me/mfa/totp/confirm", post(confirm_totp))
+        .route(
+            "/users/@me/mfa/recovery-codes",
+            post(regenerate_recovery_codes),
+        )
+}
+
+async fn load_user(state: &AppState, user_id: &str) -> Result<User, ApiError> {
+    let mut conn = state.db.get().await?;
+    users::table
+        .find(user_id)
+        .select(User::as_select())
+        .first(&mut conn)
+        .await
+        .map_err(ApiError::from)
+}
+
+/// Re-authenticate `user` with a second factor, and `password` when given,
+/// before a change to MFA. Wrong answers count against the account's sign-in
+/// failures, so a stolen access token can't be used to guess them.
+async fn reauthenticate(
+    state: &AppState,
+    client: &ClientInfo,
+    user: &User,
+    password: Option<&str>,
+    code: &str,
+) -> Result<(), ApiError> {
+    if throttle::check_login(state.kv.as_ref(), &user.id, client.ip_address)
+        .await?
+        .is_some()
+    {
+        return Err(ApiError::too_many_requests(throttle::LOGIN_THROTTLED));
+    }
+
+    let password_ok = password.is_none_or(|password| {
+        user.password_hash
+            .as_deref()
+            .is_some_and(|hash| super::oidc::verify_password(password, hash).is_ok())
+    });
+    let factor_ok = password_ok
+        && mfa::verify_second_factor(&state.db, state.kv.as_ref(), user, code)
+            .await?
+            .is_some();
+    if !factor_ok {
+        events::record(
+            &state.db,
+            client,
+            AuthEvent::new(events::MFA_FAILURE).user(&user.id),
+        )
+        .await;
+        throttle::login_failed(state, client, &user.id, Some(user)).await?;
+        return Err(ApiError::forbidden(if password.is_some() {
+            "Invalid password or code"
+        } else {
+            "Invalid code"
+        }));
+    }
+
+    throttle::login_succeeded(state.kv.as_ref(), &user.id, client.ip_address).await
+}
+
+// =========================================================================
+// GET /api/v1/users/@me/mfa — MFA status
+// =========================================================================
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MfaStatusResponse {
+    pub totp_enabled: bool,
+    pub recovery_codes_remaining: i64,
+}
+
+/// `GET /api/v1/users/@me/mfa` — Return the current user's MFA status.
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/@me/mfa",
+    tag = "MFA",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "MFA status", body = MfaStatusResponse),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+    ),
+)]
+pub async fn get_mfa(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Json<MfaStatusResponse>, ApiError> {
+    let user = load_user(&state, &auth.user_id).await?;
+
+    let mut conn = state.db.get().await?;
+    let remaining: i64 = mfa_recovery_codes::table
+        .filter(mfa_recovery_codes::user_id.eq(&auth.user_id))
+        .filter(mfa_recovery_codes::used_at.is_null())
+        .count()
+        .get_result(&mut conn)
+        .await
+        .map_err(ApiError::from)?;
+
+    Ok(Json(MfaStatusResponse {
+        totp_enabled: user.mfa_enabled,
+        recovery_codes_remaining: if user.mfa_enabled { remaining } else { 0 },
+    }))
+}
+
+// =========================================================================
+// POST /api/v1/users/@me/mfa/totp — Start TOTP enrollment
+// =========================================================================
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TotpEnrollmentResponse {
+    /// Base32 secret, for manual entry.
+    pub secret: String,
+    /// `otpauth://` URI to render as a QR code.
+    pub provisioning_uri: String,
+}
+
+/// `POST /api/v1/users/@me/mfa/totp` — Generate a new TOTP secret.
+///
+/// MFA isn't enabled until the secret is confirmed with a code. Calling this
+/// again before confirming replaces the pending secret.
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/@me/mfa/totp",
+    tag = "MFA",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Pending TOTP secret", body = TotpEnrollmentResponse),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 409, description = "MFA already enabled", body = ApiErrorBody),
+    ),
+)]
+pub async fn enroll_totp(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Json<TotpEnrollmentResponse>, ApiError> {
+    let user = load_user(&state, &auth.user_id).await?;
+    if user.mfa_enabled {
+        return Err(ApiError::conflict("MFA is already enabled"));
+    }
+
+    let secret = mfa::generate_totp_secret();
+    let mut conn = state.db.get().await?;
+    diesel::update(users::table.find(&user.id))
+        .set((
+            users::mfa_secret.eq(&secret),
+            users::updated_at.eq(Utc::now()),
+        ))
+        .execute(&mut conn)
+        .await
+        .map_err(ApiError::from)?;
+
+    let account = user.email.as_deref().unwrap_or(&user.username);
+    Ok(Json(TotpEnrollmentResponse {
+        provisioning_uri: mfa::provisioning_uri(&secret, account),
+        secret,
+    }))
+}
+
+// =========================================================================
+// POST /api/v1/users/@me/mfa/totp/confirm — Finish TOTP enrollment
+// =========================================================================
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct MfaCodeRequest {
+    pub code: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RecoveryCodesResponse {
+    /// Single-use codes. Shown only once; only hashes are stored.
+    pub recovery_codes: Vec<String>,
+}
+
+/// `POST /api/v1/users/@me/mfa/totp/confirm` — Enable MFA by proving the
+/// authenticator app holds the pending secret.
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/@me/mfa/totp/confirm",
+    tag = "MFA",
+    security(("bearer" = [])),
+    request_body = MfaCodeRequest,
+    responses(
+        (status = 200, description = "MFA enabled", body = RecoveryCodesResponse),
+        (status = 400, description = "Invalid code or no pending enrollment", body = ApiErrorBody),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 409, description = "MFA already enabled", body = ApiErrorBody),
+    ),
+)]
+pub async fn confirm_totp(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    client: ClientInfo,
+    Json(body): Json<MfaCodeRequest>,
+) -> Result<Json<RecoveryCodesResponse>, ApiError> {
+    let user = load_user(&state, &auth.user_id).await?;
+    if user.mfa_enabled {
+        return Err(ApiError::conflict("MFA is already enabled"));
+    }
+    let secret = user
+        .mfa_secret
+        .as_deref()
+        .ok_or_else(|| ApiError::bad_request("No TOTP enrollment in progress"))?;
+
+    let now = Utc::now().timestamp().max(0) as u64;
+    if mfa::verify_totp(secret, &body.code, now).is_none() {
+        return Err(ApiError::bad_request("Invalid code"));
+    }
+
+    let mut conn = state.db.get().await?;
+    diesel::update(users::table.find(&user.id))
+        .set((
+            users::mfa_enabled.eq(true),
+            users::updated_at.eq(Utc::now()),
+        ))
+        .execute(&mut conn)
+        .await
+        .map_err(ApiError::from)?;
+
+    let recovery_codes = mfa::replace_recovery_codes(&state.db, &user.id).await?;
+
+    tracing::info!(user_id = %user.id, "mfa enabled");
+    events::record(
+        &state.db,
+        &client,
+        AuthEvent::new(events::MFA_ENABLED).user(&user.id),
+    )
+    .await;
+
+    Ok(Json(RecoveryCodesResponse { recovery_codes }))
+}
+
+// =========================================================================
+// POST /api/v1/users/@me/mfa/recovery-codes — Regenerate recovery codes
+// =========================================================================
+
+/// `POST /api/v1/users/@me/mfa/recovery-codes` — Replace all recovery codes.
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/@me/mfa/recovery-codes",
+    tag = "MFA",
+    security(("bearer" = [])),
+    request_body = MfaCodeRequest,
+    responses(
+        (status = 200, description = "New recovery codes", body = RecoveryCodesResponse),
+        (status = 400, description = "MFA not enabled", body = ApiErrorBody),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 403, description = "Invalid code", body = ApiErrorBody),
+        (status = 429, description = "Too many failed attempts", body = ApiErrorBody),
+    ),
+)]
+pub async fn regenerate_recovery_codes(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    client: ClientInfo,
+    Json(body): Json<MfaCodeRequest>,
+) -> Result<Json<RecoveryCodesResponse>, ApiError> {
+    let user = load_user(&state, &auth.user_id).await?;
+    if !user.mfa_enabled {
+        return Err(ApiError::bad_request("MFA is not enabled"));
+    }
+    reauthenticate(&state, &client, &user, None, &body.code).await?;
+
+    let recovery_codes = mfa::replace_recovery_codes(&state.db, &user.id).await?;
+    Ok(Json(RecoveryCodesResponse { recovery_codes }))
+}
+
+// =========================================================================
+// DELETE /api/v1/users/@me/mfa — Disable MFA
+// =========================================================================
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct DisableMfaRequest {
+    /// Required when the account has a password.
+    #[serde(default)]
+    pub password: Option<String>,
+    /// A TOTP code or an unused recovery code.
+    pub code: String,
+}
+
+/// `DELETE /api/v1/users/@me/mfa` — Turn MFA off.
+///
+/// Requires re-authentication with the password and a second factor, so a
+/// stolen access token alone can't downgrade the account. Accounts that only
+/// sign in through an upstream provider have no password and give the second
+/// factor alone.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/users/@me/mfa",
+    tag = "MFA",
+    security(("bearer" = [])),
+    request_body = DisableMfaRequest,
+    responses(
+        (status = 204, description = "MFA disabled"),
+        (status = 400, description = "MFA not enabled", body = ApiErrorBody),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 403, description = "Invalid password or code", body = ApiErrorBody),
+        (status = 429, description = "Too many failed attempts", body = ApiErrorBody),
+    ),
+)]
+pub async fn disable_mfa(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    client: ClientInfo,
+    Json(body): Json<DisableMfaRequest>,
+) -> Result<StatusCode, ApiError> {
+    let user = load_user(&state, &auth.user_id).await?;
+    if !user.mfa_enabled {
+        return Err(ApiError::bad_request("MFA is not enabled"));
+    }
+    // Accounts from an upstream provider have no password to give.
+    let password = user
+        .password_hash
+        .is_some()
+        .then(|| body.password.as_deref().unwrap_or_default());
+    reauthenticate(&state, &client, &user, password, &body.code).await?;
+
+    let mut conn = state.db.get().await?;
+    diesel::update(users::table.find(&user.id))
+        .set((
+            users::mfa_enabled.eq(false),
+            users::mfa_secret.eq(None::<String>),
+            users::updated_at.eq(Utc::now()),
+        ))
+        .execute(&mut conn)
+        .await
+        .map_err(ApiError::from)?;
+    diesel::delete(mfa_recovery_codes::table.filter(mfa_recovery_codes::user_id.eq(&user.id)))
+        .execute(&mut conn)
+        .await
+        .map_err(ApiError::from)?;
+
+    tracing::info!(user_id = %user.id, "mfa disabled");
+    events::record(
+        &state.db,
+        &client,
+        AuthEvent::new(events::MFA_DISABLED).user(&user.id),
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,8 +1,24 @@
+pub mod account_unlock;
+pub mod admin;
+pub mod bots;
+pub mod clients;
+pub mod device;
+pub mod email;
+pub mod exports;
 pub mod health;
+pub mod identities;
+pub mod media;
+pub mod mfa;
+pub mod notifications;
 pub mod oidc;
+pub mod passkeys;
+pub mod password_reset;
 pub mod pods;
+pub mod security_events;
+pub mod sessions;
 pub mod sia;
 pub mod turn;
+pub mod upstream;
 pub mod users;
 
 use axum::Router;
@@ -16,11 +32,28 @@ pub fn router() -> Router<AppState> {
         .merge(health::router())
         // OIDC/OAuth routes live outside /api/v1 (standards-based paths).
         .merge(oidc::router())
+        .merge(device::router())
+        .merge(upstream::router())
+        .merge(media::files_router())
         .nest(
             "/api/v1",
             users::router()
+                .merge(media::router())
+                .merge(email::router())
+                .merge(mfa::router())
+                .merge(passkeys::router())
+                .merge(identities::router())
+                .merge(password_reset::router())
+                .merge(account_unlock::router())
+                .merge(sessions::router())
+                .merge(security_events::router())
+                .merge(exports::router())
+                .merge(clients::router())
+                .merge(bots::router())
+                .merge(admin::router())
                 .merge(sia::router())
                 .merge(pods::router())
+                .merge(notifications::router())
                 .merge(turn::router()),
         )
 }
@@ -48,17 +81,92 @@ impl Modify for SecurityAddon {
         oidc::jwks,
         oidc::authorize,
         oidc::authorize_submit,
+        oidc::authorize_mfa,
+        oidc::authorize_passkey_begin,
+        oidc::authorize_passkey,
         oidc::token,
         oidc::userinfo,
         oidc::revoke,
+        oidc::introspect,
+        device::device_authorization,
+        device::verify_device,
+        device::verify_device_submit,
+        upstream::authorize_upstream,
+        upstream::upstream_callback,
         // Users
         users::create_user,
         users::get_me,
         users::update_me,
+        users::delete_me,
+        users::cancel_deletion,
         users::get_user,
         users::get_my_pods,
         users::get_preferences,
         users::update_preferences,
+        media::upload_avatar,
+        media::delete_avatar,
+        media::upload_banner,
+        media::delete_banner,
+        media::get_media,
+        email::send_email_verification,
+        email::verify_email,
+        password_reset::request_password_reset,
+        password_reset::confirm_password_reset,
+        account_unlock::unlock_account,
+        // Sessions
+        sessions::list_sessions,
+        sessions::revoke_session,
+        sessions::revoke_other_sessions,
+        security_events::list_security_events,
+        // Data exports
+        exports::request_export,
+        exports::list_exports,
+        exports::get_export,
+        exports::download_export,
+        // OAuth clients
+        clients::create_client,
+        clients::list_clients,
+        clients::get_client,
+        clients::update_client,
+        clients::delete_client,
+        clients::rotate_secret,
+        // Bots
+        bots::create_bot,
+        bots::list_bots,
+        bots::delete_bot,
+        // Admin
+        admin::list_signing_keys,
+        admin::rotate_signing_keys,
+        admin::ban_user,
+        admin::get_user_ban,
+        admin::unban_user,
+        admin::search_users,
+        admin::get_user,
+        admin::list_user_sessions,
+        admin::logout_user,
+        admin::unlock_user,
+        admin::force_password_reset,
+        admin::update_user_flags,
+        admin::list_all_pods,
+        admin::update_pod,
+        admin::list_admin_audit_log,
+        // MFA
+        mfa::get_mfa,
+        mfa::enroll_totp,
+        mfa::confirm_totp,
+        mfa::regenerate_recovery_codes,
+        mfa::disable_mfa,
+        // Passkeys
+        passkeys::list_passkeys,
+        passkeys::begin_registration,
+        passkeys::complete_registration,
+        passkeys::rename_passkey,
+        passkeys::delete_passkey,
+        // Identities
+        identities::list_identity_providers,
+        identities::list_identities,
+        identities::link_identity,
+        identities::unlink_identity,
         // SIA
         sia::issue_sia,
         // Pods
@@ -66,6 +174,9 @@ impl Modify for SecurityAddon {
         pods::heartbeat,
         pods::list_pods,
         pods::get_pod,
+        // Notifications
+        notifications::relay_notifications,
+        notifications::notifications_ws,
         // TURN
         turn::turn_credentials,
     ),
@@ -75,6 +186,7 @@ impl Modify for SecurityAddon {
             crate::error::ApiErrorBody,
             crate::error::ApiErrorDetail,
             crate::error::FieldError,
+            crate::error::OAuthErrorBody,
             // User models
             crate::models::user::UserResponse,
             crate::models::user::PublicUserResponse,
@@ -85,10 +197,60 @@ impl Modify for SecurityAddon {
             health::HealthResponse,
             users::CreateUserRequest,
             users::UpdateProfileRequest,
+            users::DeleteAccountRequest,
             users::MyPodEntry,
             users::MyPodsResponse,
             users::PreferencesResponse,
             users::UpdatePreferencesRequest,
+            media::MediaUploadForm,
+            email::VerifyEmailRequest,
+            password_reset::RequestPasswordResetRequest,
+            password_reset::ConfirmPasswordResetRequest,
+            account_unlock::UnlockAccountRequest,
+            crate::models::session::SessionResponse,
+            sessions::SessionListResponse,
+            crate::models::auth_event::AuthEventResponse,
+            security_events::SecurityEventListResponse,
+            crate::models::oauth_client::OAuthClientResponse,
+            clients::CreateOAuthClientRequest,
+            clients::UpdateOAuthClientRequest,
+            clients::OAuthClientSecretResponse,
+            clients::OAuthClientListResponse,
+            bots::BotResponse,
+            bots::CreateBotRequest,
+            bots::BotCredentialsResponse,
+            bots::BotListResponse,
+            admin::SigningKeyResponse,
+            admin::SigningKeyListResponse,
+            admin::BanUserRequest,
+            crate::models::user_ban::UserBan,
+            admin::AdminUserListResponse,
+            admin::AdminLogoutResponse,
+            admin::AdminUnlockResponse,
+            admin::AdminPasswordResetResponse,
+            admin::UpdateUserFlagsRequest,
+            admin::UpdatePodRequest,
+            admin::AdminAuditLogResponse,
+            crate::models::admin_audit_log::AdminAuditLogEntry,
+            crate::auth::keys::KeyStatus,
+            mfa::MfaStatusResponse,
+            mfa::TotpEnrollmentResponse,
+            mfa::MfaCodeRequest,
+            mfa::RecoveryCodesResponse,
+            mfa::DisableMfaRequest,
+            crate::models::passkey::PasskeyResponse,
+            passkeys::PasskeyListResponse,
+            passkeys::CompleteRegistrationRequest,
+            passkeys::RenamePasskeyRequest,
+            crate::models::user_identity::UserIdentityResponse,
+            identities::IdentityProviderResponse,
+            identities::IdentityProviderListResponse,
+            identities::UserIdentityListResponse,
+            identities::LinkIdentityResponse,
+            crate::models::data_export::DataExportResponse,
+            exports::DataExportListResponse,
+            crate::auth::webauthn::CreationOptions,
+            crate::auth::webauthn::RequestOptions,
             oidc::OpenIdConfiguration,
             oidc::JwksResponse,
             oidc::JwkKey,
@@ -96,6 +258,10 @@ impl Modify for SecurityAddon {
             oidc::TokenRequest,
             oidc::TokenResponse,
             oidc::RevokeRequest,
+            oidc::IntrospectRequest,
+            oidc::IntrospectResponse,
+            device::DeviceAuthorizationRequest,
+            device::DeviceAuthorizationResponse,
             sia::SiaRequest,
             sia::SiaResponse,
             pods::RegisterPodRequest,
@@ -103,6 +269,9 @@ impl Modify for SecurityAddon {
             pods::HeartbeatResponse,
             pods::ListPodsQuery,
             pods::ListPodsResponse,
+            notifications::RelayRequest,
+            notifications::RelayNotification,
+            crate::relay::NotificationEnvelope,
             turn::TurnCredentialsResponse,
             turn::IceServer,
         )
@@ -112,8 +281,17 @@ impl Modify for SecurityAddon {
         (name = "Health", description = "Health check"),
         (name = "OIDC", description = "OpenID Connect endpoints"),
         (name = "Users", description = "User management"),
+        (name = "Sessions", description = "Signed-in devices"),
+        (name = "Data Exports", description = "Account data exports"),
+        (name = "OAuth Clients", description = "Third-party OAuth client registration"),
+        (name = "Bots", description = "Bot accounts"),
+        (name = "Admin", description = "Staff-only operations"),
+        (name = "MFA", description = "Multi-factor authentication"),
+        (name = "Passkeys", description = "WebAuthn passkey management"),
+        (name = "Identities", description = "Upstream identity providers and linked accounts"),
         (name = "SIA", description = "Signed Identity Assertions"),
         (name = "Pods", description = "Pod registration and discovery"),
+        (name = "Notifications", description = "Hub notification relay"),
         (name = "TURN", description = "TURN credential provisioning"),
     )
 )]
@@ -0,0 +1,286 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use axum::extract::ws::{CloseFrame, Message, WebSocket};
+use axum::extract::{State, WebSocketUpgrade};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use futures_util::stream::SplitSink;
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time;
+use utoipa::ToSchema;
+
+use crate::auth::pod::PodClient;
+use crate::auth::tokens;
+use crate::db::schema::user_pod_bookmarks;
+use crate::error::{ApiError, ApiErrorBody};
+use crate::relay::{NotificationEnvelope, RelayedNotification};
+use crate::AppState;
+
+/// Most envelopes a Pod may push in one request.
+pub const MAX_RELAY_BATCH: usize = 1000;
+
+/// How long a new connection has to identify.
+const IDENTIFY_TIMEOUT_SECS: u64 = 10;
+
+/// How often the server pings and re-checks the connection's access token.
+const KEEPALIVE_SECS: u64 = 30;
+
+/// Close codes (4000-range for application-level), as on the Pod gateway.
+const CLOSE_NOT_AUTHENTICATED: u16 = 4003;
+const CLOSE_AUTH_FAILED: u16 = 4004;
+const CLOSE_SESSION_REVOKED: u16 = 4005;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/notifications/relay", post(relay_notifications))
+        .route("/notifications/ws", get(notifications_ws))
+}
+
+// =========================================================================
+// POST /api/v1/notifications/relay — Pod pushes activity envelopes
+// =========================================================================
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RelayNotification {
+    pub user_id: String,
+    pub community_id: String,
+    pub channel_id: String,
+    pub unread_count: i64,
+    pub has_mention: bool,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RelayRequest {
+    /// At most 1000 per request.
+    pub notifications: Vec<RelayNotification>,
+}
+
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty() && id.len() <= 64
+}
+
+/// `POST /api/v1/notifications/relay` — Forward unread-activity envelopes to
+/// users' Hub WebSocket connections.
+///
+/// Authenticated via the Pod's `client_secret` as a Bearer token. Envelopes
+/// for users who have never signed in to the Pod are dropped, as are those
+/// for users who aren't connected.
+#[utoipa::path(
+    post,
+    path = "/api/v1/notifications/relay",
+    tag = "Notifications",
+    security(("bearer" = [])),
+    request_body = RelayRequest,
+    responses(
+        (status = 202, description = "Envelopes accepted for delivery"),
+        (status = 400, description = "Too many or malformed envelopes", body = ApiErrorBody),
+        (status = 401, description = "Invalid credentials", body = ApiErrorBody),
+        (status = 403, description = "Pod is not active", body = ApiErrorBody),
+    ),
+)]
+pub async fn relay_notifications(
+    State(state): State<AppState>,
+    pod_client: PodClient,
+    Json(body): Json<RelayRequest>,
+) -> Result<StatusCode, ApiError> {
+    let pod = &pod_client.pod;
+    if pod.status != "active" {
+        return Err(ApiError::forbidden("Pod is not active"));
+    }
+
+    if body.notifications.len() > MAX_RELAY_BATCH {
+        return Err(ApiError::bad_request(format!(
+            "At most {MAX_RELAY_BATCH} notifications per request"
+        )));
+    }
+    let valid = body.notifications.iter().all(|n| {
+        is_valid_id(&n.user_id)
+            && is_valid_id(&n.community_id)
+            && is_valid_id(&n.channel_id)
+            && n.unread_count >= 0
+    });
+    if !valid {
+        return Err(ApiError::bad_request("Invalid notification"));
+    }
+
+    // Only users who have signed in to this Pod can hear from it.
+    let user_ids: Vec<&str> = body
+        .notifications
+        .iter()
+        .map(|n| n.user_id.as_str())
+        .collect();
+    let mut conn = state.db.get().await?;
+    let members: HashSet<String> = user_pod_bookmarks::table
+        .filter(user_pod_bookmarks::pod_id.eq(&pod.id))
+        .filter(user_pod_bookmarks::user_id.eq_any(&user_ids))
+        .select(user_pod_bookmarks::user_id)
+        .load::<String>(&mut conn)
+        .await
+        .map_err(ApiError::from)?
+        .into_iter()
+        .collect();
+
+    let mut relayed = 0;
+    for n in body.notifications {
+        if !members.contains(&n.user_id) {
+            continue;
+        }
+        state.relay.publish(RelayedNotification {
+            user_id: n.user_id,
+            envelope: NotificationEnvelope {
+                pod_id: pod.id.clone(),
+                community_id: n.community_id,
+                channel_id: n.channel_id,
+                unread_count: n.unread_count,
+                has_mention: n.has_mention,
+            },
+        });
+        relayed += 1;
+    }
+
+    tracing::debug!(pod_id = %pod.id, relayed, "notifications relayed");
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+// =========================================================================
+// GET /api/v1/notifications/ws — User's notification stream
+// =========================================================================
+
+/// Messages sent by the client.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage {
+    /// First message on every connection.
+    Identify { token: String },
+}
+
+/// Messages sent by the server.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ServerMessage<'a> {
+    Ready { user_id: &'a str },
+    Notification { data: &'a NotificationEnvelope },
+}
+
+type WsSink = SplitSink<WebSocket, Message>;
+
+/// `GET /api/v1/notifications/ws` — Open the user's notification stream.
+///
+/// WebSocket. The client sends `{"type": "identify", "token": "<access
+/// token>"}` within 10 seconds and gets `{"type": "ready", "user_id": ...}`
+/// back, then `{"type": "notification", "data": {...}}` for each envelope a
+/// Pod relays. The socket is closed with 4005 once the token stops being
+/// valid, e.g. after signing out.
+#[utoipa::path(
+    get,
+    path = "/api/v1/notifications/ws",
+    tag = "Notifications",
+    responses(
+        (status = 101, description = "Switching to the WebSocket protocol"),
+    ),
+)]
+pub async fn notifications_ws(
+    ws: WebSocketUpgrade,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    ws.on_upgrade(move |socket| handle_connection(socket, state))
+}
+
+async fn handle_connection(socket: WebSocket, state: AppState) {
+    let (mut ws_tx, mut ws_rx) = socket.split();
+
+    let identify = time::timeout(Duration::from_secs(IDENTIFY_TIMEOUT_SECS), async {
+        while let Some(Ok(msg)) = ws_rx.next().await {
+            match msg {
+                Message::Text(text) => {
+                    return serde_json::from_str::<ClientMessage>(&text).ok();
+                }
+                Message::Close(_) => return None,
+                _ => continue,
+            }
+        }
+        None
+    })
+    .await;
+    let Ok(Some(ClientMessage::Identify { token })) = identify else {
+        send_close(&mut ws_tx, CLOSE_NOT_AUTHENTICATED, "Identify required").await;
+        return;
+    };
+
+    let user_id = match tokens::lookup_access_token(state.kv.as_ref(), &token).await {
+        Ok(Some(data)) => data.user_id,
+        _ => {
+            send_close(&mut ws_tx, CLOSE_AUTH_FAILED, "Authentication failed").await;
+            return;
+        }
+    };
+
+    // Subscribe before READY so nothing relayed after it is missed.
+    let mut notifications = state.relay.subscribe();
+    if send_json(&mut ws_tx, &ServerMessage::Ready { user_id: &user_id })
+        .await
+        .is_err()
+    {
+        return;
+    }
+    tracing::debug!(%user_id, "notification stream connected");
+
+    let mut keepalive = time::interval(Duration::from_secs(KEEPALIVE_SECS));
+    keepalive.tick().await; // First tick fires immediately; skip it.
+
+    loop {
+        tokio::select! {
+            msg = ws_rx.next() => match msg {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                // The stream is one-way after identify.
+                Some(Ok(_)) => {}
+            },
+            received = notifications.recv() => match received {
+                Ok(n) if n.user_id == user_id => {
+                    let msg = ServerMessage::Notification { data: &n.envelope };
+                    if send_json(&mut ws_tx, &msg).await.is_err() {
+                        break;
+                    }
+                }
+                Ok(_) => {}
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::debug!(%user_id, skipped, "notification stream lagged");
+                }
+                Err(RecvError::Closed) => break,
+            },
+            _ = keepalive.tick() => {
+                // Signing out, revoking the session or a ban deletes the token.
+                if let Ok(None) = tokens::lookup_access_token(state.kv.as_ref(), &token).await {
+                    send_close(&mut ws_tx, CLOSE_SESSION_REVOKED, "Session ended").await;
+                    break;
+                }
+                if ws_tx.send(Message::Ping(Default::default())).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+
+    tracing::debug!(%user_id, "notification stream closed");
+}
+
+async fn send_json(ws_tx: &mut WsSink, msg: &ServerMessage<'_>) -> Result<(), axum::Error> {
+    let text = serde_json::to_string(msg).expect("server messages serialize");
+    ws_tx.send(Message::Text(text.into())).await
+}
+
+async fn send_close(ws_tx: &mut WsSink, code: u16, reason: &'static str) {
+    let frame = CloseFrame {
+        code,
+        reason: reason.into(),
+    };
+    let _ = ws_tx.send(Message::Close(Some(frame))).await;
+}
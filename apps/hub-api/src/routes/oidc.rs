@@ -1,28 +1,41 @@
+use std::sync::LazyLock;
+
 use axum::extract::{Query, State};
-use axum::http::StatusCode;
+use axum::http::{HeaderMap, StatusCode};
 use axum::response::{Html, IntoResponse, Redirect, Response};
 use axum::routing::{get, post};
 use axum::{Form, Json, Router};
 use chrono::{Duration, Utc};
 use diesel::prelude::*;
 use diesel_async::RunQueryDsl;
+use ipnet::IpNet;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use utoipa::ToSchema;
 
+use crate::auth::bans;
+use crate::auth::clients;
+use crate::auth::device::{self, DeviceStatus};
+use crate::auth::events::{self, AuthEvent};
+use crate::auth::mfa::{self, amr, MfaChallengeData};
+use crate::auth::middleware::ClientInfo;
+use crate::auth::pod::PodClient;
+use crate::auth::throttle;
 use crate::auth::tokens::{
     self, generate_access_token, generate_opaque_token, generate_refresh_token, mint_id_token,
     AccessTokenData, AuthCodeData, ACCESS_TOKEN_TTL_SECS, REFRESH_TOKEN_TTL_DAYS,
 };
+use crate::auth::webauthn::{self, AuthenticationCredential, Ceremony, RequestOptions};
+use crate::config::UpstreamIdp;
 use crate::db::schema::{sessions, users};
-use crate::error::{ApiError, ApiErrorBody};
+use crate::error::{ApiError, ApiErrorBody, OAuthError, OAuthErrorBody};
+use crate::models::oauth_client::{
+    GRANT_AUTHORIZATION_CODE, GRANT_CLIENT_CREDENTIALS, GRANT_DEVICE_CODE, GRANT_REFRESH_TOKEN,
+};
 use crate::models::session::NewSession;
 use crate::models::user::User;
 use crate::AppState;
 
-/// Allowed client_id for Phase 1.
-const CLIENT_ID: &str = "voxora-web";
-
 // ===========================================================================
 // Schema-only structs for OpenAPI documentation
 // ===========================================================================
@@ -36,6 +49,8 @@ pub struct OpenIdConfiguration {
     pub userinfo_endpoint: String,
     pub jwks_uri: String,
     pub revocation_endpoint: String,
+    pub introspection_endpoint: String,
+    pub device_authorization_endpoint: String,
     pub response_types_supported: Vec<String>,
     pub grant_types_supported: Vec<String>,
     pub subject_types_supported: Vec<String>,
@@ -85,12 +100,22 @@ pub fn router() -> Router<AppState> {
         .route("/oidc/.well-known/jwks.json", get(jwks))
         // Authorization + login form submission
         .route("/oidc/authorize", get(authorize).post(authorize_submit))
+        // Second-factor challenge for MFA-enabled accounts
+        .route("/oidc/authorize/mfa", post(authorize_mfa))
+        // Passwordless sign-in with a passkey
+        .route(
+            "/oidc/authorize/passkey/begin",
+            post(authorize_passkey_begin),
+        )
+        .route("/oidc/authorize/passkey", post(authorize_passkey))
         // Token endpoint
         .route("/oidc/token", post(token))
         // UserInfo
         .route("/oidc/userinfo", get(userinfo))
         // Revocation
         .route("/oidc/revoke", post(revoke))
+        // Introspection
+        .route("/oidc/introspect", post(introspect))
 }
 
 // ===========================================================================
@@ -114,12 +139,19 @@ pub async fn openid_configuration(State(state): State<AppState>) -> Json<serde_j
         "userinfo_endpoint": format!("{hub}/oidc/userinfo"),
         "jwks_uri": format!("{hub}/oidc/.well-known/jwks.json"),
         "revocation_endpoint": format!("{hub}/oidc/revoke"),
+        "introspection_endpoint": format!("{hub}/oidc/introspect"),
+        "device_authorization_endpoint": format!("{hub}/oidc/device_authorization"),
         "response_types_supported": ["code"],
-        "grant_types_supported": ["authorization_code", "refresh_token"],
+        "grant_types_supported": [
+            GRANT_AUTHORIZATION_CODE,
+            GRANT_REFRESH_TOKEN,
+            GRANT_CLIENT_CREDENTIALS,
+            GRANT_DEVICE_CODE,
+        ],
         "subject_types_supported": ["public"],
         "id_token_signing_alg_values_supported": ["EdDSA"],
         "scopes_supported": ["openid", "profile", "email", "pods", "offline_access"],
-        "token_endpoint_auth_methods_supported": ["none"],
+        "token_endpoint_auth_methods_supported": ["none", "client_secret_basic", "client_secret_post"],
         "code_challenge_methods_supported": ["S256"]
     }))
 }
@@ -137,16 +169,21 @@ pub async fn openid_configuration(State(state): State<AppState>) -> Json<serde_j
     ),
 )]
 pub async fn jwks(State(state): State<AppState>) -> Json<serde_json::Value> {
-    let keys = &state.keys;
-    Json(serde_json::json!({
-        "keys": [{
-            "kty": "OKP",
-            "crv": "Ed25519",
-            "kid": keys.kid,
-            "use": "sig",
-            "x": keys.public_key_b64
-        }]
-    }))
+    let keys: Vec<_> = state
+        .keys
+        .published()
+        .iter()
+        .map(|k| {
+            serde_json::json!({
+                "kty": "OKP",
+                "crv": "Ed25519",
+                "kid": k.keys.kid,
+                "use": "sig",
+                "x": k.keys.public_key_b64
+            })
+        })
+        .collect();
+    Json(serde_json::json!({ "keys": keys }))
 }
 
 // ===========================================================================
@@ -155,7 +192,8 @@ pub async fn jwks(State(state): State<AppState>) -> Json<serde_json::Value> {
 // ===========================================================================
 
 #[allow(clippy::too_many_arguments)]
-fn render_login(
+pub(super) fn render_login(
+    providers: &[UpstreamIdp],
     theme_class: &str,
     response_type: &str,
     client_id: &str,
@@ -167,14 +205,20 @@ fn render_login(
     error_message: &str,
     login_value: &str,
 ) -> String {
-    let error_html = if error_message.is_empty() {
-        String::new()
-    } else {
-        format!(
-            r#"<div class="rounded-md border border-red-500/30 bg-red-500/10 px-4 py-3 text-sm text-red-600 dark:text-red-400">{}</div>"#,
-            html_escape(error_message),
-        )
-    };
+    let error_html = error_banner(error_message);
+    let providers_html = providers_html(
+        providers,
+        &[
+            ("response_type", response_type),
+            ("client_id", client_id),
+            ("redirect_uri", redirect_uri),
+            ("scope", scope),
+            ("state", state),
+            ("code_challenge", code_challenge),
+            ("code_challenge_method", "S256"),
+            ("nonce", nonce),
+        ],
+    );
     include_str!("../templates/login.html")
         .replace("{{theme_class}}", theme_class)
         .replace("{{response_type}}", response_type)
@@ -186,9 +230,50 @@ fn render_login(
         .replace("{{nonce}}", nonce)
         .replace("{{error_html}}", &error_html)
         .replace("{{login_value}}", &html_escape(login_value))
+        .replace("{{providers_html}}", &providers_html)
+}
+
+/// "Continue with ..." buttons for the upstream providers, carrying the
+/// authorization request along.
+fn providers_html(providers: &[UpstreamIdp], params: &[(&str, &str)]) -> String {
+    if providers.is_empty() {
+        return String::new();
+    }
+    let query = serde_urlencoded::to_string(params).unwrap_or_default();
+    let buttons: String = providers
+        .iter()
+        .map(|p| {
+            format!(
+                r#"<a href="/oidc/authorize/upstream/{}?{}" class="inline-flex h-9 w-full items-center justify-center rounded-md border border-input bg-transparent px-4 text-sm font-medium shadow-sm transition-colors hover:bg-muted focus-visible:outline-none focus-visible:ring-1 focus-visible:ring-ring">Continue with {}</a>"#,
+                html_escape(&p.id),
+                html_escape(&query),
+                html_escape(&p.name),
+            )
+        })
+        .collect();
+    format!(
+        r#"<div class="space-y-2"><div class="flex items-center gap-3 text-xs text-muted-foreground"><span class="h-px flex-1 bg-border"></span>or<span class="h-px flex-1 bg-border"></span></div>{buttons}</div>"#
+    )
+}
+
+pub(super) fn render_mfa(mfa_token: &str, error_message: &str) -> String {
+    include_str!("../templates/mfa.html")
+        .replace("{{theme_class}}", "")
+        .replace("{{mfa_token}}", &html_escape(mfa_token))
+        .replace("{{error_html}}", &error_banner(error_message))
 }
 
-fn html_escape(s: &str) -> String {
+pub(super) fn error_banner(error_message: &str) -> String {
+    if error_message.is_empty() {
+        return String::new();
+    }
+    format!(
+        r#"<div class="rounded-md border border-red-500/30 bg-red-500/10 px-4 py-3 text-sm text-red-600 dark:text-red-400">{}</div>"#,
+        html_escape(error_message),
+    )
+}
+
+pub(super) fn html_escape(s: &str) -> String {
     s.replace('&', "&amp;")
         .replace('<', "&lt;")
         .replace('>', "&gt;")
@@ -219,13 +304,23 @@ pub struct AuthorizeParams {
         (status = 200, description = "Login form", content_type = "text/html"),
     ),
 )]
-pub async fn authorize(Query(params): Query<AuthorizeParams>) -> Response {
+pub async fn authorize(
+    State(state): State<AppState>,
+    Query(params): Query<AuthorizeParams>,
+) -> Response {
     // Validate basics
     if params.response_type != "code" {
         return (StatusCode::BAD_REQUEST, "unsupported response_type").into_response();
     }
-    if params.client_id != CLIENT_ID {
-        return (StatusCode::BAD_REQUEST, "unknown client_id").into_response();
+    if let Err(e) = validate_client_request(
+        &state,
+        &params.client_id,
+        &params.redirect_uri,
+        params.scope.as_deref().unwrap_or("openid"),
+    )
+    .await
+    {
+        return (e.status, e.message).into_response();
     }
     if params.code_challenge.is_none() || params.code_challenge_method.as_deref() != Some("S256") {
         return (StatusCode::BAD_REQUEST, "PKCE with S256 is required").into_response();
@@ -240,6 +335,7 @@ pub async fn authorize(Query(params): Query<AuthorizeParams>) -> Response {
 
     // Render login form from template with OIDC params injected.
     let html = render_login(
+        &state.config.upstream_idps,
         theme_class,
         &params.response_type,
         &params.client_id,
@@ -271,50 +367,76 @@ pub struct AuthorizeSubmit {
 }
 
 /// Process login form — validate credentials, generate auth code, redirect.
+///
+/// Accounts with MFA enabled get a second-step challenge page instead; the
+/// code is issued by `POST /oidc/authorize/mfa`.
 #[utoipa::path(
     post,
     path = "/oidc/authorize",
     tag = "OIDC",
     responses(
         (status = 302, description = "Redirect with auth code"),
+        (status = 200, description = "MFA challenge page", content_type = "text/html"),
+        (status = 401, description = "Login form with an error", content_type = "text/html"),
+        (status = 429, description = "Login form asking to wait after too many failures", content_type = "text/html"),
     ),
 )]
 pub async fn authorize_submit(
     State(state): State<AppState>,
+    client: ClientInfo,
     Form(form): Form<AuthorizeSubmit>,
 ) -> Response {
     // Macro to re-render the login form with an error message.
     macro_rules! login_err {
-        ($msg:expr) => {
-            return Html(render_login(
-                "",
-                &form.response_type,
-                &form.client_id,
-                &form.redirect_uri,
-                form.scope.as_deref().unwrap_or("openid"),
-                form.state.as_deref().unwrap_or(""),
-                form.code_challenge.as_deref().unwrap_or(""),
-                form.nonce.as_deref().unwrap_or(""),
-                $msg,
-                &form.login,
-            ))
-            .into_response()
+        ($status:expr, $msg:expr) => {
+            return (
+                $status,
+                Html(render_login(
+                    &state.config.upstream_idps,
+                    "",
+                    &form.response_type,
+                    &form.client_id,
+                    &form.redirect_uri,
+                    form.scope.as_deref().unwrap_or("openid"),
+                    form.state.as_deref().unwrap_or(""),
+                    form.code_challenge.as_deref().unwrap_or(""),
+                    form.nonce.as_deref().unwrap_or(""),
+                    $msg,
+                    &form.login,
+                )),
+            )
+                .into_response()
         };
     }
 
-    if form.client_id != CLIENT_ID {
-        login_err!("Invalid request");
+    match validate_client_request(
+        &state,
+        &form.client_id,
+        &form.redirect_uri,
+        form.scope.as_deref().unwrap_or("openid"),
+    )
+    .await
+    {
+        Ok(()) => {}
+        Err(e) if e.status == StatusCode::INTERNAL_SERVER_ERROR => login_err!(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Something went wrong. Please try again."
+        ),
+        Err(_) => login_err!(StatusCode::BAD_REQUEST, "Invalid request"),
     }
     let code_challenge = match form.code_challenge.as_deref() {
         Some(c) => c.to_string(),
-        None => login_err!("Invalid request"),
+        None => login_err!(StatusCode::BAD_REQUEST, "Invalid request"),
     };
 
     // Look up user by username (case-insensitive) or email.
     let login_lower = form.login.trim().to_lowercase();
     let mut conn = match state.db.get().await {
         Ok(c) => c,
-        Err(_) => login_err!("Something went wrong. Please try again."),
+        Err(_) => login_err!(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Something went wrong. Please try again."
+        ),
     };
 
     let user: Option<User> = match users::table
@@ -329,26 +451,56 @@ pub async fn authorize_submit(
         .optional()
     {
         Ok(u) => u,
-        Err(_) => login_err!("Something went wrong. Please try again."),
+        Err(_) => login_err!(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Something went wrong. Please try again."
+        ),
     };
 
-    let user = match user {
-        Some(u) => u,
-        None => login_err!("Invalid username or password"),
-    };
+    // Refuse while the account or this address has failed too often. The
+    // answer is the same whether or not the login matched an account.
+    let subject = throttle::subject(user.as_ref(), &login_lower);
+    match throttle::check_login(state.kv.as_ref(), &subject, client.ip_address).await {
+        Ok(None) => {}
+        Ok(Some(_)) => login_err!(StatusCode::TOO_MANY_REQUESTS, throttle::LOGIN_THROTTLED),
+        Err(_) => login_err!(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Something went wrong. Please try again."
+        ),
+    }
 
     // Verify password.
-    let hash = match user.password_hash.as_deref() {
-        Some(h) => h,
-        None => login_err!("Invalid username or password"),
+    let password_ok = password_matches(
+        &form.password,
+        user.as_ref().and_then(|u| u.password_hash.as_deref()),
+    );
+    let user = match user {
+        Some(u) if password_ok => u,
+        user => {
+            let reason = if user.is_some() {
+                "invalid_password"
+            } else {
+                "unknown_user"
+            };
+            let mut event = AuthEvent::new(events::LOGIN_FAILURE)
+                .client(&form.client_id)
+                .details(serde_json::json!({ "reason": reason }));
+            if let Some(ref u) = user {
+                event = event.user(&u.id);
+            }
+            events::record(&state.db, &client, event).await;
+            if throttle::login_failed(&state, &client, &subject, user.as_ref())
+                .await
+                .is_err()
+            {
+                login_err!(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Something went wrong. Please try again."
+                );
+            }
+            login_err!(StatusCode::UNAUTHORIZED, "Invalid username or password")
+        }
     };
-    if verify_password(&form.password, hash).is_err() {
-        login_err!("Invalid username or password");
-    }
-
-    // Generate authorization code.
-    let code = generate_opaque_token("hac", 32);
-
     let scopes: Vec<String> = form
         .scope
         .as_deref()
@@ -357,34 +509,407 @@ pub async fn authorize_submit(
         .map(|s| s.to_string())
         .collect();
 
+    // Second step: hold the request until the user proves their second factor.
+    if user.mfa_enabled {
+        let mfa_token = generate_opaque_token("hmc", 32);
+        let challenge = MfaChallengeData {
+            user_id: user.id.clone(),
+            client_id: form.client_id.clone(),
+            redirect_uri: form.redirect_uri.clone(),
+            code_challenge,
+            scopes,
+            nonce: form.nonce.clone(),
+            state: form.state.clone(),
+            device_code: None,
+            amr: vec![amr::PASSWORD.to_string()],
+        };
+        if mfa::store_mfa_challenge(state.kv.as_ref(), &mfa_token, &challenge)
+            .await
+            .is_err()
+        {
+            login_err!(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Something went wrong. Please try again."
+            );
+        }
+        return Html(render_mfa(&mfa_token, "")).into_response();
+    }
+    // Failures are only forgotten once every factor has passed.
+    let _ = throttle::login_succeeded(state.kv.as_ref(), &subject, client.ip_address).await;
+
     let code_data = AuthCodeData {
         user_id: user.id.clone(),
         client_id: form.client_id.clone(),
         redirect_uri: form.redirect_uri.clone(),
-        code_challenge: code_challenge.to_string(),
+        code_challenge,
         scopes,
         nonce: form.nonce.clone(),
+        amr: vec![amr::PASSWORD.to_string()],
     };
 
-    if tokens::store_auth_code(state.kv.as_ref(), &code, &code_data)
-        .await
-        .is_err()
+    match redirect_with_code(&state, &client, &code_data, form.state.as_deref()).await {
+        Ok(redirect) => redirect,
+        Err(e) if e.status == StatusCode::FORBIDDEN => {
+            login_err!(StatusCode::FORBIDDEN, &e.message)
+        }
+        Err(_) => login_err!(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Something went wrong. Please try again."
+        ),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuthorizeMfaSubmit {
+    pub mfa_token: String,
+    /// A TOTP code or an unused recovery code.
+    pub code: String,
+}
+
+/// Process the MFA challenge form — verify the second factor, generate auth
+/// code, redirect.
+#[utoipa::path(
+    post,
+    path = "/oidc/authorize/mfa",
+    tag = "OIDC",
+    responses(
+        (status = 302, description = "Redirect with auth code"),
+        (status = 401, description = "Challenge form with an error", content_type = "text/html"),
+    ),
+)]
+pub async fn authorize_mfa(
+    State(state): State<AppState>,
+    client: ClientInfo,
+    Form(form): Form<AuthorizeMfaSubmit>,
+) -> Response {
+    // Macro to re-render the challenge form with an error message.
+    macro_rules! mfa_err {
+        ($status:expr, $msg:expr) => {
+            return ($status, Html(render_mfa(&form.mfa_token, $msg))).into_response()
+        };
+    }
+    const EXPIRED: &str = "This sign-in has expired. Please start again.";
+    const INTERNAL: &str = "Something went wrong. Please try again.";
+
+    let mut challenge = match mfa::load_mfa_challenge(state.kv.as_ref(), &form.mfa_token).await {
+        Ok(Some(c)) => c,
+        Ok(None) => mfa_err!(StatusCode::UNAUTHORIZED, EXPIRED),
+        Err(_) => mfa_err!(StatusCode::INTERNAL_SERVER_ERROR, INTERNAL),
+    };
+
+    let user: User = match state.db.get().await {
+        Ok(mut conn) => match users::table
+            .find(&challenge.user_id)
+            .select(User::as_select())
+            .first(&mut conn)
+            .await
+        {
+            Ok(u) => u,
+            Err(_) => mfa_err!(StatusCode::INTERNAL_SERVER_ERROR, INTERNAL),
+        },
+        Err(_) => mfa_err!(StatusCode::INTERNAL_SERVER_ERROR, INTERNAL),
+    };
+
+    // Wrong codes count against the account like wrong passwords, whichever
+    // way the first step was taken, so new challenges don't reset the count.
+    match throttle::check_login(state.kv.as_ref(), &user.id, client.ip_address).await {
+        Ok(None) => {}
+        Ok(Some(_)) => {
+            let _ = mfa::delete_mfa_challenge(state.kv.as_ref(), &form.mfa_token).await;
+            mfa_err!(StatusCode::TOO_MANY_REQUESTS, throttle::LOGIN_THROTTLED);
+        }
+        Err(_) => mfa_err!(StatusCode::INTERNAL_SERVER_ERROR, INTERNAL),
+    }
+
+    let factor =
+        match mfa::verify_second_factor(&state.db, state.kv.as_ref(), &user, &form.code).await {
+            Ok(f) => f,
+            Err(_) => mfa_err!(StatusCode::INTERNAL_SERVER_ERROR, INTERNAL),
+        };
+
+    let Some(factor) = factor else {
+        events::record(
+            &state.db,
+            &client,
+            AuthEvent::new(events::MFA_FAILURE)
+                .user(&user.id)
+                .client(&challenge.client_id),
+        )
+        .await;
+        if throttle::login_failed(&state, &client, &user.id, Some(&user))
+            .await
+            .is_err()
+        {
+            mfa_err!(StatusCode::INTERNAL_SERVER_ERROR, INTERNAL);
+        }
+        let attempts = match mfa::record_mfa_failure(state.kv.as_ref(), &form.mfa_token).await {
+            Ok(n) => n,
+            Err(_) => mfa_err!(StatusCode::INTERNAL_SERVER_ERROR, INTERNAL),
+        };
+        if attempts >= mfa::MAX_MFA_ATTEMPTS {
+            let _ = mfa::delete_mfa_challenge(state.kv.as_ref(), &form.mfa_token).await;
+            mfa_err!(StatusCode::UNAUTHORIZED, EXPIRED);
+        }
+        mfa_err!(StatusCode::UNAUTHORIZED, "Invalid code");
+    };
+
+    let _ = mfa::delete_mfa_challenge(state.kv.as_ref(), &form.mfa_token).await;
+    let _ = throttle::login_succeeded(state.kv.as_ref(), &user.id, client.ip_address).await;
+    tracing::info!(user_id = %user.id, ?factor, "mfa challenge passed");
+    events::record(
+        &state.db,
+        &client,
+        AuthEvent::new(events::MFA_SUCCESS)
+            .user(&user.id)
+            .client(&challenge.client_id)
+            .details(serde_json::json!({ "factor": factor.as_str() })),
+    )
+    .await;
+
+    let mut amr = std::mem::take(&mut challenge.amr);
+    if amr.is_empty() {
+        amr.push(amr::PASSWORD.to_string());
+    }
+    amr.extend([amr::OTP.to_string(), amr::MFA.to_string()]);
+
+    // Approving a device rather than redirecting back to a client.
+    if let Some(ref device_code) = challenge.device_code {
+        return super::device::approve_device(&state, &client, device_code, user.id, amr).await;
+    }
+
+    let code_data = AuthCodeData {
+        user_id: challenge.user_id,
+        client_id: challenge.client_id,
+        redirect_uri: challenge.redirect_uri,
+        code_challenge: challenge.code_challenge,
+        scopes: challenge.scopes,
+        nonce: challenge.nonce,
+        amr,
+    };
+
+    match redirect_with_code(&state, &client, &code_data, challenge.state.as_deref()).await {
+        Ok(redirect) => redirect,
+        Err(e) if e.status == StatusCode::FORBIDDEN => mfa_err!(StatusCode::FORBIDDEN, &e.message),
+        Err(_) => mfa_err!(StatusCode::INTERNAL_SERVER_ERROR, INTERNAL),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuthorizePasskeySubmit {
+    // OIDC params echoed back
+    pub response_type: String,
+    pub client_id: String,
+    pub redirect_uri: String,
+    pub scope: Option<String>,
+    pub state: Option<String>,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+    pub nonce: Option<String>,
+    /// JSON-encoded `PublicKeyCredential` from `navigator.credentials.get()`.
+    pub credential: String,
+}
+
+/// Start a passwordless sign-in — issue `PublicKeyCredentialRequestOptions`
+/// for the login page to pass to `navigator.credentials.get()`.
+#[utoipa::path(
+    post,
+    path = "/oidc/authorize/passkey/begin",
+    tag = "OIDC",
+    responses(
+        (status = 200, description = "Credential request options", body = RequestOptions),
+    ),
+)]
+pub async fn authorize_passkey_begin(
+    State(state): State<AppState>,
+) -> Result<Json<RequestOptions>, ApiError> {
+    let challenge =
+        webauthn::begin_ceremony(state.kv.as_ref(), Ceremony::Authentication, None).await?;
+    Ok(Json(webauthn::request_options(&state.config, challenge)))
+}
+
+/// Process a passkey assertion from the login page — verify it, generate auth
+/// code, redirect.
+///
+/// Passkeys require user verification, so this satisfies MFA on its own.
+#[utoipa::path(
+    post,
+    path = "/oidc/authorize/passkey",
+    tag = "OIDC",
+    responses(
+        (status = 302, description = "Redirect with auth code"),
+        (status = 401, description = "Login form with an error", content_type = "text/html"),
+    ),
+)]
+pub async fn authorize_passkey(
+    State(state): State<AppState>,
+    client: ClientInfo,
+    Form(form): Form<AuthorizePasskeySubmit>,
+) -> Response {
+    macro_rules! login_err {
+        ($status:expr, $msg:expr) => {
+            return (
+                $status,
+                Html(render_login(
+                    &state.config.upstream_idps,
+                    "",
+                    &form.response_type,
+                    &form.client_id,
+                    &form.redirect_uri,
+                    form.scope.as_deref().unwrap_or("openid"),
+                    form.state.as_deref().unwrap_or(""),
+                    form.code_challenge.as_deref().unwrap_or(""),
+                    form.nonce.as_deref().unwrap_or(""),
+                    $msg,
+                    "",
+                )),
+            )
+                .into_response()
+        };
+    }
+    const INTERNAL: &str = "Something went wrong. Please try again.";
+
+    match validate_client_request(
+        &state,
+        &form.client_id,
+        &form.redirect_uri,
+        form.scope.as_deref().unwrap_or("openid"),
+    )
+    .await
     {
-        login_err!("Something went wrong. Please try again.");
+        Ok(()) => {}
+        Err(e) if e.status == StatusCode::INTERNAL_SERVER_ERROR => {
+            login_err!(StatusCode::INTERNAL_SERVER_ERROR, INTERNAL)
+        }
+        Err(_) => login_err!(StatusCode::BAD_REQUEST, "Invalid request"),
+    }
+    let code_challenge = match form.code_challenge.as_deref() {
+        Some(c) => c.to_string(),
+        None => login_err!(StatusCode::BAD_REQUEST, "Invalid request"),
+    };
+    let credential: AuthenticationCredential = match serde_json::from_str(&form.credential) {
+        Ok(c) => c,
+        Err(_) => login_err!(StatusCode::BAD_REQUEST, "Invalid request"),
+    };
+
+    let passkey = match webauthn::finish_authentication(
+        &state.config,
+        &state.db,
+        state.kv.as_ref(),
+        &credential,
+    )
+    .await
+    {
+        Ok(p) => p,
+        Err(e) if e.status == StatusCode::INTERNAL_SERVER_ERROR => {
+            login_err!(StatusCode::INTERNAL_SERVER_ERROR, INTERNAL)
+        }
+        Err(_) => {
+            events::record(
+                &state.db,
+                &client,
+                AuthEvent::new(events::LOGIN_FAILURE)
+                    .client(&form.client_id)
+                    .details(serde_json::json!({ "reason": "invalid_passkey" })),
+            )
+            .await;
+            login_err!(
+                StatusCode::UNAUTHORIZED,
+                "That passkey couldn't be verified. Try again or use your password."
+            )
+        }
+    };
+
+    tracing::info!(user_id = %passkey.user_id, passkey_id = %passkey.id, "passkey sign-in");
+
+    let code_data = AuthCodeData {
+        user_id: passkey.user_id,
+        client_id: form.client_id.clone(),
+        redirect_uri: form.redirect_uri.clone(),
+        code_challenge,
+        scopes: form
+            .scope
+            .as_deref()
+            .unwrap_or("openid")
+            .split_whitespace()
+            .map(|s| s.to_string())
+            .collect(),
+        nonce: form.nonce.clone(),
+        amr: vec![amr::HARDWARE_KEY.to_string(), amr::MFA.to_string()],
+    };
+
+    match redirect_with_code(&state, &client, &code_data, form.state.as_deref()).await {
+        Ok(redirect) => redirect,
+        Err(e) if e.status == StatusCode::FORBIDDEN => {
+            login_err!(StatusCode::FORBIDDEN, &e.message)
+        }
+        Err(_) => login_err!(StatusCode::INTERNAL_SERVER_ERROR, INTERNAL),
     }
+}
+
+/// Check an authorization request against the client registry: the client
+/// must exist, be allowed to use the authorization code flow, have
+/// `redirect_uri` registered, and be allowed every requested scope.
+pub(super) async fn validate_client_request(
+    state: &AppState,
+    client_id: &str,
+    redirect_uri: &str,
+    scope: &str,
+) -> Result<(), ApiError> {
+    let client = clients::load_client(&state.db, client_id)
+        .await?
+        .ok_or_else(|| ApiError::bad_request("unknown client_id"))?;
+    if !client.allows_grant(GRANT_AUTHORIZATION_CODE) {
+        return Err(ApiError::bad_request(
+            "client is not allowed to use the authorization code flow",
+        ));
+    }
+    if !client.allows_redirect_uri(redirect_uri) {
+        return Err(ApiError::bad_request(
+            "redirect_uri is not registered for this client",
+        ));
+    }
+    let scopes: Vec<String> = scope.split_whitespace().map(|s| s.to_string()).collect();
+    if !client.allows_scopes(&scopes) {
+        return Err(ApiError::bad_request("invalid scope"));
+    }
+    Ok(())
+}
+
+/// Store a new authorization code and redirect back to the client with it.
+/// This is where every browser sign-in completes, so it records the login.
+pub(super) async fn redirect_with_code(
+    state: &AppState,
+    client: &ClientInfo,
+    code_data: &AuthCodeData,
+    client_state: Option<&str>,
+) -> Result<Response, ApiError> {
+    bans::check_standing(&state.db, &code_data.user_id).await?;
+
+    let code = generate_opaque_token("hac", 32);
+    tokens::store_auth_code(state.kv.as_ref(), &code, code_data).await?;
+
+    events::record(
+        &state.db,
+        client,
+        AuthEvent::new(events::LOGIN_SUCCESS)
+            .user(&code_data.user_id)
+            .client(&code_data.client_id)
+            .details(serde_json::json!({ "amr": code_data.amr })),
+    )
+    .await;
 
     // Build redirect URI with code + state.
-    let sep = if form.redirect_uri.contains('?') {
+    let sep = if code_data.redirect_uri.contains('?') {
         "&"
     } else {
         "?"
     };
-    let mut redirect = format!("{}{}code={}", form.redirect_uri, sep, code);
-    if let Some(ref st) = form.state {
+    let mut redirect = format!("{}{}code={}", code_data.redirect_uri, sep, code);
+    if let Some(st) = client_state {
         redirect.push_str(&format!("&state={}", st));
     }
 
-    Redirect::to(&redirect).into_response()
+    Ok(Redirect::to(&redirect).into_response())
 }
 
 // ===========================================================================
@@ -402,8 +927,18 @@ pub struct TokenRequest {
     pub code_verifier: Option<String>,
     #[serde(default)]
     pub client_id: Option<String>,
+    /// For confidential clients using `client_secret_post`.
+    #[serde(default)]
+    pub client_secret: Option<String>,
     #[serde(default)]
     pub refresh_token: Option<String>,
+    /// Space-separated scopes for `client_credentials`. Defaults to all the
+    /// client's allowed scopes.
+    #[serde(default)]
+    pub scope: Option<String>,
+    /// For the device grant.
+    #[serde(default)]
+    pub device_code: Option<String>,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
@@ -425,77 +960,127 @@ pub struct TokenResponse {
     request_body = TokenRequest,
     responses(
         (status = 200, description = "Token response", body = TokenResponse),
-        (status = 400, description = "Invalid request", body = ApiErrorBody),
+        (status = 400, description = "OAuth error, e.g. `invalid_grant` or `authorization_pending`", body = OAuthErrorBody),
+        (status = 401, description = "`invalid_client`", body = OAuthErrorBody),
     ),
 )]
 pub async fn token(
     State(state): State<AppState>,
+    headers: HeaderMap,
+    client: ClientInfo,
     Form(form): Form<TokenRequest>,
-) -> Result<Json<TokenResponse>, ApiError> {
+) -> Result<Json<TokenResponse>, OAuthError> {
     match form.grant_type.as_str() {
-        "authorization_code" => handle_authorization_code(state, form).await,
-        "refresh_token" => handle_refresh_token(state, form).await,
-        _ => Err(ApiError::bad_request("unsupported grant_type")),
+        "authorization_code" => handle_authorization_code(state, headers, client, form).await,
+        "refresh_token" => handle_refresh_token(state, headers, client, form).await,
+        "client_credentials" => handle_client_credentials(state, headers, client, form).await,
+        GRANT_DEVICE_CODE => handle_device_code(state, headers, client, form).await,
+        _ => Err(OAuthError::new("unsupported_grant_type")),
     }
 }
 
 async fn handle_authorization_code(
     state: AppState,
+    headers: HeaderMap,
+    client: ClientInfo,
     form: TokenRequest,
-) -> Result<Json<TokenResponse>, ApiError> {
+) -> Result<Json<TokenResponse>, OAuthError> {
     let code = form
         .code
         .as_deref()
-        .ok_or_else(|| ApiError::bad_request("code is required"))?;
+        .ok_or_else(|| OAuthError::invalid_request("code is required"))?;
     let code_verifier = form
         .code_verifier
         .as_deref()
-        .ok_or_else(|| ApiError::bad_request("code_verifier is required"))?;
+        .ok_or_else(|| OAuthError::invalid_request("code_verifier is required"))?;
     let redirect_uri = form
         .redirect_uri
         .as_deref()
-        .ok_or_else(|| ApiError::bad_request("redirect_uri is required"))?;
+        .ok_or_else(|| OAuthError::invalid_request("redirect_uri is required"))?;
+
+    let oauth_client = clients::authenticate_client(
+        &state.db,
+        &headers,
+        form.client_id.as_deref(),
+        form.client_secret.as_deref(),
+    )
+    .await?;
+    if !oauth_client.allows_grant(GRANT_AUTHORIZATION_CODE) {
+        return Err(OAuthError::new("unauthorized_client"));
+    }
 
     // Consume the auth code from Redis.
     let code_data = tokens::consume_auth_code(state.kv.as_ref(), code)
         .await?
-        .ok_or_else(|| ApiError::bad_request("invalid or expired code"))?;
+        .ok_or_else(|| OAuthError::invalid_grant("invalid or expired code"))?;
+
+    // Codes can only be redeemed by the client they were issued to.
+    if code_data.client_id != oauth_client.id {
+        return Err(OAuthError::invalid_grant("invalid or expired code"));
+    }
 
     // PKCE verification: SHA256(code_verifier) == code_challenge
     verify_pkce(code_verifier, &code_data.code_challenge)?;
 
     // Validate redirect_uri matches what was stored.
     if redirect_uri != code_data.redirect_uri {
-        return Err(ApiError::bad_request("redirect_uri mismatch"));
+        return Err(OAuthError::invalid_grant("redirect_uri mismatch"));
     }
 
+    issue_tokens(
+        &state,
+        client,
+        GRANT_AUTHORIZATION_CODE,
+        &oauth_client.id,
+        &code_data.user_id,
+        &code_data.scopes,
+        code_data.nonce.as_deref(),
+        &code_data.amr,
+    )
+    .await
+    .map(Json)
+    .map_err(OAuthError::from)
+}
+
+/// Start a session for a user who just signed in to `client_id`, and issue
+/// its access, refresh and ID tokens.
+#[allow(clippy::too_many_arguments)]
+async fn issue_tokens(
+    state: &AppState,
+    client: ClientInfo,
+    grant_type: &str,
+    client_id: &str,
+    user_id: &str,
+    scopes: &[String],
+    nonce: Option<&str>,
+    amr: &[String],
+) -> Result<TokenResponse, ApiError> {
     // Load user from DB.
     let mut conn = state.db.get().await?;
     let user: User = users::table
-        .find(&code_data.user_id)
+        .find(user_id)
         .select(User::as_select())
         .first(&mut conn)
         .await
         .map_err(ApiError::from)?;
+    bans::check_standing(&state.db, &user.id).await?;
 
     // Generate tokens.
     let access_token = generate_access_token();
     let refresh_token = generate_refresh_token();
 
-    // Store access token in Redis.
-    let at_data = AccessTokenData {
-        user_id: user.id.clone(),
-        scopes: code_data.scopes.clone(),
-    };
-    tokens::store_access_token(state.kv.as_ref(), &access_token, &at_data).await?;
-
-    // Store refresh token in sessions table.
+    // Store refresh token in sessions table. A sign-in starts a new family.
+    let session_id = voxora_common::id::prefixed_ulid(voxora_common::id::prefix::SESSION);
     let session = NewSession {
-        id: voxora_common::id::prefixed_ulid(voxora_common::id::prefix::SESSION),
+        id: session_id.clone(),
         user_id: user.id.clone(),
         refresh_token: refresh_token.clone(),
-        user_agent: None,
+        ip_address: client.ip_address.map(IpNet::from),
+        user_agent: client.user_agent.clone(),
+        client_id: Some(client_id.to_string()),
         expires_at: Utc::now() + Duration::days(REFRESH_TOKEN_TTL_DAYS),
+        family_id: session_id,
+        scopes: scopes.to_vec(),
     };
     diesel::insert_into(sessions::table)
         .values(&session)
@@ -503,14 +1088,37 @@ async fn handle_authorization_code(
         .await
         .map_err(ApiError::from)?;
 
+    events::record(
+        &state.db,
+        &client,
+        AuthEvent::new(events::TOKEN_ISSUED)
+            .user(&user.id)
+            .client(client_id)
+            .details(serde_json::json!({
+                "grant_type": grant_type,
+                "session_id": session.id,
+            })),
+    )
+    .await;
+
+    // Store access token in Redis.
+    let at_data = AccessTokenData::new(
+        user.id.clone(),
+        scopes.to_vec(),
+        Some(session.id.clone()),
+        Some(client_id.to_string()),
+    );
+    tokens::store_access_token(state.kv.as_ref(), &access_token, &at_data).await?;
+
     // Mint ID token.
     let id_token = mint_id_token(
-        &state.keys,
+        &state.keys.active(),
         &state.config.hub_domain,
-        &code_data.client_id,
+        client_id,
         &user.id,
-        code_data.nonce.as_deref(),
-        &code_data.scopes,
+        nonce,
+        scopes,
+        amr,
         &user.username,
         &user.display_name,
         user.avatar_url.as_deref(),
@@ -518,95 +1126,285 @@ async fn handle_authorization_code(
         user.email_verified,
     )?;
 
-    let scope = code_data.scopes.join(" ");
-
-    Ok(Json(TokenResponse {
+    Ok(TokenResponse {
         access_token,
         token_type: "Bearer",
         expires_in: ACCESS_TOKEN_TTL_SECS,
         refresh_token: Some(refresh_token),
         id_token: Some(id_token),
-        scope,
-    }))
+        scope: scopes.join(" "),
+    })
 }
 
+/// Exchange a refresh token for a new one (rotation) and an access token
+/// carrying the scopes granted at sign-in.
 async fn handle_refresh_token(
     state: AppState,
+    headers: HeaderMap,
+    client: ClientInfo,
     form: TokenRequest,
-) -> Result<Json<TokenResponse>, ApiError> {
+) -> Result<Json<TokenResponse>, OAuthError> {
     let old_rt = form
         .refresh_token
         .as_deref()
-        .ok_or_else(|| ApiError::bad_request("refresh_token is required"))?;
+        .ok_or_else(|| OAuthError::invalid_request("refresh_token is required"))?;
 
     let mut conn = state.db.get().await?;
 
-    // Look up session by refresh token.
+    // Look up session by refresh token, including rotated ones so a replay
+    // can be recognized.
     let session: crate::models::session::Session = sessions::table
         .filter(sessions::refresh_token.eq(old_rt))
-        .filter(sessions::revoked.eq(false))
-        .filter(sessions::expires_at.gt(Utc::now()))
         .select(crate::models::session::Session::as_select())
         .first(&mut conn)
         .await
-        .optional()
-        .map_err(ApiError::from)?
-        .ok_or_else(|| ApiError::unauthorized("invalid or expired refresh_token"))?;
+        .optional()?
+        .ok_or_else(|| OAuthError::invalid_grant("invalid or expired refresh_token"))?;
+
+    // Sessions from before client registration have no client recorded.
+    if let Some(ref session_client_id) = session.client_id {
+        let oauth_client = clients::authenticate_client(
+            &state.db,
+            &headers,
+            form.client_id.as_deref(),
+            form.client_secret.as_deref(),
+        )
+        .await?;
+        if &oauth_client.id != session_client_id {
+            return Err(OAuthError::invalid_grant(
+                "invalid or expired refresh_token",
+            ));
+        }
+        if !oauth_client.allows_grant(GRANT_REFRESH_TOKEN) {
+            return Err(OAuthError::new("unauthorized_client"));
+        }
+    }
 
-    // Rotate: revoke old, create new session.
-    diesel::update(sessions::table.find(&session.id))
-        .set(sessions::revoked.eq(true))
+    bans::check_standing(&state.db, &session.user_id).await?;
+
+    // A refresh token is only ever presented once. Seeing a rotated one again
+    // means two parties hold the chain, and we can't tell which is the user:
+    // sign the whole family out. Signed-out sessions are simply refused below.
+    if session.rotated_at.is_some() {
+        let revoked = super::sessions::revoke_session_family(&state, &session.family_id).await?;
+        let mut event = AuthEvent::new(events::TOKEN_REUSE_DETECTED)
+            .user(&session.user_id)
+            .details(serde_json::json!({
+                "session_id": session.id,
+                "family_id": session.family_id,
+                "revoked": revoked,
+            }));
+        if let Some(ref client_id) = session.client_id {
+            event = event.client(client_id);
+        }
+        events::record(&state.db, &client, event).await;
+        tracing::warn!(
+            user_id = %session.user_id,
+            session_id = %session.id,
+            family_id = %session.family_id,
+            revoked,
+            "refresh token reuse detected; session family revoked"
+        );
+        return Err(OAuthError::invalid_grant(
+            "invalid or expired refresh_token",
+        ));
+    }
+    if session.revoked || session.expires_at <= Utc::now() {
+        return Err(OAuthError::invalid_grant(
+            "invalid or expired refresh_token",
+        ));
+    }
+
+    // Rotate: revoke old, create new session in the same family. Only one
+    // request can win the rotation; a concurrent replay sees it revoked.
+    let rotated = diesel::update(sessions::table.find(&session.id))
+        .filter(sessions::revoked.eq(false))
+        .set((
+            sessions::revoked.eq(true),
+            sessions::rotated_at.eq(Utc::now()),
+        ))
         .execute(&mut conn)
-        .await
-        .map_err(ApiError::from)?;
+        .await?;
+    if rotated == 0 {
+        return Err(OAuthError::invalid_grant(
+            "invalid or expired refresh_token",
+        ));
+    }
 
     let new_rt = generate_refresh_token();
     let new_session = NewSession {
         id: voxora_common::id::prefixed_ulid(voxora_common::id::prefix::SESSION),
         user_id: session.user_id.clone(),
         refresh_token: new_rt.clone(),
-        user_agent: None,
+        ip_address: client.ip_address.map(IpNet::from).or(session.ip_address),
+        user_agent: client.user_agent.clone().or(session.user_agent),
+        client_id: session.client_id.clone(),
         expires_at: Utc::now() + Duration::days(REFRESH_TOKEN_TTL_DAYS),
+        family_id: session.family_id,
+        scopes: session.scopes,
     };
     diesel::insert_into(sessions::table)
         .values(&new_session)
         .execute(&mut conn)
-        .await
-        .map_err(ApiError::from)?;
+        .await?;
 
-    // Generate new access token.
+    let mut event = AuthEvent::new(events::TOKEN_REFRESHED)
+        .user(&new_session.user_id)
+        .details(serde_json::json!({ "session_id": new_session.id }));
+    if let Some(ref client_id) = new_session.client_id {
+        event = event.client(client_id);
+    }
+    events::record(&state.db, &client, event).await;
+
+    // Generate new access token with the scopes granted at sign-in.
     let access_token = generate_access_token();
+    let at_data = AccessTokenData::new(
+        new_session.user_id.clone(),
+        new_session.scopes.clone(),
+        Some(new_session.id.clone()),
+        new_session.client_id.clone(),
+    );
+    tokens::store_access_token(state.kv.as_ref(), &access_token, &at_data).await?;
 
-    // Load user for scopes (reuse same scopes — for Phase 1 we default to full set).
-    let user: User = users::table
-        .find(&session.user_id)
-        .select(User::as_select())
-        .first(&mut conn)
-        .await
-        .map_err(ApiError::from)?;
+    let scope = new_session.scopes.join(" ");
 
-    let scopes = vec![
-        "openid".to_string(),
-        "profile".to_string(),
-        "email".to_string(),
-        "pods".to_string(),
-    ];
+    Ok(Json(TokenResponse {
+        access_token,
+        token_type: "Bearer",
+        expires_in: ACCESS_TOKEN_TTL_SECS,
+        refresh_token: Some(new_rt),
+        id_token: None, // Not issued on refresh
+        scope,
+    }))
+}
 
-    let at_data = AccessTokenData {
-        user_id: user.id.clone(),
-        scopes: scopes.clone(),
+/// Devices poll until their user approves them on the verification page.
+/// While pending, polling faster than the interval gets `slow_down` and
+/// raises the interval (RFC 8628 §3.5).
+async fn handle_device_code(
+    state: AppState,
+    headers: HeaderMap,
+    client: ClientInfo,
+    form: TokenRequest,
+) -> Result<Json<TokenResponse>, OAuthError> {
+    let device_code = form
+        .device_code
+        .as_deref()
+        .ok_or_else(|| OAuthError::invalid_request("device_code is required"))?;
+
+    let oauth_client = clients::authenticate_client(
+        &state.db,
+        &headers,
+        form.client_id.as_deref(),
+        form.client_secret.as_deref(),
+    )
+    .await?;
+    if !oauth_client.allows_grant(GRANT_DEVICE_CODE) {
+        return Err(OAuthError::new("unauthorized_client"));
+    }
+
+    let kv = state.kv.as_ref();
+    let data = device::load_device_code(kv, device_code)
+        .await?
+        .ok_or_else(|| OAuthError::new("expired_token"))?;
+    if data.client_id != oauth_client.id {
+        return Err(OAuthError::new("invalid_grant"));
+    }
+
+    if data.status == DeviceStatus::Pending {
+        let too_soon = device::record_poll(kv, device_code, &data).await?;
+        return Err(OAuthError::new(if too_soon {
+            "slow_down"
+        } else {
+            "authorization_pending"
+        }));
+    }
+
+    // Decided codes are single use, like authorization codes: only the poll
+    // that takes the record acts on it.
+    let data = device::take_device_code(kv, device_code)
+        .await?
+        .ok_or_else(|| OAuthError::new("expired_token"))?;
+    if data.status != DeviceStatus::Approved {
+        return Err(OAuthError::new("access_denied"));
+    }
+    let user_id = data
+        .user_id
+        .as_deref()
+        .ok_or_else(|| ApiError::internal("approved device without a user"))?;
+    issue_tokens(
+        &state,
+        client,
+        GRANT_DEVICE_CODE,
+        &oauth_client.id,
+        user_id,
+        &data.scopes,
+        None,
+        &data.amr,
+    )
+    .await
+    .map(Json)
+    .map_err(OAuthError::from)
+}
+
+/// Bots sign in with their client's credentials. The token acts as the bot
+/// user; no refresh or ID token is issued.
+async fn handle_client_credentials(
+    state: AppState,
+    headers: HeaderMap,
+    client: ClientInfo,
+    form: TokenRequest,
+) -> Result<Json<TokenResponse>, OAuthError> {
+    let oauth_client = clients::authenticate_client(
+        &state.db,
+        &headers,
+        form.client_id.as_deref(),
+        form.client_secret.as_deref(),
+    )
+    .await?;
+    if !oauth_client.is_confidential() || !oauth_client.allows_grant(GRANT_CLIENT_CREDENTIALS) {
+        return Err(OAuthError::new("unauthorized_client"));
+    }
+    let bot_id = oauth_client
+        .bot_user_id
+        .as_deref()
+        .ok_or_else(|| OAuthError::new("unauthorized_client"))?;
+    bans::check_standing(&state.db, bot_id).await?;
+
+    let scopes: Vec<String> = match form.scope.as_deref() {
+        Some(scope) => scope.split_whitespace().map(|s| s.to_string()).collect(),
+        None => oauth_client.allowed_scopes.clone(),
     };
+    if !oauth_client.allows_scopes(&scopes) {
+        return Err(OAuthError::new("invalid_scope"));
+    }
+
+    let access_token = generate_access_token();
+    let at_data = AccessTokenData::new(
+        bot_id.to_string(),
+        scopes.clone(),
+        None,
+        Some(oauth_client.id.clone()),
+    );
     tokens::store_access_token(state.kv.as_ref(), &access_token, &at_data).await?;
 
-    let scope = scopes.join(" ");
+    events::record(
+        &state.db,
+        &client,
+        AuthEvent::new(events::TOKEN_ISSUED)
+            .user(bot_id)
+            .client(&oauth_client.id)
+            .details(serde_json::json!({ "grant_type": GRANT_CLIENT_CREDENTIALS })),
+    )
+    .await;
 
     Ok(Json(TokenResponse {
         access_token,
         token_type: "Bearer",
         expires_in: ACCESS_TOKEN_TTL_SECS,
-        refresh_token: Some(new_rt),
-        id_token: None, // Not issued on refresh
-        scope,
+        refresh_token: None,
+        id_token: None,
+        scope: scopes.join(" "),
     }))
 }
 
@@ -678,36 +1476,168 @@ pub struct RevokeRequest {
 )]
 pub async fn revoke(
     State(state): State<AppState>,
+    client: ClientInfo,
     Form(form): Form<RevokeRequest>,
 ) -> Result<StatusCode, ApiError> {
     let hint = form.token_type_hint.as_deref().unwrap_or("access_token");
 
-    match hint {
-        "access_token" => {
-            tokens::delete_access_token(state.kv.as_ref(), &form.token).await?;
+    if hint != "refresh_token" {
+        revoke_access_token(&state, &client, &form.token).await?;
+    }
+    if hint != "access_token" {
+        revoke_refresh_token(&state, &client, &form.token).await?;
+    }
+
+    // Per RFC 7009, always return 200 even if token was invalid.
+    Ok(StatusCode::OK)
+}
+
+async fn revoke_access_token(
+    state: &AppState,
+    client: &ClientInfo,
+    token: &str,
+) -> Result<(), ApiError> {
+    let Some(data) = tokens::lookup_access_token(state.kv.as_ref(), token).await? else {
+        return Ok(());
+    };
+    tokens::delete_access_token(state.kv.as_ref(), token).await?;
+
+    let mut event = AuthEvent::new(events::TOKEN_REVOKED)
+        .user(&data.user_id)
+        .details(serde_json::json!({ "token_type": "access_token" }));
+    if let Some(ref client_id) = data.client_id {
+        event = event.client(client_id);
+    }
+    events::record(&state.db, client, event).await;
+    Ok(())
+}
+
+async fn revoke_refresh_token(
+    state: &AppState,
+    client: &ClientInfo,
+    token: &str,
+) -> Result<(), ApiError> {
+    let mut conn = state.db.get().await?;
+    let revoked: Option<(String, String, Option<String>)> = diesel::update(
+        sessions::table
+            .filter(sessions::refresh_token.eq(token))
+            .filter(sessions::revoked.eq(false)),
+    )
+    .set(sessions::revoked.eq(true))
+    .returning((sessions::id, sessions::user_id, sessions::client_id))
+    .get_result(&mut conn)
+    .await
+    .optional()
+    .map_err(ApiError::from)?;
+
+    if let Some((session_id, user_id, client_id)) = revoked {
+        let mut event = AuthEvent::new(events::TOKEN_REVOKED)
+            .user(&user_id)
+            .details(serde_json::json!({
+                "token_type": "refresh_token",
+                "session_id": session_id,
+            }));
+        if let Some(ref client_id) = client_id {
+            event = event.client(client_id);
         }
-        "refresh_token" => {
-            let mut conn = state.db.get().await?;
-            diesel::update(sessions::table.filter(sessions::refresh_token.eq(&form.token)))
-                .set(sessions::revoked.eq(true))
-                .execute(&mut conn)
-                .await
-                .map_err(ApiError::from)?;
+        events::record(&state.db, client, event).await;
+    }
+    Ok(())
+}
+
+// ===========================================================================
+// POST /oidc/introspect
+// ===========================================================================
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct IntrospectRequest {
+    pub token: String,
+    /// Only access tokens can be introspected; other hints are ignored.
+    #[serde(default)]
+    pub token_type_hint: Option<String>,
+    /// For confidential clients using `client_secret_post`.
+    #[serde(default)]
+    pub client_id: Option<String>,
+    #[serde(default)]
+    pub client_secret: Option<String>,
+}
+
+/// Introspection response (RFC 7662 §2.2). Inactive tokens only carry
+/// `active: false`.
+#[derive(Debug, Default, Serialize, ToSchema)]
+pub struct IntrospectResponse {
+    pub active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    /// Client the token was issued to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    /// User ID.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exp: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+}
+
+/// Tell a resource server whether a Hub access token is active (RFC 7662).
+///
+/// Callers authenticate as a confidential OAuth client (HTTP Basic or
+/// `client_secret_post`) or as a pod (`Authorization: Bearer <pod secret>`).
+#[utoipa::path(
+    post,
+    path = "/oidc/introspect",
+    tag = "OIDC",
+    request_body(content = IntrospectRequest, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "Token status", body = IntrospectResponse),
+        (status = 401, description = "Invalid client or pod credentials", body = ApiErrorBody),
+    ),
+)]
+pub async fn introspect(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Form(form): Form<IntrospectRequest>,
+) -> Result<Json<IntrospectResponse>, ApiError> {
+    let bearer = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    match bearer {
+        Some(secret) => {
+            PodClient::authenticate(&state.db, secret).await?;
         }
-        _ => {
-            // Try both.
-            tokens::delete_access_token(state.kv.as_ref(), &form.token).await?;
-            let mut conn = state.db.get().await?;
-            diesel::update(sessions::table.filter(sessions::refresh_token.eq(&form.token)))
-                .set(sessions::revoked.eq(true))
-                .execute(&mut conn)
-                .await
-                .map_err(ApiError::from)?;
+        None => {
+            let oauth_client = clients::authenticate_client(
+                &state.db,
+                &headers,
+                form.client_id.as_deref(),
+                form.client_secret.as_deref(),
+            )
+            .await?;
+            // Public clients can't prove who they are.
+            if !oauth_client.is_confidential() {
+                return Err(ApiError::unauthorized("invalid client credentials"));
+            }
         }
     }
 
-    // Per RFC 7009, always return 200 even if token was invalid.
-    Ok(StatusCode::OK)
+    let Some(data) = tokens::lookup_access_token(state.kv.as_ref(), &form.token).await? else {
+        return Ok(Json(IntrospectResponse::default()));
+    };
+
+    Ok(Json(IntrospectResponse {
+        active: true,
+        scope: Some(data.scopes.join(" ")),
+        client_id: data.client_id,
+        sub: Some(data.user_id),
+        token_type: Some("Bearer"),
+        exp: data.expires_at,
+        iss: Some(state.config.hub_domain.clone()),
+    }))
 }
 
 // ===========================================================================
@@ -715,20 +1645,39 @@ pub async fn revoke(
 // ===========================================================================
 
 /// Verify a PKCE code_verifier against the stored code_challenge (S256 method).
-fn verify_pkce(code_verifier: &str, code_challenge: &str) -> Result<(), ApiError> {
+fn verify_pkce(code_verifier: &str, code_challenge: &str) -> Result<(), OAuthError> {
     use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 
     let hash = Sha256::digest(code_verifier.as_bytes());
     let computed = URL_SAFE_NO_PAD.encode(hash);
 
     if computed != code_challenge {
-        return Err(ApiError::bad_request("PKCE verification failed"));
+        return Err(OAuthError::invalid_grant("PKCE verification failed"));
     }
     Ok(())
 }
 
+/// Hash checked in place of a missing one, so a login that matches no
+/// account, or an account without a password, takes as long to refuse as a
+/// wrong password.
+static DUMMY_PASSWORD_HASH: LazyLock<String> = LazyLock::new(|| {
+    super::users::hash_password("voxora-dummy-password").expect("hash dummy password")
+});
+
+/// Whether `password` matches `hash`. Without a hash the password is still
+/// checked against [`DUMMY_PASSWORD_HASH`], and never matches.
+pub(crate) fn password_matches(password: &str, hash: Option<&str>) -> bool {
+    match hash {
+        Some(hash) => verify_password(password, hash).is_ok(),
+        None => {
+            let _ = verify_password(password, &DUMMY_PASSWORD_HASH);
+            false
+        }
+    }
+}
+
 /// Verify a password against an Argon2id hash.
-fn verify_password(password: &str, hash: &str) -> Result<(), ApiError> {
+pub(crate) fn verify_password(password: &str, hash: &str) -> Result<(), ApiError> {
     use argon2::Argon2;
     use password_hash::{PasswordHash, PasswordVerifier};
 
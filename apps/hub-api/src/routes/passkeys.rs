@@ -0,0 +1,294 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::routing::{get, patch, post};
+use axum::{Json, Router};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::auth::middleware::AuthUser;
+use crate::auth::webauthn::{self, Ceremony, CreationOptions, RegistrationCredential};
+use crate::db::schema::{passkeys, users};
+use crate::error::{ApiError, ApiErrorBody, FieldError};
+use crate::models::passkey::{NewPasskey, Passkey, PasskeyResponse};
+use crate::models::user::User;
+use crate::AppState;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/users/@me/passkeys", get(list_passkeys))
+        .route(
+            "/users/@me/passkeys/register/begin",
+            post(begin_registration),
+        )
+        .route(
+            "/users/@me/passkeys/register/complete",
+            post(complete_registration),
+        )
+        .route(
+            "/users/@me/passkeys/{passkey_id}",
+            patch(rename_passkey).delete(delete_passkey),
+        )
+}
+
+/// Validate and normalize a passkey label.
+fn validate_name(name: &str) -> Result<String, ApiError> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > 64 {
+        return Err(ApiError::validation(vec![FieldError {
+            field: "name".into(),
+            message: "Name must be 1–64 characters".into(),
+        }]));
+    }
+    Ok(name.to_string())
+}
+
+async fn load_passkeys(state: &AppState, user_id: &str) -> Result<Vec<Passkey>, ApiError> {
+    let mut conn = state.db.get().await?;
+    passkeys::table
+        .filter(passkeys::user_id.eq(user_id))
+        .order(passkeys::created_at.asc())
+        .select(Passkey::as_select())
+        .load(&mut conn)
+        .await
+        .map_err(ApiError::from)
+}
+
+// =========================================================================
+// GET /api/v1/users/@me/passkeys — List passkeys
+// =========================================================================
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PasskeyListResponse {
+    pub data: Vec<PasskeyResponse>,
+}
+
+/// `GET /api/v1/users/@me/passkeys` — List the current user's passkeys.
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/@me/passkeys",
+    tag = "Passkeys",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Registered passkeys", body = PasskeyListResponse),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+    ),
+)]
+pub async fn list_passkeys(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Json<PasskeyListResponse>, ApiError> {
+    let data = load_passkeys(&state, &auth.user_id)
+        .await?
+        .into_iter()
+        .map(PasskeyResponse::from)
+        .collect();
+    Ok(Json(PasskeyListResponse { data }))
+}
+
+// =========================================================================
+// POST /api/v1/users/@me/passkeys/register/begin — Start registration
+// =========================================================================
+
+/// `POST /api/v1/users/@me/passkeys/register/begin` — Issue
+/// `PublicKeyCredentialCreationOptions` for `navigator.credentials.create()`.
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/@me/passkeys/register/begin",
+    tag = "Passkeys",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Credential creation options", body = CreationOptions),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+    ),
+)]
+pub async fn begin_registration(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Json<CreationOptions>, ApiError> {
+    let user: User = {
+        let mut conn = state.db.get().await?;
+        users::table
+            .find(&auth.user_id)
+            .select(User::as_select())
+            .first(&mut conn)
+            .await
+            .map_err(ApiError::from)?
+    };
+    let existing = load_passkeys(&state, &user.id).await?;
+
+    let challenge =
+        webauthn::begin_ceremony(state.kv.as_ref(), Ceremony::Registration, Some(&user.id)).await?;
+
+    Ok(Json(webauthn::creation_options(
+        &state.config,
+        challenge,
+        &user,
+        &existing,
+    )))
+}
+
+// =========================================================================
+// POST /api/v1/users/@me/passkeys/register/complete — Finish registration
+// =========================================================================
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CompleteRegistrationRequest {
+    /// Label shown in the passkey list (e.g. "MacBook" or "YubiKey").
+    pub name: String,
+    pub credential: RegistrationCredential,
+}
+
+/// `POST /api/v1/users/@me/passkeys/register/complete` — Verify the
+/// authenticator's response and store the new passkey.
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/@me/passkeys/register/complete",
+    tag = "Passkeys",
+    security(("bearer" = [])),
+    request_body = CompleteRegistrationRequest,
+    responses(
+        (status = 201, description = "Passkey registered", body = PasskeyResponse),
+        (status = 400, description = "Invalid or expired registration", body = ApiErrorBody),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 409, description = "Passkey already registered", body = ApiErrorBody),
+    ),
+)]
+pub async fn complete_registration(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(body): Json<CompleteRegistrationRequest>,
+) -> Result<(StatusCode, Json<PasskeyResponse>), ApiError> {
+    let name = validate_name(&body.name)?;
+
+    let verified = webauthn::finish_registration(
+        &state.config,
+        state.kv.as_ref(),
+        &auth.user_id,
+        &body.credential,
+    )
+    .await?;
+
+    let new_passkey = NewPasskey {
+        id: voxora_common::id::prefixed_ulid(voxora_common::id::prefix::PASSKEY),
+        user_id: auth.user_id.clone(),
+        credential_id: verified.credential_id,
+        public_key: verified.public_key,
+        sign_count: i64::from(verified.sign_count),
+        name,
+        transports: Some(verified.transports),
+    };
+
+    let mut conn = state.db.get().await?;
+    let passkey: Passkey = diesel::insert_into(passkeys::table)
+        .values(&new_passkey)
+        .returning(Passkey::as_returning())
+        .get_result(&mut conn)
+        .await
+        .map_err(|e| match e {
+            diesel::result::Error::DatabaseError(
+                diesel::result::DatabaseErrorKind::UniqueViolation,
+                _,
+            ) => ApiError::conflict("This passkey is already registered"),
+            other => ApiError::from(other),
+        })?;
+
+    tracing::info!(user_id = %auth.user_id, passkey_id = %passkey.id, "passkey registered");
+
+    Ok((StatusCode::CREATED, Json(PasskeyResponse::from(passkey))))
+}
+
+// =========================================================================
+// PATCH /api/v1/users/@me/passkeys/{passkey_id} — Rename a passkey
+// =========================================================================
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RenamePasskeyRequest {
+    pub name: String,
+}
+
+/// `PATCH /api/v1/users/@me/passkeys/{passkey_id}` — Rename a passkey.
+#[utoipa::path(
+    patch,
+    path = "/api/v1/users/@me/passkeys/{passkey_id}",
+    tag = "Passkeys",
+    security(("bearer" = [])),
+    params(
+        ("passkey_id" = String, Path, description = "Passkey ID"),
+    ),
+    request_body = RenamePasskeyRequest,
+    responses(
+        (status = 200, description = "Renamed passkey", body = PasskeyResponse),
+        (status = 400, description = "Validation error", body = ApiErrorBody),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 404, description = "Passkey not found", body = ApiErrorBody),
+    ),
+)]
+pub async fn rename_passkey(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(passkey_id): Path<String>,
+    Json(body): Json<RenamePasskeyRequest>,
+) -> Result<Json<PasskeyResponse>, ApiError> {
+    let name = validate_name(&body.name)?;
+
+    let mut conn = state.db.get().await?;
+    let passkey: Passkey = diesel::update(
+        passkeys::table
+            .find(&passkey_id)
+            .filter(passkeys::user_id.eq(&auth.user_id)),
+    )
+    .set(passkeys::name.eq(&name))
+    .returning(Passkey::as_returning())
+    .get_result(&mut conn)
+    .await
+    .optional()
+    .map_err(ApiError::from)?
+    .ok_or_else(|| ApiError::not_found("Passkey not found"))?;
+
+    Ok(Json(PasskeyResponse::from(passkey)))
+}
+
+// =========================================================================
+// DELETE /api/v1/users/@me/passkeys/{passkey_id} — Remove a passkey
+// =========================================================================
+
+/// `DELETE /api/v1/users/@me/passkeys/{passkey_id}` — Remove a passkey.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/users/@me/passkeys/{passkey_id}",
+    tag = "Passkeys",
+    security(("bearer" = [])),
+    params(
+        ("passkey_id" = String, Path, description = "Passkey ID"),
+    ),
+    responses(
+        (status = 204, description = "Passkey removed"),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 404, description = "Passkey not found", body = ApiErrorBody),
+    ),
+)]
+pub async fn delete_passkey(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(passkey_id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    let mut conn = state.db.get().await?;
+    let deleted = diesel::delete(
+        passkeys::table
+            .find(&passkey_id)
+            .filter(passkeys::user_id.eq(&auth.user_id)),
+    )
+    .execute(&mut conn)
+    .await
+    .map_err(ApiError::from)?;
+
+    if deleted == 0 {
+        return Err(ApiError::not_found("Passkey not found"));
+    }
+
+    tracing::info!(user_id = %auth.user_id, %passkey_id, "passkey removed");
+
+    Ok(StatusCode::NO_CONTENT)
+}
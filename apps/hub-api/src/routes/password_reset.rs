@@ -0,0 +1,263 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::routing::post;
+use axum::{Json, Router};
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::auth::events::{self, AuthEvent};
+use crate::auth::middleware::ClientInfo;
+use crate::auth::throttle;
+use crate::auth::tokens::generate_opaque_token;
+use crate::db::schema::users;
+use crate::error::{ApiError, ApiErrorBody, FieldError};
+use crate::mail::EmailMessage;
+use crate::models::user::User;
+use crate::AppState;
+
+/// How long a reset link stays valid.
+pub const PASSWORD_RESET_TTL_SECS: u64 = 30 * 60;
+
+/// Minimum gap between reset emails to the same user.
+pub const PASSWORD_RESET_RESEND_SECS: u64 = 60;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/password-reset", post(request_password_reset))
+        .route("/password-reset/confirm", post(confirm_password_reset))
+}
+
+/// What a reset token in the KV store points at.
+#[derive(Debug, Serialize, Deserialize)]
+struct PasswordResetData {
+    user_id: String,
+}
+
+fn token_key(token: &str) -> String {
+    format!("hub:password_reset:{token}")
+}
+
+/// Points at the user's newest outstanding token, so requesting a new link
+/// invalidates the previous one.
+fn latest_key(user_id: &str) -> String {
+    format!("hub:password_reset_latest:{user_id}")
+}
+
+// =========================================================================
+// POST /api/v1/password-reset — Request a reset email
+// =========================================================================
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RequestPasswordResetRequest {
+    /// Email address of the account.
+    pub email: String,
+}
+
+/// `POST /api/v1/password-reset` — Email a password reset link to the
+/// account with this address.
+///
+/// Always answers 202, whether or not the address belongs to an account, so
+/// the endpoint can't be used to discover registered emails.
+#[utoipa::path(
+    post,
+    path = "/api/v1/password-reset",
+    tag = "Users",
+    request_body = RequestPasswordResetRequest,
+    responses(
+        (status = 202, description = "If the address belongs to an account, a reset email was sent"),
+    ),
+)]
+pub async fn request_password_reset(
+    State(state): State<AppState>,
+    Json(body): Json<RequestPasswordResetRequest>,
+) -> Result<StatusCode, ApiError> {
+    let email = body.email.trim().to_lowercase();
+
+    // The lookup and the send happen after the response, so neither its
+    // timing nor its content says whether the address has an account.
+    // Failures are logged.
+    tokio::spawn(async move {
+        if let Err(e) = send_reset_email(&state, &email).await {
+            tracing::warn!(error = %e.message, "failed to send password reset email");
+        }
+    });
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+async fn send_reset_email(state: &AppState, email: &str) -> Result<(), ApiError> {
+    let user: Option<User> = {
+        let mut conn = state.db.get().await?;
+        users::table
+            .filter(users::email.eq(email))
+            .select(User::as_select())
+            .first(&mut conn)
+            .await
+            .optional()
+            .map_err(ApiError::from)?
+    };
+    let Some(user) = user else {
+        return Ok(());
+    };
+
+    let throttle_key = format!("hub:password_reset_sent:{}", user.id);
+    if !state
+        .kv
+        .set_nx_ex(&throttle_key, "1", PASSWORD_RESET_RESEND_SECS)
+        .await?
+    {
+        tracing::info!(user_id = %user.id, "password reset requested too soon, not resending");
+        return Ok(());
+    }
+
+    email_reset_link(state, &user, email, false).await
+}
+
+/// Mint a reset token for `user`, replacing any outstanding one, and email
+/// the link to `email`. `forced` is for resets staff started, where the old
+/// password has already been cleared.
+pub(crate) async fn email_reset_link(
+    state: &AppState,
+    user: &User,
+    email: &str,
+    forced: bool,
+) -> Result<(), ApiError> {
+    if let Some(previous) = state.kv.get(&latest_key(&user.id)).await? {
+        state.kv.del(&token_key(&previous)).await?;
+    }
+
+    let token = generate_opaque_token("hpr", 32);
+    let data = PasswordResetData {
+        user_id: user.id.clone(),
+    };
+    let value = serde_json::to_string(&data).map_err(|_| ApiError::internal("serialization"))?;
+    state
+        .kv
+        .set_ex(&token_key(&token), &value, PASSWORD_RESET_TTL_SECS)
+        .await?;
+    state
+        .kv
+        .set_ex(&latest_key(&user.id), &token, PASSWORD_RESET_TTL_SECS)
+        .await?;
+
+    let link = format!(
+        "{}/reset-password?token={token}",
+        state.config.web_url.trim_end_matches('/')
+    );
+    let text = if forced {
+        format!(
+            "Hi {},\n\n\
+             Voxora staff have reset the password for your account and signed \
+             you out everywhere. Choose a new password by opening the link \
+             below:\n\n\
+             {link}\n\n\
+             The link expires in 30 minutes and can only be used once. If it \
+             expires, request a new one from the sign-in page.\n",
+            user.display_name
+        )
+    } else {
+        format!(
+            "Hi {},\n\n\
+             Someone asked to reset the password for your Voxora account. \
+             Choose a new password by opening the link below:\n\n\
+             {link}\n\n\
+             The link expires in 30 minutes and can only be used once. \
+             If you didn't ask for this, you can ignore this email; your \
+             password won't change.\n",
+            user.display_name
+        )
+    };
+    let message = EmailMessage {
+        to: email.to_string(),
+        subject: "Reset your Voxora password".to_string(),
+        text,
+    };
+    state.mailer.send(&message).await?;
+
+    tracing::info!(user_id = %user.id, "password reset email sent");
+    Ok(())
+}
+
+// =========================================================================
+// POST /api/v1/password-reset/confirm — Set a new password
+// =========================================================================
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ConfirmPasswordResetRequest {
+    /// Token from the reset link.
+    pub token: String,
+    /// The new password.
+    pub password: String,
+}
+
+/// `POST /api/v1/password-reset/confirm` — Set a new password using the token
+/// from a reset email. Signs the account out everywhere by revoking all of
+/// its sessions.
+#[utoipa::path(
+    post,
+    path = "/api/v1/password-reset/confirm",
+    tag = "Users",
+    request_body = ConfirmPasswordResetRequest,
+    responses(
+        (status = 204, description = "Password changed"),
+        (status = 400, description = "Invalid or expired token, or validation error", body = ApiErrorBody),
+    ),
+)]
+pub async fn confirm_password_reset(
+    State(state): State<AppState>,
+    client: ClientInfo,
+    Json(body): Json<ConfirmPasswordResetRequest>,
+) -> Result<StatusCode, ApiError> {
+    // Validate before consuming the token so a rejected password doesn't
+    // burn the link.
+    if body.password.len() < 10 {
+        return Err(ApiError::validation(vec![FieldError {
+            field: "password".into(),
+            message: "Password must be at least 10 characters".into(),
+        }]));
+    }
+
+    // Taken and deleted in one step, so concurrent confirms can't both use it.
+    let data: PasswordResetData = match state.kv.get_del(&token_key(&body.token)).await? {
+        Some(v) => {
+            serde_json::from_str(&v).map_err(|_| ApiError::internal("corrupt reset data"))?
+        }
+        None => return Err(ApiError::bad_request("Invalid or expired reset link")),
+    };
+    state.kv.del(&latest_key(&data.user_id)).await?;
+
+    let password_hash = super::users::hash_password(&body.password)?;
+
+    let mut conn = state.db.get().await?;
+    let updated = diesel::update(users::table.find(&data.user_id))
+        .set((
+            users::password_hash.eq(&password_hash),
+            users::updated_at.eq(Utc::now()),
+        ))
+        .execute(&mut conn)
+        .await
+        .map_err(ApiError::from)?;
+    if updated == 0 {
+        return Err(ApiError::bad_request("Invalid or expired reset link"));
+    }
+
+    let revoked = super::sessions::revoke_sessions(&state, &data.user_id, None).await?;
+    // Whoever holds the reset link owns the account; let them straight in.
+    throttle::unlock(state.kv.as_ref(), &data.user_id).await?;
+
+    events::record(
+        &state.db,
+        &client,
+        AuthEvent::new(events::PASSWORD_CHANGED)
+            .user(&data.user_id)
+            .details(serde_json::json!({ "method": "reset", "sessions_revoked": revoked })),
+    )
+    .await;
+
+    tracing::info!(user_id = %data.user_id, revoked, "password reset");
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,84 @@
+use axum::extract::{Query, State};
+use axum::routing::get;
+use axum::{Json, Router};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::auth::middleware::AuthUser;
+use crate::db::schema::auth_events;
+use crate::error::{ApiError, ApiErrorBody};
+use crate::models::auth_event::{AuthEvent, AuthEventResponse};
+use crate::AppState;
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/users/@me/security-events", get(list_security_events))
+}
+
+// =========================================================================
+// GET /api/v1/users/@me/security-events — Authentication history
+// =========================================================================
+
+#[derive(Debug, Deserialize)]
+pub struct SecurityEventParams {
+    pub event: Option<String>,
+    pub before: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SecurityEventListResponse {
+    pub data: Vec<AuthEventResponse>,
+    pub has_more: bool,
+}
+
+/// `GET /api/v1/users/@me/security-events` — List sign-ins, token grants,
+/// SIAs and other authentication events on the current user's account,
+/// newest first.
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/@me/security-events",
+    tag = "Sessions",
+    security(("bearer" = [])),
+    params(
+        ("event" = Option<String>, Query, description = "Filter by event, e.g. login.failure"),
+        ("before" = Option<String>, Query, description = "Cursor: event ID"),
+        ("limit" = Option<i64>, Query, description = "Number of events (1-100, default 50)"),
+    ),
+    responses(
+        (status = 200, description = "Authentication events", body = SecurityEventListResponse),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+    ),
+)]
+pub async fn list_security_events(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Query(params): Query<SecurityEventParams>,
+) -> Result<Json<SecurityEventListResponse>, ApiError> {
+    let limit = params.limit.unwrap_or(50).clamp(1, 100);
+
+    let mut query = auth_events::table
+        .filter(auth_events::user_id.eq(auth.user_id.clone()))
+        .order(auth_events::id.desc())
+        .limit(limit + 1)
+        .select(AuthEvent::as_select())
+        .into_boxed();
+    if let Some(ref event) = params.event {
+        query = query.filter(auth_events::event.eq(event.clone()));
+    }
+    if let Some(ref before) = params.before {
+        query = query.filter(auth_events::id.lt(before.clone()));
+    }
+
+    let mut conn = state.db.get().await?;
+    let rows: Vec<AuthEvent> = query.load(&mut conn).await.map_err(ApiError::from)?;
+
+    let has_more = rows.len() as i64 > limit;
+    let data = rows
+        .into_iter()
+        .take(limit as usize)
+        .map(AuthEventResponse::from)
+        .collect();
+    Ok(Json(SecurityEventListResponse { data, has_more }))
+}
@@ -0,0 +1,225 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::routing::{delete, get};
+use axum::{Json, Router};
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::auth::middleware::AuthUser;
+use crate::auth::tokens;
+use crate::db::kv::KeyValueStore;
+use crate::db::schema::sessions;
+use crate::error::{ApiError, ApiErrorBody};
+use crate::models::session::{Session, SessionResponse};
+use crate::AppState;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/users/@me/sessions",
+            get(list_sessions).delete(revoke_other_sessions),
+        )
+        .route("/users/@me/sessions/{session_id}", delete(revoke_session))
+}
+
+/// Revoke the user's active sessions, except `keep` if given, and delete
+/// their access tokens so they stop working immediately. Returns how many
+/// sessions were revoked.
+pub(crate) async fn revoke_sessions(
+    state: &AppState,
+    user_id: &str,
+    keep: Option<&str>,
+) -> Result<usize, ApiError> {
+    let mut conn = state.db.get().await?;
+    let revoked = revoke_session_rows(&mut conn, user_id, keep).await?;
+    delete_access_tokens(state.kv.as_ref(), &revoked).await?;
+    Ok(revoked.len())
+}
+
+/// Mark the user's active sessions, except `keep` if given, revoked. Returns
+/// their IDs so the caller can [`delete_access_tokens`] once the change is
+/// committed.
+pub(crate) async fn revoke_session_rows(
+    conn: &mut AsyncPgConnection,
+    user_id: &str,
+    keep: Option<&str>,
+) -> Result<Vec<String>, ApiError> {
+    let mut query = diesel::update(sessions::table)
+        .filter(sessions::user_id.eq(user_id))
+        .filter(sessions::revoked.eq(false))
+        .into_boxed();
+    if let Some(keep) = keep {
+        query = query.filter(sessions::id.ne(keep));
+    }
+    query
+        .set(sessions::revoked.eq(true))
+        .returning(sessions::id)
+        .get_results(conn)
+        .await
+        .map_err(ApiError::from)
+}
+
+/// Delete any live access tokens of revoked sessions.
+pub(crate) async fn delete_access_tokens(
+    kv: &dyn KeyValueStore,
+    session_ids: &[String],
+) -> Result<(), ApiError> {
+    for session_id in session_ids {
+        tokens::delete_session_access_token(kv, session_id).await?;
+    }
+    Ok(())
+}
+
+/// Revoke every session in a rotation family and delete access tokens that
+/// may still be live for any of them. Returns how many sessions were still
+/// active.
+pub(crate) async fn revoke_session_family(
+    state: &AppState,
+    family_id: &str,
+) -> Result<usize, ApiError> {
+    let mut conn = state.db.get().await?;
+    let active: Vec<String> = diesel::update(sessions::table)
+        .filter(sessions::family_id.eq(family_id))
+        .filter(sessions::revoked.eq(false))
+        .set(sessions::revoked.eq(true))
+        .returning(sessions::id)
+        .get_results(&mut conn)
+        .await
+        .map_err(ApiError::from)?;
+
+    // Rotated sessions' access tokens outlive the rotation by up to their TTL.
+    let recent: Vec<String> = sessions::table
+        .filter(sessions::family_id.eq(family_id))
+        .filter(
+            sessions::created_at.gt(Utc::now() - Duration::seconds(tokens::ACCESS_TOKEN_TTL_SECS)),
+        )
+        .select(sessions::id)
+        .load(&mut conn)
+        .await
+        .map_err(ApiError::from)?;
+
+    for session_id in active.iter().chain(&recent) {
+        tokens::delete_session_access_token(state.kv.as_ref(), session_id).await?;
+    }
+    Ok(active.len())
+}
+
+// =========================================================================
+// GET /api/v1/users/@me/sessions — List signed-in devices
+// =========================================================================
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SessionListResponse {
+    pub data: Vec<SessionResponse>,
+}
+
+/// `GET /api/v1/users/@me/sessions` — List the current user's active
+/// sessions, most recently used first.
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/@me/sessions",
+    tag = "Sessions",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Active sessions", body = SessionListResponse),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+    ),
+)]
+pub async fn list_sessions(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Json<SessionListResponse>, ApiError> {
+    let mut conn = state.db.get().await?;
+    let rows: Vec<Session> = sessions::table
+        .filter(sessions::user_id.eq(&auth.user_id))
+        .filter(sessions::revoked.eq(false))
+        .filter(sessions::expires_at.gt(Utc::now()))
+        .order(sessions::last_active_at.desc())
+        .select(Session::as_select())
+        .load(&mut conn)
+        .await
+        .map_err(ApiError::from)?;
+
+    let data = rows
+        .into_iter()
+        .map(|s| SessionResponse::from_session(s, auth.session_id.as_deref()))
+        .collect();
+
+    Ok(Json(SessionListResponse { data }))
+}
+
+// =========================================================================
+// DELETE /api/v1/users/@me/sessions/{session_id} — Sign out one device
+// =========================================================================
+
+/// `DELETE /api/v1/users/@me/sessions/{session_id}` — Revoke one session.
+/// Its refresh token and access token stop working immediately.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/users/@me/sessions/{session_id}",
+    tag = "Sessions",
+    security(("bearer" = [])),
+    params(("session_id" = String, Path, description = "Session ID")),
+    responses(
+        (status = 204, description = "Session revoked"),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 404, description = "Session not found", body = ApiErrorBody),
+    ),
+)]
+pub async fn revoke_session(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(session_id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    let mut conn = state.db.get().await?;
+    let updated = diesel::update(
+        sessions::table
+            .find(&session_id)
+            .filter(sessions::user_id.eq(&auth.user_id))
+            .filter(sessions::revoked.eq(false)),
+    )
+    .set(sessions::revoked.eq(true))
+    .execute(&mut conn)
+    .await
+    .map_err(ApiError::from)?;
+
+    if updated == 0 {
+        return Err(ApiError::not_found("Session not found"));
+    }
+
+    tokens::delete_session_access_token(state.kv.as_ref(), &session_id).await?;
+
+    tracing::info!(user_id = %auth.user_id, %session_id, "session revoked");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// =========================================================================
+// DELETE /api/v1/users/@me/sessions — Sign out everywhere else
+// =========================================================================
+
+/// `DELETE /api/v1/users/@me/sessions` — Revoke every session except the
+/// one this request was made with.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/users/@me/sessions",
+    tag = "Sessions",
+    security(("bearer" = [])),
+    responses(
+        (status = 204, description = "Other sessions revoked"),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+    ),
+)]
+pub async fn revoke_other_sessions(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<StatusCode, ApiError> {
+    let revoked = revoke_sessions(&state, &auth.user_id, auth.session_id.as_deref()).await?;
+
+    tracing::info!(user_id = %auth.user_id, revoked, "other sessions revoked");
+
+    Ok(StatusCode::NO_CONTENT)
+}
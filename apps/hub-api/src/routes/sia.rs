@@ -6,7 +6,9 @@ use diesel_async::RunQueryDsl;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
-use crate::auth::middleware::AuthUser;
+use crate::auth::bans;
+use crate::auth::events::{self, AuthEvent};
+use crate::auth::middleware::{AuthUser, ClientInfo};
 use crate::auth::sia;
 use crate::db::schema::{pods, user_pod_bookmarks, users};
 use crate::error::{ApiError, ApiErrorBody};
@@ -41,13 +43,14 @@ pub struct SiaResponse {
         (status = 200, description = "SIA issued", body = SiaResponse),
         (status = 400, description = "Invalid pod_id", body = ApiErrorBody),
         (status = 401, description = "Unauthorized", body = ApiErrorBody),
-        (status = 403, description = "Missing pods scope", body = ApiErrorBody),
+        (status = 403, description = "Missing pods scope, or the account is banned", body = ApiErrorBody),
         (status = 404, description = "Pod not found", body = ApiErrorBody),
     ),
 )]
 pub async fn issue_sia(
     State(state): State<AppState>,
     auth: AuthUser,
+    client: ClientInfo,
     Json(body): Json<SiaRequest>,
 ) -> Result<Json<SiaResponse>, ApiError> {
     // Require `pods` scope.
@@ -62,6 +65,9 @@ pub async fn issue_sia(
         return Err(ApiError::bad_request("Invalid pod_id format"));
     }
 
+    // Banned and suspended users can't sign in to any Pod (RFC §14.4).
+    bans::check_standing(&state.db, &auth.user_id).await?;
+
     let mut conn = state.db.get().await?;
 
     // Look up the Pod — must exist and be active.
@@ -99,7 +105,7 @@ pub async fn issue_sia(
 
     // Mint the SIA JWT.
     let (token, expires_at) = sia::mint_sia(
-        &state.keys,
+        &state.keys.active(),
         &state.config.hub_domain,
         &user.id,
         &pod.id,
@@ -111,6 +117,15 @@ pub async fn issue_sia(
         user.flags,
     )?;
 
+    events::record(
+        &state.db,
+        &client,
+        AuthEvent::new(events::SIA_ISSUED)
+            .user(&user.id)
+            .pod(&pod.id),
+    )
+    .await;
+
     Ok(Json(SiaResponse {
         sia: token,
         expires_at: expires_at.to_rfc3339(),
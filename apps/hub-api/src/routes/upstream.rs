@@ -0,0 +1,463 @@
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum::routing::get;
+use axum::Router;
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use rand::Rng;
+use serde::Deserialize;
+
+use super::oidc::{
+    redirect_with_code, render_login, render_mfa, validate_client_request, AuthorizeParams,
+};
+use crate::auth::events::{self, AuthEvent};
+use crate::auth::mfa::{self, amr, MfaChallengeData};
+use crate::auth::middleware::ClientInfo;
+use crate::auth::tokens::{generate_opaque_token, AuthCodeData};
+use crate::auth::upstream::{self, LoginRequest, UpstreamProfile, UpstreamPurpose};
+use crate::config::UpstreamIdp;
+use crate::db::schema::{user_identities, users};
+use crate::error::ApiError;
+use crate::models::user::{NewUser, User};
+use crate::models::user_identity::{NewUserIdentity, UserIdentity};
+use crate::AppState;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/oidc/authorize/upstream/{provider}",
+            get(authorize_upstream),
+        )
+        .route("/oidc/upstream/{provider}/callback", get(upstream_callback))
+}
+
+// ===========================================================================
+// GET /oidc/authorize/upstream/{provider}
+// ===========================================================================
+
+/// Start signing in through an upstream provider — check the authorization
+/// request, then send the user to the provider with it parked in the KV store.
+#[utoipa::path(
+    get,
+    path = "/oidc/authorize/upstream/{provider}",
+    tag = "OIDC",
+    params(("provider" = String, Path, description = "Upstream provider ID")),
+    responses(
+        (status = 303, description = "Redirect to the upstream provider"),
+        (status = 400, description = "Invalid authorization request"),
+        (status = 404, description = "Unknown provider"),
+    ),
+)]
+pub async fn authorize_upstream(
+    State(state): State<AppState>,
+    Path(provider): Path<String>,
+    Query(params): Query<AuthorizeParams>,
+) -> Response {
+    let Some(idp) = state.config.upstream_idp(&provider) else {
+        return (StatusCode::NOT_FOUND, "unknown provider").into_response();
+    };
+    if params.response_type != "code" {
+        return (StatusCode::BAD_REQUEST, "unsupported response_type").into_response();
+    }
+    let scope = params.scope.unwrap_or_else(|| "openid".to_string());
+    if let Err(e) =
+        validate_client_request(&state, &params.client_id, &params.redirect_uri, &scope).await
+    {
+        return (e.status, e.message).into_response();
+    }
+    let code_challenge = match params.code_challenge {
+        Some(c) if params.code_challenge_method.as_deref() == Some("S256") => c,
+        _ => return (StatusCode::BAD_REQUEST, "PKCE with S256 is required").into_response(),
+    };
+
+    let request = LoginRequest {
+        client_id: params.client_id,
+        redirect_uri: params.redirect_uri,
+        scope,
+        state: params.state,
+        code_challenge,
+        nonce: params.nonce,
+    };
+    match upstream::begin(
+        &state.config,
+        state.kv.as_ref(),
+        idp,
+        UpstreamPurpose::Login(request),
+    )
+    .await
+    {
+        Ok(url) => Redirect::to(&url).into_response(),
+        Err(e) => (e.status, e.message).into_response(),
+    }
+}
+
+// ===========================================================================
+// GET /oidc/upstream/{provider}/callback
+// ===========================================================================
+
+#[derive(Debug, Deserialize)]
+pub struct UpstreamCallbackParams {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    /// Set by the provider when the user cancelled or it refused.
+    pub error: Option<String>,
+}
+
+/// Where the provider sends the user back. Finishes a sign-in (returning to
+/// the client with an auth code, or via the MFA challenge) or a link (back to
+/// the web client's connection settings).
+#[utoipa::path(
+    get,
+    path = "/oidc/upstream/{provider}/callback",
+    tag = "OIDC",
+    params(("provider" = String, Path, description = "Upstream provider ID")),
+    responses(
+        (status = 303, description = "Redirect with auth code, or back to connection settings"),
+        (status = 200, description = "MFA challenge page", content_type = "text/html"),
+        (status = 400, description = "Expired or unknown sign-in"),
+        (status = 401, description = "Login form with an error", content_type = "text/html"),
+    ),
+)]
+pub async fn upstream_callback(
+    State(state): State<AppState>,
+    client: ClientInfo,
+    Path(provider): Path<String>,
+    Query(params): Query<UpstreamCallbackParams>,
+) -> Response {
+    const EXPIRED: &str = "This sign-in has expired. Please start again.";
+
+    let data = match params.state.as_deref() {
+        Some(s) => match upstream::consume_state(state.kv.as_ref(), s).await {
+            Ok(d) => d,
+            Err(e) => return (e.status, e.message).into_response(),
+        },
+        None => None,
+    };
+    let (Some(data), Some(idp)) = (data, state.config.upstream_idp(&provider)) else {
+        return (StatusCode::BAD_REQUEST, EXPIRED).into_response();
+    };
+    if data.provider != idp.id {
+        return (StatusCode::BAD_REQUEST, EXPIRED).into_response();
+    }
+
+    let profile = match (params.error.as_deref(), params.code.as_deref()) {
+        (None, Some(code)) => {
+            upstream::fetch_profile(&state.config, idp, code, &data.code_verifier).await
+        }
+        _ => Err(ApiError::unauthorized("upstream sign-in cancelled")),
+    };
+
+    match data.purpose {
+        UpstreamPurpose::Login(request) => {
+            finish_login(&state, &client, idp, request, profile).await
+        }
+        UpstreamPurpose::Link { user_id } => finish_link(&state, idp, &user_id, profile).await,
+    }
+}
+
+async fn finish_login(
+    state: &AppState,
+    client: &ClientInfo,
+    idp: &UpstreamIdp,
+    request: LoginRequest,
+    profile: Result<UpstreamProfile, ApiError>,
+) -> Response {
+    macro_rules! login_err {
+        ($status:expr, $msg:expr) => {
+            return (
+                $status,
+                Html(render_login(
+                    &state.config.upstream_idps,
+                    "",
+                    "code",
+                    &request.client_id,
+                    &request.redirect_uri,
+                    &request.scope,
+                    request.state.as_deref().unwrap_or(""),
+                    &request.code_challenge,
+                    request.nonce.as_deref().unwrap_or(""),
+                    $msg,
+                    "",
+                )),
+            )
+                .into_response()
+        };
+    }
+    const INTERNAL: &str = "Something went wrong. Please try again.";
+
+    let Ok(profile) = profile else {
+        events::record(
+            &state.db,
+            client,
+            AuthEvent::new(events::LOGIN_FAILURE)
+                .client(&request.client_id)
+                .details(serde_json::json!({ "reason": "upstream", "provider": idp.id })),
+        )
+        .await;
+        login_err!(
+            StatusCode::UNAUTHORIZED,
+            &format!(
+                "Signing in with {} didn't work. Please try again.",
+                idp.name
+            )
+        );
+    };
+
+    let user = match sign_in_or_register(state, idp, &profile).await {
+        Ok(u) => u,
+        Err(e) if e.status == StatusCode::CONFLICT => login_err!(StatusCode::CONFLICT, &e.message),
+        Err(_) => login_err!(StatusCode::INTERNAL_SERVER_ERROR, INTERNAL),
+    };
+
+    tracing::info!(user_id = %user.id, provider = %idp.id, "upstream sign-in");
+
+    let scopes: Vec<String> = request
+        .scope
+        .split_whitespace()
+        .map(|s| s.to_string())
+        .collect();
+
+    // The provider stands in for the password; MFA still applies.
+    if user.mfa_enabled {
+        let mfa_token = generate_opaque_token("hmc", 32);
+        let challenge = MfaChallengeData {
+            user_id: user.id,
+            client_id: request.client_id.clone(),
+            redirect_uri: request.redirect_uri.clone(),
+            code_challenge: request.code_challenge.clone(),
+            scopes,
+            nonce: request.nonce.clone(),
+            state: request.state.clone(),
+            device_code: None,
+            amr: vec![amr::FEDERATED.to_string()],
+        };
+        if mfa::store_mfa_challenge(state.kv.as_ref(), &mfa_token, &challenge)
+            .await
+            .is_err()
+        {
+            login_err!(StatusCode::INTERNAL_SERVER_ERROR, INTERNAL);
+        }
+        return Html(render_mfa(&mfa_token, "")).into_response();
+    }
+
+    let code_data = AuthCodeData {
+        user_id: user.id,
+        client_id: request.client_id.clone(),
+        redirect_uri: request.redirect_uri.clone(),
+        code_challenge: request.code_challenge.clone(),
+        scopes,
+        nonce: request.nonce.clone(),
+        amr: vec![amr::FEDERATED.to_string()],
+    };
+    match redirect_with_code(state, client, &code_data, request.state.as_deref()).await {
+        Ok(redirect) => redirect,
+        Err(e) if e.status == StatusCode::FORBIDDEN => {
+            login_err!(StatusCode::FORBIDDEN, &e.message)
+        }
+        Err(_) => login_err!(StatusCode::INTERNAL_SERVER_ERROR, INTERNAL),
+    }
+}
+
+/// Find the user linked to the provider account, or register a new one.
+///
+/// Existing accounts are never linked automatically: an email that's already
+/// registered is refused, so a provider can't be used to take over an account
+/// by asserting someone else's address. Taken usernames get a numeric suffix.
+async fn sign_in_or_register(
+    state: &AppState,
+    idp: &UpstreamIdp,
+    profile: &UpstreamProfile,
+) -> Result<User, ApiError> {
+    let mut conn = state.db.get().await?;
+
+    let linked: Option<UserIdentity> = user_identities::table
+        .filter(user_identities::provider.eq(&idp.id))
+        .filter(user_identities::subject.eq(&profile.subject))
+        .select(UserIdentity::as_select())
+        .first(&mut conn)
+        .await
+        .optional()?;
+    if let Some(identity) = linked {
+        diesel::update(user_identities::table.find(&identity.id))
+            .set(user_identities::last_used_at.eq(Utc::now()))
+            .execute(&mut conn)
+            .await?;
+        return users::table
+            .find(&identity.user_id)
+            .select(User::as_select())
+            .first(&mut conn)
+            .await
+            .map_err(ApiError::from);
+    }
+
+    let email_conflict = || {
+        ApiError::conflict(format!(
+            "An account with this email already exists. Sign in with it, then connect {} from your account settings.",
+            idp.name
+        ))
+    };
+    if let Some(ref email) = profile.email {
+        let taken: i64 = users::table
+            .filter(users::email.eq(email))
+            .count()
+            .get_result(&mut conn)
+            .await?;
+        if taken > 0 {
+            return Err(email_conflict());
+        }
+    }
+
+    let base = username_base(profile);
+    let display_name: String = profile
+        .name
+        .as_deref()
+        .unwrap_or(&base)
+        .chars()
+        .take(64)
+        .collect();
+
+    let mut user = None;
+    for attempt in 0..5 {
+        let username = if attempt == 0 {
+            base.clone()
+        } else {
+            format!("{}_{:04}", base, rand::thread_rng().gen_range(0..10_000))
+        };
+        let new_user = NewUser {
+            id: voxora_common::id::prefixed_ulid(voxora_common::id::prefix::USER),
+            username_lower: username.to_lowercase(),
+            username,
+            display_name: display_name.clone(),
+            email: profile.email.clone(),
+            password_hash: None,
+            flags: 0,
+            bot_owner_id: None,
+        };
+        match diesel::insert_into(users::table)
+            .values(&new_user)
+            .returning(User::as_returning())
+            .get_result(&mut conn)
+            .await
+        {
+            Ok(u) => {
+                user = Some(u);
+                break;
+            }
+            Err(diesel::result::Error::DatabaseError(
+                diesel::result::DatabaseErrorKind::UniqueViolation,
+                ref info,
+            )) if info.constraint_name().unwrap_or("").contains("username") => continue,
+            Err(diesel::result::Error::DatabaseError(
+                diesel::result::DatabaseErrorKind::UniqueViolation,
+                _,
+            )) => return Err(email_conflict()),
+            Err(e) => return Err(e.into()),
+        }
+    }
+    let Some(mut user) = user else {
+        return Err(ApiError::conflict("Couldn't find a free username"));
+    };
+
+    if profile.email.is_some() && profile.email_verified {
+        user = diesel::update(users::table.find(&user.id))
+            .set(users::email_verified.eq(true))
+            .returning(User::as_returning())
+            .get_result(&mut conn)
+            .await?;
+    }
+
+    let linked = diesel::insert_into(user_identities::table)
+        .values(&NewUserIdentity {
+            id: voxora_common::id::prefixed_ulid(voxora_common::id::prefix::USER_IDENTITY),
+            user_id: user.id.clone(),
+            provider: idp.id.clone(),
+            subject: profile.subject.clone(),
+            email: profile.email.clone(),
+        })
+        .execute(&mut conn)
+        .await;
+    if let Err(e) = linked {
+        // Lost a race with a concurrent sign-in for the same account.
+        let _ = diesel::delete(users::table.find(&user.id))
+            .execute(&mut conn)
+            .await;
+        return Err(e.into());
+    }
+
+    tracing::info!(user_id = %user.id, username = %user.username, provider = %idp.id, "user registered");
+    Ok(user)
+}
+
+/// A username to try for a new account: the provider's username, or the
+/// email's local part, stripped to the characters usernames allow.
+fn username_base(profile: &UpstreamProfile) -> String {
+    let raw = profile
+        .username
+        .as_deref()
+        .or_else(|| profile.email.as_deref().and_then(|e| e.split('@').next()))
+        .unwrap_or("");
+    // Leaves room for the `_NNNN` suffix within the 32-character limit.
+    let cleaned: String = raw
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '_' || *c == '.' || *c == '-')
+        .take(27)
+        .collect();
+    if cleaned.len() < 2 {
+        "user".to_string()
+    } else {
+        cleaned
+    }
+}
+
+async fn finish_link(
+    state: &AppState,
+    idp: &UpstreamIdp,
+    user_id: &str,
+    profile: Result<UpstreamProfile, ApiError>,
+) -> Response {
+    let back = |outcome: &str| {
+        Redirect::to(&format!(
+            "{}/settings/connections?{}",
+            state.config.web_url.trim_end_matches('/'),
+            outcome
+        ))
+        .into_response()
+    };
+
+    let Ok(profile) = profile else {
+        return back("error=upstream_failed");
+    };
+    let Ok(mut conn) = state.db.get().await else {
+        return back("error=server_error");
+    };
+
+    let linked = diesel::insert_into(user_identities::table)
+        .values(&NewUserIdentity {
+            id: voxora_common::id::prefixed_ulid(voxora_common::id::prefix::USER_IDENTITY),
+            user_id: user_id.to_string(),
+            provider: idp.id.clone(),
+            subject: profile.subject,
+            email: profile.email,
+        })
+        .execute(&mut conn)
+        .await;
+    match linked {
+        Ok(_) => {
+            tracing::info!(user_id = %user_id, provider = %idp.id, "upstream identity linked");
+            back(&format!("linked={}", idp.id))
+        }
+        Err(diesel::result::Error::DatabaseError(
+            diesel::result::DatabaseErrorKind::UniqueViolation,
+            ref info,
+        )) if info.constraint_name().unwrap_or("").contains("subject") => {
+            back("error=identity_in_use")
+        }
+        Err(diesel::result::Error::DatabaseError(
+            diesel::result::DatabaseErrorKind::UniqueViolation,
+            _,
+        )) => back("error=already_linked"),
+        Err(_) => back("error=server_error"),
+    }
+}
@@ -8,7 +8,8 @@ use diesel_async::RunQueryDsl;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
-use crate::auth::middleware::AuthUser;
+use crate::auth::middleware::{AuthUser, ClientInfo};
+use crate::auth::throttle;
 use crate::db::schema::{pods, user_pod_bookmarks, user_preferences, users};
 use crate::error::{ApiError, ApiErrorBody, FieldError};
 use crate::models::pod::{Pod, PodResponse};
@@ -27,7 +28,8 @@ pub struct CreateUserRequest {
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/users", post(create_user))
-        .route("/users/@me", get(get_me).patch(update_me))
+        .route("/users/@me", get(get_me).patch(update_me).delete(delete_me))
+        .route("/users/@me/deletion/cancel", post(cancel_deletion))
         .route("/users/@me/pods", get(get_my_pods))
         .route(
             "/users/@me/preferences",
@@ -45,41 +47,19 @@ pub fn router() -> Router<AppState> {
         (status = 201, description = "User created", body = UserResponse),
         (status = 400, description = "Validation error", body = ApiErrorBody),
         (status = 409, description = "Username or email conflict", body = ApiErrorBody),
+        (status = 429, description = "Too many sign-up attempts", body = ApiErrorBody),
     ),
 )]
 pub async fn create_user(
     State(state): State<AppState>,
+    client: ClientInfo,
     Json(body): Json<CreateUserRequest>,
 ) -> Result<(StatusCode, Json<UserResponse>), ApiError> {
     // --- Validation ---
     let mut errors: Vec<FieldError> = Vec::new();
 
-    // Username: 2–32 chars, alphanumeric + _ . -
-    let username = body.username.trim().to_string();
-    if username.len() < 2 || username.len() > 32 {
-        errors.push(FieldError {
-            field: "username".into(),
-            message: "Username must be 2–32 characters".into(),
-        });
-    } else if !username
-        .chars()
-        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '-')
-    {
-        errors.push(FieldError {
-            field: "username".into(),
-            message: "Username may only contain letters, digits, underscores, dots, and hyphens"
-                .into(),
-        });
-    }
-
-    // Display name: 1–64 chars
-    let display_name = body.display_name.trim().to_string();
-    if display_name.is_empty() || display_name.len() > 64 {
-        errors.push(FieldError {
-            field: "display_name".into(),
-            message: "Display name must be 1–64 characters".into(),
-        });
-    }
+    let username = validate_username(&body.username, &mut errors);
+    let display_name = validate_display_name(&body.display_name, &mut errors);
 
     // Email: basic presence check (when provided)
     let email = body.email.as_ref().map(|e| e.trim().to_lowercase());
@@ -104,6 +84,9 @@ pub async fn create_user(
         return Err(ApiError::validation(errors));
     }
 
+    // --- Rate limit by address and username ---
+    throttle::check_registration(state.kv.as_ref(), &username, client.ip_address).await?;
+
     // --- Hash password with Argon2id ---
     let password_hash = hash_password(&body.password)?;
 
@@ -117,7 +100,9 @@ pub async fn create_user(
         username_lower,
         display_name,
         email,
-        password_hash,
+        password_hash: Some(password_hash),
+        flags: 0,
+        bot_owner_id: None,
     };
 
     // --- Insert ---
@@ -147,11 +132,52 @@ pub async fn create_user(
 
     tracing::info!(user_id = %user.id, username = %user.username, "user registered");
 
+    // Registration still succeeds if the mail can't go out; the user can
+    // request another one later.
+    if user.email.is_some() {
+        if let Err(e) = super::email::send_verification_email(&state, &user).await {
+            tracing::warn!(user_id = %user.id, error = %e.message, "failed to send verification email");
+        }
+    }
+
     Ok((StatusCode::CREATED, Json(UserResponse::from(user))))
 }
 
+/// Username: 2–32 chars, alphanumeric + _ . -
+pub(crate) fn validate_username(username: &str, errors: &mut Vec<FieldError>) -> String {
+    let username = username.trim().to_string();
+    if username.len() < 2 || username.len() > 32 {
+        errors.push(FieldError {
+            field: "username".into(),
+            message: "Username must be 2–32 characters".into(),
+        });
+    } else if !username
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '-')
+    {
+        errors.push(FieldError {
+            field: "username".into(),
+            message: "Username may only contain letters, digits, underscores, dots, and hyphens"
+                .into(),
+        });
+    }
+    username
+}
+
+/// Display name: 1–64 chars
+pub(crate) fn validate_display_name(display_name: &str, errors: &mut Vec<FieldError>) -> String {
+    let display_name = display_name.trim().to_string();
+    if display_name.is_empty() || display_name.len() > 64 {
+        errors.push(FieldError {
+            field: "display_name".into(),
+            message: "Display name must be 1–64 characters".into(),
+        });
+    }
+    display_name
+}
+
 /// Hash a password using Argon2id with a random salt.
-fn hash_password(password: &str) -> Result<String, ApiError> {
+pub(crate) fn hash_password(password: &str) -> Result<String, ApiError> {
     use argon2::Argon2;
     use password_hash::rand_core::OsRng;
     use password_hash::{PasswordHasher, SaltString};
@@ -324,6 +350,137 @@ pub async fn get_user(
     Ok(Json(PublicUserResponse::from(user)))
 }
 
+// =========================================================================
+// DELETE /api/v1/users/@me — Delete own account
+// =========================================================================
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct DeleteAccountRequest {
+    /// Current password. Required unless the account signs in only through
+    /// upstream providers.
+    #[serde(default)]
+    pub password: Option<String>,
+}
+
+/// `DELETE /api/v1/users/@me` — Schedule the current user's account for
+/// deletion.
+///
+/// The account is purged once `ACCOUNT_DELETION_GRACE_DAYS` have passed,
+/// and every Pod the user signed in to is sent `user.deleted`. Until then it
+/// keeps working and the deletion can be cancelled.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/users/@me",
+    tag = "Users",
+    security(("bearer" = [])),
+    request_body = DeleteAccountRequest,
+    responses(
+        (status = 202, description = "Deletion scheduled", body = UserResponse),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 403, description = "Invalid password, or a bot account", body = ApiErrorBody),
+        (status = 409, description = "User still owns Pods", body = ApiErrorBody),
+    ),
+)]
+pub async fn delete_me(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(body): Json<DeleteAccountRequest>,
+) -> Result<(StatusCode, Json<UserResponse>), ApiError> {
+    let mut conn = state.db.get().await?;
+    let user: User = users::table
+        .find(&auth.user_id)
+        .select(User::as_select())
+        .first(&mut conn)
+        .await
+        .map_err(ApiError::from)?;
+
+    if user.is_bot() {
+        return Err(ApiError::forbidden("Bots are deleted by their owner"));
+    }
+    if let Some(ref hash) = user.password_hash {
+        let password_ok = body
+            .password
+            .as_deref()
+            .is_some_and(|p| super::oidc::verify_password(p, hash).is_ok());
+        if !password_ok {
+            return Err(ApiError::forbidden("Invalid password"));
+        }
+    }
+    if user.deletion_scheduled_at.is_some() {
+        return Ok((StatusCode::ACCEPTED, Json(UserResponse::from(user))));
+    }
+
+    let owned_pods: i64 = pods::table
+        .filter(pods::owner_id.eq(&user.id))
+        .count()
+        .get_result(&mut conn)
+        .await
+        .map_err(ApiError::from)?;
+    if owned_pods > 0 {
+        return Err(ApiError::conflict(
+            "Transfer or delete your Pods before deleting your account",
+        ));
+    }
+
+    let scheduled_at = Utc::now() + chrono::Duration::days(state.config.account_deletion_grace_days);
+    let user: User = diesel::update(users::table.find(&user.id))
+        .set((
+            users::deletion_scheduled_at.eq(scheduled_at),
+            users::updated_at.eq(Utc::now()),
+        ))
+        .returning(User::as_returning())
+        .get_result(&mut conn)
+        .await
+        .map_err(ApiError::from)?;
+
+    tracing::info!(user_id = %user.id, %scheduled_at, "account deletion scheduled");
+
+    Ok((StatusCode::ACCEPTED, Json(UserResponse::from(user))))
+}
+
+// =========================================================================
+// POST /api/v1/users/@me/deletion/cancel — Keep own account
+// =========================================================================
+
+/// `POST /api/v1/users/@me/deletion/cancel` — Cancel a scheduled account
+/// deletion.
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/@me/deletion/cancel",
+    tag = "Users",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Deletion cancelled", body = UserResponse),
+        (status = 400, description = "No deletion scheduled", body = ApiErrorBody),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+    ),
+)]
+pub async fn cancel_deletion(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Json<UserResponse>, ApiError> {
+    let mut conn = state.db.get().await?;
+    let user: Option<User> = diesel::update(
+        users::table
+            .find(&auth.user_id)
+            .filter(users::deletion_scheduled_at.is_not_null()),
+    )
+    .set((
+        users::deletion_scheduled_at.eq(None::<chrono::DateTime<Utc>>),
+        users::updated_at.eq(Utc::now()),
+    ))
+    .returning(User::as_returning())
+    .get_result(&mut conn)
+    .await
+    .optional()
+    .map_err(ApiError::from)?;
+
+    let user = user.ok_or_else(|| ApiError::bad_request("Account deletion is not scheduled"))?;
+    tracing::info!(user_id = %user.id, "account deletion cancelled");
+
+    Ok(Json(UserResponse::from(user)))
+}
+
 // =========================================================================
 // GET /api/v1/users/@me/pods — List bookmarked pods
 // =========================================================================
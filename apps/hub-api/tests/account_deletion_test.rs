@@ -0,0 +1,268 @@
+//! Integration tests for account deletion and `user.deleted` delivery to Pods.
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use axum::http::StatusCode;
+use axum::routing::post;
+use axum::{Json, Router};
+use axum_test::TestServer;
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use hub_api::db::schema::{pod_notifications, pods, sessions, user_pod_bookmarks, users};
+use hub_api::models::pod_notification::PodNotification;
+use hub_api::models::session::NewSession;
+use hub_api::models::user::USER_FLAG_BOT;
+use hub_api::pod_events::{PodEventClaims, EVENT_USER_DELETED};
+
+/// Serve a Pod's event endpoint on a local port, collecting the tokens it
+/// receives. Returns its base URL.
+async fn mock_pod(received: Arc<Mutex<Vec<String>>>) -> String {
+    let app = Router::new().route(
+        "/api/v1/hub/events",
+        post(move |Json(body): Json<serde_json::Value>| async move {
+            received
+                .lock()
+                .unwrap()
+                .push(body["token"].as_str().unwrap().to_string());
+            StatusCode::NO_CONTENT
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    base
+}
+
+async fn set_pod_url(state: &hub_api::AppState, pod_id: &str, url: &str) {
+    let mut conn = state.db.get().await.unwrap();
+    diesel::update(pods::table.find(pod_id))
+        .set(pods::url.eq(url))
+        .execute(&mut conn)
+        .await
+        .unwrap();
+}
+
+async fn issue_sia(server: &TestServer, access: &str, pod_id: &str) {
+    server
+        .post("/api/v1/oidc/sia")
+        .authorization_bearer(access)
+        .json(&serde_json::json!({ "pod_id": pod_id }))
+        .await
+        .assert_status_ok();
+}
+
+#[tokio::test]
+async fn deletion_is_scheduled_and_can_be_cancelled() {
+    let (app, state) = common::test_app().await;
+    let server = TestServer::new(app).unwrap();
+    let user = common::create_test_user(&state.db, "delete_password_1").await;
+    let access = common::store_test_access_token(state.kv.as_ref(), &user.id, &["openid"]).await;
+
+    // Deleting needs the password.
+    server
+        .delete("/api/v1/users/@me")
+        .authorization_bearer(&access)
+        .json(&serde_json::json!({}))
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+    server
+        .delete("/api/v1/users/@me")
+        .authorization_bearer(&access)
+        .json(&serde_json::json!({ "password": "wrong_password" }))
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+
+    let resp = server
+        .delete("/api/v1/users/@me")
+        .authorization_bearer(&access)
+        .json(&serde_json::json!({ "password": user.password }))
+        .await;
+    resp.assert_status(StatusCode::ACCEPTED);
+    let scheduled: chrono::DateTime<Utc> = resp.json::<serde_json::Value>()
+        ["deletion_scheduled_at"]
+        .as_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    let expected = Utc::now() + Duration::days(state.config.account_deletion_grace_days);
+    assert!((expected - scheduled).num_seconds().abs() < 60);
+
+    // The account keeps working during the grace period.
+    let me: serde_json::Value = server
+        .get("/api/v1/users/@me")
+        .authorization_bearer(&access)
+        .await
+        .json();
+    assert!(me["deletion_scheduled_at"].is_string());
+
+    let resp = server
+        .post("/api/v1/users/@me/deletion/cancel")
+        .authorization_bearer(&access)
+        .await;
+    resp.assert_status_ok();
+    assert!(resp.json::<serde_json::Value>()["deletion_scheduled_at"].is_null());
+    server
+        .post("/api/v1/users/@me/deletion/cancel")
+        .authorization_bearer(&access)
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    // Pod owners have to hand their Pods over first.
+    let pod_id = common::create_test_pod(&state.db, &user.id).await;
+    server
+        .delete("/api/v1/users/@me")
+        .authorization_bearer(&access)
+        .json(&serde_json::json!({ "password": user.password }))
+        .await
+        .assert_status(StatusCode::CONFLICT);
+
+    common::cleanup_test_pod(&state.db, &pod_id).await;
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
+
+#[tokio::test]
+async fn purge_removes_the_account_and_notifies_its_pods() {
+    let (app, state) = common::test_app().await;
+    let server = TestServer::new(app).unwrap();
+    let pod_owner = common::create_test_user(&state.db, "delete_password_1").await;
+    let user = common::create_test_user(&state.db, "delete_password_1").await;
+    let access =
+        common::store_test_access_token(state.kv.as_ref(), &user.id, &["openid", "pods"]).await;
+
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let live_pod = common::create_test_pod(&state.db, &pod_owner.id).await;
+    set_pod_url(&state, &live_pod, &mock_pod(received.clone()).await).await;
+    let down_pod = common::create_test_pod(&state.db, &pod_owner.id).await;
+    set_pod_url(&state, &down_pod, "http://127.0.0.1:1").await;
+
+    // The user signs in to both Pods, and their bot to one.
+    issue_sia(&server, &access, &live_pod).await;
+    issue_sia(&server, &access, &down_pod).await;
+    let bot_id = voxora_common::id::prefixed_ulid(voxora_common::id::prefix::USER);
+    let mut conn = state.db.get().await.unwrap();
+    diesel::insert_into(users::table)
+        .values((
+            users::id.eq(&bot_id),
+            users::username.eq(format!("bot_{}", &bot_id[4..16])),
+            users::username_lower.eq(format!("bot_{}", &bot_id[4..16]).to_lowercase()),
+            users::display_name.eq("Bot"),
+            users::flags.eq(USER_FLAG_BOT),
+            users::bot_owner_id.eq(&user.id),
+        ))
+        .execute(&mut conn)
+        .await
+        .unwrap();
+    let bot_access =
+        common::store_test_access_token(state.kv.as_ref(), &bot_id, &["openid", "pods"]).await;
+    issue_sia(&server, &bot_access, &live_pod).await;
+
+    diesel::insert_into(sessions::table)
+        .values(&NewSession {
+            id: voxora_common::id::prefixed_ulid(voxora_common::id::prefix::SESSION),
+            user_id: user.id.clone(),
+            refresh_token: hub_api::auth::tokens::generate_refresh_token(),
+            ip_address: None,
+            user_agent: None,
+            client_id: None,
+            expires_at: Utc::now() + Duration::days(30),
+            family_id: "fam_delete_test".to_string(),
+            scopes: vec!["openid".to_string()],
+        })
+        .execute(&mut conn)
+        .await
+        .unwrap();
+
+    server
+        .delete("/api/v1/users/@me")
+        .authorization_bearer(&access)
+        .json(&serde_json::json!({ "password": user.password }))
+        .await
+        .assert_status(StatusCode::ACCEPTED);
+
+    // Nothing happens until the grace period is over.
+    hub_api::accounts::purge_due_accounts(&state).await.unwrap();
+    assert!(users::table
+        .find(&user.id)
+        .select(users::id)
+        .first::<String>(&mut conn)
+        .await
+        .is_ok());
+
+    diesel::update(users::table.find(&user.id))
+        .set(users::deletion_scheduled_at.eq(Utc::now() - Duration::minutes(1)))
+        .execute(&mut conn)
+        .await
+        .unwrap();
+    hub_api::accounts::purge_due_accounts(&state).await.unwrap();
+
+    let remaining: i64 = users::table
+        .filter(users::id.eq_any([&user.id, &bot_id]))
+        .count()
+        .get_result(&mut conn)
+        .await
+        .unwrap();
+    assert_eq!(remaining, 0);
+    let sessions_left: i64 = sessions::table
+        .filter(sessions::user_id.eq(&user.id))
+        .count()
+        .get_result(&mut conn)
+        .await
+        .unwrap();
+    assert_eq!(sessions_left, 0);
+    let bookmarks_left: i64 = user_pod_bookmarks::table
+        .filter(user_pod_bookmarks::user_id.eq_any([&user.id, &bot_id]))
+        .count()
+        .get_result(&mut conn)
+        .await
+        .unwrap();
+    assert_eq!(bookmarks_left, 0);
+
+    // One event per Pod each account used, delivered signed.
+    hub_api::pod_events::deliver_pending(&state).await.unwrap();
+    let mut tokens = received.lock().unwrap().clone();
+    assert_eq!(tokens.len(), 2);
+    tokens.sort();
+    let mut validation = jsonwebtoken::Validation::new(jsonwebtoken::Algorithm::EdDSA);
+    validation.set_audience(&[&live_pod]);
+    validation.set_issuer(&[&state.config.hub_domain]);
+    let mut subjects: Vec<String> = tokens
+        .iter()
+        .map(|t| {
+            let header = jsonwebtoken::decode_header(t).unwrap();
+            assert_eq!(header.typ.as_deref(), Some("voxora-event+jwt"));
+            let claims = jsonwebtoken::decode::<PodEventClaims>(
+                t,
+                &state.keys.active().decoding,
+                &validation,
+            )
+            .unwrap()
+            .claims;
+            assert_eq!(claims.event, EVENT_USER_DELETED);
+            claims.sub
+        })
+        .collect();
+    subjects.sort();
+    let mut expected = vec![user.id.clone(), bot_id.clone()];
+    expected.sort();
+    assert_eq!(subjects, expected);
+
+    // The unreachable Pod's event waits for a retry.
+    let pending: PodNotification = pod_notifications::table
+        .filter(pod_notifications::pod_id.eq(&down_pod))
+        .select(PodNotification::as_select())
+        .first(&mut conn)
+        .await
+        .unwrap();
+    assert_eq!(pending.user_id, user.id);
+    assert_eq!(pending.attempts, 1);
+    assert!(pending.delivered_at.is_none());
+    assert!(pending.last_error.is_some());
+    assert!(pending.next_attempt_at > Utc::now());
+
+    common::cleanup_test_pod(&state.db, &live_pod).await;
+    common::cleanup_test_pod(&state.db, &down_pod).await;
+    common::cleanup_test_user(&state.db, &pod_owner.id).await;
+}
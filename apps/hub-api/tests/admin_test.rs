@@ -0,0 +1,246 @@
+//! Integration tests for the staff-only admin API and its audit log.
+
+mod common;
+
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use hub_api::db::schema::{pods, sessions, users};
+use hub_api::models::session::NewSession;
+use hub_api::models::user::USER_FLAG_VERIFIED;
+
+async fn insert_session(state: &hub_api::AppState, user_id: &str) {
+    let mut conn = state.db.get().await.unwrap();
+    let id = voxora_common::id::prefixed_ulid(voxora_common::id::prefix::SESSION);
+    diesel::insert_into(sessions::table)
+        .values(&NewSession {
+            id: id.clone(),
+            user_id: user_id.to_string(),
+            refresh_token: hub_api::auth::tokens::generate_refresh_token(),
+            ip_address: None,
+            user_agent: Some("Admin Test".to_string()),
+            client_id: None,
+            expires_at: Utc::now() + Duration::days(30),
+            family_id: id,
+            scopes: vec!["openid".to_string()],
+        })
+        .execute(&mut conn)
+        .await
+        .unwrap();
+}
+
+async fn audit_actions(server: &TestServer, access: &str, target_id: &str) -> Vec<String> {
+    let body: serde_json::Value = server
+        .get(&format!("/api/v1/admin/audit-log?target_id={target_id}"))
+        .authorization_bearer(access)
+        .await
+        .json();
+    body["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["action"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn staff_manage_users() {
+    let (app, state) = common::test_app().await;
+    let server = TestServer::new(app).unwrap();
+    let admin = common::create_test_user(&state.db, "admin_password_1").await;
+    common::make_staff(&state.db, &admin.id).await;
+    let admin_access =
+        common::store_test_access_token(state.kv.as_ref(), &admin.id, &["openid"]).await;
+    let user = common::create_test_user(&state.db, "admin_password_1").await;
+    let access = common::store_test_access_token(state.kv.as_ref(), &user.id, &["openid"]).await;
+    insert_session(&state, &user.id).await;
+
+    // Everything under /admin is staff-only.
+    server
+        .get("/api/v1/admin/users")
+        .authorization_bearer(&access)
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+
+    let found: serde_json::Value = server
+        .get(&format!(
+            "/api/v1/admin/users?q={}",
+            user.username.to_uppercase()
+        ))
+        .authorization_bearer(&admin_access)
+        .await
+        .json();
+    assert_eq!(found["data"].as_array().unwrap().len(), 1);
+    assert_eq!(found["data"][0]["id"], user.id);
+    let found: serde_json::Value = server
+        .get(&format!("/api/v1/admin/users?q={}", user.id))
+        .authorization_bearer(&admin_access)
+        .await
+        .json();
+    assert_eq!(found["data"][0]["email"], user.email);
+
+    let sessions_path = format!("/api/v1/admin/users/{}/sessions", user.id);
+    let listed: serde_json::Value = server
+        .get(&sessions_path)
+        .authorization_bearer(&admin_access)
+        .await
+        .json();
+    assert_eq!(listed["data"][0]["user_agent"], "Admin Test");
+
+    let resp = server
+        .post(&format!("/api/v1/admin/users/{}/logout", user.id))
+        .authorization_bearer(&admin_access)
+        .await;
+    resp.assert_status_ok();
+    assert_eq!(resp.json::<serde_json::Value>()["revoked_sessions"], 1);
+    let listed: serde_json::Value = server
+        .get(&sessions_path)
+        .authorization_bearer(&admin_access)
+        .await
+        .json();
+    assert!(listed["data"].as_array().unwrap().is_empty());
+
+    // A forced reset clears the password and emails a link.
+    let resp = server
+        .post(&format!("/api/v1/admin/users/{}/password-reset", user.id))
+        .authorization_bearer(&admin_access)
+        .await;
+    resp.assert_status_ok();
+    assert_eq!(resp.json::<serde_json::Value>()["email_sent"], true);
+    let mut conn = state.db.get().await.unwrap();
+    let hash: Option<String> = users::table
+        .find(&user.id)
+        .select(users::password_hash)
+        .first(&mut conn)
+        .await
+        .unwrap();
+    assert!(hash.is_none());
+    assert!(common::sent_emails(&user.email)
+        .iter()
+        .any(|m| m.subject == "Reset your Voxora password"));
+
+    let resp = server
+        .patch(&format!("/api/v1/admin/users/{}/flags", user.id))
+        .authorization_bearer(&admin_access)
+        .json(&serde_json::json!({ "verified": true, "reason": "Known creator" }))
+        .await;
+    resp.assert_status_ok();
+    assert_eq!(
+        resp.json::<serde_json::Value>()["flags"],
+        USER_FLAG_VERIFIED
+    );
+    server
+        .patch(&format!("/api/v1/admin/users/{}/flags", admin.id))
+        .authorization_bearer(&admin_access)
+        .json(&serde_json::json!({ "staff": false }))
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+
+    assert_eq!(
+        audit_actions(&server, &admin_access, &user.id).await,
+        vec!["user.flags_update", "user.password_reset", "user.logout"]
+    );
+    let entries: serde_json::Value = server
+        .get(&format!(
+            "/api/v1/admin/audit-log?target_id={}&action=user.flags_update",
+            user.id
+        ))
+        .authorization_bearer(&admin_access)
+        .await
+        .json();
+    assert_eq!(entries["data"][0]["actor_id"], admin.id);
+    assert_eq!(entries["data"][0]["reason"], "Known creator");
+    assert_eq!(
+        entries["data"][0]["changes"]["flags"]["new"],
+        USER_FLAG_VERIFIED
+    );
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+    common::cleanup_test_user(&state.db, &admin.id).await;
+}
+
+#[tokio::test]
+async fn staff_suspend_and_delist_pods() {
+    let (app, state) = common::test_app().await;
+    let server = TestServer::new(app).unwrap();
+    let admin = common::create_test_user(&state.db, "admin_password_1").await;
+    common::make_staff(&state.db, &admin.id).await;
+    let admin_access =
+        common::store_test_access_token(state.kv.as_ref(), &admin.id, &["openid"]).await;
+    let owner = common::create_test_user(&state.db, "admin_password_1").await;
+    let owner_access =
+        common::store_test_access_token(state.kv.as_ref(), &owner.id, &["openid", "pods"]).await;
+    let pod_id = common::create_test_pod(&state.db, &owner.id).await;
+
+    // Private pods are listed too.
+    let mut conn = state.db.get().await.unwrap();
+    diesel::update(pods::table.find(&pod_id))
+        .set(pods::public.eq(false))
+        .execute(&mut conn)
+        .await
+        .unwrap();
+    let listed: serde_json::Value = server
+        .get(&format!("/api/v1/admin/pods?q={pod_id}"))
+        .authorization_bearer(&admin_access)
+        .await
+        .json();
+    assert_eq!(listed["data"][0]["id"], pod_id);
+    assert_eq!(listed["data"][0]["public"], false);
+    let offline: serde_json::Value = server
+        .get(&format!("/api/v1/admin/pods?q={pod_id}&offline=true"))
+        .authorization_bearer(&admin_access)
+        .await
+        .json();
+    assert_eq!(offline["data"].as_array().unwrap().len(), 1);
+
+    let pod_path = format!("/api/v1/admin/pods/{pod_id}");
+    server
+        .patch(&pod_path)
+        .authorization_bearer(&owner_access)
+        .json(&serde_json::json!({ "status": "suspended" }))
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+    server
+        .patch(&pod_path)
+        .authorization_bearer(&admin_access)
+        .json(&serde_json::json!({ "status": "gone" }))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    let resp = server
+        .patch(&pod_path)
+        .authorization_bearer(&admin_access)
+        .json(&serde_json::json!({ "status": "suspended", "reason": "Malware links" }))
+        .await;
+    resp.assert_status_ok();
+    assert_eq!(resp.json::<serde_json::Value>()["status"], "suspended");
+    server
+        .post("/api/v1/oidc/sia")
+        .authorization_bearer(&owner_access)
+        .json(&serde_json::json!({ "pod_id": pod_id }))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    server
+        .patch(&pod_path)
+        .authorization_bearer(&admin_access)
+        .json(&serde_json::json!({ "status": "active", "public": true }))
+        .await
+        .assert_status_ok();
+    server
+        .post("/api/v1/oidc/sia")
+        .authorization_bearer(&owner_access)
+        .json(&serde_json::json!({ "pod_id": pod_id }))
+        .await
+        .assert_status_ok();
+
+    let mut actions = audit_actions(&server, &admin_access, &pod_id).await;
+    actions.sort();
+    assert_eq!(actions, vec!["pod.relist", "pod.suspend", "pod.unsuspend"]);
+
+    common::cleanup_test_pod(&state.db, &pod_id).await;
+    common::cleanup_test_user(&state.db, &owner.id).await;
+    common::cleanup_test_user(&state.db, &admin.id).await;
+}
@@ -48,6 +48,7 @@ fn sign_and_verify_roundtrip() {
         "usr_123",
         Some("nonce1"),
         &["openid".to_string(), "profile".to_string()],
+        &[],
         "testuser",
         "Test User",
         None,
@@ -88,6 +89,7 @@ fn cannot_verify_with_different_key() {
         "usr_456",
         None,
         &["openid".to_string()],
+        &[],
         "testuser",
         "Test",
         None,
@@ -107,3 +109,74 @@ fn cannot_verify_with_different_key() {
     );
     assert!(result.is_err(), "verifying with wrong key should fail");
 }
+
+// =========================================================================
+// Keyring
+// =========================================================================
+
+fn temp_keyring_dir() -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("voxora-keyring-{}", rand::random::<u64>()))
+}
+
+#[test]
+fn new_keyring_starts_from_the_seed_and_publishes_a_next_key() {
+    use hub_api::auth::keys::{KeyStatus, Keyring};
+
+    let dir = temp_keyring_dir();
+    let keyring = Keyring::load_dir(&dir, Some("test-seed")).unwrap();
+    assert_eq!(
+        keyring.active().kid,
+        SigningKeys::from_seed("test-seed").kid
+    );
+
+    let published = keyring.published();
+    assert_eq!(published.len(), 2);
+    assert_eq!(published[1].status, KeyStatus::Next);
+
+    // Reloading gives the same keyring.
+    let reloaded = Keyring::load_dir(&dir, None).unwrap();
+    assert_eq!(reloaded.active().kid, keyring.active().kid);
+    assert_eq!(reloaded.published()[1].keys.kid, published[1].keys.kid);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn rotation_activates_next_and_keeps_the_old_key_published() {
+    use hub_api::auth::keys::{KeyStatus, Keyring};
+
+    let dir = temp_keyring_dir();
+    let keyring = Keyring::load_dir(&dir, None).unwrap();
+    let old_active = keyring.active().kid.clone();
+    let old_next = keyring.published()[1].keys.kid.clone();
+
+    keyring.rotate().await.unwrap();
+
+    let published = keyring.published();
+    let status_of = |kid: &str| {
+        published
+            .iter()
+            .find(|k| k.keys.kid == kid)
+            .map(|k| k.status)
+    };
+    assert_eq!(keyring.active().kid, old_next);
+    assert_eq!(status_of(&old_active), Some(KeyStatus::Retired));
+    assert_eq!(published.len(), 3);
+    assert!(published
+        .iter()
+        .any(|k| k.status == KeyStatus::Next && k.keys.kid != old_active));
+
+    // The rotation survives a restart.
+    let reloaded = Keyring::load_dir(&dir, None).unwrap();
+    assert_eq!(reloaded.active().kid, old_next);
+    assert_eq!(reloaded.published().len(), 3);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn seed_keyring_cannot_rotate() {
+    let keyring = hub_api::auth::keys::Keyring::from_seed("test-seed");
+    assert_eq!(keyring.published().len(), 1);
+    assert!(keyring.rotate().await.is_err());
+}
@@ -50,6 +50,7 @@ fn mint_id_token_minimal_scopes() {
         "usr_abc",
         None,
         &["openid".to_string()],
+        &[],
         "alice",
         "Alice",
         None,
@@ -86,6 +87,7 @@ fn mint_id_token_profile_scope() {
         "usr_abc",
         Some("n123"),
         &["openid".to_string(), "profile".to_string()],
+        &[],
         "bob",
         "Bob Builder",
         Some("https://example.com/avatar.png"),
@@ -122,6 +124,7 @@ fn mint_id_token_email_scope() {
         "usr_abc",
         None,
         &["openid".to_string(), "email".to_string()],
+        &[],
         "carol",
         "Carol",
         None,
@@ -157,6 +160,7 @@ fn mint_id_token_all_scopes() {
             "profile".to_string(),
             "email".to_string(),
         ],
+        &[],
         "dave",
         "Dave D",
         None,
@@ -189,6 +193,7 @@ fn id_token_header_has_kid() {
         "usr_abc",
         None,
         &["openid".to_string()],
+        &[],
         "eve",
         "Eve",
         None,
@@ -212,10 +217,12 @@ async fn store_and_lookup_access_token() {
     let kv = state.kv.as_ref();
 
     let token = generate_access_token();
-    let data = AccessTokenData {
-        user_id: "usr_test1".to_string(),
-        scopes: vec!["openid".to_string(), "profile".to_string()],
-    };
+    let data = AccessTokenData::new(
+        "usr_test1".to_string(),
+        vec!["openid".to_string(), "profile".to_string()],
+        None,
+        None,
+    );
 
     store_access_token(kv, &token, &data).await.unwrap();
     let found = lookup_access_token(kv, &token).await.unwrap();
@@ -245,10 +252,12 @@ async fn delete_access_token_removes_it() {
     let kv = state.kv.as_ref();
 
     let token = generate_access_token();
-    let data = AccessTokenData {
-        user_id: "usr_del".to_string(),
-        scopes: vec!["openid".to_string()],
-    };
+    let data = AccessTokenData::new(
+        "usr_del".to_string(),
+        vec!["openid".to_string()],
+        None,
+        None,
+    );
 
     store_access_token(kv, &token, &data).await.unwrap();
     delete_access_token(kv, &token).await.unwrap();
@@ -270,6 +279,7 @@ async fn store_and_consume_auth_code() {
         code_challenge: "test_challenge".to_string(),
         scopes: vec!["openid".to_string()],
         nonce: Some("n1".to_string()),
+        amr: vec!["pwd".to_string()],
     };
 
     store_auth_code(kv, &code, &data).await.unwrap();
@@ -0,0 +1,260 @@
+//! Integration tests for bot accounts and the `client_credentials` grant.
+
+mod common;
+
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+fn basic(client_id: &str, secret: &str) -> String {
+    format!("Basic {}", STANDARD.encode(format!("{client_id}:{secret}")))
+}
+
+async fn create_bot(server: &TestServer, access: &str, username: &str) -> serde_json::Value {
+    let resp = server
+        .post("/api/v1/bots")
+        .authorization_bearer(access)
+        .json(&serde_json::json!({ "username": username, "display_name": "Helper Bot" }))
+        .await;
+    resp.assert_status(StatusCode::CREATED);
+    resp.json()
+}
+
+fn bot_username() -> String {
+    format!("bot_{}", rand::random::<u32>())
+}
+
+#[tokio::test]
+async fn bot_signs_in_with_client_credentials_and_gets_bot_sia() {
+    let (app, state) = common::test_app().await;
+    let owner = common::create_test_user(&state.db, "bots_password_1").await;
+    let owner_access =
+        common::store_test_access_token(state.kv.as_ref(), &owner.id, &["openid"]).await;
+    let pod_id = common::create_test_pod(&state.db, &owner.id).await;
+    let server = TestServer::new(app).unwrap();
+
+    let created = create_bot(&server, &owner_access, &bot_username()).await;
+    let bot_id = created["bot"]["id"].as_str().unwrap();
+    let client_id = created["client_id"].as_str().unwrap();
+    let secret = created["client_secret"].as_str().unwrap();
+    assert!(bot_id.starts_with("usr_"));
+    assert_eq!(created["bot"]["flags"], 4);
+    assert_eq!(created["bot"]["client_id"], client_id);
+
+    let resp = server
+        .post("/oidc/token")
+        .add_header("authorization", basic(client_id, secret))
+        .form(&[("grant_type", "client_credentials")])
+        .await;
+    resp.assert_status_ok();
+    let tokens: serde_json::Value = resp.json();
+    assert_eq!(tokens["scope"], "pods");
+    assert!(tokens.get("refresh_token").is_none());
+    assert!(tokens.get("id_token").is_none());
+    let bot_access = tokens["access_token"].as_str().unwrap();
+
+    // The token acts as the bot.
+    let me: serde_json::Value = server
+        .get("/api/v1/users/@me")
+        .authorization_bearer(bot_access)
+        .await
+        .json();
+    assert_eq!(me["id"], bot_id);
+
+    // SIAs for the bot carry the bot flag.
+    let resp = server
+        .post("/api/v1/oidc/sia")
+        .authorization_bearer(bot_access)
+        .json(&serde_json::json!({ "pod_id": pod_id }))
+        .await;
+    resp.assert_status_ok();
+    let sia = resp.json::<serde_json::Value>()["sia"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let mut validation = jsonwebtoken::Validation::new(jsonwebtoken::Algorithm::EdDSA);
+    validation.set_audience(&[&pod_id]);
+    let claims = jsonwebtoken::decode::<hub_api::auth::sia::SiaClaims>(
+        &sia,
+        &state.keys.active().decoding,
+        &validation,
+    )
+    .unwrap()
+    .claims;
+    assert_eq!(claims.sub, bot_id);
+    assert_eq!(claims.flags, vec!["bot".to_string()]);
+
+    // Bots can't create bots.
+    server
+        .post("/api/v1/bots")
+        .authorization_bearer(bot_access)
+        .json(&serde_json::json!({ "username": bot_username(), "display_name": "Nested" }))
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+
+    common::cleanup_test_pod(&state.db, &pod_id).await;
+    common::cleanup_test_user(&state.db, &owner.id).await;
+}
+
+#[tokio::test]
+async fn client_credentials_is_restricted() {
+    let (app, state) = common::test_app().await;
+    let owner = common::create_test_user(&state.db, "bots_password_1").await;
+    let owner_access =
+        common::store_test_access_token(state.kv.as_ref(), &owner.id, &["openid"]).await;
+    let server = TestServer::new(app).unwrap();
+
+    let created = create_bot(&server, &owner_access, &bot_username()).await;
+    let client_id = created["client_id"].as_str().unwrap();
+    let secret = created["client_secret"].as_str().unwrap();
+
+    // Wrong secret.
+    server
+        .post("/oidc/token")
+        .add_header("authorization", basic(client_id, "hcs_wrong"))
+        .form(&[("grant_type", "client_credentials")])
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+
+    // Scope the bot client isn't allowed.
+    server
+        .post("/oidc/token")
+        .form(&[
+            ("grant_type", "client_credentials"),
+            ("client_id", client_id),
+            ("client_secret", secret),
+            ("scope", "pods email"),
+        ])
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    // Regular clients can't use the grant.
+    let resp = server
+        .post("/api/v1/oauth-clients")
+        .authorization_bearer(&owner_access)
+        .json(&serde_json::json!({
+            "name": "Web",
+            "client_type": "confidential",
+            "redirect_uris": ["https://app.example.com/cb"],
+        }))
+        .await;
+    resp.assert_status(StatusCode::CREATED);
+    let web: serde_json::Value = resp.json();
+    server
+        .post("/oidc/token")
+        .add_header(
+            "authorization",
+            basic(
+                web["client_id"].as_str().unwrap(),
+                web["client_secret"].as_str().unwrap(),
+            ),
+        )
+        .form(&[("grant_type", "client_credentials")])
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    // Nor can clients register for it.
+    server
+        .post("/api/v1/oauth-clients")
+        .authorization_bearer(&owner_access)
+        .json(&serde_json::json!({
+            "name": "Sneaky",
+            "client_type": "confidential",
+            "grant_types": ["client_credentials"],
+        }))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    // And a bot's client can't be turned into a sign-in client.
+    let resp = server
+        .patch(&format!("/api/v1/oauth-clients/{client_id}"))
+        .authorization_bearer(&owner_access)
+        .json(&serde_json::json!({
+            "grant_types": ["authorization_code"],
+            "redirect_uris": ["https://app.example.com/cb"],
+        }))
+        .await;
+    resp.assert_status(StatusCode::BAD_REQUEST);
+    server
+        .delete(&format!("/api/v1/oauth-clients/{client_id}"))
+        .authorization_bearer(&owner_access)
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    common::cleanup_test_user(&state.db, &owner.id).await;
+}
+
+#[tokio::test]
+async fn owner_manages_bots() {
+    let (app, state) = common::test_app().await;
+    let owner = common::create_test_user(&state.db, "bots_password_1").await;
+    let other = common::create_test_user(&state.db, "bots_password_1").await;
+    let owner_access =
+        common::store_test_access_token(state.kv.as_ref(), &owner.id, &["openid"]).await;
+    let other_access =
+        common::store_test_access_token(state.kv.as_ref(), &other.id, &["openid"]).await;
+    let server = TestServer::new(app).unwrap();
+
+    let username = bot_username();
+    let created = create_bot(&server, &owner_access, &username).await;
+    let bot_id = created["bot"]["id"].as_str().unwrap();
+    let client_id = created["client_id"].as_str().unwrap();
+    let secret = created["client_secret"].as_str().unwrap();
+
+    // Usernames are shared with people.
+    server
+        .post("/api/v1/bots")
+        .authorization_bearer(&owner_access)
+        .json(&serde_json::json!({ "username": username, "display_name": "Copy" }))
+        .await
+        .assert_status(StatusCode::CONFLICT);
+    server
+        .post("/api/v1/bots")
+        .authorization_bearer(&owner_access)
+        .json(&serde_json::json!({ "username": "x", "display_name": "" }))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    let list: serde_json::Value = server
+        .get("/api/v1/bots")
+        .authorization_bearer(&owner_access)
+        .await
+        .json();
+    assert_eq!(list["data"].as_array().unwrap().len(), 1);
+    assert_eq!(list["data"][0]["id"], bot_id);
+    assert_eq!(list["data"][0]["client_id"], client_id);
+    let other_list: serde_json::Value = server
+        .get("/api/v1/bots")
+        .authorization_bearer(&other_access)
+        .await
+        .json();
+    assert!(other_list["data"].as_array().unwrap().is_empty());
+
+    server
+        .delete(&format!("/api/v1/bots/{bot_id}"))
+        .authorization_bearer(&other_access)
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+    server
+        .delete(&format!("/api/v1/bots/{bot_id}"))
+        .authorization_bearer(&owner_access)
+        .await
+        .assert_status(StatusCode::NO_CONTENT);
+
+    // The client went with it.
+    server
+        .post("/oidc/token")
+        .add_header("authorization", basic(client_id, secret))
+        .form(&[("grant_type", "client_credentials")])
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+    let list: serde_json::Value = server
+        .get("/api/v1/bots")
+        .authorization_bearer(&owner_access)
+        .await
+        .json();
+    assert!(list["data"].as_array().unwrap().is_empty());
+
+    common::cleanup_test_user(&state.db, &other.id).await;
+    common::cleanup_test_user(&state.db, &owner.id).await;
+}
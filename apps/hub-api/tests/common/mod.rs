@@ -1,10 +1,17 @@
+#![allow(dead_code)]
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 use std::sync::Arc;
 
 use axum::Router;
-use hub_api::auth::keys::SigningKeys;
+use axum_test::TestServer;
+use hub_api::auth::keys::Keyring;
 use hub_api::config::Config;
 use hub_api::db::kv::{KeyValueStore, MemoryStore};
 use hub_api::db::pool::DbPool;
+use hub_api::mail::{EmailMessage, FileMailer};
+use hub_api::media::LocalMediaStore;
+use hub_api::relay::NotificationRelay;
 use hub_api::AppState;
 
 /// Build an [`AppState`] connected to the real dev database and an in-memory KV store.
@@ -18,18 +25,59 @@ pub async fn test_state() -> AppState {
 
     let mut config = Config::from_env();
     config.database_url = with_test_db_suffix(&config.database_url);
+    // Trust loopback (see `proxied_server`) and one internal hop.
+    config.trusted_proxies = vec![
+        IpAddr::V4(Ipv4Addr::LOCALHOST),
+        IpAddr::V6(Ipv6Addr::LOCALHOST),
+        IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+    ];
     let db = hub_api::db::pool::connect(&config.database_url).await;
+    hub_api::auth::clients::ensure_first_party_client(&db, &config)
+        .await
+        .expect("register first-party client");
 
     let kv: Arc<dyn KeyValueStore> = Arc::new(MemoryStore::new());
 
-    let keys = Arc::new(SigningKeys::from_seed(&config.signing_key_seed));
+    let keys = Arc::new(Keyring::from_config(&config).expect("signing keys"));
 
     AppState {
         db,
         kv,
         keys,
         config: Arc::new(config),
+        mailer: Arc::new(FileMailer::new(outbox_dir())),
+        media: Arc::new(LocalMediaStore::new(media_dir())),
+        relay: NotificationRelay::new(),
+    }
+}
+
+/// Directory the test [`FileMailer`] writes to. Shared by all tests; read it
+/// through [`sent_emails`], which filters by recipient.
+pub fn outbox_dir() -> std::path::PathBuf {
+    std::env::temp_dir().join("voxora-hub-test-outbox")
+}
+
+/// Directory the test [`LocalMediaStore`] keeps uploads in.
+pub fn media_dir() -> std::path::PathBuf {
+    std::env::temp_dir().join("voxora-hub-test-media")
+}
+
+/// Emails sent to `to` by any test state, oldest first.
+pub fn sent_emails(to: &str) -> Vec<EmailMessage> {
+    FileMailer::new(outbox_dir()).sent_to(to)
+}
+
+/// Emails sent to `to`, waiting up to a few seconds for at least `count` of
+/// them, for emails sent after the response.
+pub async fn wait_for_emails(to: &str, count: usize) -> Vec<EmailMessage> {
+    for _ in 0..50 {
+        let sent = sent_emails(to);
+        if sent.len() >= count {
+            return sent;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
     }
+    sent_emails(to)
 }
 
 fn with_test_db_suffix(database_url: &str) -> String {
@@ -45,7 +93,7 @@ fn with_test_db_suffix(database_url: &str) -> String {
         return database_url.to_string();
     }
 
-    let mut updated = format!("{}/{}", prefix, format!("{db_name}_test"));
+    let mut updated = format!("{prefix}/{db_name}_test");
     if let Some(query) = query {
         updated.push('?');
         updated.push_str(query);
@@ -60,6 +108,13 @@ pub async fn test_app() -> (Router, AppState) {
     (app, state)
 }
 
+/// Serve `app` over a real loopback connection, so handlers see a peer address.
+/// Loopback is a trusted proxy in [`test_state`], so `X-Forwarded-For` set by a
+/// test is honored.
+pub fn proxied_server(app: Router) -> TestServer {
+    TestServer::new(app.into_make_service_with_connect_info::<SocketAddr>()).unwrap()
+}
+
 /// Create a unique test user and return its ID.
 ///
 /// Uses a random suffix so tests don't clash.
@@ -115,6 +170,20 @@ pub struct TestUser {
     pub password: String,
 }
 
+/// Give a user the staff flag, for the admin API.
+pub async fn make_staff(db: &DbPool, user_id: &str) {
+    use diesel::prelude::*;
+    use diesel_async::RunQueryDsl;
+    use hub_api::db::schema::users;
+
+    let mut conn = db.get().await.expect("pool");
+    diesel::update(users::table.find(user_id))
+        .set(users::flags.eq(hub_api::models::user::USER_FLAG_STAFF))
+        .execute(&mut conn)
+        .await
+        .expect("make staff");
+}
+
 /// Create a test pod owned by the given user. Returns the pod ID.
 pub async fn create_test_pod(db: &DbPool, owner_id: &str) -> String {
     use diesel::prelude::*;
@@ -151,10 +220,12 @@ pub async fn store_test_access_token(
     scopes: &[&str],
 ) -> String {
     let token = hub_api::auth::tokens::generate_access_token();
-    let data = hub_api::auth::tokens::AccessTokenData {
-        user_id: user_id.to_string(),
-        scopes: scopes.iter().map(|s| s.to_string()).collect(),
-    };
+    let data = hub_api::auth::tokens::AccessTokenData::new(
+        user_id.to_string(),
+        scopes.iter().map(|s| s.to_string()).collect(),
+        None,
+        None,
+    );
     hub_api::auth::tokens::store_access_token(kv, &token, &data)
         .await
         .expect("store test token");
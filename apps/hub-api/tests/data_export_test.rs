@@ -0,0 +1,212 @@
+//! Integration tests for account data exports.
+
+mod common;
+
+use std::io::{Cursor, Read};
+
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use hub_api::db::schema::{data_exports, sessions, users};
+use hub_api::models::session::NewSession;
+
+fn read_entry(archive: &mut zip::ZipArchive<Cursor<Vec<u8>>>, name: &str) -> String {
+    let mut contents = String::new();
+    archive
+        .by_name(name)
+        .unwrap()
+        .read_to_string(&mut contents)
+        .unwrap();
+    contents
+}
+
+#[tokio::test]
+async fn export_is_built_downloaded_and_expires() {
+    let (app, state) = common::test_app().await;
+    let server = TestServer::new(app).unwrap();
+    let user = common::create_test_user(&state.db, "export_password_1").await;
+    let access = common::store_test_access_token(state.kv.as_ref(), &user.id, &["openid"]).await;
+    let pod_id = common::create_test_pod(&state.db, &user.id).await;
+    common::create_test_bookmark(&state.db, &user.id, &pod_id).await;
+
+    let mut conn = state.db.get().await.unwrap();
+    diesel::update(users::table.find(&user.id))
+        .set(users::email_verified.eq(true))
+        .execute(&mut conn)
+        .await
+        .unwrap();
+    diesel::insert_into(sessions::table)
+        .values(&NewSession {
+            id: voxora_common::id::prefixed_ulid(voxora_common::id::prefix::SESSION),
+            user_id: user.id.clone(),
+            refresh_token: hub_api::auth::tokens::generate_refresh_token(),
+            ip_address: Some("203.0.113.7/32".parse().unwrap()),
+            user_agent: Some("Export, \"Test\" Browser".to_string()),
+            client_id: None,
+            expires_at: Utc::now() + Duration::days(30),
+            family_id: "fam_export_test".to_string(),
+            scopes: vec!["openid".to_string()],
+        })
+        .execute(&mut conn)
+        .await
+        .unwrap();
+
+    let resp = server
+        .post("/api/v1/users/@me/exports")
+        .authorization_bearer(&access)
+        .await;
+    resp.assert_status(StatusCode::ACCEPTED);
+    let export: serde_json::Value = resp.json();
+    let export_id = export["id"].as_str().unwrap().to_string();
+    assert_eq!(export["status"], "pending");
+    assert!(export["download_url"].is_null());
+
+    // One export per day.
+    server
+        .post("/api/v1/users/@me/exports")
+        .authorization_bearer(&access)
+        .await
+        .assert_status(StatusCode::TOO_MANY_REQUESTS);
+
+    hub_api::exports::process_pending(&state).await.unwrap();
+
+    let export: serde_json::Value = server
+        .get(&format!("/api/v1/users/@me/exports/{export_id}"))
+        .authorization_bearer(&access)
+        .await
+        .json();
+    assert_eq!(export["status"], "ready");
+    assert!(export["expires_at"].is_string());
+    let url = export["download_url"].as_str().unwrap();
+    let path = url.strip_prefix(&state.config.hub_domain).unwrap();
+
+    let emails = common::sent_emails(&user.email);
+    assert!(emails
+        .iter()
+        .any(|m| m.subject == "Your Voxora data export is ready"));
+
+    // The link works without a bearer token.
+    let resp = server.get(path).await;
+    resp.assert_status_ok();
+    assert_eq!(resp.header("content-type"), "application/zip");
+    let mut archive = zip::ZipArchive::new(Cursor::new(resp.as_bytes().to_vec())).unwrap();
+
+    let profile: serde_json::Value =
+        serde_json::from_str(&read_entry(&mut archive, "profile.json")).unwrap();
+    assert_eq!(profile["id"], user.id);
+    assert_eq!(profile["email"], user.email);
+    let sessions_json: serde_json::Value =
+        serde_json::from_str(&read_entry(&mut archive, "sessions.json")).unwrap();
+    assert_eq!(sessions_json[0]["ip_address"], "203.0.113.7");
+    let sessions_csv = read_entry(&mut archive, "sessions.csv");
+    assert!(sessions_csv.contains("203.0.113.7,\"Export, \"\"Test\"\" Browser\""));
+    let bookmarks: serde_json::Value =
+        serde_json::from_str(&read_entry(&mut archive, "pod_bookmarks.json")).unwrap();
+    assert_eq!(bookmarks[0]["pod_id"], pod_id);
+    let pods: serde_json::Value =
+        serde_json::from_str(&read_entry(&mut archive, "pods.json")).unwrap();
+    assert_eq!(pods[0]["id"], pod_id);
+    assert!(pods[0].get("client_secret").is_none());
+    let connections: serde_json::Value =
+        serde_json::from_str(&read_entry(&mut archive, "connections.json")).unwrap();
+    assert!(connections["identities"].is_array());
+    assert!(archive.by_name("preferences.json").is_ok());
+
+    server
+        .get("/api/v1/exports/download/dxl_not_a_real_token")
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+
+    // Once the archive expires it is deleted and its links stop working.
+    diesel::update(data_exports::table.find(&export_id))
+        .set(data_exports::expires_at.eq(Utc::now() - Duration::minutes(1)))
+        .execute(&mut conn)
+        .await
+        .unwrap();
+    hub_api::exports::purge_expired(&state.db).await.unwrap();
+    server.get(path).await.assert_status(StatusCode::NOT_FOUND);
+    let list: serde_json::Value = server
+        .get("/api/v1/users/@me/exports")
+        .authorization_bearer(&access)
+        .await
+        .json();
+    assert_eq!(list["data"][0]["status"], "expired");
+    assert!(list["data"][0]["download_url"].is_null());
+
+    common::cleanup_test_pod(&state.db, &pod_id).await;
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
+
+#[tokio::test]
+async fn exports_are_private() {
+    let (app, state) = common::test_app().await;
+    let server = TestServer::new(app).unwrap();
+    let owner = common::create_test_user(&state.db, "export_password_1").await;
+    let other = common::create_test_user(&state.db, "export_password_1").await;
+    let owner_access =
+        common::store_test_access_token(state.kv.as_ref(), &owner.id, &["openid"]).await;
+    let other_access =
+        common::store_test_access_token(state.kv.as_ref(), &other.id, &["openid"]).await;
+
+    server
+        .post("/api/v1/users/@me/exports")
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+
+    let export: serde_json::Value = server
+        .post("/api/v1/users/@me/exports")
+        .authorization_bearer(&owner_access)
+        .await
+        .json();
+    server
+        .get(&format!(
+            "/api/v1/users/@me/exports/{}",
+            export["id"].as_str().unwrap()
+        ))
+        .authorization_bearer(&other_access)
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+    let list: serde_json::Value = server
+        .get("/api/v1/users/@me/exports")
+        .authorization_bearer(&other_access)
+        .await
+        .json();
+    assert_eq!(list["data"].as_array().unwrap().len(), 0);
+
+    common::cleanup_test_user(&state.db, &owner.id).await;
+    common::cleanup_test_user(&state.db, &other.id).await;
+}
+
+#[tokio::test]
+async fn concurrent_requests_queue_one_export() {
+    let (app, state) = common::test_app().await;
+    let server = TestServer::new(app).unwrap();
+    let user = common::create_test_user(&state.db, "export_password_1").await;
+    let access = common::store_test_access_token(state.kv.as_ref(), &user.id, &["openid"]).await;
+
+    let request = || {
+        server
+            .post("/api/v1/users/@me/exports")
+            .authorization_bearer(&access)
+    };
+    let (a, b) = tokio::join!(request(), request());
+    let mut statuses = [a.status_code(), b.status_code()];
+    statuses.sort();
+    assert_eq!(
+        statuses,
+        [StatusCode::ACCEPTED, StatusCode::TOO_MANY_REQUESTS]
+    );
+
+    let mut conn = state.db.get().await.unwrap();
+    let queued: i64 = data_exports::table
+        .filter(data_exports::user_id.eq(&user.id))
+        .count()
+        .get_result(&mut conn)
+        .await
+        .unwrap();
+    assert_eq!(queued, 1);
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
@@ -0,0 +1,283 @@
+//! Integration tests for the device authorization grant (RFC 8628).
+
+mod common;
+
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use hub_api::auth::mfa;
+
+const DEVICE_GRANT: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+/// Start a device sign-in for the first-party client.
+async fn start(server: &TestServer) -> serde_json::Value {
+    let resp = server
+        .post("/oidc/device_authorization")
+        .form(&[("client_id", "voxora-web"), ("scope", "openid profile")])
+        .await;
+    resp.assert_status_ok();
+    resp.json()
+}
+
+async fn poll(server: &TestServer, device_code: &str) -> axum_test::TestResponse {
+    server
+        .post("/oidc/token")
+        .form(&[
+            ("grant_type", DEVICE_GRANT),
+            ("client_id", "voxora-web"),
+            ("device_code", device_code),
+        ])
+        .await
+}
+
+/// The OAuth error code of a refused poll (RFC 6749 §5.2).
+fn oauth_error(resp: &axum_test::TestResponse) -> String {
+    resp.json::<serde_json::Value>()["error"]
+        .as_str()
+        .expect("error is a string")
+        .to_string()
+}
+
+async fn submit(
+    server: &TestServer,
+    user_code: &str,
+    user: &common::TestUser,
+    action: &str,
+) -> axum_test::TestResponse {
+    server
+        .post("/oidc/device")
+        .form(&[
+            ("user_code", user_code),
+            ("login", user.username.as_str()),
+            ("password", user.password.as_str()),
+            ("action", action),
+        ])
+        .await
+}
+
+#[tokio::test]
+async fn device_is_approved_and_redeemed_once() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "device_password_1").await;
+    let server = TestServer::new(app).unwrap();
+
+    let started = start(&server).await;
+    let device_code = started["device_code"].as_str().unwrap();
+    let user_code = started["user_code"].as_str().unwrap();
+    assert!(device_code.starts_with("hdc_"));
+    assert_eq!(user_code.len(), 9);
+    assert_eq!(&user_code[4..5], "-");
+    assert_eq!(started["interval"], 5);
+    assert!(started["verification_uri"]
+        .as_str()
+        .unwrap()
+        .ends_with("/oidc/device"));
+    assert!(started["verification_uri_complete"]
+        .as_str()
+        .unwrap()
+        .ends_with(&format!("?user_code={user_code}")));
+
+    // Nothing yet; polling too fast earns a slow_down.
+    let resp = poll(&server, device_code).await;
+    resp.assert_status(StatusCode::BAD_REQUEST);
+    assert_eq!(oauth_error(&resp), "authorization_pending");
+    let resp = poll(&server, device_code).await;
+    resp.assert_status(StatusCode::BAD_REQUEST);
+    assert_eq!(oauth_error(&resp), "slow_down");
+
+    // The entry page names the client.
+    let resp = server
+        .get(&format!("/oidc/device?user_code={user_code}"))
+        .await;
+    resp.assert_status_ok();
+    assert!(resp.text().contains("Voxora"));
+
+    // Case and separators don't matter.
+    let typed = user_code.replace('-', "").to_lowercase();
+    let resp = submit(&server, &typed, &user, "approve").await;
+    resp.assert_status_ok();
+    assert!(resp.text().contains("Device connected"));
+
+    // The code stops working once used.
+    submit(&server, user_code, &user, "approve")
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    let resp = poll(&server, device_code).await;
+    resp.assert_status_ok();
+    let tokens: serde_json::Value = resp.json();
+    assert_eq!(tokens["scope"], "openid profile");
+    assert!(tokens["refresh_token"].as_str().is_some());
+    let access = tokens["access_token"].as_str().unwrap();
+    let me: serde_json::Value = server
+        .get("/api/v1/users/@me")
+        .authorization_bearer(access)
+        .await
+        .json();
+    assert_eq!(me["id"], user.id);
+
+    let resp = poll(&server, device_code).await;
+    resp.assert_status(StatusCode::BAD_REQUEST);
+    assert_eq!(oauth_error(&resp), "expired_token");
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
+
+#[tokio::test]
+async fn denied_device_gets_access_denied() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "device_password_1").await;
+    let server = TestServer::new(app).unwrap();
+
+    let started = start(&server).await;
+    let device_code = started["device_code"].as_str().unwrap();
+    let user_code = started["user_code"].as_str().unwrap();
+
+    // A wrong password doesn't approve anything.
+    let resp = server
+        .post("/oidc/device")
+        .form(&[
+            ("user_code", user_code),
+            ("login", user.username.as_str()),
+            ("password", "wrong_password"),
+        ])
+        .await;
+    resp.assert_status(StatusCode::UNAUTHORIZED);
+    assert!(resp.text().contains("Invalid username or password"));
+
+    let resp = submit(&server, user_code, &user, "deny").await;
+    resp.assert_status_ok();
+    assert!(resp.text().contains("Device denied"));
+
+    let resp = poll(&server, device_code).await;
+    resp.assert_status(StatusCode::BAD_REQUEST);
+    assert_eq!(oauth_error(&resp), "access_denied");
+
+    // Unknown codes are rejected on the page.
+    let resp = server.get("/oidc/device?user_code=BBBB-BBBB").await;
+    assert!(resp.text().contains("invalid or has expired"));
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
+
+#[tokio::test]
+async fn mfa_users_approve_through_the_challenge() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "device_password_1").await;
+    let access = common::store_test_access_token(state.kv.as_ref(), &user.id, &["openid"]).await;
+    let server = TestServer::new(app).unwrap();
+
+    let resp = server
+        .post("/api/v1/users/@me/mfa/totp")
+        .authorization_bearer(&access)
+        .await;
+    let secret = resp.json::<serde_json::Value>()["secret"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let now = chrono::Utc::now().timestamp() as u64;
+    server
+        .post("/api/v1/users/@me/mfa/totp/confirm")
+        .authorization_bearer(&access)
+        .json(&serde_json::json!({
+            "code": mfa::totp_code(&secret, mfa::totp_step(now)).unwrap(),
+        }))
+        .await
+        .assert_status_ok();
+
+    let started = start(&server).await;
+    let device_code = started["device_code"].as_str().unwrap();
+    let user_code = started["user_code"].as_str().unwrap();
+
+    let resp = submit(&server, user_code, &user, "approve").await;
+    resp.assert_status_ok();
+    let html = resp.text();
+    let marker = r#"name="mfa_token" value=""#;
+    let start_at = html.find(marker).expect("challenge page") + marker.len();
+    let mfa_token = &html[start_at..start_at + html[start_at..].find('"').unwrap()];
+
+    // Still pending until the second factor is in.
+    let resp = poll(&server, device_code).await;
+    assert_eq!(oauth_error(&resp), "authorization_pending");
+
+    let now = chrono::Utc::now().timestamp() as u64;
+    let code = mfa::totp_code(&secret, mfa::totp_step(now)).unwrap();
+    let resp = server
+        .post("/oidc/authorize/mfa")
+        .form(&[("mfa_token", mfa_token), ("code", code.as_str())])
+        .await;
+    resp.assert_status_ok();
+    assert!(resp.text().contains("Device connected"));
+
+    // Approval isn't held back by the poll interval.
+    let resp = poll(&server, device_code).await;
+    resp.assert_status_ok();
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
+
+#[tokio::test]
+async fn clients_without_the_grant_are_refused() {
+    let (app, state) = common::test_app().await;
+    let owner = common::create_test_user(&state.db, "device_password_1").await;
+    let access = common::store_test_access_token(state.kv.as_ref(), &owner.id, &["openid"]).await;
+    let server = TestServer::new(app).unwrap();
+
+    let resp = server
+        .post("/api/v1/oauth-clients")
+        .authorization_bearer(&access)
+        .json(&serde_json::json!({
+            "name": "Web only",
+            "client_type": "public",
+            "redirect_uris": ["https://app.example.com/cb"],
+        }))
+        .await;
+    resp.assert_status(StatusCode::CREATED);
+    let client: serde_json::Value = resp.json();
+
+    let resp = server
+        .post("/oidc/device_authorization")
+        .form(&[("client_id", client["client_id"].as_str().unwrap())])
+        .await;
+    resp.assert_status(StatusCode::BAD_REQUEST);
+    assert_eq!(oauth_error(&resp), "unauthorized_client");
+
+    // Nor may another client redeem a device code it didn't start.
+    let started = start(&server).await;
+    let resp = server
+        .post("/oidc/token")
+        .form(&[
+            ("grant_type", DEVICE_GRANT),
+            ("client_id", client["client_id"].as_str().unwrap()),
+            ("device_code", started["device_code"].as_str().unwrap()),
+        ])
+        .await;
+    resp.assert_status(StatusCode::BAD_REQUEST);
+    assert_eq!(oauth_error(&resp), "unauthorized_client");
+
+    common::cleanup_test_user(&state.db, &owner.id).await;
+}
+
+#[tokio::test]
+async fn concurrent_polls_redeem_an_approved_code_once() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "device_password_1").await;
+    let server = TestServer::new(app).unwrap();
+
+    let started = start(&server).await;
+    let device_code = started["device_code"].as_str().unwrap();
+    let user_code = started["user_code"].as_str().unwrap();
+
+    // A poll racing the approval can't put the code back to pending.
+    let (_, approved) = tokio::join!(
+        poll(&server, device_code),
+        submit(&server, user_code, &user, "approve"),
+    );
+    approved.assert_status_ok();
+
+    let (a, b) = tokio::join!(poll(&server, device_code), poll(&server, device_code));
+    let mut statuses = [a.status_code(), b.status_code()];
+    statuses.sort();
+    assert_eq!(statuses, [StatusCode::OK, StatusCode::BAD_REQUEST]);
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
@@ -0,0 +1,177 @@
+//! Integration tests for email verification: the email sent at registration,
+//! confirming with the emailed token, and resending.
+
+mod common;
+
+use axum::http::StatusCode;
+use axum_test::TestServer;
+
+/// Pull the `token` query parameter out of the link in a verification email.
+fn token_from(text: &str) -> String {
+    let start = text.find("token=").expect("link in email") + "token=".len();
+    text[start..].split_whitespace().next().unwrap().to_string()
+}
+
+async fn register(server: &TestServer) -> (String, String) {
+    let suffix: u32 = rand::random();
+    let email = format!("verify_{suffix}@example.com");
+    let resp = server
+        .post("/api/v1/users")
+        .json(&serde_json::json!({
+            "username": format!("verify_{suffix}"),
+            "display_name": "Verify Me",
+            "email": email,
+            "password": "verify_password_1",
+        }))
+        .await;
+    resp.assert_status(StatusCode::CREATED);
+    let body: serde_json::Value = resp.json();
+    assert_eq!(body["email_verified"], false);
+    (body["id"].as_str().unwrap().to_string(), email)
+}
+
+async fn email_verified(server: &TestServer, token: &str) -> bool {
+    let me: serde_json::Value = server
+        .get("/api/v1/users/@me")
+        .authorization_bearer(token)
+        .await
+        .json();
+    me["email_verified"].as_bool().unwrap()
+}
+
+#[tokio::test]
+async fn registration_sends_a_verification_email() {
+    let (app, state) = common::test_app().await;
+    let server = TestServer::new(app).unwrap();
+
+    let (user_id, email) = register(&server).await;
+
+    let sent = common::sent_emails(&email);
+    assert_eq!(sent.len(), 1);
+    assert!(sent[0].subject.contains("Verify"));
+    assert!(sent[0]
+        .text
+        .contains(&format!("{}/verify-email?token=hev_", state.config.web_url)));
+
+    common::cleanup_test_user(&state.db, &user_id).await;
+}
+
+#[tokio::test]
+async fn confirming_the_token_verifies_the_email_once() {
+    let (app, state) = common::test_app().await;
+    let server = TestServer::new(app).unwrap();
+
+    let (user_id, email) = register(&server).await;
+    let access = common::store_test_access_token(state.kv.as_ref(), &user_id, &["openid"]).await;
+    assert!(!email_verified(&server, &access).await);
+
+    let token = token_from(&common::sent_emails(&email)[0].text);
+    server
+        .post("/api/v1/email/verify")
+        .json(&serde_json::json!({ "token": token }))
+        .await
+        .assert_status(StatusCode::NO_CONTENT);
+    assert!(email_verified(&server, &access).await);
+
+    // Tokens are single-use.
+    server
+        .post("/api/v1/email/verify")
+        .json(&serde_json::json!({ "token": token }))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    // Nothing left to verify.
+    server
+        .post("/api/v1/users/@me/email/verification")
+        .authorization_bearer(&access)
+        .await
+        .assert_status(StatusCode::CONFLICT);
+
+    common::cleanup_test_user(&state.db, &user_id).await;
+}
+
+#[tokio::test]
+async fn unknown_token_is_rejected() {
+    let (app, _state) = common::test_app().await;
+    let server = TestServer::new(app).unwrap();
+
+    let resp = server
+        .post("/api/v1/email/verify")
+        .json(&serde_json::json!({ "token": "hev_not_a_real_token" }))
+        .await;
+    resp.assert_status(StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn resending_is_throttled() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "verify_password_1").await;
+    let access = common::store_test_access_token(state.kv.as_ref(), &user.id, &["openid"]).await;
+    let server = TestServer::new(app).unwrap();
+
+    server
+        .post("/api/v1/users/@me/email/verification")
+        .authorization_bearer(&access)
+        .await
+        .assert_status(StatusCode::ACCEPTED);
+    assert_eq!(common::sent_emails(&user.email).len(), 1);
+
+    let resp = server
+        .post("/api/v1/users/@me/email/verification")
+        .authorization_bearer(&access)
+        .await;
+    resp.assert_status(StatusCode::TOO_MANY_REQUESTS);
+    let body: serde_json::Value = resp.json();
+    assert_eq!(body["error"]["code"], "RATE_LIMITED");
+    assert_eq!(common::sent_emails(&user.email).len(), 1);
+
+    // The resent link works too.
+    let token = token_from(&common::sent_emails(&user.email)[0].text);
+    server
+        .post("/api/v1/email/verify")
+        .json(&serde_json::json!({ "token": token }))
+        .await
+        .assert_status(StatusCode::NO_CONTENT);
+    assert!(email_verified(&server, &access).await);
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
+
+#[tokio::test]
+async fn concurrent_requests_send_and_verify_once() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "verify_password_1").await;
+    let access = common::store_test_access_token(state.kv.as_ref(), &user.id, &["openid"]).await;
+    let server = TestServer::new(app).unwrap();
+
+    let (a, b) = tokio::join!(
+        server
+            .post("/api/v1/users/@me/email/verification")
+            .authorization_bearer(&access),
+        server
+            .post("/api/v1/users/@me/email/verification")
+            .authorization_bearer(&access),
+    );
+    let mut statuses = [a.status_code(), b.status_code()];
+    statuses.sort();
+    assert_eq!(
+        statuses,
+        [StatusCode::ACCEPTED, StatusCode::TOO_MANY_REQUESTS]
+    );
+    assert_eq!(common::sent_emails(&user.email).len(), 1);
+
+    let token = token_from(&common::sent_emails(&user.email)[0].text);
+    let (a, b) = tokio::join!(
+        server
+            .post("/api/v1/email/verify")
+            .json(&serde_json::json!({ "token": token })),
+        server
+            .post("/api/v1/email/verify")
+            .json(&serde_json::json!({ "token": token })),
+    );
+    let mut statuses = [a.status_code(), b.status_code()];
+    statuses.sort();
+    assert_eq!(statuses, [StatusCode::NO_CONTENT, StatusCode::BAD_REQUEST]);
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
@@ -0,0 +1,198 @@
+//! Integration tests for Hub-level bans and suspensions.
+
+mod common;
+
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use hub_api::db::schema::{pod_notifications, sessions, user_bans, users};
+use hub_api::models::session::NewSession;
+use hub_api::pod_events::EVENT_USER_BANNED;
+
+async fn request_sia(server: &TestServer, access: &str, pod_id: &str) -> axum_test::TestResponse {
+    server
+        .post("/api/v1/oidc/sia")
+        .authorization_bearer(access)
+        .json(&serde_json::json!({ "pod_id": pod_id }))
+        .await
+}
+
+#[tokio::test]
+async fn ban_blocks_sign_in_and_notifies_pods() {
+    let (app, state) = common::test_app().await;
+    let server = TestServer::new(app).unwrap();
+    let admin = common::create_test_user(&state.db, "bans_password_1").await;
+    common::make_staff(&state.db, &admin.id).await;
+    let admin_access =
+        common::store_test_access_token(state.kv.as_ref(), &admin.id, &["openid"]).await;
+    let user = common::create_test_user(&state.db, "bans_password_1").await;
+    let access =
+        common::store_test_access_token(state.kv.as_ref(), &user.id, &["openid", "pods"]).await;
+    let pod_id = common::create_test_pod(&state.db, &admin.id).await;
+    request_sia(&server, &access, &pod_id)
+        .await
+        .assert_status_ok();
+
+    let refresh_token = hub_api::auth::tokens::generate_refresh_token();
+    let mut conn = state.db.get().await.unwrap();
+    diesel::insert_into(sessions::table)
+        .values(&NewSession {
+            id: voxora_common::id::prefixed_ulid(voxora_common::id::prefix::SESSION),
+            user_id: user.id.clone(),
+            refresh_token: refresh_token.clone(),
+            ip_address: None,
+            user_agent: None,
+            client_id: None,
+            expires_at: Utc::now() + Duration::days(30),
+            family_id: "fam_ban_test".to_string(),
+            scopes: vec!["openid".to_string()],
+        })
+        .execute(&mut conn)
+        .await
+        .unwrap();
+
+    let ban_path = format!("/api/v1/admin/users/{}/ban", user.id);
+    let ban = serde_json::json!({ "status": "banned", "reason": "Spam" });
+
+    // Only staff can ban, and staff can't be banned.
+    server
+        .put(&ban_path)
+        .authorization_bearer(&access)
+        .json(&ban)
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+    server
+        .put(&format!("/api/v1/admin/users/{}/ban", admin.id))
+        .authorization_bearer(&admin_access)
+        .json(&ban)
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+    server
+        .put(&ban_path)
+        .authorization_bearer(&admin_access)
+        .json(&serde_json::json!({ "status": "suspended", "reason": "Spam" }))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    let resp = server
+        .put(&ban_path)
+        .authorization_bearer(&admin_access)
+        .json(&ban)
+        .await;
+    resp.assert_status_ok();
+    let body: serde_json::Value = resp.json();
+    assert_eq!(body["status"], "banned");
+    assert_eq!(body["banned_by"], admin.id);
+
+    // No SIAs or tokens while banned.
+    let resp = request_sia(&server, &access, &pod_id).await;
+    resp.assert_status(StatusCode::FORBIDDEN);
+    assert!(resp.json::<serde_json::Value>()["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("Spam"));
+    server
+        .post("/oidc/token")
+        .form(&[
+            ("grant_type", "refresh_token"),
+            ("refresh_token", refresh_token.as_str()),
+        ])
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    // Hub sessions are revoked and the Pod is told.
+    let revoked: bool = sessions::table
+        .filter(sessions::refresh_token.eq(&refresh_token))
+        .select(sessions::revoked)
+        .first(&mut conn)
+        .await
+        .unwrap();
+    assert!(revoked);
+    let events: Vec<String> = pod_notifications::table
+        .filter(pod_notifications::user_id.eq(&user.id))
+        .filter(pod_notifications::pod_id.eq(&pod_id))
+        .select(pod_notifications::event)
+        .load(&mut conn)
+        .await
+        .unwrap();
+    assert_eq!(events, vec![EVENT_USER_BANNED.to_string()]);
+
+    server
+        .get(&ban_path)
+        .authorization_bearer(&admin_access)
+        .await
+        .assert_status_ok();
+
+    // Lifting the ban restores access.
+    server
+        .delete(&ban_path)
+        .authorization_bearer(&admin_access)
+        .await
+        .assert_status(StatusCode::NO_CONTENT);
+    request_sia(&server, &access, &pod_id)
+        .await
+        .assert_status_ok();
+    server
+        .delete(&ban_path)
+        .authorization_bearer(&admin_access)
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+
+    common::cleanup_test_pod(&state.db, &pod_id).await;
+    common::cleanup_test_user(&state.db, &user.id).await;
+    common::cleanup_test_user(&state.db, &admin.id).await;
+}
+
+#[tokio::test]
+async fn suspension_lifts_when_it_expires() {
+    let (app, state) = common::test_app().await;
+    let server = TestServer::new(app).unwrap();
+    let admin = common::create_test_user(&state.db, "bans_password_1").await;
+    common::make_staff(&state.db, &admin.id).await;
+    let admin_access =
+        common::store_test_access_token(state.kv.as_ref(), &admin.id, &["openid"]).await;
+    let user = common::create_test_user(&state.db, "bans_password_1").await;
+    let access =
+        common::store_test_access_token(state.kv.as_ref(), &user.id, &["openid", "pods"]).await;
+    let pod_id = common::create_test_pod(&state.db, &admin.id).await;
+
+    server
+        .put(&format!("/api/v1/admin/users/{}/ban", user.id))
+        .authorization_bearer(&admin_access)
+        .json(&serde_json::json!({
+            "status": "suspended",
+            "reason": "Cooling off",
+            "expires_at": Utc::now() + Duration::days(1),
+        }))
+        .await
+        .assert_status_ok();
+    let resp = request_sia(&server, &access, &pod_id).await;
+    resp.assert_status(StatusCode::FORBIDDEN);
+    assert!(resp.json::<serde_json::Value>()["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("suspended until"));
+
+    let mut conn = state.db.get().await.unwrap();
+    diesel::update(user_bans::table.find(&user.id))
+        .set(user_bans::expires_at.eq(Utc::now() - Duration::minutes(1)))
+        .execute(&mut conn)
+        .await
+        .unwrap();
+    request_sia(&server, &access, &pod_id)
+        .await
+        .assert_status_ok();
+    let status: String = users::table
+        .find(&user.id)
+        .select(users::status)
+        .first(&mut conn)
+        .await
+        .unwrap();
+    assert_eq!(status, "active");
+
+    common::cleanup_test_pod(&state.db, &pod_id).await;
+    common::cleanup_test_user(&state.db, &user.id).await;
+    common::cleanup_test_user(&state.db, &admin.id).await;
+}
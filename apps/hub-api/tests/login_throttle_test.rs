@@ -0,0 +1,182 @@
+//! Integration tests for brute-force protection: progressive delays,
+//! account lockout with an emailed unlock link, and registration limits.
+
+mod common;
+
+use axum::http::StatusCode;
+use axum_test::{TestResponse, TestServer};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hub_api::auth::throttle;
+use sha2::{Digest, Sha256};
+
+async fn submit_login(server: &TestServer, login: &str, password: &str, ip: &str) -> TestResponse {
+    let verifier = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+    let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+    server
+        .post("/oidc/authorize")
+        .add_header("x-forwarded-for", ip)
+        .content_type("application/x-www-form-urlencoded")
+        .bytes(
+            format!(
+                "response_type=code&client_id=voxora-web\
+                 &redirect_uri=http://localhost:5173/callback\
+                 &scope=openid+profile&code_challenge={challenge}\
+                 &code_challenge_method=S256&login={login}&password={password}"
+            )
+            .into(),
+        )
+        .await
+}
+
+#[tokio::test]
+async fn repeated_failures_from_one_address_are_slowed_down() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "throttle_password_1").await;
+    let server = common::proxied_server(app);
+
+    for _ in 0..throttle::FREE_FAILURES_PER_ACCOUNT_IP {
+        let resp = submit_login(&server, &user.username, "wrong_password", "203.0.113.10").await;
+        resp.assert_status(StatusCode::UNAUTHORIZED);
+    }
+
+    // Even the right password has to wait.
+    let resp = submit_login(&server, &user.username, &user.password, "203.0.113.10").await;
+    resp.assert_status(StatusCode::TOO_MANY_REQUESTS);
+    assert!(resp.text().contains(throttle::LOGIN_THROTTLED));
+
+    // Another address isn't held up.
+    let resp = submit_login(&server, &user.username, &user.password, "203.0.113.11").await;
+    resp.assert_status(StatusCode::SEE_OTHER);
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
+
+#[tokio::test]
+async fn unknown_logins_are_refused_the_same_way() {
+    let (app, _state) = common::test_app().await;
+    let server = common::proxied_server(app);
+    let login = format!("nobody_{}", rand::random::<u32>());
+
+    for _ in 0..throttle::FREE_FAILURES_PER_ACCOUNT_IP {
+        let resp = submit_login(&server, &login, "wrong_password", "203.0.113.20").await;
+        resp.assert_status(StatusCode::UNAUTHORIZED);
+        assert!(resp.text().contains("Invalid username or password"));
+    }
+    let resp = submit_login(&server, &login, "wrong_password", "203.0.113.20").await;
+    resp.assert_status(StatusCode::TOO_MANY_REQUESTS);
+    assert!(resp.text().contains(throttle::LOGIN_THROTTLED));
+}
+
+#[tokio::test]
+async fn account_locks_and_unlock_link_lifts_it() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "throttle_password_2").await;
+    let server = common::proxied_server(app);
+
+    // Spread over addresses so no single one is slowed down first.
+    for i in 0..throttle::MAX_FAILURES_PER_ACCOUNT {
+        let ip = format!("198.51.100.{i}");
+        let resp = submit_login(&server, &user.username, "wrong_password", &ip).await;
+        resp.assert_status(StatusCode::UNAUTHORIZED);
+    }
+
+    let resp = submit_login(&server, &user.username, &user.password, "192.0.2.1").await;
+    resp.assert_status(StatusCode::TOO_MANY_REQUESTS);
+    assert!(throttle::is_locked(state.kv.as_ref(), &user.id)
+        .await
+        .unwrap());
+
+    let email = common::sent_emails(&user.email)
+        .pop()
+        .expect("lockout email sent");
+    assert!(email.subject.contains("locked"));
+    let token = email
+        .text
+        .split("token=")
+        .nth(1)
+        .unwrap()
+        .split_whitespace()
+        .next()
+        .unwrap()
+        .to_string();
+
+    server
+        .post("/api/v1/account-unlock")
+        .json(&serde_json::json!({ "token": token }))
+        .await
+        .assert_status(StatusCode::NO_CONTENT);
+
+    // Links work once.
+    server
+        .post("/api/v1/account-unlock")
+        .json(&serde_json::json!({ "token": token }))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    let resp = submit_login(&server, &user.username, &user.password, "192.0.2.1").await;
+    resp.assert_status(StatusCode::SEE_OTHER);
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
+
+#[tokio::test]
+async fn concurrent_failures_are_all_counted() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "throttle_password_3").await;
+    let server = common::proxied_server(app);
+
+    let ips: Vec<String> = (0..throttle::MAX_FAILURES_PER_ACCOUNT)
+        .map(|i| format!("198.51.100.{}", 100 + i))
+        .collect();
+    let attempts = ips
+        .iter()
+        .map(|ip| submit_login(&server, &user.username, "wrong_password", ip));
+    for resp in futures_util::future::join_all(attempts).await {
+        resp.assert_status(StatusCode::UNAUTHORIZED);
+    }
+
+    assert!(throttle::is_locked(state.kv.as_ref(), &user.id)
+        .await
+        .unwrap());
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
+
+#[tokio::test]
+async fn registration_is_limited_per_address() {
+    let (app, state) = common::test_app().await;
+    let server = common::proxied_server(app);
+
+    let mut created = Vec::new();
+    for _ in 0..throttle::MAX_REGISTRATIONS_PER_IP {
+        let suffix: u32 = rand::random();
+        let resp = server
+            .post("/api/v1/users")
+            .add_header("x-forwarded-for", "203.0.113.30")
+            .json(&serde_json::json!({
+                "username": format!("signup_{suffix}"),
+                "password": "signup_password_1",
+                "display_name": "Signup",
+            }))
+            .await;
+        resp.assert_status(StatusCode::CREATED);
+        let body: serde_json::Value = resp.json();
+        created.push(body["id"].as_str().unwrap().to_string());
+    }
+
+    let suffix: u32 = rand::random();
+    let resp = server
+        .post("/api/v1/users")
+        .add_header("x-forwarded-for", "203.0.113.30")
+        .json(&serde_json::json!({
+            "username": format!("signup_{suffix}"),
+            "password": "signup_password_1",
+            "display_name": "Signup",
+        }))
+        .await;
+    resp.assert_status(StatusCode::TOO_MANY_REQUESTS);
+
+    for user_id in created {
+        common::cleanup_test_user(&state.db, &user_id).await;
+    }
+}
@@ -0,0 +1,212 @@
+//! Integration tests for avatar and banner uploads: re-encoding, sizes,
+//! replacement and removal, and the public media route.
+
+mod common;
+
+use std::io::Cursor;
+
+use axum::http::StatusCode;
+use axum_test::multipart::{MultipartForm, Part};
+use axum_test::{TestResponse, TestServer};
+use hub_api::AppState;
+use image::{ImageFormat, RgbImage};
+
+/// A `width`×`height` image encoded as `format`. RGB, since JPEG can't
+/// encode an alpha channel.
+fn test_image(width: u32, height: u32, format: ImageFormat) -> Vec<u8> {
+    let image = RgbImage::from_fn(width, height, |x, y| {
+        image::Rgb([(x % 256) as u8, (y % 256) as u8, 128])
+    });
+    let mut data = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut data), format)
+        .expect("encode test image");
+    data
+}
+
+async fn upload(server: &TestServer, token: &str, path: &str, data: Vec<u8>) -> TestResponse {
+    server
+        .put(path)
+        .authorization_bearer(token)
+        .multipart(MultipartForm::new().add_part("file", Part::bytes(data).file_name("upload")))
+        .await
+}
+
+/// The path of a Hub media URL, for requesting it from the test server.
+fn media_path(state: &AppState, url: &str) -> String {
+    url.strip_prefix(state.config.hub_domain.trim_end_matches('/'))
+        .expect("media URL on this Hub")
+        .to_string()
+}
+
+fn dimensions(resp: &TestResponse) -> (u32, u32) {
+    let image = image::load_from_memory_with_format(resp.as_bytes(), ImageFormat::Png)
+        .expect("served image is a PNG");
+    (image.width(), image.height())
+}
+
+#[tokio::test]
+async fn avatar_upload_is_cropped_square_and_served_at_each_size() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "media_password_1").await;
+    let token = common::store_test_access_token(state.kv.as_ref(), &user.id, &["openid"]).await;
+    let server = TestServer::new(app).unwrap();
+
+    let resp = upload(
+        &server,
+        &token,
+        "/api/v1/users/@me/avatar",
+        test_image(300, 200, ImageFormat::Jpeg),
+    )
+    .await;
+    resp.assert_status_ok();
+    let body: serde_json::Value = resp.json();
+    let url = body["avatar_url"].as_str().unwrap().to_string();
+    assert!(url.contains(&format!("/media/avatars/{}/", user.id)));
+
+    let path = media_path(&state, &url);
+    let resp = server.get(&path).await;
+    resp.assert_status_ok();
+    assert_eq!(resp.header("content-type"), "image/png");
+    assert_eq!(dimensions(&resp), (256, 256));
+
+    for size in [64, 128, 512] {
+        let resp = server.get(&format!("{path}?size={size}")).await;
+        resp.assert_status_ok();
+        assert_eq!(dimensions(&resp), (size, size));
+    }
+
+    server
+        .get(&format!("{path}?size=100"))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    // The profile the SIA is built from points at the upload.
+    let resp = server
+        .get("/api/v1/users/@me")
+        .authorization_bearer(&token)
+        .await;
+    let body: serde_json::Value = resp.json();
+    assert_eq!(body["avatar_url"], url.as_str());
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
+
+#[tokio::test]
+async fn banner_upload_is_cropped_to_three_by_one() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "media_password_2").await;
+    let token = common::store_test_access_token(state.kv.as_ref(), &user.id, &["openid"]).await;
+    let server = TestServer::new(app).unwrap();
+
+    let resp = upload(
+        &server,
+        &token,
+        "/api/v1/users/@me/banner",
+        test_image(400, 400, ImageFormat::Png),
+    )
+    .await;
+    resp.assert_status_ok();
+    let body: serde_json::Value = resp.json();
+    assert!(body["avatar_url"].is_null());
+    let path = media_path(&state, body["banner_url"].as_str().unwrap());
+
+    let resp = server.get(&path).await;
+    resp.assert_status_ok();
+    assert_eq!(dimensions(&resp), (1500, 500));
+
+    let resp = server.get(&format!("{path}?size=600")).await;
+    assert_eq!(dimensions(&resp), (600, 200));
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
+
+#[tokio::test]
+async fn replacing_or_removing_an_avatar_deletes_the_old_upload() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "media_password_3").await;
+    let token = common::store_test_access_token(state.kv.as_ref(), &user.id, &["openid"]).await;
+    let server = TestServer::new(app).unwrap();
+
+    let resp = upload(
+        &server,
+        &token,
+        "/api/v1/users/@me/avatar",
+        test_image(64, 64, ImageFormat::Png),
+    )
+    .await;
+    let body: serde_json::Value = resp.json();
+    let first = media_path(&state, body["avatar_url"].as_str().unwrap());
+
+    let resp = upload(
+        &server,
+        &token,
+        "/api/v1/users/@me/avatar",
+        test_image(64, 64, ImageFormat::Gif),
+    )
+    .await;
+    resp.assert_status_ok();
+    let body: serde_json::Value = resp.json();
+    let second = media_path(&state, body["avatar_url"].as_str().unwrap());
+    assert_ne!(first, second);
+
+    server.get(&first).await.assert_status_not_found();
+    server.get(&second).await.assert_status_ok();
+
+    let resp = server
+        .delete("/api/v1/users/@me/avatar")
+        .authorization_bearer(&token)
+        .await;
+    resp.assert_status_ok();
+    let body: serde_json::Value = resp.json();
+    assert!(body["avatar_url"].is_null());
+    server.get(&second).await.assert_status_not_found();
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
+
+#[tokio::test]
+async fn uploads_that_are_not_images_are_rejected() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "media_password_4").await;
+    let token = common::store_test_access_token(state.kv.as_ref(), &user.id, &["openid"]).await;
+    let server = TestServer::new(app).unwrap();
+
+    let resp = upload(
+        &server,
+        &token,
+        "/api/v1/users/@me/avatar",
+        b"<svg xmlns=\"http://www.w3.org/2000/svg\"/>".to_vec(),
+    )
+    .await;
+    resp.assert_status(StatusCode::BAD_REQUEST);
+
+    // Right magic bytes, broken image.
+    let mut truncated = test_image(64, 64, ImageFormat::Png);
+    truncated.truncate(40);
+    let resp = upload(&server, &token, "/api/v1/users/@me/avatar", truncated).await;
+    resp.assert_status(StatusCode::BAD_REQUEST);
+
+    let resp = server
+        .put("/api/v1/users/@me/avatar")
+        .authorization_bearer(&token)
+        .multipart(MultipartForm::new().add_text("name", "avatar"))
+        .await;
+    resp.assert_status(StatusCode::BAD_REQUEST);
+
+    let resp = server
+        .put("/api/v1/users/@me/avatar")
+        .multipart(
+            MultipartForm::new()
+                .add_part("file", Part::bytes(test_image(64, 64, ImageFormat::Png))),
+        )
+        .await;
+    resp.assert_status(StatusCode::UNAUTHORIZED);
+
+    server
+        .get(&format!("/media/avatars/{}/..", user.id))
+        .await
+        .assert_status_not_found();
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
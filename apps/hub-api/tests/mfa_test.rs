@@ -0,0 +1,593 @@
+//! Integration tests for TOTP multi-factor authentication: enrollment, the
+//! OIDC second-step challenge, recovery codes, and disabling MFA.
+
+mod common;
+
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use hub_api::auth::{mfa, throttle};
+use hub_api::db::schema::{auth_events, users};
+use sha2::{Digest, Sha256};
+
+const CODE_VERIFIER: &str = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+const REDIRECT_URI: &str = "http://localhost:5173/callback";
+
+fn now() -> u64 {
+    chrono::Utc::now().timestamp() as u64
+}
+
+fn current_code(secret: &str) -> String {
+    mfa::totp_code(secret, mfa::totp_step(now())).unwrap()
+}
+
+/// Enroll and confirm TOTP for a user. Returns (secret, recovery codes).
+async fn enable_mfa(server: &TestServer, token: &str) -> (String, Vec<String>) {
+    let resp = server
+        .post("/api/v1/users/@me/mfa/totp")
+        .authorization_bearer(token)
+        .await;
+    resp.assert_status_ok();
+    let secret = resp.json::<serde_json::Value>()["secret"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let resp = server
+        .post("/api/v1/users/@me/mfa/totp/confirm")
+        .authorization_bearer(token)
+        .json(&serde_json::json!({ "code": current_code(&secret) }))
+        .await;
+    resp.assert_status_ok();
+    let codes = resp.json::<serde_json::Value>()["recovery_codes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c.as_str().unwrap().to_string())
+        .collect();
+    (secret, codes)
+}
+
+/// Submit the password step of the login form.
+async fn submit_login(server: &TestServer, user: &common::TestUser) -> axum_test::TestResponse {
+    let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(CODE_VERIFIER.as_bytes()));
+    server
+        .post("/oidc/authorize")
+        .content_type("application/x-www-form-urlencoded")
+        .bytes(
+            format!(
+                "response_type=code&client_id=voxora-web&redirect_uri={REDIRECT_URI}\
+             &scope=openid&state=s1&code_challenge={challenge}&code_challenge_method=S256\
+             &login={}&password={}",
+                user.username, user.password
+            )
+            .into(),
+        )
+        .await
+}
+
+/// Pull the hidden `mfa_token` out of the challenge page.
+fn mfa_token(html: &str) -> String {
+    let marker = r#"name="mfa_token" value=""#;
+    let start = html.find(marker).expect("challenge page has mfa_token") + marker.len();
+    let end = start + html[start..].find('"').unwrap();
+    html[start..end].to_string()
+}
+
+async fn submit_mfa(server: &TestServer, token: &str, code: &str) -> axum_test::TestResponse {
+    server
+        .post("/oidc/authorize/mfa")
+        .content_type("application/x-www-form-urlencoded")
+        .bytes(format!("mfa_token={token}&code={code}").into())
+        .await
+}
+
+/// Submit a code from the given forwarded address, so repeated failures
+/// aren't slowed down per address. Needs [`common::proxied_server`].
+async fn submit_mfa_from(
+    server: &TestServer,
+    token: &str,
+    code: &str,
+    ip: &str,
+) -> axum_test::TestResponse {
+    server
+        .post("/oidc/authorize/mfa")
+        .add_header("x-forwarded-for", ip)
+        .content_type("application/x-www-form-urlencoded")
+        .bytes(format!("mfa_token={token}&code={code}").into())
+        .await
+}
+
+/// Event types recorded for a user, oldest first.
+async fn event_types(state: &hub_api::AppState, user_id: &str) -> Vec<String> {
+    let mut conn = state.db.get().await.unwrap();
+    auth_events::table
+        .filter(auth_events::user_id.eq(user_id))
+        .order(auth_events::created_at.asc())
+        .select(auth_events::event)
+        .load(&mut conn)
+        .await
+        .unwrap()
+}
+
+// =========================================================================
+// TOTP primitives
+// =========================================================================
+
+#[test]
+fn totp_matches_rfc6238_test_vectors() {
+    // RFC 6238 Appendix B SHA-1 secret "12345678901234567890", truncated to 6 digits.
+    let secret = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+    assert_eq!(
+        mfa::totp_code(secret, mfa::totp_step(59)).unwrap(),
+        "287082"
+    );
+    assert_eq!(
+        mfa::totp_code(secret, mfa::totp_step(1111111109)).unwrap(),
+        "081804"
+    );
+    assert_eq!(
+        mfa::totp_code(secret, mfa::totp_step(1234567890)).unwrap(),
+        "005924"
+    );
+}
+
+#[test]
+fn verify_totp_allows_one_step_of_drift() {
+    let secret = mfa::generate_totp_secret();
+    let t = 1_700_000_000;
+    let step = mfa::totp_step(t);
+
+    let previous = mfa::totp_code(&secret, step - 1).unwrap();
+    assert_eq!(mfa::verify_totp(&secret, &previous, t), Some(step - 1));
+
+    let stale = mfa::totp_code(&secret, step - 2).unwrap();
+    assert_eq!(mfa::verify_totp(&secret, &stale, t), None);
+    assert_eq!(mfa::verify_totp(&secret, "12345", t), None);
+    assert_eq!(mfa::verify_totp(&secret, "abcdef", t), None);
+}
+
+#[test]
+fn provisioning_uri_encodes_account() {
+    let uri = mfa::provisioning_uri("ABCDEF", "alice+mfa@example.com");
+    assert!(uri.starts_with("otpauth://totp/Voxora:alice%2Bmfa%40example.com?"));
+    assert!(uri.contains("secret=ABCDEF"));
+    assert!(uri.contains("issuer=Voxora"));
+    assert!(uri.contains("digits=6"));
+    assert!(uri.contains("period=30"));
+}
+
+#[test]
+fn recovery_code_hash_ignores_formatting() {
+    let codes = mfa::generate_recovery_codes();
+    assert_eq!(codes.len(), mfa::RECOVERY_CODE_COUNT);
+    let code = &codes[0];
+    assert_eq!(code.len(), 11);
+    assert_eq!(
+        mfa::hash_recovery_code(code),
+        mfa::hash_recovery_code(&code.replace('-', " ").to_uppercase())
+    );
+    assert_ne!(
+        mfa::hash_recovery_code(&codes[0]),
+        mfa::hash_recovery_code(&codes[1])
+    );
+}
+
+// =========================================================================
+// Enrollment
+// =========================================================================
+
+#[tokio::test]
+async fn enrollment_requires_a_valid_code() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "mfa_password_1").await;
+    let token = common::store_test_access_token(state.kv.as_ref(), &user.id, &["openid"]).await;
+    let server = TestServer::new(app).unwrap();
+
+    // Nothing to confirm yet.
+    let resp = server
+        .post("/api/v1/users/@me/mfa/totp/confirm")
+        .authorization_bearer(&token)
+        .json(&serde_json::json!({ "code": "123456" }))
+        .await;
+    resp.assert_status(StatusCode::BAD_REQUEST);
+
+    let resp = server
+        .post("/api/v1/users/@me/mfa/totp")
+        .authorization_bearer(&token)
+        .await;
+    resp.assert_status_ok();
+    let body: serde_json::Value = resp.json();
+    let secret = body["secret"].as_str().unwrap();
+    let uri = body["provisioning_uri"].as_str().unwrap();
+    assert!(uri.starts_with("otpauth://totp/"));
+    assert!(uri.contains(secret));
+
+    // A wrong code leaves MFA off.
+    let wrong = if current_code(secret) == "000000" {
+        "111111"
+    } else {
+        "000000"
+    };
+    let resp = server
+        .post("/api/v1/users/@me/mfa/totp/confirm")
+        .authorization_bearer(&token)
+        .json(&serde_json::json!({ "code": wrong }))
+        .await;
+    resp.assert_status(StatusCode::BAD_REQUEST);
+
+    let status: serde_json::Value = server
+        .get("/api/v1/users/@me/mfa")
+        .authorization_bearer(&token)
+        .await
+        .json();
+    assert_eq!(status["totp_enabled"], false);
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
+
+#[tokio::test]
+async fn confirming_enables_mfa_and_issues_recovery_codes() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "mfa_password_2").await;
+    let token = common::store_test_access_token(state.kv.as_ref(), &user.id, &["openid"]).await;
+    let server = TestServer::new(app).unwrap();
+
+    let (_, codes) = enable_mfa(&server, &token).await;
+    assert_eq!(codes.len(), mfa::RECOVERY_CODE_COUNT);
+
+    let status: serde_json::Value = server
+        .get("/api/v1/users/@me/mfa")
+        .authorization_bearer(&token)
+        .await
+        .json();
+    assert_eq!(status["totp_enabled"], true);
+    assert_eq!(status["recovery_codes_remaining"], 8);
+
+    let me: serde_json::Value = server
+        .get("/api/v1/users/@me")
+        .authorization_bearer(&token)
+        .await
+        .json();
+    assert_eq!(me["mfa_enabled"], true);
+
+    // Re-enrolling would silently replace the secret.
+    server
+        .post("/api/v1/users/@me/mfa/totp")
+        .authorization_bearer(&token)
+        .await
+        .assert_status(StatusCode::CONFLICT);
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
+
+// =========================================================================
+// OIDC authorize with MFA
+// =========================================================================
+
+#[tokio::test]
+async fn authorize_without_mfa_redirects_immediately() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "mfa_password_3").await;
+    let server = TestServer::new(app).unwrap();
+
+    let resp = submit_login(&server, &user).await;
+    resp.assert_status(StatusCode::SEE_OTHER);
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
+
+#[tokio::test]
+async fn authorize_with_mfa_issues_code_after_totp() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "mfa_password_4").await;
+    let token = common::store_test_access_token(state.kv.as_ref(), &user.id, &["openid"]).await;
+    let server = TestServer::new(app).unwrap();
+    let (secret, _) = enable_mfa(&server, &token).await;
+
+    // Password alone only gets the challenge page.
+    let resp = submit_login(&server, &user).await;
+    resp.assert_status_ok();
+    let html = resp.text();
+    assert!(html.contains("/oidc/authorize/mfa"));
+    let challenge = mfa_token(&html);
+
+    let resp = submit_mfa(&server, &challenge, &current_code(&secret)).await;
+    resp.assert_status(StatusCode::SEE_OTHER);
+    let location = resp.header("location").to_str().unwrap().to_string();
+    assert!(location.starts_with(REDIRECT_URI));
+    assert!(location.contains("state=s1"));
+    let code = location
+        .split("code=")
+        .nth(1)
+        .unwrap()
+        .split('&')
+        .next()
+        .unwrap();
+
+    let resp = server
+        .post("/oidc/token")
+        .content_type("application/x-www-form-urlencoded")
+        .bytes(
+            format!(
+                "grant_type=authorization_code&code={code}&redirect_uri={REDIRECT_URI}\
+             &code_verifier={CODE_VERIFIER}&client_id=voxora-web"
+            )
+            .into(),
+        )
+        .await;
+    resp.assert_status_ok();
+    let id_token = resp.json::<serde_json::Value>()["id_token"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let mut validation = jsonwebtoken::Validation::new(jsonwebtoken::Algorithm::EdDSA);
+    validation.set_audience(&["voxora-web"]);
+    validation.set_issuer(&[&state.config.hub_domain]);
+    let claims = jsonwebtoken::decode::<hub_api::auth::tokens::IdTokenClaims>(
+        &id_token,
+        &state.keys.active().decoding,
+        &validation,
+    )
+    .expect("ID token verification must succeed")
+    .claims;
+    assert_eq!(claims.amr, vec!["pwd", "otp", "mfa"]);
+
+    // The challenge is single-use.
+    let resp = submit_mfa(&server, &challenge, &current_code(&secret)).await;
+    resp.assert_status(StatusCode::UNAUTHORIZED);
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
+
+#[tokio::test]
+async fn totp_code_cannot_be_replayed() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "mfa_password_5").await;
+    let token = common::store_test_access_token(state.kv.as_ref(), &user.id, &["openid"]).await;
+    let server = TestServer::new(app).unwrap();
+    let (secret, _) = enable_mfa(&server, &token).await;
+    let code = current_code(&secret);
+
+    let first = mfa_token(&submit_login(&server, &user).await.text());
+    submit_mfa(&server, &first, &code)
+        .await
+        .assert_status(StatusCode::SEE_OTHER);
+
+    let second = mfa_token(&submit_login(&server, &user).await.text());
+    let resp = submit_mfa(&server, &second, &code).await;
+    resp.assert_status(StatusCode::UNAUTHORIZED);
+    assert!(resp.text().contains("Invalid code"));
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
+
+#[tokio::test]
+async fn concurrent_submissions_of_one_totp_code_pass_once() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "mfa_password_9").await;
+    let token = common::store_test_access_token(state.kv.as_ref(), &user.id, &["openid"]).await;
+    let server = TestServer::new(app).unwrap();
+    let (secret, _) = enable_mfa(&server, &token).await;
+    let code = current_code(&secret);
+
+    let first = mfa_token(&submit_login(&server, &user).await.text());
+    let second = mfa_token(&submit_login(&server, &user).await.text());
+    let (a, b) = tokio::join!(
+        submit_mfa(&server, &first, &code),
+        submit_mfa(&server, &second, &code),
+    );
+    let passed = [a.status_code(), b.status_code()]
+        .iter()
+        .filter(|s| **s == StatusCode::SEE_OTHER)
+        .count();
+    assert_eq!(passed, 1);
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
+
+#[tokio::test]
+async fn recovery_codes_work_once() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "mfa_password_6").await;
+    let token = common::store_test_access_token(state.kv.as_ref(), &user.id, &["openid"]).await;
+    let server = TestServer::new(app).unwrap();
+    let (_, codes) = enable_mfa(&server, &token).await;
+
+    let challenge = mfa_token(&submit_login(&server, &user).await.text());
+    submit_mfa(&server, &challenge, &codes[0].to_uppercase())
+        .await
+        .assert_status(StatusCode::SEE_OTHER);
+
+    let challenge = mfa_token(&submit_login(&server, &user).await.text());
+    submit_mfa(&server, &challenge, &codes[0])
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+
+    let status: serde_json::Value = server
+        .get("/api/v1/users/@me/mfa")
+        .authorization_bearer(&token)
+        .await
+        .json();
+    assert_eq!(status["recovery_codes_remaining"], 7);
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
+
+#[tokio::test]
+async fn challenge_expires_after_too_many_wrong_codes() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "mfa_password_7").await;
+    let token = common::store_test_access_token(state.kv.as_ref(), &user.id, &["openid"]).await;
+    let server = common::proxied_server(app);
+    let (secret, _) = enable_mfa(&server, &token).await;
+
+    let challenge = mfa_token(&submit_login(&server, &user).await.text());
+    let key = format!("hub:mfa:{challenge}");
+    let stored = state.kv.get(&key).await.unwrap();
+    for n in 0..mfa::MAX_MFA_ATTEMPTS - 1 {
+        submit_mfa_from(&server, &challenge, "not-a-code", &format!("203.0.113.{n}"))
+            .await
+            .assert_status(StatusCode::UNAUTHORIZED);
+    }
+    // Wrong codes are counted without rewriting (and so re-arming) the challenge.
+    assert_eq!(state.kv.get(&key).await.unwrap(), stored);
+
+    submit_mfa_from(&server, &challenge, "not-a-code", "203.0.113.100")
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+
+    let resp = submit_mfa_from(&server, &challenge, &current_code(&secret), "203.0.113.101").await;
+    resp.assert_status(StatusCode::UNAUTHORIZED);
+    assert!(resp.text().contains("expired"));
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
+
+#[tokio::test]
+async fn wrong_codes_across_challenges_lock_the_account() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "mfa_password_9").await;
+    let token = common::store_test_access_token(state.kv.as_ref(), &user.id, &["openid"]).await;
+    let server = common::proxied_server(app);
+    let (secret, _) = enable_mfa(&server, &token).await;
+
+    // A fresh challenge every few guesses, never exhausting one. Knowing the
+    // password doesn't reset the count.
+    let per_challenge = (mfa::MAX_MFA_ATTEMPTS - 1) as usize;
+    let mut challenge = String::new();
+    for n in 0..throttle::MAX_FAILURES_PER_ACCOUNT {
+        if n % per_challenge == 0 {
+            let resp = submit_login(&server, &user).await;
+            resp.assert_status_ok();
+            challenge = mfa_token(&resp.text());
+        }
+        submit_mfa_from(&server, &challenge, "000000", &format!("198.51.100.{n}"))
+            .await
+            .assert_status(StatusCode::UNAUTHORIZED);
+    }
+
+    // Locked: even the right code on an open challenge is refused.
+    submit_mfa_from(
+        &server,
+        &challenge,
+        &current_code(&secret),
+        "198.51.100.200",
+    )
+    .await
+    .assert_status(StatusCode::TOO_MANY_REQUESTS);
+    submit_login(&server, &user)
+        .await
+        .assert_status(StatusCode::TOO_MANY_REQUESTS);
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
+
+#[tokio::test]
+async fn regenerating_recovery_codes_is_throttled() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "mfa_password_10").await;
+    let token = common::store_test_access_token(state.kv.as_ref(), &user.id, &["openid"]).await;
+    let server = TestServer::new(app).unwrap();
+    let (secret, _) = enable_mfa(&server, &token).await;
+
+    for _ in 0..throttle::FREE_FAILURES_PER_ACCOUNT_IP {
+        server
+            .post("/api/v1/users/@me/mfa/recovery-codes")
+            .authorization_bearer(&token)
+            .json(&serde_json::json!({ "code": "000000" }))
+            .await
+            .assert_status(StatusCode::FORBIDDEN);
+    }
+    server
+        .post("/api/v1/users/@me/mfa/recovery-codes")
+        .authorization_bearer(&token)
+        .json(&serde_json::json!({ "code": current_code(&secret) }))
+        .await
+        .assert_status(StatusCode::TOO_MANY_REQUESTS);
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
+
+// =========================================================================
+// Disabling MFA
+// =========================================================================
+
+#[tokio::test]
+async fn disabling_mfa_requires_password_and_code() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "mfa_password_8").await;
+    let token = common::store_test_access_token(state.kv.as_ref(), &user.id, &["openid"]).await;
+    let server = TestServer::new(app).unwrap();
+    let (_, codes) = enable_mfa(&server, &token).await;
+
+    server
+        .delete("/api/v1/users/@me/mfa")
+        .authorization_bearer(&token)
+        .json(&serde_json::json!({ "password": "wrong_password", "code": codes[0] }))
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+
+    server
+        .delete("/api/v1/users/@me/mfa")
+        .authorization_bearer(&token)
+        .json(&serde_json::json!({ "password": user.password, "code": "000000" }))
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+
+    server
+        .delete("/api/v1/users/@me/mfa")
+        .authorization_bearer(&token)
+        .json(&serde_json::json!({ "password": user.password, "code": codes[1] }))
+        .await
+        .assert_status(StatusCode::NO_CONTENT);
+
+    let status: serde_json::Value = server
+        .get("/api/v1/users/@me/mfa")
+        .authorization_bearer(&token)
+        .await
+        .json();
+    assert_eq!(status["totp_enabled"], false);
+    assert_eq!(status["recovery_codes_remaining"], 0);
+
+    // Back to single-step sign-in.
+    submit_login(&server, &user)
+        .await
+        .assert_status(StatusCode::SEE_OTHER);
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
+
+#[tokio::test]
+async fn accounts_without_a_password_disable_mfa_with_the_code_alone() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "mfa_password_11").await;
+    let token = common::store_test_access_token(state.kv.as_ref(), &user.id, &["openid"]).await;
+    let server = TestServer::new(app).unwrap();
+    let (_, codes) = enable_mfa(&server, &token).await;
+
+    // As if the account came from an upstream provider.
+    let mut conn = state.db.get().await.unwrap();
+    diesel::update(users::table.find(&user.id))
+        .set(users::password_hash.eq(None::<String>))
+        .execute(&mut conn)
+        .await
+        .unwrap();
+
+    server
+        .delete("/api/v1/users/@me/mfa")
+        .authorization_bearer(&token)
+        .json(&serde_json::json!({ "code": codes[0] }))
+        .await
+        .assert_status(StatusCode::NO_CONTENT);
+
+    assert_eq!(
+        event_types(&state, &user.id).await,
+        ["mfa.enabled", "mfa.disabled"]
+    );
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
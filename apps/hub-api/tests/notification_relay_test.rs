@@ -0,0 +1,231 @@
+//! Integration tests for the notification relay: Pods push envelopes and the
+//! Hub forwards them to users' notification WebSockets.
+
+mod common;
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use futures_util::{SinkExt, StreamExt};
+use hub_api::db::schema::{pods, user_pod_bookmarks};
+use hub_api::AppState;
+use tokio::net::TcpStream;
+use tokio::time;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Start a real server, since the relay is only reachable over WebSocket.
+async fn start_server() -> (SocketAddr, AppState) {
+    let state = common::test_state().await;
+    let app = hub_api::routes::router().with_state(state.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("bind");
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    (addr, state)
+}
+
+async fn pod_secret(state: &AppState, pod_id: &str) -> String {
+    let mut conn = state.db.get().await.unwrap();
+    pods::table
+        .find(pod_id)
+        .select(pods::client_secret)
+        .first(&mut conn)
+        .await
+        .unwrap()
+}
+
+async fn bookmark(state: &AppState, user_id: &str, pod_id: &str) {
+    let mut conn = state.db.get().await.unwrap();
+    diesel::insert_into(user_pod_bookmarks::table)
+        .values((
+            user_pod_bookmarks::user_id.eq(user_id),
+            user_pod_bookmarks::pod_id.eq(pod_id),
+        ))
+        .execute(&mut conn)
+        .await
+        .unwrap();
+}
+
+/// Open the notification stream and identify. Returns the socket after READY.
+async fn connect(addr: SocketAddr, token: &str) -> Socket {
+    let (mut ws, _) =
+        tokio_tungstenite::connect_async(format!("ws://{addr}/api/v1/notifications/ws"))
+            .await
+            .expect("connect");
+    ws.send(Message::Text(
+        serde_json::json!({ "type": "identify", "token": token })
+            .to_string()
+            .into(),
+    ))
+    .await
+    .unwrap();
+    let ready = next_json(&mut ws).await.expect("ready");
+    assert_eq!(ready["type"], "ready");
+    ws
+}
+
+/// The next JSON message, or `None` if nothing arrives within a second.
+async fn next_json(ws: &mut Socket) -> Option<serde_json::Value> {
+    loop {
+        let msg = time::timeout(Duration::from_secs(1), ws.next())
+            .await
+            .ok()??
+            .ok()?;
+        if let Message::Text(text) = msg {
+            return serde_json::from_str(&text).ok();
+        }
+    }
+}
+
+async fn relay(addr: SocketAddr, secret: &str, notifications: serde_json::Value) -> StatusCode {
+    let resp = reqwest::Client::new()
+        .post(format!("http://{addr}/api/v1/notifications/relay"))
+        .bearer_auth(secret)
+        .json(&serde_json::json!({ "notifications": notifications }))
+        .send()
+        .await
+        .expect("relay request");
+    StatusCode::from_u16(resp.status().as_u16()).unwrap()
+}
+
+#[tokio::test]
+async fn relayed_envelopes_reach_only_their_user() {
+    let (addr, state) = start_server().await;
+    let user = common::create_test_user(&state.db, "relay_password_1").await;
+    let other = common::create_test_user(&state.db, "relay_password_2").await;
+    let pod_id = common::create_test_pod(&state.db, &user.id).await;
+    bookmark(&state, &user.id, &pod_id).await;
+    bookmark(&state, &other.id, &pod_id).await;
+    let secret = pod_secret(&state, &pod_id).await;
+
+    let token = common::store_test_access_token(state.kv.as_ref(), &user.id, &["openid"]).await;
+    let mut ws = connect(addr, &token).await;
+
+    let status = relay(
+        addr,
+        &secret,
+        serde_json::json!([
+            { "user_id": other.id, "community_id": "com_1", "channel_id": "ch_1",
+              "unread_count": 9, "has_mention": false },
+            { "user_id": user.id, "community_id": "com_1", "channel_id": "ch_2",
+              "unread_count": 3, "has_mention": true },
+        ]),
+    )
+    .await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+
+    let msg = next_json(&mut ws).await.expect("notification");
+    assert_eq!(msg["type"], "notification");
+    assert_eq!(
+        msg["data"],
+        serde_json::json!({
+            "pod_id": pod_id,
+            "community_id": "com_1",
+            "channel_id": "ch_2",
+            "unread_count": 3,
+            "has_mention": true,
+        })
+    );
+    assert!(next_json(&mut ws).await.is_none());
+
+    common::cleanup_test_pod(&state.db, &pod_id).await;
+    common::cleanup_test_user(&state.db, &user.id).await;
+    common::cleanup_test_user(&state.db, &other.id).await;
+}
+
+#[tokio::test]
+async fn pods_cannot_notify_users_who_never_signed_in_to_them() {
+    let (addr, state) = start_server().await;
+    let user = common::create_test_user(&state.db, "relay_password_3").await;
+    let owner = common::create_test_user(&state.db, "relay_password_4").await;
+    let pod_id = common::create_test_pod(&state.db, &owner.id).await;
+    let secret = pod_secret(&state, &pod_id).await;
+
+    let token = common::store_test_access_token(state.kv.as_ref(), &user.id, &["openid"]).await;
+    let mut ws = connect(addr, &token).await;
+
+    let status = relay(
+        addr,
+        &secret,
+        serde_json::json!([
+            { "user_id": user.id, "community_id": "com_1", "channel_id": "ch_1",
+              "unread_count": 1, "has_mention": false },
+        ]),
+    )
+    .await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    assert!(next_json(&mut ws).await.is_none());
+
+    common::cleanup_test_pod(&state.db, &pod_id).await;
+    common::cleanup_test_user(&state.db, &user.id).await;
+    common::cleanup_test_user(&state.db, &owner.id).await;
+}
+
+#[tokio::test]
+async fn stream_rejects_invalid_tokens() {
+    let (addr, _state) = start_server().await;
+
+    let (mut ws, _) =
+        tokio_tungstenite::connect_async(format!("ws://{addr}/api/v1/notifications/ws"))
+            .await
+            .expect("connect");
+    ws.send(Message::Text(
+        serde_json::json!({ "type": "identify", "token": "vxa_bogus" })
+            .to_string()
+            .into(),
+    ))
+    .await
+    .unwrap();
+
+    let msg = time::timeout(Duration::from_secs(1), ws.next())
+        .await
+        .expect("close frame")
+        .expect("message")
+        .expect("ok");
+    let Message::Close(Some(frame)) = msg else {
+        panic!("expected close, got {msg:?}");
+    };
+    assert_eq!(frame.code, CloseCode::from(4004));
+}
+
+#[tokio::test]
+async fn relay_requires_pod_credentials_and_bounded_batches() {
+    let (app, state) = common::test_app().await;
+    let owner = common::create_test_user(&state.db, "relay_password_5").await;
+    let pod_id = common::create_test_pod(&state.db, &owner.id).await;
+    let secret = pod_secret(&state, &pod_id).await;
+    let server = TestServer::new(app).unwrap();
+
+    server
+        .post("/api/v1/notifications/relay")
+        .json(&serde_json::json!({ "notifications": [] }))
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+
+    let too_many: Vec<serde_json::Value> = (0..=hub_api::routes::notifications::MAX_RELAY_BATCH)
+        .map(|_| {
+            serde_json::json!({ "user_id": owner.id, "community_id": "com_1",
+                                "channel_id": "ch_1", "unread_count": 1, "has_mention": false })
+        })
+        .collect();
+    server
+        .post("/api/v1/notifications/relay")
+        .authorization_bearer(&secret)
+        .json(&serde_json::json!({ "notifications": too_many }))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    common::cleanup_test_pod(&state.db, &pod_id).await;
+    common::cleanup_test_user(&state.db, &owner.id).await;
+}
@@ -0,0 +1,471 @@
+//! Integration tests for the OAuth client registry: owner management, and
+//! `authorize`/`token` validating clients, redirect URIs, scopes and secrets.
+
+mod common;
+
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use base64::{engine::general_purpose::STANDARD, engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use sha2::{Digest, Sha256};
+
+const VERIFIER: &str = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+const REDIRECT_URI: &str = "https://app.example.com/oauth/callback";
+
+fn challenge() -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(VERIFIER.as_bytes()))
+}
+
+fn basic(client_id: &str, secret: &str) -> String {
+    format!("Basic {}", STANDARD.encode(format!("{client_id}:{secret}")))
+}
+
+async fn create_client(
+    server: &TestServer,
+    access: &str,
+    body: serde_json::Value,
+) -> serde_json::Value {
+    let resp = server
+        .post("/api/v1/oauth-clients")
+        .authorization_bearer(access)
+        .json(&body)
+        .await;
+    resp.assert_status(StatusCode::CREATED);
+    resp.json()
+}
+
+/// Sign in through `POST /oidc/authorize` and return the auth code.
+async fn authorize(
+    server: &TestServer,
+    user: &common::TestUser,
+    client_id: &str,
+    redirect_uri: &str,
+    scope: &str,
+) -> axum_test::TestResponse {
+    server
+        .post("/oidc/authorize")
+        .form(&[
+            ("response_type", "code"),
+            ("client_id", client_id),
+            ("redirect_uri", redirect_uri),
+            ("scope", scope),
+            ("code_challenge", &challenge()),
+            ("code_challenge_method", "S256"),
+            ("login", &user.username),
+            ("password", &user.password),
+        ])
+        .await
+}
+
+fn code_from(resp: &axum_test::TestResponse) -> String {
+    resp.assert_status(StatusCode::SEE_OTHER);
+    let location = resp.header("location").to_str().unwrap().to_string();
+    location
+        .split("code=")
+        .nth(1)
+        .unwrap()
+        .split('&')
+        .next()
+        .unwrap()
+        .to_string()
+}
+
+#[tokio::test]
+async fn confidential_client_full_flow() {
+    let (app, state) = common::test_app().await;
+    let owner = common::create_test_user(&state.db, "clients_password_1").await;
+    let user = common::create_test_user(&state.db, "clients_password_1").await;
+    let owner_access =
+        common::store_test_access_token(state.kv.as_ref(), &owner.id, &["openid"]).await;
+    let server = TestServer::new(app).unwrap();
+
+    let client = create_client(
+        &server,
+        &owner_access,
+        serde_json::json!({
+            "name": "Desktop Sync",
+            "client_type": "confidential",
+            "redirect_uris": [REDIRECT_URI],
+            "allowed_scopes": ["openid", "profile"],
+        }),
+    )
+    .await;
+    let client_id = client["client_id"].as_str().unwrap().to_string();
+    let secret = client["client_secret"].as_str().unwrap().to_string();
+    assert!(client_id.starts_with("cli_"));
+    assert!(secret.starts_with("hcs_"));
+    assert_eq!(
+        client["grant_types"],
+        serde_json::json!(["authorization_code", "refresh_token"])
+    );
+
+    let code =
+        code_from(&authorize(&server, &user, &client_id, REDIRECT_URI, "openid profile").await);
+    let exchange = format!(
+        "grant_type=authorization_code&code={code}&redirect_uri={REDIRECT_URI}\
+         &code_verifier={VERIFIER}"
+    );
+
+    // No secret.
+    server
+        .post("/oidc/token")
+        .content_type("application/x-www-form-urlencoded")
+        .bytes(format!("{exchange}&client_id={client_id}").into())
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+
+    // Wrong secret.
+    server
+        .post("/oidc/token")
+        .add_header("authorization", basic(&client_id, "hcs_wrong"))
+        .content_type("application/x-www-form-urlencoded")
+        .bytes(exchange.clone().into())
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+
+    // client_secret_basic. Failed client authentication didn't burn the code.
+    let resp = server
+        .post("/oidc/token")
+        .add_header("authorization", basic(&client_id, &secret))
+        .content_type("application/x-www-form-urlencoded")
+        .bytes(exchange.into())
+        .await;
+    resp.assert_status_ok();
+    let tokens: serde_json::Value = resp.json();
+
+    // The ID token is issued to the client.
+    let id_token = tokens["id_token"].as_str().unwrap();
+    let payload = id_token.split('.').nth(1).unwrap();
+    let claims: serde_json::Value =
+        serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload).unwrap()).unwrap();
+    assert_eq!(claims["aud"], client_id);
+
+    // client_secret_post on refresh.
+    let refresh_token = tokens["refresh_token"].as_str().unwrap();
+    let resp = server
+        .post("/oidc/token")
+        .form(&[
+            ("grant_type", "refresh_token"),
+            ("refresh_token", refresh_token),
+            ("client_id", &client_id),
+            ("client_secret", &secret),
+        ])
+        .await;
+    resp.assert_status_ok();
+    let refreshed: serde_json::Value = resp.json();
+
+    // The session remembers its client.
+    let sessions: serde_json::Value = server
+        .get("/api/v1/users/@me/sessions")
+        .authorization_bearer(refreshed["access_token"].as_str().unwrap())
+        .await
+        .json();
+    assert_eq!(sessions["data"][0]["client_id"], client_id);
+
+    // Another client can't use the refresh token.
+    server
+        .post("/oidc/token")
+        .form(&[
+            ("grant_type", "refresh_token"),
+            (
+                "refresh_token",
+                refreshed["refresh_token"].as_str().unwrap(),
+            ),
+            ("client_id", "voxora-web"),
+        ])
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+    common::cleanup_test_user(&state.db, &owner.id).await;
+}
+
+#[tokio::test]
+async fn public_client_needs_no_secret() {
+    let (app, state) = common::test_app().await;
+    let owner = common::create_test_user(&state.db, "clients_password_1").await;
+    let owner_access =
+        common::store_test_access_token(state.kv.as_ref(), &owner.id, &["openid"]).await;
+    let server = TestServer::new(app).unwrap();
+
+    let client = create_client(
+        &server,
+        &owner_access,
+        serde_json::json!({
+            "name": "Mobile",
+            "redirect_uris": ["com.example.voxora:/callback"],
+        }),
+    )
+    .await;
+    assert_eq!(client["client_type"], "public");
+    assert!(client["client_secret"].is_null());
+    let client_id = client["client_id"].as_str().unwrap();
+
+    let code = code_from(
+        &authorize(
+            &server,
+            &owner,
+            client_id,
+            "com.example.voxora:/callback",
+            "openid",
+        )
+        .await,
+    );
+    server
+        .post("/oidc/token")
+        .form(&[
+            ("grant_type", "authorization_code"),
+            ("code", &code),
+            ("redirect_uri", "com.example.voxora:/callback"),
+            ("code_verifier", VERIFIER),
+            ("client_id", client_id),
+        ])
+        .await
+        .assert_status_ok();
+
+    common::cleanup_test_user(&state.db, &owner.id).await;
+}
+
+#[tokio::test]
+async fn authorize_validates_redirect_uri_and_scope() {
+    let (app, state) = common::test_app().await;
+    let owner = common::create_test_user(&state.db, "clients_password_1").await;
+    let owner_access =
+        common::store_test_access_token(state.kv.as_ref(), &owner.id, &["openid"]).await;
+    let server = TestServer::new(app).unwrap();
+
+    let client = create_client(
+        &server,
+        &owner_access,
+        serde_json::json!({ "name": "Web", "redirect_uris": [REDIRECT_URI] }),
+    )
+    .await;
+    let client_id = client["client_id"].as_str().unwrap();
+
+    let get = |redirect_uri: &'static str, scope: &'static str| {
+        server
+            .get("/oidc/authorize")
+            .add_query_param("response_type", "code")
+            .add_query_param("client_id", client_id)
+            .add_query_param("redirect_uri", redirect_uri)
+            .add_query_param("scope", scope)
+            .add_query_param("code_challenge", challenge())
+            .add_query_param("code_challenge_method", "S256")
+    };
+
+    get(REDIRECT_URI, "openid profile").await.assert_status_ok();
+    get("https://evil.example.com/callback", "openid")
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+    get("https://app.example.com/oauth/callback/extra", "openid")
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+    // Only openid and profile by default.
+    get(REDIRECT_URI, "openid pods")
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    // The login form submission is checked too.
+    authorize(
+        &server,
+        &owner,
+        client_id,
+        "https://evil.example.com/cb",
+        "openid",
+    )
+    .await
+    .assert_status(StatusCode::BAD_REQUEST);
+
+    common::cleanup_test_user(&state.db, &owner.id).await;
+}
+
+#[tokio::test]
+async fn code_cannot_be_redeemed_by_another_client() {
+    let (app, state) = common::test_app().await;
+    let owner = common::create_test_user(&state.db, "clients_password_1").await;
+    let owner_access =
+        common::store_test_access_token(state.kv.as_ref(), &owner.id, &["openid"]).await;
+    let server = TestServer::new(app).unwrap();
+
+    let client = create_client(
+        &server,
+        &owner_access,
+        serde_json::json!({ "name": "Web", "redirect_uris": [REDIRECT_URI] }),
+    )
+    .await;
+    let client_id = client["client_id"].as_str().unwrap();
+
+    let code = code_from(&authorize(&server, &owner, client_id, REDIRECT_URI, "openid").await);
+    server
+        .post("/oidc/token")
+        .form(&[
+            ("grant_type", "authorization_code"),
+            ("code", &code),
+            ("redirect_uri", REDIRECT_URI),
+            ("code_verifier", VERIFIER),
+            ("client_id", "voxora-web"),
+        ])
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    common::cleanup_test_user(&state.db, &owner.id).await;
+}
+
+#[tokio::test]
+async fn registration_is_validated() {
+    let (app, state) = common::test_app().await;
+    let owner = common::create_test_user(&state.db, "clients_password_1").await;
+    let owner_access =
+        common::store_test_access_token(state.kv.as_ref(), &owner.id, &["openid"]).await;
+    let server = TestServer::new(app).unwrap();
+
+    for body in [
+        serde_json::json!({ "name": "", "redirect_uris": [REDIRECT_URI] }),
+        serde_json::json!({ "name": "Plain http", "redirect_uris": ["http://app.example.com/cb"] }),
+        serde_json::json!({ "name": "Fragment", "redirect_uris": ["https://app.example.com/#cb"] }),
+        serde_json::json!({ "name": "No redirect" }),
+        serde_json::json!({ "name": "Bad scope", "redirect_uris": [REDIRECT_URI], "allowed_scopes": ["admin"] }),
+        serde_json::json!({ "name": "Bad grant", "redirect_uris": [REDIRECT_URI], "grant_types": ["password"] }),
+        serde_json::json!({ "name": "Bad type", "redirect_uris": [REDIRECT_URI], "client_type": "trusted" }),
+    ] {
+        let resp = server
+            .post("/api/v1/oauth-clients")
+            .authorization_bearer(&owner_access)
+            .json(&body)
+            .await;
+        resp.assert_status(StatusCode::BAD_REQUEST);
+        let err: serde_json::Value = resp.json();
+        assert_eq!(err["error"]["code"], "VALIDATION_ERROR", "{body}");
+    }
+
+    // Loopback http is fine for development.
+    create_client(
+        &server,
+        &owner_access,
+        serde_json::json!({ "name": "Dev", "redirect_uris": ["http://127.0.0.1:8765/cb"] }),
+    )
+    .await;
+
+    common::cleanup_test_user(&state.db, &owner.id).await;
+}
+
+#[tokio::test]
+async fn owner_manages_clients() {
+    let (app, state) = common::test_app().await;
+    let owner = common::create_test_user(&state.db, "clients_password_1").await;
+    let other = common::create_test_user(&state.db, "clients_password_1").await;
+    let owner_access =
+        common::store_test_access_token(state.kv.as_ref(), &owner.id, &["openid"]).await;
+    let other_access =
+        common::store_test_access_token(state.kv.as_ref(), &other.id, &["openid"]).await;
+    let server = TestServer::new(app).unwrap();
+
+    let client = create_client(
+        &server,
+        &owner_access,
+        serde_json::json!({
+            "name": "Bot Dashboard",
+            "client_type": "confidential",
+            "redirect_uris": [REDIRECT_URI],
+        }),
+    )
+    .await;
+    let client_id = client["client_id"].as_str().unwrap();
+    let old_secret = client["client_secret"].as_str().unwrap();
+    let path = format!("/api/v1/oauth-clients/{client_id}");
+
+    // Listed and readable by the owner only, without the secret.
+    let list: serde_json::Value = server
+        .get("/api/v1/oauth-clients")
+        .authorization_bearer(&owner_access)
+        .await
+        .json();
+    assert_eq!(list["data"].as_array().unwrap().len(), 1);
+    assert!(list["data"][0].get("client_secret").is_none());
+    server
+        .get(&path)
+        .authorization_bearer(&other_access)
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+    let other_list: serde_json::Value = server
+        .get("/api/v1/oauth-clients")
+        .authorization_bearer(&other_access)
+        .await
+        .json();
+    assert!(other_list["data"].as_array().unwrap().is_empty());
+
+    // Update.
+    let resp = server
+        .patch(&path)
+        .authorization_bearer(&owner_access)
+        .json(&serde_json::json!({
+            "name": "Renamed",
+            "redirect_uris": [REDIRECT_URI, "https://app.example.com/other"],
+        }))
+        .await;
+    resp.assert_status_ok();
+    let updated: serde_json::Value = resp.json();
+    assert_eq!(updated["name"], "Renamed");
+    assert_eq!(updated["redirect_uris"].as_array().unwrap().len(), 2);
+    server
+        .patch(&path)
+        .authorization_bearer(&other_access)
+        .json(&serde_json::json!({ "name": "Hijacked" }))
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+
+    // Rotate the secret; the old one stops working.
+    let resp = server
+        .post(&format!("{path}/secret"))
+        .authorization_bearer(&owner_access)
+        .await;
+    resp.assert_status_ok();
+    let rotated: serde_json::Value = resp.json();
+    let new_secret = rotated["client_secret"].as_str().unwrap();
+    assert_ne!(new_secret, old_secret);
+
+    let code = code_from(&authorize(&server, &owner, client_id, REDIRECT_URI, "openid").await);
+    let exchange = [
+        ("grant_type", "authorization_code"),
+        ("code", code.as_str()),
+        ("redirect_uri", REDIRECT_URI),
+        ("code_verifier", VERIFIER),
+        ("client_id", client_id),
+    ];
+    server
+        .post("/oidc/token")
+        .form(&[&exchange[..], &[("client_secret", old_secret)]].concat())
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+    let resp = server
+        .post("/oidc/token")
+        .form(&[&exchange[..], &[("client_secret", new_secret)]].concat())
+        .await;
+    resp.assert_status_ok();
+    let tokens: serde_json::Value = resp.json();
+    let client_access = tokens["access_token"].as_str().unwrap();
+
+    // Deleting the client signs out its sessions.
+    server
+        .delete(&path)
+        .authorization_bearer(&other_access)
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+    server
+        .delete(&path)
+        .authorization_bearer(&owner_access)
+        .await
+        .assert_status(StatusCode::NO_CONTENT);
+    server
+        .get("/api/v1/users/@me")
+        .authorization_bearer(client_access)
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+    server
+        .get(&path)
+        .authorization_bearer(&owner_access)
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+
+    common::cleanup_test_user(&state.db, &other.id).await;
+    common::cleanup_test_user(&state.db, &owner.id).await;
+}
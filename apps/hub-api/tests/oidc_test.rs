@@ -7,6 +7,10 @@ mod common;
 use axum::http::StatusCode;
 use axum_test::TestServer;
 use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use hub_api::auth::events;
+use hub_api::db::schema::auth_events;
 use sha2::{Digest, Sha256};
 
 /// Helper: compute S256 code_challenge from code_verifier.
@@ -36,6 +40,10 @@ async fn discovery_returns_valid_document() {
     assert!(body["token_endpoint"].as_str().is_some());
     assert!(body["jwks_uri"].as_str().is_some());
     assert!(body["userinfo_endpoint"].as_str().is_some());
+    assert!(body["introspection_endpoint"]
+        .as_str()
+        .unwrap()
+        .ends_with("/oidc/introspect"));
 }
 
 #[tokio::test]
@@ -51,8 +59,11 @@ async fn jwks_returns_ed25519_key() {
     assert_eq!(key["kty"], "OKP");
     assert_eq!(key["crv"], "Ed25519");
     assert_eq!(key["use"], "sig");
-    assert_eq!(key["kid"].as_str().unwrap(), state.keys.kid);
-    assert_eq!(key["x"].as_str().unwrap(), state.keys.public_key_b64);
+    assert_eq!(key["kid"].as_str().unwrap(), state.keys.active().kid);
+    assert_eq!(
+        key["x"].as_str().unwrap(),
+        state.keys.active().public_key_b64
+    );
 }
 
 // =========================================================================
@@ -152,7 +163,7 @@ async fn authorize_submit_rejects_wrong_password() {
         .content_type("application/x-www-form-urlencoded")
         .bytes(
             format!(
-                "response_type=code&client_id=voxora-web&redirect_uri=http://localhost:5173/cb\
+                "response_type=code&client_id=voxora-web&redirect_uri=http://localhost:5173/callback\
              &scope=openid&code_challenge={challenge}&code_challenge_method=S256\
              &login={}&password=wrongpassword",
                 user.username
@@ -178,7 +189,7 @@ async fn authorize_submit_rejects_unknown_user() {
         .content_type("application/x-www-form-urlencoded")
         .bytes(
             format!(
-                "response_type=code&client_id=voxora-web&redirect_uri=http://localhost:5173/cb\
+                "response_type=code&client_id=voxora-web&redirect_uri=http://localhost:5173/callback\
              &scope=openid&code_challenge={challenge}&code_challenge_method=S256\
              &login=nonexistentuser99999&password=doesntmatter"
             )
@@ -267,7 +278,7 @@ async fn full_auth_code_flow() {
     validation.set_issuer(&[&state.config.hub_domain]);
     let id_claims = jsonwebtoken::decode::<hub_api::auth::tokens::IdTokenClaims>(
         id_token,
-        &state.keys.decoding,
+        &state.keys.active().decoding,
         &validation,
     )
     .expect("ID token verification must succeed");
@@ -277,6 +288,7 @@ async fn full_auth_code_flow() {
         Some(user.username.as_str())
     );
     assert_eq!(id_claims.claims.nonce.as_deref(), Some("n1"));
+    assert_eq!(id_claims.claims.amr, vec!["pwd"]);
 
     // --- Step 3: GET /oidc/userinfo with the access token ---
     let resp = server
@@ -311,19 +323,7 @@ async fn full_auth_code_flow() {
     // No id_token on refresh
     assert!(refresh_body.get("id_token").is_none() || refresh_body["id_token"].is_null());
 
-    // --- Step 5: Old refresh token should now be revoked ---
-    let resp = server
-        .post("/oidc/token")
-        .content_type("application/x-www-form-urlencoded")
-        .bytes(
-            format!("grant_type=refresh_token&refresh_token={refresh_token}&client_id=voxora-web")
-                .into(),
-        )
-        .await;
-
-    resp.assert_status(StatusCode::UNAUTHORIZED);
-
-    // --- Step 6: Userinfo works with the new access token ---
+    // --- Step 5: Userinfo works with the new access token ---
     let resp = server
         .get("/oidc/userinfo")
         .authorization_bearer(new_at)
@@ -331,7 +331,7 @@ async fn full_auth_code_flow() {
 
     resp.assert_status_ok();
 
-    // --- Step 7: Revoke the new access token ---
+    // --- Step 6: Revoke the new access token ---
     let resp = server
         .post("/oidc/revoke")
         .content_type("application/x-www-form-urlencoded")
@@ -340,7 +340,7 @@ async fn full_auth_code_flow() {
 
     resp.assert_status_ok();
 
-    // --- Step 8: Userinfo should now fail ---
+    // --- Step 7: Userinfo should now fail ---
     let resp = server
         .get("/oidc/userinfo")
         .authorization_bearer(new_at)
@@ -348,7 +348,7 @@ async fn full_auth_code_flow() {
 
     resp.assert_status(StatusCode::UNAUTHORIZED);
 
-    // --- Step 9: Revoke the new refresh token ---
+    // --- Step 8: Revoke the new refresh token ---
     let resp = server
         .post("/oidc/revoke")
         .content_type("application/x-www-form-urlencoded")
@@ -366,12 +366,204 @@ async fn full_auth_code_flow() {
         )
         .await;
 
-    resp.assert_status(StatusCode::UNAUTHORIZED);
+    resp.assert_status(StatusCode::BAD_REQUEST);
+    let body: serde_json::Value = resp.json();
+    assert_eq!(body["error"].as_str(), Some("invalid_grant"));
+
+    // --- Step 9: The rotated-out refresh token is rejected too ---
+    let resp = server
+        .post("/oidc/token")
+        .content_type("application/x-www-form-urlencoded")
+        .bytes(
+            format!("grant_type=refresh_token&refresh_token={refresh_token}&client_id=voxora-web")
+                .into(),
+        )
+        .await;
+
+    resp.assert_status(StatusCode::BAD_REQUEST);
 
     // Cleanup
     common::cleanup_test_user(&state.db, &user.id).await;
 }
 
+// =========================================================================
+// Refresh token families
+// =========================================================================
+
+/// Sign in through the authorization code flow and return the token response.
+async fn sign_in(server: &TestServer, user: &common::TestUser, scope: &str) -> serde_json::Value {
+    let code_verifier = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+    let redirect_uri = "http://localhost:5173/callback";
+    let resp = server
+        .post("/oidc/authorize")
+        .form(&[
+            ("response_type", "code"),
+            ("client_id", "voxora-web"),
+            ("redirect_uri", redirect_uri),
+            ("scope", scope),
+            ("code_challenge", &pkce_challenge(code_verifier)),
+            ("code_challenge_method", "S256"),
+            ("login", &user.username),
+            ("password", &user.password),
+        ])
+        .await;
+    resp.assert_status(StatusCode::SEE_OTHER);
+    let location = resp.header("location").to_str().unwrap().to_string();
+    let code = location
+        .split("code=")
+        .nth(1)
+        .unwrap()
+        .split('&')
+        .next()
+        .unwrap();
+
+    let resp = server
+        .post("/oidc/token")
+        .form(&[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", redirect_uri),
+            ("code_verifier", code_verifier),
+            ("client_id", "voxora-web"),
+        ])
+        .await;
+    resp.assert_status_ok();
+    resp.json()
+}
+
+async fn refresh(server: &TestServer, refresh_token: &str) -> axum_test::TestResponse {
+    server
+        .post("/oidc/token")
+        .form(&[
+            ("grant_type", "refresh_token"),
+            ("refresh_token", refresh_token),
+            ("client_id", "voxora-web"),
+        ])
+        .await
+}
+
+#[tokio::test]
+async fn refresh_keeps_the_granted_scopes() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "test_password_123").await;
+    let server = TestServer::new(app).unwrap();
+
+    let tokens = sign_in(&server, &user, "openid profile").await;
+    assert_eq!(tokens["scope"], "openid profile");
+
+    let resp = refresh(&server, tokens["refresh_token"].as_str().unwrap()).await;
+    resp.assert_status_ok();
+    let refreshed: serde_json::Value = resp.json();
+    assert_eq!(refreshed["scope"], "openid profile");
+
+    // No `pods` scope, so no SIAs.
+    server
+        .post("/api/v1/oidc/sia")
+        .authorization_bearer(refreshed["access_token"].as_str().unwrap())
+        .json(&serde_json::json!({ "pod_id": "pod_whatever" }))
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
+
+#[tokio::test]
+async fn refresh_token_reuse_revokes_the_family() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "test_password_123").await;
+    let server = TestServer::new(app).unwrap();
+
+    // An unrelated sign-in that must survive.
+    let other = sign_in(&server, &user, "openid").await;
+
+    let first = sign_in(&server, &user, "openid").await;
+    let rt1 = first["refresh_token"].as_str().unwrap();
+    let second: serde_json::Value = refresh(&server, rt1).await.json();
+    let rt2 = second["refresh_token"].as_str().unwrap();
+    let third: serde_json::Value = refresh(&server, rt2).await.json();
+    let rt3 = third["refresh_token"].as_str().unwrap();
+    let at3 = third["access_token"].as_str().unwrap();
+    server
+        .get("/oidc/userinfo")
+        .authorization_bearer(at3)
+        .await
+        .assert_status_ok();
+
+    // Replaying a rotated token fails and signs the family out.
+    refresh(&server, rt1)
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+    refresh(&server, rt3)
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+    server
+        .get("/oidc/userinfo")
+        .authorization_bearer(at3)
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+    server
+        .get("/oidc/userinfo")
+        .authorization_bearer(second["access_token"].as_str().unwrap())
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+
+    // Other sign-ins are untouched.
+    server
+        .get("/oidc/userinfo")
+        .authorization_bearer(other["access_token"].as_str().unwrap())
+        .await
+        .assert_status_ok();
+    refresh(&server, other["refresh_token"].as_str().unwrap())
+        .await
+        .assert_status_ok();
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
+
+/// How many times reuse of one of the user's refresh tokens was detected.
+async fn reuse_events(state: &hub_api::AppState, user_id: &str) -> i64 {
+    let mut conn = state.db.get().await.unwrap();
+    auth_events::table
+        .filter(auth_events::user_id.eq(user_id))
+        .filter(auth_events::event.eq(events::TOKEN_REUSE_DETECTED))
+        .count()
+        .get_result(&mut conn)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn signed_out_refresh_token_is_not_treated_as_reuse() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "test_password_123").await;
+    let server = TestServer::new(app).unwrap();
+
+    let first = sign_in(&server, &user, "openid").await;
+    let rt1 = first["refresh_token"].as_str().unwrap();
+    let second: serde_json::Value = refresh(&server, rt1).await.json();
+    let rt2 = second["refresh_token"].as_str().unwrap();
+
+    server
+        .post("/oidc/revoke")
+        .form(&[("token", rt2), ("token_type_hint", "refresh_token")])
+        .await
+        .assert_status_ok();
+
+    // A signed-out token is just refused.
+    let resp = refresh(&server, rt2).await;
+    resp.assert_status(StatusCode::BAD_REQUEST);
+    assert_eq!(resp.json::<serde_json::Value>()["error"], "invalid_grant");
+    assert_eq!(reuse_events(&state, &user.id).await, 0);
+
+    // Replaying the rotated one is reuse.
+    refresh(&server, rt1)
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+    assert_eq!(reuse_events(&state, &user.id).await, 1);
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
+
 // =========================================================================
 // Auth code is single-use
 // =========================================================================
@@ -496,7 +688,8 @@ async fn wrong_code_verifier_fails_pkce() {
     resp.assert_status(StatusCode::BAD_REQUEST);
     let body: serde_json::Value = resp.json();
     assert!(
-        body["error"]["message"].as_str().unwrap().contains("PKCE"),
+        body["error"].as_str() == Some("invalid_grant")
+            && body["error_description"].as_str().unwrap().contains("PKCE"),
         "error message should mention PKCE"
     );
 
@@ -516,7 +709,7 @@ async fn token_rejects_unsupported_grant_type() {
         .post("/oidc/token")
         .content_type("application/x-www-form-urlencoded")
         .bytes(
-            "grant_type=client_credentials&client_id=voxora-web"
+            "grant_type=password&client_id=voxora-web"
                 .to_string()
                 .into(),
         )
@@ -524,10 +717,7 @@ async fn token_rejects_unsupported_grant_type() {
 
     resp.assert_status(StatusCode::BAD_REQUEST);
     let body: serde_json::Value = resp.json();
-    assert!(body["error"]["message"]
-        .as_str()
-        .unwrap()
-        .contains("grant_type"));
+    assert_eq!(body["error"].as_str(), Some("unsupported_grant_type"));
 }
 
 #[tokio::test]
@@ -629,3 +819,123 @@ async fn login_with_email_works() {
 
     common::cleanup_test_user(&state.db, &user.id).await;
 }
+
+// =========================================================================
+// POST /oidc/introspect
+// =========================================================================
+
+async fn pod_secret(state: &hub_api::AppState, pod_id: &str) -> String {
+    use diesel::prelude::*;
+    use diesel_async::RunQueryDsl;
+
+    let mut conn = state.db.get().await.unwrap();
+    hub_api::db::schema::pods::table
+        .find(pod_id)
+        .select(hub_api::db::schema::pods::client_secret)
+        .first(&mut conn)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn pods_introspect_access_tokens() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "test_password_123").await;
+    let pod_id = common::create_test_pod(&state.db, &user.id).await;
+    let secret = pod_secret(&state, &pod_id).await;
+    let server = TestServer::new(app).unwrap();
+
+    let tokens = sign_in(&server, &user, "openid pods").await;
+    let access_token = tokens["access_token"].as_str().unwrap();
+
+    let resp = server
+        .post("/oidc/introspect")
+        .authorization_bearer(&secret)
+        .form(&[("token", access_token)])
+        .await;
+    resp.assert_status_ok();
+    let body: serde_json::Value = resp.json();
+    assert_eq!(body["active"], true);
+    assert_eq!(body["sub"], user.id);
+    assert_eq!(body["scope"], "openid pods");
+    assert_eq!(body["client_id"], "voxora-web");
+    assert_eq!(body["token_type"], "Bearer");
+    let exp = body["exp"].as_i64().unwrap();
+    assert!(exp > chrono::Utc::now().timestamp());
+
+    // Revoked and unknown tokens are inactive, with nothing else disclosed.
+    server
+        .post("/oidc/revoke")
+        .form(&[("token", access_token)])
+        .await
+        .assert_status_ok();
+    for token in [
+        access_token,
+        "hat_unknown",
+        tokens["refresh_token"].as_str().unwrap(),
+    ] {
+        let body: serde_json::Value = server
+            .post("/oidc/introspect")
+            .authorization_bearer(&secret)
+            .form(&[("token", token)])
+            .await
+            .json();
+        assert_eq!(body, serde_json::json!({ "active": false }));
+    }
+
+    common::cleanup_test_pod(&state.db, &pod_id).await;
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
+
+#[tokio::test]
+async fn introspection_requires_confidential_credentials() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "test_password_123").await;
+    let access = common::store_test_access_token(state.kv.as_ref(), &user.id, &["openid"]).await;
+    let server = TestServer::new(app).unwrap();
+
+    // Nobody, a bad pod secret, and a public client are all refused.
+    server
+        .post("/oidc/introspect")
+        .form(&[("token", access.as_str())])
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+    server
+        .post("/oidc/introspect")
+        .authorization_bearer("vxs_wrong")
+        .form(&[("token", access.as_str())])
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+    server
+        .post("/oidc/introspect")
+        .form(&[("token", access.as_str()), ("client_id", "voxora-web")])
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+
+    // A registered confidential client may introspect.
+    let resp = server
+        .post("/api/v1/oauth-clients")
+        .authorization_bearer(&access)
+        .json(&serde_json::json!({
+            "name": "Resource server",
+            "client_type": "confidential",
+            "redirect_uris": ["https://rs.example.com/cb"],
+        }))
+        .await;
+    resp.assert_status(StatusCode::CREATED);
+    let client: serde_json::Value = resp.json();
+    let resp = server
+        .post("/oidc/introspect")
+        .form(&[
+            ("token", access.as_str()),
+            ("client_id", client["client_id"].as_str().unwrap()),
+            ("client_secret", client["client_secret"].as_str().unwrap()),
+        ])
+        .await;
+    resp.assert_status_ok();
+    let body: serde_json::Value = resp.json();
+    assert_eq!(body["active"], true);
+    assert_eq!(body["sub"], user.id);
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
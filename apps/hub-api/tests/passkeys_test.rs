@@ -0,0 +1,659 @@
+//! Integration tests for passkey registration, management, and passwordless
+//! sign-in, driven by a software authenticator.
+
+mod common;
+
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ciborium::Value;
+use hub_api::AppState;
+use sha2::{Digest, Sha256};
+
+const CODE_VERIFIER: &str = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+const REDIRECT_URI: &str = "http://localhost:5173/callback";
+
+// =========================================================================
+// Software authenticator
+// =========================================================================
+
+enum SoftKey {
+    Es256(p256::ecdsa::SigningKey),
+    Ed25519(ed25519_dalek::SigningKey),
+}
+
+/// A minimal CTAP2-style authenticator holding one discoverable credential.
+struct SoftAuthenticator {
+    key: SoftKey,
+    credential_id: Vec<u8>,
+    user_handle: Option<Vec<u8>>,
+    sign_count: u32,
+    rp_id: String,
+    origin: String,
+}
+
+impl SoftAuthenticator {
+    fn es256(state: &AppState) -> Self {
+        Self::new(
+            state,
+            SoftKey::Es256(p256::ecdsa::SigningKey::random(&mut rand::thread_rng())),
+        )
+    }
+
+    fn ed25519(state: &AppState) -> Self {
+        Self::new(
+            state,
+            SoftKey::Ed25519(ed25519_dalek::SigningKey::generate(&mut rand::thread_rng())),
+        )
+    }
+
+    fn new(state: &AppState, key: SoftKey) -> Self {
+        Self {
+            key,
+            credential_id: rand::random::<[u8; 16]>().to_vec(),
+            user_handle: None,
+            sign_count: 0,
+            rp_id: state.config.webauthn_rp_id.clone(),
+            origin: state.config.webauthn_origins[0].clone(),
+        }
+    }
+
+    fn cose_key(&self) -> Vec<u8> {
+        let int = |i: i64| Value::Integer(i.into());
+        let map = match &self.key {
+            SoftKey::Es256(key) => {
+                let point = key.verifying_key().to_encoded_point(false);
+                vec![
+                    (int(1), int(2)),
+                    (int(3), int(-7)),
+                    (int(-1), int(1)),
+                    (int(-2), Value::Bytes(point.x().unwrap().to_vec())),
+                    (int(-3), Value::Bytes(point.y().unwrap().to_vec())),
+                ]
+            }
+            SoftKey::Ed25519(key) => vec![
+                (int(1), int(1)),
+                (int(3), int(-8)),
+                (int(-1), int(6)),
+                (
+                    int(-2),
+                    Value::Bytes(key.verifying_key().to_bytes().to_vec()),
+                ),
+            ],
+        };
+        let mut out = Vec::new();
+        ciborium::into_writer(&Value::Map(map), &mut out).unwrap();
+        out
+    }
+
+    fn client_data(&self, kind: &str, challenge: &str) -> Vec<u8> {
+        serde_json::to_vec(&serde_json::json!({
+            "type": kind,
+            "challenge": challenge,
+            "origin": self.origin,
+            "crossOrigin": false,
+        }))
+        .unwrap()
+    }
+
+    fn auth_data(&self, flags: u8, attested: bool) -> Vec<u8> {
+        let mut data = Sha256::digest(self.rp_id.as_bytes()).to_vec();
+        data.push(flags);
+        data.extend_from_slice(&self.sign_count.to_be_bytes());
+        if attested {
+            data.extend_from_slice(&[0u8; 16]); // aaguid
+            data.extend_from_slice(&(self.credential_id.len() as u16).to_be_bytes());
+            data.extend_from_slice(&self.credential_id);
+            data.extend_from_slice(&self.cose_key());
+        }
+        data
+    }
+
+    fn sign(&self, message: &[u8]) -> Vec<u8> {
+        match &self.key {
+            SoftKey::Es256(key) => {
+                use p256::ecdsa::signature::Signer;
+                let sig: p256::ecdsa::Signature = key.sign(message);
+                sig.to_der().as_bytes().to_vec()
+            }
+            SoftKey::Ed25519(key) => {
+                use ed25519_dalek::Signer;
+                key.sign(message).to_bytes().to_vec()
+            }
+        }
+    }
+
+    /// Answer `navigator.credentials.create()`.
+    fn create(&mut self, options: &serde_json::Value) -> serde_json::Value {
+        self.user_handle = Some(
+            URL_SAFE_NO_PAD
+                .decode(options["user"]["id"].as_str().unwrap())
+                .unwrap(),
+        );
+        let client_data =
+            self.client_data("webauthn.create", options["challenge"].as_str().unwrap());
+
+        let attestation = Value::Map(vec![
+            (Value::Text("fmt".into()), Value::Text("none".into())),
+            (Value::Text("attStmt".into()), Value::Map(vec![])),
+            (
+                Value::Text("authData".into()),
+                Value::Bytes(self.auth_data(0x45, true)),
+            ),
+        ]);
+        let mut attestation_object = Vec::new();
+        ciborium::into_writer(&attestation, &mut attestation_object).unwrap();
+
+        let id = URL_SAFE_NO_PAD.encode(&self.credential_id);
+        serde_json::json!({
+            "id": id,
+            "rawId": id,
+            "type": "public-key",
+            "response": {
+                "clientDataJSON": URL_SAFE_NO_PAD.encode(&client_data),
+                "attestationObject": URL_SAFE_NO_PAD.encode(&attestation_object),
+                "transports": ["internal"],
+            },
+        })
+    }
+
+    /// Answer `navigator.credentials.get()`, bumping the signature counter.
+    fn get(&mut self, options: &serde_json::Value) -> serde_json::Value {
+        self.sign_count += 1;
+        self.assert_with_flags(options["challenge"].as_str().unwrap(), 0x05)
+    }
+
+    fn assert_with_flags(&self, challenge: &str, flags: u8) -> serde_json::Value {
+        let client_data = self.client_data("webauthn.get", challenge);
+        let auth_data = self.auth_data(flags, false);
+        let mut signed = auth_data.clone();
+        signed.extend_from_slice(&Sha256::digest(&client_data));
+
+        let id = URL_SAFE_NO_PAD.encode(&self.credential_id);
+        serde_json::json!({
+            "id": id,
+            "rawId": id,
+            "type": "public-key",
+            "response": {
+                "clientDataJSON": URL_SAFE_NO_PAD.encode(&client_data),
+                "authenticatorData": URL_SAFE_NO_PAD.encode(&auth_data),
+                "signature": URL_SAFE_NO_PAD.encode(self.sign(&signed)),
+                "userHandle": self.user_handle.as_ref().map(|h| URL_SAFE_NO_PAD.encode(h)),
+            },
+        })
+    }
+}
+
+// =========================================================================
+// Helpers
+// =========================================================================
+
+async fn begin_registration(server: &TestServer, token: &str) -> serde_json::Value {
+    let resp = server
+        .post("/api/v1/users/@me/passkeys/register/begin")
+        .authorization_bearer(token)
+        .await;
+    resp.assert_status_ok();
+    resp.json()
+}
+
+async fn complete_registration(
+    server: &TestServer,
+    token: &str,
+    name: &str,
+    credential: serde_json::Value,
+) -> axum_test::TestResponse {
+    server
+        .post("/api/v1/users/@me/passkeys/register/complete")
+        .authorization_bearer(token)
+        .json(&serde_json::json!({ "name": name, "credential": credential }))
+        .await
+}
+
+/// Register `authenticator` for the user behind `token`. Returns the passkey ID.
+async fn register(
+    server: &TestServer,
+    token: &str,
+    authenticator: &mut SoftAuthenticator,
+    name: &str,
+) -> String {
+    let options = begin_registration(server, token).await;
+    let credential = authenticator.create(&options);
+    let resp = complete_registration(server, token, name, credential).await;
+    resp.assert_status(StatusCode::CREATED);
+    resp.json::<serde_json::Value>()["id"]
+        .as_str()
+        .unwrap()
+        .to_string()
+}
+
+async fn begin_sign_in(server: &TestServer) -> serde_json::Value {
+    let resp = server.post("/oidc/authorize/passkey/begin").await;
+    resp.assert_status_ok();
+    resp.json()
+}
+
+/// Post a passkey assertion from the login page.
+async fn submit_passkey(
+    server: &TestServer,
+    credential: &serde_json::Value,
+) -> axum_test::TestResponse {
+    let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(CODE_VERIFIER.as_bytes()));
+    let form = [
+        ("response_type", "code".to_string()),
+        ("client_id", "voxora-web".to_string()),
+        ("redirect_uri", REDIRECT_URI.to_string()),
+        ("scope", "openid profile".to_string()),
+        ("state", "s1".to_string()),
+        ("code_challenge", challenge),
+        ("code_challenge_method", "S256".to_string()),
+        ("credential", credential.to_string()),
+    ];
+    server.post("/oidc/authorize/passkey").form(&form).await
+}
+
+// =========================================================================
+// Registration and management
+// =========================================================================
+
+#[tokio::test]
+async fn register_multiple_named_passkeys() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "passkey_password_1").await;
+    let token = common::store_test_access_token(state.kv.as_ref(), &user.id, &["openid"]).await;
+    let server = TestServer::new(app).unwrap();
+
+    let options = begin_registration(&server, &token).await;
+    assert_eq!(options["rp"]["id"], state.config.webauthn_rp_id.as_str());
+    assert_eq!(options["rp"]["name"], "Voxora");
+    assert_eq!(options["user"]["name"], user.username.as_str());
+    assert_eq!(options["authenticatorSelection"]["residentKey"], "required");
+    assert_eq!(options["attestation"], "none");
+    assert!(options["excludeCredentials"].as_array().unwrap().is_empty());
+    let algs: Vec<i64> = options["pubKeyCredParams"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| p["alg"].as_i64().unwrap())
+        .collect();
+    assert_eq!(algs, vec![-7, -8]);
+
+    let mut laptop = SoftAuthenticator::es256(&state);
+    let credential = laptop.create(&options);
+    let resp = complete_registration(&server, &token, "  Laptop  ", credential).await;
+    resp.assert_status(StatusCode::CREATED);
+    let body: serde_json::Value = resp.json();
+    assert!(body["id"].as_str().unwrap().starts_with("pk_"));
+    assert_eq!(body["name"], "Laptop");
+    assert_eq!(body["transports"], serde_json::json!(["internal"]));
+    assert!(body["last_used_at"].is_null());
+    assert!(body.get("public_key").is_none());
+
+    let mut key = SoftAuthenticator::ed25519(&state);
+    register(&server, &token, &mut key, "Security key").await;
+
+    let resp = server
+        .get("/api/v1/users/@me/passkeys")
+        .authorization_bearer(&token)
+        .await;
+    resp.assert_status_ok();
+    let names: Vec<String> = resp.json::<serde_json::Value>()["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| p["name"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(names, vec!["Laptop", "Security key"]);
+
+    // Both are excluded from the next registration.
+    let options = begin_registration(&server, &token).await;
+    assert_eq!(options["excludeCredentials"].as_array().unwrap().len(), 2);
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
+
+#[tokio::test]
+async fn registration_challenge_is_single_use() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "passkey_password_2").await;
+    let token = common::store_test_access_token(state.kv.as_ref(), &user.id, &["openid"]).await;
+    let server = TestServer::new(app).unwrap();
+
+    let options = begin_registration(&server, &token).await;
+    let mut authenticator = SoftAuthenticator::es256(&state);
+    let credential = authenticator.create(&options);
+
+    complete_registration(&server, &token, "Phone", credential.clone())
+        .await
+        .assert_status(StatusCode::CREATED);
+    complete_registration(&server, &token, "Phone", credential)
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    // Racing submissions of one response consume the challenge once.
+    let options = begin_registration(&server, &token).await;
+    let credential = SoftAuthenticator::es256(&state).create(&options);
+    let (a, b) = tokio::join!(
+        complete_registration(&server, &token, "Tablet", credential.clone()),
+        complete_registration(&server, &token, "Tablet", credential),
+    );
+    let mut statuses = [a.status_code(), b.status_code()];
+    statuses.sort();
+    assert_eq!(statuses, [StatusCode::CREATED, StatusCode::BAD_REQUEST]);
+
+    // The same authenticator can't be registered twice, even with a new challenge.
+    let options = begin_registration(&server, &token).await;
+    let credential = authenticator.create(&options);
+    complete_registration(&server, &token, "Phone again", credential)
+        .await
+        .assert_status(StatusCode::CONFLICT);
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
+
+#[tokio::test]
+async fn registration_rejects_foreign_challenge_origin_and_rp() {
+    let (app, state) = common::test_app().await;
+    let alice = common::create_test_user(&state.db, "passkey_password_3").await;
+    let bob = common::create_test_user(&state.db, "passkey_password_3").await;
+    let alice_token =
+        common::store_test_access_token(state.kv.as_ref(), &alice.id, &["openid"]).await;
+    let bob_token = common::store_test_access_token(state.kv.as_ref(), &bob.id, &["openid"]).await;
+    let server = TestServer::new(app).unwrap();
+
+    // Bob can't complete a ceremony Alice started.
+    let options = begin_registration(&server, &alice_token).await;
+    let credential = SoftAuthenticator::es256(&state).create(&options);
+    complete_registration(&server, &bob_token, "Stolen", credential)
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    // Phishing origin.
+    let options = begin_registration(&server, &alice_token).await;
+    let mut phished = SoftAuthenticator::es256(&state);
+    phished.origin = "https://voxora.example.evil".to_string();
+    let credential = phished.create(&options);
+    complete_registration(&server, &alice_token, "Phished", credential)
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    // Credential scoped to another relying party.
+    let options = begin_registration(&server, &alice_token).await;
+    let mut other_rp = SoftAuthenticator::es256(&state);
+    other_rp.rp_id = "evil.example".to_string();
+    let credential = other_rp.create(&options);
+    complete_registration(&server, &alice_token, "Other RP", credential)
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    // Names are validated before anything else.
+    let options = begin_registration(&server, &alice_token).await;
+    let credential = SoftAuthenticator::es256(&state).create(&options);
+    complete_registration(&server, &alice_token, "   ", credential)
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    let resp = server
+        .get("/api/v1/users/@me/passkeys")
+        .authorization_bearer(&alice_token)
+        .await;
+    assert!(resp.json::<serde_json::Value>()["data"]
+        .as_array()
+        .unwrap()
+        .is_empty());
+
+    common::cleanup_test_user(&state.db, &alice.id).await;
+    common::cleanup_test_user(&state.db, &bob.id).await;
+}
+
+#[tokio::test]
+async fn rename_and_delete_passkeys() {
+    let (app, state) = common::test_app().await;
+    let alice = common::create_test_user(&state.db, "passkey_password_4").await;
+    let bob = common::create_test_user(&state.db, "passkey_password_4").await;
+    let alice_token =
+        common::store_test_access_token(state.kv.as_ref(), &alice.id, &["openid"]).await;
+    let bob_token = common::store_test_access_token(state.kv.as_ref(), &bob.id, &["openid"]).await;
+    let server = TestServer::new(app).unwrap();
+
+    let mut authenticator = SoftAuthenticator::es256(&state);
+    let passkey_id = register(&server, &alice_token, &mut authenticator, "Laptop").await;
+
+    let resp = server
+        .patch(&format!("/api/v1/users/@me/passkeys/{passkey_id}"))
+        .authorization_bearer(&alice_token)
+        .json(&serde_json::json!({ "name": "Work laptop" }))
+        .await;
+    resp.assert_status_ok();
+    assert_eq!(resp.json::<serde_json::Value>()["name"], "Work laptop");
+
+    // Other users can't see or touch it.
+    server
+        .patch(&format!("/api/v1/users/@me/passkeys/{passkey_id}"))
+        .authorization_bearer(&bob_token)
+        .json(&serde_json::json!({ "name": "Mine now" }))
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+    server
+        .delete(&format!("/api/v1/users/@me/passkeys/{passkey_id}"))
+        .authorization_bearer(&bob_token)
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+
+    server
+        .delete(&format!("/api/v1/users/@me/passkeys/{passkey_id}"))
+        .authorization_bearer(&alice_token)
+        .await
+        .assert_status(StatusCode::NO_CONTENT);
+
+    // A removed passkey can no longer sign in.
+    let options = begin_sign_in(&server).await;
+    let assertion = authenticator.get(&options);
+    submit_passkey(&server, &assertion)
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+
+    common::cleanup_test_user(&state.db, &alice.id).await;
+    common::cleanup_test_user(&state.db, &bob.id).await;
+}
+
+// =========================================================================
+// Passwordless sign-in
+// =========================================================================
+
+#[tokio::test]
+async fn passkey_sign_in_issues_code_and_tokens() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "passkey_password_5").await;
+    let token = common::store_test_access_token(state.kv.as_ref(), &user.id, &["openid"]).await;
+    let server = TestServer::new(app).unwrap();
+
+    let mut authenticator = SoftAuthenticator::es256(&state);
+    register(&server, &token, &mut authenticator, "Laptop").await;
+
+    // The login page offers passkeys.
+    let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(CODE_VERIFIER.as_bytes()));
+    let page = server
+        .get(&format!(
+            "/oidc/authorize?response_type=code&client_id=voxora-web&redirect_uri={REDIRECT_URI}\
+             &code_challenge={challenge}&code_challenge_method=S256"
+        ))
+        .await
+        .text();
+    assert!(page.contains("/oidc/authorize/passkey/begin"));
+
+    let options = begin_sign_in(&server).await;
+    assert_eq!(options["rpId"], state.config.webauthn_rp_id.as_str());
+    assert_eq!(options["userVerification"], "required");
+    assert!(options["allowCredentials"].as_array().unwrap().is_empty());
+
+    let assertion = authenticator.get(&options);
+    let resp = submit_passkey(&server, &assertion).await;
+    resp.assert_status(StatusCode::SEE_OTHER);
+    let location = resp.header("location").to_str().unwrap().to_string();
+    assert!(location.starts_with(REDIRECT_URI));
+    assert!(location.contains("state=s1"));
+    let code = location
+        .split("code=")
+        .nth(1)
+        .unwrap()
+        .split('&')
+        .next()
+        .unwrap();
+
+    let resp = server
+        .post("/oidc/token")
+        .form(&[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", REDIRECT_URI),
+            ("code_verifier", CODE_VERIFIER),
+            ("client_id", "voxora-web"),
+        ])
+        .await;
+    resp.assert_status_ok();
+    let id_token = resp.json::<serde_json::Value>()["id_token"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let mut validation = jsonwebtoken::Validation::new(jsonwebtoken::Algorithm::EdDSA);
+    validation.set_audience(&["voxora-web"]);
+    validation.set_issuer(&[&state.config.hub_domain]);
+    let claims = jsonwebtoken::decode::<hub_api::auth::tokens::IdTokenClaims>(
+        &id_token,
+        &state.keys.active().decoding,
+        &validation,
+    )
+    .unwrap()
+    .claims;
+    assert_eq!(claims.sub, user.id);
+    assert_eq!(claims.amr, vec!["hwk", "mfa"]);
+
+    let list: serde_json::Value = server
+        .get("/api/v1/users/@me/passkeys")
+        .authorization_bearer(&token)
+        .await
+        .json();
+    assert!(list["data"][0]["last_used_at"].is_string());
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
+
+#[tokio::test]
+async fn passkey_sign_in_works_for_ed25519_and_mfa_accounts() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "passkey_password_6").await;
+    let token = common::store_test_access_token(state.kv.as_ref(), &user.id, &["openid"]).await;
+    let server = TestServer::new(app).unwrap();
+
+    // Passkeys skip the TOTP step: user verification already makes them MFA.
+    let secret: serde_json::Value = server
+        .post("/api/v1/users/@me/mfa/totp")
+        .authorization_bearer(&token)
+        .await
+        .json();
+    let secret = secret["secret"].as_str().unwrap();
+    let now = chrono::Utc::now().timestamp() as u64;
+    let code = hub_api::auth::mfa::totp_code(secret, hub_api::auth::mfa::totp_step(now)).unwrap();
+    server
+        .post("/api/v1/users/@me/mfa/totp/confirm")
+        .authorization_bearer(&token)
+        .json(&serde_json::json!({ "code": code }))
+        .await
+        .assert_status_ok();
+
+    let mut authenticator = SoftAuthenticator::ed25519(&state);
+    register(&server, &token, &mut authenticator, "Security key").await;
+
+    let options = begin_sign_in(&server).await;
+    let assertion = authenticator.get(&options);
+    submit_passkey(&server, &assertion)
+        .await
+        .assert_status(StatusCode::SEE_OTHER);
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
+
+#[tokio::test]
+async fn passkey_sign_in_rejects_replays_and_counter_regression() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "passkey_password_7").await;
+    let token = common::store_test_access_token(state.kv.as_ref(), &user.id, &["openid"]).await;
+    let server = TestServer::new(app).unwrap();
+
+    let mut authenticator = SoftAuthenticator::es256(&state);
+    register(&server, &token, &mut authenticator, "Laptop").await;
+
+    let options = begin_sign_in(&server).await;
+    let assertion = authenticator.get(&options);
+    submit_passkey(&server, &assertion)
+        .await
+        .assert_status(StatusCode::SEE_OTHER);
+
+    // The challenge is consumed.
+    submit_passkey(&server, &assertion)
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+
+    // A cloned authenticator replaying an old counter value is refused.
+    authenticator.sign_count = 0;
+    let options = begin_sign_in(&server).await;
+    let assertion = authenticator.get(&options);
+    let resp = submit_passkey(&server, &assertion).await;
+    resp.assert_status(StatusCode::UNAUTHORIZED);
+    assert!(resp.text().contains("couldn't be verified"));
+
+    // Moving the counter forward works again.
+    authenticator.sign_count = 10;
+    let options = begin_sign_in(&server).await;
+    let assertion = authenticator.get(&options);
+    submit_passkey(&server, &assertion)
+        .await
+        .assert_status(StatusCode::SEE_OTHER);
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
+
+#[tokio::test]
+async fn passkey_sign_in_requires_valid_signature_and_verification() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "passkey_password_8").await;
+    let token = common::store_test_access_token(state.kv.as_ref(), &user.id, &["openid"]).await;
+    let server = TestServer::new(app).unwrap();
+
+    let mut authenticator = SoftAuthenticator::es256(&state);
+    register(&server, &token, &mut authenticator, "Laptop").await;
+
+    // User present but not verified.
+    let options = begin_sign_in(&server).await;
+    authenticator.sign_count += 1;
+    let assertion = authenticator.assert_with_flags(options["challenge"].as_str().unwrap(), 0x01);
+    submit_passkey(&server, &assertion)
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+
+    // Signed by a different key.
+    let options = begin_sign_in(&server).await;
+    let mut impostor = SoftAuthenticator::es256(&state);
+    impostor.credential_id = authenticator.credential_id.clone();
+    impostor.sign_count = 100;
+    let assertion = impostor.get(&options);
+    submit_passkey(&server, &assertion)
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+
+    // A registration challenge can't be used to sign in.
+    let options = begin_registration(&server, &token).await;
+    let assertion = authenticator.assert_with_flags(options["challenge"].as_str().unwrap(), 0x05);
+    submit_passkey(&server, &assertion)
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+
+    // Garbage credential JSON.
+    submit_passkey(&server, &serde_json::json!("not a credential"))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
@@ -0,0 +1,220 @@
+//! Integration tests for password reset: requesting a link, setting a new
+//! password with it, and the sessions it revokes.
+
+mod common;
+
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use hub_api::db::pool::DbPool;
+use hub_api::db::schema::{sessions, users};
+
+/// Pull the `token` query parameter out of the link in a reset email.
+fn token_from(text: &str) -> String {
+    let start = text.find("token=").expect("link in email") + "token=".len();
+    text[start..].split_whitespace().next().unwrap().to_string()
+}
+
+async fn create_session(db: &DbPool, user_id: &str) -> String {
+    let id = voxora_common::id::prefixed_ulid(voxora_common::id::prefix::SESSION);
+    let mut conn = db.get().await.expect("pool");
+    diesel::insert_into(sessions::table)
+        .values(&hub_api::models::session::NewSession {
+            id: id.clone(),
+            user_id: user_id.to_string(),
+            refresh_token: hub_api::auth::tokens::generate_refresh_token(),
+            ip_address: None,
+            user_agent: None,
+            client_id: None,
+            expires_at: chrono::Utc::now() + chrono::Duration::days(1),
+            family_id: id.clone(),
+            scopes: vec!["openid".to_string()],
+        })
+        .execute(&mut conn)
+        .await
+        .expect("insert session");
+    id
+}
+
+async fn session_revoked(db: &DbPool, session_id: &str) -> bool {
+    let mut conn = db.get().await.expect("pool");
+    sessions::table
+        .find(session_id)
+        .select(sessions::revoked)
+        .first(&mut conn)
+        .await
+        .expect("session")
+}
+
+fn password_matches(hash: &str, password: &str) -> bool {
+    use argon2::Argon2;
+    use password_hash::{PasswordHash, PasswordVerifier};
+    let parsed = PasswordHash::new(hash).unwrap();
+    Argon2::default()
+        .verify_password(password.as_bytes(), &parsed)
+        .is_ok()
+}
+
+async fn password_hash(db: &DbPool, user_id: &str) -> String {
+    let mut conn = db.get().await.expect("pool");
+    users::table
+        .find(user_id)
+        .select(users::password_hash)
+        .first::<Option<String>>(&mut conn)
+        .await
+        .expect("user")
+        .expect("password set")
+}
+
+#[tokio::test]
+async fn reset_changes_password_and_revokes_sessions() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "old_password_1").await;
+    let session_id = create_session(&state.db, &user.id).await;
+    let server = TestServer::new(app).unwrap();
+
+    server
+        .post("/api/v1/password-reset")
+        .json(&serde_json::json!({ "email": user.email.to_uppercase() }))
+        .await
+        .assert_status(StatusCode::ACCEPTED);
+
+    let sent = common::wait_for_emails(&user.email, 1).await;
+    assert_eq!(sent.len(), 1);
+    assert!(sent[0].subject.contains("Reset"));
+    assert!(sent[0].text.contains(&format!(
+        "{}/reset-password?token=hpr_",
+        state.config.web_url
+    )));
+    let token = token_from(&sent[0].text);
+
+    server
+        .post("/api/v1/password-reset/confirm")
+        .json(&serde_json::json!({ "token": token, "password": "new_password_1" }))
+        .await
+        .assert_status(StatusCode::NO_CONTENT);
+
+    let hash = password_hash(&state.db, &user.id).await;
+    assert!(password_matches(&hash, "new_password_1"));
+    assert!(!password_matches(&hash, "old_password_1"));
+    assert!(session_revoked(&state.db, &session_id).await);
+
+    // Tokens are single-use.
+    server
+        .post("/api/v1/password-reset/confirm")
+        .json(&serde_json::json!({ "token": token, "password": "another_password" }))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
+
+#[tokio::test]
+async fn unknown_email_gets_the_same_response() {
+    let (app, _state) = common::test_app().await;
+    let server = TestServer::new(app).unwrap();
+
+    let email = format!("nobody_{}@example.com", rand::random::<u32>());
+    let resp = server
+        .post("/api/v1/password-reset")
+        .json(&serde_json::json!({ "email": email }))
+        .await;
+    resp.assert_status(StatusCode::ACCEPTED);
+    assert!(resp.text().is_empty());
+    tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+    assert!(common::sent_emails(&email).is_empty());
+}
+
+#[tokio::test]
+async fn requesting_again_is_throttled_silently() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "old_password_1").await;
+    let server = TestServer::new(app).unwrap();
+
+    let (a, b) = tokio::join!(
+        server
+            .post("/api/v1/password-reset")
+            .json(&serde_json::json!({ "email": user.email })),
+        server
+            .post("/api/v1/password-reset")
+            .json(&serde_json::json!({ "email": user.email })),
+    );
+    a.assert_status(StatusCode::ACCEPTED);
+    b.assert_status(StatusCode::ACCEPTED);
+    common::wait_for_emails(&user.email, 1).await;
+    tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+    assert_eq!(common::sent_emails(&user.email).len(), 1);
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
+
+#[tokio::test]
+async fn short_password_does_not_consume_the_token() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "old_password_1").await;
+    let server = TestServer::new(app).unwrap();
+
+    server
+        .post("/api/v1/password-reset")
+        .json(&serde_json::json!({ "email": user.email }))
+        .await
+        .assert_status(StatusCode::ACCEPTED);
+    let token = token_from(&common::wait_for_emails(&user.email, 1).await[0].text);
+
+    let resp = server
+        .post("/api/v1/password-reset/confirm")
+        .json(&serde_json::json!({ "token": token, "password": "short" }))
+        .await;
+    resp.assert_status(StatusCode::BAD_REQUEST);
+    let body: serde_json::Value = resp.json();
+    assert_eq!(body["error"]["code"], "VALIDATION_ERROR");
+
+    server
+        .post("/api/v1/password-reset/confirm")
+        .json(&serde_json::json!({ "token": token, "password": "long_enough_password" }))
+        .await
+        .assert_status(StatusCode::NO_CONTENT);
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
+
+#[tokio::test]
+async fn concurrent_confirms_use_the_token_once() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "old_password_1").await;
+    let server = TestServer::new(app).unwrap();
+
+    server
+        .post("/api/v1/password-reset")
+        .json(&serde_json::json!({ "email": user.email }))
+        .await
+        .assert_status(StatusCode::ACCEPTED);
+    let token = token_from(&common::wait_for_emails(&user.email, 1).await[0].text);
+
+    let (a, b) = tokio::join!(
+        server
+            .post("/api/v1/password-reset/confirm")
+            .json(&serde_json::json!({ "token": token, "password": "first_new_password" })),
+        server
+            .post("/api/v1/password-reset/confirm")
+            .json(&serde_json::json!({ "token": token, "password": "second_new_password" })),
+    );
+    let mut statuses = [a.status_code(), b.status_code()];
+    statuses.sort();
+    assert_eq!(statuses, [StatusCode::NO_CONTENT, StatusCode::BAD_REQUEST]);
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
+
+#[tokio::test]
+async fn unknown_token_is_rejected() {
+    let (app, _state) = common::test_app().await;
+    let server = TestServer::new(app).unwrap();
+
+    server
+        .post("/api/v1/password-reset/confirm")
+        .json(&serde_json::json!({ "token": "hpr_not_a_real_token", "password": "new_password_1" }))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
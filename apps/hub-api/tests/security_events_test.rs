@@ -0,0 +1,258 @@
+//! Integration tests for the authentication event log: events recorded by
+//! sign-in, token and SIA flows, the user-facing listing, and retention.
+
+mod common;
+
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use hub_api::auth::events;
+use hub_api::db::schema::auth_events;
+use sha2::{Digest, Sha256};
+
+const VERIFIER: &str = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+const REDIRECT_URI: &str = "http://localhost:5173/callback";
+
+/// Submit the login form from the given `User-Agent` and forwarded address.
+async fn submit_login(
+    server: &TestServer,
+    login: &str,
+    password: &str,
+    user_agent: &str,
+    ip: &str,
+) -> axum_test::TestResponse {
+    let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(VERIFIER.as_bytes()));
+    server
+        .post("/oidc/authorize")
+        .add_header("user-agent", user_agent)
+        .add_header("x-forwarded-for", ip)
+        .content_type("application/x-www-form-urlencoded")
+        .bytes(
+            format!(
+                "response_type=code&client_id=voxora-web&redirect_uri={REDIRECT_URI}\
+                 &scope=openid+profile+pods&code_challenge={challenge}\
+                 &code_challenge_method=S256&login={login}&password={password}"
+            )
+            .into(),
+        )
+        .await
+}
+
+/// Sign in and exchange the code. Returns the access and refresh tokens.
+async fn sign_in(server: &TestServer, user: &common::TestUser) -> (String, String) {
+    let resp = submit_login(
+        server,
+        &user.username,
+        &user.password,
+        "Laptop/1.0",
+        "203.0.113.7",
+    )
+    .await;
+    resp.assert_status(StatusCode::SEE_OTHER);
+    let location = resp.header("location").to_str().unwrap().to_string();
+    let code = location
+        .split("code=")
+        .nth(1)
+        .unwrap()
+        .split('&')
+        .next()
+        .unwrap()
+        .to_string();
+
+    let resp = server
+        .post("/oidc/token")
+        .content_type("application/x-www-form-urlencoded")
+        .bytes(
+            format!(
+                "grant_type=authorization_code&code={code}&redirect_uri={REDIRECT_URI}\
+                 &code_verifier={VERIFIER}&client_id=voxora-web"
+            )
+            .into(),
+        )
+        .await;
+    resp.assert_status_ok();
+    let body: serde_json::Value = resp.json();
+    (
+        body["access_token"].as_str().unwrap().to_string(),
+        body["refresh_token"].as_str().unwrap().to_string(),
+    )
+}
+
+async fn list(server: &TestServer, access: &str, query: &str) -> serde_json::Value {
+    let resp = server
+        .get(&format!("/api/v1/users/@me/security-events{query}"))
+        .authorization_bearer(access)
+        .await;
+    resp.assert_status_ok();
+    resp.json()
+}
+
+fn event_names(body: &serde_json::Value) -> Vec<String> {
+    body["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["event"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn sign_in_records_login_and_token_events() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "events_password_1").await;
+    let server = common::proxied_server(app);
+
+    let resp = submit_login(
+        &server,
+        &user.username,
+        "wrong_password",
+        "Phone/2.0",
+        "198.51.100.4",
+    )
+    .await;
+    resp.assert_status(StatusCode::UNAUTHORIZED);
+
+    let (access, refresh) = sign_in(&server, &user).await;
+    server
+        .post("/oidc/token")
+        .content_type("application/x-www-form-urlencoded")
+        .bytes(
+            format!("grant_type=refresh_token&refresh_token={refresh}&client_id=voxora-web").into(),
+        )
+        .await
+        .assert_status_ok();
+
+    let body = list(&server, &access, "").await;
+    // Newest first.
+    assert_eq!(
+        event_names(&body),
+        [
+            events::TOKEN_REFRESHED,
+            events::TOKEN_ISSUED,
+            events::LOGIN_SUCCESS,
+            events::LOGIN_FAILURE,
+        ]
+    );
+    assert_eq!(body["has_more"], false);
+
+    let failure = &body["data"][3];
+    assert_eq!(failure["details"]["reason"], "invalid_password");
+    assert_eq!(failure["ip_address"], "198.51.100.4");
+    assert_eq!(failure["user_agent"], "Phone/2.0");
+    assert_eq!(failure["client_id"], "voxora-web");
+
+    let login = &body["data"][2];
+    assert_eq!(login["details"]["amr"], serde_json::json!(["pwd"]));
+    assert_eq!(login["ip_address"], "203.0.113.7");
+
+    let body = list(&server, &access, "?event=login.failure").await;
+    assert_eq!(event_names(&body), [events::LOGIN_FAILURE]);
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
+
+#[tokio::test]
+async fn sia_issuance_records_the_pod() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "events_password_2").await;
+    let pod_id = common::create_test_pod(&state.db, &user.id).await;
+    let server = common::proxied_server(app);
+
+    let (access, _) = sign_in(&server, &user).await;
+    server
+        .post("/api/v1/oidc/sia")
+        .authorization_bearer(&access)
+        .json(&serde_json::json!({ "pod_id": pod_id }))
+        .await
+        .assert_status_ok();
+
+    let body = list(&server, &access, "?event=sia.issued").await;
+    assert_eq!(event_names(&body), [events::SIA_ISSUED]);
+    assert_eq!(body["data"][0]["pod_id"], pod_id.as_str());
+
+    common::cleanup_test_pod(&state.db, &pod_id).await;
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
+
+#[tokio::test]
+async fn listing_is_paginated_and_scoped_to_the_user() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "events_password_3").await;
+    let other = common::create_test_user(&state.db, "events_password_4").await;
+    let server = common::proxied_server(app);
+
+    let client = hub_api::auth::middleware::ClientInfo::default();
+    for _ in 0..3 {
+        events::record(
+            &state.db,
+            &client,
+            events::AuthEvent::new(events::LOGIN_FAILURE).user(&user.id),
+        )
+        .await;
+    }
+    events::record(
+        &state.db,
+        &client,
+        events::AuthEvent::new(events::LOGIN_FAILURE).user(&other.id),
+    )
+    .await;
+
+    let token = common::store_test_access_token(state.kv.as_ref(), &user.id, &["openid"]).await;
+    let first = list(&server, &token, "?limit=2").await;
+    assert_eq!(first["data"].as_array().unwrap().len(), 2);
+    assert_eq!(first["has_more"], true);
+
+    let cursor = first["data"][1]["id"].as_str().unwrap();
+    let rest = list(&server, &token, &format!("?limit=2&before={cursor}")).await;
+    assert_eq!(rest["data"].as_array().unwrap().len(), 1);
+    assert_eq!(rest["has_more"], false);
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+    common::cleanup_test_user(&state.db, &other.id).await;
+}
+
+#[tokio::test]
+async fn events_past_retention_are_purged() {
+    let state = common::test_state().await;
+    let user = common::create_test_user(&state.db, "events_password_5").await;
+
+    let client = hub_api::auth::middleware::ClientInfo::default();
+    for _ in 0..2 {
+        events::record(
+            &state.db,
+            &client,
+            events::AuthEvent::new(events::LOGIN_SUCCESS).user(&user.id),
+        )
+        .await;
+    }
+
+    let mut conn = state.db.get().await.unwrap();
+    let old_id: String = auth_events::table
+        .filter(auth_events::user_id.eq(&user.id))
+        .select(auth_events::id)
+        .first(&mut conn)
+        .await
+        .unwrap();
+    diesel::update(auth_events::table.find(&old_id))
+        .set(auth_events::created_at.eq(Utc::now() - Duration::days(91)))
+        .execute(&mut conn)
+        .await
+        .unwrap();
+
+    let purged = events::purge_expired(&state.db, 90).await.unwrap();
+    assert!(purged >= 1);
+
+    let remaining: Vec<String> = auth_events::table
+        .filter(auth_events::user_id.eq(&user.id))
+        .select(auth_events::id)
+        .load(&mut conn)
+        .await
+        .unwrap();
+    assert_eq!(remaining.len(), 1);
+    assert_ne!(remaining[0], old_id);
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
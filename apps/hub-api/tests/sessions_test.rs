@@ -0,0 +1,283 @@
+//! Integration tests for session management: listing signed-in devices and
+//! revoking one or all of the others.
+
+mod common;
+
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use sha2::{Digest, Sha256};
+
+struct Tokens {
+    access: String,
+    refresh: String,
+}
+
+/// Sign in through the authorization code flow from a client with the given
+/// `User-Agent` and forwarded address.
+async fn sign_in(
+    server: &TestServer,
+    user: &common::TestUser,
+    user_agent: &str,
+    ip: &str,
+) -> Tokens {
+    let verifier = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+    let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+    let redirect_uri = "http://localhost:5173/callback";
+
+    let resp = server
+        .post("/oidc/authorize")
+        .content_type("application/x-www-form-urlencoded")
+        .bytes(
+            format!(
+                "response_type=code&client_id=voxora-web&redirect_uri={redirect_uri}\
+                 &scope=openid+profile&code_challenge={challenge}\
+                 &code_challenge_method=S256&login={}&password={}",
+                user.username, user.password
+            )
+            .into(),
+        )
+        .await;
+    resp.assert_status(StatusCode::SEE_OTHER);
+    let location = resp.header("location").to_str().unwrap().to_string();
+    let code = location
+        .split("code=")
+        .nth(1)
+        .unwrap()
+        .split('&')
+        .next()
+        .unwrap()
+        .to_string();
+
+    let resp = server
+        .post("/oidc/token")
+        .add_header("user-agent", user_agent)
+        .add_header("x-forwarded-for", format!("{ip}, 10.0.0.1"))
+        .content_type("application/x-www-form-urlencoded")
+        .bytes(
+            format!(
+                "grant_type=authorization_code&code={code}&redirect_uri={redirect_uri}\
+                 &code_verifier={verifier}&client_id=voxora-web"
+            )
+            .into(),
+        )
+        .await;
+    resp.assert_status_ok();
+    let body: serde_json::Value = resp.json();
+    Tokens {
+        access: body["access_token"].as_str().unwrap().to_string(),
+        refresh: body["refresh_token"].as_str().unwrap().to_string(),
+    }
+}
+
+async fn list(server: &TestServer, access: &str) -> Vec<serde_json::Value> {
+    let resp = server
+        .get("/api/v1/users/@me/sessions")
+        .authorization_bearer(access)
+        .await;
+    resp.assert_status_ok();
+    let body: serde_json::Value = resp.json();
+    body["data"].as_array().unwrap().clone()
+}
+
+async fn refresh(server: &TestServer, refresh_token: &str) -> StatusCode {
+    server
+        .post("/oidc/token")
+        .content_type("application/x-www-form-urlencoded")
+        .bytes(
+            format!("grant_type=refresh_token&refresh_token={refresh_token}&client_id=voxora-web")
+                .into(),
+        )
+        .await
+        .status_code()
+}
+
+#[tokio::test]
+async fn list_shows_device_info_and_current_session() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "sessions_password_1").await;
+    let server = common::proxied_server(app);
+
+    let laptop = sign_in(&server, &user, "Laptop/1.0", "203.0.113.7").await;
+    let _phone = sign_in(&server, &user, "Phone/2.0", "198.51.100.4").await;
+
+    let sessions = list(&server, &laptop.access).await;
+    assert_eq!(sessions.len(), 2);
+
+    let current: Vec<_> = sessions.iter().filter(|s| s["current"] == true).collect();
+    assert_eq!(current.len(), 1);
+    assert_eq!(current[0]["user_agent"], "Laptop/1.0");
+    assert_eq!(current[0]["ip_address"], "203.0.113.7");
+    assert!(current[0].get("refresh_token").is_none());
+
+    let other = sessions.iter().find(|s| s["current"] == false).unwrap();
+    assert_eq!(other["user_agent"], "Phone/2.0");
+    assert_eq!(other["ip_address"], "198.51.100.4");
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
+
+#[tokio::test]
+async fn forwarded_address_is_ignored_unless_the_peer_is_a_trusted_proxy() {
+    let mut state = common::test_state().await;
+    let mut config = (*state.config).clone();
+    config.trusted_proxies.clear();
+    state.config = std::sync::Arc::new(config);
+    let app = hub_api::routes::router().with_state(state.clone());
+    let user = common::create_test_user(&state.db, "sessions_password_1").await;
+    let server = common::proxied_server(app);
+
+    let laptop = sign_in(&server, &user, "Laptop/1.0", "203.0.113.7").await;
+
+    let sessions = list(&server, &laptop.access).await;
+    assert_eq!(sessions[0]["ip_address"], "127.0.0.1");
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
+
+#[tokio::test]
+async fn revoking_a_session_invalidates_its_tokens_immediately() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "sessions_password_1").await;
+    let server = common::proxied_server(app);
+
+    let laptop = sign_in(&server, &user, "Laptop/1.0", "203.0.113.7").await;
+    let phone = sign_in(&server, &user, "Phone/2.0", "198.51.100.4").await;
+
+    let phone_id = list(&server, &phone.access)
+        .await
+        .into_iter()
+        .find(|s| s["current"] == true)
+        .unwrap()["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    server
+        .delete(&format!("/api/v1/users/@me/sessions/{phone_id}"))
+        .authorization_bearer(&laptop.access)
+        .await
+        .assert_status(StatusCode::NO_CONTENT);
+
+    server
+        .get("/api/v1/users/@me")
+        .authorization_bearer(&phone.access)
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        refresh(&server, &phone.refresh).await,
+        StatusCode::BAD_REQUEST
+    );
+
+    // The laptop is unaffected.
+    server
+        .get("/api/v1/users/@me")
+        .authorization_bearer(&laptop.access)
+        .await
+        .assert_status_ok();
+    assert_eq!(list(&server, &laptop.access).await.len(), 1);
+
+    // Already revoked.
+    server
+        .delete(&format!("/api/v1/users/@me/sessions/{phone_id}"))
+        .authorization_bearer(&laptop.access)
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
+
+#[tokio::test]
+async fn cannot_revoke_another_users_session() {
+    let (app, state) = common::test_app().await;
+    let alice = common::create_test_user(&state.db, "sessions_password_1").await;
+    let bob = common::create_test_user(&state.db, "sessions_password_1").await;
+    let server = common::proxied_server(app);
+
+    let alice_tokens = sign_in(&server, &alice, "Laptop/1.0", "203.0.113.7").await;
+    let bob_tokens = sign_in(&server, &bob, "Laptop/1.0", "203.0.113.8").await;
+    let bob_session = list(&server, &bob_tokens.access).await[0]["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    server
+        .delete(&format!("/api/v1/users/@me/sessions/{bob_session}"))
+        .authorization_bearer(&alice_tokens.access)
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+    server
+        .get("/api/v1/users/@me")
+        .authorization_bearer(&bob_tokens.access)
+        .await
+        .assert_status_ok();
+
+    common::cleanup_test_user(&state.db, &alice.id).await;
+    common::cleanup_test_user(&state.db, &bob.id).await;
+}
+
+#[tokio::test]
+async fn revoke_others_keeps_the_current_session() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "sessions_password_1").await;
+    let server = common::proxied_server(app);
+
+    let laptop = sign_in(&server, &user, "Laptop/1.0", "203.0.113.7").await;
+    let phone = sign_in(&server, &user, "Phone/2.0", "198.51.100.4").await;
+    let tablet = sign_in(&server, &user, "Tablet/3.0", "198.51.100.5").await;
+
+    server
+        .delete("/api/v1/users/@me/sessions")
+        .authorization_bearer(&laptop.access)
+        .await
+        .assert_status(StatusCode::NO_CONTENT);
+
+    for other in [&phone, &tablet] {
+        server
+            .get("/api/v1/users/@me")
+            .authorization_bearer(&other.access)
+            .await
+            .assert_status(StatusCode::UNAUTHORIZED);
+    }
+
+    let remaining = list(&server, &laptop.access).await;
+    assert_eq!(remaining.len(), 1);
+    assert_eq!(remaining[0]["current"], true);
+    assert_eq!(refresh(&server, &laptop.refresh).await, StatusCode::OK);
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
+
+#[tokio::test]
+async fn refreshed_session_stays_current() {
+    let (app, state) = common::test_app().await;
+    let user = common::create_test_user(&state.db, "sessions_password_1").await;
+    let server = common::proxied_server(app);
+
+    let laptop = sign_in(&server, &user, "Laptop/1.0", "203.0.113.7").await;
+
+    let resp = server
+        .post("/oidc/token")
+        .add_header("x-forwarded-for", "203.0.113.7")
+        .content_type("application/x-www-form-urlencoded")
+        .bytes(
+            format!(
+                "grant_type=refresh_token&refresh_token={}&client_id=voxora-web",
+                laptop.refresh
+            )
+            .into(),
+        )
+        .await;
+    resp.assert_status_ok();
+    let body: serde_json::Value = resp.json();
+    let access = body["access_token"].as_str().unwrap();
+
+    // Device info carries over from the session that was rotated.
+    let sessions = list(&server, access).await;
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0]["current"], true);
+    assert_eq!(sessions[0]["user_agent"], "Laptop/1.0");
+    assert_eq!(sessions[0]["ip_address"], "203.0.113.7");
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
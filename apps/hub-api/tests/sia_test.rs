@@ -57,7 +57,7 @@ async fn issue_sia_returns_valid_jwt() {
 
     let decoded = jsonwebtoken::decode::<hub_api::auth::sia::SiaClaims>(
         sia_jwt,
-        &state.keys.decoding,
+        &state.keys.active().decoding,
         &validation,
     )
     .expect("SIA JWT must be valid");
@@ -74,7 +74,7 @@ async fn issue_sia_returns_valid_jwt() {
     // Verify header has correct typ
     let header = jsonwebtoken::decode_header(sia_jwt).unwrap();
     assert_eq!(header.typ.as_deref(), Some("voxora-sia+jwt"));
-    assert_eq!(header.kid.as_deref(), Some(state.keys.kid.as_str()));
+    assert_eq!(header.kid.as_deref(), Some(state.keys.active().kid.as_str()));
 
     // TTL should be ~5 minutes
     let ttl = claims.exp - claims.iat;
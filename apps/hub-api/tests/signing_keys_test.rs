@@ -0,0 +1,144 @@
+//! Integration tests for signing key rotation through the admin API.
+
+mod common;
+
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use hub_api::auth::keys::Keyring;
+
+async fn jwks_kids(server: &TestServer) -> Vec<String> {
+    let body: serde_json::Value = server.get("/oidc/.well-known/jwks.json").await.json();
+    body["keys"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|k| k["kid"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn staff_rotate_keys_without_breaking_outstanding_tokens() {
+    let dir = std::env::temp_dir().join(format!("voxora-keyring-{}", rand::random::<u64>()));
+    let mut state = common::test_state().await;
+    state.keys = Arc::new(Keyring::load_dir(&dir, None).unwrap());
+    let app = hub_api::routes::router().with_state(state.clone());
+    let server = TestServer::new(app).unwrap();
+
+    let admin = common::create_test_user(&state.db, "keys_password_1").await;
+    common::make_staff(&state.db, &admin.id).await;
+    let admin_access =
+        common::store_test_access_token(state.kv.as_ref(), &admin.id, &["openid", "pods"]).await;
+    let pod_id = common::create_test_pod(&state.db, &admin.id).await;
+
+    // Active and next keys are both published up front.
+    let before = server
+        .get("/api/v1/admin/signing-keys")
+        .authorization_bearer(&admin_access)
+        .await
+        .json::<serde_json::Value>();
+    assert_eq!(before["data"][0]["status"], "active");
+    assert_eq!(before["data"][1]["status"], "next");
+    let old_kid = before["data"][0]["kid"].as_str().unwrap().to_string();
+    let next_kid = before["data"][1]["kid"].as_str().unwrap().to_string();
+    assert_eq!(
+        jwks_kids(&server).await,
+        vec![old_kid.clone(), next_kid.clone()]
+    );
+
+    // An SIA issued before the rotation...
+    let sia = server
+        .post("/api/v1/oidc/sia")
+        .authorization_bearer(&admin_access)
+        .json(&serde_json::json!({ "pod_id": pod_id }))
+        .await
+        .json::<serde_json::Value>()["sia"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    assert_eq!(
+        jsonwebtoken::decode_header(&sia).unwrap().kid.as_deref(),
+        Some(old_kid.as_str())
+    );
+
+    let resp = server
+        .post("/api/v1/admin/signing-keys/rotate")
+        .authorization_bearer(&admin_access)
+        .await;
+    resp.assert_status_ok();
+    let after: serde_json::Value = resp.json();
+    assert_eq!(after["data"][0]["kid"], next_kid);
+    assert_eq!(after["data"][0]["status"], "active");
+    assert_eq!(after["data"][1]["status"], "next");
+    assert_eq!(after["data"][2]["kid"], old_kid);
+    assert_eq!(after["data"][2]["status"], "retired");
+    assert!(after["data"][2]["expires_at"].is_string());
+
+    // ...still verifies against the published JWKS.
+    let kids = jwks_kids(&server).await;
+    assert!(kids.contains(&old_kid));
+    assert_eq!(kids.len(), 3);
+    let retired = state
+        .keys
+        .published()
+        .into_iter()
+        .find(|k| k.keys.kid == old_kid)
+        .unwrap();
+    let mut validation = jsonwebtoken::Validation::new(jsonwebtoken::Algorithm::EdDSA);
+    validation.set_audience(&[&pod_id]);
+    jsonwebtoken::decode::<hub_api::auth::sia::SiaClaims>(
+        &sia,
+        &retired.keys.decoding,
+        &validation,
+    )
+    .unwrap();
+
+    // New tokens use the new key.
+    let sia = server
+        .post("/api/v1/oidc/sia")
+        .authorization_bearer(&admin_access)
+        .json(&serde_json::json!({ "pod_id": pod_id }))
+        .await
+        .json::<serde_json::Value>()["sia"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    assert_eq!(
+        jsonwebtoken::decode_header(&sia).unwrap().kid.as_deref(),
+        Some(next_kid.as_str())
+    );
+
+    common::cleanup_test_pod(&state.db, &pod_id).await;
+    common::cleanup_test_user(&state.db, &admin.id).await;
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn rotation_is_staff_only_and_needs_a_key_directory() {
+    let (app, state) = common::test_app().await;
+    let server = TestServer::new(app).unwrap();
+    let user = common::create_test_user(&state.db, "keys_password_1").await;
+    let access = common::store_test_access_token(state.kv.as_ref(), &user.id, &["openid"]).await;
+
+    server
+        .post("/api/v1/admin/signing-keys/rotate")
+        .authorization_bearer(&access)
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+    server
+        .get("/api/v1/admin/signing-keys")
+        .authorization_bearer(&access)
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+
+    // The test Hub derives its key from SIGNING_KEY_SEED.
+    common::make_staff(&state.db, &user.id).await;
+    server
+        .post("/api/v1/admin/signing-keys/rotate")
+        .authorization_bearer(&access)
+        .await
+        .assert_status(StatusCode::CONFLICT);
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+}
@@ -0,0 +1,343 @@
+//! Integration tests for signing in and linking accounts through an upstream
+//! identity provider, against a mock IdP served on a local port.
+
+mod common;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::routing::{get, post};
+use axum::{Form, Json, Router};
+use axum_test::TestServer;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use hub_api::config::UpstreamIdp;
+use hub_api::db::schema::{user_identities, users};
+use hub_api::models::user::User;
+
+const REDIRECT_URI: &str = "http://localhost:5173/callback";
+
+/// Userinfo claims the mock IdP returns, keyed by authorization code.
+type Profiles = Arc<Mutex<HashMap<String, serde_json::Value>>>;
+
+async fn mock_token(
+    State(profiles): State<Profiles>,
+    Form(form): Form<HashMap<String, String>>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let known = profiles.lock().unwrap().contains_key(&form["code"]);
+    if !known || form["client_secret"] != "mock-secret" || form["code_verifier"].len() < 43 {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    Ok(Json(serde_json::json!({
+        "access_token": format!("mock_{}", form["code"]),
+        "token_type": "Bearer",
+    })))
+}
+
+async fn mock_userinfo(
+    State(profiles): State<Profiles>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let code = headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer mock_"))
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+    profiles
+        .lock()
+        .unwrap()
+        .get(code)
+        .cloned()
+        .map(Json)
+        .ok_or(StatusCode::UNAUTHORIZED)
+}
+
+/// Start the mock IdP and a Hub configured to use it.
+async fn setup() -> (TestServer, hub_api::AppState, Profiles) {
+    let profiles: Profiles = Arc::default();
+    let idp = Router::new()
+        .route("/token", post(mock_token))
+        .route("/userinfo", get(mock_userinfo))
+        .with_state(profiles.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, idp).await.unwrap() });
+
+    let mut state = common::test_state().await;
+    let mut config = (*state.config).clone();
+    config.upstream_idps = vec![UpstreamIdp {
+        id: "mock".to_string(),
+        name: "Mock IdP".to_string(),
+        authorization_endpoint: format!("{base}/authorize"),
+        token_endpoint: format!("{base}/token"),
+        userinfo_endpoint: format!("{base}/userinfo"),
+        client_id: "hub".to_string(),
+        client_secret: "mock-secret".to_string(),
+        scopes: "openid profile email".to_string(),
+        subject_claim: "sub".to_string(),
+        username_claim: "preferred_username".to_string(),
+        email_claim: "email".to_string(),
+        name_claim: "name".to_string(),
+    }];
+    state.config = Arc::new(config);
+    let app = hub_api::routes::router().with_state(state.clone());
+    (TestServer::new(app).unwrap(), state, profiles)
+}
+
+/// The `state` parameter of a redirect to the mock IdP.
+fn upstream_state(resp: &axum_test::TestResponse) -> String {
+    let location = resp.header("location").to_str().unwrap().to_string();
+    let url = reqwest::Url::parse(&location).unwrap();
+    assert!(url.path().ends_with("/authorize"));
+    assert_eq!(
+        url.query_pairs().find(|(k, _)| k == "client_id").unwrap().1,
+        "hub"
+    );
+    assert!(url
+        .query_pairs()
+        .any(|(k, v)| k == "code_challenge_method" && v == "S256"));
+    url.query_pairs()
+        .find(|(k, _)| k == "state")
+        .unwrap()
+        .1
+        .into_owned()
+}
+
+/// Sign in through the mock IdP as the account behind `code`.
+async fn sign_in(server: &TestServer, code: &str) -> axum_test::TestResponse {
+    let resp = server
+        .get("/oidc/authorize/upstream/mock")
+        .add_query_param("response_type", "code")
+        .add_query_param("client_id", "voxora-web")
+        .add_query_param("redirect_uri", REDIRECT_URI)
+        .add_query_param("scope", "openid profile")
+        .add_query_param("state", "client-state")
+        .add_query_param("code_challenge", "abc")
+        .add_query_param("code_challenge_method", "S256")
+        .await;
+    resp.assert_status(StatusCode::SEE_OTHER);
+    let state = upstream_state(&resp);
+    server
+        .get("/oidc/upstream/mock/callback")
+        .add_query_param("code", code)
+        .add_query_param("state", &state)
+        .await
+}
+
+fn profile(profiles: &Profiles, code: &str, claims: serde_json::Value) {
+    profiles.lock().unwrap().insert(code.to_string(), claims);
+}
+
+async fn linked_user(state: &hub_api::AppState, subject: &str) -> Option<User> {
+    let mut conn = state.db.get().await.unwrap();
+    users::table
+        .inner_join(user_identities::table)
+        .filter(user_identities::provider.eq("mock"))
+        .filter(user_identities::subject.eq(subject))
+        .select(User::as_select())
+        .first(&mut conn)
+        .await
+        .optional()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn new_accounts_register_and_sign_back_in() {
+    let (server, state, profiles) = setup().await;
+    let existing = common::create_test_user(&state.db, "upstream_password_1").await;
+    let suffix: u32 = rand::random();
+    let subject = format!("mock-{suffix}");
+
+    // The login page offers the provider, carrying the request along.
+    let html = server
+        .get("/oidc/authorize")
+        .add_query_param("response_type", "code")
+        .add_query_param("client_id", "voxora-web")
+        .add_query_param("redirect_uri", REDIRECT_URI)
+        .add_query_param("code_challenge", "abc")
+        .add_query_param("code_challenge_method", "S256")
+        .await
+        .text();
+    assert!(html.contains("Continue with Mock IdP"));
+    assert!(
+        html.contains("/oidc/authorize/upstream/mock?response_type=code&amp;client_id=voxora-web")
+    );
+
+    // The provider's username is taken, so the new account gets a suffix.
+    profile(
+        &profiles,
+        "first",
+        serde_json::json!({
+            "sub": subject,
+            "preferred_username": existing.username,
+            "email": format!("upstream_{suffix}@example.com"),
+            "email_verified": true,
+            "name": "Upstream User",
+        }),
+    );
+    let resp = sign_in(&server, "first").await;
+    resp.assert_status(StatusCode::SEE_OTHER);
+    let location = resp.header("location").to_str().unwrap().to_string();
+    assert!(location.starts_with(&format!("{REDIRECT_URI}?code=hac_")));
+    assert!(location.ends_with("&state=client-state"));
+
+    let user = linked_user(&state, &subject).await.expect("registered");
+    assert!(user
+        .username
+        .starts_with(&format!("{}_", existing.username)));
+    assert_eq!(user.display_name, "Upstream User");
+    assert!(user.email_verified);
+    assert!(user.password_hash.is_none());
+
+    // Signing in again lands on the same account.
+    profile(&profiles, "second", serde_json::json!({ "sub": subject }));
+    sign_in(&server, "second")
+        .await
+        .assert_status(StatusCode::SEE_OTHER);
+    assert_eq!(linked_user(&state, &subject).await.unwrap().id, user.id);
+
+    // A passwordless account can't drop its only sign-in method.
+    let access = common::store_test_access_token(state.kv.as_ref(), &user.id, &["openid"]).await;
+    server
+        .delete("/api/v1/users/@me/identities/mock")
+        .authorization_bearer(&access)
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+    common::cleanup_test_user(&state.db, &existing.id).await;
+}
+
+#[tokio::test]
+async fn existing_emails_are_not_taken_over() {
+    let (server, state, profiles) = setup().await;
+    let victim = common::create_test_user(&state.db, "upstream_password_1").await;
+    let subject = format!("mock-{}", rand::random::<u32>());
+
+    profile(
+        &profiles,
+        "takeover",
+        serde_json::json!({
+            "sub": subject,
+            "preferred_username": "someone",
+            "email": victim.email.to_uppercase(),
+            "email_verified": true,
+        }),
+    );
+    let resp = sign_in(&server, "takeover").await;
+    resp.assert_status(StatusCode::CONFLICT);
+    assert!(resp
+        .text()
+        .contains("An account with this email already exists"));
+    assert!(linked_user(&state, &subject).await.is_none());
+
+    // Cancelling at the provider, or replaying a finished round trip, fails cleanly.
+    let resp = server
+        .get("/oidc/authorize/upstream/mock")
+        .add_query_param("response_type", "code")
+        .add_query_param("client_id", "voxora-web")
+        .add_query_param("redirect_uri", REDIRECT_URI)
+        .add_query_param("code_challenge", "abc")
+        .add_query_param("code_challenge_method", "S256")
+        .await;
+    let upstream = upstream_state(&resp);
+    let resp = server
+        .get("/oidc/upstream/mock/callback")
+        .add_query_param("error", "access_denied")
+        .add_query_param("state", &upstream)
+        .await;
+    resp.assert_status(StatusCode::UNAUTHORIZED);
+    assert!(resp.text().contains("Signing in with Mock IdP didn't work"));
+    server
+        .get("/oidc/upstream/mock/callback")
+        .add_query_param("code", "takeover")
+        .add_query_param("state", &upstream)
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    common::cleanup_test_user(&state.db, &victim.id).await;
+}
+
+#[tokio::test]
+async fn users_link_and_unlink_providers() {
+    let (server, state, profiles) = setup().await;
+    let user = common::create_test_user(&state.db, "upstream_password_1").await;
+    let other = common::create_test_user(&state.db, "upstream_password_1").await;
+    let access = common::store_test_access_token(state.kv.as_ref(), &user.id, &["openid"]).await;
+    let other_access =
+        common::store_test_access_token(state.kv.as_ref(), &other.id, &["openid"]).await;
+    let subject = format!("mock-{}", rand::random::<u32>());
+    profile(&profiles, "link", serde_json::json!({ "sub": subject }));
+
+    let providers: serde_json::Value = server.get("/api/v1/identity-providers").await.json();
+    assert_eq!(providers["data"][0]["id"], "mock");
+    assert_eq!(providers["data"][0]["name"], "Mock IdP");
+
+    // Link, via the provider and back to the web client.
+    let link = |access: String| {
+        let server = &server;
+        async move {
+            let body: serde_json::Value = server
+                .post("/api/v1/users/@me/identities/mock")
+                .authorization_bearer(&access)
+                .await
+                .json();
+            let url = reqwest::Url::parse(body["authorization_url"].as_str().unwrap()).unwrap();
+            let upstream = url.query_pairs().find(|(k, _)| k == "state").unwrap().1;
+            let resp = server
+                .get("/oidc/upstream/mock/callback")
+                .add_query_param("code", "link")
+                .add_query_param("state", &upstream)
+                .await;
+            resp.assert_status(StatusCode::SEE_OTHER);
+            resp.header("location").to_str().unwrap().to_string()
+        }
+    };
+    let web = state.config.web_url.trim_end_matches('/').to_string();
+    assert_eq!(
+        link(access.clone()).await,
+        format!("{web}/settings/connections?linked=mock")
+    );
+    let listed: serde_json::Value = server
+        .get("/api/v1/users/@me/identities")
+        .authorization_bearer(&access)
+        .await
+        .json();
+    assert_eq!(listed["data"][0]["provider"], "mock");
+    server
+        .post("/api/v1/users/@me/identities/mock")
+        .authorization_bearer(&access)
+        .await
+        .assert_status(StatusCode::CONFLICT);
+
+    // The same provider account can't be linked to someone else.
+    assert_eq!(
+        link(other_access).await,
+        format!("{web}/settings/connections?error=identity_in_use")
+    );
+
+    // Signing in with the provider now lands on the linked account.
+    profile(&profiles, "login", serde_json::json!({ "sub": subject }));
+    sign_in(&server, "login")
+        .await
+        .assert_status(StatusCode::SEE_OTHER);
+    assert_eq!(linked_user(&state, &subject).await.unwrap().id, user.id);
+
+    // The password still works, so unlinking is allowed.
+    server
+        .delete("/api/v1/users/@me/identities/mock")
+        .authorization_bearer(&access)
+        .await
+        .assert_status(StatusCode::NO_CONTENT);
+    server
+        .delete("/api/v1/users/@me/identities/mock")
+        .authorization_bearer(&access)
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+    assert!(linked_user(&state, &subject).await.is_none());
+
+    common::cleanup_test_user(&state.db, &user.id).await;
+    common::cleanup_test_user(&state.db, &other.id).await;
+}
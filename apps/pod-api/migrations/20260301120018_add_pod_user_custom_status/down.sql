@@ -0,0 +1,4 @@
+ALTER TABLE pod_users
+    DROP COLUMN status_text,
+    DROP COLUMN status_emoji,
+    DROP COLUMN status_expires_at;
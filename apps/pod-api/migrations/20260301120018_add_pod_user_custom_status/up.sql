@@ -0,0 +1,5 @@
+-- Custom status chosen by the user; `status` holds the chosen online/idle/dnd.
+ALTER TABLE pod_users
+    ADD COLUMN status_text       TEXT,
+    ADD COLUMN status_emoji      TEXT,
+    ADD COLUMN status_expires_at TIMESTAMPTZ;
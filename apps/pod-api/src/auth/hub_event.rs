@@ -0,0 +1,68 @@
+//! Validation of signed events pushed by the Hub (RFC §18.1).
+
+use jsonwebtoken::{Algorithm, Validation};
+use serde::{Deserialize, Serialize};
+
+use crate::auth::jwks::JwksClient;
+use crate::error::ApiError;
+
+/// JWT `typ` the Hub sets on events, so an SIA can't be passed off as one.
+const HUB_EVENT_TYP: &str = "voxora-event+jwt";
+
+/// The user deleted their Hub account.
+pub const EVENT_USER_DELETED: &str = "user.deleted";
+
+/// The Hub banned or suspended the user (RFC §14.4).
+pub const EVENT_USER_BANNED: &str = "user.banned";
+
+/// Event claims (mirrors hub-api's `PodEventClaims`).
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HubEventClaims {
+    pub iss: String,
+    /// The user the event is about.
+    pub sub: String,
+    pub aud: String,
+    pub iat: i64,
+    pub exp: i64,
+    /// Stable across delivery retries.
+    pub jti: String,
+    pub event: String,
+}
+
+/// Validate a Hub event JWT and return its claims.
+///
+/// Checks the signature via Hub JWKS, `typ`, `exp`, `aud` (this Pod's ID)
+/// and `iss`. Replays aren't rejected: the Hub retries with the same `jti`
+/// and handlers are idempotent.
+pub async fn validate_hub_event(
+    token: &str,
+    jwks: &JwksClient,
+    expected_pod_id: &str,
+    expected_issuer: &str,
+) -> Result<HubEventClaims, ApiError> {
+    let header = jsonwebtoken::decode_header(token).map_err(|e| {
+        tracing::debug!(?e, "hub event header decode failed");
+        ApiError::unauthorized("Invalid event token")
+    })?;
+
+    if header.typ.as_deref() != Some(HUB_EVENT_TYP) {
+        return Err(ApiError::unauthorized("Invalid event token"));
+    }
+    let kid = header
+        .kid
+        .ok_or_else(|| ApiError::unauthorized("Event token missing kid"))?;
+
+    let key = jwks.get_key(&kid).await?;
+
+    let mut validation = Validation::new(Algorithm::EdDSA);
+    validation.set_audience(&[expected_pod_id]);
+    validation.set_issuer(&[expected_issuer]);
+
+    let token_data =
+        jsonwebtoken::decode::<HubEventClaims>(token, &key, &validation).map_err(|e| {
+            tracing::debug!(?e, "hub event validation failed");
+            ApiError::unauthorized("Invalid or expired event token")
+        })?;
+
+    Ok(token_data.claims)
+}
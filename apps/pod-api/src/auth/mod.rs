@@ -1,3 +1,4 @@
+pub mod hub_event;
 pub mod jwks;
 pub mod middleware;
 pub mod sia;
@@ -1,4 +1,8 @@
 //! Pod Access Token (PAT), Refresh Token, and WebSocket ticket management.
+//!
+//! Every token records when its user signed in. When the Hub bans a user,
+//! the Pod stores a revocation time for them and treats every token from an
+//! earlier sign-in as expired.
 
 use serde::{Deserialize, Serialize};
 
@@ -29,6 +33,9 @@ pub const PAT_TTL_SECS: u64 = 3600;
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PatData {
     pub user_id: String,
+    /// Unix time of the SIA login this token descends from.
+    #[serde(default)]
+    pub signed_in_at: i64,
 }
 
 pub fn generate_pat() -> String {
@@ -51,6 +58,9 @@ pub async fn lookup_pat(kv: &dyn KeyValueStore, token: &str) -> Result<Option<Pa
         Some(v) => {
             let data: PatData =
                 serde_json::from_str(&v).map_err(|_| ApiError::internal("corrupt token data"))?;
+            if is_revoked(kv, &data.user_id, data.signed_in_at).await? {
+                return Ok(None);
+            }
             Ok(Some(data))
         }
         None => Ok(None),
@@ -68,6 +78,9 @@ pub const REFRESH_TTL_SECS: u64 = 30 * 24 * 3600;
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RefreshData {
     pub user_id: String,
+    /// Unix time of the SIA login this token descends from.
+    #[serde(default)]
+    pub signed_in_at: i64,
 }
 
 pub fn generate_refresh_token() -> String {
@@ -97,6 +110,9 @@ pub async fn consume_refresh_token(
         Some(v) => {
             let data: RefreshData =
                 serde_json::from_str(&v).map_err(|_| ApiError::internal("corrupt token data"))?;
+            if is_revoked(kv, &data.user_id, data.signed_in_at).await? {
+                return Ok(None);
+            }
             Ok(Some(data))
         }
         None => Ok(None),
@@ -114,6 +130,9 @@ pub const WS_TICKET_TTL_SECS: u64 = 30;
 #[derive(Debug, Serialize, Deserialize)]
 pub struct WsTicketData {
     pub user_id: String,
+    /// Unix time of the SIA login this token descends from.
+    #[serde(default)]
+    pub signed_in_at: i64,
 }
 
 pub fn generate_ws_ticket() -> String {
@@ -144,8 +163,53 @@ pub async fn consume_ws_ticket(
         Some(v) => {
             let data: WsTicketData =
                 serde_json::from_str(&v).map_err(|_| ApiError::internal("corrupt ticket data"))?;
+            if is_revoked(kv, &data.user_id, data.signed_in_at).await? {
+                return Ok(None);
+            }
             Ok(Some(data))
         }
         None => Ok(None),
     }
 }
+
+// ---------------------------------------------------------------------------
+// Hub revocation — kept as long as the longest-lived token
+// ---------------------------------------------------------------------------
+
+fn revoked_key(user_id: &str) -> String {
+    format!("pod:revoked:{}", user_id)
+}
+
+/// Revoke every token from a sign-in at or before `before` (Unix time).
+/// Refresh tokens live longest, so the marker can go once they would have
+/// expired.
+pub async fn revoke_user_tokens(
+    kv: &dyn KeyValueStore,
+    user_id: &str,
+    before: i64,
+) -> Result<(), ApiError> {
+    kv.set_ex(&revoked_key(user_id), &before.to_string(), REFRESH_TTL_SECS)
+        .await
+}
+
+/// When the user's tokens were last revoked, if they have been.
+pub async fn revoked_before(
+    kv: &dyn KeyValueStore,
+    user_id: &str,
+) -> Result<Option<i64>, ApiError> {
+    Ok(kv
+        .get(&revoked_key(user_id))
+        .await?
+        .and_then(|v| v.parse().ok()))
+}
+
+/// Whether a sign-in at `signed_in_at` has been revoked.
+pub async fn is_revoked(
+    kv: &dyn KeyValueStore,
+    user_id: &str,
+    signed_in_at: i64,
+) -> Result<bool, ApiError> {
+    Ok(revoked_before(kv, user_id)
+        .await?
+        .is_some_and(|before| signed_in_at <= before))
+}
@@ -0,0 +1,13 @@
+use pod_api::gateway::version::GatewayVersion;
+
+fn main() {
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../../specs");
+    std::fs::create_dir_all(&dir).unwrap();
+
+    for version in GatewayVersion::ALL {
+        let spec = pod_api::gateway::asyncapi::spec(*version);
+        let out = dir.join(format!("pod-gateway.v{}.asyncapi.json", version.number()));
+        std::fs::write(&out, serde_json::to_string_pretty(&spec).unwrap()).unwrap();
+        println!("Wrote {}", out.display());
+    }
+}
@@ -13,6 +13,10 @@ pub struct Config {
     pub pod_client_secret: String,
     /// Port the HTTP server binds to.
     pub port: u16,
+    /// How long shutdown waits for in-flight requests and connections to drain.
+    pub shutdown_timeout_secs: u64,
+    /// Inactivity after which an "online" user is shown as idle (0 disables).
+    pub presence_idle_after_secs: u64,
     /// Optional pod owner user ID. When set, this user gets implicit POD_ADMINISTRATOR.
     pub pod_owner_id: Option<String>,
 }
@@ -32,6 +36,14 @@ impl Config {
                 .ok()
                 .and_then(|v| v.parse().ok())
                 .unwrap_or(4002),
+            shutdown_timeout_secs: std::env::var("SHUTDOWN_TIMEOUT_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(30),
+            presence_idle_after_secs: std::env::var("PRESENCE_IDLE_AFTER_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(300),
             pod_owner_id: std::env::var("POD_OWNER_ID").ok().filter(|s| !s.is_empty()),
         }
     }
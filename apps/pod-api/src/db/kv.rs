@@ -23,6 +23,12 @@ pub struct MemoryStore {
     data: Mutex<HashMap<String, String>>,
 }
 
+impl Default for MemoryStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl MemoryStore {
     pub fn new() -> Self {
         Self {
@@ -10,6 +10,9 @@ diesel::table! {
         status -> Text,
         first_seen_at -> Timestamptz,
         last_seen_at -> Timestamptz,
+        status_text -> Nullable<Text>,
+        status_emoji -> Nullable<Text>,
+        status_expires_at -> Nullable<Timestamptz>,
     }
 }
 
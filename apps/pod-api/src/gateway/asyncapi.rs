@@ -0,0 +1,287 @@
+//! AsyncAPI description of the Gateway protocol.
+//!
+//! Payload schemas come from the same `ToSchema` derives used for the REST
+//! OpenAPI spec, so the gateway and REST shapes for e.g. `Message` can never
+//! drift apart.
+
+use serde_json::{json, Map, Value};
+use utoipa::OpenApi;
+
+use super::events::{
+    ChannelDeleteEvent, ChannelPinsUpdateEvent, HeartbeatAckPayload, HeartbeatPayload,
+    IdentifyPayload, MemberLeaveEvent, MessageDeleteEvent, MessageReactionAddEvent,
+    MessageReactionRemoveEvent, PresenceUpdateEvent, PresenceUpdatePayload, ReadyEvent,
+    ReconnectPayload, ResumePayload, ResumedEvent, TypingPayload, TypingStartEvent,
+    DISPATCH_EVENTS, OP_DISPATCH, OP_HEARTBEAT, OP_HEARTBEAT_ACK, OP_IDENTIFY, OP_PRESENCE_UPDATE,
+    OP_RECONNECT, OP_RESUME,
+};
+use super::version::GatewayVersion;
+
+/// Collects every gateway payload schema (and the models they reference).
+#[derive(OpenApi)]
+#[openapi(components(schemas(
+    // Dispatch payloads
+    ReadyEvent,
+    ResumedEvent,
+    MessageDeleteEvent,
+    MessageReactionAddEvent,
+    MessageReactionRemoveEvent,
+    ChannelDeleteEvent,
+    MemberLeaveEvent,
+    TypingStartEvent,
+    ChannelPinsUpdateEvent,
+    PresenceUpdateEvent,
+    crate::models::message::Message,
+    crate::models::reaction::Reaction,
+    crate::models::channel::Channel,
+    crate::models::community::Community,
+    crate::models::community_member::CommunityMember,
+    // Control payloads
+    HeartbeatAckPayload,
+    ReconnectPayload,
+    // Client payloads
+    IdentifyPayload,
+    ResumePayload,
+    HeartbeatPayload,
+    TypingPayload,
+    PresenceUpdatePayload,
+)))]
+struct GatewaySchemas;
+
+/// A non-dispatch message, described by its opcode.
+struct OpSpec {
+    key: &'static str,
+    op: u8,
+    /// Event name for client-sent dispatches (op=0), e.g. `TYPING`.
+    t: Option<&'static str>,
+    payload: &'static str,
+    description: &'static str,
+}
+
+/// Messages the client sends to the server.
+const CLIENT_OPS: &[OpSpec] = &[
+    OpSpec {
+        key: "op.IDENTIFY",
+        op: OP_IDENTIFY,
+        t: None,
+        payload: "IdentifyPayload",
+        description: "Authenticate with a single-use WS ticket",
+    },
+    OpSpec {
+        key: "op.RESUME",
+        op: OP_RESUME,
+        t: None,
+        payload: "ResumePayload",
+        description: "Resume a dropped session and replay missed events",
+    },
+    OpSpec {
+        key: "op.HEARTBEAT",
+        op: OP_HEARTBEAT,
+        t: None,
+        payload: "HeartbeatPayload",
+        description: "Keep-alive ping",
+    },
+    OpSpec {
+        key: "op.PRESENCE_UPDATE",
+        op: OP_PRESENCE_UPDATE,
+        t: None,
+        payload: "PresenceUpdatePayload",
+        description: "Update own presence",
+    },
+    OpSpec {
+        key: "client.TYPING",
+        op: OP_DISPATCH,
+        t: Some("TYPING"),
+        payload: "TypingPayload",
+        description: "Signal that the user started typing in a channel",
+    },
+];
+
+/// Non-dispatch messages the server sends to the client.
+const SERVER_OPS: &[OpSpec] = &[
+    OpSpec {
+        key: "op.HEARTBEAT_ACK",
+        op: OP_HEARTBEAT_ACK,
+        t: None,
+        payload: "HeartbeatAckPayload",
+        description: "Heartbeat response",
+    },
+    OpSpec {
+        key: "op.RECONNECT",
+        op: OP_RECONNECT,
+        t: None,
+        payload: "ReconnectPayload",
+        description: "Server requests client reconnect",
+    },
+];
+
+/// Build the AsyncAPI 3.0 document for one version of the Pod Gateway.
+pub fn spec(version: GatewayVersion) -> Value {
+    let schemas = GatewaySchemas::openapi()
+        .components
+        .map(|c| serde_json::to_value(c.schemas).unwrap_or_default())
+        .unwrap_or_default();
+
+    let mut messages = Map::new();
+    let mut send_refs = Vec::new();
+    let mut receive_refs = Vec::new();
+
+    for event in DISPATCH_EVENTS {
+        let key = format!("dispatch.{}", event.name);
+        messages.insert(
+            key.clone(),
+            message(
+                event.name,
+                event.description,
+                OP_DISPATCH,
+                Some(event.name),
+                true,
+                event.payload_for(version),
+            ),
+        );
+        send_refs.push(message_ref(&key));
+    }
+    for op in SERVER_OPS {
+        messages.insert(
+            op.key.to_string(),
+            message(op.key, op.description, op.op, op.t, false, op.payload),
+        );
+        send_refs.push(message_ref(op.key));
+    }
+    for op in CLIENT_OPS {
+        messages.insert(
+            op.key.to_string(),
+            message(op.key, op.description, op.op, op.t, false, op.payload),
+        );
+        receive_refs.push(message_ref(op.key));
+    }
+
+    let channel_messages: Map<String, Value> = messages
+        .keys()
+        .map(|k| {
+            (
+                k.clone(),
+                json!({ "$ref": format!("#/components/messages/{k}") }),
+            )
+        })
+        .collect();
+
+    json!({
+        "asyncapi": "3.0.0",
+        "info": {
+            "title": "Voxora Pod Gateway",
+            "version": version.number().to_string(),
+            "description": format!(
+                "WebSocket gateway protocol (RFC-0001 §13), selected with `/gateway?v={}`. \
+                 Every frame is a JSON object `{{ op, t?, s?, d }}`.",
+                version.number()
+            ),
+        },
+        "defaultContentType": "application/json",
+        "channels": {
+            "gateway": {
+                "address": "/gateway",
+                "messages": channel_messages,
+            }
+        },
+        "operations": {
+            "sendServerMessages": {
+                "action": "send",
+                "channel": { "$ref": "#/channels/gateway" },
+                "messages": send_refs,
+            },
+            "receiveClientMessages": {
+                "action": "receive",
+                "channel": { "$ref": "#/channels/gateway" },
+                "messages": receive_refs,
+            }
+        },
+        "components": {
+            "messages": messages,
+            "schemas": schemas,
+        }
+    })
+}
+
+/// Build a message component wrapping `payload` in the `{ op, t, s, d }` envelope.
+fn message(
+    name: &str,
+    description: &str,
+    op: u8,
+    t: Option<&str>,
+    sequenced: bool,
+    payload: &str,
+) -> Value {
+    let mut properties = Map::new();
+    properties.insert("op".into(), json!({ "type": "integer", "const": op }));
+    if let Some(t) = t {
+        properties.insert("t".into(), json!({ "type": "string", "const": t }));
+    }
+    if sequenced {
+        properties.insert("s".into(), json!({ "type": "integer", "minimum": 1 }));
+    }
+    properties.insert(
+        "d".into(),
+        json!({ "$ref": format!("#/components/schemas/{payload}") }),
+    );
+
+    let required: Vec<&str> = properties.keys().map(String::as_str).collect();
+
+    json!({
+        "name": name,
+        "summary": description,
+        "payload": {
+            "type": "object",
+            "properties": properties,
+            "required": required,
+        }
+    })
+}
+
+fn message_ref(key: &str) -> Value {
+    json!({ "$ref": format!("#/channels/gateway/messages/{key}") })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_message_payload_has_a_schema() {
+        for version in GatewayVersion::ALL {
+            let doc = spec(*version);
+            let schemas = doc["components"]["schemas"].as_object().unwrap();
+            for (key, msg) in doc["components"]["messages"].as_object().unwrap() {
+                let payload_ref = msg["payload"]["properties"]["d"]["$ref"].as_str().unwrap();
+                let name = payload_ref.trim_start_matches("#/components/schemas/");
+                assert!(
+                    schemas.contains_key(name),
+                    "{key} references missing schema {name}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn referenced_models_are_collected() {
+        let doc = spec(GatewayVersion::LATEST);
+        let schemas = doc["components"]["schemas"].as_object().unwrap();
+        // Nested inside ReadyEvent, not listed explicitly.
+        assert!(schemas.contains_key("Role"));
+        assert!(schemas.contains_key("ReadyCommunity"));
+    }
+
+    #[test]
+    fn versioned_payloads_follow_the_requested_version() {
+        let payload = |version| {
+            spec(version)["components"]["messages"]["dispatch.MESSAGE_REACTION_ADD"]["payload"]
+                ["properties"]["d"]["$ref"]
+                .clone()
+        };
+        assert_eq!(payload(GatewayVersion::V1), "#/components/schemas/Reaction");
+        assert_eq!(
+            payload(GatewayVersion::V2),
+            "#/components/schemas/MessageReactionAddEvent"
+        );
+    }
+}
@@ -1,7 +1,18 @@
 //! Gateway opcodes, event types, and wire-format messages.
 
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use utoipa::ToSchema;
+
+use crate::models::channel::Channel;
+use crate::models::community::Community;
+use crate::models::community_member::CommunityMember;
+use crate::models::message::Message;
+use crate::models::reaction::Reaction;
+use crate::models::role::Role;
+
+use super::version::GatewayVersion;
 
 // ---------------------------------------------------------------------------
 // Opcodes
@@ -31,8 +42,15 @@ pub struct GatewayMessage {
 }
 
 impl GatewayMessage {
-    /// Build a DISPATCH message (op=0).
-    pub fn dispatch(event_name: &str, seq: u64, data: Value) -> Self {
+    /// Build a DISPATCH message (op=0) from a typed event, serialized for
+    /// the session's negotiated protocol version.
+    pub fn dispatch(event: &DispatchEvent, seq: u64, version: GatewayVersion) -> Self {
+        Self::dispatch_raw(event.name(), seq, event.data(version))
+    }
+
+    /// Build a DISPATCH message (op=0) from an already-serialized event,
+    /// e.g. one read back from the replay buffer.
+    pub fn dispatch_raw(event_name: &str, seq: u64, data: Value) -> Self {
         Self {
             op: OP_DISPATCH,
             t: Some(event_name.to_string()),
@@ -47,7 +65,9 @@ impl GatewayMessage {
             op: OP_RECONNECT,
             t: None,
             s: None,
-            d: serde_json::json!({ "reason": reason }),
+            d: to_data(&ReconnectPayload {
+                reason: reason.to_string(),
+            }),
         }
     }
 
@@ -57,11 +77,28 @@ impl GatewayMessage {
             op: OP_HEARTBEAT_ACK,
             t: None,
             s: None,
-            d: serde_json::json!({ "ack": seq }),
+            d: to_data(&HeartbeatAckPayload { ack: seq }),
         }
     }
 }
 
+/// RECONNECT (op=7) payload.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReconnectPayload {
+    pub reason: String,
+}
+
+/// HEARTBEAT_ACK (op=6) payload.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct HeartbeatAckPayload {
+    /// The `seq` echoed back from the client's HEARTBEAT.
+    pub ack: u64,
+}
+
+fn to_data<T: Serialize>(payload: &T) -> Value {
+    serde_json::to_value(payload).unwrap_or_default()
+}
+
 // ---------------------------------------------------------------------------
 // Client → Server message
 // ---------------------------------------------------------------------------
@@ -80,7 +117,7 @@ pub struct ClientMessage {
 // IDENTIFY payload
 // ---------------------------------------------------------------------------
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct IdentifyPayload {
     pub ticket: String,
 }
@@ -89,7 +126,7 @@ pub struct IdentifyPayload {
 // RESUME payload
 // ---------------------------------------------------------------------------
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct ResumePayload {
     pub session_id: String,
     pub token: String,
@@ -100,7 +137,7 @@ pub struct ResumePayload {
 // HEARTBEAT payload
 // ---------------------------------------------------------------------------
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct HeartbeatPayload {
     #[serde(default)]
     pub seq: u64,
@@ -110,7 +147,7 @@ pub struct HeartbeatPayload {
 // TYPING payload
 // ---------------------------------------------------------------------------
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct TypingPayload {
     pub channel_id: String,
 }
@@ -119,34 +156,327 @@ pub struct TypingPayload {
 // PRESENCE_UPDATE payload
 // ---------------------------------------------------------------------------
 
-#[derive(Debug, Deserialize)]
+/// The client's complete presence. Omitted fields are cleared, so a
+/// status-only update also clears custom status and activities.
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct PresenceUpdatePayload {
+    /// One of "online", "idle", or "dnd".
     pub status: String,
+    #[serde(default)]
+    pub custom_status: Option<CustomStatus>,
+    #[serde(default)]
+    pub activities: Vec<Activity>,
+}
+
+/// Free-form status shown next to a user's name. Persisted across reconnects
+/// until `expires_at`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct CustomStatus {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// A unicode emoji or custom emoji name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub emoji: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Something the user is currently doing. Not persisted — activities
+/// disappear when the user goes offline.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Activity {
+    #[serde(rename = "type")]
+    pub kind: ActivityKind,
+    /// e.g. the game, song, or voice channel name.
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<String>,
+    /// The voice channel, for `voice` activities.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityKind {
+    Playing,
+    Streaming,
+    Listening,
+    Watching,
+    /// In a voice channel.
+    Voice,
 }
 
 // ---------------------------------------------------------------------------
-// Dispatch event types
+// Dispatch events (Server → Client, op=0)
 // ---------------------------------------------------------------------------
 
-/// Event names dispatched to clients.
-pub struct EventName;
+/// Catalog entry describing one dispatch event for schema generation.
+pub struct EventSpec {
+    /// Wire event name (`t`).
+    pub name: &'static str,
+    /// Name of the payload's `ToSchema` component.
+    pub payload: &'static str,
+    pub description: &'static str,
+    /// Payload schema overrides, as `(first version, schema)` pairs in
+    /// ascending version order.
+    pub versioned: &'static [(GatewayVersion, &'static str)],
+}
+
+impl EventSpec {
+    /// The payload schema name used by a given protocol version.
+    pub fn payload_for(&self, version: GatewayVersion) -> &'static str {
+        self.versioned
+            .iter()
+            .rev()
+            .find(|(since, _)| *since <= version)
+            .map(|(_, schema)| *schema)
+            .unwrap_or(self.payload)
+    }
+}
+
+/// Declares [`DispatchEvent`], its wire names and the [`DISPATCH_EVENTS`]
+/// catalog from one list, so the three can't drift apart.
+///
+/// Each entry is `Variant(Payload) => "WIRE_NAME", "description"`, optionally
+/// followed by `schema = "Name"` when the catalog's payload schema isn't the
+/// payload type, and `since Version = "Name"` for each version that changed it.
+macro_rules! dispatch_events {
+    ($(
+        $variant:ident($payload:ty) => $name:literal, $description:literal
+        $(, schema = $schema:literal)?
+        $(, since $since:ident = $versioned:literal)*;
+    )*) => {
+        /// Every event the server can dispatch, paired with its payload.
+        ///
+        /// The wire name is the `t` field and the payload is the `d` field.
+        #[derive(Debug, Clone, Serialize)]
+        #[serde(tag = "t", content = "d")]
+        pub enum DispatchEvent {
+            $(
+                #[serde(rename = $name)]
+                $variant($payload),
+            )*
+        }
+
+        impl DispatchEvent {
+            /// The wire event name (`t`).
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(Self::$variant(_) => $name,)*
+                }
+            }
+        }
+
+        /// All dispatch events, in the order documented in RFC §13.4.
+        pub const DISPATCH_EVENTS: &[EventSpec] = &[
+            $(
+                EventSpec {
+                    name: $name,
+                    payload: dispatch_events!(@schema $payload $(, $schema)?),
+                    description: $description,
+                    versioned: &[$((GatewayVersion::$since, $versioned)),*],
+                },
+            )*
+        ];
+    };
+    (@schema $payload:ty) => {
+        stringify!($payload)
+    };
+    (@schema $payload:ty, $schema:literal) => {
+        $schema
+    };
+}
+
+dispatch_events! {
+    Ready(ReadyEvent) => "READY", "Initial state after IDENTIFY";
+    Resumed(ResumedEvent) => "RESUMED", "Session resumed successfully";
+    MessageCreate(Message) => "MESSAGE_CREATE", "New message";
+    MessageUpdate(Message) => "MESSAGE_UPDATE", "Message edited";
+    MessageDelete(MessageDeleteEvent) => "MESSAGE_DELETE", "Message deleted";
+    MessageReactionAdd(MessageReactionAddEvent) => "MESSAGE_REACTION_ADD", "Reaction added",
+        schema = "Reaction", since V2 = "MessageReactionAddEvent";
+    MessageReactionRemove(MessageReactionRemoveEvent) => "MESSAGE_REACTION_REMOVE",
+        "Reaction removed";
+    ChannelCreate(Channel) => "CHANNEL_CREATE", "Channel created";
+    ChannelUpdate(Channel) => "CHANNEL_UPDATE", "Channel modified";
+    ChannelDelete(ChannelDeleteEvent) => "CHANNEL_DELETE", "Channel deleted";
+    CommunityUpdate(Community) => "COMMUNITY_UPDATE", "Community settings changed";
+    MemberJoin(CommunityMember) => "MEMBER_JOIN", "New member";
+    MemberLeave(MemberLeaveEvent) => "MEMBER_LEAVE", "Member left";
+    MemberUpdate(CommunityMember) => "MEMBER_UPDATE", "Member roles/nickname changed";
+    PresenceUpdate(PresenceUpdateEvent) => "PRESENCE_UPDATE", "User presence changed";
+    TypingStart(TypingStartEvent) => "TYPING_START", "User started typing";
+    ChannelPinsUpdate(ChannelPinsUpdateEvent) => "CHANNEL_PINS_UPDATE", "Channel pins changed";
+}
+
+impl DispatchEvent {
+    /// The serialized event payload (`d`) for a given protocol version.
+    ///
+    /// Events whose shape changed between versions get an explicit arm per
+    /// version; all others serialize identically everywhere.
+    pub fn data(&self, version: GatewayVersion) -> Value {
+        match self {
+            Self::Ready(d) => to_data(d),
+            Self::Resumed(d) => to_data(d),
+            Self::MessageCreate(d) | Self::MessageUpdate(d) => to_data(d),
+            Self::MessageDelete(d) => to_data(d),
+            Self::MessageReactionAdd(d) => match version {
+                GatewayVersion::V1 => to_data(&d.reaction),
+                GatewayVersion::V2 => to_data(d),
+            },
+            Self::MessageReactionRemove(d) => to_data(d),
+            Self::ChannelCreate(d) | Self::ChannelUpdate(d) => to_data(d),
+            Self::ChannelDelete(d) => to_data(d),
+            Self::CommunityUpdate(d) => to_data(d),
+            Self::MemberJoin(d) | Self::MemberUpdate(d) => to_data(d),
+            Self::MemberLeave(d) => to_data(d),
+            Self::TypingStart(d) => to_data(d),
+            Self::ChannelPinsUpdate(d) => to_data(d),
+            Self::PresenceUpdate(d) => to_data(d),
+        }
+    }
+
+    /// Whether this event is written to the replay buffer for RESUME.
+    ///
+    /// Presence and typing are ephemeral — replaying them after a reconnect
+    /// would show stale state.
+    pub fn is_replayable(&self) -> bool {
+        !matches!(self, Self::PresenceUpdate(_) | Self::TypingStart(_))
+    }
+}
+
+/// READY payload, sent once after a successful IDENTIFY.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ReadyEvent {
+    /// The negotiated Gateway protocol version.
+    pub v: u8,
+    pub session_id: String,
+    pub user: ReadyUser,
+    pub communities: Vec<ReadyCommunity>,
+    /// Non-offline users across all of the user's communities.
+    pub presences: Vec<PresenceUpdateEvent>,
+    /// Heartbeat interval in milliseconds.
+    pub heartbeat_interval: u64,
+}
+
+/// The identified user, as included in READY.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ReadyUser {
+    pub id: String,
+    pub username: String,
+    pub display_name: String,
+    pub avatar_url: Option<String>,
+}
+
+/// A community the user is a member of, with its channels and roles.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ReadyCommunity {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub icon_url: Option<String>,
+    pub owner_id: String,
+    pub member_count: i32,
+    pub channels: Vec<Channel>,
+    pub roles: Vec<Role>,
+}
+
+/// RESUMED payload (always empty).
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ResumedEvent {}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct MessageDeleteEvent {
+    pub id: String,
+    pub channel_id: String,
+}
+
+/// MESSAGE_REACTION_ADD payload. Protocol v1 sends only the flattened
+/// reaction, without `channel_id`.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct MessageReactionAddEvent {
+    #[serde(flatten)]
+    pub reaction: Reaction,
+    pub channel_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct MessageReactionRemoveEvent {
+    pub message_id: String,
+    pub user_id: String,
+    pub emoji: String,
+    pub channel_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ChannelDeleteEvent {
+    pub id: String,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct MemberLeaveEvent {
+    pub user_id: String,
+}
 
-impl EventName {
-    pub const READY: &'static str = "READY";
-    pub const MESSAGE_CREATE: &'static str = "MESSAGE_CREATE";
-    pub const MESSAGE_UPDATE: &'static str = "MESSAGE_UPDATE";
-    pub const MESSAGE_DELETE: &'static str = "MESSAGE_DELETE";
-    pub const MESSAGE_REACTION_ADD: &'static str = "MESSAGE_REACTION_ADD";
-    pub const MESSAGE_REACTION_REMOVE: &'static str = "MESSAGE_REACTION_REMOVE";
-    pub const CHANNEL_CREATE: &'static str = "CHANNEL_CREATE";
-    pub const CHANNEL_UPDATE: &'static str = "CHANNEL_UPDATE";
-    pub const CHANNEL_DELETE: &'static str = "CHANNEL_DELETE";
-    pub const COMMUNITY_UPDATE: &'static str = "COMMUNITY_UPDATE";
-    pub const MEMBER_JOIN: &'static str = "MEMBER_JOIN";
-    pub const MEMBER_LEAVE: &'static str = "MEMBER_LEAVE";
-    pub const MEMBER_UPDATE: &'static str = "MEMBER_UPDATE";
-    pub const RESUMED: &'static str = "RESUMED";
-    pub const TYPING_START: &'static str = "TYPING_START";
-    pub const CHANNEL_PINS_UPDATE: &'static str = "CHANNEL_PINS_UPDATE";
-    pub const PRESENCE_UPDATE: &'static str = "PRESENCE_UPDATE";
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct TypingStartEvent {
+    pub channel_id: String,
+    pub user_id: String,
+    pub username: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ChannelPinsUpdateEvent {
+    pub channel_id: String,
+}
+
+/// A user's presence, dispatched on change and included in READY.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct PresenceUpdateEvent {
+    pub user_id: String,
+    /// One of "online", "idle", "dnd", or "offline".
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub custom_status: Option<CustomStatus>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub activities: Vec<Activity>,
+}
+
+impl PresenceUpdateEvent {
+    /// A bare offline presence (custom status and activities are hidden).
+    pub fn offline(user_id: impl Into<String>) -> Self {
+        Self {
+            user_id: user_id.into(),
+            status: "offline".to_string(),
+            custom_status: None,
+            activities: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn event_names_are_unique() {
+        let names: HashSet<&str> = DISPATCH_EVENTS.iter().map(|e| e.name).collect();
+        assert_eq!(names.len(), DISPATCH_EVENTS.len());
+    }
+
+    #[test]
+    fn name_is_the_serialized_tag() {
+        let event = DispatchEvent::ChannelPinsUpdate(ChannelPinsUpdateEvent {
+            channel_id: "ch".into(),
+        });
+        let wire = serde_json::to_value(&event).unwrap();
+        assert_eq!(wire["t"], "CHANNEL_PINS_UPDATE");
+        assert_eq!(wire["t"], event.name());
+    }
 }
@@ -6,34 +6,66 @@
 
 use std::sync::Arc;
 
-use serde_json::Value;
-use tokio::sync::broadcast;
+use tokio::sync::{broadcast, watch};
+
+use super::events::DispatchEvent;
 
 /// Capacity of the broadcast channel. Slow receivers that fall behind will
 /// skip messages (RecvError::Lagged).
 const BROADCAST_CAPACITY: usize = 4096;
 
+/// Capacity of the disconnect channel. Disconnects are rare.
+const DISCONNECT_CAPACITY: usize = 256;
+
 /// A payload broadcast to all connected gateway sessions.
 #[derive(Debug, Clone)]
 pub struct BroadcastPayload {
     /// The community this event belongs to.
     pub community_id: String,
-    /// The dispatch event name (e.g. "MESSAGE_CREATE").
-    pub event_name: String,
-    /// Serialized event data (serde_json::Value).
-    pub data: Value,
+    /// The typed dispatch event. Serialized per session when sent.
+    pub event: DispatchEvent,
 }
 
 /// The global broadcast hub. Cloneable — store in AppState.
+///
+/// Also carries the shutdown signal: every live connection holds a
+/// [`ShutdownSignal`], so the number of outstanding signals is the number of
+/// connections still draining.
 #[derive(Clone)]
 pub struct GatewayBroadcast {
     sender: broadcast::Sender<Arc<BroadcastPayload>>,
+    shutdown: Arc<watch::Sender<bool>>,
+    /// IDs of users whose sessions must close, e.g. after a Hub ban.
+    disconnects: broadcast::Sender<Arc<str>>,
+}
+
+/// Per-connection handle that resolves once the server starts shutting down.
+pub struct ShutdownSignal(watch::Receiver<bool>);
+
+impl ShutdownSignal {
+    /// Wait until shutdown begins. Resolves immediately if it already has.
+    pub async fn recv(&mut self) {
+        // Err means the hub was dropped, which only happens at exit.
+        let _ = self.0.wait_for(|stopping| *stopping).await;
+    }
+}
+
+impl Default for GatewayBroadcast {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl GatewayBroadcast {
     pub fn new() -> Self {
         let (sender, _) = broadcast::channel(BROADCAST_CAPACITY);
-        Self { sender }
+        let (shutdown, _) = watch::channel(false);
+        let (disconnects, _) = broadcast::channel(DISCONNECT_CAPACITY);
+        Self {
+            sender,
+            shutdown: Arc::new(shutdown),
+            disconnects,
+        }
     }
 
     /// Subscribe to the broadcast channel. Each gateway session should call
@@ -47,4 +79,69 @@ impl GatewayBroadcast {
         // send() returns Err if there are no receivers — that's fine.
         let _ = self.sender.send(Arc::new(payload));
     }
+
+    /// Subscribe to forced disconnects. Each session checks the user IDs it
+    /// receives against its own.
+    pub fn subscribe_disconnects(&self) -> broadcast::Receiver<Arc<str>> {
+        self.disconnects.subscribe()
+    }
+
+    /// Close every session belonging to `user_id`.
+    pub fn disconnect_user(&self, user_id: &str) {
+        let _ = self.disconnects.send(Arc::from(user_id));
+    }
+
+    /// Get a shutdown handle. Each connection should hold one for its whole
+    /// lifetime so [`drained`](Self::drained) can wait for it.
+    pub fn shutdown_signal(&self) -> ShutdownSignal {
+        ShutdownSignal(self.shutdown.subscribe())
+    }
+
+    /// Tell every connection to send RECONNECT and close.
+    pub fn begin_shutdown(&self) {
+        self.shutdown.send_replace(true);
+    }
+
+    /// Number of connections that haven't closed yet.
+    pub fn live_connections(&self) -> usize {
+        self.shutdown.receiver_count()
+    }
+
+    /// Resolve once every connection has dropped its [`ShutdownSignal`].
+    pub async fn drained(&self) {
+        self.shutdown.closed().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn shutdown_signal_fires_for_existing_and_late_connections() {
+        let hub = GatewayBroadcast::new();
+        let mut early = hub.shutdown_signal();
+        hub.begin_shutdown();
+        early.recv().await;
+
+        // Connections that start after shutdown began see it immediately.
+        let mut late = hub.shutdown_signal();
+        late.recv().await;
+    }
+
+    #[tokio::test]
+    async fn drained_waits_for_every_connection() {
+        let hub = GatewayBroadcast::new();
+        let a = hub.shutdown_signal();
+        let b = hub.shutdown_signal();
+        assert_eq!(hub.live_connections(), 2);
+
+        drop(a);
+        let pending = tokio::time::timeout(std::time::Duration::from_millis(20), hub.drained());
+        assert!(pending.await.is_err());
+
+        drop(b);
+        hub.drained().await;
+        assert_eq!(hub.live_connections(), 0);
+    }
 }
@@ -3,7 +3,6 @@
 use std::collections::HashSet;
 
 use diesel::prelude::*;
-use serde_json::Value;
 
 use crate::auth::tokens;
 use crate::db::schema::{channels, communities, community_members, roles};
@@ -14,8 +13,12 @@ use crate::models::pod_user::PodUser;
 use crate::models::role::Role;
 use crate::AppState;
 
-use super::events::{GatewayMessage, IdentifyPayload};
+use super::events::{
+    DispatchEvent, GatewayMessage, IdentifyPayload, PresenceUpdateEvent, ReadyCommunity,
+    ReadyEvent, ReadyUser,
+};
 use super::session::GatewaySession;
+use super::version::GatewayVersion;
 
 /// Heartbeat interval sent to clients in the READY payload (ms).
 pub const HEARTBEAT_INTERVAL_MS: u64 = 41250;
@@ -24,15 +27,28 @@ pub const HEARTBEAT_INTERVAL_MS: u64 = 41250;
 pub async fn handle_identify(
     state: &AppState,
     payload: IdentifyPayload,
+    version: GatewayVersion,
 ) -> Result<(GatewaySession, GatewayMessage), &'static str> {
-    // Consume the WS ticket (single-use).
-    let ticket_data = tokens::consume_ws_ticket(state.kv.as_ref(), &payload.ticket)
+    let user_id = consume_ticket(state, &payload.ticket).await?;
+    identify_user(state, user_id, version).await
+}
+
+/// Consume a single-use WS ticket, returning the user it was issued to.
+pub async fn consume_ticket(state: &AppState, ticket: &str) -> Result<String, &'static str> {
+    let ticket_data = tokens::consume_ws_ticket(state.kv.as_ref(), ticket)
         .await
         .map_err(|_| "Ticket lookup failed")?
         .ok_or("Invalid or expired ticket")?;
+    Ok(ticket_data.user_id)
+}
 
-    let user_id = ticket_data.user_id;
-
+/// Build and register a new session for an already-authenticated user.
+/// Returns a (`GatewaySession`, READY message) on success.
+pub async fn identify_user(
+    state: &AppState,
+    user_id: String,
+    version: GatewayVersion,
+) -> Result<(GatewaySession, GatewayMessage), &'static str> {
     let mut conn = state.db.get().await.map_err(|_| "Database unavailable")?;
 
     // Load user profile.
@@ -59,7 +75,7 @@ pub async fn handle_identify(
     let community_set: HashSet<String> = community_ids.iter().cloned().collect();
 
     // Build the READY payload with communities, channels, and roles.
-    let mut community_data: Vec<Value> = Vec::new();
+    let mut community_data: Vec<ReadyCommunity> = Vec::new();
 
     if !community_ids.is_empty() {
         let comms: Vec<Community> = diesel_async::RunQueryDsl::load(
@@ -92,63 +108,76 @@ pub async fn handle_identify(
         .map_err(|_| "Failed to load roles")?;
 
         for comm in comms {
-            let chs: Vec<&Channel> = all_channels
+            let chs: Vec<Channel> = all_channels
                 .iter()
                 .filter(|c| c.community_id == comm.id)
+                .cloned()
                 .collect();
-            let rls: Vec<&Role> = all_roles
+            let rls: Vec<Role> = all_roles
                 .iter()
                 .filter(|r| r.community_id == comm.id)
+                .cloned()
                 .collect();
 
-            community_data.push(serde_json::json!({
-                "id": comm.id,
-                "name": comm.name,
-                "description": comm.description,
-                "icon_url": comm.icon_url,
-                "owner_id": comm.owner_id,
-                "member_count": comm.member_count,
-                "channels": serde_json::to_value(&chs).unwrap_or_default(),
-                "roles": serde_json::to_value(&rls).unwrap_or_default(),
-            }));
+            community_data.push(ReadyCommunity {
+                id: comm.id,
+                name: comm.name,
+                description: comm.description,
+                icon_url: comm.icon_url,
+                owner_id: comm.owner_id,
+                member_count: comm.member_count,
+                channels: chs,
+                roles: rls,
+            });
         }
     }
 
     // Gather online presences across all communities (deduplicated by user_id).
     let mut seen_users = HashSet::new();
-    let mut presences: Vec<Value> = Vec::new();
+    let mut presences: Vec<PresenceUpdateEvent> = Vec::new();
     for community_id in &community_ids {
-        for (uid, status) in state.presence.get_online_users(community_id) {
+        for (uid, _) in state.presence.get_online_users(community_id) {
             if seen_users.insert(uid.clone()) {
-                presences.push(serde_json::json!({
-                    "user_id": uid,
-                    "status": status,
-                }));
+                presences.extend(state.presence.get_presence(&uid));
             }
         }
     }
 
     let session_id = voxora_common::id::prefixed_ulid("gw_");
 
-    let ready_data = serde_json::json!({
-        "session_id": session_id,
-        "user": {
-            "id": user.id,
-            "username": user.username,
-            "display_name": user.display_name,
-            "avatar_url": user.avatar_url,
+    let ready = DispatchEvent::Ready(ReadyEvent {
+        v: version.number(),
+        session_id: session_id.clone(),
+        user: ReadyUser {
+            id: user.id,
+            username: user.username.clone(),
+            display_name: user.display_name,
+            avatar_url: user.avatar_url,
         },
-        "communities": community_data,
-        "presences": presences,
-        "heartbeat_interval": HEARTBEAT_INTERVAL_MS,
+        communities: community_data,
+        presences,
+        heartbeat_interval: HEARTBEAT_INTERVAL_MS,
     });
 
-    let session = GatewaySession::new(session_id.clone(), user_id.clone(), user.username.clone(), community_set.clone());
+    let session = GatewaySession::new(
+        session_id.clone(),
+        user_id.clone(),
+        user.username.clone(),
+        community_set.clone(),
+        version,
+    );
     let seq = session.next_seq();
-    let ready_msg = GatewayMessage::dispatch("READY", seq, ready_data);
+    let ready_msg = GatewayMessage::dispatch(&ready, seq, version);
 
     // Register the session in the registry for resume support.
-    state.sessions.register(session_id, user_id, user.username.clone(), community_set);
+    state.sessions.register(
+        session_id.clone(),
+        user_id,
+        user.username.clone(),
+        community_set,
+        version,
+    );
+    state.sessions.advance_seq(&session_id, seq);
 
     Ok((session, ready_msg))
 }
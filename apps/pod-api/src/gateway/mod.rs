@@ -1,8 +1,12 @@
+pub mod asyncapi;
 pub mod events;
 pub mod fanout;
 pub mod handler;
+pub mod ops;
 pub mod presence;
 pub mod registry;
 pub mod resume;
 pub mod server;
 pub mod session;
+pub mod sse;
+pub mod version;
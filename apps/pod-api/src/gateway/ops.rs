@@ -0,0 +1,335 @@
+//! Transport-independent session logic shared by the WebSocket and SSE gateways.
+
+use chrono::Utc;
+use diesel::prelude::*;
+
+use crate::db::schema::{channels, pod_users};
+use crate::error::ApiError;
+use crate::models::pod_user::PodUser;
+use crate::AppState;
+
+use super::events::{
+    Activity, ActivityKind, CustomStatus, DispatchEvent, GatewayMessage, PresenceUpdatePayload,
+    TypingStartEvent,
+};
+use super::fanout::BroadcastPayload;
+use super::presence::{PresenceChange, SavedPresence};
+use super::session::GatewaySession;
+
+/// Statuses a client may set for itself (`offline` is server-managed).
+const CLIENT_STATUSES: &[&str] = &["online", "idle", "dnd"];
+
+const MAX_STATUS_TEXT_CHARS: usize = 128;
+const MAX_STATUS_EMOJI_CHARS: usize = 64;
+const MAX_ACTIVITIES: usize = 5;
+const MAX_ACTIVITY_FIELD_CHARS: usize = 128;
+
+/// Why a client-sent op was dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientOpError {
+    RateLimited,
+    InvalidStatus,
+    InvalidPresence(&'static str),
+    UnknownChannel,
+    Unavailable,
+}
+
+impl From<ClientOpError> for ApiError {
+    fn from(e: ClientOpError) -> Self {
+        match e {
+            ClientOpError::RateLimited => ApiError::too_many_requests("Rate limited"),
+            ClientOpError::InvalidStatus => {
+                ApiError::bad_request("Status must be one of: online, idle, dnd")
+            }
+            ClientOpError::InvalidPresence(reason) => ApiError::bad_request(reason),
+            ClientOpError::UnknownChannel => ApiError::not_found("Channel not found"),
+            ClientOpError::Unavailable => ApiError::internal("Database unavailable"),
+        }
+    }
+}
+
+/// Mark the session's user online and, if they just came online, broadcast
+/// the transition to their communities. A user coming back from offline gets
+/// the status and custom status they last chose.
+pub async fn announce_online(state: &AppState, session: &GatewaySession) {
+    let saved = load_saved_presence(state, &session.user_id).await;
+    let prev_status =
+        state
+            .presence
+            .set_online_with(&session.user_id, &session.communities, saved.as_ref());
+    if prev_status.is_some() {
+        broadcast_presence(state, &session.user_id);
+    }
+}
+
+async fn load_saved_presence(state: &AppState, user_id: &str) -> Option<SavedPresence> {
+    let mut conn = state.db.get().await.ok()?;
+    let user: PodUser = diesel_async::RunQueryDsl::get_result(
+        pod_users::table.find(user_id).select(PodUser::as_select()),
+        &mut conn,
+    )
+    .await
+    .ok()?;
+
+    // Rows created before rich presence carry "active"; treat anything a
+    // client couldn't have chosen as online.
+    let status = if CLIENT_STATUSES.contains(&user.status.as_str()) {
+        user.status
+    } else {
+        "online".to_string()
+    };
+    let custom_status = CustomStatus {
+        text: user.status_text,
+        emoji: user.status_emoji,
+        expires_at: user.status_expires_at,
+    };
+    let expired = custom_status.expires_at.is_some_and(|at| at <= Utc::now());
+    let custom_status = (!expired
+        && (custom_status.text.is_some() || custom_status.emoji.is_some()))
+    .then_some(custom_status);
+
+    Some(SavedPresence {
+        status,
+        custom_status,
+    })
+}
+
+/// Deregister a connection that has gone away. The presence sweeper handles
+/// the offline grace period and the registry keeps the session resumable.
+pub fn session_closed(state: &AppState, session: &GatewaySession) {
+    state
+        .presence
+        .remove_session(&session.user_id, &session.communities);
+    state.sessions.mark_disconnected(&session.session_id);
+}
+
+/// Turn a fanout payload into the next dispatch for `session`, recording it in
+/// the replay buffer. Returns `None` if the session isn't subscribed.
+pub fn next_dispatch(
+    state: &AppState,
+    session: &GatewaySession,
+    payload: &BroadcastPayload,
+) -> Option<GatewayMessage> {
+    if !session.is_subscribed(&payload.community_id) {
+        return None;
+    }
+
+    let seq = session.next_seq();
+    let msg = GatewayMessage::dispatch(&payload.event, seq, session.version);
+
+    // Skip ephemeral events (presence, typing) — they shouldn't be replayed.
+    if payload.event.is_replayable() {
+        state.sessions.append_event(
+            &session.session_id,
+            seq,
+            payload.event.name(),
+            msg.d.clone(),
+        );
+    } else {
+        state.sessions.advance_seq(&session.session_id, seq);
+    }
+
+    Some(msg)
+}
+
+/// Handle a client TYPING: broadcast TYPING_START to the channel's community.
+pub async fn start_typing(
+    state: &AppState,
+    session: &GatewaySession,
+    channel_id: String,
+) -> Result<(), ClientOpError> {
+    if !state
+        .sessions
+        .allow_typing(&session.session_id, &channel_id)
+    {
+        return Err(ClientOpError::RateLimited);
+    }
+
+    // Look up channel to get community_id.
+    let mut conn = state
+        .db
+        .get()
+        .await
+        .map_err(|_| ClientOpError::Unavailable)?;
+    let community_id: String = diesel_async::RunQueryDsl::get_result(
+        channels::table
+            .find(&channel_id)
+            .select(channels::community_id),
+        &mut conn,
+    )
+    .await
+    .map_err(|_| ClientOpError::UnknownChannel)?;
+
+    // Channels outside the user's communities look the same as missing ones.
+    if !session.is_subscribed(&community_id) {
+        return Err(ClientOpError::UnknownChannel);
+    }
+
+    record_activity(state, &session.user_id);
+
+    state.broadcast.dispatch(BroadcastPayload {
+        community_id,
+        event: DispatchEvent::TypingStart(TypingStartEvent {
+            channel_id,
+            user_id: session.user_id.clone(),
+            username: session.username.clone(),
+            timestamp: chrono::Utc::now(),
+        }),
+    });
+    Ok(())
+}
+
+/// Handle a client PRESENCE_UPDATE: replace the user's status, custom status
+/// and activities, persist the chosen status, and broadcast if anything
+/// changed.
+pub async fn update_presence(
+    state: &AppState,
+    session: &GatewaySession,
+    payload: PresenceUpdatePayload,
+) -> Result<(), ClientOpError> {
+    if !CLIENT_STATUSES.contains(&payload.status.as_str()) {
+        return Err(ClientOpError::InvalidStatus);
+    }
+    let custom_status = validate_custom_status(payload.custom_status)?;
+    validate_activities(&payload.activities)?;
+    if !state.sessions.allow_presence_update(&session.session_id) {
+        return Err(ClientOpError::RateLimited);
+    }
+
+    save_presence(
+        state,
+        &session.user_id,
+        &payload.status,
+        custom_status.as_ref(),
+    )
+    .await;
+
+    let user_id = &session.user_id;
+    let status_changed = state
+        .presence
+        .set_status(user_id, &payload.status)
+        .is_some();
+    let custom_changed = state.presence.set_custom_status(user_id, custom_status);
+    let activities_changed = state.presence.set_activities(user_id, payload.activities);
+    if status_changed || custom_changed || activities_changed {
+        broadcast_presence(state, user_id);
+    }
+    Ok(())
+}
+
+/// Record that the user did something. Brings an auto-idle user back to
+/// their chosen status.
+pub fn record_activity(state: &AppState, user_id: &str) {
+    if let Some(change) = state.presence.touch(user_id) {
+        dispatch_change(state, change);
+    }
+}
+
+/// Drop empty custom statuses and reject oversized or already-expired ones.
+fn validate_custom_status(
+    custom_status: Option<CustomStatus>,
+) -> Result<Option<CustomStatus>, ClientOpError> {
+    let Some(mut custom) = custom_status else {
+        return Ok(None);
+    };
+    custom.text = custom.text.filter(|t| !t.trim().is_empty());
+    custom.emoji = custom.emoji.filter(|e| !e.trim().is_empty());
+    if custom.text.is_none() && custom.emoji.is_none() {
+        return Ok(None);
+    }
+    if custom
+        .text
+        .as_ref()
+        .is_some_and(|t| t.chars().count() > MAX_STATUS_TEXT_CHARS)
+    {
+        return Err(ClientOpError::InvalidPresence(
+            "Custom status text must be at most 128 characters",
+        ));
+    }
+    if custom
+        .emoji
+        .as_ref()
+        .is_some_and(|e| e.chars().count() > MAX_STATUS_EMOJI_CHARS)
+    {
+        return Err(ClientOpError::InvalidPresence(
+            "Custom status emoji must be at most 64 characters",
+        ));
+    }
+    if custom.expires_at.is_some_and(|at| at <= Utc::now()) {
+        return Err(ClientOpError::InvalidPresence(
+            "Custom status expires_at must be in the future",
+        ));
+    }
+    Ok(Some(custom))
+}
+
+fn validate_activities(activities: &[Activity]) -> Result<(), ClientOpError> {
+    if activities.len() > MAX_ACTIVITIES {
+        return Err(ClientOpError::InvalidPresence(
+            "At most 5 activities are allowed",
+        ));
+    }
+    for activity in activities {
+        let too_long = |s: &str| s.chars().count() > MAX_ACTIVITY_FIELD_CHARS;
+        if activity.name.trim().is_empty() || too_long(&activity.name) {
+            return Err(ClientOpError::InvalidPresence(
+                "Activity name must be 1-128 characters",
+            ));
+        }
+        if activity.details.as_deref().is_some_and(too_long) {
+            return Err(ClientOpError::InvalidPresence(
+                "Activity details must be at most 128 characters",
+            ));
+        }
+        if activity.kind == ActivityKind::Voice && activity.channel_id.is_none() {
+            return Err(ClientOpError::InvalidPresence(
+                "Voice activities require a channel_id",
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Persist the chosen status so it survives reconnects. Failures only cost
+/// persistence, so they're logged rather than surfaced.
+async fn save_presence(
+    state: &AppState,
+    user_id: &str,
+    status: &str,
+    custom_status: Option<&CustomStatus>,
+) {
+    let result = async {
+        let mut conn = state.db.get().await?;
+        diesel_async::RunQueryDsl::execute(
+            diesel::update(pod_users::table.find(user_id)).set((
+                pod_users::status.eq(status),
+                pod_users::status_text.eq(custom_status.and_then(|c| c.text.as_deref())),
+                pod_users::status_emoji.eq(custom_status.and_then(|c| c.emoji.as_deref())),
+                pod_users::status_expires_at.eq(custom_status.and_then(|c| c.expires_at)),
+            )),
+            &mut conn,
+        )
+        .await?;
+        Ok::<_, ApiError>(())
+    }
+    .await;
+    if let Err(e) = result {
+        tracing::warn!(%user_id, error = ?e, "failed to persist presence");
+    }
+}
+
+/// Broadcast the user's current presence to all of their communities.
+fn broadcast_presence(state: &AppState, user_id: &str) {
+    if let Some(change) = state.presence.get_change(user_id) {
+        dispatch_change(state, change);
+    }
+}
+
+fn dispatch_change(state: &AppState, change: PresenceChange) {
+    for community_id in &change.communities {
+        state.broadcast.dispatch(BroadcastPayload {
+            community_id: community_id.clone(),
+            event: DispatchEvent::PresenceUpdate(change.presence.clone()),
+        });
+    }
+}
@@ -2,16 +2,30 @@
 //!
 //! Presence is per-**user**, not per-session. A user is only considered offline
 //! when ALL of their gateway sessions have disconnected past the grace period.
+//!
+//! A user who chose "online" is shown as idle once none of their sessions has
+//! done anything for a while, and switches back on their next action.
 
 use std::collections::HashSet;
 use std::time::{Duration, Instant};
 
+use chrono::{DateTime, Utc};
 use dashmap::DashMap;
 
+use super::events::{Activity, CustomStatus, PresenceUpdateEvent};
+
 /// Per-user presence state.
 struct UserPresence {
     /// Current status: "online", "idle", "dnd", or "offline".
     status: String,
+    /// The status the user picked; `status` differs from it while auto-idle.
+    chosen_status: String,
+    /// Set when the server moved the user to idle for inactivity.
+    auto_idle: bool,
+    custom_status: Option<CustomStatus>,
+    activities: Vec<Activity>,
+    /// Last client action across all sessions (heartbeats don't count).
+    last_active: Instant,
     /// Number of active gateway sessions for this user.
     session_count: usize,
     /// Union of community IDs across all sessions.
@@ -28,11 +42,30 @@ pub struct OfflineUser {
     pub communities: HashSet<String>,
 }
 
+/// A presence change the caller should broadcast to `communities`.
+pub struct PresenceChange {
+    pub communities: HashSet<String>,
+    pub presence: PresenceUpdateEvent,
+}
+
+/// The presence a user last chose, as persisted in `pod_users`.
+#[derive(Debug, Clone)]
+pub struct SavedPresence {
+    pub status: String,
+    pub custom_status: Option<CustomStatus>,
+}
+
 /// Thread-safe, DashMap-backed presence registry.
 pub struct PresenceRegistry {
     inner: DashMap<String, UserPresence>,
 }
 
+impl Default for PresenceRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl PresenceRegistry {
     pub fn new() -> Self {
         Self {
@@ -49,9 +82,28 @@ impl PresenceRegistry {
         user_id: &str,
         communities: &HashSet<String>,
     ) -> Option<String> {
+        self.set_online_with(user_id, communities, None)
+    }
+
+    /// Like [`set_online`](Self::set_online), but a user who isn't currently
+    /// present starts from their `saved` presence instead of plain "online".
+    pub fn set_online_with(
+        &self,
+        user_id: &str,
+        communities: &HashSet<String>,
+        saved: Option<&SavedPresence>,
+    ) -> Option<String> {
+        let saved_status = saved.map_or("online", |s| s.status.as_str());
+        let saved_custom = saved.and_then(|s| s.custom_status.clone());
+
         let mut entry = self.inner.entry(user_id.to_string()).or_insert_with(|| {
             UserPresence {
-                status: "online".to_string(),
+                status: saved_status.to_string(),
+                chosen_status: saved_status.to_string(),
+                auto_idle: false,
+                custom_status: saved_custom.clone(),
+                activities: Vec::new(),
+                last_active: Instant::now(),
                 session_count: 0,
                 communities: HashSet::new(),
                 updated_at: Instant::now(),
@@ -64,10 +116,20 @@ impl PresenceRegistry {
         entry.communities = entry.communities.union(communities).cloned().collect();
         entry.disconnected_at = None;
         entry.updated_at = Instant::now();
+        entry.last_active = Instant::now();
 
-        // Restore to "online" if they were offline; keep "dnd" if they set it.
+        // Restore the chosen status if they were offline; keep "dnd" if they
+        // set it in another session.
         if prev_status == "offline" {
-            entry.status = "online".to_string();
+            entry.status = saved_status.to_string();
+            entry.chosen_status = saved_status.to_string();
+            if saved.is_some() {
+                entry.custom_status = saved_custom;
+            }
+        } else if entry.auto_idle {
+            // Connecting counts as activity.
+            entry.auto_idle = false;
+            entry.status = entry.chosen_status.clone();
         }
 
         let new_status = entry.status.clone();
@@ -83,6 +145,9 @@ impl PresenceRegistry {
     /// Returns the previous status if it changed.
     pub fn set_status(&self, user_id: &str, status: &str) -> Option<String> {
         let mut entry = self.inner.get_mut(user_id)?;
+        entry.chosen_status = status.to_string();
+        entry.auto_idle = false;
+        entry.last_active = Instant::now();
         let prev = entry.status.clone();
         if prev == status {
             return None;
@@ -92,6 +157,86 @@ impl PresenceRegistry {
         Some(prev)
     }
 
+    /// Replace the user's custom status. Returns `true` if it changed.
+    pub fn set_custom_status(&self, user_id: &str, custom_status: Option<CustomStatus>) -> bool {
+        let Some(mut entry) = self.inner.get_mut(user_id) else {
+            return false;
+        };
+        if entry.custom_status == custom_status {
+            return false;
+        }
+        entry.custom_status = custom_status;
+        entry.updated_at = Instant::now();
+        true
+    }
+
+    /// Replace the user's activities. Returns `true` if they changed.
+    pub fn set_activities(&self, user_id: &str, activities: Vec<Activity>) -> bool {
+        let Some(mut entry) = self.inner.get_mut(user_id) else {
+            return false;
+        };
+        if entry.activities == activities {
+            return false;
+        }
+        entry.activities = activities;
+        entry.updated_at = Instant::now();
+        true
+    }
+
+    /// Record client activity. If the user was auto-idle, restores their
+    /// chosen status and returns the change to broadcast.
+    pub fn touch(&self, user_id: &str) -> Option<PresenceChange> {
+        let mut entry = self.inner.get_mut(user_id)?;
+        entry.last_active = Instant::now();
+        if !entry.auto_idle {
+            return None;
+        }
+        entry.auto_idle = false;
+        entry.status = entry.chosen_status.clone();
+        entry.updated_at = Instant::now();
+        Some(change(user_id, &entry))
+    }
+
+    /// Move connected users who chose "online" to idle once all of their
+    /// sessions have been quiet for `idle_after`.
+    pub fn sweep_idle(&self, idle_after: Duration) -> Vec<PresenceChange> {
+        let now = Instant::now();
+        let mut changes = Vec::new();
+        for mut entry in self.inner.iter_mut() {
+            if entry.session_count > 0
+                && entry.status == "online"
+                && now.duration_since(entry.last_active) >= idle_after
+            {
+                entry.status = "idle".to_string();
+                entry.auto_idle = true;
+                entry.updated_at = now;
+                changes.push(change(entry.key(), &entry));
+            }
+        }
+        changes
+    }
+
+    /// Clear custom statuses whose `expires_at` has passed. Only users who
+    /// aren't offline are reported for broadcast.
+    pub fn sweep_expired_statuses(&self, now: DateTime<Utc>) -> Vec<PresenceChange> {
+        let mut changes = Vec::new();
+        for mut entry in self.inner.iter_mut() {
+            let expired = entry
+                .custom_status
+                .as_ref()
+                .and_then(|c| c.expires_at)
+                .is_some_and(|at| at <= now);
+            if !expired {
+                continue;
+            }
+            entry.custom_status = None;
+            if entry.status != "offline" {
+                changes.push(change(entry.key(), &entry));
+            }
+        }
+        changes
+    }
+
     /// Decrement session count when a session disconnects. If count reaches 0,
     /// sets `disconnected_at` so the sweeper can handle the grace period.
     /// No broadcast here — that's the sweeper's job.
@@ -144,6 +289,8 @@ impl PresenceRegistry {
         for (ref uid, _) in &gone_offline {
             if let Some(mut entry) = self.inner.get_mut(uid) {
                 entry.status = "offline".to_string();
+                entry.auto_idle = false;
+                entry.activities.clear();
                 entry.disconnected_at = None;
                 entry.updated_at = Instant::now();
             }
@@ -163,6 +310,30 @@ impl PresenceRegistry {
             .collect()
     }
 
+    /// Transition every user still shown as present to offline, ignoring the
+    /// grace period. Used at shutdown, when no session will come back to
+    /// this process. Returns the users that changed so the caller can broadcast.
+    pub fn flush_offline(&self) -> Vec<OfflineUser> {
+        let now = Instant::now();
+        let mut gone_offline = Vec::new();
+        for mut entry in self.inner.iter_mut() {
+            if entry.status == "offline" {
+                continue;
+            }
+            entry.status = "offline".to_string();
+            entry.auto_idle = false;
+            entry.activities.clear();
+            entry.session_count = 0;
+            entry.disconnected_at = None;
+            entry.updated_at = now;
+            gone_offline.push(OfflineUser {
+                user_id: entry.key().clone(),
+                communities: entry.communities.clone(),
+            });
+        }
+        gone_offline
+    }
+
     /// Get all non-offline users in a given community. Returns `(user_id, status)`.
     pub fn get_online_users(&self, community_id: &str) -> Vec<(String, String)> {
         let mut result = Vec::new();
@@ -179,11 +350,41 @@ impl PresenceRegistry {
     pub fn get_status(&self, user_id: &str) -> Option<String> {
         self.inner.get(user_id).map(|e| e.status.clone())
     }
+
+    /// Get a user's full presence as dispatched to clients, if tracked.
+    pub fn get_presence(&self, user_id: &str) -> Option<PresenceUpdateEvent> {
+        self.inner.get(user_id).map(|e| snapshot(user_id, &e))
+    }
+
+    /// Get a user's full presence along with the communities to broadcast to.
+    pub fn get_change(&self, user_id: &str) -> Option<PresenceChange> {
+        self.inner.get(user_id).map(|e| change(user_id, &e))
+    }
+}
+
+fn snapshot(user_id: &str, presence: &UserPresence) -> PresenceUpdateEvent {
+    if presence.status == "offline" {
+        return PresenceUpdateEvent::offline(user_id);
+    }
+    PresenceUpdateEvent {
+        user_id: user_id.to_string(),
+        status: presence.status.clone(),
+        custom_status: presence.custom_status.clone(),
+        activities: presence.activities.clone(),
+    }
+}
+
+fn change(user_id: &str, presence: &UserPresence) -> PresenceChange {
+    PresenceChange {
+        communities: presence.communities.clone(),
+        presence: snapshot(user_id, presence),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::gateway::events::ActivityKind;
 
     fn communities(ids: &[&str]) -> HashSet<String> {
         ids.iter().map(|s| s.to_string()).collect()
@@ -436,4 +637,165 @@ mod tests {
         assert_eq!(reg.get_online_users("c1").len(), 1);
         assert_eq!(reg.get_online_users("c2").len(), 1);
     }
+
+    #[test]
+    fn flush_offline_skips_grace_period() {
+        let reg = PresenceRegistry::new();
+        let comms = communities(&["c1"]);
+        reg.set_online("connected", &comms);
+        reg.set_online("grace", &comms);
+        reg.remove_session("grace", &comms);
+
+        let mut flushed: Vec<String> = reg.flush_offline().into_iter().map(|u| u.user_id).collect();
+        flushed.sort();
+        assert_eq!(flushed, vec!["connected", "grace"]);
+        assert_eq!(reg.get_status("connected").as_deref(), Some("offline"));
+        assert!(reg.get_online_users("c1").is_empty());
+
+        // Already offline — nothing to flush, and the sweeper won't repeat it.
+        assert!(reg.flush_offline().is_empty());
+        assert!(reg.sweep_offline(Duration::ZERO).is_empty());
+    }
+
+    fn custom(text: &str) -> CustomStatus {
+        CustomStatus {
+            text: Some(text.to_string()),
+            emoji: None,
+            expires_at: None,
+        }
+    }
+
+    #[test]
+    fn set_online_with_restores_saved_presence_after_offline() {
+        let reg = PresenceRegistry::new();
+        let comms = communities(&["c1"]);
+        let saved = SavedPresence {
+            status: "dnd".to_string(),
+            custom_status: Some(custom("focusing")),
+        };
+
+        assert!(reg.set_online_with("u1", &comms, Some(&saved)).is_none());
+        let presence = reg.get_presence("u1").unwrap();
+        assert_eq!(presence.status, "dnd");
+        assert_eq!(presence.custom_status, Some(custom("focusing")));
+
+        reg.flush_offline();
+        assert_eq!(
+            reg.set_online_with("u1", &comms, Some(&saved)).as_deref(),
+            Some("offline")
+        );
+        assert_eq!(reg.get_status("u1").as_deref(), Some("dnd"));
+    }
+
+    #[test]
+    fn offline_presence_hides_custom_status_and_clears_activities() {
+        let reg = PresenceRegistry::new();
+        let comms = communities(&["c1"]);
+        reg.set_online("u1", &comms);
+        reg.set_custom_status("u1", Some(custom("away")));
+        reg.set_activities(
+            "u1",
+            vec![Activity {
+                kind: ActivityKind::Playing,
+                name: "chess".to_string(),
+                details: None,
+                channel_id: None,
+                started_at: None,
+            }],
+        );
+        assert_eq!(reg.get_presence("u1").unwrap().activities.len(), 1);
+
+        reg.flush_offline();
+        let presence = reg.get_presence("u1").unwrap();
+        assert_eq!(presence.status, "offline");
+        assert!(presence.custom_status.is_none());
+
+        reg.set_online("u1", &comms);
+        let presence = reg.get_presence("u1").unwrap();
+        assert!(presence.activities.is_empty());
+        assert_eq!(presence.custom_status, Some(custom("away")));
+    }
+
+    #[test]
+    fn set_custom_status_reports_changes_only() {
+        let reg = PresenceRegistry::new();
+        reg.set_online("u1", &communities(&["c1"]));
+        assert!(reg.set_custom_status("u1", Some(custom("hi"))));
+        assert!(!reg.set_custom_status("u1", Some(custom("hi"))));
+        assert!(reg.set_custom_status("u1", None));
+        assert!(!reg.set_custom_status("unknown", Some(custom("hi"))));
+    }
+
+    #[test]
+    fn sweep_idle_moves_quiet_online_users_to_idle() {
+        let reg = PresenceRegistry::new();
+        let comms = communities(&["c1"]);
+        reg.set_online("quiet", &comms);
+        reg.set_online("busy", &comms);
+        reg.set_status("busy", "dnd");
+
+        let changes = reg.sweep_idle(Duration::ZERO);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].presence.user_id, "quiet");
+        assert_eq!(changes[0].presence.status, "idle");
+        assert_eq!(changes[0].communities, comms);
+        assert_eq!(reg.get_status("busy").as_deref(), Some("dnd"));
+
+        // Already idle — not reported again.
+        assert!(reg.sweep_idle(Duration::ZERO).is_empty());
+        assert!(reg.sweep_idle(Duration::from_secs(3600)).is_empty());
+    }
+
+    #[test]
+    fn sweep_idle_skips_disconnected_users() {
+        let reg = PresenceRegistry::new();
+        let comms = communities(&["c1"]);
+        reg.set_online("u1", &comms);
+        reg.remove_session("u1", &comms);
+        assert!(reg.sweep_idle(Duration::ZERO).is_empty());
+    }
+
+    #[test]
+    fn touch_restores_auto_idle_user() {
+        let reg = PresenceRegistry::new();
+        reg.set_online("u1", &communities(&["c1"]));
+        assert!(reg.touch("u1").is_none());
+
+        reg.sweep_idle(Duration::ZERO);
+        let change = reg.touch("u1").unwrap();
+        assert_eq!(change.presence.status, "online");
+        assert_eq!(reg.get_status("u1").as_deref(), Some("online"));
+        assert!(reg.touch("u1").is_none());
+    }
+
+    #[test]
+    fn chosen_idle_is_not_undone_by_activity() {
+        let reg = PresenceRegistry::new();
+        reg.set_online("u1", &communities(&["c1"]));
+        reg.set_status("u1", "idle");
+        assert!(reg.touch("u1").is_none());
+        assert_eq!(reg.get_status("u1").as_deref(), Some("idle"));
+    }
+
+    #[test]
+    fn sweep_expired_statuses_clears_and_reports_connected_users() {
+        let reg = PresenceRegistry::new();
+        let comms = communities(&["c1"]);
+        let now = Utc::now();
+        let expiring = CustomStatus {
+            expires_at: Some(now - chrono::Duration::seconds(1)),
+            ..custom("lunch")
+        };
+        reg.set_online("u1", &comms);
+        reg.set_custom_status("u1", Some(expiring.clone()));
+        reg.set_online("u2", &comms);
+        reg.set_custom_status("u2", Some(custom("forever")));
+
+        let changes = reg.sweep_expired_statuses(now);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].presence.user_id, "u1");
+        assert!(changes[0].presence.custom_status.is_none());
+        assert!(reg.get_presence("u2").unwrap().custom_status.is_some());
+        assert!(reg.sweep_expired_statuses(now).is_empty());
+    }
 }
@@ -1,6 +1,6 @@
 //! Session registry with per-session replay buffers for gateway resume.
 
-use std::collections::{HashSet, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
@@ -8,12 +8,21 @@ use dashmap::DashMap;
 use parking_lot::Mutex;
 use serde_json::Value;
 
+use super::version::GatewayVersion;
+
 /// Maximum number of events stored in a session's replay buffer.
 const MAX_REPLAY_BUFFER: usize = 1000;
 
 /// Sessions disconnected longer than this are eligible for cleanup.
 const SESSION_TTL: Duration = Duration::from_secs(5 * 60);
 
+/// Rate limit: at most one TYPING event per 5 seconds per channel.
+const TYPING_RATE_LIMIT: Duration = Duration::from_secs(5);
+
+/// Rate limit: at most 5 presence updates per 60 seconds.
+const PRESENCE_RATE_LIMIT_MAX: usize = 5;
+const PRESENCE_RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+
 /// A single event stored in the replay buffer.
 #[derive(Debug, Clone)]
 pub struct ReplayEntry {
@@ -28,9 +37,24 @@ pub struct SessionEntry {
     pub user_id: String,
     pub username: String,
     pub communities: HashSet<String>,
+    /// Replay buffer entries are serialized for this version, so a RESUME
+    /// must use the same one.
+    pub version: GatewayVersion,
     pub seq: u64,
     pub replay_buffer: VecDeque<ReplayEntry>,
     pub disconnected_at: Option<Instant>,
+    /// Client-op rate limit state. Kept here rather than per connection so it
+    /// applies across transports and survives RESUME.
+    pub limits: ClientOpLimits,
+}
+
+/// Rate limit state for client-sent ops (TYPING, PRESENCE_UPDATE).
+#[derive(Debug, Default)]
+pub struct ClientOpLimits {
+    /// Last accepted TYPING per channel.
+    typing_last: HashMap<String, Instant>,
+    /// Timestamps of accepted presence updates within the sliding window.
+    presence_updates: VecDeque<Instant>,
 }
 
 /// Shared registry of all gateway sessions.
@@ -41,6 +65,12 @@ pub struct SessionRegistry {
     sessions: Arc<DashMap<String, Mutex<SessionEntry>>>,
 }
 
+impl Default for SessionRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SessionRegistry {
     pub fn new() -> Self {
         Self {
@@ -49,15 +79,24 @@ impl SessionRegistry {
     }
 
     /// Register a new session after IDENTIFY.
-    pub fn register(&self, session_id: String, user_id: String, username: String, communities: HashSet<String>) {
+    pub fn register(
+        &self,
+        session_id: String,
+        user_id: String,
+        username: String,
+        communities: HashSet<String>,
+        version: GatewayVersion,
+    ) {
         let entry = SessionEntry {
             session_id: session_id.clone(),
             user_id,
             username,
             communities,
+            version,
             seq: 0,
             replay_buffer: VecDeque::new(),
             disconnected_at: None,
+            limits: ClientOpLimits::default(),
         };
         self.sessions.insert(session_id, Mutex::new(entry));
     }
@@ -79,6 +118,15 @@ impl SessionRegistry {
         }
     }
 
+    /// Record a sequence number used by a dispatch that isn't buffered (READY,
+    /// RESUMED, ephemeral events) so a later RESUME continues after it.
+    pub fn advance_seq(&self, session_id: &str, seq: u64) {
+        if let Some(entry) = self.sessions.get(session_id) {
+            let mut e = entry.lock();
+            e.seq = e.seq.max(seq);
+        }
+    }
+
     /// Mark a session as disconnected (sets `disconnected_at`).
     pub fn mark_disconnected(&self, session_id: &str) {
         if let Some(entry) = self.sessions.get(session_id) {
@@ -95,6 +143,18 @@ impl SessionRegistry {
         }
     }
 
+    /// Users with at least one live connection. Sessions waiting to be
+    /// resumed don't count.
+    pub fn connected_users(&self) -> HashSet<String> {
+        self.sessions
+            .iter()
+            .filter_map(|entry| {
+                let e = entry.lock();
+                e.disconnected_at.is_none().then(|| e.user_id.clone())
+            })
+            .collect()
+    }
+
     /// Return all buffered events with `seq > after_seq`.
     ///
     /// Returns `None` if the session doesn't exist or the requested seq is
@@ -107,8 +167,6 @@ impl SessionRegistry {
         if e.replay_buffer.is_empty() {
             return if after_seq == e.seq {
                 Some(Vec::new())
-            } else if after_seq == 0 && e.seq == 0 {
-                Some(Vec::new())
             } else {
                 None
             };
@@ -140,6 +198,52 @@ impl SessionRegistry {
         Some((e.user_id.clone(), e.username.clone(), e.communities.clone(), e.seq))
     }
 
+    /// Protocol version the session was established with.
+    pub fn get_session_version(&self, session_id: &str) -> Option<GatewayVersion> {
+        let entry = self.sessions.get(session_id)?;
+        let version = entry.lock().version;
+        Some(version)
+    }
+
+    /// Record a TYPING for `channel_id`. Returns `false` if it should be
+    /// dropped (rate limited, or the session doesn't exist).
+    pub fn allow_typing(&self, session_id: &str, channel_id: &str) -> bool {
+        let Some(entry) = self.sessions.get(session_id) else {
+            return false;
+        };
+        let mut e = entry.lock();
+        let now = Instant::now();
+        if let Some(last) = e.limits.typing_last.get(channel_id) {
+            if now.duration_since(*last) < TYPING_RATE_LIMIT {
+                return false;
+            }
+        }
+        e.limits.typing_last.insert(channel_id.to_string(), now);
+        true
+    }
+
+    /// Record a presence update. Returns `false` if it should be dropped
+    /// (sliding window exceeded, or the session doesn't exist).
+    pub fn allow_presence_update(&self, session_id: &str) -> bool {
+        let Some(entry) = self.sessions.get(session_id) else {
+            return false;
+        };
+        let mut e = entry.lock();
+        let now = Instant::now();
+        let updates = &mut e.limits.presence_updates;
+        while updates
+            .front()
+            .is_some_and(|t| now.duration_since(*t) > PRESENCE_RATE_LIMIT_WINDOW)
+        {
+            updates.pop_front();
+        }
+        if updates.len() >= PRESENCE_RATE_LIMIT_MAX {
+            return false;
+        }
+        updates.push_back(now);
+        true
+    }
+
     /// Remove sessions that have been disconnected longer than the TTL.
     /// Returns the number of sessions removed.
     pub fn cleanup_expired(&self) -> usize {
@@ -165,7 +269,13 @@ mod tests {
         let session_id = "gw_test_session".to_string();
         let mut communities = HashSet::new();
         communities.insert("comm1".to_string());
-        registry.register(session_id.clone(), "user1".to_string(), "testuser".to_string(), communities);
+        registry.register(
+            session_id.clone(),
+            "user1".to_string(),
+            "testuser".to_string(),
+            communities,
+            GatewayVersion::V1,
+        );
         (registry, session_id)
     }
 
@@ -179,6 +289,16 @@ mod tests {
         assert_eq!(seq, 0);
     }
 
+    #[test]
+    fn register_records_version() {
+        let (registry, session_id) = make_registry_with_session();
+        assert_eq!(
+            registry.get_session_version(&session_id),
+            Some(GatewayVersion::V1)
+        );
+        assert!(registry.get_session_version("bogus").is_none());
+    }
+
     #[test]
     fn get_session_info_returns_none_for_unknown() {
         let registry = SessionRegistry::new();
@@ -266,8 +386,20 @@ mod tests {
         communities.insert("c".to_string());
 
         // Create two sessions.
-        registry.register("s1".to_string(), "u1".to_string(), "user1".to_string(), communities.clone());
-        registry.register("s2".to_string(), "u2".to_string(), "user2".to_string(), communities);
+        registry.register(
+            "s1".to_string(),
+            "u1".to_string(),
+            "user1".to_string(),
+            communities.clone(),
+            GatewayVersion::V1,
+        );
+        registry.register(
+            "s2".to_string(),
+            "u2".to_string(),
+            "user2".to_string(),
+            communities,
+            GatewayVersion::V2,
+        );
 
         // Mark s1 as disconnected a long time ago.
         registry.mark_disconnected("s1");
@@ -290,4 +422,71 @@ mod tests {
         let events = registry.replay_after(&session_id, 0).unwrap();
         assert!(events.is_empty());
     }
+
+    #[test]
+    fn advance_seq_allows_resume_without_buffered_events() {
+        let (registry, session_id) = make_registry_with_session();
+        // READY at seq 1 is never buffered.
+        registry.advance_seq(&session_id, 1);
+        assert!(registry.replay_after(&session_id, 1).unwrap().is_empty());
+        let (_, _, _, seq) = registry.get_session_info(&session_id).unwrap();
+        assert_eq!(seq, 1);
+
+        // Never moves backwards.
+        registry.advance_seq(&session_id, 0);
+        let (_, _, _, seq) = registry.get_session_info(&session_id).unwrap();
+        assert_eq!(seq, 1);
+    }
+
+    #[test]
+    fn typing_is_rate_limited_per_channel() {
+        let (registry, session_id) = make_registry_with_session();
+        assert!(registry.allow_typing(&session_id, "ch1"));
+        assert!(!registry.allow_typing(&session_id, "ch1"));
+        // Other channels have their own window.
+        assert!(registry.allow_typing(&session_id, "ch2"));
+        // Unknown sessions never pass.
+        assert!(!registry.allow_typing("bogus", "ch1"));
+    }
+
+    #[test]
+    fn presence_updates_use_sliding_window() {
+        let (registry, session_id) = make_registry_with_session();
+        for _ in 0..PRESENCE_RATE_LIMIT_MAX {
+            assert!(registry.allow_presence_update(&session_id));
+        }
+        assert!(!registry.allow_presence_update(&session_id));
+
+        // Age the window out.
+        {
+            let entry = registry.sessions.get(&session_id).unwrap();
+            let mut e = entry.lock();
+            for t in e.limits.presence_updates.iter_mut() {
+                *t -= PRESENCE_RATE_LIMIT_WINDOW + Duration::from_secs(1);
+            }
+        }
+        assert!(registry.allow_presence_update(&session_id));
+    }
+
+    #[test]
+    fn connected_users_skips_disconnected_sessions() {
+        let (registry, session_id) = make_registry_with_session();
+        assert!(registry.connected_users().contains("user1"));
+
+        registry.mark_disconnected(&session_id);
+        assert!(registry.connected_users().is_empty());
+
+        // Any live session is enough.
+        registry.register(
+            "gw_second".to_string(),
+            "user1".to_string(),
+            "testuser".to_string(),
+            HashSet::new(),
+            GatewayVersion::V1,
+        );
+        assert_eq!(
+            registry.connected_users(),
+            HashSet::from(["user1".to_string()])
+        );
+    }
 }
@@ -6,6 +6,7 @@ use crate::AppState;
 use super::events::ResumePayload;
 use super::registry::ReplayEntry;
 use super::session::GatewaySession;
+use super::version::GatewayVersion;
 
 /// Process a RESUME opcode.
 ///
@@ -17,6 +18,7 @@ use super::session::GatewaySession;
 pub async fn handle_resume(
     state: &AppState,
     payload: ResumePayload,
+    version: GatewayVersion,
 ) -> Result<(GatewaySession, Vec<ReplayEntry>), &'static str> {
     // 1. Validate the PAT (non-destructive lookup).
     let pat_data = tokens::lookup_pat(state.kv.as_ref(), &payload.token)
@@ -24,34 +26,53 @@ pub async fn handle_resume(
         .map_err(|_| "Token lookup failed")?
         .ok_or("Invalid or expired token")?;
 
-    // 2. Look up the session in the registry.
-    let (session_user_id, username, communities, seq) = state
+    resume_session(state, &pat_data.user_id, &payload.session_id, payload.seq, version)
+}
+
+/// Resume `session_id` for an already-authenticated user, replaying every
+/// event after `seq`.
+pub fn resume_session(
+    state: &AppState,
+    user_id: &str,
+    session_id: &str,
+    seq: u64,
+    version: GatewayVersion,
+) -> Result<(GatewaySession, Vec<ReplayEntry>), &'static str> {
+    // 1. Look up the session in the registry.
+    let (session_user_id, username, communities, current_seq) = state
         .sessions
-        .get_session_info(&payload.session_id)
+        .get_session_info(session_id)
         .ok_or("Session not found")?;
 
-    // 3. Verify the token's user matches the session's user.
-    if pat_data.user_id != session_user_id {
-        return Err("Token user mismatch");
+    // 2. Verify the authenticated user matches the session's user.
+    if user_id != session_user_id {
+        return Err("Session user mismatch");
+    }
+
+    // 3. The replay buffer is serialized for the original version, so the
+    //    client must reconnect with the same `?v=`.
+    if state.sessions.get_session_version(session_id) != Some(version) {
+        return Err("Gateway version mismatch — please re-identify");
     }
 
     // 4. Replay events after the client's last seq.
     let replay = state
         .sessions
-        .replay_after(&payload.session_id, payload.seq)
+        .replay_after(session_id, seq)
         .ok_or("Sequence too old — please re-identify")?;
 
     // 5. Reconstruct the session with the registry's current seq.
     let session = GatewaySession::with_seq(
-        payload.session_id.clone(),
+        session_id.to_string(),
         session_user_id,
         username,
         communities,
-        seq,
+        version,
+        current_seq,
     );
 
     // 6. Mark session as connected.
-    state.sessions.mark_connected(&payload.session_id);
+    state.sessions.mark_connected(session_id);
 
     Ok((session, replay))
 }
@@ -1,38 +1,43 @@
 //! WebSocket upgrade handler and per-connection event loop.
 
-use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use axum::extract::ws::{Message, WebSocket};
-use axum::extract::{State, WebSocketUpgrade};
+use axum::extract::{Query, State, WebSocketUpgrade};
 use axum::response::IntoResponse;
 use axum::routing::get;
 use axum::Router;
-use diesel::prelude::*;
 use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
 use tokio::sync::broadcast;
 use tokio::time;
 
-use crate::db::schema::channels;
 use crate::AppState;
 
 use super::events::{
-    ClientMessage, EventName, GatewayMessage, HeartbeatPayload, IdentifyPayload,
-    PresenceUpdatePayload, ResumePayload, TypingPayload, OP_DISPATCH, OP_HEARTBEAT, OP_IDENTIFY,
-    OP_PRESENCE_UPDATE, OP_RESUME,
+    ClientMessage, DispatchEvent, GatewayMessage, HeartbeatPayload, IdentifyPayload,
+    PresenceUpdatePayload, ResumePayload, ResumedEvent, TypingPayload, OP_DISPATCH, OP_HEARTBEAT,
+    OP_IDENTIFY, OP_PRESENCE_UPDATE, OP_RESUME,
 };
-use super::fanout::BroadcastPayload;
+use super::fanout::{BroadcastPayload, ShutdownSignal};
 use super::handler::{handle_identify, HEARTBEAT_INTERVAL_MS};
+use super::ops;
 use super::resume::handle_resume;
 use super::session::GatewaySession;
+use super::version::GatewayVersion;
 
 /// Close codes (4000-range for application-level).
 const CLOSE_UNKNOWN_ERROR: u16 = 4000;
 const CLOSE_UNKNOWN_OPCODE: u16 = 4001;
 const CLOSE_NOT_AUTHENTICATED: u16 = 4003;
 const CLOSE_AUTH_FAILED: u16 = 4004;
+const CLOSE_SESSION_REVOKED: u16 = 4005;
 const CLOSE_SESSION_TIMEOUT: u16 = 4009;
+const CLOSE_INVALID_VERSION: u16 = 4012;
+
+/// Standard "service restart" close code, sent after RECONNECT on shutdown.
+const CLOSE_SERVICE_RESTART: u16 = 1012;
 
 /// Timeout for receiving IDENTIFY/RESUME after connection (seconds).
 const IDENTIFY_TIMEOUT_SECS: u64 = 10;
@@ -43,15 +48,35 @@ enum InitialOp {
     Resume(ResumePayload),
 }
 
+/// Query parameters accepted on `/gateway`.
+#[derive(Debug, Deserialize)]
+struct GatewayQuery {
+    /// Protocol version. Parsed by hand so bad values get a close code
+    /// instead of an HTTP 400.
+    v: Option<String>,
+}
+
 pub fn router() -> Router<AppState> {
     Router::new().route("/gateway", get(ws_upgrade))
 }
 
-async fn ws_upgrade(ws: WebSocketUpgrade, State(state): State<AppState>) -> impl IntoResponse {
-    ws.on_upgrade(move |socket| handle_connection(socket, state))
+async fn ws_upgrade(
+    ws: WebSocketUpgrade,
+    Query(query): Query<GatewayQuery>,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    match GatewayVersion::parse(query.v.as_deref()) {
+        Some(version) => ws.on_upgrade(move |socket| handle_connection(socket, state, version)),
+        None => ws.on_upgrade(|socket| async move {
+            let (mut ws_tx, _) = socket.split();
+            let _ = send_close(&mut ws_tx, CLOSE_INVALID_VERSION, "Unsupported gateway version").await;
+        }),
+    }
 }
 
-async fn handle_connection(socket: WebSocket, state: AppState) {
+async fn handle_connection(socket: WebSocket, state: AppState, version: GatewayVersion) {
+    // Held for the whole connection so shutdown can wait for it to drain.
+    let shutdown = state.broadcast.shutdown_signal();
     let (mut ws_tx, mut ws_rx) = socket.split();
 
     // Step 1: Wait for IDENTIFY or RESUME within timeout.
@@ -118,10 +143,10 @@ async fn handle_connection(socket: WebSocket, state: AppState) {
 
     match initial_op {
         InitialOp::Identify(payload) => {
-            handle_identify_path(&state, payload, ws_tx, ws_rx).await;
+            handle_identify_path(&state, payload, version, ws_tx, ws_rx, shutdown).await;
         }
         InitialOp::Resume(payload) => {
-            handle_resume_path(&state, payload, ws_tx, ws_rx).await;
+            handle_resume_path(&state, payload, version, ws_tx, ws_rx, shutdown).await;
         }
     }
 }
@@ -130,10 +155,12 @@ async fn handle_connection(socket: WebSocket, state: AppState) {
 async fn handle_identify_path(
     state: &AppState,
     payload: IdentifyPayload,
+    version: GatewayVersion,
     mut ws_tx: futures_util::stream::SplitSink<WebSocket, Message>,
     ws_rx: futures_util::stream::SplitStream<WebSocket>,
+    shutdown: ShutdownSignal,
 ) {
-    let (session, ready_msg) = match handle_identify(state, payload).await {
+    let (session, ready_msg) = match handle_identify(state, payload, version).await {
         Ok(result) => result,
         Err(reason) => {
             tracing::debug!(%reason, "identify handler failed");
@@ -146,24 +173,12 @@ async fn handle_identify_path(
         session_id = %session.session_id,
         user_id = %session.user_id,
         communities = session.communities.len(),
+        version = session.version.number(),
         "gateway session established"
     );
 
     // Register presence (may broadcast online to other users).
-    let prev_status = state.presence.set_online(&session.user_id, &session.communities);
-    if prev_status.is_some() {
-        // User just came online — broadcast to all their communities.
-        for community_id in &session.communities {
-            state.broadcast.dispatch(BroadcastPayload {
-                community_id: community_id.clone(),
-                event_name: EventName::PRESENCE_UPDATE.to_string(),
-                data: serde_json::json!({
-                    "user_id": session.user_id,
-                    "status": "online",
-                }),
-            });
-        }
-    }
+    ops::announce_online(state, &session).await;
 
     // Send READY.
     let ready_json = serde_json::to_string(&ready_msg).unwrap();
@@ -174,13 +189,10 @@ async fn handle_identify_path(
     // Run the main event loop.
     let session = Arc::new(session);
     let broadcast_rx = state.broadcast.subscribe();
-    run_session(session.clone(), ws_tx, ws_rx, broadcast_rx, state).await;
-
-    // Deregister presence session (sweeper handles grace period).
-    state.presence.remove_session(&session.user_id, &session.communities);
+    run_session(session.clone(), ws_tx, ws_rx, broadcast_rx, shutdown, state).await;
 
-    // Mark session as disconnected for resume support.
-    state.sessions.mark_disconnected(&session.session_id);
+    // Deregister presence and keep the session resumable.
+    ops::session_closed(state, &session);
 
     tracing::info!(
         session_id = %session.session_id,
@@ -193,10 +205,12 @@ async fn handle_identify_path(
 async fn handle_resume_path(
     state: &AppState,
     payload: ResumePayload,
+    version: GatewayVersion,
     mut ws_tx: futures_util::stream::SplitSink<WebSocket, Message>,
     ws_rx: futures_util::stream::SplitStream<WebSocket>,
+    shutdown: ShutdownSignal,
 ) {
-    let (session, replay_events) = match handle_resume(state, payload).await {
+    let (session, replay_events) = match handle_resume(state, payload, version).await {
         Ok(result) => result,
         Err(reason) => {
             tracing::debug!(%reason, "resume handler failed");
@@ -216,19 +230,7 @@ async fn handle_resume_path(
     );
 
     // Re-register presence on resume (clears any pending disconnect timer).
-    let prev_status = state.presence.set_online(&session.user_id, &session.communities);
-    if prev_status.is_some() {
-        for community_id in &session.communities {
-            state.broadcast.dispatch(BroadcastPayload {
-                community_id: community_id.clone(),
-                event_name: EventName::PRESENCE_UPDATE.to_string(),
-                data: serde_json::json!({
-                    "user_id": session.user_id,
-                    "status": "online",
-                }),
-            });
-        }
-    }
+    ops::announce_online(state, &session).await;
 
     // Subscribe to broadcasts before sending replayed events so we don't miss
     // anything that arrives concurrently.
@@ -236,7 +238,7 @@ async fn handle_resume_path(
 
     // Replay missed events.
     for entry in &replay_events {
-        let msg = GatewayMessage::dispatch(&entry.event_name, entry.seq, entry.data.clone());
+        let msg = GatewayMessage::dispatch_raw(&entry.event_name, entry.seq, entry.data.clone());
         let json = serde_json::to_string(&msg).unwrap();
         if ws_tx.send(Message::Text(json.into())).await.is_err() {
             return;
@@ -246,20 +248,19 @@ async fn handle_resume_path(
     // Send RESUMED dispatch.
     let session = Arc::new(session);
     let seq = session.next_seq();
-    let resumed_msg = GatewayMessage::dispatch(EventName::RESUMED, seq, serde_json::json!({}));
+    state.sessions.advance_seq(&session.session_id, seq);
+    let resumed_msg =
+        GatewayMessage::dispatch(&DispatchEvent::Resumed(ResumedEvent {}), seq, session.version);
     let json = serde_json::to_string(&resumed_msg).unwrap();
     if ws_tx.send(Message::Text(json.into())).await.is_err() {
         return;
     }
 
     // Enter the normal event loop.
-    run_session(session.clone(), ws_tx, ws_rx, broadcast_rx, state).await;
-
-    // Deregister presence session (sweeper handles grace period).
-    state.presence.remove_session(&session.user_id, &session.communities);
+    run_session(session.clone(), ws_tx, ws_rx, broadcast_rx, shutdown, state).await;
 
-    // Mark session as disconnected again.
-    state.sessions.mark_disconnected(&session.session_id);
+    // Deregister presence and keep the session resumable.
+    ops::session_closed(state, &session);
 
     tracing::info!(
         session_id = %session.session_id,
@@ -268,34 +269,21 @@ async fn handle_resume_path(
     );
 }
 
-/// Rate limit: at most one TYPING event per 5 seconds per channel.
-const TYPING_RATE_LIMIT_SECS: u64 = 5;
-
-/// Rate limit: at most 5 presence updates per 60 seconds.
-const PRESENCE_RATE_LIMIT_MAX: usize = 5;
-const PRESENCE_RATE_LIMIT_WINDOW_SECS: u64 = 60;
-
 /// Main session event loop: read client messages, forward broadcasts, enforce heartbeat.
 async fn run_session(
     session: Arc<GatewaySession>,
     mut ws_tx: futures_util::stream::SplitSink<WebSocket, Message>,
     mut ws_rx: futures_util::stream::SplitStream<WebSocket>,
     mut broadcast_rx: broadcast::Receiver<Arc<BroadcastPayload>>,
+    mut shutdown: ShutdownSignal,
     state: &AppState,
 ) {
-    let registry = &state.sessions;
-
     // Heartbeat deadline: client must heartbeat within 1.5× the interval.
     let heartbeat_deadline = Duration::from_millis(HEARTBEAT_INTERVAL_MS * 3 / 2);
     let mut heartbeat_timer = time::interval(heartbeat_deadline);
     heartbeat_timer.tick().await; // First tick fires immediately; skip it.
     let mut got_heartbeat = true;
-
-    // Per-channel typing rate limit state.
-    let mut typing_last: HashMap<String, Instant> = HashMap::new();
-
-    // Sliding window rate limit for presence updates.
-    let mut presence_timestamps: VecDeque<Instant> = VecDeque::new();
+    let mut disconnects = state.broadcast.subscribe_disconnects();
 
     loop {
         tokio::select! {
@@ -329,51 +317,8 @@ async fn run_session(
                                             Ok(p) => p,
                                             Err(_) => continue,
                                         };
-
-                                        // Rate limit: skip if last typing for this channel was <5s ago.
-                                        let now = Instant::now();
-                                        if let Some(last) = typing_last.get(&payload.channel_id) {
-                                            if now.duration_since(*last) < Duration::from_secs(TYPING_RATE_LIMIT_SECS) {
-                                                continue;
-                                            }
-                                        }
-                                        typing_last.insert(payload.channel_id.clone(), now);
-
-                                        // Look up channel to get community_id.
-                                        let community_id = {
-                                            let mut conn = match state.db.get().await {
-                                                Ok(c) => c,
-                                                Err(_) => continue,
-                                            };
-                                            match diesel_async::RunQueryDsl::get_result::<String>(
-                                                channels::table
-                                                    .find(&payload.channel_id)
-                                                    .select(channels::community_id),
-                                                &mut conn,
-                                            )
-                                            .await
-                                            {
-                                                Ok(cid) => cid,
-                                                Err(_) => continue, // channel not found — silently ignore
-                                            }
-                                        };
-
-                                        // Verify subscription.
-                                        if !session.is_subscribed(&community_id) {
-                                            continue;
-                                        }
-
-                                        // Broadcast TYPING_START.
-                                        state.broadcast.dispatch(BroadcastPayload {
-                                            community_id,
-                                            event_name: EventName::TYPING_START.to_string(),
-                                            data: serde_json::json!({
-                                                "channel_id": payload.channel_id,
-                                                "user_id": session.user_id,
-                                                "username": session.username,
-                                                "timestamp": chrono::Utc::now(),
-                                            }),
-                                        });
+                                        // Rate-limited or unknown channels are silently ignored.
+                                        let _ = ops::start_typing(state, &session, payload.channel_id).await;
                                     }
                                     _ => continue, // ignore unknown client events
                                 }
@@ -384,38 +329,8 @@ async fn run_session(
                                     Err(_) => continue,
                                 };
 
-                                // Only allow client-set statuses.
-                                match payload.status.as_str() {
-                                    "online" | "idle" | "dnd" => {}
-                                    _ => continue,
-                                }
-
-                                // Sliding window rate limit: max 5 per 60s.
-                                let now = Instant::now();
-                                let window = Duration::from_secs(PRESENCE_RATE_LIMIT_WINDOW_SECS);
-                                while presence_timestamps.front().map_or(false, |t| now.duration_since(*t) > window) {
-                                    presence_timestamps.pop_front();
-                                }
-                                if presence_timestamps.len() >= PRESENCE_RATE_LIMIT_MAX {
-                                    continue; // silently drop
-                                }
-                                presence_timestamps.push_back(now);
-
-                                // Update presence registry.
-                                let changed = state.presence.set_status(&session.user_id, &payload.status);
-                                if changed.is_some() {
-                                    // Broadcast to all communities the user belongs to.
-                                    for community_id in &session.communities {
-                                        state.broadcast.dispatch(BroadcastPayload {
-                                            community_id: community_id.clone(),
-                                            event_name: EventName::PRESENCE_UPDATE.to_string(),
-                                            data: serde_json::json!({
-                                                "user_id": session.user_id,
-                                                "status": payload.status,
-                                            }),
-                                        });
-                                    }
-                                }
+                                // Invalid or rate-limited updates are silently dropped.
+                                let _ = ops::update_presence(state, &session, payload).await;
                             }
                             OP_IDENTIFY => {
                                 // Already identified.
@@ -442,29 +357,13 @@ async fn run_session(
             result = broadcast_rx.recv() => {
                 match result {
                     Ok(payload) => {
-                        if !session.is_subscribed(&payload.community_id) {
+                        let Some(msg) = ops::next_dispatch(state, &session, &payload) else {
                             continue;
-                        }
-
-                        let seq = session.next_seq();
-                        let msg = GatewayMessage::dispatch(&payload.event_name, seq, payload.data.clone());
+                        };
                         let json = serde_json::to_string(&msg).unwrap();
                         if ws_tx.send(Message::Text(json.into())).await.is_err() {
                             break;
                         }
-
-                        // Write to replay buffer for resume support.
-                        // Skip ephemeral events (presence, typing) — they shouldn't be replayed.
-                        if payload.event_name != EventName::PRESENCE_UPDATE
-                            && payload.event_name != EventName::TYPING_START
-                        {
-                            registry.append_event(
-                                &session.session_id,
-                                seq,
-                                &payload.event_name,
-                                payload.data.clone(),
-                            );
-                        }
                     }
                     Err(broadcast::error::RecvError::Lagged(n)) => {
                         tracing::warn!(
@@ -480,6 +379,41 @@ async fn run_session(
                 }
             }
 
+            // The user's tokens were revoked, e.g. by a Hub ban.
+            Ok(user_id) = disconnects.recv() => {
+                if *user_id == *session.user_id {
+                    let _ = send_close(&mut ws_tx, CLOSE_SESSION_REVOKED, "Session revoked").await;
+                    break;
+                }
+            }
+
+            // Server is shutting down — the session stays resumable, so ask
+            // the client to RESUME against the next instance.
+            _ = shutdown.recv() => {
+                // Deliver what was dispatched before shutdown began, such as
+                // the final offline presences, ahead of RECONNECT.
+                loop {
+                    match broadcast_rx.try_recv() {
+                        Ok(payload) => {
+                            let Some(msg) = ops::next_dispatch(state, &session, &payload) else {
+                                continue;
+                            };
+                            let json = serde_json::to_string(&msg).unwrap();
+                            if ws_tx.send(Message::Text(json.into())).await.is_err() {
+                                break;
+                            }
+                        }
+                        Err(broadcast::error::TryRecvError::Lagged(_)) => continue,
+                        Err(_) => break,
+                    }
+                }
+                let reconnect = GatewayMessage::reconnect("Server restarting");
+                let json = serde_json::to_string(&reconnect).unwrap();
+                let _ = ws_tx.send(Message::Text(json.into())).await;
+                let _ = send_close(&mut ws_tx, CLOSE_SERVICE_RESTART, "Server restarting").await;
+                break;
+            }
+
             // Heartbeat timeout check.
             _ = heartbeat_timer.tick() => {
                 if !got_heartbeat {
@@ -3,6 +3,8 @@
 use std::collections::HashSet;
 use std::sync::atomic::{AtomicU64, Ordering};
 
+use super::version::GatewayVersion;
+
 /// State for a single WebSocket connection.
 pub struct GatewaySession {
     /// Unique session identifier (`gw_` prefixed ULID).
@@ -13,17 +15,26 @@ pub struct GatewaySession {
     pub username: String,
     /// Community IDs this user is a member of (populated at IDENTIFY).
     pub communities: HashSet<String>,
+    /// Protocol version negotiated via `/gateway?v=`.
+    pub version: GatewayVersion,
     /// Monotonically increasing sequence number for dispatch events.
     seq: AtomicU64,
 }
 
 impl GatewaySession {
-    pub fn new(session_id: String, user_id: String, username: String, communities: HashSet<String>) -> Self {
+    pub fn new(
+        session_id: String,
+        user_id: String,
+        username: String,
+        communities: HashSet<String>,
+        version: GatewayVersion,
+    ) -> Self {
         Self {
             session_id,
             user_id,
             username,
             communities,
+            version,
             seq: AtomicU64::new(0),
         }
     }
@@ -34,6 +45,7 @@ impl GatewaySession {
         user_id: String,
         username: String,
         communities: HashSet<String>,
+        version: GatewayVersion,
         seq: u64,
     ) -> Self {
         Self {
@@ -41,6 +53,7 @@ impl GatewaySession {
             user_id,
             username,
             communities,
+            version,
             seq: AtomicU64::new(seq),
         }
     }
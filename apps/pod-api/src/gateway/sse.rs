@@ -0,0 +1,257 @@
+//! Server-Sent Events transport for clients that can't hold a WebSocket open.
+//!
+//! `GET /gateway/sse?ticket=<ws_ticket>&v=<version>` authenticates with the
+//! same single-use ticket as IDENTIFY and streams the same frames as
+//! `/gateway`: each SSE event is named after the dispatch (`READY`,
+//! `MESSAGE_CREATE`, ...), carries the full `{ op, t, s, d }` frame as `data`,
+//! and has `id: <session_id>:<seq>`.
+//!
+//! To resume, reconnect with a fresh ticket and the last id seen, either as the
+//! `Last-Event-ID` header or the `last_event_id` query parameter (browsers'
+//! `EventSource` can't set headers on a new connection). Missed events are
+//! replayed, followed by `RESUMED`. If the session can no longer be resumed,
+//! the stream starts a new session with `READY` instead.
+//!
+//! There is no heartbeat opcode: the server sends keep-alive comments and
+//! treats a closed stream as a disconnect. On shutdown the server sends a
+//! `RECONNECT` event and ends the stream. Client-sent ops go through the
+//! REST endpoints in `routes::gateway`.
+
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use axum::extract::{Query, State};
+use axum::http::HeaderMap;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::routing::get;
+use axum::Router;
+use futures_util::Stream;
+use serde::Deserialize;
+use tokio::sync::{broadcast, mpsc};
+
+use crate::error::ApiError;
+use crate::AppState;
+
+use super::events::{DispatchEvent, GatewayMessage, ResumedEvent};
+use super::fanout::{BroadcastPayload, ShutdownSignal};
+use super::handler::{consume_ticket, identify_user};
+use super::ops;
+use super::resume::resume_session;
+use super::session::GatewaySession;
+use super::version::GatewayVersion;
+
+/// Events buffered between the session task and the HTTP response body.
+const SSE_BUFFER: usize = 64;
+
+/// Query parameters accepted on `/gateway/sse`.
+#[derive(Debug, Deserialize)]
+struct SseQuery {
+    ticket: String,
+    v: Option<String>,
+    last_event_id: Option<String>,
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/gateway/sse", get(sse_connect))
+}
+
+async fn sse_connect(
+    State(state): State<AppState>,
+    Query(query): Query<SseQuery>,
+    headers: HeaderMap,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let version = GatewayVersion::parse(query.v.as_deref())
+        .ok_or_else(|| ApiError::bad_request("Unsupported gateway version"))?;
+
+    let user_id = consume_ticket(&state, &query.ticket)
+        .await
+        .map_err(ApiError::unauthorized)?;
+
+    let last_event_id = headers
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+        .or(query.last_event_id);
+
+    // Subscribe before building the session so nothing dispatched in between
+    // is missed.
+    let broadcast_rx = state.broadcast.subscribe();
+
+    let resumed =
+        last_event_id
+            .as_deref()
+            .and_then(parse_event_id)
+            .and_then(|(session_id, seq)| {
+                resume_session(&state, &user_id, session_id, seq, version)
+                    .inspect_err(|reason| tracing::debug!(%reason, "sse resume failed"))
+                    .ok()
+            });
+
+    let (session, initial) = match resumed {
+        Some((session, replay)) => {
+            let mut frames: Vec<GatewayMessage> = replay
+                .into_iter()
+                .map(|e| GatewayMessage::dispatch_raw(&e.event_name, e.seq, e.data))
+                .collect();
+            let seq = session.next_seq();
+            state.sessions.advance_seq(&session.session_id, seq);
+            frames.push(GatewayMessage::dispatch(
+                &DispatchEvent::Resumed(ResumedEvent {}),
+                seq,
+                version,
+            ));
+            tracing::info!(
+                session_id = %session.session_id,
+                user_id = %session.user_id,
+                replayed = frames.len() - 1,
+                "sse gateway session resumed"
+            );
+            (session, frames)
+        }
+        None => {
+            let (session, ready) = identify_user(&state, user_id, version)
+                .await
+                .map_err(ApiError::unauthorized)?;
+            tracing::info!(
+                session_id = %session.session_id,
+                user_id = %session.user_id,
+                version = version.number(),
+                "sse gateway session established"
+            );
+            (session, vec![ready])
+        }
+    };
+
+    ops::announce_online(&state, &session).await;
+
+    let shutdown = state.broadcast.shutdown_signal();
+    let (tx, rx) = mpsc::channel(SSE_BUFFER);
+    let session = Arc::new(session);
+    tokio::spawn(run_session(
+        state,
+        session,
+        initial,
+        broadcast_rx,
+        shutdown,
+        tx,
+    ));
+
+    let stream = futures_util::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|event| (Ok(event), rx))
+    });
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+/// Forward broadcasts to the stream until the client goes away.
+async fn run_session(
+    state: AppState,
+    session: Arc<GatewaySession>,
+    initial: Vec<GatewayMessage>,
+    mut broadcast_rx: broadcast::Receiver<Arc<BroadcastPayload>>,
+    mut shutdown: ShutdownSignal,
+    tx: mpsc::Sender<Event>,
+) {
+    for msg in &initial {
+        if tx.send(to_event(&session, msg)).await.is_err() {
+            ops::session_closed(&state, &session);
+            return;
+        }
+    }
+
+    let mut disconnects = state.broadcast.subscribe_disconnects();
+    loop {
+        tokio::select! {
+            // Response body dropped — the client disconnected.
+            _ = tx.closed() => break,
+
+            // The user's tokens were revoked, e.g. by a Hub ban.
+            Ok(user_id) = disconnects.recv() => {
+                if *user_id == *session.user_id {
+                    break;
+                }
+            }
+
+            // Server is shutting down — ending the stream lets the HTTP
+            // server drain, and the client resumes with its Last-Event-ID.
+            _ = shutdown.recv() => {
+                // Events queued before shutdown began still go out first.
+                loop {
+                    match broadcast_rx.try_recv() {
+                        Ok(payload) => {
+                            let Some(msg) = ops::next_dispatch(&state, &session, &payload) else {
+                                continue;
+                            };
+                            if tx.send(to_event(&session, &msg)).await.is_err() {
+                                break;
+                            }
+                        }
+                        Err(broadcast::error::TryRecvError::Lagged(_)) => continue,
+                        Err(_) => break,
+                    }
+                }
+                let reconnect = GatewayMessage::reconnect("Server restarting");
+                let _ = tx.send(to_event(&session, &reconnect).event("RECONNECT")).await;
+                break;
+            }
+
+            result = broadcast_rx.recv() => {
+                match result {
+                    Ok(payload) => {
+                        let Some(msg) = ops::next_dispatch(&state, &session, &payload) else {
+                            continue;
+                        };
+                        if tx.send(to_event(&session, &msg)).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        tracing::warn!(
+                            session_id = %session.session_id,
+                            skipped = n,
+                            "sse gateway session lagged behind broadcast"
+                        );
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        }
+    }
+
+    ops::session_closed(&state, &session);
+
+    tracing::info!(
+        session_id = %session.session_id,
+        user_id = %session.user_id,
+        "sse gateway session ended"
+    );
+}
+
+/// Wrap a gateway frame as an SSE event.
+fn to_event(session: &GatewaySession, msg: &GatewayMessage) -> Event {
+    let mut event = Event::default().data(serde_json::to_string(msg).unwrap());
+    if let Some(t) = &msg.t {
+        event = event.event(t);
+    }
+    if let Some(s) = msg.s {
+        event = event.id(format!("{}:{s}", session.session_id));
+    }
+    event
+}
+
+/// Split a `<session_id>:<seq>` event id.
+fn parse_event_id(id: &str) -> Option<(&str, u64)> {
+    let (session_id, seq) = id.rsplit_once(':')?;
+    Some((session_id, seq.parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_event_ids() {
+        assert_eq!(parse_event_id("gw_01ABC:42"), Some(("gw_01ABC", 42)));
+        assert_eq!(parse_event_id("gw_01ABC"), None);
+        assert_eq!(parse_event_id("gw_01ABC:x"), None);
+    }
+}
@@ -0,0 +1,69 @@
+//! Gateway protocol versions, negotiated with `/gateway?v=N` (RFC §20.2).
+//!
+//! A version only needs a new serializer arm in [`DispatchEvent::data`] when
+//! an event's shape changes; everything else is shared across versions.
+//!
+//! | Version | Changes                                              |
+//! | ------- | ---------------------------------------------------- |
+//! | 1       | Initial protocol                                     |
+//! | 2       | `MESSAGE_REACTION_ADD` includes `channel_id`         |
+//!
+//! [`DispatchEvent::data`]: super::events::DispatchEvent::data
+
+/// A supported Gateway protocol version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum GatewayVersion {
+    V1 = 1,
+    V2 = 2,
+}
+
+impl GatewayVersion {
+    /// Used when the client omits `?v=` (clients that predate versioning).
+    pub const DEFAULT: Self = Self::V1;
+    /// The newest version this server speaks.
+    pub const LATEST: Self = Self::V2;
+    /// Every supported version, oldest first.
+    pub const ALL: &'static [Self] = &[Self::V1, Self::V2];
+
+    /// Parse the `v` query parameter. Returns `None` for unsupported versions.
+    pub fn parse(v: Option<&str>) -> Option<Self> {
+        match v {
+            None => Some(Self::DEFAULT),
+            Some(raw) => match raw.trim().parse::<u8>().ok()? {
+                1 => Some(Self::V1),
+                2 => Some(Self::V2),
+                _ => None,
+            },
+        }
+    }
+
+    /// The numeric version as sent on the wire.
+    pub fn number(self) -> u8 {
+        self as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_version_uses_default() {
+        assert_eq!(GatewayVersion::parse(None), Some(GatewayVersion::DEFAULT));
+    }
+
+    #[test]
+    fn parses_supported_versions() {
+        assert_eq!(GatewayVersion::parse(Some("1")), Some(GatewayVersion::V1));
+        assert_eq!(GatewayVersion::parse(Some("2")), Some(GatewayVersion::V2));
+        assert_eq!(GatewayVersion::LATEST.number(), 2);
+    }
+
+    #[test]
+    fn rejects_unsupported_versions() {
+        assert_eq!(GatewayVersion::parse(Some("0")), None);
+        assert_eq!(GatewayVersion::parse(Some("3")), None);
+        assert_eq!(GatewayVersion::parse(Some("abc")), None);
+        assert_eq!(GatewayVersion::parse(Some("")), None);
+    }
+}
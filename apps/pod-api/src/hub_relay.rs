@@ -0,0 +1,96 @@
+//! Client for the Hub notification relay (RFC §16.5.2).
+//!
+//! Members with no live gateway session don't see `MESSAGE_CREATE`, so the Pod
+//! tells the Hub instead, which forwards an envelope to their Hub WebSocket.
+//! Envelopes say where the activity is and how much of it there is, never
+//! what was said or by whom.
+
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::Serialize;
+
+/// Most envelopes the Hub accepts in one request.
+const MAX_RELAY_BATCH: usize = 1000;
+
+/// Relay requests are best-effort; don't let a slow Hub pile them up.
+const RELAY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long a channel's relay waits for further messages before counting, so
+/// a burst of messages costs one relay rather than one each.
+pub const RELAY_DEBOUNCE: Duration = Duration::from_secs(1);
+
+/// Unread activity in a channel for one user.
+#[derive(Debug, Clone, Serialize)]
+pub struct RelayNotification {
+    pub user_id: String,
+    pub community_id: String,
+    pub channel_id: String,
+    pub unread_count: i64,
+    pub has_mention: bool,
+}
+
+#[derive(Serialize)]
+struct RelayRequest<'a> {
+    notifications: &'a [RelayNotification],
+}
+
+/// Pushes envelopes to `POST {hub_url}/api/v1/notifications/relay`,
+/// authenticated with the Pod's client secret. Cloneable — store in AppState.
+#[derive(Clone)]
+pub struct HubRelay {
+    relay_url: String,
+    client_secret: String,
+    http: reqwest::Client,
+    /// Channels with a relay waiting out [`RELAY_DEBOUNCE`].
+    pending: Arc<Mutex<HashSet<String>>>,
+}
+
+impl HubRelay {
+    pub fn new(hub_url: &str, client_secret: &str) -> Self {
+        Self {
+            relay_url: format!(
+                "{}/api/v1/notifications/relay",
+                hub_url.trim_end_matches('/')
+            ),
+            client_secret: client_secret.to_string(),
+            http: reqwest::Client::new(),
+            pending: Arc::default(),
+        }
+    }
+
+    /// Wait out [`RELAY_DEBOUNCE`] for `channel_id`. Returns `false` straight
+    /// away if a relay for the channel is already waiting — its counts, taken
+    /// afterwards, will include this message too.
+    pub async fn debounce(&self, channel_id: &str) -> bool {
+        if !self.pending.lock().unwrap().insert(channel_id.to_string()) {
+            return false;
+        }
+        tokio::time::sleep(RELAY_DEBOUNCE).await;
+        self.pending.lock().unwrap().remove(channel_id);
+        true
+    }
+
+    /// Send envelopes to the Hub in batches. Failures are logged and dropped:
+    /// the next message in the channel carries the current counts anyway.
+    pub async fn send(&self, notifications: &[RelayNotification]) {
+        for batch in notifications.chunks(MAX_RELAY_BATCH) {
+            let result = self
+                .http
+                .post(&self.relay_url)
+                .bearer_auth(&self.client_secret)
+                .timeout(RELAY_TIMEOUT)
+                .json(&RelayRequest {
+                    notifications: batch,
+                })
+                .send()
+                .await
+                .and_then(|resp| resp.error_for_status());
+            if let Err(e) = result {
+                tracing::warn!(error = %e, count = batch.len(), "failed to relay notifications to hub");
+                return;
+            }
+        }
+    }
+}
@@ -3,6 +3,7 @@ pub mod config;
 pub mod db;
 pub mod error;
 pub mod gateway;
+pub mod hub_relay;
 pub mod models;
 pub mod permissions;
 pub mod pod_permissions;
@@ -17,6 +18,7 @@ use db::pool::DbPool;
 use gateway::fanout::GatewayBroadcast;
 use gateway::presence::PresenceRegistry;
 use gateway::registry::SessionRegistry;
+use hub_relay::HubRelay;
 use voxora_common::SnowflakeGenerator;
 
 /// Shared application state available to all route handlers.
@@ -30,4 +32,5 @@ pub struct AppState {
     pub broadcast: Arc<GatewayBroadcast>,
     pub sessions: Arc<SessionRegistry>,
     pub presence: Arc<PresenceRegistry>,
+    pub relay: HubRelay,
 }
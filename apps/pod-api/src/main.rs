@@ -13,9 +13,11 @@ use utoipa_swagger_ui::SwaggerUi;
 use pod_api::auth::jwks::JwksClient;
 use pod_api::config::Config;
 use pod_api::db::kv::{KeyValueStore, MemoryStore};
-use pod_api::gateway::fanout::GatewayBroadcast;
-use pod_api::gateway::presence::PresenceRegistry;
+use pod_api::gateway::events::{DispatchEvent, PresenceUpdateEvent};
+use pod_api::gateway::fanout::{BroadcastPayload, GatewayBroadcast};
+use pod_api::gateway::presence::{OfflineUser, PresenceChange, PresenceRegistry};
 use pod_api::gateway::registry::SessionRegistry;
+use pod_api::hub_relay::HubRelay;
 use pod_api::routes::ApiDoc;
 use pod_api::AppState;
 use std::path::Path;
@@ -36,6 +38,7 @@ async fn main() {
 
     let config = Config::from_env();
     let port = config.port;
+    let shutdown_timeout = Duration::from_secs(config.shutdown_timeout_secs);
 
     // Connect to PostgreSQL.
     let db = pod_api::db::pool::connect(&config.database_url).await;
@@ -46,6 +49,9 @@ async fn main() {
     // JWKS client for validating Hub SIA tokens.
     let jwks = JwksClient::new(&config.hub_url);
 
+    // Notification envelopes for members without a live gateway session.
+    let relay = HubRelay::new(&config.hub_url, &config.pod_client_secret);
+
     tracing::info!(pod_id = %config.pod_id, hub_url = %config.hub_url, "pod-api configured");
 
     let snowflake = Arc::new(SnowflakeGenerator::new(0));
@@ -66,30 +72,26 @@ async fn main() {
         }
     });
 
-    // Spawn background task to sweep expired presence entries (every 5s).
+    // Spawn background task to sweep expired presence entries, idle users and
+    // expired custom statuses (every 5s).
     {
         let sweep_presence = presence.clone();
         let sweep_broadcast = broadcast.clone();
+        let idle_after = (config.presence_idle_after_secs > 0)
+            .then(|| Duration::from_secs(config.presence_idle_after_secs));
         tokio::spawn(async move {
             let mut interval = tokio::time::interval(Duration::from_secs(5));
             let grace = Duration::from_secs(30);
             loop {
                 interval.tick().await;
                 let gone_offline = sweep_presence.sweep_offline(grace);
-                for user in gone_offline {
-                    for community_id in &user.communities {
-                        sweep_broadcast.dispatch(
-                            pod_api::gateway::fanout::BroadcastPayload {
-                                community_id: community_id.clone(),
-                                event_name: "PRESENCE_UPDATE".to_string(),
-                                data: serde_json::json!({
-                                    "user_id": user.user_id,
-                                    "status": "offline",
-                                }),
-                            },
-                        );
-                    }
+                broadcast_offline(&sweep_broadcast, gone_offline);
+                if let Some(idle_after) = idle_after {
+                    let gone_idle = sweep_presence.sweep_idle(idle_after);
+                    broadcast_changes(&sweep_broadcast, gone_idle);
                 }
+                let expired = sweep_presence.sweep_expired_statuses(chrono::Utc::now());
+                broadcast_changes(&sweep_broadcast, expired);
             }
         });
     }
@@ -100,9 +102,10 @@ async fn main() {
         jwks,
         config: Arc::new(config),
         snowflake,
-        broadcast,
+        broadcast: broadcast.clone(),
         sessions,
-        presence,
+        presence: presence.clone(),
+        relay,
     };
 
     let cors = CorsLayer::new()
@@ -123,5 +126,71 @@ async fn main() {
     let listener = tokio::net::TcpListener::bind(addr)
         .await
         .expect("failed to bind");
-    axum::serve(listener, app).await.expect("server error");
+
+    let (stop_tx, stop_rx) = tokio::sync::oneshot::channel::<()>();
+    let mut server = tokio::spawn(async move {
+        axum::serve(listener, app)
+            .with_graceful_shutdown(async {
+                let _ = stop_rx.await;
+            })
+            .await
+    });
+
+    tokio::select! {
+        _ = voxora_common::shutdown::signal() => {}
+        result = &mut server => voxora_common::shutdown::server_stopped(result),
+    }
+    tracing::info!(
+        connections = broadcast.live_connections(),
+        "shutdown signal received, draining"
+    );
+
+    // Stop accepting connections.
+    let _ = stop_tx.send(());
+
+    // Nothing will reconnect to this process, so skip the grace period. Tell
+    // peers while their connections are still open.
+    let gone_offline = presence.flush_offline();
+    tracing::info!(users = gone_offline.len(), "flushed presence offline");
+    broadcast_offline(&broadcast, gone_offline);
+
+    // Ask every gateway session to RESUME elsewhere. Sessions stay in the
+    // registry so a RESUME can find them.
+    broadcast.begin_shutdown();
+
+    let drained = tokio::time::timeout(shutdown_timeout, async {
+        let _ = server.await;
+        broadcast.drained().await;
+    })
+    .await;
+    if drained.is_err() {
+        tracing::warn!(
+            connections = broadcast.live_connections(),
+            "shutdown deadline reached before all connections drained"
+        );
+    }
+}
+
+/// Broadcast `PRESENCE_UPDATE offline` for each user to their communities.
+fn broadcast_offline(broadcast: &GatewayBroadcast, users: Vec<OfflineUser>) {
+    for user in users {
+        for community_id in &user.communities {
+            broadcast.dispatch(BroadcastPayload {
+                community_id: community_id.clone(),
+                event: DispatchEvent::PresenceUpdate(PresenceUpdateEvent::offline(&user.user_id)),
+            });
+        }
+    }
+}
+
+/// Broadcast each change as `PRESENCE_UPDATE` to the user's communities.
+fn broadcast_changes(broadcast: &GatewayBroadcast, changes: Vec<PresenceChange>) {
+    for change in changes {
+        for community_id in &change.communities {
+            broadcast.dispatch(BroadcastPayload {
+                community_id: community_id.clone(),
+                event: DispatchEvent::PresenceUpdate(change.presence.clone()),
+            });
+        }
+    }
 }
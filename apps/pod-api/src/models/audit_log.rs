@@ -36,6 +36,7 @@ pub struct AuditLogEntry {
 }
 
 /// Reusable helper to insert an audit log entry.
+#[allow(clippy::too_many_arguments)]
 pub async fn log(
     pool: &DbPool,
     community_id: &str,
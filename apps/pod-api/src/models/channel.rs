@@ -16,7 +16,7 @@ pub struct UpdateChannel {
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Queryable, Selectable, Serialize, ToSchema)]
+#[derive(Debug, Clone, Queryable, Selectable, Serialize, ToSchema)]
 #[diesel(table_name = channels)]
 pub struct Channel {
     pub id: String,
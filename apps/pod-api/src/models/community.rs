@@ -7,7 +7,7 @@ use crate::db::schema::communities;
 use crate::models::channel::Channel;
 use crate::models::role::Role;
 
-#[derive(Debug, Queryable, Selectable, Serialize, ToSchema)]
+#[derive(Debug, Clone, Queryable, Selectable, Serialize, ToSchema)]
 #[diesel(table_name = communities)]
 pub struct Community {
     pub id: String,
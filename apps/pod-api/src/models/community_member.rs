@@ -17,7 +17,7 @@ pub struct CommunityMemberRow {
 }
 
 /// Enriched member returned by the API (member + user info).
-#[derive(Debug, Serialize, ToSchema)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct CommunityMember {
     pub community_id: String,
     pub user_id: String,
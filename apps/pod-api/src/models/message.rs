@@ -5,6 +5,9 @@ use utoipa::ToSchema;
 
 use crate::db::schema::messages;
 
+/// Message `flags` bit: sent by a bot account, so clients can mark it.
+pub const MESSAGE_FLAG_BOT: i32 = 1 << 0;
+
 fn serialize_i64_as_string<S: Serializer>(val: &i64, s: S) -> Result<S::Ok, S::Error> {
     s.serialize_str(&val.to_string())
 }
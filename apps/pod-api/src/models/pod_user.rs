@@ -5,6 +5,11 @@ use utoipa::ToSchema;
 
 use crate::db::schema::pod_users;
 
+// `hub_flags` bits, mapped from the SIA `flags` claim names.
+pub const HUB_FLAG_STAFF: i64 = 1 << 0;
+pub const HUB_FLAG_VERIFIED: i64 = 1 << 1;
+pub const HUB_FLAG_BOT: i64 = 1 << 2;
+
 /// A user record local to this Pod, created from a Hub SIA.
 #[derive(Debug, Queryable, Selectable, Serialize, ToSchema)]
 #[diesel(table_name = pod_users)]
@@ -17,6 +22,15 @@ pub struct PodUser {
     pub status: String,
     pub first_seen_at: DateTime<Utc>,
     pub last_seen_at: DateTime<Utc>,
+    pub status_text: Option<String>,
+    pub status_emoji: Option<String>,
+    pub status_expires_at: Option<DateTime<Utc>>,
+}
+
+impl PodUser {
+    pub fn is_bot(&self) -> bool {
+        self.hub_flags & HUB_FLAG_BOT != 0
+    }
 }
 
 /// Insertable form for creating a new pod_user.
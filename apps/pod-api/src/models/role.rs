@@ -5,7 +5,7 @@ use utoipa::ToSchema;
 
 use crate::db::schema::roles;
 
-#[derive(Debug, Queryable, Selectable, Serialize, ToSchema)]
+#[derive(Debug, Clone, Queryable, Selectable, Serialize, ToSchema)]
 #[diesel(table_name = roles)]
 pub struct Role {
     pub id: String,
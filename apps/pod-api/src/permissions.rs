@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use diesel::prelude::*;
 use diesel::result::OptionalExtension;
 
@@ -188,23 +190,123 @@ pub async fn check_channel_permission(
     )
     .await?;
 
-    // Collect the user's role IDs (including the @everyone default role).
-    let user_role_ids: Vec<&str> = role_rows.iter().map(|(id, _, _)| id.as_str()).collect();
+    // 6. Apply the overrides on top of the base permissions.
+    if has_channel_permission(&role_rows, &member.roles, &overrides, user_id, required) {
+        Ok(())
+    } else {
+        Err(ApiError::forbidden(
+            "You do not have permission to perform this action",
+        ))
+    }
+}
+
+/// Members of a community who hold `required` in `channel_id`, resolved with
+/// a fixed number of queries however large the community is. Members with the
+/// same roles and no override of their own share one evaluation.
+pub async fn channel_members_with_permission(
+    pool: &DbPool,
+    community_id: &str,
+    channel_id: &str,
+    required: i64,
+) -> Result<Vec<String>, ApiError> {
+    let mut conn = pool.get().await?;
+
+    let owner_id: String = diesel_async::RunQueryDsl::get_result(
+        communities::table
+            .find(community_id)
+            .select(communities::owner_id),
+        &mut conn,
+    )
+    .await?;
+
+    let members: Vec<(String, Vec<String>)> = diesel_async::RunQueryDsl::load(
+        community_members::table
+            .filter(community_members::community_id.eq(community_id))
+            .select((community_members::user_id, community_members::roles)),
+        &mut conn,
+    )
+    .await?;
 
-    // 6. Apply role overrides: OR together allow/deny for each role the user has.
+    let role_rows: Vec<(String, i64, bool)> = diesel_async::RunQueryDsl::load(
+        roles::table
+            .filter(roles::community_id.eq(community_id))
+            .select((roles::id, roles::permissions, roles::is_default)),
+        &mut conn,
+    )
+    .await?;
+
+    let overrides: Vec<ChannelOverride> = diesel_async::RunQueryDsl::load(
+        channel_overrides::table
+            .filter(channel_overrides::channel_id.eq(channel_id))
+            .select(ChannelOverride::as_select()),
+        &mut conn,
+    )
+    .await?;
+
+    let mut by_roles: HashMap<Vec<String>, bool> = HashMap::new();
+    let mut permitted = Vec::new();
+    for (user_id, mut member_roles) in members {
+        let allowed = if user_id == owner_id {
+            true
+        } else if overrides
+            .iter()
+            .any(|ov| ov.target_type == 1 && ov.target_id == user_id)
+        {
+            has_channel_permission(&role_rows, &member_roles, &overrides, &user_id, required)
+        } else {
+            member_roles.sort_unstable();
+            member_roles.dedup();
+            *by_roles
+                .entry(member_roles)
+                .or_insert_with_key(|member_roles| {
+                    has_channel_permission(&role_rows, member_roles, &overrides, &user_id, required)
+                })
+        };
+        if allowed {
+            permitted.push(user_id);
+        }
+    }
+
+    Ok(permitted)
+}
+
+/// Whether a member holding `member_roles` has `required` in a channel.
+/// `role_rows` are `(id, permissions, is_default)` and may include roles the
+/// member doesn't hold; `overrides` are the channel's.
+fn has_channel_permission(
+    role_rows: &[(String, i64, bool)],
+    member_roles: &[String],
+    overrides: &[ChannelOverride],
+    user_id: &str,
+    required: i64,
+) -> bool {
+    // Roles that apply: explicit + @everyone.
+    let held: Vec<&(String, i64, bool)> = role_rows
+        .iter()
+        .filter(|(id, _, is_default)| *is_default || member_roles.contains(id))
+        .collect();
+
+    let base: i64 = held.iter().fold(0i64, |acc, (_, p, _)| acc | p);
+
+    // ADMINISTRATOR bypass.
+    if base & ADMINISTRATOR != 0 {
+        return true;
+    }
+
+    // Role overrides: OR together allow/deny for each role the user has.
     let mut channel_allow: i64 = 0;
     let mut channel_deny: i64 = 0;
 
-    for ov in &overrides {
+    for ov in overrides {
         // target_type 0 = role
-        if ov.target_type == 0 && user_role_ids.contains(&ov.target_id.as_str()) {
+        if ov.target_type == 0 && held.iter().any(|(id, _, _)| *id == ov.target_id) {
             channel_allow |= ov.allow;
             channel_deny |= ov.deny;
         }
     }
 
-    // 7. Apply user-specific overrides last (higher priority — override role overrides).
-    for ov in &overrides {
+    // User-specific overrides last (higher priority — override role overrides).
+    for ov in overrides {
         // target_type 1 = user
         if ov.target_type == 1 && ov.target_id == user_id {
             channel_allow |= ov.allow;
@@ -212,14 +314,7 @@ pub async fn check_channel_permission(
         }
     }
 
-    // 8. effective = (base & ~channel_deny) | channel_allow
+    // effective = (base & ~channel_deny) | channel_allow
     let effective = (base & !channel_deny) | channel_allow;
-
-    if effective & required != 0 {
-        Ok(())
-    } else {
-        Err(ApiError::forbidden(
-            "You do not have permission to perform this action",
-        ))
-    }
+    effective & required != 0
 }
@@ -44,6 +44,8 @@ pub struct UserInfo {
     pub username: String,
     pub display_name: String,
     pub avatar_url: Option<String>,
+    /// Whether the Hub flagged this account as a bot.
+    pub bot: bool,
 }
 
 #[utoipa::path(
@@ -54,6 +56,7 @@ pub struct UserInfo {
     responses(
         (status = 200, description = "Login successful", body = LoginResponse),
         (status = 401, description = "Invalid SIA token", body = ApiErrorBody),
+        (status = 403, description = "Banned from the Pod or suspended by the Hub", body = ApiErrorBody),
     ),
 )]
 pub async fn login(
@@ -70,6 +73,11 @@ pub async fn login(
     )
     .await?;
 
+    // An SIA minted before the Hub banned the user doesn't get past the ban.
+    if tokens::is_revoked(state.kv.as_ref(), &claims.sub, claims.iat).await? {
+        return Err(ApiError::forbidden("Your Voxora account is suspended"));
+    }
+
     // Convert flags vec to bitfield for storage.
     let hub_flags = flags_to_bitfield(&claims.flags);
 
@@ -90,6 +98,7 @@ pub async fn login(
     }
 
     // Generate tokens.
+    let signed_in_at = chrono::Utc::now().timestamp();
     let pat = tokens::generate_pat();
     let refresh = tokens::generate_refresh_token();
     let ws_ticket = tokens::generate_ws_ticket();
@@ -101,6 +110,7 @@ pub async fn login(
         &pat,
         &tokens::PatData {
             user_id: user.id.clone(),
+            signed_in_at,
         },
     )
     .await?;
@@ -109,6 +119,7 @@ pub async fn login(
         &refresh,
         &tokens::RefreshData {
             user_id: user.id.clone(),
+            signed_in_at,
         },
     )
     .await?;
@@ -117,6 +128,7 @@ pub async fn login(
         &ws_ticket,
         &tokens::WsTicketData {
             user_id: user.id.clone(),
+            signed_in_at,
         },
     )
     .await?;
@@ -131,6 +143,7 @@ pub async fn login(
         ws_ticket,
         ws_url,
         user: UserInfo {
+            bot: user.is_bot(),
             id: user.id,
             username: user.username,
             display_name: user.display_name,
@@ -144,8 +157,9 @@ fn flags_to_bitfield(flags: &[String]) -> i64 {
     let mut bits: i64 = 0;
     for flag in flags {
         match flag.as_str() {
-            "staff" => bits |= 1,
-            "verified" => bits |= 2,
+            "staff" => bits |= pod_user::HUB_FLAG_STAFF,
+            "verified" => bits |= pod_user::HUB_FLAG_VERIFIED,
+            "bot" => bits |= pod_user::HUB_FLAG_BOT,
             _ => {}
         }
     }
@@ -209,6 +223,7 @@ pub async fn refresh(
         &new_pat,
         &tokens::PatData {
             user_id: data.user_id.clone(),
+            signed_in_at: data.signed_in_at,
         },
     )
     .await?;
@@ -217,6 +232,7 @@ pub async fn refresh(
         &new_refresh,
         &tokens::RefreshData {
             user_id: data.user_id.clone(),
+            signed_in_at: data.signed_in_at,
         },
     )
     .await?;
@@ -229,6 +245,7 @@ pub async fn refresh(
             &ticket,
             &tokens::WsTicketData {
                 user_id: data.user_id,
+                signed_in_at: data.signed_in_at,
             },
         )
         .await?;
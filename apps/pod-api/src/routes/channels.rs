@@ -13,7 +13,7 @@ use utoipa::ToSchema;
 use crate::auth::middleware::AuthUser;
 use crate::db::schema::{channels, communities};
 use crate::error::{ApiError, ApiErrorBody, FieldError};
-use crate::gateway::events::EventName;
+use crate::gateway::events::{ChannelDeleteEvent, DispatchEvent};
 use crate::gateway::fanout::BroadcastPayload;
 use crate::models::audit_log;
 use crate::models::channel::{Channel, NewChannel, UpdateChannel};
@@ -146,8 +146,7 @@ pub async fn create_channel(
 
     state.broadcast.dispatch(BroadcastPayload {
         community_id: community_id.clone(),
-        event_name: EventName::CHANNEL_CREATE.to_string(),
-        data: serde_json::to_value(&channel).unwrap(),
+        event: DispatchEvent::ChannelCreate(channel.clone()),
     });
 
     Ok((StatusCode::CREATED, Json(channel)))
@@ -374,8 +373,7 @@ pub async fn update_channel(
 
     state.broadcast.dispatch(BroadcastPayload {
         community_id: channel.community_id.clone(),
-        event_name: EventName::CHANNEL_UPDATE.to_string(),
-        data: serde_json::to_value(&updated).unwrap(),
+        event: DispatchEvent::ChannelUpdate(updated.clone()),
     });
 
     Ok(Json(updated))
@@ -455,10 +453,7 @@ pub async fn delete_channel(
 
     state.broadcast.dispatch(BroadcastPayload {
         community_id: channel.community_id,
-        event_name: EventName::CHANNEL_DELETE.to_string(),
-        data: serde_json::json!({
-            "id": id,
-        }),
+        event: DispatchEvent::ChannelDelete(ChannelDeleteEvent { id }),
     });
 
     Ok(StatusCode::NO_CONTENT)
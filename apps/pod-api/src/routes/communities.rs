@@ -15,7 +15,7 @@ use utoipa::ToSchema;
 use crate::auth::middleware::AuthUser;
 use crate::db::schema::{channels, communities, community_members, roles};
 use crate::error::{ApiError, ApiErrorBody, FieldError};
-use crate::gateway::events::EventName;
+use crate::gateway::events::DispatchEvent;
 use crate::gateway::fanout::BroadcastPayload;
 use crate::models::audit_log;
 use crate::models::channel::{Channel, NewChannel};
@@ -403,8 +403,7 @@ pub async fn update_community(
 
     state.broadcast.dispatch(BroadcastPayload {
         community_id: id,
-        event_name: EventName::COMMUNITY_UPDATE.to_string(),
-        data: serde_json::to_value(&community).unwrap(),
+        event: DispatchEvent::CommunityUpdate(community.clone()),
     });
 
     Ok(Json(community))
@@ -0,0 +1,115 @@
+//! REST counterparts of client-sent gateway ops, for SSE clients (which can't
+//! send frames upstream). Any live session may use them.
+
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::routing::post;
+use axum::{Json, Router};
+
+use crate::auth::middleware::AuthUser;
+use crate::error::{ApiError, ApiErrorBody};
+use crate::gateway::events::{PresenceUpdatePayload, TypingPayload};
+use crate::gateway::ops;
+use crate::gateway::session::GatewaySession;
+use crate::AppState;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/gateway/sessions/{session_id}/typing", post(send_typing))
+        .route(
+            "/gateway/sessions/{session_id}/presence",
+            post(update_presence),
+        )
+}
+
+/// Load a gateway session owned by `user_id`. Other users' sessions look the
+/// same as missing ones.
+fn load_session(
+    state: &AppState,
+    session_id: &str,
+    user_id: &str,
+) -> Result<GatewaySession, ApiError> {
+    let not_found = || ApiError::not_found("Gateway session not found");
+    let (owner, username, communities, seq) = state
+        .sessions
+        .get_session_info(session_id)
+        .ok_or_else(not_found)?;
+    if owner != user_id {
+        return Err(not_found());
+    }
+    let version = state
+        .sessions
+        .get_session_version(session_id)
+        .ok_or_else(not_found)?;
+    Ok(GatewaySession::with_seq(
+        session_id.to_string(),
+        owner,
+        username,
+        communities,
+        version,
+        seq,
+    ))
+}
+
+// ---------------------------------------------------------------------------
+// POST /api/v1/gateway/sessions/:session_id/typing
+// ---------------------------------------------------------------------------
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/gateway/sessions/{session_id}/typing",
+    tag = "Gateway",
+    security(("bearer" = [])),
+    params(
+        ("session_id" = String, Path, description = "Gateway session ID"),
+    ),
+    request_body = TypingPayload,
+    responses(
+        (status = 204, description = "TYPING_START broadcast"),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 404, description = "Session or channel not found", body = ApiErrorBody),
+        (status = 429, description = "Rate limited", body = ApiErrorBody),
+    ),
+)]
+pub async fn send_typing(
+    AuthUser { user_id }: AuthUser,
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+    Json(body): Json<TypingPayload>,
+) -> Result<StatusCode, ApiError> {
+    let session = load_session(&state, &session_id, &user_id)?;
+    ops::start_typing(&state, &session, body.channel_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// ---------------------------------------------------------------------------
+// POST /api/v1/gateway/sessions/:session_id/presence
+// ---------------------------------------------------------------------------
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/gateway/sessions/{session_id}/presence",
+    tag = "Gateway",
+    security(("bearer" = [])),
+    params(
+        ("session_id" = String, Path, description = "Gateway session ID"),
+    ),
+    request_body = PresenceUpdatePayload,
+    responses(
+        (status = 204, description = "Presence updated"),
+        (status = 400, description = "Invalid status, custom status or activities", body = ApiErrorBody),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 404, description = "Session not found", body = ApiErrorBody),
+        (status = 429, description = "Rate limited", body = ApiErrorBody),
+    ),
+)]
+pub async fn update_presence(
+    AuthUser { user_id }: AuthUser,
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+    Json(body): Json<PresenceUpdatePayload>,
+) -> Result<StatusCode, ApiError> {
+    let session = load_session(&state, &session_id, &user_id)?;
+    ops::update_presence(&state, &session, body).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,153 @@
+//! Hub event receiver (RFC §18.1): signed notifications pushed by the Hub.
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::routing::post;
+use axum::{Json, Router};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+use crate::auth::hub_event::{self, EVENT_USER_BANNED, EVENT_USER_DELETED};
+use crate::auth::tokens;
+use crate::db::schema::{
+    communities, community_members, invites, messages, pod_member_roles, pod_users, reactions,
+    read_states,
+};
+use crate::error::{ApiError, ApiErrorBody};
+use crate::gateway::events::{DispatchEvent, MemberLeaveEvent};
+use crate::gateway::fanout::BroadcastPayload;
+use crate::AppState;
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/hub/events", post(receive_event))
+}
+
+// ---------------------------------------------------------------------------
+// POST /api/v1/hub/events
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct HubEventRequest {
+    /// Event JWT signed by the Hub.
+    pub token: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/hub/events",
+    tag = "Hub Events",
+    request_body = HubEventRequest,
+    responses(
+        (status = 204, description = "Event processed"),
+        (status = 401, description = "Invalid event token", body = ApiErrorBody),
+    ),
+)]
+pub async fn receive_event(
+    State(state): State<AppState>,
+    Json(body): Json<HubEventRequest>,
+) -> Result<StatusCode, ApiError> {
+    let claims = hub_event::validate_hub_event(
+        &body.token,
+        &state.jwks,
+        &state.config.pod_id,
+        &state.config.hub_url,
+    )
+    .await?;
+
+    match claims.event.as_str() {
+        EVENT_USER_BANNED => end_sessions(&state, &claims.sub, claims.iat).await?,
+        EVENT_USER_DELETED => {
+            end_sessions(&state, &claims.sub, claims.iat).await?;
+            anonymize_user(&state, &claims.sub).await?;
+        }
+        // Newer Hubs may send events this Pod doesn't know yet.
+        other => tracing::debug!(event = %other, "ignoring unknown hub event"),
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Revoke the user's tokens from sign-ins up to `at` and close their
+/// gateway sessions. A later sign-in needs a fresh SIA, which the Hub only
+/// issues once the ban is lifted.
+async fn end_sessions(state: &AppState, user_id: &str, at: i64) -> Result<(), ApiError> {
+    tokens::revoke_user_tokens(state.kv.as_ref(), user_id, at).await?;
+    state.broadcast.disconnect_user(user_id);
+    tracing::info!(%user_id, "revoked sessions at the hub's request");
+    Ok(())
+}
+
+/// Strip a deleted Hub account from this Pod. The `pod_users` row stays so
+/// messages, audit entries and owned communities keep a valid author, but it
+/// no longer identifies anyone. Safe to run more than once.
+async fn anonymize_user(state: &AppState, user_id: &str) -> Result<(), ApiError> {
+    let mut conn = state.db.get().await?;
+
+    // IDs are ASCII ULIDs; their tail keeps the placeholder names distinct.
+    let suffix = user_id[user_id.len().saturating_sub(8)..].to_lowercase();
+    let updated = diesel::update(pod_users::table.find(user_id))
+        .set((
+            pod_users::username.eq(format!("deleted_user_{suffix}")),
+            pod_users::display_name.eq("Deleted User"),
+            pod_users::avatar_url.eq(None::<String>),
+            pod_users::hub_flags.eq(0),
+            pod_users::status.eq("offline"),
+            pod_users::status_text.eq(None::<String>),
+            pod_users::status_emoji.eq(None::<String>),
+            pod_users::status_expires_at.eq(None::<chrono::DateTime<chrono::Utc>>),
+        ))
+        .execute(&mut conn)
+        .await?;
+    if updated == 0 {
+        // Never signed in here, or the Pod's data was reset.
+        return Ok(());
+    }
+
+    let left: Vec<String> =
+        diesel::delete(community_members::table.filter(community_members::user_id.eq(user_id)))
+            .returning(community_members::community_id)
+            .get_results(&mut conn)
+            .await?;
+    if !left.is_empty() {
+        diesel::update(communities::table.filter(communities::id.eq_any(&left)))
+            .set(communities::member_count.eq(communities::member_count - 1))
+            .execute(&mut conn)
+            .await?;
+    }
+
+    diesel::delete(pod_member_roles::table.filter(pod_member_roles::user_id.eq(user_id)))
+        .execute(&mut conn)
+        .await?;
+    diesel::delete(read_states::table.filter(read_states::user_id.eq(user_id)))
+        .execute(&mut conn)
+        .await?;
+    diesel::delete(reactions::table.filter(reactions::user_id.eq(user_id)))
+        .execute(&mut conn)
+        .await?;
+    diesel::delete(invites::table.filter(invites::inviter_id.eq(user_id)))
+        .execute(&mut conn)
+        .await?;
+    let cleared = diesel::update(messages::table.filter(messages::author_id.eq(user_id)))
+        .set(messages::content.eq(None::<String>))
+        .execute(&mut conn)
+        .await?;
+
+    for community_id in &left {
+        state.broadcast.dispatch(BroadcastPayload {
+            community_id: community_id.clone(),
+            event: DispatchEvent::MemberLeave(MemberLeaveEvent {
+                user_id: user_id.to_string(),
+            }),
+        });
+    }
+
+    tracing::info!(
+        %user_id,
+        communities = left.len(),
+        messages = cleared,
+        "anonymized deleted hub user"
+    );
+    Ok(())
+}
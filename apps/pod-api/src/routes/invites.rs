@@ -17,7 +17,7 @@ use utoipa::ToSchema;
 use crate::auth::middleware::AuthUser;
 use crate::db::schema::{bans, communities, community_members, invites, pod_users};
 use crate::error::{ApiError, ApiErrorBody};
-use crate::gateway::events::EventName;
+use crate::gateway::events::DispatchEvent;
 use crate::gateway::fanout::BroadcastPayload;
 use crate::models::audit_log;
 use crate::models::community_member::{CommunityMember, CommunityMemberRow, NewCommunityMember};
@@ -487,8 +487,7 @@ pub async fn accept_invite(
 
     state.broadcast.dispatch(BroadcastPayload {
         community_id: invite.community_id,
-        event_name: EventName::MEMBER_JOIN.to_string(),
-        data: serde_json::to_value(&member).unwrap(),
+        event: DispatchEvent::MemberJoin(member.clone()),
     });
 
     Ok((StatusCode::CREATED, Json(member)))
@@ -12,13 +12,16 @@ use utoipa::ToSchema;
 use crate::auth::middleware::AuthUser;
 use crate::db::schema::{communities, community_members, pod_users};
 use crate::error::{ApiError, ApiErrorBody};
-use crate::gateway::events::EventName;
+use crate::gateway::events::{DispatchEvent, MemberLeaveEvent};
 use crate::gateway::fanout::BroadcastPayload;
 use crate::models::audit_log;
 use crate::models::community_member::{CommunityMember, CommunityMemberRow};
 use crate::permissions;
 use crate::AppState;
 
+/// `(display_name, username, avatar_url)` joined from `pod_users`.
+type MemberUserColumns = (String, String, Option<String>);
+
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/communities/{community_id}/members", get(list_members))
@@ -93,7 +96,7 @@ pub async fn list_members(
         query = query.filter(community_members::user_id.gt(after));
     }
 
-    let rows: Vec<(CommunityMemberRow, (String, String, Option<String>))> =
+    let rows: Vec<(CommunityMemberRow, MemberUserColumns)> =
         diesel_async::RunQueryDsl::load(query, &mut conn).await?;
 
     let has_more = rows.len() as i64 > limit;
@@ -252,9 +255,8 @@ pub async fn remove_member(
 
     state.broadcast.dispatch(BroadcastPayload {
         community_id: path.community_id,
-        event_name: EventName::MEMBER_LEAVE.to_string(),
-        data: serde_json::json!({
-            "user_id": path.user_id,
+        event: DispatchEvent::MemberLeave(MemberLeaveEvent {
+            user_id: path.user_id,
         }),
     });
 
@@ -400,8 +402,7 @@ pub async fn update_member(
 
     state.broadcast.dispatch(BroadcastPayload {
         community_id: path.community_id,
-        event_name: EventName::MEMBER_UPDATE.to_string(),
-        data: serde_json::to_value(&updated).unwrap(),
+        event: DispatchEvent::MemberUpdate(updated.clone()),
     });
 
     Ok(Json(updated))
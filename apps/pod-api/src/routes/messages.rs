@@ -11,13 +11,20 @@ use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
 use crate::auth::middleware::AuthUser;
-use crate::db::schema::{channels, community_members, messages, reactions, read_states};
+use crate::db::schema::{
+    channels, community_members, messages, pod_users, reactions, read_states,
+};
 use crate::error::{ApiError, ApiErrorBody, FieldError};
-use crate::gateway::events::EventName;
+use crate::gateway::events::{
+    DispatchEvent, MessageDeleteEvent, MessageReactionAddEvent, MessageReactionRemoveEvent,
+};
 use crate::gateway::fanout::BroadcastPayload;
+use crate::gateway::ops;
+use crate::hub_relay::RelayNotification;
 use crate::models::audit_log;
 use crate::models::channel::Channel;
-use crate::models::message::{Message, NewMessage, UpdateMessage};
+use crate::models::message::{Message, NewMessage, UpdateMessage, MESSAGE_FLAG_BOT};
+use crate::models::pod_user::HUB_FLAG_BOT;
 use crate::models::reaction::{NewReaction, Reaction};
 use crate::models::read_state::NewReadState;
 use crate::permissions;
@@ -140,6 +147,20 @@ pub async fn send_message(
         }
     }
 
+    // Mark messages from bot accounts.
+    let author_flags: i64 = diesel_async::RunQueryDsl::get_result(
+        pod_users::table.find(&user_id).select(pod_users::hub_flags),
+        &mut conn,
+    )
+    .await
+    .optional()?
+    .unwrap_or(0);
+    let flags = if author_flags & HUB_FLAG_BOT != 0 {
+        MESSAGE_FLAG_BOT
+    } else {
+        0
+    };
+
     let id = state.snowflake.generate();
     let now = Utc::now();
 
@@ -151,7 +172,7 @@ pub async fn send_message(
                 author_id: &user_id,
                 content: Some(content),
                 type_: 0,
-                flags: 0,
+                flags,
                 reply_to: body.reply_to,
                 pinned: false,
                 created_at: now,
@@ -169,10 +190,12 @@ pub async fn send_message(
     )
     .await;
 
+    // Sending a message counts as activity for auto-idle.
+    ops::record_activity(&state, &user_id);
+
     state.broadcast.dispatch(BroadcastPayload {
         community_id: channel.community_id.clone(),
-        event_name: EventName::MESSAGE_CREATE.to_string(),
-        data: serde_json::to_value(&message).unwrap(),
+        event: DispatchEvent::MessageCreate(message.clone()),
     });
 
     // Mention detection: parse <@user_id> patterns and increment mention_count.
@@ -213,9 +236,100 @@ pub async fn send_message(
         }
     }
 
+    // Members who aren't connected hear about it through the Hub.
+    tokio::spawn(relay_to_offline_members(
+        state,
+        channel.community_id,
+        channel_id,
+        user_id,
+    ));
+
     Ok((StatusCode::CREATED, Json(message)))
 }
 
+/// Relay unread counts for `channel_id` to community members with no live
+/// gateway session who can see the channel (RFC §16.5.2). Debounced per
+/// channel; best-effort.
+async fn relay_to_offline_members(
+    state: AppState,
+    community_id: String,
+    channel_id: String,
+    author_id: String,
+) {
+    if !state.relay.debounce(&channel_id).await {
+        return;
+    }
+
+    let unread: Result<Vec<(String, i64, Option<i32>)>, ApiError> = async {
+        let viewers = permissions::channel_members_with_permission(
+            &state.db,
+            &community_id,
+            &channel_id,
+            permissions::VIEW_CHANNEL,
+        )
+        .await?;
+        let connected = state.sessions.connected_users();
+        let offline: Vec<String> = viewers
+            .into_iter()
+            .filter(|user_id| *user_id != author_id && !connected.contains(user_id))
+            .collect();
+        if offline.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // Messages after each member's read marker (all of them without one).
+        let mut conn = state.db.get().await?;
+        Ok(diesel_async::RunQueryDsl::load(
+            community_members::table
+                .left_join(
+                    read_states::table.on(read_states::user_id
+                        .eq(community_members::user_id)
+                        .and(read_states::channel_id.eq(channel_id.clone()))),
+                )
+                .inner_join(
+                    messages::table.on(messages::channel_id.eq(channel_id.clone()).and(
+                        read_states::user_id
+                            .is_null()
+                            .or(messages::id.gt(read_states::last_read_id)),
+                    )),
+                )
+                .filter(community_members::community_id.eq(community_id.clone()))
+                .filter(community_members::user_id.eq_any(offline))
+                .group_by(community_members::user_id)
+                .select((
+                    community_members::user_id,
+                    diesel::dsl::count_star(),
+                    diesel::dsl::max(read_states::mention_count.nullable()),
+                )),
+            &mut conn,
+        )
+        .await?)
+    }
+    .await;
+    let unread = match unread {
+        Ok(unread) => unread,
+        Err(e) => {
+            tracing::warn!(error = ?e, %channel_id, "failed to load unread counts for relay");
+            return;
+        }
+    };
+
+    let notifications: Vec<RelayNotification> = unread
+        .into_iter()
+        .map(|(user_id, unread_count, mention_count)| RelayNotification {
+            user_id,
+            community_id: community_id.clone(),
+            channel_id: channel_id.clone(),
+            unread_count,
+            has_mention: mention_count.unwrap_or(0) > 0,
+        })
+        .collect();
+
+    if !notifications.is_empty() {
+        state.relay.send(&notifications).await;
+    }
+}
+
 /// Extract user IDs from `<@user_id>` mention patterns in message content.
 fn parse_mentions(content: &str) -> Vec<String> {
     let mut mentions = Vec::new();
@@ -488,8 +602,7 @@ pub async fn edit_message(
 
     state.broadcast.dispatch(BroadcastPayload {
         community_id: channel.community_id,
-        event_name: EventName::MESSAGE_UPDATE.to_string(),
-        data: serde_json::to_value(&updated).unwrap(),
+        event: DispatchEvent::MessageUpdate(updated.clone()),
     });
 
     Ok(Json(updated))
@@ -607,10 +720,9 @@ pub async fn delete_message(
 
     state.broadcast.dispatch(BroadcastPayload {
         community_id: channel_community_id,
-        event_name: EventName::MESSAGE_DELETE.to_string(),
-        data: serde_json::json!({
-            "id": path.message_id,
-            "channel_id": path.channel_id,
+        event: DispatchEvent::MessageDelete(MessageDeleteEvent {
+            id: path.message_id,
+            channel_id: path.channel_id,
         }),
     });
 
@@ -761,8 +873,10 @@ pub async fn add_reaction(
 
     state.broadcast.dispatch(BroadcastPayload {
         community_id: channel.community_id,
-        event_name: EventName::MESSAGE_REACTION_ADD.to_string(),
-        data: serde_json::to_value(&reaction).unwrap(),
+        event: DispatchEvent::MessageReactionAdd(MessageReactionAddEvent {
+            reaction: reaction.clone(),
+            channel_id: path.channel_id.clone(),
+        }),
     });
 
     Ok(Json(reaction))
@@ -832,12 +946,11 @@ pub async fn remove_reaction(
 
     state.broadcast.dispatch(BroadcastPayload {
         community_id: channel_community_id,
-        event_name: EventName::MESSAGE_REACTION_REMOVE.to_string(),
-        data: serde_json::json!({
-            "message_id": path.message_id,
-            "user_id": user_id,
-            "emoji": path.emoji,
-            "channel_id": path.channel_id,
+        event: DispatchEvent::MessageReactionRemove(MessageReactionRemoveEvent {
+            message_id: path.message_id,
+            user_id,
+            emoji: path.emoji,
+            channel_id: path.channel_id,
         }),
     });
 
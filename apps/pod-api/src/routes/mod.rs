@@ -4,7 +4,9 @@ pub mod bans;
 pub mod channel_overrides;
 pub mod channels;
 pub mod communities;
+pub mod gateway;
 pub mod health;
+pub mod hub_events;
 pub mod invites;
 pub mod members;
 pub mod messages;
@@ -23,6 +25,7 @@ pub fn router() -> Router<AppState> {
     Router::new()
         .merge(health::router())
         .merge(crate::gateway::server::router())
+        .merge(crate::gateway::sse::router())
         .nest(
             "/api/v1",
             auth::router()
@@ -37,7 +40,9 @@ pub fn router() -> Router<AppState> {
                 .merge(read_states::router())
                 .merge(audit_log::router())
                 .merge(pod::router())
-                .merge(channel_overrides::router()),
+                .merge(channel_overrides::router())
+                .merge(gateway::router())
+                .merge(hub_events::router()),
         )
 }
 
@@ -126,6 +131,11 @@ impl Modify for SecurityAddon {
         channel_overrides::list_overrides,
         channel_overrides::upsert_override,
         channel_overrides::delete_override,
+        // Gateway
+        gateway::send_typing,
+        gateway::update_presence,
+        // Hub Events
+        hub_events::receive_event,
     ),
     components(
         schemas(
@@ -179,6 +189,9 @@ impl Modify for SecurityAddon {
             pod::UpdatePodRoleRequest,
             pod::PodBanRequest,
             channel_overrides::UpsertOverrideRequest,
+            crate::gateway::events::TypingPayload,
+            crate::gateway::events::PresenceUpdatePayload,
+            hub_events::HubEventRequest,
         )
     ),
     modifiers(&SecurityAddon),
@@ -199,6 +212,8 @@ impl Modify for SecurityAddon {
         (name = "Pod Roles", description = "Pod-level role management"),
         (name = "Pod Bans", description = "Pod-level ban management"),
         (name = "Channel Overrides", description = "Channel permission overrides"),
+        (name = "Gateway", description = "Client gateway ops for SSE sessions"),
+        (name = "Hub Events", description = "Signed events pushed by the Hub"),
     )
 )]
 pub struct ApiDoc;
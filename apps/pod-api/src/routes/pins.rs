@@ -10,7 +10,7 @@ use serde::Deserialize;
 use crate::auth::middleware::AuthUser;
 use crate::db::schema::{channels, messages};
 use crate::error::{ApiError, ApiErrorBody};
-use crate::gateway::events::EventName;
+use crate::gateway::events::{ChannelPinsUpdateEvent, DispatchEvent};
 use crate::gateway::fanout::BroadcastPayload;
 use crate::models::audit_log;
 use crate::models::channel::Channel;
@@ -152,9 +152,8 @@ pub async fn pin_message(
     // Broadcast CHANNEL_PINS_UPDATE.
     state.broadcast.dispatch(BroadcastPayload {
         community_id: channel.community_id,
-        event_name: EventName::CHANNEL_PINS_UPDATE.to_string(),
-        data: serde_json::json!({
-            "channel_id": path.channel_id,
+        event: DispatchEvent::ChannelPinsUpdate(ChannelPinsUpdateEvent {
+            channel_id: path.channel_id.clone(),
         }),
     });
 
@@ -255,9 +254,8 @@ pub async fn unpin_message(
     // Broadcast CHANNEL_PINS_UPDATE.
     state.broadcast.dispatch(BroadcastPayload {
         community_id: channel.community_id,
-        event_name: EventName::CHANNEL_PINS_UPDATE.to_string(),
-        data: serde_json::json!({
-            "channel_id": path.channel_id,
+        event: DispatchEvent::ChannelPinsUpdate(ChannelPinsUpdateEvent {
+            channel_id: path.channel_id.clone(),
         }),
     });
 
@@ -131,12 +131,12 @@ pub async fn create_role(
     let perms = body.permissions.unwrap_or(0);
 
     // Cannot set ADMINISTRATOR unless caller is owner.
-    if perms & permissions::ADMINISTRATOR != 0 {
-        if !permissions::is_owner(&state.db, &community_id, &user_id).await? {
-            return Err(ApiError::forbidden(
-                "Only the community owner can grant ADMINISTRATOR",
-            ));
-        }
+    if perms & permissions::ADMINISTRATOR != 0
+        && !permissions::is_owner(&state.db, &community_id, &user_id).await?
+    {
+        return Err(ApiError::forbidden(
+            "Only the community owner can grant ADMINISTRATOR",
+        ));
     }
 
     let mut conn = state.db.get().await?;
@@ -260,12 +260,10 @@ pub async fn update_role(
     .ok_or_else(|| ApiError::not_found("Role not found"))?;
 
     // Prevent editing @everyone's name.
-    if target.is_default {
-        if body.name.is_some() {
-            return Err(ApiError::bad_request(
-                "Cannot change the name of the @everyone role",
-            ));
-        }
+    if target.is_default && body.name.is_some() {
+        return Err(ApiError::bad_request(
+            "Cannot change the name of the @everyone role",
+        ));
     }
 
     // Role hierarchy: target role position must be < caller's highest.
@@ -289,12 +287,12 @@ pub async fn update_role(
 
     // Cannot set ADMINISTRATOR unless caller is owner.
     if let Some(perms) = body.permissions {
-        if perms & permissions::ADMINISTRATOR != 0 {
-            if !permissions::is_owner(&state.db, &path.community_id, &user_id).await? {
-                return Err(ApiError::forbidden(
-                    "Only the community owner can grant ADMINISTRATOR",
-                ));
-            }
+        if perms & permissions::ADMINISTRATOR != 0
+            && !permissions::is_owner(&state.db, &path.community_id, &user_id).await?
+        {
+            return Err(ApiError::forbidden(
+                "Only the community owner can grant ADMINISTRATOR",
+            ));
         }
     }
 
@@ -44,6 +44,7 @@ async fn login_with_valid_sia_returns_tokens_and_user() {
     assert_eq!(body["user"]["id"], user_id);
     assert_eq!(body["user"]["username"], "testuser");
     assert_eq!(body["user"]["display_name"], "Test User");
+    assert_eq!(body["user"]["bot"], false);
 
     // ws_url is present.
     assert!(body["ws_url"].as_str().unwrap().contains("gateway"));
@@ -1,3 +1,5 @@
+#![allow(dead_code)]
+
 use std::sync::atomic::{AtomicU16, Ordering};
 use std::sync::Arc;
 
@@ -12,6 +14,7 @@ use pod_api::config::Config;
 use pod_api::db::kv::{KeyValueStore, MemoryStore};
 use pod_api::gateway::fanout::GatewayBroadcast;
 use pod_api::gateway::registry::SessionRegistry;
+use pod_api::hub_relay::HubRelay;
 use pod_api::AppState;
 use voxora_common::SnowflakeGenerator;
 
@@ -45,11 +48,10 @@ impl TestSigningKeys {
         let kid_hash = Sha256::digest(public_bytes);
         let kid = format!(
             "hub-{}",
-            kid_hash
+            &kid_hash
                 .iter()
                 .flat_map(|b| [format!("{:02x}", b)])
                 .collect::<String>()[..8]
-                .to_string()
         );
 
         Self {
@@ -98,6 +100,19 @@ pub fn mint_test_sia(
     pod_id: &str,
     username: &str,
     display_name: &str,
+) -> String {
+    mint_test_sia_with_flags(keys, issuer, user_id, pod_id, username, display_name, &[])
+}
+
+/// Mint a test SIA JWT carrying Hub flags (e.g. `"bot"`).
+pub fn mint_test_sia_with_flags(
+    keys: &TestSigningKeys,
+    issuer: &str,
+    user_id: &str,
+    pod_id: &str,
+    username: &str,
+    display_name: &str,
+    flags: &[&str],
 ) -> String {
     let now = chrono::Utc::now();
     let claims = TestSiaClaims {
@@ -112,7 +127,7 @@ pub fn mint_test_sia(
         avatar_url: None,
         email: None,
         email_verified: false,
-        flags: vec![],
+        flags: flags.iter().map(|f| f.to_string()).collect(),
         hub_version: 1,
     };
 
@@ -154,6 +169,44 @@ pub fn mint_expired_sia(
     jsonwebtoken::encode(&header, &claims, &keys.encoding).expect("mint expired SIA")
 }
 
+/// Hub event claims for minting test tokens (mirrors hub-api's PodEventClaims).
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TestHubEventClaims {
+    pub iss: String,
+    pub sub: String,
+    pub aud: String,
+    pub iat: i64,
+    pub exp: i64,
+    pub jti: String,
+    pub event: String,
+}
+
+/// Mint a test Hub event JWT about `user_id`.
+pub fn mint_test_hub_event(
+    keys: &TestSigningKeys,
+    issuer: &str,
+    pod_id: &str,
+    user_id: &str,
+    event: &str,
+) -> String {
+    let now = chrono::Utc::now();
+    let claims = TestHubEventClaims {
+        iss: issuer.to_string(),
+        sub: user_id.to_string(),
+        aud: pod_id.to_string(),
+        iat: now.timestamp(),
+        exp: (now + chrono::Duration::seconds(300)).timestamp(),
+        jti: voxora_common::id::prefixed_ulid(voxora_common::id::prefix::POD_NOTIFICATION),
+        event: event.to_string(),
+    };
+
+    let mut header = Header::new(Algorithm::EdDSA);
+    header.kid = Some(keys.kid.clone());
+    header.typ = Some("voxora-event+jwt".to_string());
+
+    jsonwebtoken::encode(&header, &claims, &keys.encoding).expect("mint test hub event")
+}
+
 /// Build a test AppState with in-memory KV and a static JWKS key.
 pub async fn test_state() -> (AppState, TestSigningKeys) {
     let env_path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(".env");
@@ -179,6 +232,7 @@ pub async fn test_state() -> (AppState, TestSigningKeys) {
 
     let sessions = Arc::new(SessionRegistry::new());
     let presence = Arc::new(pod_api::gateway::presence::PresenceRegistry::new());
+    let relay = HubRelay::new(&config.hub_url, &config.pod_client_secret);
 
     let state = AppState {
         db,
@@ -189,6 +243,7 @@ pub async fn test_state() -> (AppState, TestSigningKeys) {
         broadcast,
         sessions,
         presence,
+        relay,
     };
 
     (state, signing_keys)
@@ -207,7 +262,7 @@ fn with_test_db_suffix(database_url: &str) -> String {
         return database_url.to_string();
     }
 
-    let mut updated = format!("{}/{}", prefix, format!("{db_name}_test"));
+    let mut updated = format!("{prefix}/{db_name}_test");
     if let Some(query) = query {
         updated.push('?');
         updated.push_str(query);
@@ -305,7 +360,10 @@ pub async fn join_via_invite(
 ) -> String {
     let resp = server
         .post(&format!("/api/v1/communities/{community_id}/invites"))
-        .add_header(axum::http::header::AUTHORIZATION, format!("Bearer {owner_token}"))
+        .add_header(
+            axum::http::header::AUTHORIZATION,
+            format!("Bearer {owner_token}"),
+        )
         .json(&serde_json::json!({}))
         .await;
     let code = resp.json::<serde_json::Value>()["code"]
@@ -313,12 +371,14 @@ pub async fn join_via_invite(
         .unwrap()
         .to_string();
 
-    let joiner_token =
-        login_test_user(server, keys, config, joiner_id, joiner_username).await;
+    let joiner_token = login_test_user(server, keys, config, joiner_id, joiner_username).await;
 
     server
         .post(&format!("/api/v1/invites/{code}/accept"))
-        .add_header(axum::http::header::AUTHORIZATION, format!("Bearer {joiner_token}"))
+        .add_header(
+            axum::http::header::AUTHORIZATION,
+            format!("Bearer {joiner_token}"),
+        )
         .await
         .assert_status(axum::http::StatusCode::CREATED);
 
@@ -151,6 +151,7 @@ async fn list_communities_is_public() {
 // ---------------------------------------------------------------------------
 
 #[tokio::test]
+#[allow(clippy::len_zero)]
 async fn get_community_returns_nested_data() {
     let (app, state, keys) = common::test_app().await;
     let server = TestServer::new(app).unwrap();
@@ -5,6 +5,8 @@ use std::time::Duration;
 
 use futures_util::{SinkExt, StreamExt};
 use tokio::time;
+use pod_api::gateway::events::{DispatchEvent, PresenceUpdateEvent};
+use pod_api::gateway::fanout::BroadcastPayload;
 use tokio_tungstenite::tungstenite;
 
 /// Helper: start an actual TCP server for WebSocket testing.
@@ -1132,3 +1134,468 @@ async fn gateway_typing_rate_limited() {
     common::cleanup_test_user(&state.db, &user_a).await;
     common::cleanup_test_user(&state.db, &user_b).await;
 }
+
+// ---------------------------------------------------------------------------
+// Protocol versioning
+// ---------------------------------------------------------------------------
+
+/// Connect to `/gateway{query}`, IDENTIFY, and return the stream plus READY.
+async fn connect_versioned(
+    addr: SocketAddr,
+    ticket: &str,
+    query: &str,
+) -> (
+    tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>,
+    serde_json::Value,
+) {
+    let url = format!("ws://{addr}/gateway{query}");
+    let (mut ws, _) = tokio_tungstenite::connect_async(&url)
+        .await
+        .expect("ws connect");
+
+    let identify = serde_json::json!({ "op": 2, "d": { "ticket": ticket } });
+    ws.send(tungstenite::Message::Text(identify.to_string().into()))
+        .await
+        .expect("send identify");
+
+    let msg = time::timeout(Duration::from_secs(5), ws.next())
+        .await
+        .expect("timeout waiting for READY")
+        .expect("stream ended")
+        .expect("ws read error");
+    let ready: serde_json::Value =
+        serde_json::from_str(&msg.into_text().expect("not text")).expect("parse READY");
+    assert_eq!(ready["t"], "READY");
+    (ws, ready)
+}
+
+#[tokio::test]
+async fn gateway_ready_reports_negotiated_version() {
+    let (addr, state, keys) = start_ws_server().await;
+    let user_id = voxora_common::id::prefixed_ulid("usr");
+
+    let ticket = login_and_get_ticket(addr, &keys, &state.config, &user_id, "gw_version").await;
+    let (_ws, ready) = connect_versioned(addr, &ticket, "").await;
+    assert_eq!(ready["d"]["v"], 1, "missing ?v= should default to v1");
+
+    let ticket = login_and_get_ticket(addr, &keys, &state.config, &user_id, "gw_version").await;
+    let (_ws, ready) = connect_versioned(addr, &ticket, "?v=2").await;
+    assert_eq!(ready["d"]["v"], 2);
+
+    common::cleanup_test_user(&state.db, &user_id).await;
+}
+
+#[tokio::test]
+async fn gateway_rejects_unsupported_version() {
+    let (addr, _state, _keys) = start_ws_server().await;
+
+    let url = format!("ws://{addr}/gateway?v=99");
+    let (mut ws, _) = tokio_tungstenite::connect_async(&url)
+        .await
+        .expect("ws connect");
+
+    let msg = time::timeout(Duration::from_secs(5), ws.next())
+        .await
+        .expect("timeout")
+        .expect("stream ended")
+        .expect("read error");
+    match msg {
+        tungstenite::Message::Close(Some(frame)) => {
+            assert_eq!(
+                frame.code,
+                tungstenite::protocol::frame::coding::CloseCode::from(4012)
+            );
+        }
+        other => panic!("expected close frame, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn gateway_reaction_add_shape_depends_on_version() {
+    let (addr, state, keys) = start_ws_server().await;
+    let user_id = voxora_common::id::prefixed_ulid("usr");
+
+    let (token, ticket_v1) =
+        login_and_get_token_and_ticket(addr, &keys, &state.config, &user_id, "gw_react_ver")
+            .await;
+    let ticket_v2 =
+        login_and_get_ticket(addr, &keys, &state.config, &user_id, "gw_react_ver").await;
+
+    let client = reqwest::Client::new();
+    let community: serde_json::Value = client
+        .post(format!("http://{addr}/api/v1/communities"))
+        .header("Authorization", format!("Bearer {token}"))
+        .json(&serde_json::json!({ "name": "Version Test Community" }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let community_id = community["id"].as_str().unwrap().to_string();
+    let channel_id = community["channels"][0]["id"].as_str().unwrap().to_string();
+
+    let (mut ws_v1, _) = connect_versioned(addr, &ticket_v1, "?v=1").await;
+    let (mut ws_v2, _) = connect_versioned(addr, &ticket_v2, "?v=2").await;
+
+    let message: serde_json::Value = client
+        .post(format!("http://{addr}/api/v1/channels/{channel_id}/messages"))
+        .header("Authorization", format!("Bearer {token}"))
+        .json(&serde_json::json!({ "content": "react to me" }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let message_id = message["id"].as_str().unwrap().to_string();
+
+    let resp = client
+        .put(format!(
+            "http://{addr}/api/v1/channels/{channel_id}/messages/{message_id}/reactions/%F0%9F%91%8D"
+        ))
+        .header("Authorization", format!("Bearer {token}"))
+        .send()
+        .await
+        .unwrap();
+    assert!(resp.status().is_success());
+
+    async fn next_reaction(
+        ws: &mut tokio_tungstenite::WebSocketStream<
+            tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
+        >,
+    ) -> serde_json::Value {
+        loop {
+            let msg = time::timeout(Duration::from_secs(5), ws.next())
+                .await
+                .expect("timeout")
+                .expect("stream ended")
+                .expect("read error");
+            let event: serde_json::Value =
+                serde_json::from_str(&msg.into_text().expect("not text")).unwrap();
+            if event["t"] == "MESSAGE_REACTION_ADD" {
+                return event;
+            }
+        }
+    }
+
+    let v1 = next_reaction(&mut ws_v1).await;
+    assert_eq!(v1["d"]["emoji"], "👍");
+    assert!(v1["d"].get("channel_id").is_none());
+
+    let v2 = next_reaction(&mut ws_v2).await;
+    assert_eq!(v2["d"]["emoji"], "👍");
+    assert_eq!(v2["d"]["channel_id"], channel_id.as_str());
+
+    common::cleanup_community(&state.db, &community_id).await;
+    common::cleanup_test_user(&state.db, &user_id).await;
+}
+
+// ---------------------------------------------------------------------------
+// Graceful shutdown
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn gateway_shutdown_sends_reconnect_and_keeps_session() {
+    let (addr, state, keys) = start_ws_server().await;
+    let user_id = voxora_common::id::prefixed_ulid("usr");
+
+    let ticket = login_and_get_ticket(addr, &keys, &state.config, &user_id, "gw_shutdown").await;
+    let (mut ws, ready) = connect_versioned(addr, &ticket, "").await;
+    let session_id = ready["d"]["session_id"].as_str().unwrap().to_string();
+    assert_eq!(state.broadcast.live_connections(), 1);
+
+    state.broadcast.begin_shutdown();
+
+    let msg = time::timeout(Duration::from_secs(5), ws.next())
+        .await
+        .expect("timeout")
+        .expect("stream ended")
+        .expect("read error");
+    let event: serde_json::Value =
+        serde_json::from_str(&msg.into_text().expect("not text")).unwrap();
+    assert_eq!(event["op"], 7);
+    assert_eq!(event["d"]["reason"], "Server restarting");
+
+    let msg = time::timeout(Duration::from_secs(5), ws.next())
+        .await
+        .expect("timeout")
+        .expect("stream ended")
+        .expect("read error");
+    match msg {
+        tungstenite::Message::Close(Some(frame)) => {
+            assert_eq!(
+                frame.code,
+                tungstenite::protocol::frame::coding::CloseCode::from(1012)
+            );
+        }
+        other => panic!("expected close frame, got {other:?}"),
+    }
+
+    time::timeout(Duration::from_secs(5), state.broadcast.drained())
+        .await
+        .expect("connections should drain");
+    // Still resumable.
+    assert!(state.sessions.get_session_info(&session_id).is_some());
+
+    common::cleanup_test_user(&state.db, &user_id).await;
+}
+
+#[tokio::test]
+async fn gateway_shutdown_delivers_earlier_dispatches_before_reconnect() {
+    let (addr, state, keys) = start_ws_server().await;
+    let user_a = voxora_common::id::prefixed_ulid("usr");
+    let user_b = voxora_common::id::prefixed_ulid("usr");
+    let (ticket_a, _, _, community_id, _) =
+        two_member_community(addr, &state, &keys, &user_a, &user_b).await;
+    let (mut ws, _) = connect_versioned(addr, &ticket_a, "").await;
+
+    // What the server does on SIGTERM: flush presence, then close sessions.
+    state.broadcast.dispatch(BroadcastPayload {
+        community_id: community_id.clone(),
+        event: DispatchEvent::PresenceUpdate(PresenceUpdateEvent::offline(&user_b)),
+    });
+    state.broadcast.begin_shutdown();
+
+    let mut saw_offline = false;
+    loop {
+        let msg = time::timeout(Duration::from_secs(5), ws.next())
+            .await
+            .expect("timeout")
+            .expect("stream ended")
+            .expect("read error");
+        let Ok(text) = msg.into_text() else { continue };
+        let event: serde_json::Value = serde_json::from_str(&text).expect("parse frame");
+        if event["op"] == 7 {
+            break;
+        }
+        if event["t"] == "PRESENCE_UPDATE" && event["d"]["user_id"] == user_b.as_str() {
+            assert_eq!(event["d"]["status"], "offline");
+            saw_offline = true;
+        }
+    }
+    assert!(saw_offline, "offline presence should arrive before RECONNECT");
+
+    common::cleanup_community(&state.db, &community_id).await;
+    common::cleanup_test_user(&state.db, &user_a).await;
+    common::cleanup_test_user(&state.db, &user_b).await;
+}
+
+// ---------------------------------------------------------------------------
+// Rich presence
+// ---------------------------------------------------------------------------
+
+type WsStream =
+    tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+
+/// Log in two users and put both in a new community owned by the first.
+/// Returns (ticket_a, ticket_b, token_a, community_id, channel_id).
+async fn two_member_community(
+    addr: SocketAddr,
+    state: &pod_api::AppState,
+    keys: &common::TestSigningKeys,
+    user_a: &str,
+    user_b: &str,
+) -> (String, String, String, String, String) {
+    let client = reqwest::Client::new();
+    let (token_a, ticket_a) =
+        login_and_get_token_and_ticket(addr, keys, &state.config, user_a, "gw_rich_a").await;
+    let (token_b, ticket_b) =
+        login_and_get_token_and_ticket(addr, keys, &state.config, user_b, "gw_rich_b").await;
+
+    let community: serde_json::Value = client
+        .post(format!("http://{addr}/api/v1/communities"))
+        .header("Authorization", format!("Bearer {token_a}"))
+        .json(&serde_json::json!({ "name": "Presence Test Community" }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let community_id = community["id"].as_str().unwrap().to_string();
+    let channel_id = community["channels"][0]["id"].as_str().unwrap().to_string();
+
+    let invite: serde_json::Value = client
+        .post(format!(
+            "http://{addr}/api/v1/communities/{community_id}/invites"
+        ))
+        .header("Authorization", format!("Bearer {token_a}"))
+        .json(&serde_json::json!({}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    client
+        .post(format!(
+            "http://{addr}/api/v1/invites/{}/accept",
+            invite["code"].as_str().unwrap()
+        ))
+        .header("Authorization", format!("Bearer {token_b}"))
+        .send()
+        .await
+        .unwrap();
+
+    (ticket_a, ticket_b, token_a, community_id, channel_id)
+}
+
+/// Read frames until a dispatch named `t` arrives.
+async fn next_dispatch_named(ws: &mut WsStream, t: &str) -> serde_json::Value {
+    loop {
+        let msg = time::timeout(Duration::from_secs(5), ws.next())
+            .await
+            .unwrap_or_else(|_| panic!("timeout waiting for {t}"))
+            .expect("stream ended")
+            .expect("read error");
+        let Ok(text) = msg.into_text() else { continue };
+        let event: serde_json::Value = serde_json::from_str(&text).expect("parse frame");
+        if event["t"] == t {
+            return event;
+        }
+    }
+}
+
+async fn send_presence(ws: &mut WsStream, d: serde_json::Value) {
+    let frame = serde_json::json!({ "op": 9, "d": d });
+    ws.send(tungstenite::Message::Text(frame.to_string().into()))
+        .await
+        .expect("send presence update");
+}
+
+#[tokio::test]
+async fn gateway_presence_update_carries_custom_status_and_activities() {
+    let (addr, state, keys) = start_ws_server().await;
+    let user_a = voxora_common::id::prefixed_ulid("usr");
+    let user_b = voxora_common::id::prefixed_ulid("usr");
+    let (ticket_a, ticket_b, _, community_id, channel_id) =
+        two_member_community(addr, &state, &keys, &user_a, &user_b).await;
+
+    let mut ws_a = connect_and_identify(addr, &ticket_a).await;
+    let mut ws_b = connect_and_identify(addr, &ticket_b).await;
+
+    send_presence(
+        &mut ws_a,
+        serde_json::json!({
+            "status": "dnd",
+            "custom_status": { "text": "heads down", "emoji": "🎧" },
+            "activities": [
+                { "type": "listening", "name": "Lo-fi beats" },
+                { "type": "voice", "name": "General", "channel_id": channel_id },
+            ],
+        }),
+    )
+    .await;
+
+    let event = next_dispatch_named(&mut ws_b, "PRESENCE_UPDATE").await;
+    assert_eq!(event["d"]["user_id"], user_a.as_str());
+    assert_eq!(event["d"]["status"], "dnd");
+    assert_eq!(event["d"]["custom_status"]["text"], "heads down");
+    assert_eq!(event["d"]["custom_status"]["emoji"], "🎧");
+    assert_eq!(event["d"]["activities"][0]["type"], "listening");
+    assert_eq!(
+        event["d"]["activities"][1]["channel_id"],
+        channel_id.as_str()
+    );
+
+    // A voice activity without a channel is rejected, so nothing is broadcast.
+    send_presence(
+        &mut ws_a,
+        serde_json::json!({
+            "status": "online",
+            "activities": [{ "type": "voice", "name": "General" }],
+        }),
+    )
+    .await;
+    let result = time::timeout(Duration::from_millis(500), ws_b.next()).await;
+    assert!(result.is_err(), "invalid presence should not be broadcast");
+
+    let _ = ws_a.close(None).await;
+    let _ = ws_b.close(None).await;
+    common::cleanup_community(&state.db, &community_id).await;
+    common::cleanup_test_user(&state.db, &user_a).await;
+    common::cleanup_test_user(&state.db, &user_b).await;
+}
+
+#[tokio::test]
+async fn gateway_chosen_presence_survives_going_offline() {
+    let (addr, state, keys) = start_ws_server().await;
+    let user_a = voxora_common::id::prefixed_ulid("usr");
+    let user_b = voxora_common::id::prefixed_ulid("usr");
+    let (ticket_a, ticket_b, _, community_id, _) =
+        two_member_community(addr, &state, &keys, &user_a, &user_b).await;
+
+    let mut ws_a = connect_and_identify(addr, &ticket_a).await;
+    let mut ws_b = connect_and_identify(addr, &ticket_b).await;
+    send_presence(
+        &mut ws_a,
+        serde_json::json!({
+            "status": "dnd",
+            "custom_status": { "text": "out to lunch" },
+            "activities": [{ "type": "playing", "name": "Chess" }],
+        }),
+    )
+    .await;
+    next_dispatch_named(&mut ws_b, "PRESENCE_UPDATE").await;
+
+    // Both disconnect and the grace period passes.
+    let _ = ws_a.close(None).await;
+    let _ = ws_b.close(None).await;
+    time::sleep(Duration::from_millis(200)).await;
+    state.presence.flush_offline();
+
+    let ticket_a = login_and_get_ticket(addr, &keys, &state.config, &user_a, "gw_rich_a").await;
+    let mut ws_a = connect_and_identify(addr, &ticket_a).await;
+    let ticket_b = login_and_get_ticket(addr, &keys, &state.config, &user_b, "gw_rich_b").await;
+    let (mut ws_b, ready) = connect_versioned(addr, &ticket_b, "").await;
+
+    let presences = ready["d"]["presences"].as_array().unwrap();
+    let presence_a = presences
+        .iter()
+        .find(|p| p["user_id"] == user_a.as_str())
+        .expect("user A in READY presences");
+    assert_eq!(presence_a["status"], "dnd");
+    assert_eq!(presence_a["custom_status"]["text"], "out to lunch");
+    // Activities don't outlive the connection.
+    assert!(presence_a.get("activities").is_none());
+
+    let _ = ws_a.close(None).await;
+    let _ = ws_b.close(None).await;
+    common::cleanup_community(&state.db, &community_id).await;
+    common::cleanup_test_user(&state.db, &user_a).await;
+    common::cleanup_test_user(&state.db, &user_b).await;
+}
+
+#[tokio::test]
+async fn gateway_activity_ends_auto_idle() {
+    let (addr, state, keys) = start_ws_server().await;
+    let user_a = voxora_common::id::prefixed_ulid("usr");
+    let user_b = voxora_common::id::prefixed_ulid("usr");
+    let (ticket_a, ticket_b, _, community_id, channel_id) =
+        two_member_community(addr, &state, &keys, &user_a, &user_b).await;
+
+    let mut ws_a = connect_and_identify(addr, &ticket_a).await;
+    let mut ws_b = connect_and_identify(addr, &ticket_b).await;
+
+    // What the sweeper does once a user has been quiet for too long.
+    let idle = state.presence.sweep_idle(Duration::ZERO);
+    assert!(idle.iter().any(|c| c.presence.user_id == user_a));
+    assert_eq!(state.presence.get_status(&user_a).as_deref(), Some("idle"));
+
+    let typing = serde_json::json!({ "op": 0, "t": "TYPING", "d": { "channel_id": channel_id } });
+    ws_a.send(tungstenite::Message::Text(typing.to_string().into()))
+        .await
+        .expect("send typing");
+
+    let event = next_dispatch_named(&mut ws_b, "PRESENCE_UPDATE").await;
+    assert_eq!(event["d"]["user_id"], user_a.as_str());
+    assert_eq!(event["d"]["status"], "online");
+    next_dispatch_named(&mut ws_b, "TYPING_START").await;
+
+    let _ = ws_a.close(None).await;
+    let _ = ws_b.close(None).await;
+    common::cleanup_community(&state.db, &community_id).await;
+    common::cleanup_test_user(&state.db, &user_a).await;
+    common::cleanup_test_user(&state.db, &user_b).await;
+}
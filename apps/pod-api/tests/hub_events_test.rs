@@ -0,0 +1,255 @@
+mod common;
+
+use axum::http::header::AUTHORIZATION;
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use pod_api::db::schema::{communities, community_members, messages, pod_users, reactions};
+
+// ---------------------------------------------------------------------------
+// POST /api/v1/hub/events
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn user_deleted_anonymizes_the_user() {
+    let (app, state, keys) = common::test_app().await;
+    let server = TestServer::new(app).unwrap();
+
+    let owner_id = voxora_common::id::prefixed_ulid("usr");
+    let (community_id, channel_id, owner_token) = common::setup_community_and_channel(
+        &server,
+        &keys,
+        &state.config,
+        &owner_id,
+        "hubevt_owner",
+    )
+    .await;
+    let user_id = voxora_common::id::prefixed_ulid("usr");
+    let token = common::join_via_invite(
+        &server,
+        &keys,
+        &state.config,
+        &community_id,
+        &owner_token,
+        &user_id,
+        "hubevt_leaver",
+    )
+    .await;
+
+    let resp = server
+        .post(&format!("/api/v1/channels/{channel_id}/messages"))
+        .add_header(AUTHORIZATION, format!("Bearer {token}"))
+        .json(&serde_json::json!({ "content": "something personal" }))
+        .await;
+    resp.assert_status(StatusCode::CREATED);
+    let message_id: i64 = resp.json::<serde_json::Value>()["id"]
+        .as_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    server
+        .put(&format!(
+            "/api/v1/channels/{channel_id}/messages/{message_id}/reactions/%F0%9F%91%8D"
+        ))
+        .add_header(AUTHORIZATION, format!("Bearer {token}"))
+        .await
+        .assert_status_ok();
+
+    let event = common::mint_test_hub_event(
+        &keys,
+        &state.config.hub_url,
+        &state.config.pod_id,
+        &user_id,
+        "user.deleted",
+    );
+    // Deliveries are retried, so handling the same event twice is fine.
+    for _ in 0..2 {
+        server
+            .post("/api/v1/hub/events")
+            .json(&serde_json::json!({ "token": event }))
+            .await
+            .assert_status(StatusCode::NO_CONTENT);
+    }
+
+    let mut conn = state.db.get().await.unwrap();
+    let (username, display_name): (String, String) = pod_users::table
+        .find(&user_id)
+        .select((pod_users::username, pod_users::display_name))
+        .first(&mut conn)
+        .await
+        .unwrap();
+    assert!(username.starts_with("deleted_user_"));
+    assert_eq!(display_name, "Deleted User");
+
+    let memberships: i64 = community_members::table
+        .filter(community_members::user_id.eq(&user_id))
+        .count()
+        .get_result(&mut conn)
+        .await
+        .unwrap();
+    assert_eq!(memberships, 0);
+    let member_count: i32 = communities::table
+        .find(&community_id)
+        .select(communities::member_count)
+        .first(&mut conn)
+        .await
+        .unwrap();
+    assert_eq!(member_count, 1);
+
+    let content: Option<String> = messages::table
+        .find(message_id)
+        .select(messages::content)
+        .first(&mut conn)
+        .await
+        .unwrap();
+    assert!(content.is_none());
+    let reaction_count: i64 = reactions::table
+        .filter(reactions::user_id.eq(&user_id))
+        .count()
+        .get_result(&mut conn)
+        .await
+        .unwrap();
+    assert_eq!(reaction_count, 0);
+
+    // Cleanup.
+    common::cleanup_community(&state.db, &community_id).await;
+    common::cleanup_test_user(&state.db, &user_id).await;
+    common::cleanup_test_user(&state.db, &owner_id).await;
+}
+
+#[tokio::test]
+async fn user_banned_revokes_pod_tokens() {
+    let (app, state, keys) = common::test_app().await;
+    let server = TestServer::new(app).unwrap();
+    let user_id = voxora_common::id::prefixed_ulid("usr");
+
+    let sia = common::mint_test_sia(
+        &keys,
+        &state.config.hub_url,
+        &user_id,
+        &state.config.pod_id,
+        "hubevt_banned",
+        "hubevt_banned",
+    );
+    let login: serde_json::Value = server
+        .post("/api/v1/auth/login")
+        .json(&serde_json::json!({ "sia": sia }))
+        .await
+        .json();
+    let pat = login["access_token"].as_str().unwrap();
+    let refresh_token = login["refresh_token"].as_str().unwrap();
+    let ws_ticket = login["ws_ticket"].as_str().unwrap();
+    server
+        .get("/api/v1/unread-counts")
+        .add_header(AUTHORIZATION, format!("Bearer {pat}"))
+        .await
+        .assert_status_ok();
+
+    let event = common::mint_test_hub_event(
+        &keys,
+        &state.config.hub_url,
+        &state.config.pod_id,
+        &user_id,
+        "user.banned",
+    );
+    server
+        .post("/api/v1/hub/events")
+        .json(&serde_json::json!({ "token": event }))
+        .await
+        .assert_status(StatusCode::NO_CONTENT);
+
+    server
+        .get("/api/v1/unread-counts")
+        .add_header(AUTHORIZATION, format!("Bearer {pat}"))
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+    server
+        .post("/api/v1/auth/refresh")
+        .json(&serde_json::json!({ "refresh_token": refresh_token }))
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+    assert!(
+        pod_api::auth::tokens::consume_ws_ticket(state.kv.as_ref(), ws_ticket)
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    // An SIA minted before the ban can't start a new session either.
+    let stale = common::mint_test_sia(
+        &keys,
+        &state.config.hub_url,
+        &user_id,
+        &state.config.pod_id,
+        "hubevt_banned",
+        "hubevt_banned",
+    );
+    server
+        .post("/api/v1/auth/login")
+        .json(&serde_json::json!({ "sia": stale }))
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+
+    // Once the Hub issues SIAs again, the user can sign back in.
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+    let pat =
+        common::login_test_user(&server, &keys, &state.config, &user_id, "hubevt_banned").await;
+    server
+        .get("/api/v1/unread-counts")
+        .add_header(AUTHORIZATION, format!("Bearer {pat}"))
+        .await
+        .assert_status_ok();
+
+    common::cleanup_test_user(&state.db, &user_id).await;
+}
+
+#[tokio::test]
+async fn hub_event_rejects_other_tokens() {
+    let (app, state, keys) = common::test_app().await;
+    let server = TestServer::new(app).unwrap();
+    let user_id = voxora_common::id::prefixed_ulid("usr");
+
+    // An SIA is signed by the same key but is not an event.
+    let sia = common::mint_test_sia(
+        &keys,
+        &state.config.hub_url,
+        &user_id,
+        &state.config.pod_id,
+        "hubevt_sia",
+        "hubevt_sia",
+    );
+    server
+        .post("/api/v1/hub/events")
+        .json(&serde_json::json!({ "token": sia }))
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+
+    // Events for another Pod are rejected.
+    let other_pod = common::mint_test_hub_event(
+        &keys,
+        &state.config.hub_url,
+        "pod_someone_else",
+        &user_id,
+        "user.deleted",
+    );
+    server
+        .post("/api/v1/hub/events")
+        .json(&serde_json::json!({ "token": other_pod }))
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+
+    // Unknown events are accepted and ignored.
+    let unknown = common::mint_test_hub_event(
+        &keys,
+        &state.config.hub_url,
+        &state.config.pod_id,
+        &user_id,
+        "user.renamed",
+    );
+    server
+        .post("/api/v1/hub/events")
+        .json(&serde_json::json!({ "token": unknown }))
+        .await
+        .assert_status(StatusCode::NO_CONTENT);
+}
@@ -43,6 +43,61 @@ async fn send_message_returns_correct_fields() {
     common::cleanup_test_user(&state.db, &user_id).await;
 }
 
+#[tokio::test]
+async fn send_message_from_bot_is_flagged() {
+    let (app, state, keys) = common::test_app().await;
+    let server = TestServer::new(app).unwrap();
+
+    let owner_id = voxora_common::id::prefixed_ulid("usr");
+    let (community_id, channel_id, owner_token) =
+        common::setup_community_and_channel(&server, &keys, &state.config, &owner_id, "msg_bot_owner").await;
+
+    let bot_id = voxora_common::id::prefixed_ulid("usr");
+    common::join_via_invite(
+        &server,
+        &keys,
+        &state.config,
+        &community_id,
+        &owner_token,
+        &bot_id,
+        "msg_bot",
+    )
+    .await;
+
+    // Signing in with the Hub's bot flag marks the account as a bot.
+    let sia = common::mint_test_sia_with_flags(
+        &keys,
+        &state.config.hub_url,
+        &bot_id,
+        &state.config.pod_id,
+        "msg_bot",
+        "msg_bot",
+        &["bot"],
+    );
+    let resp = server
+        .post("/api/v1/auth/login")
+        .json(&serde_json::json!({ "sia": sia }))
+        .await;
+    resp.assert_status_ok();
+    let login: serde_json::Value = resp.json();
+    assert_eq!(login["user"]["bot"], true);
+    let bot_token = login["access_token"].as_str().unwrap();
+
+    let resp = server
+        .post(&format!("/api/v1/channels/{channel_id}/messages"))
+        .add_header(AUTHORIZATION, format!("Bearer {bot_token}"))
+        .json(&serde_json::json!({ "content": "beep" }))
+        .await;
+    resp.assert_status(StatusCode::CREATED);
+    let body: serde_json::Value = resp.json();
+    assert_eq!(body["flags"], 1);
+
+    // Cleanup.
+    common::cleanup_community(&state.db, &community_id).await;
+    common::cleanup_test_user(&state.db, &owner_id).await;
+    common::cleanup_test_user(&state.db, &bot_id).await;
+}
+
 #[tokio::test]
 async fn send_message_requires_auth() {
     let (app, state, keys) = common::test_app().await;
@@ -418,3 +473,197 @@ async fn delete_message_by_author_succeeds() {
     common::cleanup_community(&state.db, &community_id).await;
     common::cleanup_test_user(&state.db, &user_id).await;
 }
+
+// ---------------------------------------------------------------------------
+// Hub notification relay
+// ---------------------------------------------------------------------------
+
+/// Stand-in for the Hub's relay endpoint. Returns its origin and the
+/// `(authorization, body)` of each request it receives.
+async fn mock_hub_relay() -> (
+    String,
+    tokio::sync::mpsc::UnboundedReceiver<(String, serde_json::Value)>,
+) {
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let app = axum::Router::new().route(
+        "/api/v1/notifications/relay",
+        axum::routing::post(
+            move |headers: axum::http::HeaderMap, axum::Json(body): axum::Json<serde_json::Value>| {
+                let tx = tx.clone();
+                async move {
+                    let auth = headers[AUTHORIZATION].to_str().unwrap().to_string();
+                    let _ = tx.send((auth, body));
+                    StatusCode::ACCEPTED
+                }
+            },
+        ),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    (format!("http://{addr}"), rx)
+}
+
+#[tokio::test]
+async fn send_message_relays_to_members_without_a_gateway_session() {
+    let (hub_url, mut relayed) = mock_hub_relay().await;
+    let (state, keys) = common::test_state().await;
+    let state = pod_api::AppState {
+        relay: pod_api::hub_relay::HubRelay::new(&hub_url, "test-pod-secret"),
+        ..state
+    };
+    let server = TestServer::new(pod_api::routes::router().with_state(state.clone())).unwrap();
+
+    let owner_id = voxora_common::id::prefixed_ulid("usr");
+    let (community_id, channel_id, owner_token) =
+        common::setup_community_and_channel(&server, &keys, &state.config, &owner_id, "relay_owner").await;
+    let member_id = voxora_common::id::prefixed_ulid("usr");
+    common::join_via_invite(
+        &server,
+        &keys,
+        &state.config,
+        &community_id,
+        &owner_token,
+        &member_id,
+        "relay_member",
+    )
+    .await;
+
+    server
+        .post(&format!("/api/v1/channels/{channel_id}/messages"))
+        .add_header(AUTHORIZATION, format!("Bearer {owner_token}"))
+        .json(&serde_json::json!({ "content": format!("hey <@{member_id}>") }))
+        .await
+        .assert_status(StatusCode::CREATED);
+
+    // The member has no session; the author is never notified.
+    let (auth, body) = tokio::time::timeout(std::time::Duration::from_secs(5), relayed.recv())
+        .await
+        .expect("relay request")
+        .unwrap();
+    assert_eq!(auth, "Bearer test-pod-secret");
+    assert_eq!(
+        body,
+        serde_json::json!({
+            "notifications": [{
+                "user_id": member_id,
+                "community_id": community_id,
+                "channel_id": channel_id,
+                "unread_count": 1,
+                "has_mention": true,
+            }]
+        })
+    );
+
+    // Once connected, the member gets MESSAGE_CREATE instead.
+    state.sessions.register(
+        "gw_relay_test".to_string(),
+        member_id.clone(),
+        "relay_member".to_string(),
+        std::collections::HashSet::from([community_id.clone()]),
+        pod_api::gateway::version::GatewayVersion::V1,
+    );
+    server
+        .post(&format!("/api/v1/channels/{channel_id}/messages"))
+        .add_header(AUTHORIZATION, format!("Bearer {owner_token}"))
+        .json(&serde_json::json!({ "content": "again" }))
+        .await
+        .assert_status(StatusCode::CREATED);
+    assert!(tokio::time::timeout(
+        pod_api::hub_relay::RELAY_DEBOUNCE + std::time::Duration::from_millis(500),
+        relayed.recv()
+    )
+    .await
+    .is_err());
+
+    // Cleanup.
+    common::cleanup_community(&state.db, &community_id).await;
+    common::cleanup_test_user(&state.db, &owner_id).await;
+    common::cleanup_test_user(&state.db, &member_id).await;
+}
+
+#[tokio::test]
+async fn relay_batches_a_burst_and_skips_members_who_cannot_view_the_channel() {
+    let (hub_url, mut relayed) = mock_hub_relay().await;
+    let (state, keys) = common::test_state().await;
+    let state = pod_api::AppState {
+        relay: pod_api::hub_relay::HubRelay::new(&hub_url, "test-pod-secret"),
+        ..state
+    };
+    let server = TestServer::new(pod_api::routes::router().with_state(state.clone())).unwrap();
+
+    let owner_id = voxora_common::id::prefixed_ulid("usr");
+    let (community_id, channel_id, owner_token) =
+        common::setup_community_and_channel(&server, &keys, &state.config, &owner_id, "burst_owner").await;
+    let reader_id = voxora_common::id::prefixed_ulid("usr");
+    common::join_via_invite(
+        &server,
+        &keys,
+        &state.config,
+        &community_id,
+        &owner_token,
+        &reader_id,
+        "burst_reader",
+    )
+    .await;
+    let hidden_id = voxora_common::id::prefixed_ulid("usr");
+    common::join_via_invite(
+        &server,
+        &keys,
+        &state.config,
+        &community_id,
+        &owner_token,
+        &hidden_id,
+        "burst_hidden",
+    )
+    .await;
+
+    // Deny VIEW_CHANNEL to one member.
+    server
+        .put(&format!("/api/v1/channels/{channel_id}/overrides/user/{hidden_id}"))
+        .add_header(AUTHORIZATION, format!("Bearer {owner_token}"))
+        .json(&serde_json::json!({ "allow": 0, "deny": 1 }))
+        .await
+        .assert_status_ok();
+
+    for content in ["one", "two", "three"] {
+        server
+            .post(&format!("/api/v1/channels/{channel_id}/messages"))
+            .add_header(AUTHORIZATION, format!("Bearer {owner_token}"))
+            .json(&serde_json::json!({ "content": content }))
+            .await
+            .assert_status(StatusCode::CREATED);
+    }
+
+    // One relay for the whole burst, to the member who can see the channel.
+    let (_, body) = tokio::time::timeout(std::time::Duration::from_secs(5), relayed.recv())
+        .await
+        .expect("relay request")
+        .unwrap();
+    assert_eq!(
+        body,
+        serde_json::json!({
+            "notifications": [{
+                "user_id": reader_id,
+                "community_id": community_id,
+                "channel_id": channel_id,
+                "unread_count": 3,
+                "has_mention": false,
+            }]
+        })
+    );
+    assert!(tokio::time::timeout(
+        pod_api::hub_relay::RELAY_DEBOUNCE + std::time::Duration::from_millis(500),
+        relayed.recv()
+    )
+    .await
+    .is_err());
+
+    // Cleanup.
+    common::cleanup_community(&state.db, &community_id).await;
+    common::cleanup_test_user(&state.db, &owner_id).await;
+    common::cleanup_test_user(&state.db, &reader_id).await;
+    common::cleanup_test_user(&state.db, &hidden_id).await;
+}
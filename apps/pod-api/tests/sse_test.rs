@@ -0,0 +1,483 @@
+mod common;
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use tokio::time;
+
+/// Helper: start an actual TCP server so the SSE response can be streamed.
+async fn start_server() -> (SocketAddr, pod_api::AppState, common::TestSigningKeys) {
+    let (state, keys) = common::test_state().await;
+    let app = pod_api::routes::router().with_state(state.clone());
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("bind");
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    (addr, state, keys)
+}
+
+/// Helper: log in a user and return both the PAT (access_token) and ws_ticket.
+async fn login(
+    addr: SocketAddr,
+    keys: &common::TestSigningKeys,
+    config: &pod_api::config::Config,
+    user_id: &str,
+    username: &str,
+) -> (String, String) {
+    let sia = common::mint_test_sia(
+        keys,
+        &config.hub_url,
+        user_id,
+        &config.pod_id,
+        username,
+        username,
+    );
+
+    let body: serde_json::Value = reqwest::Client::new()
+        .post(format!("http://{addr}/api/v1/auth/login"))
+        .json(&serde_json::json!({ "sia": sia }))
+        .send()
+        .await
+        .expect("login request")
+        .json()
+        .await
+        .expect("parse login response");
+    (
+        body["access_token"].as_str().unwrap().to_string(),
+        body["ws_ticket"].as_str().unwrap().to_string(),
+    )
+}
+
+/// A parsed SSE event.
+#[derive(Debug)]
+struct SseEvent {
+    event: String,
+    id: String,
+    frame: serde_json::Value,
+}
+
+/// Minimal SSE reader over a streaming response.
+struct SseStream {
+    resp: reqwest::Response,
+    buf: String,
+}
+
+impl SseStream {
+    async fn next(&mut self) -> SseEvent {
+        loop {
+            if let Some(end) = self.buf.find("\n\n") {
+                let block: String = self.buf.drain(..end + 2).collect();
+                let mut event = SseEvent {
+                    event: String::new(),
+                    id: String::new(),
+                    frame: serde_json::Value::Null,
+                };
+                for line in block.lines() {
+                    if let Some(v) = line.strip_prefix("event:") {
+                        event.event = v.trim().to_string();
+                    } else if let Some(v) = line.strip_prefix("id:") {
+                        event.id = v.trim().to_string();
+                    } else if let Some(v) = line.strip_prefix("data:") {
+                        event.frame = serde_json::from_str(v.trim()).expect("parse data");
+                    }
+                }
+                // Skip keep-alive comments.
+                if event.frame.is_null() {
+                    continue;
+                }
+                return event;
+            }
+
+            let chunk = time::timeout(Duration::from_secs(5), self.resp.chunk())
+                .await
+                .expect("timeout waiting for SSE event")
+                .expect("read error")
+                .expect("stream ended");
+            self.buf.push_str(std::str::from_utf8(&chunk).unwrap());
+        }
+    }
+
+    /// Read events until one named `name` arrives.
+    async fn next_named(&mut self, name: &str) -> SseEvent {
+        loop {
+            let event = self.next().await;
+            if event.event == name {
+                return event;
+            }
+        }
+    }
+}
+
+async fn open_sse(addr: SocketAddr, query: &str, last_event_id: Option<&str>) -> SseStream {
+    let mut req = reqwest::Client::new().get(format!("http://{addr}/gateway/sse?{query}"));
+    if let Some(id) = last_event_id {
+        req = req.header("Last-Event-ID", id);
+    }
+    let resp = req.send().await.expect("sse request");
+    assert_eq!(resp.status(), 200);
+    assert!(resp.headers()["content-type"]
+        .to_str()
+        .unwrap()
+        .starts_with("text/event-stream"));
+    SseStream {
+        resp,
+        buf: String::new(),
+    }
+}
+
+async fn create_community(addr: SocketAddr, token: &str, name: &str) -> (String, String) {
+    let community: serde_json::Value = reqwest::Client::new()
+        .post(format!("http://{addr}/api/v1/communities"))
+        .header("Authorization", format!("Bearer {token}"))
+        .json(&serde_json::json!({ "name": name }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    (
+        community["id"].as_str().unwrap().to_string(),
+        community["channels"][0]["id"].as_str().unwrap().to_string(),
+    )
+}
+
+async fn send_message(addr: SocketAddr, token: &str, channel_id: &str, content: &str) {
+    let resp = reqwest::Client::new()
+        .post(format!(
+            "http://{addr}/api/v1/channels/{channel_id}/messages"
+        ))
+        .header("Authorization", format!("Bearer {token}"))
+        .json(&serde_json::json!({ "content": content }))
+        .send()
+        .await
+        .unwrap();
+    assert!(resp.status().is_success());
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn sse_streams_ready() {
+    let (addr, state, keys) = start_server().await;
+    let user_id = voxora_common::id::prefixed_ulid("usr");
+    let (_token, ticket) = login(addr, &keys, &state.config, &user_id, "sse_ready").await;
+
+    let mut sse = open_sse(addr, &format!("ticket={ticket}&v=2"), None).await;
+    let ready = sse.next().await;
+    assert_eq!(ready.event, "READY");
+    assert_eq!(ready.frame["op"], 0);
+    assert_eq!(ready.frame["t"], "READY");
+    assert_eq!(ready.frame["s"], 1);
+    assert_eq!(ready.frame["d"]["v"], 2);
+    assert_eq!(ready.frame["d"]["user"]["id"], user_id.as_str());
+    let session_id = ready.frame["d"]["session_id"].as_str().unwrap();
+    assert_eq!(ready.id, format!("{session_id}:1"));
+
+    common::cleanup_test_user(&state.db, &user_id).await;
+}
+
+#[tokio::test]
+async fn sse_rejects_invalid_ticket() {
+    let (addr, _state, _keys) = start_server().await;
+
+    let resp = reqwest::get(format!("http://{addr}/gateway/sse?ticket=bogus"))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 401);
+}
+
+#[tokio::test]
+async fn sse_ticket_is_single_use() {
+    let (addr, state, keys) = start_server().await;
+    let user_id = voxora_common::id::prefixed_ulid("usr");
+    let (_token, ticket) = login(addr, &keys, &state.config, &user_id, "sse_single").await;
+
+    let mut sse = open_sse(addr, &format!("ticket={ticket}"), None).await;
+    assert_eq!(sse.next().await.event, "READY");
+
+    let resp = reqwest::get(format!("http://{addr}/gateway/sse?ticket={ticket}"))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 401);
+
+    common::cleanup_test_user(&state.db, &user_id).await;
+}
+
+#[tokio::test]
+async fn sse_rejects_unsupported_version() {
+    let (addr, state, keys) = start_server().await;
+    let user_id = voxora_common::id::prefixed_ulid("usr");
+    let (_token, ticket) = login(addr, &keys, &state.config, &user_id, "sse_badver").await;
+
+    let resp = reqwest::get(format!("http://{addr}/gateway/sse?ticket={ticket}&v=99"))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 400);
+
+    common::cleanup_test_user(&state.db, &user_id).await;
+}
+
+#[tokio::test]
+async fn sse_receives_message_create() {
+    let (addr, state, keys) = start_server().await;
+    let user_id = voxora_common::id::prefixed_ulid("usr");
+    let (token, ticket) = login(addr, &keys, &state.config, &user_id, "sse_msg").await;
+    let (community_id, channel_id) = create_community(addr, &token, "SSE Community").await;
+
+    let mut sse = open_sse(addr, &format!("ticket={ticket}"), None).await;
+    let ready = sse.next().await;
+    let session_id = ready.frame["d"]["session_id"].as_str().unwrap().to_string();
+
+    send_message(addr, &token, &channel_id, "hello over sse").await;
+
+    let event = sse.next_named("MESSAGE_CREATE").await;
+    assert_eq!(event.frame["d"]["content"], "hello over sse");
+    let seq = event.frame["s"].as_u64().unwrap();
+    assert_eq!(event.id, format!("{session_id}:{seq}"));
+
+    common::cleanup_community(&state.db, &community_id).await;
+    common::cleanup_test_user(&state.db, &user_id).await;
+}
+
+#[tokio::test]
+async fn sse_resume_with_last_event_id_replays_missed_events() {
+    let (addr, state, keys) = start_server().await;
+    let user_id = voxora_common::id::prefixed_ulid("usr");
+    let (token, ticket) = login(addr, &keys, &state.config, &user_id, "sse_resume").await;
+    let (community_id, channel_id) = create_community(addr, &token, "SSE Resume").await;
+
+    let mut sse = open_sse(addr, &format!("ticket={ticket}"), None).await;
+    let ready = sse.next().await;
+    let session_id = ready.frame["d"]["session_id"].as_str().unwrap().to_string();
+
+    send_message(addr, &token, &channel_id, "first").await;
+    let first = sse.next_named("MESSAGE_CREATE").await;
+    send_message(addr, &token, &channel_id, "second").await;
+    let second = sse.next_named("MESSAGE_CREATE").await;
+
+    // Disconnect having only acknowledged the first message.
+    drop(sse);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let (_token, ticket) = login(addr, &keys, &state.config, &user_id, "sse_resume").await;
+    let mut sse = open_sse(addr, &format!("ticket={ticket}"), Some(&first.id)).await;
+
+    let replayed = sse.next().await;
+    assert_eq!(replayed.event, "MESSAGE_CREATE");
+    assert_eq!(replayed.frame["d"]["content"], "second");
+    assert_eq!(replayed.id, second.id);
+
+    let resumed = sse.next().await;
+    assert_eq!(resumed.event, "RESUMED");
+    assert_eq!(resumed.frame["s"], second.frame["s"].as_u64().unwrap() + 1);
+    assert!(resumed.id.starts_with(&format!("{session_id}:")));
+
+    common::cleanup_community(&state.db, &community_id).await;
+    common::cleanup_test_user(&state.db, &user_id).await;
+}
+
+#[tokio::test]
+async fn sse_resume_via_query_param() {
+    let (addr, state, keys) = start_server().await;
+    let user_id = voxora_common::id::prefixed_ulid("usr");
+    let (_token, ticket) = login(addr, &keys, &state.config, &user_id, "sse_resume_q").await;
+
+    let mut sse = open_sse(addr, &format!("ticket={ticket}"), None).await;
+    let ready = sse.next().await;
+    drop(sse);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let (_token, ticket) = login(addr, &keys, &state.config, &user_id, "sse_resume_q").await;
+    let mut sse = open_sse(
+        addr,
+        &format!("ticket={ticket}&last_event_id={}", ready.id),
+        None,
+    )
+    .await;
+    assert_eq!(sse.next().await.event, "RESUMED");
+
+    common::cleanup_test_user(&state.db, &user_id).await;
+}
+
+#[tokio::test]
+async fn sse_resume_of_other_users_session_starts_fresh() {
+    let (addr, state, keys) = start_server().await;
+    let user_a = voxora_common::id::prefixed_ulid("usr");
+    let user_b = voxora_common::id::prefixed_ulid("usr");
+    let (_token_a, ticket_a) = login(addr, &keys, &state.config, &user_a, "sse_owner_a").await;
+    let (_token_b, ticket_b) = login(addr, &keys, &state.config, &user_b, "sse_owner_b").await;
+
+    let mut sse_a = open_sse(addr, &format!("ticket={ticket_a}"), None).await;
+    let ready_a = sse_a.next().await;
+
+    let mut sse_b = open_sse(addr, &format!("ticket={ticket_b}"), Some(&ready_a.id)).await;
+    let ready_b = sse_b.next().await;
+    assert_eq!(ready_b.event, "READY");
+    assert_eq!(ready_b.frame["d"]["user"]["id"], user_b.as_str());
+    assert_ne!(
+        ready_b.frame["d"]["session_id"],
+        ready_a.frame["d"]["session_id"]
+    );
+
+    common::cleanup_test_user(&state.db, &user_a).await;
+    common::cleanup_test_user(&state.db, &user_b).await;
+}
+
+#[tokio::test]
+async fn sse_typing_via_rest_broadcasts_and_rate_limits() {
+    let (addr, state, keys) = start_server().await;
+    let user_id = voxora_common::id::prefixed_ulid("usr");
+    let (token, ticket) = login(addr, &keys, &state.config, &user_id, "sse_typing").await;
+    let (community_id, channel_id) = create_community(addr, &token, "SSE Typing").await;
+
+    let mut sse = open_sse(addr, &format!("ticket={ticket}"), None).await;
+    let ready = sse.next().await;
+    let session_id = ready.frame["d"]["session_id"].as_str().unwrap().to_string();
+
+    let client = reqwest::Client::new();
+    let url = format!("http://{addr}/api/v1/gateway/sessions/{session_id}/typing");
+    let resp = client
+        .post(&url)
+        .header("Authorization", format!("Bearer {token}"))
+        .json(&serde_json::json!({ "channel_id": channel_id }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 204);
+
+    let event = sse.next_named("TYPING_START").await;
+    assert_eq!(event.frame["d"]["channel_id"], channel_id.as_str());
+    assert_eq!(event.frame["d"]["user_id"], user_id.as_str());
+    assert_eq!(event.frame["d"]["username"], "sse_typing");
+
+    // Second TYPING within the window is rejected.
+    let resp = client
+        .post(&url)
+        .header("Authorization", format!("Bearer {token}"))
+        .json(&serde_json::json!({ "channel_id": channel_id }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 429);
+
+    // Unknown channel.
+    let resp = client
+        .post(&url)
+        .header("Authorization", format!("Bearer {token}"))
+        .json(&serde_json::json!({ "channel_id": "nonexistent" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 404);
+
+    common::cleanup_community(&state.db, &community_id).await;
+    common::cleanup_test_user(&state.db, &user_id).await;
+}
+
+#[tokio::test]
+async fn sse_presence_via_rest() {
+    let (addr, state, keys) = start_server().await;
+    let user_id = voxora_common::id::prefixed_ulid("usr");
+    let (token, ticket) = login(addr, &keys, &state.config, &user_id, "sse_presence").await;
+    let (community_id, _channel_id) = create_community(addr, &token, "SSE Presence").await;
+
+    let mut sse = open_sse(addr, &format!("ticket={ticket}"), None).await;
+    let ready = sse.next().await;
+    let session_id = ready.frame["d"]["session_id"].as_str().unwrap().to_string();
+
+    let client = reqwest::Client::new();
+    let url = format!("http://{addr}/api/v1/gateway/sessions/{session_id}/presence");
+
+    let resp = client
+        .post(&url)
+        .header("Authorization", format!("Bearer {token}"))
+        .json(&serde_json::json!({ "status": "offline" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 400);
+
+    let resp = client
+        .post(&url)
+        .header("Authorization", format!("Bearer {token}"))
+        .json(&serde_json::json!({ "status": "dnd" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 204);
+
+    let event = sse.next_named("PRESENCE_UPDATE").await;
+    assert_eq!(event.frame["d"]["user_id"], user_id.as_str());
+    assert_eq!(event.frame["d"]["status"], "dnd");
+    assert_eq!(state.presence.get_status(&user_id).as_deref(), Some("dnd"));
+
+    common::cleanup_community(&state.db, &community_id).await;
+    common::cleanup_test_user(&state.db, &user_id).await;
+}
+
+#[tokio::test]
+async fn gateway_ops_reject_other_users_session() {
+    let (addr, state, keys) = start_server().await;
+    let user_a = voxora_common::id::prefixed_ulid("usr");
+    let user_b = voxora_common::id::prefixed_ulid("usr");
+    let (_token_a, ticket_a) = login(addr, &keys, &state.config, &user_a, "sse_ops_a").await;
+    let (token_b, _ticket_b) = login(addr, &keys, &state.config, &user_b, "sse_ops_b").await;
+
+    let mut sse = open_sse(addr, &format!("ticket={ticket_a}"), None).await;
+    let ready = sse.next().await;
+    let session_id = ready.frame["d"]["session_id"].as_str().unwrap().to_string();
+
+    let resp = reqwest::Client::new()
+        .post(format!(
+            "http://{addr}/api/v1/gateway/sessions/{session_id}/presence"
+        ))
+        .header("Authorization", format!("Bearer {token_b}"))
+        .json(&serde_json::json!({ "status": "idle" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 404);
+
+    common::cleanup_test_user(&state.db, &user_a).await;
+    common::cleanup_test_user(&state.db, &user_b).await;
+}
+
+#[tokio::test]
+async fn sse_shutdown_sends_reconnect_and_ends_stream() {
+    let (addr, state, keys) = start_server().await;
+    let user_id = voxora_common::id::prefixed_ulid("usr");
+    let (_token, ticket) = login(addr, &keys, &state.config, &user_id, "sse_shutdown").await;
+
+    let mut sse = open_sse(addr, &format!("ticket={ticket}"), None).await;
+    let ready = sse.next().await;
+    let session_id = ready.frame["d"]["session_id"].as_str().unwrap().to_string();
+
+    state.broadcast.begin_shutdown();
+
+    let reconnect = sse.next().await;
+    assert_eq!(reconnect.event, "RECONNECT");
+    assert_eq!(reconnect.frame["op"], 7);
+
+    // The stream ends so the HTTP server can drain.
+    let rest = time::timeout(Duration::from_secs(5), sse.resp.chunk())
+        .await
+        .expect("timeout")
+        .expect("read error");
+    assert!(rest.is_none());
+
+    time::timeout(Duration::from_secs(5), state.broadcast.drained())
+        .await
+        .expect("connections should drain");
+    assert!(state.sessions.get_session_info(&session_id).is_some());
+
+    common::cleanup_test_user(&state.db, &user_id).await;
+}
@@ -31,6 +31,14 @@ pub mod prefix {
     pub const ATTACHMENT: &str = "att";
     pub const AUDIT: &str = "aud";
     pub const SIA: &str = "sia";
+    pub const RECOVERY_CODE: &str = "rc";
+    pub const PASSKEY: &str = "pk";
+    pub const OAUTH_CLIENT: &str = "cli";
+    pub const USER_IDENTITY: &str = "idn";
+    pub const POD_NOTIFICATION: &str = "pn";
+    pub const DATA_EXPORT: &str = "dxp";
+    pub const AUTH_EVENT: &str = "aev";
+    pub const MEDIA: &str = "med";
 }
 
 #[cfg(test)]
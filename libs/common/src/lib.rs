@@ -1,4 +1,5 @@
 pub mod id;
+pub mod shutdown;
 pub mod snowflake;
 
 pub use id::PrefixedId;
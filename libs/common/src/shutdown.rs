@@ -0,0 +1,38 @@
+//! Process shutdown signal shared by the Hub and Pod servers.
+
+/// Resolve when the process receives SIGTERM (deploys) or Ctrl-C.
+pub async fn signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to install Ctrl-C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+/// Handle the server task finishing before a shutdown signal arrived. It only
+/// does that when serving failed, so log why and exit non-zero rather than
+/// idle until someone notices.
+pub fn server_stopped(result: Result<std::io::Result<()>, tokio::task::JoinError>) -> ! {
+    match result {
+        Ok(Ok(())) => tracing::error!("server stopped unexpectedly"),
+        Ok(Err(e)) => tracing::error!(error = %e, "server error"),
+        Err(e) => tracing::error!(error = %e, "server task failed"),
+    }
+    std::process::exit(1);
+}